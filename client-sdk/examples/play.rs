@@ -0,0 +1,62 @@
+//! Kết nối gateway, join room, chạy sang phải 5 giây rồi in score.
+//!
+//! ```text
+//! cargo run -p client-sdk --example play -- <gateway-url> <jwt> [room_id]
+//! cargo run -p client-sdk --example play -- ws://127.0.0.1:8080 "$ACCESS_TOKEN" demo-room
+//! ```
+//!
+//! JWT lấy từ `POST /auth/login` (trường `access_token`). Có thể thay tham số bằng biến môi trường
+//! `GATEWAY_URL`, `GATEWAY_TOKEN`, `ROOM_ID`.
+
+use std::time::Duration;
+
+use client_sdk::{GameClient, PlayerInput};
+use futures::StreamExt;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let url = args
+        .next()
+        .or_else(|| std::env::var("GATEWAY_URL").ok())
+        .unwrap_or_else(|| "ws://127.0.0.1:8080".to_string());
+    let token = args
+        .next()
+        .or_else(|| std::env::var("GATEWAY_TOKEN").ok())
+        .ok_or("usage: play <gateway-url> <jwt> [room_id]")?;
+    let room_id = args
+        .next()
+        .or_else(|| std::env::var("ROOM_ID").ok())
+        .unwrap_or_else(|| "demo-room".to_string());
+
+    let client = GameClient::connect(&url, &token).await?;
+    client.join_room(&room_id).await?;
+    println!("joined {room_id} as {}", client.player_id());
+
+    // In tick nhận được ở nền trong lúc chạy
+    let mut snapshots = client.snapshots();
+    let printer = tokio::spawn(async move {
+        while let Some(snapshot) = snapshots.next().await {
+            if snapshot.tick % 60 == 0 {
+                println!("tick {} - {} entities", snapshot.tick, snapshot.entities.len());
+            }
+        }
+    });
+
+    // 20 input/giây trong 5 giây
+    let mut ticker = tokio::time::interval(Duration::from_millis(50));
+    for _ in 0..100 {
+        ticker.tick().await;
+        client.send_input(PlayerInput::movement(1.0, 0.0, 0.0))?;
+    }
+
+    let score = client
+        .latest_snapshot()
+        .and_then(|snapshot| snapshot.score(client.player_id()))
+        .unwrap_or_default();
+    println!("score: {score}");
+
+    client.close().await;
+    printer.abort();
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! Input client gửi lên mỗi frame; khớp với `PlayerInput` của worker trừ player_id/sequence do gateway/SDK điền.
+
+use serde::{Deserialize, Serialize};
+
+/// Các nút hành động trong một input frame
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputActions {
+    pub jump: bool,
+    pub slide: bool,
+    pub use_item: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInput {
+    /// x, y, z; mỗi thành phần trong [-10, 10]
+    pub movement: [f32; 3],
+    #[serde(default)]
+    pub actions: InputActions,
+}
+
+impl PlayerInput {
+    pub fn movement(x: f32, y: f32, z: f32) -> Self {
+        Self {
+            movement: [x, y, z],
+            ..Self::default()
+        }
+    }
+
+    /// Payload của `ControlMessage::Input`; timestamp là thời điểm gửi (worker từ chối input quá cũ)
+    pub(crate) fn to_payload(&self, timestamp_ms: u64) -> serde_json::Value {
+        serde_json::json!({
+            "movement": self.movement,
+            "timestamp": timestamp_ms,
+            "actions": self.actions,
+        })
+    }
+}
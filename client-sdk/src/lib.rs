@@ -0,0 +1,677 @@
+//! SDK client cho gateway: `GameClient` lo WS upgrade, handshake, join room, gửi input, dựng snapshot từ
+//! delta, chat, ping/pong và tự reconnect rồi join lại room. Chỉ dựa vào định dạng frame của `common-net`,
+//! không đụng tới nội bộ gateway/worker.
+
+mod input;
+mod snapshot;
+
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use base64::Engine;
+use common_net::message::{self, ControlMessage, Frame, FramePayload, StateMessage};
+use futures::{stream::BoxStream, SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    net::TcpStream,
+    sync::{broadcast, mpsc, oneshot, watch},
+    task::JoinHandle,
+};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, http::HeaderValue, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+pub use input::{InputActions, PlayerInput};
+pub use snapshot::GameSnapshot;
+
+/// Path WS của gateway
+pub const WS_PATH: &str = "/ws";
+/// Subprotocol mang JWT: `Sec-WebSocket-Protocol: bearer, <jwt>`
+const WS_BEARER_PROTOCOL: &str = "bearer";
+/// Số sequence gần nhất nhớ để bỏ control frame gateway gửi lại
+const DEDUPE_WINDOW: usize = 256;
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(5);
+const CHAT_CAPACITY: usize = 64;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("websocket error: {0}")]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+    #[error("handshake failed: {0}")]
+    Handshake(String),
+    #[error("kicked from room: {0}")]
+    Kicked(String),
+    #[error("gateway speaks protocol version {server_version}, this client speaks {}", message::PROTOCOL_VERSION)]
+    VersionMismatch { server_version: u32 },
+    #[error("timed out waiting for the gateway")]
+    Timeout,
+    #[error("connection closed")]
+    Closed,
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for ClientError {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(error))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Thời gian chờ frame hello sau upgrade và chờ gateway xác nhận join
+    pub handshake_timeout: Duration,
+    /// Chu kỳ client tự ping gateway
+    pub ping_interval: Duration,
+    /// Không nhận frame nào trong khoảng này thì coi là mất kết nối và reconnect
+    pub idle_timeout: Duration,
+    /// Số lần thử reconnect trước khi bỏ cuộc; 0 = không reconnect
+    pub reconnect_attempts: u32,
+    /// Chờ trước lần reconnect đầu, nhân đôi sau mỗi lần hỏng
+    pub reconnect_backoff: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            handshake_timeout: Duration::from_secs(5),
+            ping_interval: Duration::from_secs(5),
+            idle_timeout: Duration::from_secs(15),
+            reconnect_attempts: 10,
+            reconnect_backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    /// Mất kết nối, đang dial lại; room đã join sẽ được join lại
+    Reconnecting,
+    Closed,
+}
+
+/// Tin chat trong room, gateway điền player_id từ JWT của người gửi
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub player_id: String,
+    pub message: String,
+    #[serde(default)]
+    pub timestamp_ms: u64,
+}
+
+enum Command {
+    Send(Frame),
+    Join {
+        room_id: String,
+        joined: oneshot::Sender<Result<(), ClientError>>,
+    },
+    Close,
+}
+
+/// Một connection tới gateway. Socket do task nền giữ; clone handle chat để gửi/nhận chat song song
+pub struct GameClient {
+    player_id: String,
+    config: ClientConfig,
+    commands: mpsc::UnboundedSender<Command>,
+    snapshots: watch::Receiver<Option<GameSnapshot>>,
+    chat: broadcast::Sender<ChatMessage>,
+    state: watch::Receiver<ConnectionState>,
+    input_sequence: AtomicU32,
+    driver: JoinHandle<()>,
+}
+
+impl GameClient {
+    /// Upgrade `url` (ví dụ `ws://127.0.0.1:8080`, tự thêm `/ws`) bằng JWT rồi chờ frame hello của gateway
+    pub async fn connect(url: &str, token: &str) -> Result<Self, ClientError> {
+        Self::connect_with(url, token, ClientConfig::default()).await
+    }
+
+    pub async fn connect_with(url: &str, token: &str, config: ClientConfig) -> Result<Self, ClientError> {
+        let player_id = jwt_subject(token)?;
+        let url = ws_url(url);
+        let link = Link::dial(&url, token, &config).await?;
+
+        let (commands, command_rx) = mpsc::unbounded_channel();
+        let (snapshot_tx, snapshots) = watch::channel(None);
+        let (state_tx, state) = watch::channel(ConnectionState::Connected);
+        let (chat, _) = broadcast::channel(CHAT_CAPACITY);
+
+        let driver = Driver {
+            url,
+            token: token.to_string(),
+            player_id: player_id.clone(),
+            config: config.clone(),
+            commands: command_rx,
+            snapshots: snapshot_tx,
+            chat: chat.clone(),
+            state: state_tx,
+            room_id: None,
+            resume_token: None,
+            joined: None,
+            snapshot: None,
+        };
+        Ok(Self {
+            player_id,
+            config,
+            commands,
+            snapshots,
+            chat,
+            state,
+            input_sequence: AtomicU32::new(0),
+            driver: tokio::spawn(driver.run(link)),
+        })
+    }
+
+    /// player_id gateway gán cho connection (claim `sub` của JWT)
+    pub fn player_id(&self) -> &str {
+        &self.player_id
+    }
+
+    pub fn connection_state(&self) -> ConnectionState {
+        *self.state.borrow()
+    }
+
+    /// Join room và chờ gateway xác nhận; worker spawn player (hoặc nối lại entity trong rejoin grace)
+    pub async fn join_room(&self, room_id: &str) -> Result<(), ClientError> {
+        let (joined, reply) = oneshot::channel();
+        self.command(Command::Join { room_id: room_id.to_string(), joined })?;
+        match tokio::time::timeout(self.config.handshake_timeout, reply).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(ClientError::Closed),
+            Err(_) => Err(ClientError::Timeout),
+        }
+    }
+
+    /// Gửi input của frame hiện tại; sequence tăng dần kể cả qua các lần reconnect
+    pub fn send_input(&self, input: PlayerInput) -> Result<(), ClientError> {
+        let seq = self.input_sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        let payload = input.to_payload(now_millis());
+        self.command(Command::Send(Frame::control(0, 0, ControlMessage::Input { seq, payload })))
+    }
+
+    /// Snapshot mới nhất đã ghép đủ delta
+    pub fn latest_snapshot(&self) -> Option<GameSnapshot> {
+        self.snapshots.borrow().clone()
+    }
+
+    /// Mỗi lần state room đổi thì yield snapshot đã dựng lại; client chậm chỉ thấy bản mới nhất.
+    /// Stream kết thúc khi connection đóng hẳn
+    pub fn snapshots(&self) -> BoxStream<'static, GameSnapshot> {
+        futures::stream::unfold(self.snapshots.clone(), |mut snapshots| async move {
+            loop {
+                snapshots.changed().await.ok()?;
+                let latest = snapshots.borrow_and_update().clone();
+                if let Some(snapshot) = latest {
+                    return Some((snapshot, snapshots));
+                }
+            }
+        })
+        .boxed()
+    }
+
+    /// Handle chat của room hiện tại; chỉ nhận tin gửi tới sau khi tạo handle
+    pub fn chat(&self) -> Chat {
+        Chat {
+            commands: self.commands.clone(),
+            incoming: self.chat.subscribe(),
+        }
+    }
+
+    /// Đóng connection; không reconnect nữa
+    pub async fn close(self) {
+        let _ = self.commands.send(Command::Close);
+        let _ = self.driver.await;
+    }
+
+    fn command(&self, command: Command) -> Result<(), ClientError> {
+        self.commands.send(command).map_err(|_| ClientError::Closed)
+    }
+}
+
+pub struct Chat {
+    commands: mpsc::UnboundedSender<Command>,
+    incoming: broadcast::Receiver<ChatMessage>,
+}
+
+impl Chat {
+    pub fn send(&self, text: &str) -> Result<(), ClientError> {
+        let event = StateMessage::Event {
+            name: "chat".to_string(),
+            data: serde_json::json!({ "message": text }),
+        };
+        self.commands
+            .send(Command::Send(Frame::state(0, 0, event)))
+            .map_err(|_| ClientError::Closed)
+    }
+
+    /// Tin kế tiếp; None khi connection đã đóng. Tin bị tràn buffer thì bỏ qua
+    pub async fn recv(&mut self) -> Option<ChatMessage> {
+        loop {
+            match self.incoming.recv().await {
+                Ok(message) => return Some(message),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// Một WS connection: sequence hai chiều và cửa sổ dedupe riêng, reconnect thì làm lại từ đầu
+struct Link {
+    socket: WsStream,
+    sent: u32,
+    received: u32,
+    /// Sequence của control frame cần ack đã nhận, gửi kèm frame kế tiếp trong `acks`
+    acks: Vec<u32>,
+    seen: VecDeque<u32>,
+    seen_set: HashSet<u32>,
+    last_inbound: Instant,
+}
+
+impl Link {
+    async fn dial(url: &str, token: &str, config: &ClientConfig) -> Result<Self, ClientError> {
+        let mut request = url
+            .into_client_request()
+            .map_err(|e| ClientError::InvalidRequest(e.to_string()))?;
+        let protocol = HeaderValue::from_str(&format!("{WS_BEARER_PROTOCOL}, {token}"))
+            .map_err(|e| ClientError::InvalidRequest(e.to_string()))?;
+        request.headers_mut().insert("sec-websocket-protocol", protocol);
+        let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+
+        let mut link = Self {
+            socket,
+            sent: 0,
+            received: 0,
+            acks: Vec::new(),
+            seen: VecDeque::with_capacity(DEDUPE_WINDOW),
+            seen_set: HashSet::with_capacity(DEDUPE_WINDOW),
+            last_inbound: Instant::now(),
+        };
+        // Client mở đầu bằng Hello; gateway trả frame transport nào được chọn, hoặc VersionMismatch rồi đóng
+        link.send(Frame::control(0, 0, ControlMessage::Hello { protocol_version: message::PROTOCOL_VERSION })).await?;
+        match tokio::time::timeout(config.handshake_timeout, link.next_frame()).await {
+            Ok(Some(Frame {
+                payload: FramePayload::Control { message: ControlMessage::TransportSelected { .. } },
+                ..
+            })) => Ok(link),
+            Ok(Some(Frame {
+                payload: FramePayload::Control { message: ControlMessage::VersionMismatch { server_version } },
+                ..
+            })) => Err(ClientError::VersionMismatch { server_version }),
+            Ok(Some(frame)) => Err(ClientError::Handshake(format!("unexpected first frame {:?}", frame.payload))),
+            Ok(None) => Err(ClientError::Closed),
+            Err(_) => Err(ClientError::Timeout),
+        }
+    }
+
+    /// Frame kế tiếp (bỏ frame trùng và message không decode được); None khi socket đóng/lỗi
+    async fn next_frame(&mut self) -> Option<Frame> {
+        loop {
+            let bytes = match self.socket.next().await? {
+                Ok(Message::Binary(bytes)) => bytes,
+                Ok(Message::Text(text)) => text.into_bytes(),
+                Ok(Message::Close(_)) | Err(_) => return None,
+                Ok(_) => continue,
+            };
+            let Ok(frame) = message::decode(&bytes) else {
+                continue;
+            };
+            self.last_inbound = Instant::now();
+            self.received = self.received.max(frame.sequence);
+            // Bản gửi lại cũng ack lại: có thể ack trước đã mất
+            if frame.needs_ack() && self.acks.len() < DEDUPE_WINDOW {
+                self.acks.push(frame.sequence);
+            }
+            if !self.is_duplicate(frame.sequence) {
+                return Some(frame);
+            }
+            if self.flush_acks().await.is_err() {
+                return None;
+            }
+        }
+    }
+
+    /// Gửi ngay các ack đang chờ nếu chưa có frame nào khác mang chúng đi
+    async fn flush_acks(&mut self) -> Result<(), ClientError> {
+        if self.acks.is_empty() {
+            return Ok(());
+        }
+        self.send(Frame::control(0, 0, ControlMessage::Ack)).await
+    }
+
+    fn is_duplicate(&mut self, sequence: u32) -> bool {
+        if sequence == 0 {
+            return false;
+        }
+        if !self.seen_set.insert(sequence) {
+            return true;
+        }
+        if self.seen.len() >= DEDUPE_WINDOW {
+            if let Some(oldest) = self.seen.pop_front() {
+                self.seen_set.remove(&oldest);
+            }
+        }
+        self.seen.push_back(sequence);
+        false
+    }
+
+    /// Đóng sequence, ack (cumulative) và `acks` rồi gửi; `acks` là cách gateway biết ngừng gửi lại control frame
+    async fn send(&mut self, mut frame: Frame) -> Result<(), ClientError> {
+        self.sent = self.sent.wrapping_add(1);
+        frame.sequence = self.sent;
+        frame.ack = self.received;
+        frame.acks = std::mem::take(&mut self.acks);
+        frame.timestamp_ms = now_millis();
+        let bytes = message::encode(&frame).map_err(|e| ClientError::InvalidRequest(e.to_string()))?;
+        self.socket.send(Message::Binary(bytes)).await?;
+        Ok(())
+    }
+}
+
+/// Vì sao một connection kết thúc
+enum Ended {
+    /// Client đóng hoặc bị kick: dừng hẳn
+    Closed,
+    /// Socket chết hoặc im quá lâu: reconnect
+    Lost,
+}
+
+/// Task nền giữ socket, nhận command của `GameClient` và đẩy snapshot/chat ra ngoài
+struct Driver {
+    url: String,
+    token: String,
+    player_id: String,
+    config: ClientConfig,
+    commands: mpsc::UnboundedReceiver<Command>,
+    snapshots: watch::Sender<Option<GameSnapshot>>,
+    chat: broadcast::Sender<ChatMessage>,
+    state: watch::Sender<ConnectionState>,
+    room_id: Option<String>,
+    /// connection_id gateway cấp lần join trước, gửi lại làm `reconnect_token`
+    resume_token: Option<String>,
+    joined: Option<oneshot::Sender<Result<(), ClientError>>>,
+    /// None khi chưa có keyframe sau lần join gần nhất
+    snapshot: Option<GameSnapshot>,
+}
+
+impl Driver {
+    async fn run(mut self, mut link: Link) {
+        loop {
+            match self.serve(&mut link).await {
+                Ended::Closed => break,
+                Ended::Lost => {
+                    self.state.send_replace(ConnectionState::Reconnecting);
+                    match self.reconnect().await {
+                        Some(next) => link = next,
+                        None => break,
+                    }
+                }
+            }
+        }
+        let _ = link.socket.close(None).await;
+        self.state.send_replace(ConnectionState::Closed);
+        if let Some(joined) = self.joined.take() {
+            let _ = joined.send(Err(ClientError::Closed));
+        }
+    }
+
+    async fn serve(&mut self, link: &mut Link) -> Ended {
+        let period = self.config.ping_interval;
+        let mut ping = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            let sent = tokio::select! {
+                command = self.commands.recv() => match command {
+                    None | Some(Command::Close) => return Ended::Closed,
+                    Some(Command::Send(frame)) => link.send(frame).await,
+                    Some(Command::Join { room_id, joined }) => {
+                        if let Some(previous) = self.joined.replace(joined) {
+                            let _ = previous.send(Err(ClientError::Closed));
+                        }
+                        self.room_id = Some(room_id.clone());
+                        self.snapshot = None;
+                        link.send(Frame::control(0, 0, ControlMessage::JoinRoom { room_id, reconnect_token: None })).await
+                    }
+                },
+                frame = link.next_frame() => match frame {
+                    Some(frame) => match self.on_frame(link, frame).await {
+                        Some(ended) => return ended,
+                        None => link.flush_acks().await,
+                    },
+                    None => return Ended::Lost,
+                },
+                _ = ping.tick() => {
+                    if link.last_inbound.elapsed() > self.config.idle_timeout {
+                        tracing::debug!(player_id = %self.player_id, "client-sdk: connection idle, reconnecting");
+                        return Ended::Lost;
+                    }
+                    link.send(Frame::control(0, 0, ControlMessage::Ping { nonce: now_millis() })).await
+                }
+            };
+            if sent.is_err() {
+                return Ended::Lost;
+            }
+        }
+    }
+
+    async fn on_frame(&mut self, link: &mut Link, frame: Frame) -> Option<Ended> {
+        match frame.payload {
+            FramePayload::Control { message } => match message {
+                ControlMessage::Ping { nonce } => {
+                    return link.send(Frame::control(0, 0, ControlMessage::Pong { nonce })).await.err().map(|_| Ended::Lost);
+                }
+                ControlMessage::MigrationToken { connection_id, .. } => {
+                    self.resume_token = Some(connection_id);
+                    if let Some(joined) = self.joined.take() {
+                        let _ = joined.send(Ok(()));
+                    }
+                }
+                ControlMessage::Kicked { reason } => {
+                    if let Some(joined) = self.joined.take() {
+                        let _ = joined.send(Err(ClientError::Kicked(reason.clone())));
+                    }
+                    tracing::info!(player_id = %self.player_id, %reason, "client-sdk: kicked from room");
+                    return Some(Ended::Closed);
+                }
+                ControlMessage::Error { code, message } => {
+                    tracing::warn!(player_id = %self.player_id, %code, %message, "client-sdk: gateway reported an error");
+                }
+                _ => {}
+            },
+            FramePayload::State { message: StateMessage::Event { name, data } } => {
+                if let Some(chat) = serde_json::from_value::<ChatMessage>(data).ok().filter(|_| name == "chat") {
+                    let _ = self.chat.send(chat);
+                }
+            }
+            FramePayload::State { message } => {
+                let keyframe = matches!(message, StateMessage::Snapshot { .. });
+                // Delta tới trước keyframe: vẫn áp (delta mang nguyên entity) nhưng xin keyframe để có entity đã xoá
+                if self.snapshot.is_none() && !keyframe {
+                    if let Some(room_id) = self.room_id.clone() {
+                        let request = ControlMessage::RequestKeyframe { room_id, player_id: self.player_id.clone() };
+                        if link.send(Frame::control(0, 0, request)).await.is_err() {
+                            return Some(Ended::Lost);
+                        }
+                    }
+                }
+                let snapshot = self.snapshot.get_or_insert_with(GameSnapshot::default);
+                snapshot.apply(&message);
+                self.snapshots.send_replace(Some(snapshot.clone()));
+            }
+        }
+        None
+    }
+
+    /// Dial lại với backoff rồi join lại room: worker nối lại entity cũ nếu còn trong rejoin grace,
+    /// keyframe dựng lại state vì delta của connection cũ đã mất
+    async fn reconnect(&mut self) -> Option<Link> {
+        let mut backoff = self.config.reconnect_backoff;
+        for attempt in 1..=self.config.reconnect_attempts {
+            tokio::time::sleep(backoff).await;
+            let mut link = match Link::dial(&self.url, &self.token, &self.config).await {
+                Ok(link) => link,
+                // Gateway đã đổi protocol: dial lại cũng không được
+                Err(e @ ClientError::VersionMismatch { .. }) => {
+                    tracing::warn!(player_id = %self.player_id, error = %e, "client-sdk: gateway protocol changed, giving up");
+                    return None;
+                }
+                Err(e) => {
+                    tracing::debug!(player_id = %self.player_id, attempt, error = %e, "client-sdk: reconnect failed");
+                    backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                    continue;
+                }
+            };
+            if let Some(room_id) = self.room_id.clone() {
+                self.snapshot = None;
+                let join = ControlMessage::JoinRoom { room_id: room_id.clone(), reconnect_token: self.resume_token.clone() };
+                let keyframe = ControlMessage::RequestKeyframe { room_id, player_id: self.player_id.clone() };
+                if link.send(Frame::control(0, 0, join)).await.is_err()
+                    || link.send(Frame::control(0, 0, keyframe)).await.is_err()
+                {
+                    continue;
+                }
+            }
+            tracing::info!(player_id = %self.player_id, attempt, "client-sdk: reconnected");
+            self.state.send_replace(ConnectionState::Connected);
+            return Some(link);
+        }
+        None
+    }
+}
+
+/// URL gateway có thể thiếu path `/ws`
+fn ws_url(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    if url.ends_with(WS_PATH) {
+        url.to_string()
+    } else {
+        format!("{url}{WS_PATH}")
+    }
+}
+
+/// Claim `sub` của JWT; không kiểm chữ ký, gateway tự xác thực lúc upgrade
+fn jwt_subject(token: &str) -> Result<String, ClientError> {
+    let invalid = || ClientError::InvalidRequest("token is not a JWT with a `sub` claim".to_string());
+    let payload = token.split('.').nth(1).ok_or_else(invalid)?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|_| invalid())?;
+    let claims: serde_json::Value = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+    claims.get("sub").and_then(|sub| sub.as_str()).map(str::to_string).ok_or_else(invalid)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+    #[test]
+    fn player_id_comes_from_jwt_subject_and_url_gets_ws_path() {
+        let claims = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"sub":"alice","exp":1}"#);
+        assert_eq!(jwt_subject(&format!("header.{claims}.sig")).unwrap(), "alice");
+        assert!(matches!(jwt_subject("not-a-jwt"), Err(ClientError::InvalidRequest(_))));
+
+        assert_eq!(ws_url("ws://127.0.0.1:8080/"), "ws://127.0.0.1:8080/ws");
+        assert_eq!(ws_url("ws://127.0.0.1:8080/ws"), "ws://127.0.0.1:8080/ws");
+    }
+
+    /// Gateway chấp nhận subprotocol bearer
+    #[allow(clippy::result_large_err)]
+    fn accept_bearer(_: &Request, mut response: Response) -> Result<Response, ErrorResponse> {
+        response.headers_mut().insert("sec-websocket-protocol", HeaderValue::from_static(WS_BEARER_PROTOCOL));
+        Ok(response)
+    }
+
+    /// Gateway giả: chờ Hello, gửi transport đã chọn, trả MigrationToken cho JoinRoom; trả về control message
+    /// client gửi lên sau Hello
+    async fn serve_connection(listener: &tokio::net::TcpListener, drop_after_join: bool) -> Vec<ControlMessage> {
+        let (stream, _) = listener.accept().await.expect("accept");
+        let mut socket = tokio_tungstenite::accept_hdr_async(stream, accept_bearer).await.expect("ws accept");
+        let Some(Ok(Message::Binary(bytes))) = socket.next().await else {
+            panic!("client must open with a frame");
+        };
+        assert!(matches!(
+            message::decode(&bytes).expect("frame").payload,
+            FramePayload::Control { message: ControlMessage::Hello { protocol_version } }
+                if protocol_version == message::PROTOCOL_VERSION
+        ));
+        let hello = ControlMessage::TransportSelected {
+            kind: common_net::transport::TransportKind::WebSocket,
+            fallback_used: false,
+            reason: "test".to_string(),
+        };
+        let mut sequence = 1;
+        let encode = |sequence, message| Message::Binary(message::encode(&Frame::control(sequence, 0, message)).unwrap());
+        socket.send(encode(sequence, hello)).await.expect("hello");
+
+        let mut received = Vec::new();
+        while let Some(Ok(Message::Binary(bytes))) = socket.next().await {
+            let FramePayload::Control { message } = message::decode(&bytes).expect("frame").payload else {
+                continue;
+            };
+            received.push(message.clone());
+            match message {
+                ControlMessage::JoinRoom { .. } => {
+                    sequence += 1;
+                    let token = ControlMessage::MigrationToken { connection_id: format!("conn-{sequence}"), auth_nonce: String::new() };
+                    socket.send(encode(sequence, token.clone())).await.expect("token");
+                    // Control frame gửi lại cùng sequence thì client phải bỏ
+                    socket.send(encode(sequence, token)).await.expect("retransmit");
+                    if drop_after_join {
+                        return received;
+                    }
+                }
+                ControlMessage::RequestKeyframe { .. } => return received,
+                _ => {}
+            }
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn lost_connection_reconnects_and_rejoins_the_room() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let url = format!("ws://{}", listener.local_addr().expect("addr"));
+        let claims = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"sub":"alice"}"#);
+        let token = format!("h.{claims}.s");
+        let connect = GameClient::connect_with(&url, &token, ClientConfig {
+            reconnect_backoff: Duration::from_millis(10),
+            ..ClientConfig::default()
+        });
+        let (client, first) = tokio::join!(
+            async {
+                let client = connect.await.expect("connect");
+                client.join_room("room-1").await.expect("join");
+                client
+            },
+            serve_connection(&listener, true)
+        );
+        assert!(matches!(&first[..], [ControlMessage::JoinRoom { room_id, reconnect_token: None }] if room_id == "room-1"));
+
+        let second = serve_connection(&listener, false).await;
+        assert!(matches!(
+            &second[..],
+            [
+                ControlMessage::JoinRoom { room_id, reconnect_token: Some(token) },
+                ControlMessage::RequestKeyframe { player_id, .. },
+            ] if room_id == "room-1" && token == "conn-2" && player_id == "alice"
+        ));
+        assert_eq!(client.connection_state(), ConnectionState::Connected);
+        client.close().await;
+    }
+}
@@ -0,0 +1,108 @@
+//! Dựng lại state của room phía client từ Snapshot/Delta gateway gửi xuống.
+
+use std::collections::BTreeMap;
+
+use common_net::message::StateMessage;
+
+/// State room đã ghép đủ delta; key là id entity, value là JSON entity quantize của worker
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameSnapshot {
+    pub tick: u64,
+    pub entities: BTreeMap<String, serde_json::Value>,
+    /// Độ dài tick của worker (ms) theo snapshot gần nhất, dùng để nội suy
+    pub tick_duration_ms: f32,
+    /// Sequence input mới nhất worker đã xử lý; input cũ hơn không cần replay khi reconcile
+    pub last_acked_sequence: Option<u32>,
+}
+
+impl GameSnapshot {
+    /// Áp một Snapshot (thay toàn bộ) hoặc Delta (entity mới/đổi thay nguyên entity, `deleted` thì xoá).
+    /// Trả về false với message không phải state của room (event, ...)
+    pub fn apply(&mut self, message: &StateMessage) -> bool {
+        match message {
+            StateMessage::Snapshot { tick, entities, tick_duration_ms, last_acked_sequence } => {
+                self.tick = *tick;
+                self.tick_duration_ms = *tick_duration_ms;
+                self.last_acked_sequence = *last_acked_sequence;
+                self.entities = entities
+                    .iter()
+                    .map(|entity| (entity.id.clone(), entity.components.clone()))
+                    .collect();
+                true
+            }
+            StateMessage::Delta { tick, changes, tick_duration_ms, last_acked_sequence } => {
+                self.tick = *tick;
+                self.tick_duration_ms = *tick_duration_ms;
+                self.last_acked_sequence = *last_acked_sequence;
+                for change in changes {
+                    if change.changes.get("deleted").and_then(|v| v.as_bool()) == Some(true) {
+                        self.entities.remove(&change.id);
+                    } else {
+                        self.entities.insert(change.id.clone(), change.changes.clone());
+                    }
+                }
+                true
+            }
+            StateMessage::Event { .. } => false,
+        }
+    }
+
+    /// Entity của player (theo `player.id`)
+    pub fn player(&self, player_id: &str) -> Option<&serde_json::Value> {
+        self.entities
+            .values()
+            .find(|entity| entity.pointer("/player/id").and_then(|v| v.as_str()) == Some(player_id))
+    }
+
+    pub fn score(&self, player_id: &str) -> Option<u32> {
+        self.player(player_id)?
+            .pointer("/player/score")
+            .and_then(|v| v.as_u64())
+            .map(|score| score as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_net::message::{EntityDelta, EntitySnapshot};
+    use serde_json::json;
+
+    fn player(id: u32, player_id: &str, score: u32) -> serde_json::Value {
+        json!({ "id": id, "player": { "id": player_id, "score": score } })
+    }
+
+    #[test]
+    fn delta_updates_creates_and_deletes_entities_on_top_of_keyframe() {
+        let mut snapshot = GameSnapshot::default();
+        snapshot.apply(&StateMessage::Snapshot {
+            tick: 10,
+            entities: vec![
+                EntitySnapshot { id: "1".to_string(), components: player(1, "alice", 0) },
+                EntitySnapshot { id: "2".to_string(), components: json!({ "id": 2, "pickup": { "value": 5 } }) },
+            ],
+            tick_duration_ms: 16.0,
+            last_acked_sequence: None,
+        });
+        assert_eq!(snapshot.score("alice"), Some(0));
+
+        assert!(snapshot.apply(&StateMessage::Delta {
+            tick: 12,
+            changes: vec![
+                EntityDelta { id: "1".to_string(), changes: player(1, "alice", 5) },
+                EntityDelta { id: "2".to_string(), changes: json!({ "deleted": true }) },
+                EntityDelta { id: "3".to_string(), changes: player(3, "bob", 1) },
+            ],
+            tick_duration_ms: 16.0,
+            last_acked_sequence: Some(4),
+        }));
+        assert_eq!(snapshot.tick, 12);
+        assert_eq!(snapshot.last_acked_sequence, Some(4));
+        assert_eq!(snapshot.entities.keys().collect::<Vec<_>>(), ["1", "3"]);
+        assert_eq!(snapshot.score("alice"), Some(5));
+        assert_eq!(snapshot.score("bob"), Some(1));
+
+        assert!(!snapshot.apply(&StateMessage::Event { name: "chat".to_string(), data: json!({}) }));
+        assert_eq!(snapshot.tick, 12);
+    }
+}
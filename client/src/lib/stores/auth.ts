@@ -1,4 +1,5 @@
 import { writable } from 'svelte/store';
+import { gatewayErrorMessage } from './types';
 // Simplified auth store for debugging SSR issues
 
 export interface User {
@@ -71,8 +72,8 @@ export const authActions = {
       });
 
       if (!response.ok) {
-        const errorData = await response.json().catch(() => ({ message: 'Login failed' }));
-        throw new Error(errorData.message || `HTTP ${response.status}`);
+        const errorData = await response.json().catch(() => ({}));
+        throw new Error(gatewayErrorMessage(errorData, 'Login failed'));
       }
 
       const data = await response.json();
@@ -1,5 +1,6 @@
 import { writable, derived, get } from 'svelte/store';
 import type { GameSnapshot, PlayerInput, EntitySnapshot } from './types';
+import { gatewayErrorMessage } from './types';
 
 // Game state store
 export const gameState = writable<GameSnapshot | null>(null);
@@ -137,7 +138,7 @@ export class GameService {
                 console.log(`✅ Player ${playerId} joined room ${this.roomId}`);
                 return true;
             } else {
-                throw new Error(data.error || `HTTP ${response.status}: ${response.statusText}`);
+                throw new Error(gatewayErrorMessage(data, `HTTP ${response.status}: ${response.statusText}`));
             }
         } catch (error) {
             const errorMessage = error.message || 'Unknown error';
@@ -1,5 +1,7 @@
 import { writable, derived } from 'svelte/store';
+import { authActions } from './auth';
 import type { Room, RoomInfo, RoomSettings, RoomListFilter, CreateRoomRequest, JoinRoomRequest, RoomOperationResponse, RoomState, GameMode } from './types';
+import { gatewayErrorMessage } from './types';
 
 // Room state store
 export const currentRoom = writable<Room | null>(null);
@@ -53,10 +55,10 @@ export class RoomService {
                     data: data,
                 };
             } else {
-                roomError.set(data.error || 'Failed to create room');
+                roomError.set(gatewayErrorMessage(data, 'Failed to create room'));
                 return {
                     success: false,
-                    error: data.error || 'Failed to create room',
+                    error: gatewayErrorMessage(data, 'Failed to create room'),
                 };
             }
         } catch (error) {
@@ -99,10 +101,10 @@ export class RoomService {
                     data: transformedRooms,
                 };
             } else {
-                roomError.set(data.error || 'Failed to list rooms');
+                roomError.set(gatewayErrorMessage(data, 'Failed to list rooms'));
                 return {
                     success: false,
-                    error: data.error || 'Failed to list rooms',
+                    error: gatewayErrorMessage(data, 'Failed to list rooms'),
                 };
             }
         } catch (error) {
@@ -131,10 +133,10 @@ export class RoomService {
                     data: data.room,
                 };
             } else {
-                roomError.set(data.error || 'Failed to get room info');
+                roomError.set(gatewayErrorMessage(data, 'Failed to get room info'));
                 return {
                     success: false,
-                    error: data.error || 'Failed to get room info',
+                    error: gatewayErrorMessage(data, 'Failed to get room info'),
                 };
             }
         } catch (error) {
@@ -171,10 +173,10 @@ export class RoomService {
                     data: data,
                 };
             } else {
-                roomError.set(data.error || 'Failed to join room');
+                roomError.set(gatewayErrorMessage(data, 'Failed to join room'));
                 return {
                     success: false,
-                    error: data.error || 'Failed to join room',
+                    error: gatewayErrorMessage(data, 'Failed to join room'),
                 };
             }
         } catch (error) {
@@ -212,10 +214,10 @@ export class RoomService {
                     data: data,
                 };
             } else {
-                roomError.set(data.error || 'Failed to start game');
+                roomError.set(gatewayErrorMessage(data, 'Failed to start game'));
                 return {
                     success: false,
-                    error: data.error || 'Failed to start game',
+                    error: gatewayErrorMessage(data, 'Failed to start game'),
                 };
             }
         } catch (error) {
@@ -520,7 +522,7 @@ export const roomActions = {
                 leaderboardData.set(data.leaderboard || []);
                 return data.leaderboard || [];
             } else {
-                throw new Error(data.error || `HTTP ${response.status}: ${response.statusText}`);
+                throw new Error(gatewayErrorMessage(data, `HTTP ${response.status}: ${response.statusText}`));
             }
         } catch (error) {
             const errorMessage = error.message || 'Unknown error';
@@ -534,17 +536,18 @@ export const roomActions = {
 
     async submitScore(playerId: string, score: number, gameMode: string, roomId?: string): Promise<boolean> {
         try {
+            // Gateway lấy player từ token; điểm phải dưới trần của mode và kèm room vừa chơi
             const response = await fetch(`${this.gatewayUrl}/api/leaderboard/submit`, {
                 method: 'POST',
                 headers: {
                     'Content-Type': 'application/json',
+                    ...authActions.getAuthHeaders(),
                 },
                 body: JSON.stringify({
-                    playerId,
+                    player_id: playerId,
                     score,
-                    gameMode,
-                    roomId,
-                    timestamp: Date.now(),
+                    game_mode: gameMode,
+                    room_id: roomId,
                 }),
             });
 
@@ -556,7 +559,7 @@ export const roomActions = {
                 await this.loadLeaderboard(gameMode);
                 return true;
             } else {
-                throw new Error(data.error || `HTTP ${response.status}: ${response.statusText}`);
+                throw new Error(gatewayErrorMessage(data, `HTTP ${response.status}: ${response.statusText}`));
             }
         } catch (error) {
             console.error('❌ Failed to submit score:', error);
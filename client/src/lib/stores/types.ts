@@ -213,3 +213,7 @@ export enum GameMode {
     KingOfTheHill = 'king_of_the_hill'
 }
 
+// Envelope lỗi chung của gateway: { error: { code, message } }
+export function gatewayErrorMessage(data: any, fallback: string): string {
+    return data?.error?.message || fallback;
+}
@@ -0,0 +1,200 @@
+//! Registry các game mode dùng chung cho room-manager, gateway và worker. Mode dựng sẵn được đăng ký
+//! khi registry được dùng lần đầu; thêm mode chỉ cần `register` một descriptor, không phải sửa handler.
+
+use std::{collections::BTreeMap, fmt, sync::RwLock};
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+pub const DEATHMATCH: &str = "deathmatch";
+pub const TEAM_DEATHMATCH: &str = "team_deathmatch";
+pub const CAPTURE_THE_FLAG: &str = "capture_the_flag";
+pub const KING_OF_THE_HILL: &str = "king_of_the_hill";
+pub const ENDLESS_RUNNER: &str = "endless_runner";
+
+/// Cách mode tính điểm và kết thúc trận
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoringType {
+    /// Điểm theo kill, trận dừng khi có người đạt `score_target`
+    Kills,
+    /// Điểm theo mục tiêu (cờ, vùng chiếm)
+    Objective,
+    /// Mỗi player chạy tới khi chết, điểm theo quãng đường/pickup
+    Distance,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameModeDescriptor {
+    pub id: String,
+    pub display_name: String,
+    /// Khoảng sức chứa hợp lệ của phòng
+    pub min_players: u32,
+    pub max_players: u32,
+    /// Chia hai đội bằng nhau
+    pub team_based: bool,
+    /// Settings mặc định của phòng mode này (`max_players`, `min_players_to_start`, `max_score`, ...)
+    pub default_settings: serde_json::Value,
+    pub scoring: ScoringType,
+}
+
+impl GameModeDescriptor {
+    /// Giá trị u32 trong `default_settings`
+    pub fn default_setting(&self, key: &str) -> Option<u32> {
+        self.default_settings.get(key).and_then(|value| value.as_u64()).map(|value| value as u32)
+    }
+}
+
+/// Mode dựng sẵn của game
+pub fn builtin_modes() -> Vec<GameModeDescriptor> {
+    let mode = |id: &str, display_name: &str, (min_players, max_players): (u32, u32), team_based, (default_max, min_to_start): (u32, u32), scoring, max_score: u32| {
+        GameModeDescriptor {
+            id: id.to_string(),
+            display_name: display_name.to_string(),
+            min_players,
+            max_players,
+            team_based,
+            default_settings: serde_json::json!({
+                "max_players": default_max,
+                "min_players_to_start": min_to_start,
+                "max_score": max_score,
+            }),
+            scoring,
+        }
+    };
+    vec![
+        mode(DEATHMATCH, "Deathmatch", (2, 16), false, (8, 2), ScoringType::Kills, 10_000),
+        mode(TEAM_DEATHMATCH, "Team Deathmatch", (2, 16), true, (8, 4), ScoringType::Kills, 10_000),
+        mode(CAPTURE_THE_FLAG, "Capture the Flag", (2, 16), true, (10, 4), ScoringType::Objective, 10_000),
+        mode(KING_OF_THE_HILL, "King of the Hill", (2, 16), false, (8, 2), ScoringType::Objective, 10_000),
+        mode(ENDLESS_RUNNER, "Endless Runner", (1, 8), false, (4, 1), ScoringType::Distance, 1_000_000),
+    ]
+}
+
+static REGISTRY: OnceCell<RwLock<BTreeMap<String, GameModeDescriptor>>> = OnceCell::new();
+
+fn registry() -> &'static RwLock<BTreeMap<String, GameModeDescriptor>> {
+    REGISTRY.get_or_init(|| RwLock::new(builtin_modes().into_iter().map(|mode| (mode.id.clone(), mode)).collect()))
+}
+
+/// Đăng ký (hoặc thay) mode theo id
+pub fn register(descriptor: GameModeDescriptor) {
+    let mut modes = registry().write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    modes.insert(descriptor.id.clone(), descriptor);
+}
+
+pub fn from_id(id: &str) -> Option<GameModeDescriptor> {
+    registry().read().unwrap_or_else(|poisoned| poisoned.into_inner()).get(id).cloned()
+}
+
+/// Id của mọi mode đã đăng ký, theo thứ tự chữ cái
+pub fn ids() -> Vec<String> {
+    registry().read().unwrap_or_else(|poisoned| poisoned.into_inner()).keys().cloned().collect()
+}
+
+pub fn all() -> Vec<GameModeDescriptor> {
+    registry().read().unwrap_or_else(|poisoned| poisoned.into_inner()).values().cloned().collect()
+}
+
+/// Id không có trong registry, kèm các id hợp lệ để trả về cho client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownGameMode {
+    pub id: String,
+    pub valid_ids: Vec<String>,
+}
+
+impl fmt::Display for UnknownGameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown game mode '{}', valid ids: {}", self.id, self.valid_ids.join(", "))
+    }
+}
+
+impl std::error::Error for UnknownGameMode {}
+
+/// Id của một mode đã đăng ký; serde dạng chuỗi id và từ chối id lạ
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct GameModeId(String);
+
+impl GameModeId {
+    pub fn parse(id: &str) -> Result<Self, UnknownGameMode> {
+        match from_id(id) {
+            Some(_) => Ok(Self(id.to_string())),
+            None => Err(UnknownGameMode { id: id.to_string(), valid_ids: ids() }),
+        }
+    }
+
+    pub fn deathmatch() -> Self {
+        Self(DEATHMATCH.to_string())
+    }
+
+    pub fn team_deathmatch() -> Self {
+        Self(TEAM_DEATHMATCH.to_string())
+    }
+
+    pub fn capture_the_flag() -> Self {
+        Self(CAPTURE_THE_FLAG.to_string())
+    }
+
+    pub fn endless_runner() -> Self {
+        Self(ENDLESS_RUNNER.to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Descriptor hiện tại của mode (registry không xoá mode nên id đã parse luôn có)
+    pub fn descriptor(&self) -> Option<GameModeDescriptor> {
+        from_id(&self.0)
+    }
+}
+
+impl TryFrom<String> for GameModeId {
+    type Error = UnknownGameMode;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        Self::parse(&id)
+    }
+}
+
+impl From<GameModeId> for String {
+    fn from(id: GameModeId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for GameModeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registered_modes_parse_and_unknown_ids_list_the_valid_ones() {
+        register(GameModeDescriptor {
+            id: "test_sumo".to_string(),
+            display_name: "Sumo".to_string(),
+            min_players: 2,
+            max_players: 2,
+            team_based: false,
+            default_settings: serde_json::json!({ "max_players": 2, "min_players_to_start": 2 }),
+            scoring: ScoringType::Objective,
+        });
+
+        let sumo: GameModeId = serde_json::from_str("\"test_sumo\"").unwrap();
+        assert_eq!(sumo.descriptor().unwrap().display_name, "Sumo");
+        assert_eq!(serde_json::to_string(&GameModeId::endless_runner()).unwrap(), "\"endless_runner\"");
+        assert_eq!(from_id(ENDLESS_RUNNER).unwrap().scoring, ScoringType::Distance);
+
+        let unknown = GameModeId::parse("tag").unwrap_err();
+        assert!(unknown.valid_ids.iter().any(|id| id == DEATHMATCH));
+        assert!(unknown.valid_ids.iter().any(|id| id == "test_sumo"));
+        let message = serde_json::from_str::<GameModeId>("\"tag\"").unwrap_err().to_string();
+        assert!(message.contains("unknown game mode 'tag'"), "{message}");
+    }
+}
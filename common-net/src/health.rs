@@ -0,0 +1,243 @@
+//! Readiness dùng chung cho gateway, worker và room-manager.
+//! `/healthz` chỉ là liveness (process còn sống); `/readyz` chạy các check phụ thuộc, cache kết quả vài giây
+//! để scrape dồn dập không đập vào dependency, và trả 503 khi có dependency bắt buộc bị down.
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use once_cell::sync::Lazy;
+use prometheus::{register_int_gauge_vec, IntGaugeVec};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+pub const HEALTHZ_PATH: &str = "/healthz";
+pub const READYZ_PATH: &str = "/readyz";
+
+/// Kết quả check được cache ngần này trước khi chạy lại
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(3);
+/// Mỗi check bị cắt sau ngần này và tính là down
+pub const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+static DEPENDENCY_STATUS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "service_dependency_status",
+        "Trang thai dependency trong /readyz: 2 = ok, 1 = degraded, 0 = down",
+        &["service", "dependency"]
+    )
+    .expect("register service_dependency_status")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyStatus {
+    Ok,
+    Degraded,
+    Down,
+}
+
+impl DependencyStatus {
+    pub fn gauge_value(self) -> i64 {
+        match self {
+            DependencyStatus::Ok => 2,
+            DependencyStatus::Degraded => 1,
+            DependencyStatus::Down => 0,
+        }
+    }
+}
+
+pub type CheckFuture = Pin<Box<dyn Future<Output = DependencyStatus> + Send>>;
+type CheckFn = Arc<dyn Fn() -> CheckFuture + Send + Sync>;
+
+struct Check {
+    name: &'static str,
+    /// Dependency bắt buộc: down thì cả service not ready. Dependency phụ down chỉ báo `degraded`
+    required: bool,
+    run: CheckFn,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub dependencies: BTreeMap<&'static str, DependencyStatus>,
+}
+
+impl IntoResponse for ReadinessReport {
+    fn into_response(self) -> Response {
+        let status = if self.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        (status, Json(self.dependencies)).into_response()
+    }
+}
+
+/// Tập check readiness của một service, clone rẻ để làm state cho router
+#[derive(Clone)]
+pub struct Readiness {
+    service: &'static str,
+    checks: Arc<Vec<Check>>,
+    cache_ttl: Duration,
+    check_timeout: Duration,
+    cached: Arc<Mutex<Option<(Instant, ReadinessReport)>>>,
+}
+
+impl Readiness {
+    pub fn new(service: &'static str) -> Self {
+        Self {
+            service,
+            checks: Arc::new(Vec::new()),
+            cache_ttl: DEFAULT_CACHE_TTL,
+            check_timeout: DEFAULT_CHECK_TIMEOUT,
+            cached: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    pub fn with_check_timeout(mut self, timeout: Duration) -> Self {
+        self.check_timeout = timeout;
+        self
+    }
+
+    /// Dependency bắt buộc, down thì `/readyz` trả 503
+    pub fn required<F, Fut>(self, name: &'static str, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = DependencyStatus> + Send + 'static,
+    {
+        self.push(name, true, check)
+    }
+
+    /// Dependency phụ, down được báo là `degraded` nhưng service vẫn ready
+    pub fn optional<F, Fut>(self, name: &'static str, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = DependencyStatus> + Send + 'static,
+    {
+        self.push(name, false, check)
+    }
+
+    fn push<F, Fut>(mut self, name: &'static str, required: bool, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = DependencyStatus> + Send + 'static,
+    {
+        let run: CheckFn = Arc::new(move || Box::pin(check()) as CheckFuture);
+        Arc::get_mut(&mut self.checks)
+            .expect("readiness checks are registered before the probe is cloned")
+            .push(Check { name, required, run });
+        self
+    }
+
+    /// Kết quả cache nếu còn hạn, không thì chạy lại mọi check song song và cập nhật gauge
+    pub async fn report(&self) -> ReadinessReport {
+        let mut cached = self.cached.lock().await;
+        if let Some((at, report)) = cached.as_ref() {
+            if at.elapsed() < self.cache_ttl {
+                return report.clone();
+            }
+        }
+
+        let timeout = self.check_timeout;
+        let results = futures::future::join_all(self.checks.iter().map(|check| {
+            let run = check.run.clone();
+            async move {
+                tokio::time::timeout(timeout, run())
+                    .await
+                    .unwrap_or(DependencyStatus::Down)
+            }
+        }))
+        .await;
+
+        let mut report = ReadinessReport { ready: true, dependencies: BTreeMap::new() };
+        for (check, status) in self.checks.iter().zip(results) {
+            let status = match (check.required, status) {
+                (false, DependencyStatus::Down) => DependencyStatus::Degraded,
+                (_, status) => status,
+            };
+            if check.required && status == DependencyStatus::Down {
+                report.ready = false;
+            }
+            DEPENDENCY_STATUS
+                .with_label_values(&[self.service, check.name])
+                .set(status.gauge_value());
+            report.dependencies.insert(check.name, status);
+        }
+
+        *cached = Some((Instant::now(), report.clone()));
+        report
+    }
+
+    /// Router có `/healthz` (luôn 200) và `/readyz`, merge vào router của service
+    pub fn router(self) -> Router {
+        Router::new()
+            .route(HEALTHZ_PATH, get(|| async { StatusCode::OK }))
+            .route(READYZ_PATH, get(readyz_handler))
+            .with_state(self)
+    }
+}
+
+async fn readyz_handler(State(readiness): State<Readiness>) -> ReadinessReport {
+    readiness.report().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn required_down_fails_and_optional_down_degrades() {
+        let readiness = Readiness::new("health-test")
+            .required("lock", || async { DependencyStatus::Ok })
+            .optional("pocketbase", || async { DependencyStatus::Down });
+        let report = readiness.report().await;
+        assert!(report.ready);
+        assert_eq!(report.dependencies["pocketbase"], DependencyStatus::Degraded);
+
+        let readiness = Readiness::new("health-test").required("worker", || async { DependencyStatus::Down });
+        assert!(!readiness.report().await.ready);
+    }
+
+    #[tokio::test]
+    async fn reports_are_cached_within_ttl() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let readiness = Readiness::new("health-test")
+            .with_cache_ttl(Duration::from_secs(60))
+            .required("worker", move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { DependencyStatus::Ok }
+            });
+
+        for _ in 0..5 {
+            readiness.report().await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn slow_check_times_out_as_down() {
+        let readiness = Readiness::new("health-test")
+            .with_check_timeout(Duration::from_millis(20))
+            .required("worker", || async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                DependencyStatus::Ok
+            });
+        let report = readiness.report().await;
+        assert!(!report.ready);
+        assert_eq!(report.dependencies["worker"], DependencyStatus::Down);
+    }
+}
@@ -1,8 +1,12 @@
 pub mod cache;
 pub mod compression;
+pub mod game_modes;
+pub mod health;
 pub mod message;
+pub mod matchmaking;
 pub mod metrics;
 pub mod quantization;
+pub mod scores;
 pub mod shutdown;
 pub mod snapshot;
 pub mod telemetry;
@@ -1,14 +1,29 @@
+use pocketbase::PocketBaseClient;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, BinaryHeap};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::sync::{RwLock, RwLockReadGuard};
-use tracing::{debug, error, info, warn};
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
+/// player_id prefix of worker bot players; bots are never rated
+pub const BOT_PLAYER_PREFIX: &str = "bot-";
+
+/// PocketBase collection holding `PlayerRating` records (record id = player_id)
+pub const PLAYER_RATINGS_COLLECTION: &str = "player_ratings";
+
+/// Glicko-2 scale factor between the rating scale and the internal μ/φ scale
+const GLICKO_SCALE: f32 = 173.7178;
+/// Rating mapped to μ = 0
+const GLICKO_CENTER: f32 = 1500.0;
+/// System constant τ, constrains how fast volatility changes
+const GLICKO_TAU: f64 = 0.5;
+const GLICKO_EPSILON: f64 = 0.000_001;
+
 /// Advanced matchmaking system for skill-based matching and tournaments
 #[derive(Debug)]
 pub struct MatchmakingSystem {
@@ -18,6 +33,7 @@ pub struct MatchmakingSystem {
     player_ratings: Arc<RwLock<HashMap<String, PlayerRating>>>,
     metrics: Arc<MatchmakingMetrics>,
     config: MatchmakingConfig,
+    ratings_store: Option<PocketBaseClient>,
 }
 
 /// Matchmaking queue for different game modes
@@ -36,8 +52,19 @@ pub struct QueuedPlayer {
     pub skill_rating: f32,
     pub queued_at: u64,
     pub region: String,
+    /// Latency (ms) the player will accept; at or above `cross_region_latency_ms` they play cross-region right away
     pub preferred_latency: u32,
     pub priority: i32, // Higher priority players get matched first
+    /// Other members when `player_id` queues as party leader; the whole party is matched as one entry
+    #[serde(default)]
+    pub party_members: Vec<String>,
+}
+
+impl QueuedPlayer {
+    /// Match slots taken by this entry: the leader plus their party
+    pub fn size(&self) -> usize {
+        1 + self.party_members.len()
+    }
 }
 
 impl Ord for QueuedPlayer {
@@ -218,10 +245,20 @@ pub struct MatchmakingConfig {
     pub max_players_per_match: u32,
     /// Enable strict skill matching
     pub strict_skill_matching: bool,
-    /// Enable region-based matching
+    /// Enable region-based matching: players only meet other regions once both tolerate it
     pub region_based_matching: bool,
+    /// Estimated latency (ms) added by playing against another region
+    pub cross_region_latency_ms: u32,
+    /// Seconds in queue after which a player accepts cross-region matches regardless of `preferred_latency`
+    pub cross_region_wait: u64,
     /// Enable priority queue for premium players
     pub priority_queue: bool,
+    /// Record queue/match metrics
+    pub enable_metrics: bool,
+    /// Extra skill gap allowed once a player has waited the full `max_wait_time`
+    pub skill_diff_relaxation: f32,
+    /// A party queues at its average rating plus this fraction of the gap between its best and worst member
+    pub party_skill_spread_penalty: f32,
 }
 
 impl Default for MatchmakingConfig {
@@ -233,7 +270,12 @@ impl Default for MatchmakingConfig {
             max_players_per_match: 8,
             strict_skill_matching: false,
             region_based_matching: true,
+            cross_region_latency_ms: 100,
+            cross_region_wait: 30,
             priority_queue: true,
+            enable_metrics: true,
+            skill_diff_relaxation: 800.0,
+            party_skill_spread_penalty: 0.25,
         }
     }
 }
@@ -286,6 +328,10 @@ impl MatchmakingMetrics {
         }
     }
 
+    pub fn set_players_waiting(&self, waiting: u64) {
+        self.players_waiting.store(waiting, Ordering::Relaxed);
+    }
+
     pub fn update_queue_size(&self, size: u64) {
         let current = self.avg_queue_size.load(Ordering::Relaxed);
         let new_avg = (current + size) / 2;
@@ -315,9 +361,16 @@ impl MatchmakingSystem {
             player_ratings: Arc::new(RwLock::new(HashMap::new())),
             metrics: Arc::new(MatchmakingMetrics::default()),
             config,
+            ratings_store: None,
         }
     }
 
+    /// Persist ratings updated by `record_result` to PocketBase
+    pub fn with_pocketbase(mut self, client: PocketBaseClient) -> Self {
+        self.ratings_store = Some(client);
+        self
+    }
+
     /// Queue a player for matchmaking
     pub async fn queue_player(&self, player_id: &str, game_mode: &str, region: &str) -> Result<String, BoxError> {
         let player_rating = self.get_or_create_player_rating(player_id).await;
@@ -329,8 +382,57 @@ impl MatchmakingSystem {
             region: region.to_string(),
             preferred_latency: 50, // Default 50ms
             priority: if self.config.priority_queue { 1 } else { 0 },
+            party_members: Vec::new(),
+        };
+
+        self.enqueue(game_mode, queued_player).await;
+
+        debug!("Player {} queued for {} matchmaking", player_id, game_mode);
+        Ok("queued".to_string())
+    }
+
+    /// Queue a party as a single entry led by `leader_id`; matches take every member or none
+    pub async fn queue_party(&self, leader_id: &str, members: &[String], game_mode: &str, region: &str) -> Result<String, BoxError> {
+        let party_size = 1 + members.len();
+        if party_size > self.config.max_players_per_match as usize {
+            return Err(format!("party of {party_size} does not fit a {game_mode} match").into());
+        }
+
+        let mut skills = vec![self.get_or_create_player_rating(leader_id).await.skill_rating];
+        for member in members {
+            skills.push(self.get_or_create_player_rating(member).await.skill_rating);
+        }
+
+        let queued_party = QueuedPlayer {
+            player_id: leader_id.to_string(),
+            skill_rating: self.party_skill(&skills),
+            queued_at: chrono::Utc::now().timestamp() as u64,
+            region: region.to_string(),
+            preferred_latency: 50,
+            priority: if self.config.priority_queue { 1 } else { 0 },
+            party_members: members.to_vec(),
         };
 
+        self.enqueue(game_mode, queued_party).await;
+
+        debug!("Party of {} led by {} queued for {} matchmaking", party_size, leader_id, game_mode);
+        Ok("queued".to_string())
+    }
+
+    /// Rating a party queues at: the members' average, raised by `party_skill_spread_penalty`
+    /// of their spread so a strong player cannot carry low-rated friends into easy lobbies
+    pub fn party_skill(&self, skills: &[f32]) -> f32 {
+        if skills.is_empty() {
+            return 0.0;
+        }
+        let average = skills.iter().sum::<f32>() / skills.len() as f32;
+        let (low, high) = skills
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), &skill| (low.min(skill), high.max(skill)));
+        average + self.config.party_skill_spread_penalty * (high - low)
+    }
+
+    async fn enqueue(&self, game_mode: &str, queued_player: QueuedPlayer) {
         let mut queues = self.queues.write().await;
 
         let queue = queues.entry(game_mode.to_string()).or_insert_with(|| {
@@ -350,96 +452,151 @@ impl MatchmakingSystem {
             self.metrics.record_player_waiting(true);
             self.metrics.update_queue_size(queue.players.len() as u64);
         }
-
-        debug!("Player {} queued for {} matchmaking", player_id, game_mode);
-        Ok("queued".to_string())
     }
 
     /// Find matches for all game modes
     pub async fn find_matches(&self) -> Result<Vec<GameMatch>, BoxError> {
+        Ok(self.tick().await)
+    }
+
+    /// One matchmaking pass over every queue; call periodically (see `spawn_tick_loop`).
+    /// Returns the matches formed in this pass, their players are removed from the queue.
+    pub async fn tick(&self) -> Vec<GameMatch> {
+        self.tick_at(chrono::Utc::now().timestamp() as u64).await
+    }
+
+    async fn tick_at(&self, now: u64) -> Vec<GameMatch> {
         let mut matches = Vec::new();
-        let queues = self.queues.read().await;
+        let mut queues = self.queues.write().await;
 
-        for (game_mode, queue) in queues.iter() {
-            if let Some(new_matches) = self.find_matches_in_queue(queue).await {
-                matches.extend(new_matches);
-            }
+        for queue in queues.values_mut() {
+            matches.extend(self.form_matches(queue, now));
         }
 
-        if self.config.enable_metrics && !matches.is_empty() {
-            for _ in &matches {
-                self.metrics.record_match_created();
-            }
+        if self.config.enable_metrics {
+            let waiting: usize = queues.values().map(|queue| queue.players.len()).sum();
+            self.metrics.set_players_waiting(waiting as u64);
         }
 
-        Ok(matches)
+        if !matches.is_empty() {
+            info!("Matchmaking tick formed {} matches", matches.len());
+        }
+        matches
     }
 
-    /// Find matches within a specific queue
-    async fn find_matches_in_queue(&self, queue: &MatchmakingQueue) -> Option<Vec<GameMatch>> {
-        let mut players = Vec::new();
-        let mut remaining_players = BinaryHeap::new();
+    /// Runs `tick` every `interval` and emits formed matches; stops once the receiver is dropped
+    pub fn spawn_tick_loop(self: Arc<Self>, interval: Duration) -> (tokio::task::JoinHandle<()>, mpsc::UnboundedReceiver<GameMatch>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                for game_match in self.tick().await {
+                    if tx.send(game_match).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        (handle, rx)
+    }
 
-        // Extract players that have been waiting too long or can be matched
-        let now = chrono::Utc::now().timestamp() as u64;
+    /// Skill gap a player accepts: starts at the queue's `min_skill_diff` and widens linearly
+    /// by `skill_diff_relaxation` as the wait approaches `max_wait_time`
+    fn allowed_skill_diff(&self, queue: &MatchmakingQueue, player: &QueuedPlayer, now: u64) -> f32 {
+        let max_wait = queue.max_wait_time.as_secs().max(1) as f32;
+        let waited = now.saturating_sub(player.queued_at) as f32;
+        queue.min_skill_diff + self.config.skill_diff_relaxation * (waited / max_wait).min(1.0)
+    }
 
-        while let Some(player) = queue.players.peek().cloned() {
-            if players.len() >= self.config.max_players_per_match as usize {
-                remaining_players.push(player);
-                break;
-            }
+    /// Whether a player tolerates the latency of a match hosted in another region
+    fn accepts_cross_region(&self, player: &QueuedPlayer, now: u64) -> bool {
+        player.preferred_latency >= self.config.cross_region_latency_ms
+            || now.saturating_sub(player.queued_at) >= self.config.cross_region_wait
+    }
 
-            // Check if player has been waiting too long
-            if now - player.queued_at > self.config.max_wait_time {
-                players.push(queue.players.pop().unwrap());
+    /// Greedy grouping: the longest-waiting (highest priority first) player anchors a group and
+    /// takes compatible players until `max_players_per_match`, same-region candidates first.
+    /// With `region_based_matching` a candidate from another region joins only when both it and
+    /// the anchor accept cross-region play. An anchor past `max_wait_time` may start with only
+    /// `min_players_per_match`. A party counts as all its members and is never split: it is only
+    /// taken when every member fits in the remaining slots, and larger parties are tried first.
+    fn form_matches(&self, queue: &mut MatchmakingQueue, now: u64) -> Vec<GameMatch> {
+        let mut waiting = std::mem::take(&mut queue.players).into_vec();
+        waiting.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.queued_at.cmp(&b.queued_at)));
+
+        let match_size = queue.max_players_per_match.max(1) as usize;
+        let min_size = (self.config.min_players_per_match.max(1) as usize).min(match_size);
+        let mut matched = vec![false; waiting.len()];
+        let mut matches = Vec::new();
+
+        for anchor in 0..waiting.len() {
+            if matched[anchor] {
                 continue;
             }
 
-            // Check if we can create a balanced match
-            if self.can_create_balanced_match(&players, &player, queue).await {
-                players.push(queue.players.pop().unwrap());
-            } else {
-                remaining_players.push(player);
-                break;
+            if waiting[anchor].size() > match_size {
+                continue;
+            }
+            let mut group = vec![anchor];
+            let mut slots = waiting[anchor].size();
+            let (mut low, mut high) = (waiting[anchor].skill_rating, waiting[anchor].skill_rating);
+            let anchor_allowed = self.allowed_skill_diff(queue, &waiting[anchor], now);
+            let anchor_crosses = self.accepts_cross_region(&waiting[anchor], now);
+            let mut candidates: Vec<usize> = (anchor + 1..waiting.len()).collect();
+            // Larger parties first so solo players do not fragment the remaining slots
+            candidates.sort_by_key(|&candidate| {
+                (waiting[candidate].region != waiting[anchor].region, std::cmp::Reverse(waiting[candidate].size()))
+            });
+            for candidate in candidates {
+                if slots == match_size {
+                    break;
+                }
+                if matched[candidate] || slots + waiting[candidate].size() > match_size {
+                    continue;
+                }
+                if self.config.region_based_matching
+                    && waiting[candidate].region != waiting[anchor].region
+                    && !(anchor_crosses && self.accepts_cross_region(&waiting[candidate], now))
+                {
+                    continue;
+                }
+                let skill = waiting[candidate].skill_rating;
+                let allowed = anchor_allowed.max(self.allowed_skill_diff(queue, &waiting[candidate], now));
+                if high.max(skill) - low.min(skill) <= allowed {
+                    low = low.min(skill);
+                    high = high.max(skill);
+                    group.push(candidate);
+                    slots += waiting[candidate].size();
+                }
             }
-        }
-
-        // Restore remaining players to queue
-        while let Some(player) = remaining_players.pop() {
-            // In a real implementation, this would push back to the queue
-            // For now, we'll just continue
-        }
-
-        if players.len() >= self.config.min_players_per_match as usize {
-            Some(vec![self.create_match_from_players(&players, &queue.game_mode)])
-        } else {
-            None
-        }
-    }
-
-    /// Check if we can create a balanced match with the given players
-    async fn can_create_balanced_match(&self, current_players: &[QueuedPlayer], new_player: &QueuedPlayer, queue: &MatchmakingQueue) -> bool {
-        if current_players.is_empty() {
-            return true;
-        }
-
-        let avg_skill = current_players.iter().map(|p| p.skill_rating).sum::<f32>() / current_players.len() as f32;
-        let skill_diff = (new_player.skill_rating - avg_skill).abs();
 
-        // Check skill balance
-        if skill_diff > queue.min_skill_diff && self.config.strict_skill_matching {
-            return false;
-        }
+            let timed_out = now.saturating_sub(waiting[anchor].queued_at) >= queue.max_wait_time.as_secs();
+            if slots < match_size && !(timed_out && slots >= min_size) {
+                continue;
+            }
 
-        // Check region compatibility (simplified)
-        if self.config.region_based_matching {
-            let regions: std::collections::HashSet<&String> = current_players.iter().map(|p| &p.region).collect();
-            if !regions.contains(&new_player.region) && regions.len() >= 2 {
-                return false;
+            let players: Vec<QueuedPlayer> = group.iter().map(|&index| waiting[index].clone()).collect();
+            for &index in &group {
+                matched[index] = true;
             }
+            if self.config.enable_metrics {
+                self.metrics.record_match_created();
+                for player in &players {
+                    self.metrics.record_matchmaking_time(now.saturating_sub(player.queued_at) * 1000);
+                }
+            }
+            matches.push(self.create_match_from_players(&players, &queue.game_mode));
         }
 
-        true
+        queue.players = waiting
+            .into_iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(player, _)| player)
+            .collect();
+        matches
     }
 
     /// Create a match from queued players
@@ -447,8 +604,16 @@ impl MatchmakingSystem {
         GameMatch {
             match_id: Uuid::new_v4().to_string(),
             game_mode: game_mode.to_string(),
-            players: players.iter().map(|p| p.player_id.clone()).collect(),
-            max_players: players.len() as u32,
+            players: players
+                .iter()
+                .flat_map(|p| std::iter::once(&p.player_id).chain(&p.party_members).cloned())
+                .collect(),
+            max_players: players.iter().map(QueuedPlayer::size).sum::<usize>() as u32,
+            parties: players
+                .iter()
+                .filter(|p| !p.party_members.is_empty())
+                .map(|p| std::iter::once(&p.player_id).chain(&p.party_members).cloned().collect())
+                .collect(),
             status: MatchStatus::Scheduled,
             skill_range: self.calculate_skill_range(players),
             region: self.determine_match_region(players),
@@ -466,18 +631,23 @@ impl MatchmakingSystem {
         (min_skill, max_skill)
     }
 
-    /// Determine the best region for the match
+    /// Determine the region hosting the match: the most common one, ties go to the earliest
+    /// player (the group's anchor)
     fn determine_match_region(&self, players: &[QueuedPlayer]) -> String {
-        let mut region_count: HashMap<String, u32> = HashMap::new();
+        let mut region_count: Vec<(&str, u32)> = Vec::new();
 
         for player in players {
-            *region_count.entry(player.region.clone()).or_insert(0) += 1;
+            match region_count.iter_mut().find(|(region, _)| *region == player.region) {
+                Some((_, count)) => *count += 1,
+                None => region_count.push((&player.region, 1)),
+            }
         }
 
         region_count
             .into_iter()
+            .rev()
             .max_by_key(|(_, count)| *count)
-            .map(|(region, _)| region)
+            .map(|(region, _)| region.to_string())
             .unwrap_or_else(|| "unknown".to_string())
     }
 
@@ -534,6 +704,106 @@ impl MatchmakingSystem {
         Ok(())
     }
 
+    /// Glicko-2 update for every participant of a finished match. Each player is scored against
+    /// every other participant (Win > Draw > Loss) using the pre-match ratings; bots are skipped.
+    /// Returns the updated ratings, which are also persisted to PocketBase when configured.
+    pub async fn record_result(&self, match_id: &str, outcomes: Vec<(String, GameOutcome)>) -> Result<Vec<PlayerRating>, BoxError> {
+        let outcomes: Vec<(String, GameOutcome)> = outcomes
+            .into_iter()
+            .filter(|(player_id, _)| !player_id.starts_with(BOT_PLAYER_PREFIX))
+            .collect();
+        if outcomes.len() < 2 {
+            return Err(format!("Match {} needs at least two rated players", match_id).into());
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let updated = {
+            let mut ratings = self.player_ratings.write().await;
+            let before: Vec<PlayerRating> = outcomes
+                .iter()
+                .map(|(player_id, _)| {
+                    ratings.get(player_id).cloned().unwrap_or_else(|| Self::default_rating(player_id))
+                })
+                .collect();
+
+            let mut updated = Vec::with_capacity(outcomes.len());
+            for (index, (player_id, outcome)) in outcomes.iter().enumerate() {
+                let results: Vec<(&PlayerRating, f64)> = before
+                    .iter()
+                    .zip(&outcomes)
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .map(|(_, (opponent, (_, opponent_outcome)))| (opponent, outcome.score_against(opponent_outcome)))
+                    .collect();
+
+                let mut rating = before[index].clone();
+                glicko2_update(&mut rating, &results);
+                rating.games_played += 1;
+                rating.last_updated = now;
+                match outcome {
+                    GameOutcome::Win => {
+                        rating.wins += 1;
+                        rating.win_streak += 1;
+                        rating.best_streak = rating.best_streak.max(rating.win_streak);
+                    }
+                    GameOutcome::Loss => {
+                        rating.losses += 1;
+                        rating.win_streak = 0;
+                    }
+                    GameOutcome::Draw => {
+                        rating.draws += 1;
+                        rating.win_streak = 0;
+                    }
+                }
+                self.update_player_rank_and_tier(&mut rating);
+
+                ratings.insert(player_id.clone(), rating.clone());
+                updated.push(rating);
+            }
+            updated
+        };
+
+        if let Some(store) = &self.ratings_store {
+            for rating in &updated {
+                if let Err(e) = Self::persist_rating(store, rating).await {
+                    warn!("Failed to persist rating for {} after match {}: {}", rating.player_id, match_id, e);
+                }
+            }
+        }
+
+        debug!("Recorded result of match {} for {} players", match_id, updated.len());
+        Ok(updated)
+    }
+
+    /// Upsert: update the existing record, create it on first rated match
+    async fn persist_rating(store: &PocketBaseClient, rating: &PlayerRating) -> Result<(), BoxError> {
+        let mut data = serde_json::to_value(rating)?;
+        if store.update_record(PLAYER_RATINGS_COLLECTION, &rating.player_id, data.clone()).await.is_ok() {
+            return Ok(());
+        }
+        data["id"] = serde_json::json!(rating.player_id);
+        store.create_record(PLAYER_RATINGS_COLLECTION, data).await?;
+        Ok(())
+    }
+
+    fn default_rating(player_id: &str) -> PlayerRating {
+        PlayerRating {
+            player_id: player_id.to_string(),
+            skill_rating: 1200.0, // Default ELO rating
+            rating_deviation: 200.0,
+            volatility: 0.06,
+            games_played: 0,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            win_streak: 0,
+            best_streak: 0,
+            last_updated: chrono::Utc::now().timestamp() as u64,
+            rank: None,
+            tier: None,
+        }
+    }
+
     /// Calculate ELO rating change based on game result
     fn calculate_elo_change(&self, player_rating: &PlayerRating, game_result: &GameResult) -> f32 {
         let mut total_opponent_rating = 0.0;
@@ -592,35 +862,25 @@ impl MatchmakingSystem {
         player_rating.rank = Some(format!("{} {}", player_rating.tier.as_ref().unwrap(), player_rating.games_played));
     }
 
+    /// Put a rating loaded elsewhere (e.g. the caller's PocketBase `player_ratings` record) into the cache
+    /// used for tournament seeding and skill checks. A rating already in the cache is newer and is kept.
+    pub async fn cache_player_rating(&self, rating: PlayerRating) {
+        self.player_ratings.write().await.entry(rating.player_id.clone()).or_insert(rating);
+    }
+
     /// Get or create player rating
     async fn get_or_create_player_rating(&self, player_id: &str) -> PlayerRating {
         let ratings = self.player_ratings.read().await;
 
-        ratings.get(player_id).cloned().unwrap_or_else(|| {
-            PlayerRating {
-                player_id: player_id.to_string(),
-                skill_rating: 1200.0, // Default ELO rating
-                rating_deviation: 200.0,
-                volatility: 0.06,
-                games_played: 0,
-                wins: 0,
-                losses: 0,
-                draws: 0,
-                win_streak: 0,
-                best_streak: 0,
-                last_updated: chrono::Utc::now().timestamp() as u64,
-                rank: None,
-                tier: None,
-            }
-        })
+        ratings.get(player_id).cloned().unwrap_or_else(|| Self::default_rating(player_id))
     }
 
     /// Tournament Management
     pub async fn create_tournament(&self, tournament: Tournament) -> Result<(), BoxError> {
+        info!("Created tournament: {}", tournament.id);
+
         let mut tournaments = self.tournaments.write().await;
         tournaments.insert(tournament.id.clone(), tournament);
-
-        info!("Created tournament: {}", tournament.id);
         Ok(())
     }
 
@@ -669,12 +929,136 @@ impl MatchmakingSystem {
         }
     }
 
+    /// Seed a single-elimination bracket and generate round 1. Higher rating gets the better seed
+    /// (ties keep registration order); when the count is not a power of two the top seeds get byes.
+    pub async fn seed_bracket(&self, tournament_id: &str) -> Result<TournamentBracket, BoxError> {
+        let mut tournaments = self.tournaments.write().await;
+        let tournament = tournaments
+            .get_mut(tournament_id)
+            .ok_or_else(|| format!("Tournament {} not found", tournament_id))?;
+
+        if tournament.format != TournamentFormat::SingleElimination {
+            return Err(format!("Tournament {} is not single elimination", tournament_id).into());
+        }
+        if tournament.status != TournamentStatus::Registration {
+            return Err(format!("Tournament {} is already seeded", tournament_id).into());
+        }
+        if tournament.participants.len() < 2 {
+            return Err("Tournament needs at least 2 participants".into());
+        }
+
+        {
+            let ratings = self.player_ratings.read().await;
+            let rating_of = |player_id: &str| {
+                ratings
+                    .get(player_id)
+                    .map_or_else(|| Self::default_rating(player_id).skill_rating, |rating| rating.skill_rating)
+            };
+            tournament.participants.sort_by(|a, b| {
+                rating_of(&b.player_id)
+                    .total_cmp(&rating_of(&a.player_id))
+                    .then(a.seed.cmp(&b.seed))
+            });
+        }
+        for (index, participant) in tournament.participants.iter_mut().enumerate() {
+            participant.seed = index as u32 + 1;
+            participant.current_round = 1;
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let slots: Vec<Option<String>> = bracket_seed_order(tournament.participants.len().next_power_of_two())
+            .into_iter()
+            .map(|seed| tournament.participants.get(seed as usize - 1).map(|p| p.player_id.clone()))
+            .collect();
+        let matches = slots
+            .chunks(2)
+            .enumerate()
+            .map(|(index, pair)| bracket_match(tournament_id, 1, index, pair.iter().flatten().cloned().collect(), now))
+            .collect();
+
+        let bracket = TournamentBracket { round: 1, matches };
+        tournament.brackets = vec![bracket.clone()];
+        tournament.status = TournamentStatus::InProgress;
+
+        info!("Seeded tournament {} with {} participants", tournament_id, tournament.participants.len());
+        Ok(bracket)
+    }
+
+    /// Record the winner of a match in the current round. Once every match of the round is done
+    /// the next round is generated; returns the champion when the final is reported.
+    pub async fn report_match(&self, tournament_id: &str, match_id: &str, winner: &str) -> Result<Option<String>, BoxError> {
+        let mut tournaments = self.tournaments.write().await;
+        let tournament = tournaments
+            .get_mut(tournament_id)
+            .ok_or_else(|| format!("Tournament {} not found", tournament_id))?;
+
+        if tournament.status != TournamentStatus::InProgress {
+            return Err(format!("Tournament {} is not in progress", tournament_id).into());
+        }
+        let bracket = tournament.brackets.last_mut().ok_or("Tournament bracket is not seeded")?;
+        let round = bracket.round;
+        let game = bracket
+            .matches
+            .iter_mut()
+            .find(|game| game.match_id == match_id)
+            .ok_or_else(|| format!("Match {} is not in the current round", match_id))?;
+
+        if game.status == MatchStatus::Completed {
+            return Err(format!("Match {} is already reported", match_id).into());
+        }
+        if !game.players.iter().any(|player| player == winner) {
+            return Err(format!("Player {} is not in match {}", winner, match_id).into());
+        }
+        game.winner = Some(winner.to_string());
+        game.status = MatchStatus::Completed;
+        let loser = game.players.iter().find(|player| *player != winner).cloned();
+
+        let round_complete = bracket.matches.iter().all(|game| game.status == MatchStatus::Completed);
+        let winners: Vec<String> = bracket.matches.iter().filter_map(|game| game.winner.clone()).collect();
+
+        for participant in &mut tournament.participants {
+            if participant.player_id == winner {
+                participant.wins += 1;
+            } else if loser.as_deref() == Some(participant.player_id.as_str()) {
+                participant.losses += 1;
+            }
+        }
+
+        if !round_complete {
+            return Ok(None);
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        if let [champion] = winners.as_slice() {
+            tournament.status = TournamentStatus::Completed;
+            tournament.end_time = now;
+            info!("Tournament {} completed, champion {}", tournament_id, champion);
+            return Ok(Some(champion.clone()));
+        }
+
+        let next_round = round + 1;
+        for participant in &mut tournament.participants {
+            if winners.contains(&participant.player_id) {
+                participant.current_round = next_round;
+            }
+        }
+        let matches = winners
+            .chunks(2)
+            .enumerate()
+            .map(|(index, pair)| bracket_match(tournament_id, next_round, index, pair.to_vec(), now))
+            .collect();
+        tournament.brackets.push(TournamentBracket { round: next_round, matches });
+
+        debug!("Tournament {} advanced to round {}", tournament_id, next_round);
+        Ok(None)
+    }
+
     /// League Management
     pub async fn create_league(&self, league: League) -> Result<(), BoxError> {
+        info!("Created league: {}", league.id);
+
         let mut leagues = self.leagues.write().await;
         leagues.insert(league.id.clone(), league);
-
-        info!("Created league: {}", league.id);
         Ok(())
     }
 
@@ -720,9 +1104,13 @@ impl MatchmakingSystem {
 
                 if self.config.enable_metrics {
                     self.metrics.update_queue_size(queue.players.len() as u64);
-                    self.metrics.record_player_waiting(false);
                 }
             }
+
+            if self.config.enable_metrics {
+                let waiting: usize = queues.values().map(|queue| queue.players.len()).sum();
+                self.metrics.set_players_waiting(waiting as u64);
+            }
         }
 
         debug!("Cleaned up {} expired players from matchmaking queues", cleaned_count);
@@ -737,8 +1125,11 @@ pub struct GameMatch {
     pub game_mode: String,
     pub players: Vec<String>,
     pub max_players: u32,
+    /// Members of each queued party (leader first); each party belongs on one team
+    pub parties: Vec<Vec<String>>,
     pub status: MatchStatus,
     pub skill_range: (f32, f32),
+    /// Region the match is hosted in
     pub region: String,
     pub created_at: u64,
     pub scheduled_start: u64,
@@ -761,6 +1152,116 @@ pub enum GameOutcome {
     Draw,
 }
 
+impl GameOutcome {
+    fn rank(&self) -> u8 {
+        match self {
+            GameOutcome::Win => 2,
+            GameOutcome::Draw => 1,
+            GameOutcome::Loss => 0,
+        }
+    }
+
+    /// Glicko score of this outcome against another participant's outcome
+    fn score_against(&self, other: &GameOutcome) -> f64 {
+        match self.rank().cmp(&other.rank()) {
+            std::cmp::Ordering::Greater => 1.0,
+            std::cmp::Ordering::Equal => 0.5,
+            std::cmp::Ordering::Less => 0.0,
+        }
+    }
+}
+
+/// Seeds in bracket slot order so that seed 1 and 2 can only meet in the final:
+/// size 8 gives [1, 8, 4, 5, 2, 7, 3, 6]. Adjacent slots play each other in round 1.
+fn bracket_seed_order(size: usize) -> Vec<u32> {
+    let mut order = vec![1u32];
+    while order.len() < size {
+        let mirror = order.len() as u32 * 2 + 1;
+        order = order.iter().flat_map(|&seed| [seed, mirror - seed]).collect();
+    }
+    order
+}
+
+/// Match of a single-elimination round; a lone player is a bye and advances immediately
+fn bracket_match(tournament_id: &str, round: u32, index: usize, players: Vec<String>, scheduled_time: u64) -> TournamentMatch {
+    let bye = players.len() == 1;
+    TournamentMatch {
+        match_id: format!("{}-r{}-m{}", tournament_id, round, index + 1),
+        winner: if bye { players.first().cloned() } else { None },
+        players,
+        scores: HashMap::new(),
+        status: if bye { MatchStatus::Completed } else { MatchStatus::Scheduled },
+        scheduled_time,
+    }
+}
+
+/// One Glicko-2 rating period (Glickman, "Example of the Glicko-2 system") applied in place.
+/// `results` pairs each opponent's pre-match rating with the score against them.
+fn glicko2_update(rating: &mut PlayerRating, results: &[(&PlayerRating, f64)]) {
+    let to_mu = |r: f32| ((r - GLICKO_CENTER) / GLICKO_SCALE) as f64;
+    let to_phi = |rd: f32| (rd / GLICKO_SCALE) as f64;
+    let g = |phi: f64| 1.0 / (1.0 + 3.0 * phi * phi / (std::f64::consts::PI * std::f64::consts::PI)).sqrt();
+
+    let mu = to_mu(rating.skill_rating);
+    let phi = to_phi(rating.rating_deviation);
+    let sigma = rating.volatility as f64;
+
+    let (mut inv_v, mut improvement) = (0.0, 0.0);
+    for (opponent, score) in results {
+        let g_j = g(to_phi(opponent.rating_deviation));
+        let expected = 1.0 / (1.0 + (-g_j * (mu - to_mu(opponent.skill_rating))).exp());
+        inv_v += g_j * g_j * expected * (1.0 - expected);
+        improvement += g_j * (score - expected);
+    }
+    if inv_v <= 0.0 {
+        return;
+    }
+    let v = 1.0 / inv_v;
+    let delta = v * improvement;
+
+    // Volatility: tìm nghiệm của f bằng Illinois algorithm
+    let a = (sigma * sigma).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        ex * (delta * delta - phi * phi - v - ex) / (2.0 * (phi * phi + v + ex).powi(2)) - (x - a) / (GLICKO_TAU * GLICKO_TAU)
+    };
+    let mut lower = a;
+    let mut upper = if delta * delta > phi * phi + v {
+        (delta * delta - phi * phi - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * GLICKO_TAU) < 0.0 && k < 100.0 {
+            k += 1.0;
+        }
+        a - k * GLICKO_TAU
+    };
+    let (mut f_lower, mut f_upper) = (f(lower), f(upper));
+    for _ in 0..100 {
+        if (upper - lower).abs() <= GLICKO_EPSILON {
+            break;
+        }
+        let next = lower + (lower - upper) * f_lower / (f_upper - f_lower);
+        let f_next = f(next);
+        if f_next * f_upper <= 0.0 {
+            lower = upper;
+            f_lower = f_upper;
+        } else {
+            f_lower /= 2.0;
+        }
+        upper = next;
+        f_upper = f_next;
+    }
+    let new_sigma = (lower / 2.0).exp();
+
+    let phi_star = (phi * phi + new_sigma * new_sigma).sqrt();
+    let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+    let new_mu = mu + new_phi * new_phi * improvement;
+
+    rating.skill_rating = new_mu as f32 * GLICKO_SCALE + GLICKO_CENTER;
+    rating.rating_deviation = new_phi as f32 * GLICKO_SCALE;
+    rating.volatility = new_sigma as f32;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -795,13 +1296,13 @@ mod tests {
     async fn test_match_creation() {
         let config = MatchmakingConfig {
             min_players_per_match: 2,
-            max_players_per_match: 4,
+            max_players_per_match: 2,
             max_wait_time: 60,
             ..Default::default()
         };
         let system = MatchmakingSystem::new(config);
 
-        // Queue minimum players for a match
+        // Queue enough players for a full match
         system.queue_player("player1", "deathmatch", "us-east").await.unwrap();
         system.queue_player("player2", "deathmatch", "us-east").await.unwrap();
 
@@ -813,6 +1314,197 @@ mod tests {
         println!("✅ Match creation test completed");
     }
 
+    fn queued(player_id: &str, skill_rating: f32, queued_at: u64) -> QueuedPlayer {
+        queued_in(player_id, "us-east", skill_rating, queued_at)
+    }
+
+    fn queued_in(player_id: &str, region: &str, skill_rating: f32, queued_at: u64) -> QueuedPlayer {
+        QueuedPlayer {
+            player_id: player_id.to_string(),
+            skill_rating,
+            queued_at,
+            region: region.to_string(),
+            preferred_latency: 50,
+            priority: 0,
+            party_members: Vec::new(),
+        }
+    }
+
+    fn tick_config() -> MatchmakingConfig {
+        MatchmakingConfig {
+            max_wait_time: 60,
+            max_skill_diff: 100.0,
+            skill_diff_relaxation: 800.0,
+            min_players_per_match: 2,
+            max_players_per_match: 4,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn tick_forms_full_match_of_close_skills() {
+        let system = MatchmakingSystem::new(tick_config());
+        let now = 1_000_000;
+        for (i, skill) in [1200.0, 1250.0, 1230.0, 1290.0].into_iter().enumerate() {
+            system.enqueue("deathmatch", queued(&format!("p{i}"), skill, now)).await;
+        }
+        // Lệch quá xa, phải chờ
+        system.enqueue("deathmatch", queued("outlier", 1900.0, now)).await;
+
+        let matches = system.tick_at(now).await;
+        assert_eq!(matches.len(), 1);
+        let mut players = matches[0].players.clone();
+        players.sort();
+        assert_eq!(players, vec!["p0", "p1", "p2", "p3"]);
+        assert_eq!(matches[0].skill_range, (1200.0, 1290.0));
+
+        assert_eq!(system.get_queue_sizes().await.get("deathmatch"), Some(&1));
+        let (created, _, _, waiting, _, _, _) = system.get_metrics().get_stats();
+        assert_eq!((created, waiting), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn tick_relaxes_skill_gap_as_players_wait() {
+        let system = MatchmakingSystem::new(MatchmakingConfig { max_players_per_match: 2, ..tick_config() });
+        let queued_at = 1_000_000;
+        system.enqueue("deathmatch", queued("low", 1000.0, queued_at)).await;
+        system.enqueue("deathmatch", queued("high", 1500.0, queued_at)).await;
+
+        // Gap 500 > 100 lúc mới vào queue
+        assert!(system.tick_at(queued_at).await.is_empty());
+        // Sau 45s: 100 + 800 * 0.75 = 700 >= 500
+        let matches = system.tick_at(queued_at + 45).await;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].players.len(), 2);
+
+        let (created, avg_time, _, waiting, _, _, _) = system.get_metrics().get_stats();
+        assert_eq!((created, waiting), (1, 0));
+        assert!(avg_time > 0);
+    }
+
+    #[tokio::test]
+    async fn tick_matches_same_region_first_and_crosses_after_wait() {
+        let system = MatchmakingSystem::new(MatchmakingConfig {
+            max_players_per_match: 2,
+            cross_region_wait: 30,
+            ..tick_config()
+        });
+        let t = 1_000_000;
+        system.enqueue("deathmatch", queued_in("us-1", "us-east", 1200.0, t)).await;
+        system.enqueue("deathmatch", queued_in("eu-1", "eu-west", 1200.0, t + 1)).await;
+        system.enqueue("deathmatch", queued_in("us-2", "us-east", 1200.0, t + 2)).await;
+        system.enqueue("deathmatch", queued_in("eu-2", "eu-west", 1200.0, t + 3)).await;
+
+        // Có đủ người cùng region thì không ghép chéo dù us-1 xếp ngay cạnh eu-1
+        let mut matches: Vec<(String, Vec<String>)> = system
+            .tick_at(t + 3)
+            .await
+            .into_iter()
+            .map(|game_match| (game_match.region, game_match.players))
+            .collect();
+        matches.sort();
+        assert_eq!(
+            matches,
+            vec![
+                ("eu-west".to_string(), vec!["eu-1".to_string(), "eu-2".to_string()]),
+                ("us-east".to_string(), vec!["us-1".to_string(), "us-2".to_string()]),
+            ]
+        );
+
+        // Mỗi region chỉ còn một người: chờ tới khi cả hai quá cross_region_wait
+        let t = t + 100;
+        system.enqueue("deathmatch", queued_in("us-3", "us-east", 1200.0, t)).await;
+        system.enqueue("deathmatch", queued_in("eu-3", "eu-west", 1200.0, t + 5)).await;
+        assert!(system.tick_at(t + 5).await.is_empty());
+        assert!(system.tick_at(t + 30).await.is_empty(), "eu-3 has only waited 25s");
+        let matches = system.tick_at(t + 35).await;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].players, vec!["us-3", "eu-3"]);
+        assert_eq!(matches[0].region, "us-east");
+
+        // Cả hai chịu được latency cao thì ghép chéo ngay
+        let t = t + 100;
+        let tolerant = |player_id: &str, region: &str, queued_at: u64| QueuedPlayer {
+            preferred_latency: 150,
+            ..queued_in(player_id, region, 1200.0, queued_at)
+        };
+        system.enqueue("deathmatch", tolerant("eu-4", "eu-west", t)).await;
+        system.enqueue("deathmatch", tolerant("us-4", "us-east", t + 1)).await;
+        let matches = system.tick_at(t + 1).await;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].players, vec!["eu-4", "us-4"]);
+        assert_eq!(matches[0].region, "eu-west");
+    }
+
+    #[tokio::test]
+    async fn party_is_matched_whole_or_not_at_all() {
+        let system = MatchmakingSystem::new(tick_config());
+        let now = 1_000_000;
+        let party = |leader: &str, members: &[&str]| QueuedPlayer {
+            party_members: members.iter().map(|m| m.to_string()).collect(),
+            ..queued(leader, 1200.0, now)
+        };
+        // solo-1 neo nhóm, party 3 người vừa đủ 4 slot; party 2 người phải chờ trận sau
+        system.enqueue("deathmatch", queued("solo-1", 1200.0, now)).await;
+        system.enqueue("deathmatch", party("duo-lead", &["duo-2"])).await;
+        system.enqueue("deathmatch", party("trio-lead", &["trio-2", "trio-3"])).await;
+
+        let matches = system.tick_at(now).await;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].players, vec!["solo-1", "trio-lead", "trio-2", "trio-3"]);
+        assert_eq!(matches[0].parties, vec![vec!["trio-lead", "trio-2", "trio-3"]]);
+        assert_eq!(matches[0].max_players, 4);
+        assert_eq!(system.get_queue_sizes().await.get("deathmatch"), Some(&1));
+
+        // Hết giờ chờ thì party 2 người thành trận tối thiểu, vẫn đi cùng nhau
+        let matches = system.tick_at(now + 60).await;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].players, vec!["duo-lead", "duo-2"]);
+    }
+
+    #[test]
+    fn party_skill_penalizes_spread() {
+        let system = MatchmakingSystem::new(MatchmakingConfig { party_skill_spread_penalty: 0.5, ..Default::default() });
+        assert_eq!(system.party_skill(&[1200.0, 1200.0, 1200.0]), 1200.0);
+        // Trung bình 1400, chênh 600 -> +300
+        assert_eq!(system.party_skill(&[1100.0, 1400.0, 1700.0]), 1700.0);
+    }
+
+    #[tokio::test]
+    async fn region_based_matching_off_ignores_region() {
+        let system = MatchmakingSystem::new(MatchmakingConfig {
+            max_players_per_match: 2,
+            region_based_matching: false,
+            ..tick_config()
+        });
+        let t = 1_000_000;
+        system.enqueue("deathmatch", queued_in("us-1", "us-east", 1200.0, t)).await;
+        system.enqueue("deathmatch", queued_in("eu-1", "eu-west", 1200.0, t)).await;
+        assert_eq!(system.tick_at(t).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn tick_waits_when_not_enough_players() {
+        let system = MatchmakingSystem::new(tick_config());
+        let queued_at = 1_000_000;
+        system.enqueue("deathmatch", queued("solo", 1200.0, queued_at)).await;
+
+        // Dưới min_players_per_match thì timeout cũng không ghép
+        assert!(system.tick_at(queued_at + 120).await.is_empty());
+        assert_eq!(system.get_queue_sizes().await.get("deathmatch"), Some(&1));
+
+        // 3/4 player: chờ đủ trận cho tới khi player lâu nhất quá max_wait_time
+        system.enqueue("deathmatch", queued("late-1", 1210.0, queued_at + 20)).await;
+        system.enqueue("deathmatch", queued("late-2", 1220.0, queued_at + 20)).await;
+        assert!(system.tick_at(queued_at + 30).await.is_empty());
+        let matches = system.tick_at(queued_at + 120).await;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].players.len(), 3);
+
+        let (created, _, _, waiting, _, _, _) = system.get_metrics().get_stats();
+        assert_eq!((created, waiting), (1, 0));
+    }
+
     #[tokio::test]
     async fn test_elo_rating_system() {
         let config = MatchmakingConfig::default();
@@ -837,6 +1529,135 @@ mod tests {
         println!("✅ ELO rating system test completed");
     }
 
+    async fn seed_rating(system: &MatchmakingSystem, player_id: &str, skill_rating: f32) {
+        let mut rating = MatchmakingSystem::default_rating(player_id);
+        rating.skill_rating = skill_rating;
+        system.player_ratings.write().await.insert(player_id.to_string(), rating);
+    }
+
+    fn rating_of<'a>(ratings: &'a [PlayerRating], player_id: &str) -> &'a PlayerRating {
+        ratings.iter().find(|rating| rating.player_id == player_id).unwrap()
+    }
+
+    #[test]
+    fn glicko2_matches_reference_example() {
+        let rated = |skill_rating: f32, rating_deviation: f32| PlayerRating {
+            skill_rating,
+            rating_deviation,
+            ..MatchmakingSystem::default_rating("p")
+        };
+        let mut player = rated(1500.0, 200.0);
+        let opponents = [rated(1400.0, 30.0), rated(1550.0, 100.0), rated(1700.0, 300.0)];
+        glicko2_update(&mut player, &[(&opponents[0], 1.0), (&opponents[1], 0.0), (&opponents[2], 0.0)]);
+
+        assert!((player.skill_rating - 1464.06).abs() < 0.1, "{}", player.skill_rating);
+        assert!((player.rating_deviation - 151.52).abs() < 0.1, "{}", player.rating_deviation);
+        assert!((player.volatility - 0.05999).abs() < 0.0001, "{}", player.volatility);
+    }
+
+    #[tokio::test]
+    async fn glicko_win_raises_rating_and_lowers_deviation() {
+        let system = MatchmakingSystem::new(MatchmakingConfig::default());
+        let ratings = system
+            .record_result(
+                "match-1",
+                vec![("winner".to_string(), GameOutcome::Win), ("loser".to_string(), GameOutcome::Loss)],
+            )
+            .await
+            .unwrap();
+
+        let winner = rating_of(&ratings, "winner");
+        assert!(winner.skill_rating > 1200.0);
+        assert!(winner.rating_deviation < 200.0);
+        assert_eq!((winner.wins, winner.win_streak, winner.best_streak, winner.games_played), (1, 1, 1, 1));
+
+        let loser = system.get_player_rating("loser").await.unwrap();
+        assert!(loser.skill_rating < 1200.0);
+        assert_eq!((loser.losses, loser.win_streak), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn glicko_upset_moves_ratings_more_than_expected_win() {
+        let system = MatchmakingSystem::new(MatchmakingConfig::default());
+        seed_rating(&system, "underdog", 1200.0).await;
+        seed_rating(&system, "favourite", 1800.0).await;
+        seed_rating(&system, "strong", 1800.0).await;
+        seed_rating(&system, "weak", 1200.0).await;
+
+        let upset = system
+            .record_result(
+                "upset",
+                vec![("underdog".to_string(), GameOutcome::Win), ("favourite".to_string(), GameOutcome::Loss)],
+            )
+            .await
+            .unwrap();
+        let expected = system
+            .record_result(
+                "expected",
+                vec![("strong".to_string(), GameOutcome::Win), ("weak".to_string(), GameOutcome::Loss)],
+            )
+            .await
+            .unwrap();
+
+        let upset_gain = rating_of(&upset, "underdog").skill_rating - 1200.0;
+        let expected_gain = rating_of(&expected, "strong").skill_rating - 1800.0;
+        assert!(upset_gain > expected_gain * 2.0, "upset {upset_gain} vs expected {expected_gain}");
+        assert!(1800.0 - rating_of(&upset, "favourite").skill_rating > 1200.0 - rating_of(&expected, "weak").skill_rating);
+    }
+
+    #[tokio::test]
+    async fn recorded_ratings_are_persisted_to_pocketbase() {
+        use axum::{extract::State, http::StatusCode, routing::{patch, post}, Json, Router};
+
+        type Saved = Arc<std::sync::Mutex<Vec<serde_json::Value>>>;
+        async fn create(State(saved): State<Saved>, Json(body): Json<serde_json::Value>) -> Json<serde_json::Value> {
+            saved.lock().unwrap().push(body.clone());
+            let mut record = body;
+            record["created"] = serde_json::json!("");
+            record["updated"] = serde_json::json!("");
+            Json(record)
+        }
+
+        let saved = Saved::default();
+        let app = Router::new()
+            .route("/api/collections/player_ratings/records", post(create))
+            .route("/api/collections/player_ratings/records/:id", patch(|| async { StatusCode::NOT_FOUND }))
+            .with_state(saved.clone());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+
+        let system = MatchmakingSystem::new(MatchmakingConfig::default())
+            .with_pocketbase(PocketBaseClient::new(&format!("http://{addr}")));
+        system
+            .record_result("persisted", vec![("a".to_string(), GameOutcome::Win), ("b".to_string(), GameOutcome::Loss)])
+            .await
+            .unwrap();
+
+        let saved = saved.lock().unwrap();
+        let ids: Vec<&str> = saved.iter().map(|record| record["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert_eq!(saved[0]["wins"], 1);
+    }
+
+    #[tokio::test]
+    async fn glicko_ignores_bots() {
+        let system = MatchmakingSystem::new(MatchmakingConfig::default());
+        let ratings = system
+            .record_result(
+                "with-bots",
+                vec![
+                    ("human-1".to_string(), GameOutcome::Win),
+                    (format!("{BOT_PLAYER_PREFIX}1"), GameOutcome::Loss),
+                    ("human-2".to_string(), GameOutcome::Loss),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(ratings.len(), 2);
+        assert!(system.get_player_rating(&format!("{BOT_PLAYER_PREFIX}1")).await.is_none());
+    }
+
     #[tokio::test]
     async fn test_tournament_creation() {
         let config = MatchmakingConfig::default();
@@ -873,13 +1694,149 @@ mod tests {
         println!("✅ Tournament creation test completed");
     }
 
+    async fn bracket_tournament(system: &MatchmakingSystem, players: usize) -> String {
+        let tournament_id = format!("bracket{players}");
+        system
+            .create_tournament(Tournament {
+                id: tournament_id.clone(),
+                name: "Bracket".to_string(),
+                game_mode: "deathmatch".to_string(),
+                format: TournamentFormat::SingleElimination,
+                max_participants: 16,
+                current_participants: 0,
+                status: TournamentStatus::Registration,
+                start_time: 0,
+                end_time: 0,
+                prize_pool: vec![],
+                brackets: vec![],
+                participants: vec![],
+                rules: TournamentRules {
+                    max_round_time: 600,
+                    allow_rematches: false,
+                    skill_range: (0.0, 3000.0),
+                    region_restriction: None,
+                },
+                created_at: 0,
+            })
+            .await
+            .unwrap();
+        for i in 1..=players {
+            system
+                .register_player_for_tournament(&tournament_id, &format!("p{i}"), &format!("P{i}"))
+                .await
+                .unwrap();
+        }
+        tournament_id
+    }
+
+    fn pairings(bracket: &TournamentBracket) -> Vec<Vec<&str>> {
+        bracket
+            .matches
+            .iter()
+            .map(|game| game.players.iter().map(String::as_str).collect())
+            .collect()
+    }
+
+    /// Báo cáo mọi trận đang chờ của round hiện tại, người có seed tốt hơn thắng
+    async fn play_round_by_seed(system: &MatchmakingSystem, tournament_id: &str) -> Option<String> {
+        let tournament = system.get_tournament(tournament_id).await.unwrap();
+        let seed_of = |player: &String| {
+            tournament.participants.iter().find(|p| &p.player_id == player).unwrap().seed
+        };
+        let mut champion = None;
+        for game in &tournament.brackets.last().unwrap().matches {
+            if game.status == MatchStatus::Completed {
+                continue;
+            }
+            let winner = game.players.iter().min_by_key(|player| seed_of(player)).unwrap();
+            champion = system.report_match(tournament_id, &game.match_id, winner).await.unwrap();
+        }
+        champion
+    }
+
+    #[tokio::test]
+    async fn four_player_bracket_seeds_by_rating_and_crowns_champion() {
+        let system = MatchmakingSystem::new(MatchmakingConfig::default());
+        let tournament_id = bracket_tournament(&system, 4).await;
+        let mut strong = MatchmakingSystem::default_rating("p4");
+        strong.skill_rating = 2000.0;
+        system.player_ratings.write().await.insert("p4".to_string(), strong);
+
+        let round1 = system.seed_bracket(&tournament_id).await.unwrap();
+        assert_eq!(pairings(&round1), vec![vec!["p4", "p3"], vec!["p1", "p2"]]);
+        assert!(system.seed_bracket(&tournament_id).await.is_err());
+
+        // Upset ở trận 2: p2 thắng p1
+        assert_eq!(system.report_match(&tournament_id, &round1.matches[0].match_id, "p4").await.unwrap(), None);
+        assert!(system.report_match(&tournament_id, &round1.matches[1].match_id, "p3").await.is_err());
+        assert_eq!(system.report_match(&tournament_id, &round1.matches[1].match_id, "p2").await.unwrap(), None);
+        assert!(system.report_match(&tournament_id, &round1.matches[1].match_id, "p2").await.is_err());
+
+        let tournament = system.get_tournament(&tournament_id).await.unwrap();
+        assert_eq!(tournament.brackets.len(), 2);
+        let final_match = &tournament.brackets[1].matches[0];
+        assert_eq!(pairings(&tournament.brackets[1]), vec![vec!["p4", "p2"]]);
+
+        let champion = system.report_match(&tournament_id, &final_match.match_id, "p2").await.unwrap();
+        assert_eq!(champion.as_deref(), Some("p2"));
+        let tournament = system.get_tournament(&tournament_id).await.unwrap();
+        assert_eq!(tournament.status, TournamentStatus::Completed);
+        let p2 = tournament.participants.iter().find(|p| p.player_id == "p2").unwrap();
+        assert_eq!((p2.wins, p2.losses, p2.current_round), (2, 0, 2));
+    }
+
+    #[tokio::test]
+    async fn five_player_bracket_gives_top_seeds_byes() {
+        let system = MatchmakingSystem::new(MatchmakingConfig::default());
+        let tournament_id = bracket_tournament(&system, 5).await;
+
+        let round1 = system.seed_bracket(&tournament_id).await.unwrap();
+        assert_eq!(pairings(&round1), vec![vec!["p1"], vec!["p4", "p5"], vec!["p2"], vec!["p3"]]);
+        let byes: Vec<_> = round1.matches.iter().filter(|game| game.status == MatchStatus::Completed).collect();
+        assert_eq!(byes.len(), 3);
+        assert!(byes.iter().all(|game| game.winner.as_ref() == game.players.first()));
+
+        system.report_match(&tournament_id, &round1.matches[1].match_id, "p5").await.unwrap();
+        let tournament = system.get_tournament(&tournament_id).await.unwrap();
+        assert_eq!(pairings(&tournament.brackets[1]), vec![vec!["p1", "p5"], vec!["p2", "p3"]]);
+
+        assert_eq!(play_round_by_seed(&system, &tournament_id).await, None);
+        assert_eq!(play_round_by_seed(&system, &tournament_id).await.as_deref(), Some("p1"));
+        let tournament = system.get_tournament(&tournament_id).await.unwrap();
+        assert_eq!(tournament.brackets.len(), 3);
+        assert_eq!(tournament.status, TournamentStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn eight_player_bracket_runs_three_rounds() {
+        let system = MatchmakingSystem::new(MatchmakingConfig::default());
+        let tournament_id = bracket_tournament(&system, 8).await;
+
+        let round1 = system.seed_bracket(&tournament_id).await.unwrap();
+        assert_eq!(
+            pairings(&round1),
+            vec![vec!["p1", "p8"], vec!["p4", "p5"], vec!["p2", "p7"], vec!["p3", "p6"]]
+        );
+        assert!(round1.matches.iter().all(|game| game.status == MatchStatus::Scheduled));
+
+        assert_eq!(play_round_by_seed(&system, &tournament_id).await, None);
+        let tournament = system.get_tournament(&tournament_id).await.unwrap();
+        assert_eq!(pairings(&tournament.brackets[1]), vec![vec!["p1", "p4"], vec!["p2", "p3"]]);
+
+        assert_eq!(play_round_by_seed(&system, &tournament_id).await, None);
+        assert_eq!(play_round_by_seed(&system, &tournament_id).await.as_deref(), Some("p1"));
+        let tournament = system.get_tournament(&tournament_id).await.unwrap();
+        assert_eq!(tournament.status, TournamentStatus::Completed);
+        assert!(system.report_match(&tournament_id, "bracket8-r3-m1", "p1").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_performance_metrics() {
         let config = MatchmakingConfig::default();
         let system = MatchmakingSystem::new(config);
 
         let metrics = system.get_metrics();
-        let (matches, avg_time, queued, waiting, per_minute, avg_queue, longest) = metrics.get_stats();
+        let (matches, _, queued, _, _, _, _) = metrics.get_stats();
 
         assert_eq!(matches, 0);
         assert_eq!(queued, 0);
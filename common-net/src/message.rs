@@ -1,5 +1,10 @@
 use serde::{Deserialize, Serialize};
 
+use crate::transport::TransportKind;
+
+/// Phiên bản wire format của `Frame`; tăng khi client cũ không còn đọc được frame mới
+pub const PROTOCOL_VERSION: u32 = 1;
+
 /// Logical channel for the transport pipeline.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -12,7 +17,15 @@ pub enum Channel {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Frame {
     pub channel: Channel,
+    /// Tăng dần theo từng connection, mỗi chiều đánh số riêng
     pub sequence: u32,
+    /// Sequence cao nhất bên gửi đã nhận từ phía bên kia (0 = chưa nhận gì / client cũ)
+    #[serde(default)]
+    pub ack: u32,
+    /// Sequence của từng control frame cần ack (`needs_ack`) nhận được từ lần gửi trước. Ack riêng từng
+    /// frame chứ không cumulative như `ack`, vì sequence đánh chung với state frame có thể mất
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub acks: Vec<u32>,
     pub timestamp_ms: u64,
     #[serde(flatten)]
     pub payload: FramePayload,
@@ -23,6 +36,8 @@ impl Frame {
         Self {
             channel: Channel::Control,
             sequence,
+            ack: 0,
+            acks: Vec::new(),
             timestamp_ms,
             payload: FramePayload::Control { message },
         }
@@ -32,10 +47,23 @@ impl Frame {
         Self {
             channel: Channel::State,
             sequence,
+            ack: 0,
+            acks: Vec::new(),
             timestamp_ms,
             payload: FramePayload::State { message },
         }
     }
+
+    /// Control frame cần ack và được gửi lại khi mất. State frame, Ping/Pong và Ack thì không:
+    /// frame mới hơn thay thế được, còn Ping gửi lại sẽ làm sai RTT.
+    pub fn needs_ack(&self) -> bool {
+        match &self.payload {
+            FramePayload::Control { message } => {
+                !matches!(message, ControlMessage::Ping { .. } | ControlMessage::Pong { .. } | ControlMessage::Ack)
+            }
+            FramePayload::State { .. } => false,
+        }
+    }
 }
 
 /// Payload distinguishing control/state channels.
@@ -50,12 +78,22 @@ pub enum FramePayload {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ControlMessage {
+    /// Frame đầu tiên client gửi sau khi mở connection; gateway chỉ chạy tiếp khi khớp `PROTOCOL_VERSION`
+    Hello {
+        protocol_version: u32,
+    },
+    /// Gateway trả khi thiếu Hello hoặc lệch version, ngay sau đó connection bị đóng
+    VersionMismatch {
+        server_version: u32,
+    },
     Ping {
         nonce: u64,
     },
     Pong {
         nonce: u64,
     },
+    /// Chỉ để mang `acks` khi không có frame nào khác sắp gửi
+    Ack,
     JoinRoom {
         room_id: String,
         reconnect_token: Option<String>,
@@ -92,6 +130,36 @@ pub enum ControlMessage {
         sdp_mid: String,
         sdp_mline_index: u32,
     },
+    /// Server báo lỗi cho client (message không hợp lệ, ...)
+    Error {
+        code: String,
+        message: String,
+    },
+    /// Client lỡ mất delta, xin một Full snapshot để resync
+    RequestKeyframe {
+        room_id: String,
+        player_id: String,
+    },
+    /// Server báo client bị kick/ban khỏi phòng, ngay sau đó connection bị đóng
+    Kicked {
+        reason: String,
+    },
+    /// Frame đầu tiên server gửi sau khi connection lập xong: transport nào được chọn và vì sao
+    TransportSelected {
+        kind: TransportKind,
+        fallback_used: bool,
+        reason: String,
+    },
+    /// Gateway cấp sau khi join: client dùng cặp này để chuyển sang connection khác mà không rời room
+    MigrationToken {
+        connection_id: String,
+        auth_nonce: String,
+    },
+    /// Gửi trên connection mới: chuyển binding peer/room (và frame đang chờ gửi) từ connection cũ sang
+    MigrateTransport {
+        from_connection_id: String,
+        auth_nonce: String,
+    },
 }
 
 /// State plane messages (snapshot, delta, event...).
@@ -101,10 +169,22 @@ pub enum StateMessage {
     Snapshot {
         tick: u64,
         entities: Vec<EntitySnapshot>,
+        /// Độ dài tick của worker (ms) để client nội suy; giờ server nằm ở `timestamp_ms` của frame
+        #[serde(default)]
+        tick_duration_ms: f32,
+        /// Sequence input mới nhất của player đã được worker xử lý
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        last_acked_sequence: Option<u32>,
     },
     Delta {
         tick: u64,
         changes: Vec<EntityDelta>,
+        /// Độ dài tick của worker (ms) để client nội suy; giờ server nằm ở `timestamp_ms` của frame
+        #[serde(default)]
+        tick_duration_ms: f32,
+        /// Sequence input mới nhất của player đã được worker xử lý
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        last_acked_sequence: Option<u32>,
     },
     Event {
         name: String,
@@ -154,5 +234,15 @@ mod tests {
 
         assert_eq!(decoded.channel, Channel::Control);
         assert_eq!(decoded.sequence, 42);
+        assert!(decoded.needs_ack());
+    }
+
+    #[test]
+    fn frame_without_ack_decodes_as_zero() {
+        let decoded = decode(br#"{"channel":"control","sequence":3,"timestamp_ms":1,"kind":"control","message":{"type":"ping","nonce":1}}"#)
+            .expect("decode");
+        assert_eq!(decoded.ack, 0);
+        assert!(decoded.acks.is_empty());
+        assert!(!decoded.needs_ack());
     }
 }
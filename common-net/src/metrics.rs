@@ -9,24 +9,49 @@ use axum::{
 };
 use once_cell::sync::OnceCell;
 use prometheus::{
-    register_histogram, register_int_counter, register_int_gauge, Encoder, Histogram, IntCounter,
-    IntGauge, TextEncoder,
+    register_gauge, register_histogram, register_int_counter, register_int_counter_vec, register_int_gauge, Encoder,
+    Gauge, Histogram, IntCounter, IntCounterVec, IntGauge, TextEncoder,
 };
 use tokio::net::TcpListener;
 use tracing::error;
 
+use crate::health::Readiness;
+
 pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
 /// Metric set cho worker mo phong gameplay.
 pub struct SimulationMetrics {
     pub ticks_total: IntCounter,
     pub active_players: IntGauge,
+    pub inputs_dropped_total: IntCounterVec,
+    /// Label `source`: "reused" (body lấy từ pool) hoặc "allocated"; reuse rate = reused / tổng
+    pub body_spawns_total: IntCounterVec,
+    /// 0 = bình thường, càng cao càng nhiều phần mô phỏng bị cắt giảm vì tick vượt budget
+    pub degradation_level: IntGauge,
+    /// Thời gian mô phỏng còn nợ (accumulator chưa chạy hết), tăng dần nghĩa là worker đang tụt nhịp
+    pub accumulated_lag_seconds: Gauge,
+    /// Kích thước JSON của mỗi delta encoder tính ra (kể cả delta bị thay bằng Full)
+    pub delta_bytes: Histogram,
+    /// Kích thước JSON của Full snapshot ở mỗi lần encode, dù có gửi Full hay không
+    pub full_bytes: Histogram,
+    /// delta_bytes / full_bytes của từng lần encode có tính delta
+    pub delta_ratio: Histogram,
+    /// Label `reason`: vì sao encoder gửi Full thay vì delta
+    pub full_forced_total: IntCounterVec,
 }
 
 impl SimulationMetrics {
     pub fn on_startup(&self) {
         self.ticks_total.inc_by(0);
         self.active_players.set(0);
+        for source in ["reused", "allocated"] {
+            self.body_spawns_total.with_label_values(&[source]).inc_by(0);
+        }
+        self.degradation_level.set(0);
+        self.accumulated_lag_seconds.set(0.0);
+        for reason in FULL_SNAPSHOT_REASONS {
+            self.full_forced_total.with_label_values(&[reason]).inc_by(0);
+        }
     }
 
     pub fn inc_ticks(&self, delta: u64) {
@@ -36,20 +61,68 @@ impl SimulationMetrics {
     pub fn set_active_players(&self, players: i64) {
         self.active_players.set(players);
     }
+
+    pub fn inc_inputs_dropped(&self, player_id: &str) {
+        self.inputs_dropped_total.with_label_values(&[player_id]).inc();
+    }
+
+    pub fn inc_body_spawns(&self, reused: bool) {
+        let source = if reused { "reused" } else { "allocated" };
+        self.body_spawns_total.with_label_values(&[source]).inc();
+    }
+
+    pub fn set_degradation_level(&self, level: i64) {
+        self.degradation_level.set(level);
+    }
+
+    pub fn set_accumulated_lag(&self, lag: std::time::Duration) {
+        self.accumulated_lag_seconds.set(lag.as_secs_f64());
+    }
+
+    /// Một lần encode snapshot: `delta_bytes` None khi encoder không tính delta (snapshot đầu, đến hạn keyframe...)
+    pub fn observe_snapshot_encoding(&self, full_bytes: usize, delta_bytes: Option<usize>, forced_full: Option<&str>) {
+        self.full_bytes.observe(full_bytes as f64);
+        if let Some(delta_bytes) = delta_bytes {
+            self.delta_bytes.observe(delta_bytes as f64);
+            if full_bytes > 0 {
+                self.delta_ratio.observe(delta_bytes as f64 / full_bytes as f64);
+            }
+        }
+        if let Some(reason) = forced_full {
+            self.full_forced_total.with_label_values(&[reason]).inc();
+        }
+    }
 }
 
+/// Giá trị label `reason` của `worker_snapshot_full_forced_total`
+pub const FULL_SNAPSHOT_REASONS: [&str; 6] =
+    ["first", "keyframe_interval", "quantization_changed", "too_many_changes", "below_threshold", "requested"];
+
 /// Metric set cho room-manager/matchmaking.
 pub struct MatchmakingMetrics {
     pub rooms_created_total: IntCounter,
     pub active_rooms: IntGauge,
+    pub players_in_rooms: IntGauge,
+    /// Player trong phòng Waiting, đang chờ trận bắt đầu
+    pub waiting_players: IntGauge,
     pub matchmaking_queue_depth: IntGauge,
+    pub reconciliation_fixed_total: IntCounter,
+    pub tournaments_created_total: IntCounter,
+    pub tournaments_completed_total: IntCounter,
+    /// Thời gian xếp một player (hoặc party) vào phòng, tính cả lúc chờ lock state
+    pub assign_room_duration_ms: Histogram,
 }
 
 impl MatchmakingMetrics {
     pub fn on_startup(&self) {
         self.rooms_created_total.inc_by(0);
         self.active_rooms.set(0);
+        self.players_in_rooms.set(0);
+        self.waiting_players.set(0);
         self.matchmaking_queue_depth.set(0);
+        self.reconciliation_fixed_total.inc_by(0);
+        self.tournaments_created_total.inc_by(0);
+        self.tournaments_completed_total.inc_by(0);
     }
 
     pub fn inc_rooms_created(&self) {
@@ -60,9 +133,33 @@ impl MatchmakingMetrics {
         self.active_rooms.set(rooms);
     }
 
+    pub fn set_players_in_rooms(&self, players: i64) {
+        self.players_in_rooms.set(players);
+    }
+
+    pub fn set_waiting_players(&self, players: i64) {
+        self.waiting_players.set(players);
+    }
+
+    pub fn observe_assign_room(&self, elapsed: std::time::Duration) {
+        self.assign_room_duration_ms.observe(elapsed.as_secs_f64() * 1000.0);
+    }
+
     pub fn set_queue_depth(&self, depth: i64) {
         self.matchmaking_queue_depth.set(depth);
     }
+
+    pub fn inc_reconciliation_fixed(&self, rooms: u64) {
+        self.reconciliation_fixed_total.inc_by(rooms);
+    }
+
+    pub fn inc_tournaments_created(&self) {
+        self.tournaments_created_total.inc();
+    }
+
+    pub fn inc_tournaments_completed(&self) {
+        self.tournaments_completed_total.inc();
+    }
 }
 
 /// Metric set cho snapshot/delta pipeline trong tuong lai.
@@ -97,6 +194,52 @@ pub fn simulation_metrics() -> &'static SimulationMetrics {
             "So luong player dang duoc mo phong tren worker"
         )
         .expect("register worker_active_players"),
+        inputs_dropped_total: register_int_counter_vec!(
+            "worker_inputs_dropped_total",
+            "Số input bị bỏ vì input buffer của player đầy",
+            &["player_id"]
+        )
+        .expect("register worker_inputs_dropped_total"),
+        body_spawns_total: register_int_counter_vec!(
+            "worker_body_spawns_total",
+            "Số body Rapier được spawn cho obstacle/enemy/pickup, theo nguồn pool hay cấp phát mới",
+            &["source"]
+        )
+        .expect("register worker_body_spawns_total"),
+        degradation_level: register_int_gauge!(
+            "worker_simulation_degradation_level",
+            "Bậc giảm tải hiện tại của mô phỏng (0 = bình thường) khi fixed_update vượt tick budget"
+        )
+        .expect("register worker_simulation_degradation_level"),
+        accumulated_lag_seconds: register_gauge!(
+            "worker_simulation_accumulated_lag_seconds",
+            "Thời gian mô phỏng còn nợ chưa chạy (accumulator của fixed timestep)"
+        )
+        .expect("register worker_simulation_accumulated_lag_seconds"),
+        delta_bytes: register_histogram!(
+            "worker_snapshot_delta_bytes",
+            "Kích thước JSON (byte) của delta encoder tính ra mỗi lần encode",
+            prometheus::exponential_buckets(64.0, 2.0, 14).expect("delta byte buckets")
+        )
+        .expect("register worker_snapshot_delta_bytes"),
+        full_bytes: register_histogram!(
+            "worker_snapshot_full_bytes",
+            "Kích thước JSON (byte) của Full snapshot tại mỗi lần encode",
+            prometheus::exponential_buckets(64.0, 2.0, 14).expect("full byte buckets")
+        )
+        .expect("register worker_snapshot_full_bytes"),
+        delta_ratio: register_histogram!(
+            "worker_snapshot_delta_ratio",
+            "Tỉ lệ kích thước delta so với Full snapshot cùng tick",
+            vec![0.01, 0.05, 0.1, 0.25, 0.5, 0.75, 1.0, 1.5]
+        )
+        .expect("register worker_snapshot_delta_ratio"),
+        full_forced_total: register_int_counter_vec!(
+            "worker_snapshot_full_forced_total",
+            "Số lần encoder gửi Full snapshot thay vì delta, theo lý do",
+            &["reason"]
+        )
+        .expect("register worker_snapshot_full_forced_total"),
     })
 }
 
@@ -109,11 +252,42 @@ pub fn matchmaking_metrics() -> &'static MatchmakingMetrics {
         .expect("register room_manager_rooms_created_total"),
         active_rooms: register_int_gauge!("room_manager_active_rooms", "So phong dang hoat dong")
             .expect("register room_manager_active_rooms"),
+        players_in_rooms: register_int_gauge!(
+            "room_manager_players_in_rooms",
+            "Số player đang ở trong các phòng hoạt động"
+        )
+        .expect("register room_manager_players_in_rooms"),
+        waiting_players: register_int_gauge!(
+            "room_manager_waiting_players",
+            "Số player trong các phòng đang chờ bắt đầu trận"
+        )
+        .expect("register room_manager_waiting_players"),
         matchmaking_queue_depth: register_int_gauge!(
             "room_manager_matchmaking_queue_depth",
             "So luong yeu cau dang cho trong hang doi matchmaking"
         )
         .expect("register room_manager_matchmaking_queue_depth"),
+        reconciliation_fixed_total: register_int_counter!(
+            "room_manager_reconciliation_fixed_total",
+            "Số phòng ma đã được đóng do lệch với worker"
+        )
+        .expect("register room_manager_reconciliation_fixed_total"),
+        tournaments_created_total: register_int_counter!(
+            "room_manager_tournaments_created_total",
+            "Tổng số giải đấu được tạo"
+        )
+        .expect("register room_manager_tournaments_created_total"),
+        tournaments_completed_total: register_int_counter!(
+            "room_manager_tournaments_completed_total",
+            "Số giải đấu đã có nhà vô địch"
+        )
+        .expect("register room_manager_tournaments_completed_total"),
+        assign_room_duration_ms: register_histogram!(
+            "room_manager_assign_room_duration_ms",
+            "Thời gian xếp player vào phòng (ms)",
+            vec![1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0]
+        )
+        .expect("register room_manager_assign_room_duration_ms"),
     })
 }
 
@@ -141,8 +315,20 @@ pub async fn serve_metrics(
     listener: tokio::net::TcpListener,
     metrics_path: &'static str,
 ) -> Result<(), BoxError> {
+    serve_router(listener, metrics_router(metrics_path)).await
+}
+
+/// Như `serve_metrics` nhưng listener còn phục vụ `/healthz` và `/readyz` của service
+pub async fn serve_metrics_with_readiness(
+    listener: tokio::net::TcpListener,
+    metrics_path: &'static str,
+    readiness: Readiness,
+) -> Result<(), BoxError> {
+    serve_router(listener, metrics_router(metrics_path).merge(readiness.router())).await
+}
+
+async fn serve_router(listener: tokio::net::TcpListener, router: Router) -> Result<(), BoxError> {
     let std_listener = listener.into_std()?;
-    let router = metrics_router(metrics_path);
     Server::from_tcp(std_listener)?
         .serve(router.into_make_service())
         .await
@@ -153,11 +339,12 @@ pub fn spawn_metrics_exporter(
     addr: SocketAddr,
     metrics_path: &'static str,
     service_name: &'static str,
+    readiness: Readiness,
 ) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         match TcpListener::bind(addr).await {
             Ok(listener) => {
-                if let Err(err) = serve_metrics(listener, metrics_path).await {
+                if let Err(err) = serve_metrics_with_readiness(listener, metrics_path, readiness).await {
                     error!(%err, service = service_name, %addr, path = metrics_path, "metrics exporter dung bat thuong");
                 }
             }
@@ -1,7 +1,13 @@
 /// Quantization utilities for reducing precision to save bandwidth
 /// Uses i16 for positions (range: -32768 to 32767) and i8 for smaller values
 
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Số giá trị bị clamp vì vượt range i16 kể từ khi process chạy
+static CLAMPED_VALUES: AtomicU64 = AtomicU64::new(0);
 
 /// Quantization configuration for different value types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,13 +33,42 @@ impl Default for QuantizationConfig {
     }
 }
 
+impl QuantizationConfig {
+    /// Position factor nhỏ nhất (mịn nhất, không mịn hơn mặc định) mà i16 vẫn phủ được ±`max_offset` đơn vị
+    pub fn for_extent(max_offset: f32) -> Self {
+        let default = Self::default();
+        Self {
+            position_factor: default.position_factor.max(max_offset / i16::MAX as f32),
+            ..default
+        }
+    }
+}
+
+/// `value / factor` làm tròn về i16. Vượt range thì clamp về biên thay vì wrap, đếm lại và warn
+/// (lần đầu rồi mỗi 1000 lần để không ngập log)
+pub fn quantize_i16(value: f32, factor: f32) -> i16 {
+    let scaled = (value / factor).round();
+    if scaled.is_nan() || scaled > i16::MAX as f32 || scaled < i16::MIN as f32 {
+        let clamped_before = CLAMPED_VALUES.fetch_add(1, Ordering::Relaxed);
+        if clamped_before.is_multiple_of(1000) {
+            warn!(value, factor, clamped_total = clamped_before + 1, "quantization overflow, value clamped to i16 range");
+        }
+        if scaled.is_nan() {
+            return 0;
+        }
+        return if scaled > 0.0 { i16::MAX } else { i16::MIN };
+    }
+    scaled as i16
+}
+
+/// Tổng số giá trị đã bị `quantize_i16` clamp
+pub fn clamped_value_count() -> u64 {
+    CLAMPED_VALUES.load(Ordering::Relaxed)
+}
+
 /// Quantize a 3D position vector (f32) to i16
 pub fn quantize_position(position: [f32; 3], config: &QuantizationConfig) -> [i16; 3] {
-    [
-        (position[0] / config.position_factor) as i16,
-        (position[1] / config.position_factor) as i16,
-        (position[2] / config.position_factor) as i16,
-    ]
+    position.map(|axis| quantize_i16(axis, config.position_factor))
 }
 
 /// Dequantize an i16 position back to f32
@@ -67,11 +102,7 @@ pub fn dequantize_scale(scale: i8, config: &QuantizationConfig) -> f32 {
 
 /// Quantize a 3D velocity vector (f32) to i16
 pub fn quantize_velocity(velocity: [f32; 3], config: &QuantizationConfig) -> [i16; 3] {
-    [
-        (velocity[0] / config.velocity_factor) as i16,
-        (velocity[1] / config.velocity_factor) as i16,
-        (velocity[2] / config.velocity_factor) as i16,
-    ]
+    velocity.map(|axis| quantize_i16(axis, config.velocity_factor))
 }
 
 /// Dequantize an i16 velocity back to f32
@@ -206,6 +237,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn out_of_range_values_are_clamped_instead_of_wrapping() {
+        let config = QuantizationConfig::default();
+        let before = clamped_value_count();
+        assert_eq!(quantize_position([5000.0, -5000.0, 1.0], &config), [i16::MAX, i16::MIN, 100]);
+        assert!(clamped_value_count() >= before + 2);
+
+        // Extent lớn hơn thì factor thô hơn nhưng vẫn round-trip
+        let wide = QuantizationConfig::for_extent(5000.0);
+        assert!(wide.position_factor > config.position_factor);
+        let restored = dequantize_position(quantize_position([0.0, 0.0, 5000.0], &wide), &wide);
+        assert!((restored[2] - 5000.0).abs() <= wide.position_factor);
+        assert_eq!(QuantizationConfig::for_extent(1.0).position_factor, config.position_factor);
+    }
+
     #[test]
     fn test_size_calculations() {
         // Original: 3 f32 positions + 1 f32 rotation + 1 f32 scale = 5 * 4 = 20 bytes
@@ -0,0 +1,18 @@
+//! Điểm leaderboard worker gửi lên gateway: giá trị do simulation tính, gắn room và tick lúc chốt điểm
+
+use serde::{Deserialize, Serialize};
+
+/// Route nội bộ của gateway nhận điểm từ worker, xác thực bằng shared secret
+pub const SCORES_PATH: &str = "/internal/leaderboard/scores";
+
+/// Điểm một player đạt trong room
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreSubmission {
+    pub room_id: String,
+    /// Tick simulation lúc chốt điểm
+    pub tick: u64,
+    pub game_mode: String,
+    pub player_id: String,
+    pub player_name: String,
+    pub score: u64,
+}
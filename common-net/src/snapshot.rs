@@ -1,6 +1,7 @@
 /// Snapshot and delta encoding for game state synchronization
 /// Provides efficient serialization with quantization for reduced bandwidth
 
+use crate::compression::{Compression, CompressedData, CompressionAlgorithm, CompressionConfig, CompressionError};
 use crate::message::{EntitySnapshot, EntityDelta};
 use crate::quantization::{QuantizationConfig, QuantizedTransform, QuantizedPhysics};
 use serde::{Deserialize, Serialize};
@@ -354,6 +355,91 @@ pub fn decode_delta(
     Ok(deltas)
 }
 
+// Byte đầu của payload state trên dây, cho decoder biết thuật toán cần inflate
+const PAYLOAD_RAW: u8 = 0;
+#[cfg(feature = "compression")]
+const PAYLOAD_LZ4: u8 = 1;
+#[cfg(feature = "compression")]
+const PAYLOAD_ZSTD: u8 = 2;
+#[cfg(feature = "compression")]
+const PAYLOAD_SNAPPY: u8 = 3;
+
+/// Thêm header flag vào payload state đã serialize; chỉ nén khi payload từ `config.threshold` trở lên
+/// và bản nén thật sự nhỏ hơn, còn lại gửi nguyên
+pub fn compress_state_payload(payload: &[u8], config: &CompressionConfig) -> Vec<u8> {
+    let compressed = Compression::compress(payload, config);
+    let flag = match compressed.algorithm {
+        _ if compressed.compressed_size >= payload.len() => PAYLOAD_RAW,
+        CompressionAlgorithm::None => PAYLOAD_RAW,
+        #[cfg(feature = "compression")]
+        CompressionAlgorithm::Lz4 => PAYLOAD_LZ4,
+        #[cfg(feature = "compression")]
+        CompressionAlgorithm::Zstd => PAYLOAD_ZSTD,
+        #[cfg(feature = "compression")]
+        CompressionAlgorithm::Snappy => PAYLOAD_SNAPPY,
+    };
+
+    let body = if flag == PAYLOAD_RAW { payload } else { &compressed.data };
+    let mut framed = Vec::with_capacity(body.len() + 1);
+    framed.push(flag);
+    framed.extend_from_slice(body);
+    framed
+}
+
+/// Ngược với `compress_state_payload`: đọc header flag rồi inflate nếu cần
+pub fn decompress_state_payload(data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+    let (&flag, body) = data.split_first().ok_or(CompressionError::DataCorrupted)?;
+    let algorithm = match flag {
+        PAYLOAD_RAW => return Ok(body.to_vec()),
+        #[cfg(feature = "compression")]
+        PAYLOAD_LZ4 => CompressionAlgorithm::Lz4,
+        #[cfg(feature = "compression")]
+        PAYLOAD_ZSTD => CompressionAlgorithm::Zstd,
+        #[cfg(feature = "compression")]
+        PAYLOAD_SNAPPY => CompressionAlgorithm::Snappy,
+        _ => return Err(CompressionError::UnsupportedAlgorithm),
+    };
+    Compression::decompress(&CompressedData {
+        algorithm,
+        original_size: 0,
+        compressed_size: body.len(),
+        data: body.to_vec(),
+    })
+}
+
+/// `encode_snapshot` kèm header flag nén theo `compression`
+pub fn encode_snapshot_compressed(
+    snapshot: &[EntitySnapshot],
+    config: &QuantizationConfig,
+    compression: &CompressionConfig,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(compress_state_payload(&encode_snapshot(snapshot, config)?, compression))
+}
+
+/// Decode snapshot do `encode_snapshot_compressed` tạo ra, nén hay không đều được
+pub fn decode_snapshot_compressed(
+    data: &[u8],
+    config: &QuantizationConfig,
+) -> Result<Vec<EntitySnapshot>, Box<dyn std::error::Error + Send + Sync>> {
+    decode_snapshot(&decompress_state_payload(data)?, config)
+}
+
+/// `encode_delta` kèm header flag nén theo `compression`
+pub fn encode_delta_compressed(
+    delta: &[EntityDelta],
+    config: &QuantizationConfig,
+    compression: &CompressionConfig,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(compress_state_payload(&encode_delta(delta, config)?, compression))
+}
+
+pub fn decode_delta_compressed(
+    data: &[u8],
+    config: &QuantizationConfig,
+) -> Result<Vec<EntityDelta>, Box<dyn std::error::Error + Send + Sync>> {
+    decode_delta(&decompress_state_payload(data)?, config)
+}
+
 /// Calculate compression ratio for a snapshot
 pub fn calculate_snapshot_compression_ratio(
     original_size: usize,
@@ -532,4 +618,59 @@ mod tests {
         // Test that quantized values are actually smaller in range
         assert!(quantized_pos.iter().all(|&x| x.abs() <= 32767));
     }
+
+    #[cfg(feature = "compression")]
+    fn large_snapshot(count: usize) -> Vec<EntitySnapshot> {
+        (0..count)
+            .map(|i| EntitySnapshot {
+                id: format!("entity_{i}"),
+                components: serde_json::json!({
+                    "transform": { "position": [i as f32 * 0.5, 1.0, -(i as f32)], "rotation": 0.0, "scale": 1.0 },
+                    "physics": { "velocity": [0.0, -9.8, 0.0], "angular_velocity": [0.0, 0.0, 0.0], "mass": 1.0, "friction": 0.5 },
+                    "health": 100,
+                }),
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn large_snapshot_roundtrips_compressed_and_uncompressed() {
+        use crate::compression::CompressionLevel;
+
+        let config = QuantizationConfig::default();
+        let snapshot = large_snapshot(200);
+        let expected = serde_json::to_value(decode_snapshot(&encode_snapshot(&snapshot, &config).unwrap(), &config).unwrap()).unwrap();
+
+        let uncompressed = encode_snapshot_compressed(&snapshot, &config, &CompressionConfig::default()).unwrap();
+        assert_eq!(uncompressed[0], PAYLOAD_RAW);
+        let decoded = decode_snapshot_compressed(&uncompressed, &config).unwrap();
+        assert_eq!(serde_json::to_value(decoded).unwrap(), expected);
+
+        for (algorithm, flag) in [(CompressionAlgorithm::Zstd, PAYLOAD_ZSTD), (CompressionAlgorithm::Lz4, PAYLOAD_LZ4)] {
+            let compression = CompressionConfig {
+                algorithm,
+                level: CompressionLevel::Balanced,
+                threshold: 1024,
+            };
+            let compressed = encode_snapshot_compressed(&snapshot, &config, &compression).unwrap();
+            assert_eq!(compressed[0], flag);
+            assert!(
+                compressed.len() * 2 < uncompressed.len(),
+                "{algorithm:?}: {} vs {} bytes",
+                compressed.len(),
+                uncompressed.len()
+            );
+            let decoded = decode_snapshot_compressed(&compressed, &config).unwrap();
+            assert_eq!(serde_json::to_value(decoded).unwrap(), expected);
+
+            // Dưới ngưỡng thì không nén
+            let small = encode_snapshot_compressed(&snapshot[..2], &config, &compression).unwrap();
+            assert_eq!(small[0], PAYLOAD_RAW);
+            assert_eq!(decode_snapshot_compressed(&small, &config).unwrap().len(), 2);
+        }
+
+        assert!(matches!(decompress_state_payload(&[]), Err(CompressionError::DataCorrupted)));
+        assert!(matches!(decompress_state_payload(&[9, 1, 2]), Err(CompressionError::UnsupportedAlgorithm)));
+    }
 }
@@ -1,21 +1,90 @@
-use std::sync::Once;
+use std::sync::{Once, OnceLock};
 
 use tracing::info;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry};
+
+/// Header mang correlation id của một request qua gateway và sang worker (HTTP lẫn gRPC metadata)
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
 
 static INIT: Once = Once::new();
+/// Đổi filter log lúc đang chạy, có sau `init`
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Id mới cho request không mang sẵn `x-request-id`
+pub fn new_request_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
 
 pub fn init(service_name: &str) {
     INIT.call_once(|| {
         let env_filter =
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-        tracing_subscriber::fmt()
-            .with_env_filter(env_filter)
+        let (env_filter, filter_handle) = reload::Layer::new(env_filter);
+        let _ = FILTER_HANDLE.set(filter_handle);
+        let fmt_layer = tracing_subscriber::fmt::layer()
             .with_target(false)
             .with_thread_names(true)
-            .compact()
+            .compact();
+
+        #[cfg(feature = "otlp")]
+        let otlp_layer = otlp::layer(service_name);
+        #[cfg(not(feature = "otlp"))]
+        let otlp_layer: Option<tracing_subscriber::layer::Identity> = None;
+
+        tracing_subscriber::registry()
+            .with(env_filter)
+            .with(fmt_layer)
+            .with(otlp_layer)
             .init();
     });
 
     info!(service = service_name, "telemetry initialized");
 }
+
+/// Kiểm tra cú pháp filter kiểu RUST_LOG (vd. `info,gateway=debug`) mà không áp dụng
+pub fn parse_log_filter(directives: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(directives).map_err(|err| format!("invalid log filter '{directives}': {err}"))
+}
+
+/// Thay filter log của process; lỗi nếu filter sai cú pháp hoặc `init` chưa chạy
+pub fn set_log_filter(directives: &str) -> Result<(), String> {
+    let filter = parse_log_filter(directives)?;
+    let handle = FILTER_HANDLE.get().ok_or("telemetry not initialized")?;
+    handle.reload(filter).map_err(|err| err.to_string())?;
+    info!(filter = directives, "log filter reloaded");
+    Ok(())
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{trace, Resource};
+    use tracing::Subscriber;
+    use tracing_subscriber::registry::LookupSpan;
+
+    /// Collector OTLP/gRPC, ví dụ `http://localhost:4317`; không đặt thì không export
+    pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+    pub fn layer<S>(service_name: &str) -> Option<tracing_opentelemetry::OpenTelemetryLayer<S, trace::Tracer>>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let endpoint = std::env::var(OTLP_ENDPOINT_ENV).ok().filter(|endpoint| !endpoint.is_empty())?;
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+            .with_trace_config(
+                trace::config().with_resource(Resource::new(vec![KeyValue::new("service.name", service_name.to_string())])),
+            )
+            .install_batch(opentelemetry_sdk::runtime::Tokio);
+        match tracer {
+            Ok(tracer) => Some(tracing_opentelemetry::layer().with_tracer(tracer)),
+            Err(e) => {
+                // Subscriber chưa init nên chỉ in ra stderr
+                eprintln!("otlp exporter disabled: {e}");
+                None
+            }
+        }
+    }
+}
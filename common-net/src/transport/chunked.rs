@@ -0,0 +1,298 @@
+//! Chia frame lớn (keyframe snapshot) thành nhiều fragment vừa MTU của DataChannel/WS và ráp lại
+//! ở phía nhận. Frame vừa MTU vẫn gửi nguyên JSON như cũ nên bên nhận chưa hỗ trợ chunking vẫn đọc được.
+//!
+//! Fragment: `[MAGIC][message_id u32][index u16][count u16][len u32][payload]`, số nguyên big-endian,
+//! `len` là độ dài payload của fragment này. Ráp lại chịu được fragment đến lệch thứ tự hoặc trùng.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use super::{TransportError, TransportErrorKind};
+use crate::message::{self, Frame};
+
+/// Byte đầu của fragment; JSON frame luôn bắt đầu bằng `{` nên không nhầm được
+pub const FRAGMENT_MAGIC: u8 = 0xF7;
+/// magic + message_id + index + count + len
+pub const FRAGMENT_HEADER_LEN: usize = 1 + 4 + 2 + 2 + 4;
+/// MTU mặc định: 16KB là mức DataChannel gửi được trên mọi browser
+pub const DEFAULT_MTU: usize = 16 * 1024;
+/// Frame ráp lại lớn hơn mức này bị bỏ, tránh peer gửi count lớn để chiếm bộ nhớ
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 4 * 1024 * 1024;
+/// Message chưa đủ fragment sau khoảng này coi như mất (state channel không gửi lại)
+pub const DEFAULT_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Số message đang ráp dở tối đa; vượt thì bỏ message cũ nhất
+const MAX_PARTIAL_MESSAGES: usize = 32;
+/// Nhớ id các message vừa ráp xong để fragment trùng đến muộn không mở message mới
+const COMPLETED_HISTORY: usize = 64;
+
+/// Phía gửi: encode frame và chia theo MTU
+#[derive(Debug)]
+pub struct ChunkEncoder {
+    mtu: usize,
+    next_message_id: u32,
+}
+
+impl ChunkEncoder {
+    pub fn new(mtu: usize) -> Result<Self, TransportError> {
+        if mtu <= FRAGMENT_HEADER_LEN {
+            return Err(TransportError::new(
+                TransportErrorKind::Unsupported,
+                format!("mtu {mtu} must be larger than the {FRAGMENT_HEADER_LEN} byte fragment header"),
+            ));
+        }
+        Ok(Self { mtu, next_message_id: 0 })
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    /// Các buffer cần gửi theo thứ tự; một phần tử (JSON nguyên) nếu frame vừa MTU
+    pub fn encode(&mut self, frame: &Frame) -> Result<Vec<Vec<u8>>, TransportError> {
+        let bytes = message::encode(frame)
+            .map_err(|e| TransportError::new(TransportErrorKind::EncodingFailure, e.to_string()))?;
+        self.fragment(bytes)
+    }
+
+    fn fragment(&mut self, bytes: Vec<u8>) -> Result<Vec<Vec<u8>>, TransportError> {
+        if bytes.len() <= self.mtu {
+            return Ok(vec![bytes]);
+        }
+
+        let chunk_len = self.mtu - FRAGMENT_HEADER_LEN;
+        let count = u16::try_from(bytes.len().div_ceil(chunk_len)).map_err(|_| {
+            TransportError::new(
+                TransportErrorKind::EncodingFailure,
+                format!("frame of {} bytes needs more than {} fragments", bytes.len(), u16::MAX),
+            )
+        })?;
+        let message_id = self.next_message_id;
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+
+        Ok(bytes
+            .chunks(chunk_len)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut fragment = Vec::with_capacity(FRAGMENT_HEADER_LEN + chunk.len());
+                fragment.push(FRAGMENT_MAGIC);
+                fragment.extend_from_slice(&message_id.to_be_bytes());
+                fragment.extend_from_slice(&(index as u16).to_be_bytes());
+                fragment.extend_from_slice(&count.to_be_bytes());
+                fragment.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+                fragment.extend_from_slice(chunk);
+                fragment
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug)]
+struct PartialMessage {
+    fragments: Vec<Option<Vec<u8>>>,
+    received: usize,
+    bytes: usize,
+    started_at: Instant,
+}
+
+/// Phía nhận: gom fragment theo message_id, chỉ trả ra frame đã đủ
+#[derive(Debug)]
+pub struct ChunkReassembler {
+    partial: HashMap<u32, PartialMessage>,
+    completed: VecDeque<u32>,
+    max_message_bytes: usize,
+    timeout: Duration,
+}
+
+impl Default for ChunkReassembler {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MESSAGE_BYTES, DEFAULT_REASSEMBLY_TIMEOUT)
+    }
+}
+
+impl ChunkReassembler {
+    pub fn new(max_message_bytes: usize, timeout: Duration) -> Self {
+        Self {
+            partial: HashMap::new(),
+            completed: VecDeque::new(),
+            max_message_bytes,
+            timeout,
+        }
+    }
+
+    /// Số message đang chờ thêm fragment
+    pub fn pending(&self) -> usize {
+        self.partial.len()
+    }
+
+    /// Một buffer nhận từ transport. `Ok(None)` khi fragment đã được giữ lại chờ phần còn thiếu
+    /// (hoặc là fragment trùng); lỗi khi buffer hỏng, fragment hỏng không làm hỏng message khác.
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Option<Frame>, TransportError> {
+        self.push_at(bytes, Instant::now())
+    }
+
+    fn push_at(&mut self, bytes: &[u8], now: Instant) -> Result<Option<Frame>, TransportError> {
+        if bytes.first() != Some(&FRAGMENT_MAGIC) {
+            return decode(bytes).map(Some);
+        }
+
+        let header = parse_header(bytes)?;
+        self.partial.retain(|_, partial| now.duration_since(partial.started_at) < self.timeout);
+        if self.completed.contains(&header.message_id) {
+            return Ok(None);
+        }
+
+        if !self.partial.contains_key(&header.message_id) && self.partial.len() == MAX_PARTIAL_MESSAGES {
+            if let Some(oldest) = self.partial.iter().min_by_key(|(_, p)| p.started_at).map(|(id, _)| *id) {
+                self.partial.remove(&oldest);
+            }
+        }
+        let partial = self.partial.entry(header.message_id).or_insert_with(|| PartialMessage {
+            fragments: vec![None; header.count as usize],
+            received: 0,
+            bytes: 0,
+            started_at: now,
+        });
+        if partial.fragments.len() != header.count as usize {
+            self.partial.remove(&header.message_id);
+            return Err(malformed("fragment count changed within a message"));
+        }
+
+        let slot = &mut partial.fragments[header.index as usize];
+        if slot.is_some() {
+            return Ok(None);
+        }
+        partial.bytes += header.payload.len();
+        if partial.bytes > self.max_message_bytes {
+            self.partial.remove(&header.message_id);
+            return Err(malformed("reassembled frame exceeds the size limit"));
+        }
+        *slot = Some(header.payload.to_vec());
+        partial.received += 1;
+        if partial.received < partial.fragments.len() {
+            return Ok(None);
+        }
+
+        let Some(partial) = self.partial.remove(&header.message_id) else {
+            return Ok(None);
+        };
+        if self.completed.len() == COMPLETED_HISTORY {
+            self.completed.pop_front();
+        }
+        self.completed.push_back(header.message_id);
+
+        let bytes: Vec<u8> = partial.fragments.into_iter().flatten().flatten().collect();
+        decode(&bytes).map(Some)
+    }
+}
+
+struct FragmentHeader<'a> {
+    message_id: u32,
+    index: u16,
+    count: u16,
+    payload: &'a [u8],
+}
+
+fn parse_header(bytes: &[u8]) -> Result<FragmentHeader<'_>, TransportError> {
+    if bytes.len() < FRAGMENT_HEADER_LEN {
+        return Err(malformed("fragment shorter than its header"));
+    }
+    let message_id = u32::from_be_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]);
+    let index = u16::from_be_bytes([bytes[5], bytes[6]]);
+    let count = u16::from_be_bytes([bytes[7], bytes[8]]);
+    let len = u32::from_be_bytes([bytes[9], bytes[10], bytes[11], bytes[12]]) as usize;
+    let payload = &bytes[FRAGMENT_HEADER_LEN..];
+
+    if count == 0 || index >= count {
+        return Err(malformed("fragment index out of range"));
+    }
+    if payload.len() != len {
+        return Err(malformed("fragment length prefix does not match payload"));
+    }
+    Ok(FragmentHeader {
+        message_id,
+        index,
+        count,
+        payload,
+    })
+}
+
+fn decode(bytes: &[u8]) -> Result<Frame, TransportError> {
+    message::decode(bytes).map_err(|e| TransportError::new(TransportErrorKind::DecodingFailure, e.to_string()))
+}
+
+fn malformed(context: &str) -> TransportError {
+    TransportError::new(TransportErrorKind::DecodingFailure, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{EntitySnapshot, StateMessage};
+
+    /// Snapshot encode ra ít nhất `target` byte
+    fn large_snapshot(target: usize) -> Frame {
+        let entity = |i: usize| EntitySnapshot {
+            id: format!("entity-{i}"),
+            components: serde_json::json!({
+                "transform": { "position": [i as f32 * 0.5, 1.0, -3.25], "rotation": [0.0, 0.0, 0.0, 1.0] },
+                "health": 100,
+            }),
+        };
+        let per_entity = serde_json::to_vec(&entity(0)).unwrap().len();
+        let entities = (0..target / per_entity + 1).map(entity).collect();
+        Frame::state(7, 1_000, StateMessage::Snapshot { tick: 42, entities, tick_duration_ms: 0.0, last_acked_sequence: None })
+    }
+
+    #[test]
+    fn large_snapshot_survives_fragmentation_out_of_order_and_duplicates() {
+        let frame = large_snapshot(200 * 1024);
+        let original = message::encode(&frame).unwrap();
+        let mut encoder = ChunkEncoder::new(16 * 1024).unwrap();
+        let fragments = encoder.encode(&frame).unwrap();
+        assert_eq!(fragments.len(), original.len().div_ceil(16 * 1024 - FRAGMENT_HEADER_LEN));
+        assert!(fragments.iter().all(|fragment| fragment.len() <= 16 * 1024));
+
+        // Đảo thứ tự và gửi trùng vài fragment
+        let mut delivery: Vec<&Vec<u8>> = fragments.iter().rev().collect();
+        delivery.insert(3, &fragments[5]);
+        delivery.push(&fragments[0]);
+
+        let mut reassembler = ChunkReassembler::default();
+        let mut complete = Vec::new();
+        for fragment in delivery {
+            if let Some(frame) = reassembler.push(fragment).unwrap() {
+                complete.push(frame);
+            }
+        }
+        assert_eq!(complete.len(), 1, "only the complete frame is surfaced, once");
+        assert_eq!(message::encode(&complete[0]).unwrap(), original);
+        assert_eq!(reassembler.pending(), 0);
+
+        // Frame nhỏ vẫn là JSON nguyên
+        let small = Frame::state(8, 1_001, StateMessage::Snapshot { tick: 43, entities: Vec::new(), tick_duration_ms: 0.0, last_acked_sequence: None });
+        let encoded = encoder.encode(&small).unwrap();
+        assert_eq!(encoded, [message::encode(&small).unwrap()]);
+        assert_eq!(reassembler.push(&encoded[0]).unwrap().map(|f| f.sequence), Some(8));
+    }
+
+    #[test]
+    fn incomplete_messages_expire_and_bad_fragments_are_rejected() {
+        let mut encoder = ChunkEncoder::new(FRAGMENT_HEADER_LEN + 64).unwrap();
+        let fragments = encoder.encode(&large_snapshot(1024)).unwrap();
+        let mut reassembler = ChunkReassembler::new(DEFAULT_MAX_MESSAGE_BYTES, Duration::from_secs(1));
+
+        let start = Instant::now();
+        assert!(reassembler.push_at(&fragments[0], start).unwrap().is_none());
+        assert_eq!(reassembler.pending(), 1);
+        // Fragment kế tiếp tới quá hạn: message cũ bị bỏ, chỉ còn message mới dở dang
+        assert!(reassembler.push_at(&fragments[1], start + Duration::from_secs(2)).unwrap().is_none());
+        assert_eq!(reassembler.pending(), 1);
+
+        let mut truncated = fragments[2].clone();
+        truncated.pop();
+        assert!(reassembler.push(&truncated).is_err());
+        assert!(ChunkEncoder::new(FRAGMENT_HEADER_LEN).is_err());
+    }
+}
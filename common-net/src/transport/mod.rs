@@ -4,8 +4,14 @@ pub use ws::WsTransport;
 #[cfg(feature = "webrtc")]
 pub mod webrtc;
 #[cfg(feature = "webrtc")]
-pub use webrtc::WebRtcTransport;
+pub use webrtc::{IceCandidate, IceCandidateSink, WebRtcInbound, WebRtcNegotiation, WebRtcTransport};
 
+#[cfg(feature = "quic")]
+pub mod quic;
+#[cfg(feature = "quic")]
+pub use quic::{QuicListener, QuicTransport};
+
+pub mod chunked;
 pub mod manager;
 pub mod traits;
 pub mod metrics;
@@ -83,11 +89,15 @@ pub enum TransportEvent {
 }
 
 // Legacy transport kind for backward compatibility
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TransportKind {
     WebSocket,
     WebTransport,
     WebRtc,
+    Quic,
+    /// HTTP long-poll cho mạng chặn cả WS upgrade lẫn UDP
+    LongPoll,
 }
 
 #[derive(Debug)]
@@ -0,0 +1,330 @@
+//! QUIC transport (quinn): state frame đi bằng datagram không tin cậy (frame lớn hơn datagram thì
+//! chia fragment như `chunked`), control frame đi trên một bidirectional stream tin cậy, mỗi frame
+//! có tiền tố độ dài u32 big-endian.
+//!
+//! Client mở control stream ngay khi kết nối và gửi `CONTROL_STREAM_TAG` để server nhận được stream
+//! trước khi có control frame đầu tiên.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use quinn::{Connection, Endpoint, RecvStream, SendStream};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use super::{
+    chunked::{ChunkEncoder, ChunkReassembler, DEFAULT_MAX_MESSAGE_BYTES},
+    GameTransport, TransportError, TransportErrorKind, TransportKind,
+};
+use crate::{
+    compression::CompressionConfig,
+    message::{self, Channel, Frame},
+};
+
+/// Byte đầu tiên client ghi lên control stream
+pub const CONTROL_STREAM_TAG: u8 = 0x51;
+/// Số frame đã nhận nhưng chưa được `recv_frame` lấy; đầy thì reader chờ (control) hoặc bỏ (state)
+const INCOMING_BUFFER: usize = 256;
+
+fn io_error(err: impl std::fmt::Display) -> TransportError {
+    TransportError::new(TransportErrorKind::Io, err.to_string())
+}
+
+fn closed(err: impl std::fmt::Display) -> TransportError {
+    TransportError::new(TransportErrorKind::ConnectionClosed, err.to_string())
+}
+
+pub struct QuicTransport {
+    connection: Connection,
+    control: SendStream,
+    incoming: mpsc::Receiver<Result<Frame, TransportError>>,
+    chunker: ChunkEncoder,
+    compression_config: CompressionConfig,
+    /// Client giữ endpoint của mình để socket UDP sống cùng connection
+    _endpoint: Option<Endpoint>,
+}
+
+impl std::fmt::Debug for QuicTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuicTransport")
+            .field("remote_address", &self.connection.remote_address())
+            .finish()
+    }
+}
+
+impl QuicTransport {
+    /// Dial server QUIC, chỉ tin certificate `server_cert` (DER), vd. cert self-signed của `QuicListener`
+    pub async fn connect(addr: SocketAddr, server_name: &str, server_cert: &[u8]) -> Result<Self, TransportError> {
+        let mut roots = rustls::RootCertStore::empty();
+        roots
+            .add(CertificateDer::from(server_cert.to_vec()))
+            .map_err(|e| TransportError::new(TransportErrorKind::Unsupported, e.to_string()))?;
+        let client_config = quinn::ClientConfig::with_root_certificates(Arc::new(roots))
+            .map_err(|e| TransportError::new(TransportErrorKind::Unsupported, e.to_string()))?;
+
+        let bind_addr: SocketAddr = if addr.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" }
+            .parse()
+            .expect("valid wildcard address");
+        let endpoint = Endpoint::client(bind_addr).map_err(io_error)?;
+        let connection = endpoint
+            .connect_with(client_config, addr, server_name)
+            .map_err(io_error)?
+            .await
+            .map_err(io_error)?;
+
+        let (mut send, recv) = connection.open_bi().await.map_err(io_error)?;
+        send.write_all(&[CONTROL_STREAM_TAG]).await.map_err(io_error)?;
+        Self::from_parts(connection, send, recv, Some(endpoint))
+    }
+
+    /// Phía server: chờ client mở control stream
+    async fn accept(connection: Connection) -> Result<Self, TransportError> {
+        let (send, mut recv) = connection.accept_bi().await.map_err(closed)?;
+        let mut tag = [0u8; 1];
+        recv.read_exact(&mut tag).await.map_err(io_error)?;
+        if tag[0] != CONTROL_STREAM_TAG {
+            connection.close(0u32.into(), b"unexpected control stream");
+            return Err(TransportError::new(TransportErrorKind::DecodingFailure, "unexpected control stream tag"));
+        }
+        Self::from_parts(connection, send, recv, None)
+    }
+
+    fn from_parts(
+        connection: Connection,
+        control: SendStream,
+        control_recv: RecvStream,
+        endpoint: Option<Endpoint>,
+    ) -> Result<Self, TransportError> {
+        let (tx, incoming) = mpsc::channel(INCOMING_BUFFER);
+        tokio::spawn(read_control_stream(control_recv, tx.clone()));
+        tokio::spawn(read_datagrams(connection.clone(), tx));
+
+        // Không hỗ trợ datagram thì state frame đi chung control stream; MTU khi đó không dùng tới
+        let mtu = connection.max_datagram_size().unwrap_or(DEFAULT_MAX_MESSAGE_BYTES);
+        Ok(Self {
+            connection,
+            control,
+            incoming,
+            chunker: ChunkEncoder::new(mtu)?,
+            compression_config: CompressionConfig::default(),
+            _endpoint: endpoint,
+        })
+    }
+
+    pub fn remote_address(&self) -> SocketAddr {
+        self.connection.remote_address()
+    }
+
+    async fn write_control(&mut self, bytes: &[u8]) -> Result<(), TransportError> {
+        let len = u32::try_from(bytes.len())
+            .ok()
+            .filter(|len| *len as usize <= DEFAULT_MAX_MESSAGE_BYTES)
+            .ok_or_else(|| TransportError::new(TransportErrorKind::EncodingFailure, "control frame too large"))?;
+        self.control.write_all(&len.to_be_bytes()).await.map_err(io_error)?;
+        self.control.write_all(bytes).await.map_err(io_error)
+    }
+}
+
+/// Đọc control frame có tiền tố độ dài cho tới khi stream đóng
+async fn read_control_stream(mut recv: RecvStream, tx: mpsc::Sender<Result<Frame, TransportError>>) {
+    loop {
+        let mut len = [0u8; 4];
+        if let Err(e) = recv.read_exact(&mut len).await {
+            debug!(error = %e, "quic control stream ended");
+            return;
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > DEFAULT_MAX_MESSAGE_BYTES {
+            let _ = tx
+                .send(Err(TransportError::new(TransportErrorKind::DecodingFailure, "control frame too large")))
+                .await;
+            return;
+        }
+        let mut bytes = vec![0u8; len];
+        if let Err(e) = recv.read_exact(&mut bytes).await {
+            debug!(error = %e, "quic control stream ended mid-frame");
+            return;
+        }
+        let frame = message::decode(&bytes)
+            .map_err(|e| TransportError::new(TransportErrorKind::DecodingFailure, e.to_string()));
+        if tx.send(frame).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Ráp datagram thành state frame; buffer đầy thì bỏ frame (state cũ không còn giá trị)
+async fn read_datagrams(connection: Connection, tx: mpsc::Sender<Result<Frame, TransportError>>) {
+    let mut reassembler = ChunkReassembler::default();
+    loop {
+        let datagram = match connection.read_datagram().await {
+            Ok(datagram) => datagram,
+            Err(e) => {
+                debug!(error = %e, "quic datagram reader stopped");
+                return;
+            }
+        };
+        match reassembler.push(&datagram) {
+            Ok(None) => {}
+            Ok(Some(frame)) => {
+                if let Err(mpsc::error::TrySendError::Closed(_)) = tx.try_send(Ok(frame)) {
+                    return;
+                }
+            }
+            Err(e) => debug!(error = %e, "dropping malformed quic datagram"),
+        }
+    }
+}
+
+#[async_trait]
+impl GameTransport for QuicTransport {
+    fn kind(&self) -> TransportKind {
+        TransportKind::Quic
+    }
+
+    async fn send_frame(&mut self, frame: Frame) -> Result<(), TransportError> {
+        if frame.channel == Channel::State && self.connection.max_datagram_size().is_some() {
+            for datagram in self.chunker.encode(&frame)? {
+                self.connection.send_datagram(Bytes::from(datagram)).map_err(io_error)?;
+            }
+            return Ok(());
+        }
+        let bytes = message::encode(&frame)
+            .map_err(|e| TransportError::new(TransportErrorKind::EncodingFailure, e.to_string()))?;
+        self.write_control(&bytes).await
+    }
+
+    async fn recv_frame(&mut self) -> Result<Frame, TransportError> {
+        self.incoming
+            .recv()
+            .await
+            .unwrap_or_else(|| Err(TransportError::new(TransportErrorKind::ConnectionClosed, "quic connection closed")))
+    }
+
+    async fn close(&mut self) -> Result<(), TransportError> {
+        let _ = self.control.finish();
+        self.connection.close(0u32.into(), b"closed");
+        Ok(())
+    }
+
+    fn set_compression_config(&mut self, config: CompressionConfig) {
+        self.compression_config = config;
+    }
+
+    fn get_compression_config(&self) -> &CompressionConfig {
+        &self.compression_config
+    }
+}
+
+/// Endpoint QUIC phía server
+#[derive(Debug)]
+pub struct QuicListener {
+    endpoint: Endpoint,
+    certificate: Vec<u8>,
+}
+
+impl QuicListener {
+    /// Bind với certificate/private key (PKCS#8) dạng DER
+    pub fn bind(addr: SocketAddr, certificate: Vec<u8>, private_key: Vec<u8>) -> Result<Self, TransportError> {
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(private_key));
+        let server_config = quinn::ServerConfig::with_single_cert(vec![CertificateDer::from(certificate.clone())], key)
+            .map_err(|e| TransportError::new(TransportErrorKind::Unsupported, e.to_string()))?;
+        let endpoint = Endpoint::server(server_config, addr).map_err(io_error)?;
+        Ok(Self { endpoint, certificate })
+    }
+
+    /// Bind với certificate self-signed cho `server_name` (dev/test); client lấy cert qua `certificate_der`
+    pub fn bind_self_signed(addr: SocketAddr, server_name: &str) -> Result<Self, TransportError> {
+        let certified = rcgen::generate_simple_self_signed(vec![server_name.to_string()])
+            .map_err(|e| TransportError::new(TransportErrorKind::Unsupported, e.to_string()))?;
+        Self::bind(addr, certified.cert.der().to_vec(), certified.key_pair.serialize_der())
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, TransportError> {
+        self.endpoint.local_addr().map_err(io_error)
+    }
+
+    pub fn certificate_der(&self) -> &[u8] {
+        &self.certificate
+    }
+
+    /// Connection kế tiếp đã mở control stream; `None` khi endpoint đã đóng
+    pub async fn accept(&self) -> Option<Result<QuicTransport, TransportError>> {
+        let incoming = self.endpoint.accept().await?;
+        Some(match incoming.await {
+            Ok(connection) => QuicTransport::accept(connection).await,
+            Err(e) => Err(io_error(e)),
+        })
+    }
+
+    pub fn close(&self) {
+        self.endpoint.close(0u32.into(), b"shutdown");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{ControlMessage, EntitySnapshot, StateMessage};
+
+    #[tokio::test]
+    async fn quic_loopback_carries_control_and_state_frames() {
+        let listener = QuicListener::bind_self_signed("127.0.0.1:0".parse().unwrap(), "localhost").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let certificate = listener.certificate_der().to_vec();
+        let server = tokio::spawn(async move {
+            let mut transport = listener.accept().await.expect("incoming").expect("accept");
+            let mut frames = Vec::new();
+            for _ in 0..3 {
+                frames.push(transport.recv_frame().await.expect("recv"));
+            }
+            frames
+        });
+
+        let mut client = QuicTransport::connect(addr, "localhost", &certificate).await.expect("connect");
+        assert_eq!(client.kind(), TransportKind::Quic);
+        client
+            .send_frame(Frame::control(1, 10, ControlMessage::JoinRoom { room_id: "r1".to_string(), reconnect_token: None }))
+            .await
+            .expect("send control");
+        client
+            .send_frame(Frame::state(1, 11, StateMessage::Snapshot { tick: 7, entities: Vec::new(), tick_duration_ms: 0.0, last_acked_sequence: None }))
+            .await
+            .expect("send state");
+        // Keyframe lớn hơn một datagram: đi thành nhiều fragment rồi ráp lại
+        let entities = (0..400)
+            .map(|id| EntitySnapshot {
+                id: format!("entity-{id}"),
+                components: serde_json::json!({ "position": [id as f32, 1.0, 2.0], "rotation": [0.0, 0.0, 0.0, 1.0] }),
+            })
+            .collect();
+        client
+            .send_frame(Frame::state(2, 12, StateMessage::Snapshot { tick: 8, entities, tick_duration_ms: 0.0, last_acked_sequence: None }))
+            .await
+            .expect("send large state");
+
+        let frames = tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .expect("frames arrive")
+            .expect("join");
+        let control = frames.iter().find(|frame| frame.channel == Channel::Control).expect("control frame");
+        assert!(matches!(
+            &control.payload,
+            message::FramePayload::Control { message: ControlMessage::JoinRoom { room_id, .. } } if room_id == "r1"
+        ));
+        let mut ticks: Vec<_> = frames
+            .iter()
+            .filter_map(|frame| match &frame.payload {
+                message::FramePayload::State { message: StateMessage::Snapshot { tick, entities, .. } } => {
+                    Some((*tick, entities.len()))
+                }
+                _ => None,
+            })
+            .collect();
+        ticks.sort();
+        assert_eq!(ticks, [(7, 0), (8, 400)]);
+        client.close().await.expect("close");
+    }
+}
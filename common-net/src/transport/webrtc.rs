@@ -1,10 +1,26 @@
 use async_trait::async_trait;
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use bytes::Bytes;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{mpsc, watch, RwLock};
 use tracing::{info, warn};
+use webrtc::{
+    api::APIBuilder,
+    data_channel::{data_channel_init::RTCDataChannelInit, data_channel_message::DataChannelMessage, RTCDataChannel},
+    ice_transport::{
+        ice_candidate::{RTCIceCandidate, RTCIceCandidateInit},
+        ice_server::RTCIceServer,
+    },
+    peer_connection::{configuration::RTCConfiguration, sdp::session_description::RTCSessionDescription, RTCPeerConnection},
+};
 
 use crate::{message::{ControlMessage, Frame}, compression::CompressionConfig};
-use super::{GameTransport, TransportError, TransportErrorKind, TransportKind};
+use super::{
+    chunked::{ChunkEncoder, ChunkReassembler, DEFAULT_MTU},
+    GameTransport, TransportError, TransportErrorKind, TransportKind,
+};
+
+/// Thời gian chờ hai DataChannel mở sau khi trao SDP, quá thì client dùng WebSocket
+pub const DEFAULT_NEGOTIATION_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// WebRTC DataChannel configuration
 #[derive(Debug, Clone)]
@@ -15,6 +31,8 @@ pub struct DataChannelConfig {
     pub ordered: bool,
     /// Maximum retransmits (0 for unreliable)
     pub max_retransmits: Option<u16>,
+    /// Stream id of the pre-negotiated channel; both peers create it with the same id, no in-band DCEP
+    pub id: u16,
 }
 
 impl DataChannelConfig {
@@ -24,6 +42,7 @@ impl DataChannelConfig {
             label: "control".to_string(),
             ordered: true,
             max_retransmits: None, // Reliable
+            id: 0,
         }
     }
 
@@ -33,13 +52,261 @@ impl DataChannelConfig {
             label: "state".to_string(),
             ordered: false,
             max_retransmits: Some(0), // Max 0 retransmits for partial reliability
+            id: 1,
+        }
+    }
+
+    fn init(&self) -> RTCDataChannelInit {
+        RTCDataChannelInit {
+            ordered: Some(self.ordered),
+            max_retransmits: self.max_retransmits,
+            negotiated: Some(self.id),
+            ..Default::default()
+        }
+    }
+}
+
+/// ICE candidate trao qua signaling (`/rtc/ice` hoặc `ControlMessage::WebRtcIceCandidate`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IceCandidate {
+    pub candidate: String,
+    pub sdp_mid: String,
+    pub sdp_mline_index: u32,
+}
+
+fn rtc_error(context: &str, err: webrtc::Error) -> TransportError {
+    TransportError::new(TransportErrorKind::Io, format!("{context}: {err}"))
+}
+
+/// Một đầu của peer connection webrtc-rs đang trao SDP/ICE. Hai DataChannel (control tin cậy, state
+/// không thứ tự và không gửi lại) được tạo sẵn với id cố định ở cả hai đầu; `connect` chờ chúng mở rồi
+/// trả về `WebRtcTransport`. Gateway dùng `accept_offer`, client dùng `create_offer` + `accept_answer`.
+pub struct WebRtcNegotiation {
+    peer: Arc<RTCPeerConnection>,
+    control: Arc<RTCDataChannel>,
+    state: Arc<RTCDataChannel>,
+    control_rx: mpsc::UnboundedReceiver<Frame>,
+    state_rx: mpsc::UnboundedReceiver<Frame>,
+    /// Số DataChannel đã mở
+    opened_rx: watch::Receiver<u8>,
+    connected: Arc<RwLock<bool>>,
+}
+
+impl WebRtcNegotiation {
+    /// `stun_servers` dạng `stun:host:port`; rỗng thì chỉ dùng host candidate
+    pub async fn new(stun_servers: &[String]) -> Result<Self, TransportError> {
+        let ice_servers = if stun_servers.is_empty() {
+            Vec::new()
+        } else {
+            vec![RTCIceServer { urls: stun_servers.to_vec(), ..Default::default() }]
+        };
+        let api = APIBuilder::new().build();
+        let peer = api
+            .new_peer_connection(RTCConfiguration { ice_servers, ..Default::default() })
+            .await
+            .map_err(|err| rtc_error("create peer connection", err))?;
+        let peer = Arc::new(peer);
+
+        let connected = Arc::new(RwLock::new(false));
+        let (opened_tx, opened_rx) = watch::channel(0u8);
+        let opened_tx = Arc::new(opened_tx);
+        let (control_tx, control_rx) = mpsc::unbounded_channel();
+        let (state_tx, state_rx) = mpsc::unbounded_channel();
+        let control =
+            open_channel(&peer, DataChannelConfig::control(), control_tx, opened_tx.clone(), connected.clone()).await?;
+        let state = open_channel(&peer, DataChannelConfig::state(), state_tx, opened_tx, connected.clone()).await?;
+
+        Ok(Self { peer, control, state, control_rx, state_rx, opened_rx, connected })
+    }
+
+    /// Client: tạo offer và đặt làm local description; candidate được trickle qua `on_local_candidate`
+    pub async fn create_offer(&self) -> Result<String, TransportError> {
+        let offer = self.peer.create_offer(None).await.map_err(|err| rtc_error("create offer", err))?;
+        let sdp = offer.sdp.clone();
+        self.peer.set_local_description(offer).await.map_err(|err| rtc_error("set local offer", err))?;
+        Ok(sdp)
+    }
+
+    /// Server: nhận offer của client, trả answer đã chứa đủ candidate của server (không cần trickle chiều về)
+    pub async fn accept_offer(&self, offer_sdp: &str) -> Result<String, TransportError> {
+        let offer = RTCSessionDescription::offer(offer_sdp.to_string())
+            .map_err(|err| TransportError::new(TransportErrorKind::DecodingFailure, format!("invalid offer sdp: {err}")))?;
+        self.peer
+            .set_remote_description(offer)
+            .await
+            .map_err(|err| TransportError::new(TransportErrorKind::DecodingFailure, format!("invalid offer sdp: {err}")))?;
+        let answer = self.peer.create_answer(None).await.map_err(|err| rtc_error("create answer", err))?;
+        let mut gathered = self.peer.gathering_complete_promise().await;
+        self.peer.set_local_description(answer).await.map_err(|err| rtc_error("set local answer", err))?;
+        let _ = gathered.recv().await;
+        self.peer
+            .local_description()
+            .await
+            .map(|description| description.sdp)
+            .ok_or_else(|| TransportError::new(TransportErrorKind::Io, "answer has no local description"))
+    }
+
+    /// Client: đặt answer của server làm remote description
+    pub async fn accept_answer(&self, answer_sdp: &str) -> Result<(), TransportError> {
+        let answer = RTCSessionDescription::answer(answer_sdp.to_string())
+            .map_err(|err| TransportError::new(TransportErrorKind::DecodingFailure, format!("invalid answer sdp: {err}")))?;
+        self.peer.set_remote_description(answer).await.map_err(|err| rtc_error("set remote answer", err))
+    }
+
+    /// Candidate của đầu kia; chỉ hợp lệ sau khi đã có remote description
+    pub async fn add_ice_candidate(&self, candidate: IceCandidate) -> Result<(), TransportError> {
+        self.ice_sink().add(candidate).await
+    }
+
+    /// Handle nhận candidate của đầu kia, vẫn dùng được trong lúc `connect` đang chờ
+    pub fn ice_sink(&self) -> IceCandidateSink {
+        IceCandidateSink { peer: self.peer.clone() }
+    }
+
+    /// Gọi `f` với từng local candidate gom được, để gửi sang đầu kia qua signaling
+    pub fn on_local_candidate(&self, mut f: impl FnMut(IceCandidate) + Send + Sync + 'static) {
+        self.peer.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+            if let Some(init) = candidate.and_then(|candidate| candidate.to_json().ok()) {
+                f(IceCandidate {
+                    candidate: init.candidate,
+                    sdp_mid: init.sdp_mid.unwrap_or_default(),
+                    sdp_mline_index: init.sdp_mline_index.unwrap_or(0).into(),
+                });
+            }
+            Box::pin(async {})
+        }));
+    }
+
+    /// Chờ cả hai DataChannel mở; quá `timeout` thì đóng peer connection và trả lỗi để caller fallback
+    pub async fn connect(mut self, room_id: String, peer_id: String, timeout: Duration) -> Result<WebRtcTransport, TransportError> {
+        let opened = tokio::time::timeout(timeout, self.opened_rx.wait_for(|opened| *opened >= 2))
+            .await
+            .is_ok_and(|opened| opened.is_ok());
+        if !opened {
+            self.close().await;
+            return Err(TransportError::new(
+                TransportErrorKind::ConnectionClosed,
+                format!("webrtc data channels did not open within {}ms", timeout.as_millis()),
+            ));
+        }
+        *self.connected.write().await = true;
+        WebRtcTransport::over_data_channels(room_id, peer_id, self)
+    }
+
+    pub async fn close(&self) {
+        if let Err(err) = self.peer.close().await {
+            warn!(%err, "webrtc: đóng peer connection lỗi");
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IceCandidateSink {
+    peer: Arc<RTCPeerConnection>,
+}
+
+impl IceCandidateSink {
+    pub async fn add(&self, candidate: IceCandidate) -> Result<(), TransportError> {
+        let init = RTCIceCandidateInit {
+            candidate: candidate.candidate,
+            sdp_mid: Some(candidate.sdp_mid),
+            sdp_mline_index: u16::try_from(candidate.sdp_mline_index).ok(),
+            username_fragment: None,
+        };
+        self.peer
+            .add_ice_candidate(init)
+            .await
+            .map_err(|err| TransportError::new(TransportErrorKind::DecodingFailure, format!("invalid ice candidate: {err}")))
+    }
+
+    /// Cùng một peer connection
+    pub fn same_peer(&self, other: &IceCandidateSink) -> bool {
+        Arc::ptr_eq(&self.peer, &other.peer)
+    }
+}
+
+impl std::fmt::Debug for IceCandidateSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IceCandidateSink").finish_non_exhaustive()
+    }
+}
+
+/// Message nhận trên channel được ráp fragment (xem `chunked`), decode và đẩy vào `inbound`;
+/// channel đóng thì transport hết connected
+async fn open_channel(
+    peer: &RTCPeerConnection,
+    config: DataChannelConfig,
+    inbound: mpsc::UnboundedSender<Frame>,
+    opened: Arc<watch::Sender<u8>>,
+    connected: Arc<RwLock<bool>>,
+) -> Result<Arc<RTCDataChannel>, TransportError> {
+    let channel = peer
+        .create_data_channel(&config.label, Some(config.init()))
+        .await
+        .map_err(|err| rtc_error("create data channel", err))?;
+    channel.on_open(Box::new(move || {
+        opened.send_modify(|opened| *opened += 1);
+        Box::pin(async {})
+    }));
+    channel.on_close(Box::new(move || {
+        let connected = connected.clone();
+        Box::pin(async move {
+            *connected.write().await = false;
+        })
+    }));
+    let label = config.label;
+    let mut reassembler = ChunkReassembler::default();
+    channel.on_message(Box::new(move |data: DataChannelMessage| {
+        match reassembler.push(&data.data) {
+            Ok(Some(frame)) => {
+                let _ = inbound.send(frame);
+            }
+            Ok(None) => {}
+            Err(err) => warn!(%err, channel = %label, "webrtc: bỏ qua frame không decode được"),
         }
+        Box::pin(async {})
+    }));
+    Ok(channel)
+}
+
+/// Frame đầu kia gửi qua hai DataChannel, control được ưu tiên
+#[derive(Debug)]
+pub struct WebRtcInbound {
+    control_rx: mpsc::UnboundedReceiver<Frame>,
+    state_rx: mpsc::UnboundedReceiver<Frame>,
+}
+
+impl WebRtcInbound {
+    /// None khi DataChannel đã bị huỷ
+    pub async fn recv(&mut self) -> Option<Frame> {
+        tokio::select! {
+            biased;
+            frame = self.control_rx.recv() => frame,
+            frame = self.state_rx.recv() => frame,
+        }
+    }
+}
+
+/// DataChannel thật phía sau một `WebRtcTransport` đã kết nối
+struct DataChannelLink {
+    peer: Arc<RTCPeerConnection>,
+    control: Arc<RTCDataChannel>,
+    state: Arc<RTCDataChannel>,
+    /// Frame lớn hơn một SCTP message (vd. keyframe snapshot) được chia fragment như QUIC datagram
+    chunker: ChunkEncoder,
+}
+
+impl std::fmt::Debug for DataChannelLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataChannelLink")
+            .field("control", &self.control.label())
+            .field("state", &self.state.label())
+            .finish()
     }
 }
 
-/// WebRTC DataChannel transport implementation
-/// This implementation uses internal channels to simulate DataChannels
-/// For production, integrate with actual WebRTC library like webrtc-rs
+/// WebRTC DataChannel transport. `WebRtcNegotiation::connect` trả về transport chạy trên DataChannel thật;
+/// `new` là loopback trong process (frame gửi đi đọc lại được bằng `recv_frame`), dùng cho test và benchmark
 #[derive(Debug)]
 pub struct WebRtcTransport {
     room_id: String,
@@ -65,6 +332,9 @@ pub struct WebRtcTransport {
 
     // Statistics
     stats: Arc<RwLock<TransportStats>>,
+
+    /// Có khi transport chạy trên DataChannel thật
+    link: Option<DataChannelLink>,
 }
 
 /// Statistics for WebRTC transport
@@ -99,6 +369,40 @@ impl WebRtcTransport {
             fallback_to_ws: Arc::new(RwLock::new(false)),
             compression_config: CompressionConfig::default(),
             stats: Arc::new(RwLock::new(TransportStats::default())),
+            link: None,
+        }
+    }
+
+    fn over_data_channels(
+        room_id: String,
+        peer_id: String,
+        negotiation: WebRtcNegotiation,
+    ) -> Result<Self, TransportError> {
+        let WebRtcNegotiation { peer, control, state, control_rx, state_rx, connected, .. } = negotiation;
+        let chunker = ChunkEncoder::new(DEFAULT_MTU)?;
+        Ok(Self {
+            control_tx: None,
+            control_rx: Some(control_rx),
+            state_tx: None,
+            state_rx: Some(state_rx),
+            connected,
+            link: Some(DataChannelLink { peer, control, state, chunker }),
+            ..Self::new(room_id, peer_id)
+        })
+    }
+
+    /// True khi frame đi qua DataChannel thật thay vì loopback
+    pub fn has_data_channels(&self) -> bool {
+        self.link.is_some()
+    }
+
+    /// Tách phần nhận frame ra để đọc mà không giữ transport (transport dùng chung sau Mutex cho phía gửi);
+    /// sau đó `recv_frame` không còn frame nào. None với transport loopback hoặc đã tách
+    pub fn take_inbound(&mut self) -> Option<WebRtcInbound> {
+        self.link.as_ref()?;
+        match (self.control_rx.take(), self.state_rx.take()) {
+            (Some(control_rx), Some(state_rx)) => Some(WebRtcInbound { control_rx, state_rx }),
+            _ => None,
         }
     }
 
@@ -185,6 +489,20 @@ impl GameTransport for WebRtcTransport {
             stats.bytes_sent += frame_size;
         }).await;
 
+        if let Some(link) = &mut self.link {
+            let fragments = link.chunker.encode(&frame)?;
+            let channel = match frame.channel {
+                crate::message::Channel::Control => &link.control,
+                crate::message::Channel::State => &link.state,
+            };
+            for fragment in fragments {
+                channel.send(&Bytes::from(fragment)).await.map_err(|err| {
+                    TransportError::new(TransportErrorKind::ConnectionClosed, format!("data channel send failed: {err}"))
+                })?;
+            }
+            return Ok(());
+        }
+
         // Route to appropriate DataChannel based on channel type
         match frame.channel {
             crate::message::Channel::Control => {
@@ -284,6 +602,29 @@ impl GameTransport for WebRtcTransport {
             }
         }
 
+        // DataChannel thật: chờ frame tiếp theo, control được ưu tiên
+        if self.link.is_some() {
+            if let (Some(control_rx), Some(state_rx)) = (self.control_rx.as_mut(), self.state_rx.as_mut()) {
+                let frame = tokio::select! {
+                    biased;
+                    frame = control_rx.recv() => frame,
+                    frame = state_rx.recv() => frame,
+                };
+                let Some(frame) = frame else {
+                    return Err(TransportError::new(TransportErrorKind::ConnectionClosed, "data channel closed"));
+                };
+                let frame_size = match &frame.payload {
+                    crate::message::FramePayload::Control { .. } => std::mem::size_of::<crate::message::ControlMessage>() as u64,
+                    crate::message::FramePayload::State { .. } => std::mem::size_of::<crate::message::StateMessage>() as u64,
+                };
+                self.update_stats(|stats| {
+                    stats.messages_received += 1;
+                    stats.bytes_received += frame_size;
+                }).await;
+                return Ok(frame);
+            }
+        }
+
         // No frames available - this is normal for non-blocking recv
         Err(TransportError::new(
             TransportErrorKind::Backpressure,
@@ -295,6 +636,11 @@ impl GameTransport for WebRtcTransport {
         info!("Closing WebRTC transport for room: {} peer: {}", self.room_id, self.peer_id);
 
         self.set_connected(false).await;
+        if let Some(link) = self.link.take() {
+            if let Err(err) = link.peer.close().await {
+                warn!(%err, "webrtc: đóng peer connection lỗi");
+            }
+        }
 
         // Update reconnect stats if we fell back to WebSocket
         if *self.fallback_to_ws.read().await {
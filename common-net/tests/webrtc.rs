@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use common_net::{
+    message::{self, Channel, ControlMessage, Frame, FramePayload, StateMessage},
+    transport::{chunked::DEFAULT_MTU, GameTransport, TransportKind, WebRtcNegotiation},
+};
+use tokio::sync::mpsc;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+type Transport = Box<dyn GameTransport + Send + Sync>;
+
+/// Hai peer trong process, trao SDP/ICE trực tiếp rồi chờ DataChannel mở; trả về (server, client)
+async fn connected_pair() -> (Transport, Transport) {
+    let server = WebRtcNegotiation::new(&[]).await.expect("server peer");
+    let client = WebRtcNegotiation::new(&[]).await.expect("client peer");
+
+    // Client trickle candidate như qua `/rtc/ice`; answer của server đã chứa sẵn candidate của nó
+    let (candidate_tx, mut candidate_rx) = mpsc::unbounded_channel();
+    client.on_local_candidate(move |candidate| {
+        let _ = candidate_tx.send(candidate);
+    });
+
+    let offer = client.create_offer().await.expect("offer");
+    let answer = server.accept_offer(&offer).await.expect("answer");
+    assert!(answer.contains("a=candidate"), "answer carries server candidates");
+    client.accept_answer(&answer).await.expect("apply answer");
+
+    let server_ice = server.ice_sink();
+    tokio::spawn(async move {
+        while let Some(candidate) = candidate_rx.recv().await {
+            server_ice.add(candidate).await.expect("server accepts client candidate");
+        }
+    });
+
+    let (server, client) = tokio::join!(
+        server.connect("room-rtc".to_string(), "client".to_string(), TIMEOUT),
+        client.connect("room-rtc".to_string(), "server".to_string(), TIMEOUT),
+    );
+    (
+        Box::new(server.expect("server data channels open")),
+        Box::new(client.expect("client data channels open")),
+    )
+}
+
+#[tokio::test]
+async fn ping_round_trips_over_real_data_channels() {
+    let (mut server, mut client) = connected_pair().await;
+    assert_eq!(server.kind(), TransportKind::WebRtc);
+
+    client.send_frame(Frame::control(1, 10, ControlMessage::Ping { nonce: 42 })).await.expect("send ping");
+    let ping = tokio::time::timeout(TIMEOUT, server.recv_frame()).await.expect("ping arrives").expect("frame");
+    assert_eq!(ping.sequence, 1);
+    let FramePayload::Control { message: ControlMessage::Ping { nonce } } = ping.payload else {
+        panic!("expected ping, got {:?}", ping.payload);
+    };
+
+    server.send_frame(Frame::control(1, 11, ControlMessage::Pong { nonce })).await.expect("send pong");
+    let pong = tokio::time::timeout(TIMEOUT, client.recv_frame()).await.expect("pong arrives").expect("frame");
+    assert!(matches!(pong.payload, FramePayload::Control { message: ControlMessage::Pong { nonce: 42 } }));
+
+    // State frame đi trên channel không thứ tự
+    let state = StateMessage::Event { name: "tick".to_string(), data: serde_json::json!({ "n": 1 }) };
+    server.send_frame(Frame::state(2, 12, state)).await.expect("send state");
+    let state = tokio::time::timeout(TIMEOUT, client.recv_frame()).await.expect("state arrives").expect("frame");
+    assert!(matches!(state.payload, FramePayload::State { .. }));
+
+    client.close().await.expect("close client");
+    server.close().await.expect("close server");
+}
+
+#[tokio::test]
+async fn frames_larger_than_one_chunk_round_trip_over_data_channels() {
+    let (mut server, mut client) = connected_pair().await;
+
+    // Lớn hơn nhiều so với DEFAULT_MTU: phải đi thành nhiều fragment và được ráp lại ở đầu kia
+    let blob = "x".repeat(DEFAULT_MTU * 5);
+    assert!(message::encode(&Frame::state(0, 0, blob_event(&blob))).unwrap().len() > DEFAULT_MTU);
+
+    server.send_frame(Frame::state(3, 20, blob_event(&blob))).await.expect("send large state");
+    let state = tokio::time::timeout(TIMEOUT, client.recv_frame()).await.expect("state arrives").expect("frame");
+    assert_eq!(state.sequence, 3);
+    let FramePayload::State { message: StateMessage::Event { data, .. } } = state.payload else {
+        panic!("expected event, got {:?}", state.payload);
+    };
+    assert_eq!(data["blob"].as_str().map(str::len), Some(blob.len()));
+
+    // Control channel cũng chia fragment
+    client.send_frame(Frame::control(4, 21, ControlMessage::Ping { nonce: 7 })).await.expect("send ping");
+    let mut big_control = Frame::state(5, 22, blob_event(&blob));
+    big_control.channel = Channel::Control;
+    client.send_frame(big_control).await.expect("send large control");
+    let ping = tokio::time::timeout(TIMEOUT, server.recv_frame()).await.expect("ping arrives").expect("frame");
+    assert_eq!(ping.sequence, 4);
+    let large = tokio::time::timeout(TIMEOUT, server.recv_frame()).await.expect("large arrives").expect("frame");
+    assert_eq!(large.sequence, 5);
+
+    client.close().await.expect("close client");
+    server.close().await.expect("close server");
+}
+
+fn blob_event(blob: &str) -> StateMessage {
+    StateMessage::Event { name: "blob".to_string(), data: serde_json::json!({ "blob": blob }) }
+}
+
+#[tokio::test]
+async fn connect_times_out_without_an_answer() {
+    let client = WebRtcNegotiation::new(&[]).await.expect("client peer");
+    client.create_offer().await.expect("offer");
+
+    let err = client
+        .connect("room-rtc".to_string(), "server".to_string(), Duration::from_millis(200))
+        .await
+        .expect_err("no answer, no data channels");
+    assert!(err.to_string().contains("did not open"), "{err}");
+}
@@ -0,0 +1,19 @@
+use std::process::Command;
+
+// Ghi SHA commit và thời điểm build vào env lúc compile để `/version` báo đúng bản đang chạy
+fn main() {
+    let git_sha = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GATEWAY_GIT_SHA={git_sha}");
+    println!("cargo:rustc-env=GATEWAY_BUILT_AT={}", chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+}
@@ -0,0 +1,182 @@
+//! Session event cho funnel sản phẩm (connect → join room → chơi → kết thúc trận). ws session đẩy event vào
+//! channel có giới hạn, task nền gom thành batch và POST lên `POST /api/ingest/events` của services.
+//! Channel đầy hoặc services lỗi thì event bị bỏ và được đếm, không bao giờ chặn ws session.
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use services::collections::AnalyticsEvent;
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+use crate::metrics;
+
+pub use services::analytics::{DISCONNECT, ROOM_JOINED, ROOM_LEFT, SESSION_STARTED, TRANSPORT_FALLBACK_USED};
+
+pub const INGEST_PATH: &str = "/api/ingest/events";
+
+/// Lý do disconnect gửi kèm event `disconnect`
+pub const CLIENT_CLOSED: &str = "client_closed";
+pub const SERVER_CLOSED: &str = "server_closed";
+pub const SATURATED: &str = "saturated";
+
+#[derive(Debug, Clone)]
+pub struct AnalyticsConfig {
+    /// Base URL của services (ANALYTICS_INGEST_URL); None thì tắt analytics
+    pub ingest_url: Option<String>,
+    /// ANALYTICS_ANONYMIZE=1: player id được thay bằng sha256(salt + id)
+    pub anonymize: bool,
+    /// ANALYTICS_SALT; thiếu thì mỗi process tự sinh salt, id ẩn danh không nối được qua các lần restart
+    pub salt: Option<String>,
+    /// Số event chờ gửi tối đa trước khi bắt đầu bỏ
+    pub capacity: usize,
+    pub batch_size: usize,
+    /// Batch chưa đầy cũng được gửi sau khoảng này
+    pub flush_interval: Duration,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            ingest_url: None,
+            anonymize: false,
+            salt: None,
+            capacity: 10_000,
+            batch_size: 200,
+            flush_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl AnalyticsConfig {
+    pub fn from_env() -> Self {
+        Self {
+            ingest_url: std::env::var("ANALYTICS_INGEST_URL").ok().filter(|url| !url.is_empty()),
+            anonymize: std::env::var("ANALYTICS_ANONYMIZE").ok().as_deref() == Some("1"),
+            salt: std::env::var("ANALYTICS_SALT").ok().filter(|salt| !salt.is_empty()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Handle phát event, clone rẻ; bản `disabled` bỏ qua mọi event
+#[derive(Debug, Clone, Default)]
+pub struct SessionAnalytics {
+    tx: Option<mpsc::Sender<AnalyticsEvent>>,
+    /// Some khi bật ẩn danh
+    salt: Option<Arc<str>>,
+}
+
+impl SessionAnalytics {
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Bật khi có `ingest_url`, spawn task gửi batch; phải gọi trong tokio runtime
+    pub fn spawn(config: AnalyticsConfig) -> Self {
+        let Some(ingest_url) = config.ingest_url.clone() else {
+            return Self::disabled();
+        };
+        let salt = config.anonymize.then(|| {
+            let salt = config.salt.clone().unwrap_or_else(|| {
+                tracing::warn!("gateway: ANALYTICS_ANONYMIZE=1 nhưng thiếu ANALYTICS_SALT, dùng salt ngẫu nhiên cho process này");
+                uuid::Uuid::new_v4().simple().to_string()
+            });
+            Arc::from(salt)
+        });
+        let (tx, rx) = mpsc::channel(config.capacity.max(1));
+        let url = format!("{}{}", ingest_url.trim_end_matches('/'), INGEST_PATH);
+        tokio::spawn(run_flusher(rx, url, config.batch_size.max(1), config.flush_interval));
+        Self { tx: Some(tx), salt }
+    }
+
+    /// Event mới với `occurred_at` là bây giờ; các trường còn lại do call site điền rồi `emit`
+    pub fn event(&self, event_type: &str, session_id: &str, player_id: &str) -> AnalyticsEvent {
+        AnalyticsEvent {
+            id: String::new(),
+            event_type: event_type.to_string(),
+            player_id: self.player_id(player_id),
+            session_id: session_id.to_string(),
+            room_id: String::new(),
+            transport: String::new(),
+            reason: String::new(),
+            duration_ms: None,
+            occurred_at: Utc::now(),
+            date: String::new(),
+        }
+    }
+
+    /// Không chờ: channel đầy thì event bị bỏ và đếm vào `gateway_analytics_events_dropped_total`
+    pub fn emit(&self, event: AnalyticsEvent) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        if tx.try_send(event).is_err() {
+            metrics::record_analytics_dropped("backpressure", 1);
+        }
+    }
+
+    fn player_id(&self, player_id: &str) -> String {
+        match &self.salt {
+            Some(salt) => anonymize(salt, player_id),
+            None => player_id.to_string(),
+        }
+    }
+}
+
+/// Hex của sha256(salt + player_id); cùng salt thì cùng player ra cùng id
+fn anonymize(salt: &str, player_id: &str) -> String {
+    let digest = Sha256::new().chain_update(salt.as_bytes()).chain_update(player_id.as_bytes()).finalize();
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Gom event tới khi đủ `batch_size` hoặc hết `flush_interval` kể từ event đầu của batch, rồi POST một lần
+async fn run_flusher(mut rx: mpsc::Receiver<AnalyticsEvent>, url: String, batch_size: usize, flush_interval: Duration) {
+    let http = reqwest::Client::new();
+    let mut batch = Vec::with_capacity(batch_size);
+    while let Some(first) = rx.recv().await {
+        batch.push(first);
+        let deadline = tokio::time::Instant::now() + flush_interval;
+        while batch.len() < batch_size {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        send_batch(&http, &url, &batch).await;
+        batch.clear();
+    }
+}
+
+async fn send_batch(http: &reqwest::Client, url: &str, batch: &[AnalyticsEvent]) {
+    let result = http
+        .post(url)
+        .timeout(Duration::from_secs(10))
+        .json(&serde_json::json!({ "events": batch }))
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status);
+    if let Err(err) = result {
+        tracing::warn!(%err, events = batch.len(), "gateway: gửi analytics event thất bại, bỏ batch");
+        metrics::record_analytics_dropped("ingest_failed", batch.len() as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anonymized_ids_are_stable_salted_hashes() {
+        let analytics = SessionAnalytics { tx: None, salt: Some(Arc::from("pepper")) };
+        let first = analytics.event(SESSION_STARTED, "conn-1", "alice");
+        let again = analytics.event(DISCONNECT, "conn-2", "alice");
+        assert_eq!(first.player_id, again.player_id);
+        assert_eq!(first.player_id.len(), 64);
+        assert_ne!(first.player_id, "alice");
+        assert_ne!(first.player_id, anonymize("salt", "alice"));
+
+        // Không bật ẩn danh thì giữ id gốc
+        assert_eq!(SessionAnalytics::disabled().event(SESSION_STARTED, "conn-1", "alice").player_id, "alice");
+    }
+}
@@ -1,18 +1,15 @@
-use axum::{
-    extract::State,
-    http::{header::AUTHORIZATION, StatusCode},
-    response::{IntoResponse, Response},
-    Json,
-};
+use axum::{http::header::AUTHORIZATION, response::Response};
 use hyper::Request;
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
-use tracing::{error, warn};
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::warn;
 
-// Re-export AppState for use in auth module
-use crate::AppState;
+/// Role trong JWT được quyền moderation trên mọi phòng (kick/ban không cần là host)
+pub const ADMIN_ROLE: &str = "admin";
 
 // JWT Claims structure
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -24,6 +21,8 @@ pub struct Claims {
     pub exp: i64,     // Expiration time
     pub iat: i64,     // Issued at
     pub iss: String,  // Issuer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>, // Token ID - chỉ có ở refresh token, dùng cho rotation/revocation
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,6 +31,14 @@ pub struct AuthRequest {
     pub password: String,
 }
 
+/// Body cho POST /auth/login - client cũ gửi `username`, map sang `email`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailLoginRequest {
+    #[serde(alias = "username")]
+    pub email: String,
+    pub password: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthResponse {
     pub access_token: String,
@@ -62,11 +69,13 @@ pub struct UserInfo {
     pub role: String,
 }
 
+/// Body cho POST /auth/register - `display_name` map sang field `name` của users collection
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RegisterRequest {
-    pub username: String,
     pub email: String,
     pub password: String,
+    #[serde(default, alias = "username")]
+    pub display_name: Option<String>,
 }
 
 // JWT configuration
@@ -74,6 +83,132 @@ const JWT_SECRET_KEY: &str = "JWT_SECRET";
 const JWT_ISSUER: &str = "gamev1-gateway";
 const ACCESS_TOKEN_EXPIRY: i64 = 15 * 60; // 15 minutes
 const REFRESH_TOKEN_EXPIRY: i64 = 7 * 24 * 60 * 60; // 7 days
+const MIN_PASSWORD_LENGTH: usize = 8; // Khớp min length mặc định của PocketBase
+
+/// Auth errors
+#[derive(Debug)]
+pub enum AuthError {
+    InvalidCredentials(String),
+    InvalidToken(String),
+    TokenGeneration(String),
+    InvalidRequest(String),
+    UserExists(String),
+    UserStore(String),
+    /// PocketBase trả lỗi không phải do credentials (5xx, body hỏng...)
+    Upstream(String),
+    /// Không kết nối được PocketBase (connection refused, timeout)
+    Unavailable(String),
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::InvalidCredentials(msg) => write!(f, "Invalid credentials: {}", msg),
+            AuthError::InvalidToken(msg) => write!(f, "Invalid token: {}", msg),
+            AuthError::TokenGeneration(msg) => write!(f, "Token generation error: {}", msg),
+            AuthError::InvalidRequest(msg) => write!(f, "Invalid request: {}", msg),
+            AuthError::UserExists(email) => write!(f, "User already exists: {}", email),
+            AuthError::UserStore(msg) => write!(f, "User store error: {}", msg),
+            AuthError::Upstream(msg) => write!(f, "User store upstream error: {}", msg),
+            AuthError::Unavailable(msg) => write!(f, "User store unavailable: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// Cấu hình auth đọc từ env; AppState giữ một bản để handler/main dùng chung
+#[derive(Clone)]
+pub struct AuthConfig {
+    pub jwt_secret: String,
+    pub pocketbase_url: String,
+    /// Chỉ bật khi POCKETBASE_URL được set; tắt thì user lưu in-memory (dev/test)
+    pub pocketbase_users: bool,
+}
+
+impl AuthConfig {
+    pub fn from_env() -> Self {
+        Self {
+            jwt_secret: env::var(JWT_SECRET_KEY)
+                .unwrap_or_else(|_| "your-secret-key-change-in-production".to_string()),
+            pocketbase_url: env::var("POCKETBASE_URL")
+                .unwrap_or_else(|_| "http://localhost:8090".to_string()),
+            pocketbase_users: env::var("POCKETBASE_URL").is_ok(),
+        }
+    }
+}
+
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Không log JWT secret
+        f.debug_struct("AuthConfig")
+            .field("jwt_secret", &"<redacted>")
+            .field("pocketbase_url", &self.pocketbase_url)
+            .field("pocketbase_users", &self.pocketbase_users)
+            .finish()
+    }
+}
+
+/// Token tĩnh cho route `/admin/*` (ops, script) thay cho JWT role admin. Các bản clone dùng chung giá trị
+/// nên `set` có hiệu lực ngay khi reload config; None hoặc chuỗi rỗng là tắt.
+#[derive(Clone, Default)]
+pub struct AdminToken {
+    token: Arc<RwLock<Option<String>>>,
+}
+
+impl AdminToken {
+    pub fn new(token: Option<String>) -> Self {
+        let admin_token = Self::default();
+        admin_token.set(token);
+        admin_token
+    }
+
+    pub fn set(&self, token: Option<String>) {
+        *self.token.write().expect("admin token lock") = token.filter(|token| !token.is_empty());
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.token.read().expect("admin token lock").is_some()
+    }
+
+    /// `Authorization: Bearer <token>` khớp token đang cấu hình; so sánh không dừng sớm ở byte khác đầu tiên
+    pub fn matches(&self, headers: &axum::http::HeaderMap) -> bool {
+        let Some(presented) = headers
+            .get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| s.strip_prefix("Bearer "))
+        else {
+            return false;
+        };
+        match self.token.read().expect("admin token lock").as_deref() {
+            Some(token) => {
+                token.len() == presented.len()
+                    && token.bytes().zip(presented.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+            }
+            None => false,
+        }
+    }
+}
+
+impl std::fmt::Debug for AdminToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminToken").field("set", &self.is_set()).finish()
+    }
+}
+
+/// Nơi xác thực/lưu user. Refresh token luôn do gateway tự cấp, không phụ thuộc store.
+#[derive(Clone)]
+enum UserStore {
+    PocketBase(String),
+    /// Fallback khi không có PocketBase: email (lowercase) -> user + bcrypt hash
+    Local(Arc<Mutex<HashMap<String, LocalUser>>>),
+}
+
+#[derive(Clone)]
+struct LocalUser {
+    user: User,
+    password_hash: String,
+}
 
 // Authentication utilities
 #[derive(Clone)]
@@ -81,20 +216,162 @@ pub struct AuthService {
     secret: String,
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
+    user_store: UserStore,
+    /// Refresh tokens còn hiệu lực: jti -> exp. Mỗi refresh token chỉ dùng được một lần.
+    refresh_tokens: Arc<Mutex<HashMap<String, i64>>>,
 }
 
 impl AuthService {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let secret = env::var(JWT_SECRET_KEY)
-            .unwrap_or_else(|_| "your-secret-key-change-in-production".to_string());
+        Ok(Self::from_config(&AuthConfig::from_env()))
+    }
+
+    pub fn from_config(config: &AuthConfig) -> Self {
+        Self {
+            secret: config.jwt_secret.clone(),
+            encoding_key: EncodingKey::from_secret(config.jwt_secret.as_ref()),
+            decoding_key: DecodingKey::from_secret(config.jwt_secret.as_ref()),
+            user_store: if config.pocketbase_users {
+                UserStore::PocketBase(config.pocketbase_url.clone())
+            } else {
+                UserStore::Local(Arc::new(Mutex::new(HashMap::new())))
+            },
+            refresh_tokens: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
 
-        let encoding_key = EncodingKey::from_secret(secret.as_ref());
-        let decoding_key = DecodingKey::from_secret(secret.as_ref());
+    pub fn with_pocketbase_url(mut self, url: impl Into<String>) -> Self {
+        self.user_store = UserStore::PocketBase(url.into());
+        self
+    }
+
+    /// URL PocketBase đang dùng làm user store; `None` khi chạy fallback in-memory
+    pub fn pocketbase_url(&self) -> Option<&str> {
+        match &self.user_store {
+            UserStore::PocketBase(url) => Some(url),
+            UserStore::Local(_) => None,
+        }
+    }
+
+    /// Kiểm tra email/password với user store, trả về user để cấp JWT (`sub` = record id)
+    pub async fn authenticate(&self, email: &str, password: &str) -> Result<User, AuthError> {
+        match &self.user_store {
+            UserStore::PocketBase(url) => authenticate_with_pocketbase(url, email, password)
+                .await
+                .map_err(login_error),
+            UserStore::Local(users) => {
+                let found = users.lock().unwrap().get(&email.to_lowercase()).cloned();
+                let Some(local) = found else {
+                    return Err(AuthError::InvalidCredentials("unknown email".to_string()));
+                };
+
+                let password = password.to_string();
+                let valid = tokio::task::spawn_blocking(move || {
+                    Self::verify_password(&password, &local.password_hash).map_err(|e| e.to_string())
+                })
+                .await
+                .map_err(|e| AuthError::UserStore(e.to_string()))?
+                .map_err(AuthError::UserStore)?;
+
+                if valid {
+                    Ok(local.user)
+                } else {
+                    Err(AuthError::InvalidCredentials("wrong password".to_string()))
+                }
+            }
+        }
+    }
 
-        Ok(Self {
-            secret,
-            encoding_key,
-            decoding_key,
+    /// Tạo user mới; email trùng trả về `AuthError::UserExists`
+    pub async fn register(&self, payload: RegisterRequest) -> Result<User, AuthError> {
+        let email = payload.email.trim().to_string();
+        if !email.contains('@') {
+            return Err(AuthError::InvalidRequest("invalid email".to_string()));
+        }
+        if payload.password.len() < MIN_PASSWORD_LENGTH {
+            return Err(AuthError::InvalidRequest(format!(
+                "password must be at least {} characters",
+                MIN_PASSWORD_LENGTH
+            )));
+        }
+        let display_name = payload
+            .display_name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty());
+
+        match &self.user_store {
+            UserStore::PocketBase(url) => {
+                let client = pocketbase::PocketBaseClient::new(url);
+                let record = client
+                    .create_user(&email, &payload.password, display_name.as_deref())
+                    .await
+                    .map_err(|e| match e {
+                        pocketbase::PocketBaseError::Conflict(email) => AuthError::UserExists(email),
+                        pocketbase::PocketBaseError::Api { message, code } if code.starts_with("400") => {
+                            AuthError::InvalidRequest(message)
+                        }
+                        other => AuthError::UserStore(other.to_string()),
+                    })?;
+
+                Ok(User {
+                    id: record.id,
+                    username: display_name.unwrap_or_else(|| email.clone()),
+                    email,
+                    role: "user".to_string(),
+                })
+            }
+            UserStore::Local(users) => {
+                let key = email.to_lowercase();
+                if users.lock().unwrap().contains_key(&key) {
+                    return Err(AuthError::UserExists(email));
+                }
+
+                let password = payload.password;
+                let password_hash = tokio::task::spawn_blocking(move || {
+                    Self::hash_password(&password).map_err(|e| e.to_string())
+                })
+                .await
+                .map_err(|e| AuthError::UserStore(e.to_string()))?
+                .map_err(AuthError::UserStore)?;
+
+                let user = User {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    username: display_name.unwrap_or_else(|| email.clone()),
+                    email: email.clone(),
+                    role: "user".to_string(),
+                };
+
+                // Kiểm tra lại dưới lock - hai request cùng email có thể hash song song
+                let mut users = users.lock().unwrap();
+                if users.contains_key(&key) {
+                    return Err(AuthError::UserExists(email));
+                }
+                users.insert(key, LocalUser { user: user.clone(), password_hash });
+                Ok(user)
+            }
+        }
+    }
+
+    // Issue access + refresh token pair for an authenticated user
+    pub fn issue_tokens(&self, user: User) -> Result<AuthResponse, AuthError> {
+        let access_token = self
+            .generate_token(&user)
+            .map_err(|e| AuthError::TokenGeneration(e.to_string()))?;
+        let refresh_token = self
+            .generate_refresh_token(&user)
+            .map_err(|e| AuthError::TokenGeneration(e.to_string()))?;
+
+        Ok(AuthResponse {
+            access_token,
+            refresh_token,
+            token_type: "Bearer".to_string(),
+            expires_in: ACCESS_TOKEN_EXPIRY * 60,
+            user: UserInfo {
+                id: user.id,
+                username: user.username,
+                email: user.email,
+                role: user.role,
+            },
         })
     }
 
@@ -111,6 +388,7 @@ impl AuthService {
             exp: exp.timestamp(),
             iat: now.timestamp(),
             iss: JWT_ISSUER.to_string(),
+            jti: None,
         };
 
         let token = encode(&Header::default(), &claims, &self.encoding_key)?;
@@ -130,17 +408,78 @@ impl AuthService {
             exp: exp.timestamp(),
             iat: now.timestamp(),
             iss: JWT_ISSUER.to_string(),
+            jti: Some(uuid::Uuid::new_v4().to_string()),
         };
 
         // Add refresh token indicator
         refresh_claims.role.push_str(":refresh");
 
         let token = encode(&Header::default(), &refresh_claims, &self.encoding_key)?;
+
+        // Register token; dọn luôn các token đã hết hạn
+        let mut store = self.refresh_tokens.lock().unwrap_or_else(|e| e.into_inner());
+        store.retain(|_, exp| *exp > now.timestamp());
+        if let Some(jti) = refresh_claims.jti {
+            store.insert(jti, refresh_claims.exp);
+        }
+
         Ok(token)
     }
 
-    // Verify JWT token
+    // Rotate refresh token: token cũ bị vô hiệu, cấp cặp token mới
+    pub fn rotate_refresh_token(&self, refresh_token: &str) -> Result<AuthResponse, AuthError> {
+        let claims = self.take_refresh_token(refresh_token)?;
+
+        let user = User {
+            id: claims.sub,
+            username: claims.username,
+            email: claims.email,
+            role: claims.role.strip_suffix(":refresh").unwrap_or("user").to_string(),
+        };
+
+        self.issue_tokens(user)
+    }
+
+    // Revoke refresh token (logout)
+    pub fn revoke_refresh_token(&self, refresh_token: &str) -> Result<(), AuthError> {
+        self.take_refresh_token(refresh_token).map(|_| ())
+    }
+
+    // Verify refresh token và xoá khỏi store; token đã dùng/đã revoke bị từ chối
+    fn take_refresh_token(&self, refresh_token: &str) -> Result<Claims, AuthError> {
+        let claims = self
+            .decode_token(refresh_token)
+            .map_err(|e| AuthError::InvalidToken(e.to_string()))?
+            .claims;
+
+        if !claims.role.ends_with(":refresh") {
+            return Err(AuthError::InvalidToken("not a refresh token".to_string()));
+        }
+
+        let jti = claims
+            .jti
+            .as_deref()
+            .ok_or_else(|| AuthError::InvalidToken("missing jti".to_string()))?;
+
+        let mut store = self.refresh_tokens.lock().unwrap_or_else(|e| e.into_inner());
+        if store.remove(jti).is_none() {
+            return Err(AuthError::InvalidToken("refresh token revoked or already used".to_string()));
+        }
+
+        Ok(claims)
+    }
+
+    // Verify access token (Bearer, WS, QUIC); refresh token (có jti, role `*:refresh`) bị từ chối
     pub fn verify_token(&self, token: &str) -> Result<TokenData<Claims>, Box<dyn std::error::Error>> {
+        let token_data = self.decode_token(token)?;
+        if token_data.claims.jti.is_some() || token_data.claims.role.ends_with(":refresh") {
+            return Err("refresh token cannot be used as an access token".into());
+        }
+        Ok(token_data)
+    }
+
+    // Kiểm tra chữ ký và hạn của JWT, không phân biệt access hay refresh token
+    fn decode_token(&self, token: &str) -> Result<TokenData<Claims>, Box<dyn std::error::Error>> {
         let validation = Validation::default();
         let token_data = decode::<Claims>(token, &self.decoding_key, &validation)?;
         Ok(token_data)
@@ -193,295 +532,66 @@ pub async fn auth_middleware<B>(
     next.run(request).await
 }
 
-// Login handler
-pub async fn login_handler(
-    Json(payload): Json<AuthRequest>,
-) -> impl IntoResponse {
-    // Initialize auth service
-    let auth_service = match AuthService::new() {
-        Ok(service) => service,
-        Err(e) => {
-            error!("Failed to initialize auth service: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Authentication service error").into_response();
-        }
-    };
-
-    // Demo credentials validation (for testing)
-    if payload.username == "demo@example.com" && payload.password == "password123" {
-        let user = User {
-            id: "demo-user-id".to_string(),
-            username: "Demo User".to_string(),
-            email: payload.username.clone(),
-            role: "user".to_string(),
-        };
-
-        match auth_service.generate_token(&user) {
-            Ok(access_token) => {
-                match auth_service.generate_refresh_token(&user) {
-                    Ok(refresh_token) => {
-                        let response = AuthResponse {
-                            access_token,
-                            refresh_token,
-                            token_type: "Bearer".to_string(),
-                            expires_in: ACCESS_TOKEN_EXPIRY * 60,
-                            user: UserInfo {
-                                id: user.id,
-                                username: user.username,
-                                email: user.email,
-                                role: user.role,
-                            },
-                        };
-
-                        return (StatusCode::OK, Json(response)).into_response();
-                    }
-                    Err(e) => {
-                        error!("Failed to generate refresh token: {}", e);
-                        return (StatusCode::INTERNAL_SERVER_ERROR, "Token generation error").into_response();
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Failed to generate access token: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Token generation error").into_response();
-            }
-        }
-    }
-
-    // Try to authenticate with PocketBase
-    match authenticate_with_pocketbase(&payload.username, &payload.password).await {
-        Ok(user) => {
-            match auth_service.generate_token(&user) {
-                Ok(access_token) => {
-                    match auth_service.generate_refresh_token(&user) {
-                        Ok(refresh_token) => {
-                            let response = AuthResponse {
-                                access_token,
-                                refresh_token,
-                                token_type: "Bearer".to_string(),
-                                expires_in: ACCESS_TOKEN_EXPIRY * 60,
-                                user: UserInfo {
-                                    id: user.id,
-                                    username: user.username,
-                                    email: user.email,
-                                    role: user.role,
-                                },
-                            };
-
-                            return (StatusCode::OK, Json(response)).into_response();
-                        }
-                        Err(e) => {
-                            error!("Failed to generate refresh token: {}", e);
-                            return (StatusCode::INTERNAL_SERVER_ERROR, "Token generation error").into_response();
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to generate access token: {}", e);
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Token generation error").into_response();
-                }
-            }
-        }
-        Err(e) => {
-            error!("PocketBase authentication failed: {}", e);
-            return (StatusCode::UNAUTHORIZED, "Invalid username or password").into_response();
-        }
-    }
+// Login: validate email/password với user store (PocketBase auth-with-password) rồi mới cấp JWT
+pub async fn email_login_handler(
+    auth_service: &AuthService,
+    payload: EmailLoginRequest,
+) -> Result<AuthResponse, AuthError> {
+    let user = auth_service.authenticate(&payload.email, &payload.password).await?;
+    auth_service.issue_tokens(user)
 }
 
-// Register handler
-pub async fn register_handler(
-    Json(payload): Json<RegisterRequest>,
-) -> impl IntoResponse {
-    // Initialize auth service
-    let auth_service = match AuthService::new() {
-        Ok(service) => service,
-        Err(e) => {
-            error!("Failed to initialize auth service: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Authentication service error").into_response();
-        }
-    };
-
-    // Demo registration - accept any valid email format
-    if payload.email.contains('@') && payload.email.contains('.') && payload.password.len() >= 6 {
-        let user = User {
-            id: uuid::Uuid::new_v4().to_string(),
-            username: payload.username.clone(),
-            email: payload.email.clone(),
-            role: "user".to_string(),
-        };
-
-    match auth_service.generate_token(&user) {
-        Ok(access_token) => {
-            match auth_service.generate_refresh_token(&user) {
-                Ok(refresh_token) => {
-                    let response = AuthResponse {
-        access_token,
-        refresh_token,
-                        token_type: "Bearer".to_string(),
-                        expires_in: ACCESS_TOKEN_EXPIRY * 60,
-                        user: UserInfo {
-                            id: user.id,
-                            username: user.username,
-                            email: user.email,
-                            role: user.role,
-                        },
-                    };
-
-                    (StatusCode::CREATED, Json(response)).into_response()
-                }
-                Err(e) => {
-                    error!("Failed to generate refresh token: {}", e);
-                    (StatusCode::INTERNAL_SERVER_ERROR, "Token generation error").into_response()
-                }
-            }
-        }
-        Err(e) => {
-            error!("Failed to generate access token: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Token generation error").into_response()
-        }
-    }
-    } else {
-        // Invalid registration data
-        (StatusCode::BAD_REQUEST, "Invalid email or password (password must be at least 6 characters)").into_response()
-    }
+// Register: tạo user trong store rồi cấp luôn cặp token như login
+pub async fn register_user(
+    auth_service: &AuthService,
+    payload: RegisterRequest,
+) -> Result<AuthResponse, AuthError> {
+    let user = auth_service.register(payload).await?;
+    auth_service.issue_tokens(user)
 }
 
-// Refresh token handler
-pub async fn refresh_handler(
-    Json(payload): Json<RefreshRequest>,
-) -> impl IntoResponse {
-    // Initialize auth service
-    let auth_service = match AuthService::new() {
-        Ok(service) => service,
-        Err(e) => {
-            error!("Failed to initialize auth service: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Authentication service error").into_response();
-        }
-    };
-
-    // Verify refresh token
-    match auth_service.verify_token(&payload.refresh_token) {
-        Ok(token_data) => {
-            // Check if it's a refresh token
-            if !token_data.claims.role.ends_with(":refresh") {
-                return (StatusCode::UNAUTHORIZED, "Invalid refresh token").into_response();
-            }
-
-            // TODO: Get user from database using token_data.claims.sub
-            // For now, create a dummy user
-            let user = User {
-                id: token_data.claims.sub.clone(),
-                username: token_data.claims.username.clone(),
-                email: token_data.claims.email.clone(),
-                role: token_data.claims.role.strip_suffix(":refresh").unwrap_or("user").to_string(),
-            };
-
-            // Generate new tokens
-            match auth_service.generate_token(&user) {
-                Ok(access_token) => {
-                    match auth_service.generate_refresh_token(&user) {
-                        Ok(refresh_token) => {
-                            let response = AuthResponse {
-        access_token,
-        refresh_token,
-                                token_type: "Bearer".to_string(),
-                                expires_in: ACCESS_TOKEN_EXPIRY * 60,
-                                user: UserInfo {
-                                    id: user.id,
-                                    username: user.username,
-                                    email: user.email,
-                                    role: user.role,
-                                },
-                            };
-
-                            (StatusCode::OK, Json(response)).into_response()
-                        }
-                        Err(e) => {
-                            error!("Failed to generate refresh token: {}", e);
-                            (StatusCode::INTERNAL_SERVER_ERROR, "Token generation error").into_response()
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to generate access token: {}", e);
-                    (StatusCode::INTERNAL_SERVER_ERROR, "Token generation error").into_response()
-                }
-            }
-        }
-        Err(e) => {
-            warn!("Invalid refresh token: {}", e);
-            (StatusCode::UNAUTHORIZED, "Invalid refresh token").into_response()
+/// Chỉ 400/401 của auth-with-password là sai email/password; lỗi còn lại là của PocketBase,
+/// không được tính là login thất bại của player
+fn login_error(err: pocketbase::PocketBaseError) -> AuthError {
+    match err {
+        pocketbase::PocketBaseError::Api { message, code } if code.starts_with("400") || code.starts_with("401") => {
+            AuthError::InvalidCredentials(message)
         }
+        pocketbase::PocketBaseError::Http(e) if e.is_connect() || e.is_timeout() => AuthError::Unavailable(e.to_string()),
+        other => AuthError::Upstream(other.to_string()),
     }
 }
 
-// Logout handler
-pub async fn logout_handler(
-    request: Request<hyper::Body>,
-) -> impl IntoResponse {
-    // TODO: Implement token blacklisting/invalidation
-    (StatusCode::OK, "Logged out successfully").into_response()
-}
-
 // Authenticate user with PocketBase
-async fn authenticate_with_pocketbase(email: &str, password: &str) -> Result<User, Box<dyn std::error::Error>> {
-    use serde::{Deserialize, Serialize};
-
-    #[derive(Debug, Serialize)]
-    struct AuthRequest {
-        identity: String,
-        password: String,
-    }
-
-    #[derive(Debug, Deserialize)]
-    struct AuthResponse {
-        token: String,
-        record: PocketBaseUser,
-    }
-
-    #[derive(Debug, Deserialize)]
-    struct PocketBaseUser {
-        id: String,
-        email: String,
-        username: Option<String>,
-        verified: bool,
-    }
-
-    // Create auth request
-    let auth_payload = AuthRequest {
-        identity: email.to_string(),
-        password: password.to_string(),
-    };
-
-    // Send authentication request to PocketBase
-    let client = reqwest::Client::new();
-    let response = client
-        .post("http://localhost:8090/api/collections/users/auth-with-password")
-        .header("Content-Type", "application/json")
-        .json(&auth_payload)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(format!("Authentication failed: {}", response.status()).into());
-    }
-
-    let auth_response: AuthResponse = response.json().await?;
+async fn authenticate_with_pocketbase(
+    pocketbase_url: &str,
+    email: &str,
+    password: &str,
+) -> Result<User, pocketbase::PocketBaseError> {
+    let client = pocketbase::PocketBaseClient::new(pocketbase_url);
+    let auth_record = client.auth_user(email, password).await?;
 
     // TODO: Check if user is verified (temporarily disabled for testing)
-    // if !auth_response.record.verified {
-    //     return Err("User email not verified".into());
-    // }
+    let fields = &auth_record.record.fields;
+    let email = fields
+        .get("email")
+        .and_then(|v| v.as_str())
+        .unwrap_or(email)
+        .to_string();
+    let username = ["username", "name"]
+        .iter()
+        .filter_map(|key| fields.get(*key).and_then(|v| v.as_str()))
+        .find(|name| !name.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| email.clone());
 
     // Convert PocketBase user to our User struct
-    let user = User {
-        id: auth_response.record.id,
-        username: auth_response.record.username.unwrap_or_else(|| auth_response.record.email.clone()),
-        email: auth_response.record.email,
+    Ok(User {
+        id: auth_record.record.id,
+        username,
+        email,
         role: "user".to_string(), // Default role
-    };
-
-    Ok(user)
+    })
 }
 
 #[cfg(test)]
@@ -522,4 +632,22 @@ mod tests {
         let refresh_token = auth_service.generate_refresh_token(&user);
         assert!(refresh_token.is_ok());
     }
+
+    #[test]
+    fn refresh_tokens_are_not_access_tokens() {
+        let auth_service = AuthService::new().unwrap();
+        let tokens = auth_service
+            .issue_tokens(User {
+                id: "test-id".to_string(),
+                username: "testuser".to_string(),
+                email: "test@example.com".to_string(),
+                role: "user".to_string(),
+            })
+            .unwrap();
+
+        assert_eq!(auth_service.verify_token(&tokens.access_token).unwrap().claims.sub, "test-id");
+        assert!(auth_service.verify_token(&tokens.refresh_token).is_err());
+        // Refresh token vẫn đổi được lấy cặp token mới
+        assert!(auth_service.rotate_refresh_token(&tokens.refresh_token).is_ok());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,317 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use axum::extract::ws::Message;
+use common_net::message::{Frame, FramePayload, StateMessage};
+use dashmap::DashMap;
+use serde::Serialize;
+
+use crate::outbox::OutboundKind;
+
+/// Chu kỳ flush counters sang metrics; top-K của `/admin/bandwidth` tính trên đúng khoảng này
+pub const BANDWIDTH_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// room_id của connection chưa join room nào
+const NO_ROOM: &str = "unknown";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl Direction {
+    const ALL: [Direction; 2] = [Direction::Sent, Direction::Received];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::Sent => "sent",
+            Direction::Received => "received",
+        }
+    }
+}
+
+/// Phân loại byte theo loại message: control/signaling, state (snapshot/delta/event), chat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Control,
+    State,
+    Chat,
+}
+
+impl MessageKind {
+    const ALL: [MessageKind; 3] = [MessageKind::Control, MessageKind::State, MessageKind::Chat];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MessageKind::Control => "control",
+            MessageKind::State => "state",
+            MessageKind::Chat => "chat",
+        }
+    }
+
+    /// Chat đi qua state plane dưới dạng event tên `chat`
+    pub fn of(frame: &Frame) -> Self {
+        match &frame.payload {
+            FramePayload::Control { .. } => MessageKind::Control,
+            FramePayload::State { message: StateMessage::Event { name, .. } } if name == "chat" => MessageKind::Chat,
+            FramePayload::State { .. } => MessageKind::State,
+        }
+    }
+
+    pub fn of_outbound(kind: OutboundKind) -> Self {
+        match kind {
+            OutboundKind::Control => MessageKind::Control,
+            OutboundKind::Keyframe | OutboundKind::State => MessageKind::State,
+        }
+    }
+}
+
+/// Kích thước payload của WS message (không tính header framing của WebSocket)
+pub fn message_len(message: &Message) -> u64 {
+    let len = match message {
+        Message::Text(text) => text.len(),
+        Message::Binary(bytes) | Message::Ping(bytes) | Message::Pong(bytes) => bytes.len(),
+        Message::Close(frame) => frame.as_ref().map_or(0, |frame| 2 + frame.reason.len()),
+    };
+    len as u64
+}
+
+/// Byte theo [direction][kind]: `pending` chờ flush, `last_interval` là kết quả lần flush gần nhất
+#[derive(Debug, Default)]
+struct Usage {
+    pending: [[AtomicU64; 3]; 2],
+    last_interval: [AtomicU64; 2],
+    total: [AtomicU64; 2],
+}
+
+impl Usage {
+    fn add(&self, direction: Direction, kind: MessageKind, bytes: u64) {
+        self.pending[direction as usize][kind as usize].fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Lấy pending ra và trả về byte theo [direction][kind]
+    fn take(&self) -> [[u64; 3]; 2] {
+        let mut taken = [[0; 3]; 2];
+        for direction in Direction::ALL {
+            for kind in MessageKind::ALL {
+                taken[direction as usize][kind as usize] =
+                    self.pending[direction as usize][kind as usize].swap(0, Ordering::Relaxed);
+            }
+            let bytes: u64 = taken[direction as usize].iter().sum();
+            self.last_interval[direction as usize].store(bytes, Ordering::Relaxed);
+            self.total[direction as usize].fetch_add(bytes, Ordering::Relaxed);
+        }
+        taken
+    }
+
+    fn report(&self, id: &str, room_id: Option<&str>) -> BandwidthEntry {
+        BandwidthEntry {
+            id: id.to_string(),
+            room_id: room_id.map(str::to_string),
+            bytes_sent: self.last_interval[Direction::Sent as usize].load(Ordering::Relaxed),
+            bytes_received: self.last_interval[Direction::Received as usize].load(Ordering::Relaxed),
+            total_bytes_sent: self.total[Direction::Sent as usize].load(Ordering::Relaxed),
+            total_bytes_received: self.total[Direction::Received as usize].load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ConnectionUsage {
+    room_id: Mutex<String>,
+    usage: Usage,
+}
+
+impl ConnectionUsage {
+    fn room_id(&self) -> String {
+        self.room_id.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Một dòng trong báo cáo `/admin/bandwidth`; `bytes_*` là của interval vừa flush
+#[derive(Debug, Clone, Serialize)]
+pub struct BandwidthEntry {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room_id: Option<String>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub total_bytes_sent: u64,
+    pub total_bytes_received: u64,
+}
+
+impl BandwidthEntry {
+    fn interval_bytes(&self) -> u64 {
+        self.bytes_sent + self.bytes_received
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BandwidthReport {
+    pub interval_secs: u64,
+    pub rooms: Vec<BandwidthEntry>,
+    pub connections: Vec<BandwidthEntry>,
+}
+
+/// Đếm byte WS theo connection và room. Đường gửi/nhận chỉ cộng atomic; metrics được cập nhật khi `flush`.
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthTracker {
+    connections: Arc<DashMap<String, Arc<ConnectionUsage>>>,
+    rooms: Arc<DashMap<String, Arc<Usage>>>,
+    kinds: Arc<Usage>,
+}
+
+impl BandwidthTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn connect(&self, connection_id: &str) {
+        self.connections.entry(connection_id.to_string()).or_insert_with(|| {
+            Arc::new(ConnectionUsage {
+                room_id: Mutex::new(NO_ROOM.to_string()),
+                usage: Usage::default(),
+            })
+        });
+    }
+
+    pub fn set_room(&self, connection_id: &str, room_id: &str) {
+        if let Some(connection) = self.connections.get(connection_id) {
+            *connection.room_id.lock().unwrap_or_else(|e| e.into_inner()) = room_id.to_string();
+        }
+    }
+
+    pub fn disconnect(&self, connection_id: &str) {
+        self.connections.remove(connection_id);
+    }
+
+    pub fn record(&self, connection_id: &str, direction: Direction, kind: MessageKind, bytes: u64) {
+        self.kinds.add(direction, kind, bytes);
+
+        let Some(connection) = self.connections.get(connection_id).map(|entry| entry.value().clone()) else {
+            return;
+        };
+        connection.usage.add(direction, kind, bytes);
+
+        let room_id = connection.room_id();
+        if room_id != NO_ROOM {
+            self.rooms.entry(room_id).or_default().add(direction, kind, bytes);
+        }
+    }
+
+    pub fn record_message(&self, connection_id: &str, direction: Direction, kind: MessageKind, message: &Message) {
+        self.record(connection_id, direction, kind, message_len(message));
+    }
+
+    /// Đẩy byte đã đếm sang metrics và chốt số liệu interval. Room không còn connection nào
+    /// coi như đã đóng: bị xoá khỏi tracker và khỏi `/metrics` để label không tăng mãi.
+    pub fn flush(&self) {
+        let taken = self.kinds.take();
+        for direction in Direction::ALL {
+            for kind in MessageKind::ALL {
+                let bytes = taken[direction as usize][kind as usize];
+                if bytes > 0 {
+                    crate::metrics::record_bytes_by_kind(direction.as_str(), kind.as_str(), bytes);
+                }
+            }
+        }
+
+        for connection in self.connections.iter() {
+            connection.usage.take();
+        }
+
+        let occupied: std::collections::HashSet<String> =
+            self.connections.iter().map(|connection| connection.room_id()).collect();
+        self.rooms.retain(|room_id, usage| {
+            let taken = usage.take();
+            for direction in Direction::ALL {
+                let bytes: u64 = taken[direction as usize].iter().sum();
+                if bytes > 0 {
+                    crate::metrics::record_room_bytes(direction.as_str(), room_id, bytes);
+                }
+            }
+
+            if occupied.contains(room_id) {
+                return true;
+            }
+            crate::metrics::forget_room_bytes(room_id);
+            false
+        });
+    }
+
+    /// Top-K room và connection theo tổng byte (gửi + nhận) của interval vừa flush
+    pub fn report(&self, limit: usize) -> BandwidthReport {
+        let mut rooms: Vec<BandwidthEntry> = self.rooms.iter().map(|room| room.report(room.key(), None)).collect();
+        let mut connections: Vec<BandwidthEntry> = self
+            .connections
+            .iter()
+            .map(|connection| connection.usage.report(connection.key(), Some(&connection.room_id())))
+            .collect();
+
+        for entries in [&mut rooms, &mut connections] {
+            entries.sort_by(|a, b| b.interval_bytes().cmp(&a.interval_bytes()).then_with(|| a.id.cmp(&b.id)));
+            entries.truncate(limit);
+        }
+
+        BandwidthReport {
+            interval_secs: BANDWIDTH_FLUSH_INTERVAL.as_secs(),
+            rooms,
+            connections,
+        }
+    }
+}
+
+pub fn spawn_flusher(tracker: BandwidthTracker) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(BANDWIDTH_FLUSH_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            tracker.flush();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn room_totals_follow_connections_and_labels_drop_on_close() {
+        let tracker = BandwidthTracker::new();
+        let room_id = format!("bw-room-{}", uuid::Uuid::new_v4());
+        tracker.connect("bw-a");
+        tracker.connect("bw-b");
+        tracker.set_room("bw-a", &room_id);
+        tracker.set_room("bw-b", &room_id);
+
+        tracker.record("bw-a", Direction::Sent, MessageKind::State, 300);
+        tracker.record("bw-b", Direction::Sent, MessageKind::Control, 100);
+        tracker.record("bw-b", Direction::Received, MessageKind::Chat, 40);
+        tracker.flush();
+
+        let report = tracker.report(10);
+        let room = report.rooms.iter().find(|r| r.id == room_id).expect("room entry");
+        assert_eq!((room.bytes_sent, room.bytes_received), (400, 40));
+        assert_eq!(report.connections[0].id, "bw-a");
+        let room_bytes = || crate::metrics::sample("gateway_bytes_sent_total", &[("direction", "sent"), ("room_id", &room_id)]);
+        assert_eq!(room_bytes(), Some(400.0));
+
+        // Interval kế tiếp không có traffic
+        tracker.flush();
+        let report = tracker.report(10);
+        assert_eq!(report.rooms.iter().find(|r| r.id == room_id).map(|r| r.bytes_sent), Some(0));
+
+        tracker.disconnect("bw-a");
+        tracker.disconnect("bw-b");
+        tracker.flush();
+        assert!(tracker.report(10).rooms.iter().all(|r| r.id != room_id));
+        assert_eq!(room_bytes(), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Origin được phép gọi gateway từ trình duyệt: CORS cho HTTP và kiểm tra `Origin` khi upgrade WebSocket.
+
+use std::sync::{Arc, RwLock};
+
+use axum::http::{header, HeaderValue, Method, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use tower::{Layer, Service};
+
+const ALLOW_METHODS: &str = "GET, POST, PUT, DELETE, OPTIONS";
+const ALLOW_HEADERS: &str = "Content-Type, Authorization, Accept, Cache-Control, Pragma, X-Request-Id, Idempotency-Key";
+/// Client trình duyệt đọc được request id để gửi kèm khi báo lỗi
+const EXPOSE_HEADERS: &str = "X-Request-Id";
+const MAX_AGE_SECS: &str = "86400";
+
+#[derive(Debug, PartialEq, Eq)]
+struct OriginList {
+    any: bool,
+    origins: Vec<String>,
+}
+
+/// Danh sách origin cho phép; `*` cho phép mọi origin (chỉ nên dùng khi dev).
+/// Các bản clone (router, middleware CORS) dùng chung danh sách nên `replace` có hiệu lực ngay khi reload config.
+#[derive(Debug, Clone)]
+pub struct AllowedOrigins {
+    list: Arc<RwLock<OriginList>>,
+}
+
+impl AllowedOrigins {
+    pub fn any() -> Self {
+        Self::from_list(OriginList { any: true, origins: Vec::new() })
+    }
+
+    pub fn new<I, S>(origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let origins: Vec<String> = origins
+            .into_iter()
+            .map(|origin| origin.into().trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        Self::from_list(OriginList { any: origins.iter().any(|origin| origin == "*"), origins })
+    }
+
+    fn from_list(list: OriginList) -> Self {
+        Self { list: Arc::new(RwLock::new(list)) }
+    }
+
+    /// Danh sách cách nhau bởi dấu phẩy, ví dụ `https://play.example.com,http://localhost:5173`
+    pub fn parse(raw: &str) -> Self {
+        Self::new(raw.split(','))
+    }
+
+    /// Thay danh sách bằng của `other` cho mọi bản clone đang dùng
+    pub fn replace(&self, other: &AllowedOrigins) {
+        if Arc::ptr_eq(&self.list, &other.list) {
+            return;
+        }
+        let next = other.list.read().expect("allowed origins lock");
+        let next = OriginList { any: next.any, origins: next.origins.clone() };
+        *self.list.write().expect("allowed origins lock") = next;
+    }
+
+    pub fn allows(&self, origin: &str) -> bool {
+        let list = self.list.read().expect("allowed origins lock");
+        list.any || list.origins.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin))
+    }
+
+    /// Request không có header `Origin` (client native, curl) không chịu ràng buộc của trình duyệt nên luôn qua
+    pub fn allows_request(&self, origin: Option<&HeaderValue>) -> bool {
+        match origin {
+            None => true,
+            Some(value) => value.to_str().is_ok_and(|origin| self.allows(origin)),
+        }
+    }
+
+    /// Giá trị `Access-Control-Allow-Origin` cho request có `origin`; None thì không gửi header
+    fn allow_origin_header(&self, origin: Option<&HeaderValue>) -> Option<HeaderValue> {
+        if self.list.read().expect("allowed origins lock").any {
+            return Some(HeaderValue::from_static("*"));
+        }
+        origin.filter(|origin| self.allows_request(Some(origin))).cloned()
+    }
+}
+
+impl PartialEq for AllowedOrigins {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.list, &other.list)
+            || *self.list.read().expect("allowed origins lock") == *other.list.read().expect("allowed origins lock")
+    }
+}
+
+impl Eq for AllowedOrigins {}
+
+impl Default for AllowedOrigins {
+    fn default() -> Self {
+        Self::any()
+    }
+}
+
+/// Layer gắn CORS header cho mọi response của router và tự trả lời preflight `OPTIONS`
+#[derive(Clone)]
+pub struct CorsMiddleware {
+    origins: AllowedOrigins,
+}
+
+impl CorsMiddleware {
+    pub fn new(origins: AllowedOrigins) -> Self {
+        Self { origins }
+    }
+}
+
+impl<S> Layer<S> for CorsMiddleware {
+    type Service = CorsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CorsService {
+            inner,
+            origins: self.origins.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CorsService<S> {
+    inner: S,
+    origins: AllowedOrigins,
+}
+
+impl<S, B> Service<Request<B>> for CorsService<S>
+where
+    S: Service<Request<B>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let allow_origin = self.origins.allow_origin_header(request.headers().get(header::ORIGIN));
+
+        if request.method() == Method::OPTIONS {
+            let mut response = StatusCode::OK.into_response();
+            apply_headers(&mut response, allow_origin);
+            response
+                .headers_mut()
+                .insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static(MAX_AGE_SECS));
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+            apply_headers(&mut response, allow_origin);
+            Ok(response)
+        })
+    }
+}
+
+fn apply_headers(response: &mut Response, allow_origin: Option<HeaderValue>) {
+    let headers = response.headers_mut();
+    match allow_origin {
+        Some(origin) => headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin),
+        None => headers.remove(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+    };
+    headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static(ALLOW_METHODS));
+    headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_static(ALLOW_HEADERS));
+    headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static(EXPOSE_HEADERS));
+    headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_origins_match_exactly_and_ignore_trailing_slash() {
+        let origins = AllowedOrigins::parse("https://play.example.com/, http://localhost:5173");
+        assert!(origins.allows("https://play.example.com"));
+        assert!(origins.allows("http://localhost:5173"));
+        assert!(!origins.allows("https://evil.example.com"));
+        assert!(!origins.allows("http://localhost:5174"));
+        assert!(origins.allows_request(None));
+
+        assert!(AllowedOrigins::parse("*").allows("https://anything.example"));
+        assert_eq!(AllowedOrigins::default(), AllowedOrigins::any());
+
+        let shared = origins.clone();
+        origins.replace(&AllowedOrigins::parse("https://beta.example.com"));
+        assert!(shared.allows("https://beta.example.com"));
+        assert!(!shared.allows("https://play.example.com"));
+    }
+}
@@ -0,0 +1,103 @@
+//! Drain khi deploy: gateway vẫn phục vụ connection đang mở nhưng trả 503 cho `/rooms/*` và connection mới
+//! (WS upgrade, long-poll join) để trận đang chơi kết thúc trước khi process dừng.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use axum::http::{Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use tower::{Layer, Service};
+
+/// Path bị từ chối khi đang drain
+fn is_refused(path: &str) -> bool {
+    path == "/rooms" || path.starts_with("/rooms/") || matches!(path, crate::WS_PATH | crate::POLL_JOIN_PATH)
+}
+
+/// Các bản clone dùng chung cờ drain
+#[derive(Debug, Clone, Default)]
+pub struct DrainState {
+    draining: Arc<AtomicBool>,
+}
+
+impl DrainState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    pub fn begin(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Layer trả 503 cho room và connection mới khi `DrainState` đã bật
+#[derive(Clone)]
+pub struct DrainLayer {
+    drain: DrainState,
+}
+
+impl DrainLayer {
+    pub fn new(drain: DrainState) -> Self {
+        Self { drain }
+    }
+}
+
+impl<S> Layer<S> for DrainLayer {
+    type Service = DrainService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DrainService { inner, drain: self.drain.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct DrainService<S> {
+    inner: S,
+    drain: DrainState,
+}
+
+impl<S, B> Service<Request<B>> for DrainService<S>
+where
+    S: Service<Request<B>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        if self.drain.is_draining() && is_refused(request.uri().path()) {
+            return Box::pin(async { Ok((StatusCode::SERVICE_UNAVAILABLE, "server is draining").into_response()) });
+        }
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_rooms_and_new_connections_are_refused() {
+        for path in ["/rooms/create", "/rooms/room-1", "/rooms/room-1/players", "/ws", "/poll/join"] {
+            assert!(is_refused(path), "{path}");
+        }
+        for path in ["/healthz", "/poll/recv", "/game/input", "/roomsx", "/api/leaderboard"] {
+            assert!(!is_refused(path), "{path}");
+        }
+
+        let drain = DrainState::new();
+        assert!(!drain.is_draining());
+        drain.clone().begin();
+        assert!(drain.is_draining());
+    }
+}
@@ -0,0 +1,164 @@
+//! Lỗi trả về từ handler HTTP của gateway: mỗi variant có status riêng và cùng một envelope
+//! `{ "error": { "code", "message" } }` để client xử lý lỗi một kiểu.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::metrics;
+use crate::scores::Rejection;
+use crate::types::FieldError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GatewayError {
+    /// Body/query sai schema hoặc không qua validate; `fields` liệt kê từng field lỗi
+    Validation(Vec<FieldError>),
+    /// Tham số hợp lệ về schema nhưng không dùng được
+    BadRequest(String),
+    /// Thiếu hoặc sai credentials/token
+    Unauthorized(String),
+    /// Đã xác thực nhưng không có quyền
+    Forbidden(String),
+    NotFound(String),
+    /// Trạng thái hiện tại không cho phép (room đầy, email đã đăng ký, worker từ chối...)
+    Conflict(String),
+    /// Điểm bị chặn bởi kiểm tra anti-abuse; status và `reason` lấy theo lý do từ chối
+    ScoreRejected(Rejection),
+    /// Worker, room-manager hoặc PocketBase trả lỗi / không gọi được
+    Upstream(String),
+    /// Worker từ chối credentials của gateway (token nội bộ, TLS/mTLS): cấu hình sai, retry không giúp được
+    UpstreamAuth(String),
+    /// Service phụ thuộc chưa sẵn sàng
+    Unavailable(String),
+    Internal(String),
+}
+
+impl GatewayError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            GatewayError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            GatewayError::ScoreRejected(rejection) => rejection.status(),
+            GatewayError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            GatewayError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            GatewayError::Forbidden(_) => StatusCode::FORBIDDEN,
+            GatewayError::NotFound(_) => StatusCode::NOT_FOUND,
+            GatewayError::Conflict(_) => StatusCode::CONFLICT,
+            GatewayError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            GatewayError::UpstreamAuth(_) => StatusCode::BAD_GATEWAY,
+            GatewayError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            GatewayError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Mã ổn định trong `error.code`, client rẽ nhánh theo mã này thay vì message
+    pub fn code(&self) -> &'static str {
+        match self {
+            GatewayError::Validation(_) => "validation_failed",
+            GatewayError::BadRequest(_) => "bad_request",
+            GatewayError::Unauthorized(_) => "unauthorized",
+            GatewayError::Forbidden(_) => "forbidden",
+            GatewayError::NotFound(_) => "not_found",
+            GatewayError::Conflict(_) => "conflict",
+            GatewayError::ScoreRejected(_) => "score_rejected",
+            GatewayError::Upstream(_) => "upstream_error",
+            GatewayError::UpstreamAuth(_) => "upstream_auth_failed",
+            GatewayError::Unavailable(_) => "unavailable",
+            GatewayError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            GatewayError::Validation(_) => "Request validation failed",
+            GatewayError::ScoreRejected(rejection) => rejection.message(),
+            GatewayError::BadRequest(message)
+            | GatewayError::Unauthorized(message)
+            | GatewayError::Forbidden(message)
+            | GatewayError::NotFound(message)
+            | GatewayError::Conflict(message)
+            | GatewayError::Upstream(message)
+            | GatewayError::UpstreamAuth(message)
+            | GatewayError::Unavailable(message)
+            | GatewayError::Internal(message) => message,
+        }
+    }
+
+    /// Body JSON của lỗi; field riêng của variant (fields, reason) nằm cạnh code/message
+    pub fn body(&self) -> serde_json::Value {
+        let mut error = serde_json::json!({ "code": self.code(), "message": self.message() });
+        match self {
+            GatewayError::Validation(fields) => error["fields"] = serde_json::json!(fields),
+            GatewayError::ScoreRejected(rejection) => error["reason"] = serde_json::json!(rejection.reason()),
+            _ => {}
+        }
+        serde_json::json!({ "error": error })
+    }
+}
+
+impl std::fmt::Display for GatewayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+impl std::error::Error for GatewayError {}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        if matches!(self, GatewayError::Validation(_)) {
+            metrics::record_invalid_request();
+        }
+        (self.status(), Json(self.body())).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn respond(error: GatewayError) -> (StatusCode, serde_json::Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.expect("body");
+        (status, serde_json::from_slice(&bytes).expect("json body"))
+    }
+
+    #[tokio::test]
+    async fn every_variant_maps_to_its_status_and_the_shared_envelope() {
+        let cases = [
+            (GatewayError::BadRequest("bad".into()), StatusCode::BAD_REQUEST, "bad_request"),
+            (GatewayError::Unauthorized("who".into()), StatusCode::UNAUTHORIZED, "unauthorized"),
+            (GatewayError::Forbidden("no".into()), StatusCode::FORBIDDEN, "forbidden"),
+            (GatewayError::NotFound("gone".into()), StatusCode::NOT_FOUND, "not_found"),
+            (GatewayError::Conflict("taken".into()), StatusCode::CONFLICT, "conflict"),
+            (GatewayError::Upstream("worker down".into()), StatusCode::BAD_GATEWAY, "upstream_error"),
+            (GatewayError::UpstreamAuth("bad token".into()), StatusCode::BAD_GATEWAY, "upstream_auth_failed"),
+            (GatewayError::Unavailable("later".into()), StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+            (GatewayError::Internal("oops".into()), StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+        ];
+        for (error, status, code) in cases {
+            let message = error.message().to_string();
+            let (got_status, body) = respond(error).await;
+            assert_eq!(got_status, status, "{body}");
+            assert_eq!(body, serde_json::json!({ "error": { "code": code, "message": message } }));
+        }
+    }
+
+    #[tokio::test]
+    async fn validation_and_score_rejection_carry_their_details() {
+        let (status, body) = respond(GatewayError::Validation(vec![FieldError::new("room_id", "must not be empty")])).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "validation_failed");
+        assert_eq!(body["error"]["fields"][0]["field"], "room_id");
+        assert_eq!(body["error"]["fields"][0]["message"], "must not be empty");
+
+        let (status, body) = respond(GatewayError::ScoreRejected(Rejection::RateLimited)).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "error": { "code": "score_rejected", "message": Rejection::RateLimited.message(), "reason": "rate_limited" }
+            })
+        );
+    }
+}
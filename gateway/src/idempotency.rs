@@ -0,0 +1,141 @@
+//! `Idempotency-Key` cho `POST /rooms/create`: client retry (timeout, rớt mạng) với cùng key thì nhận lại response
+//! của lần tạo đầu thay vì tạo thêm phòng. Key tính riêng theo user và hết hạn sau `KEY_TTL`.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use room_manager::CreateRoomResponse;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Key cũ hơn ngần này coi như chưa thấy, request cùng key sẽ tạo phòng mới
+pub const KEY_TTL: Duration = Duration::from_secs(10 * 60);
+pub const MAX_KEY_LEN: usize = 255;
+/// Dọn entry hết hạn khi cache vượt ngần này key
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Debug)]
+struct Record {
+    seen_at: Instant,
+    /// Body của request đầu, cùng key mà body khác thì không replay
+    fingerprint: String,
+    /// None khi request đầu còn đang tạo phòng
+    response: Option<CreateRoomResponse>,
+}
+
+/// Kết quả khi nhận một request có key
+#[derive(Debug)]
+pub enum Claim {
+    /// Key mới: gọi room-manager rồi `Pending::complete`
+    New(Pending),
+    /// Đã tạo xong với key này, trả lại response cũ
+    Replay(CreateRoomResponse),
+    /// Request đầu với key này chưa xong
+    InProgress,
+    /// Key đã dùng cho body khác
+    Mismatch,
+}
+
+/// Các bản clone dùng chung cache
+#[derive(Debug, Clone, Default)]
+pub struct IdempotencyCache {
+    entries: Arc<DashMap<(String, String), Record>>,
+}
+
+impl IdempotencyCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `scope` là user gửi request nên hai user dùng trùng key không thấy response của nhau
+    pub fn begin(&self, scope: &str, key: &str, fingerprint: &str, now: Instant) -> Claim {
+        if self.entries.len() > PRUNE_THRESHOLD {
+            self.entries.retain(|_, record| now.duration_since(record.seen_at) < KEY_TTL);
+        }
+        let id = (scope.to_string(), key.to_string());
+        match self.entries.entry(id.clone()) {
+            Entry::Occupied(entry) if now.duration_since(entry.get().seen_at) < KEY_TTL => {
+                let record = entry.get();
+                if record.fingerprint != fingerprint {
+                    Claim::Mismatch
+                } else {
+                    record.response.clone().map_or(Claim::InProgress, Claim::Replay)
+                }
+            }
+            entry => {
+                let record = Record { seen_at: now, fingerprint: fingerprint.to_string(), response: None };
+                match entry {
+                    Entry::Occupied(mut expired) => {
+                        expired.insert(record);
+                    }
+                    Entry::Vacant(vacant) => {
+                        vacant.insert(record);
+                    }
+                }
+                Claim::New(Pending { cache: self.clone(), id: Some(id) })
+            }
+        }
+    }
+}
+
+/// Key đang giữ chỗ; drop mà chưa `complete` (lỗi, client huỷ request) thì bỏ key để lần retry sau tạo lại
+#[derive(Debug)]
+pub struct Pending {
+    cache: IdempotencyCache,
+    id: Option<(String, String)>,
+}
+
+impl Pending {
+    /// Lưu response để các lần retry trong `KEY_TTL` nhận lại
+    pub fn complete(mut self, response: &CreateRoomResponse) {
+        if let Some(id) = self.id.take() {
+            if let Some(mut record) = self.cache.entries.get_mut(&id) {
+                record.response = Some(response.clone());
+            }
+        }
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.cache.entries.remove_if(&id, |_, record| record.response.is_none());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn created(room_id: &str) -> CreateRoomResponse {
+        CreateRoomResponse { room_id: room_id.to_string(), success: true, error: None, invite_code: None }
+    }
+
+    #[test]
+    fn keys_replay_per_user_until_they_expire() {
+        let cache = IdempotencyCache::new();
+        let now = Instant::now();
+
+        let Claim::New(pending) = cache.begin("alice", "k1", "body", now) else { panic!("first use is new") };
+        assert!(matches!(cache.begin("alice", "k1", "body", now), Claim::InProgress));
+        pending.complete(&created("room-1"));
+
+        assert!(matches!(cache.begin("alice", "k1", "body", now), Claim::Replay(ref r) if r.room_id == "room-1"));
+        assert!(matches!(cache.begin("alice", "k1", "other body", now), Claim::Mismatch));
+        assert!(matches!(cache.begin("bob", "k1", "body", now), Claim::New(_)));
+        assert!(matches!(cache.begin("alice", "k1", "body", now + KEY_TTL), Claim::New(_)));
+    }
+
+    #[test]
+    fn abandoned_keys_can_be_retried() {
+        let cache = IdempotencyCache::new();
+        let now = Instant::now();
+
+        let Claim::New(pending) = cache.begin("alice", "k1", "body", now) else { panic!("first use is new") };
+        drop(pending);
+        assert!(matches!(cache.begin("alice", "k1", "body", now), Claim::New(_)));
+    }
+}
@@ -0,0 +1,251 @@
+//! Đo RTT từng WS connection bằng `ControlMessage::Ping`/`Pong` của gateway và báo lên worker
+//! để worker đưa vào snapshot (`rtt_ms` của player).
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use proto::worker::v1::{PlayerLatency, UpdatePlayerLatencyRequest};
+use tracing::debug;
+
+use crate::{worker_client::WorkerRpcClient, WebSocketRegistry};
+
+/// Chu kỳ gateway gửi Ping cho mỗi connection
+pub const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(2);
+/// Chu kỳ gom RTT gửi lên worker; mỗi player tối đa một lần mỗi chu kỳ
+pub const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+/// Pong về trễ hơn mức này vẫn tính nhưng chỉ ghi 10s
+pub const MAX_RTT_MS: u32 = 10_000;
+/// Trọng số mẫu mới trong EWMA
+const RTT_SMOOTHING: f64 = 0.2;
+/// Số Ping chưa có Pong được nhớ; nonce cũ hơn coi như mất
+const OUTSTANDING_PINGS: usize = 8;
+
+#[derive(Debug, Default)]
+struct LatencyState {
+    outstanding: VecDeque<u64>,
+    smoothed_ms: Option<f64>,
+    reported_ms: Option<u32>,
+    /// Worker báo peer không có player entity (spectator...), thôi gửi RTT cho tới khi đổi room
+    not_player: bool,
+}
+
+/// RTT của một WS connection. Clone dùng chung state giữa ws session và registry.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyTracker(Arc<Mutex<LatencyState>>);
+
+impl LatencyTracker {
+    /// Nonce cho Ping tiếp theo: thời điểm gửi tính bằng micro giây unix
+    pub fn next_ping_nonce(&self) -> u64 {
+        let nonce = now_micros();
+        let mut state = self.0.lock().unwrap();
+        if state.outstanding.len() == OUTSTANDING_PINGS {
+            state.outstanding.pop_front();
+        }
+        state.outstanding.push_back(nonce);
+        nonce
+    }
+
+    /// Pong từ client; trả về RTT (ms) của mẫu này, None nếu nonce không phải do gateway gửi
+    pub fn on_pong(&self, nonce: u64) -> Option<u32> {
+        self.on_pong_at(nonce, now_micros())
+    }
+
+    fn on_pong_at(&self, nonce: u64, now_micros: u64) -> Option<u32> {
+        let mut state = self.0.lock().unwrap();
+        let index = state.outstanding.iter().position(|sent| *sent == nonce)?;
+        state.outstanding.remove(index);
+
+        let rtt_ms = (now_micros.saturating_sub(nonce) / 1000).min(MAX_RTT_MS as u64) as u32;
+        state.smoothed_ms = Some(match state.smoothed_ms {
+            Some(smoothed) => smoothed + RTT_SMOOTHING * (rtt_ms as f64 - smoothed),
+            None => rtt_ms as f64,
+        });
+        crate::metrics::record_peer_rtt(rtt_ms);
+        Some(rtt_ms)
+    }
+
+    pub fn smoothed_ms(&self) -> Option<u32> {
+        self.0.lock().unwrap().smoothed_ms.map(|ms| ms.round() as u32)
+    }
+
+    /// RTT cần báo lên worker: có mẫu, khác lần báo trước và peer là player
+    fn take_report(&self) -> Option<u32> {
+        let mut state = self.0.lock().unwrap();
+        if state.not_player {
+            return None;
+        }
+        let rtt_ms = state.smoothed_ms?.round() as u32;
+        if state.reported_ms == Some(rtt_ms) {
+            return None;
+        }
+        state.reported_ms = Some(rtt_ms);
+        Some(rtt_ms)
+    }
+
+    fn mark_not_player(&self) {
+        self.0.lock().unwrap().not_player = true;
+    }
+
+    /// Connection đổi room: room mới có thể có player entity, báo lại từ đầu
+    pub fn reset_room(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.not_player = false;
+        state.reported_ms = None;
+    }
+}
+
+fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+/// Task gom RTT của mọi connection đang ở trong room thành một `UpdatePlayerLatency` mỗi chu kỳ.
+/// Bật khi có WS connection, tự dừng khi registry rỗng.
+#[derive(Clone)]
+pub struct LatencyReporter {
+    worker_client: WorkerRpcClient,
+    ws_registry: WebSocketRegistry,
+    /// Chu kỳ Ping (ms), dùng chung giữa các bản clone nên đổi được khi reload config
+    ping_interval_ms: Arc<AtomicU64>,
+    report_interval: Duration,
+    running: Arc<AtomicBool>,
+}
+
+impl LatencyReporter {
+    pub fn new(worker_client: WorkerRpcClient, ws_registry: WebSocketRegistry) -> Self {
+        Self {
+            worker_client,
+            ws_registry,
+            ping_interval_ms: Arc::new(AtomicU64::new(DEFAULT_PING_INTERVAL.as_millis() as u64)),
+            report_interval: DEFAULT_REPORT_INTERVAL,
+            running: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn with_intervals(mut self, ping_interval: Duration, report_interval: Duration) -> Self {
+        self.set_ping_interval(ping_interval);
+        self.report_interval = report_interval;
+        self
+    }
+
+    pub fn ping_interval(&self) -> Duration {
+        Duration::from_millis(self.ping_interval_ms.load(Ordering::Relaxed))
+    }
+
+    /// Connection đang mở chuyển sang chu kỳ mới sau lần Ping kế tiếp; chặn dưới 1ms
+    pub fn set_ping_interval(&self, ping_interval: Duration) {
+        self.ping_interval_ms.store((ping_interval.as_millis() as u64).max(1), Ordering::Relaxed);
+    }
+
+    /// Bật task report nếu chưa chạy. Gọi sau khi connection đã vào registry.
+    pub fn ensure_running(&self) {
+        if !self.running.swap(true, Ordering::SeqCst) {
+            let reporter = self.clone();
+            tokio::spawn(async move { reporter.run().await });
+        }
+    }
+
+    async fn run(mut self) {
+        let mut ticker = tokio::time::interval(self.report_interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            ticker.tick().await;
+
+            if self.ws_registry.is_empty() {
+                self.running.store(false, Ordering::SeqCst);
+                // Connection insert ngay trước khi tắt cờ đã thấy cờ còn bật nên không bật task mới: kiểm tra lại
+                if self.ws_registry.is_empty() || self.running.swap(true, Ordering::SeqCst) {
+                    debug!("latency reporter stopped, no ws clients");
+                    return;
+                }
+            }
+            // Một peer nhiều connection: chỉ gửi một giá trị
+            let by_player: HashMap<_, _> = self
+                .ws_registry
+                .filter_map(|_, conn| {
+                    if conn.room_id == "unknown" {
+                        return None;
+                    }
+                    let rtt_ms = conn.latency.take_report()?;
+                    Some((conn.peer_id.clone(), PlayerLatency {
+                        room_id: conn.room_id.clone(),
+                        player_id: conn.peer_id.clone(),
+                        rtt_ms,
+                    }))
+                })
+                .into_iter()
+                .collect();
+            let latencies: Vec<_> = by_player.into_values().collect();
+            if latencies.is_empty() {
+                continue;
+            }
+
+            let response = match self
+                .worker_client
+                .update_player_latency(UpdatePlayerLatencyRequest { latencies })
+                .await
+            {
+                Ok(response) => response.into_inner(),
+                Err(e) => {
+                    debug!(error = %e, "gateway: update_player_latency failed");
+                    continue;
+                }
+            };
+            if response.not_players.is_empty() {
+                continue;
+            }
+            let not_players: HashSet<_> = response.not_players.into_iter().collect();
+            for latency in self.ws_registry.filter_map(|_, conn| not_players.contains(&conn.peer_id).then(|| conn.latency.clone())) {
+                latency.mark_not_player();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_nonce_is_ignored() {
+        let tracker = LatencyTracker::default();
+        let nonce = tracker.next_ping_nonce();
+        assert_eq!(tracker.on_pong_at(nonce + 1, nonce + 50_000), None);
+        assert_eq!(tracker.smoothed_ms(), None);
+
+        assert_eq!(tracker.on_pong_at(nonce, nonce + 50_000), Some(50));
+        // Pong lặp lại cùng nonce không được tính hai lần
+        assert_eq!(tracker.on_pong_at(nonce, nonce + 90_000), None);
+        assert_eq!(tracker.smoothed_ms(), Some(50));
+    }
+
+    #[test]
+    fn rtt_is_capped_and_smoothed() {
+        let tracker = LatencyTracker::default();
+        let first = tracker.next_ping_nonce();
+        assert_eq!(tracker.on_pong_at(first, first + 60_000_000), Some(MAX_RTT_MS));
+        assert_eq!(tracker.smoothed_ms(), Some(MAX_RTT_MS));
+
+        let second = tracker.next_ping_nonce();
+        assert_eq!(tracker.on_pong_at(second, second), Some(0));
+        assert_eq!(tracker.smoothed_ms(), Some(8_000));
+
+        assert_eq!(tracker.take_report(), Some(8_000));
+        assert_eq!(tracker.take_report(), None, "unchanged rtt is not reported again");
+        tracker.mark_not_player();
+        let third = tracker.next_ping_nonce();
+        tracker.on_pong_at(third, third);
+        assert_eq!(tracker.take_report(), None);
+        tracker.reset_room();
+        assert_eq!(tracker.take_report(), Some(6_400));
+    }
+}
@@ -6,28 +6,47 @@ use tokio::sync::oneshot;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use axum::{extract::{State, Path, Query}, http::{StatusCode, Method, HeaderValue, HeaderMap}, response::{IntoResponse, Response}, routing::{get, post, delete}, Json, Router};
+use axum::{extract::{rejection::JsonRejection, State, Path, Query}, http::{StatusCode, Method, HeaderValue, HeaderMap}, response::{IntoResponse, Response}, routing::{get, post, delete}, Json, Router};
 use chrono::{DateTime, Utc};
 use hyper::{header::AUTHORIZATION, server::conn::AddrIncoming};
-use once_cell::sync::Lazy;
-use prometheus::{register_int_counter_vec, register_int_gauge, register_int_gauge_vec, Encoder, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder};
-use tracing::error;
-use metrics::{counter, histogram};
-use tower_http::cors::{Any, CorsLayer};
-use tower::{Layer, Service};
+use tracing::{error, Instrument};
 use tonic::transport::Endpoint;
 
+use common_net::game_modes;
+use common_net::health::{self, DependencyStatus};
 use common_net::message::{self, ControlMessage, Frame, FramePayload, StateMessage};
-use common_net::transport::{GameTransport, TransportKind, WebRtcTransport};
+use common_net::transport::{GameTransport, IceCandidate, TransportError, TransportKind, WebRtcInbound, WebRtcTransport};
 use common_net::quantization::QuantizationConfig;
 use common_net::snapshot::{encode_snapshot, decode_snapshot, encode_delta, decode_delta};
 
+pub mod analytics;
 pub mod auth;
+pub mod bandwidth;
+pub mod cors;
+pub mod drain;
+pub mod error;
+pub mod idempotency;
+pub mod latency;
+pub mod longpoll;
+pub mod metrics;
+pub mod outbox;
+pub mod quic;
+pub mod rate_limit;
+pub mod registry;
+pub mod reliable;
+pub mod request_id;
+pub mod rtc;
+pub mod room_client;
+pub mod room_detail;
+pub mod scores;
+pub mod snapshots;
 pub mod types;
 pub mod worker_client;
 
-use proto::worker::v1::worker_client::WorkerClient;
-use room_manager::{RoomManagerState, GameMode, RoomStatus};
+use room_manager::{GameMode, Room, RoomStatus};
+
+pub use error::GatewayError;
+pub use registry::{PollRegistry, SignalingState, TransportRegistry, WebRTCSessionRegistry, WebSocketRegistry};
 
 pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
@@ -38,13 +57,53 @@ pub struct AppState {
     pub webrtc_sessions: WebRTCSessionRegistry,
     pub ws_registry: WebSocketRegistry,
     pub transport_registry: TransportRegistry,
-    pub worker_client: WorkerClient<tonic::transport::Channel>,
+    /// Connection long-poll theo poll_token; outbox của chúng cũng nằm trong `ws_registry`
+    pub poll_registry: PollRegistry,
+    pub worker_client: worker_client::WorkerRpcClient,
+    pub auth_config: auth::AuthConfig,
     pub auth_service: auth::AuthService,
-    pub room_manager: std::sync::Arc<tokio::sync::RwLock<RoomManagerState>>,
+    pub room_manager: room_client::RoomManagerClient,
+    /// Debug: echo lại text frame thay vì parse (GATEWAY_WS_ECHO=1)
+    pub ws_echo: bool,
+    pub ws_outbox: outbox::OutboxConfig,
+    pub snapshots: snapshots::SnapshotBroadcaster,
+    pub latency: latency::LatencyReporter,
+    pub bandwidth: bandwidth::BandwidthTracker,
+    /// QUIC connection đã xác thực, ws session của peer lấy làm transport
+    pub quic_sessions: quic::QuicSessions,
+    /// Origin được phép cho CORS và WebSocket upgrade
+    pub allowed_origins: cors::AllowedOrigins,
+    /// Số HTTP request mỗi giây của một IP, đổi được khi reload config
+    pub rate_limiter: rate_limit::RateLimiter,
+    /// Token tĩnh cho `/admin/*` bên cạnh JWT role admin, đổi được khi reload config
+    pub admin_token: auth::AdminToken,
+    /// Peer connection WebRTC gateway đã trả lời offer, chờ ws session của peer nhận DataChannel
+    pub rtc_peers: rtc::ServerPeers,
+    /// Kết quả ghép room-manager + worker của `GET /rooms/:room_id`
+    pub room_details: room_detail::RoomDetailCache,
+    /// Response của `/rooms/create` theo (user, Idempotency-Key), để request retry không tạo thêm phòng
+    pub room_creates: idempotency::IdempotencyCache,
+    /// Bật khi server bắt đầu drain: room và connection mới bị trả 503
+    pub drain: drain::DrainState,
+    /// Check worker/room-manager/PocketBase cho `/readyz`, kết quả cache vài giây
+    pub readiness: health::Readiness,
+    /// Leaderboard theo season trong PocketBase; None (không set POCKETBASE_URL) thì dùng `memory_leaderboard`
+    pub leaderboard: Option<services::persistence::PocketBaseStore>,
+    /// Điểm tốt nhất giữ trong process khi không có PocketBase
+    pub memory_leaderboard: scores::MemoryLeaderboard,
+    /// Số lần submit điểm mỗi giờ của client, trong giai đoạn chuyển sang điểm do worker gửi
+    pub score_limiter: scores::SubmitLimiter,
+    /// Session event gửi sang services cho funnel analytics; tắt khi không set ANALYTICS_INGEST_URL
+    pub analytics: analytics::SessionAnalytics,
 }
 
+/// Liveness: process còn chạy thì 200, không kiểm tra dependency
 pub const HEALTHZ_PATH: &str = "/healthz";
+/// Readiness: 503 khi worker hoặc room-manager không tới được
+pub const READYZ_PATH: &str = "/readyz";
 pub const VERSION_PATH: &str = "/version";
+/// Số dòng tối đa một lần gọi `/api/leaderboard`
+const LEADERBOARD_MAX_LIMIT: usize = 100;
 pub const METRICS_PATH: &str = "/metrics";
 pub const WS_PATH: &str = "/ws";
 pub const GAME_INPUT_PATH: &str = "/game/input";
@@ -52,85 +111,207 @@ pub const GAME_JOIN_PATH: &str = "/game/join";
 pub const GAME_LEAVE_PATH: &str = "/game/leave";
 pub const CHAT_SEND_PATH: &str = "/chat/send";
 pub const CHAT_HISTORY_PATH: &str = "/chat/history";
+/// Long-poll cho client không dùng được WS lẫn WebRTC: join cần Bearer token, recv/send dùng poll_token
+pub const POLL_JOIN_PATH: &str = "/poll/join";
+pub const POLL_RECV_PATH: &str = "/poll/recv";
+pub const POLL_SEND_PATH: &str = "/poll/send";
+pub const RTC_OFFER_PATH: &str = "/rtc/offer";
+pub const RTC_ANSWER_PATH: &str = "/rtc/answer";
+pub const RTC_ICE_PATH: &str = "/rtc/ice";
+pub const RTC_SESSIONS_PATH: &str = "/rtc/sessions";
+pub const ADMIN_BANDWIDTH_PATH: &str = "/admin/bandwidth";
+/// System message server.announcement tới một room hoặc mọi room trên worker, chỉ token role admin
+pub const ADMIN_ANNOUNCE_PATH: &str = "/admin/announce";
 
 // Room Manager paths
 pub const ROOMS_CREATE_PATH: &str = "/rooms/create";
 pub const ROOMS_JOIN_PATH: &str = "/rooms/join";
 pub const ROOMS_LIST_PATH: &str = "/rooms/list";
+/// Số phòng một trang của `/rooms/list` khi client không gửi `limit`
+pub const ROOMS_LIST_DEFAULT_LIMIT: usize = 50;
+pub const ROOMS_LIST_MAX_LIMIT: usize = 100;
 pub const ROOMS_ASSIGN_PATH: &str = "/rooms/assign";
+/// Trả slot trong phòng room-manager (host rời thì room-manager chuyển host)
+pub const ROOMS_LEAVE_PATH: &str = "/rooms/leave";
+/// Player trong phòng room-manager; cùng tên tham số với `ROOM_DETAIL_PATH`
+pub const ROOMS_PLAYERS_PATH: &str = "/rooms/:room_id/players";
+pub const ROOMS_RESOLVE_INVITE_PATH: &str = "/rooms/resolve_invite";
+/// Host quản lý invite code phòng private: POST tạo code mới, DELETE thu hồi
+pub const ROOMS_INVITE_PATH: &str = "/rooms/invite";
+/// Host hoặc admin đuổi/ban player, cần Bearer token
+pub const ROOMS_KICK_PATH: &str = "/rooms/kick";
+/// Player bật/tắt ready trong lobby của worker, cần Bearer token
+pub const ROOMS_READY_PATH: &str = "/rooms/ready";
+/// Room trên worker kèm danh sách player và trạng thái ready
+pub const ROOM_DETAIL_PATH: &str = "/rooms/:room_id";
+
+// Party paths, đều cần Bearer token; player là user trong JWT
+pub const PARTY_CREATE_PATH: &str = "/party/create";
+/// Leader tạo invite code mới cho party
+pub const PARTY_INVITE_PATH: &str = "/party/invite";
+pub const PARTY_JOIN_PATH: &str = "/party/join";
+/// Leader rời thì party giải tán
+pub const PARTY_LEAVE_PATH: &str = "/party/leave";
+/// Leader xếp cả party vào một phòng, cùng đội
+pub const PARTY_QUEUE_PATH: &str = "/party/queue";
+
+// Tournament paths, trừ GET chi tiết đều cần Bearer token
+pub const TOURNAMENTS_CREATE_PATH: &str = "/tournaments/create";
+pub const TOURNAMENTS_REGISTER_PATH: &str = "/tournaments/register";
+/// Host hoặc admin chốt danh sách và mở phòng vòng 1
+pub const TOURNAMENTS_START_PATH: &str = "/tournaments/start";
+/// Host hoặc admin báo người thắng một trận
+pub const TOURNAMENTS_REPORT_PATH: &str = "/tournaments/report";
+pub const TOURNAMENT_DETAIL_PATH: &str = "/tournaments/:id";
+
+/// Lý do mặc định gửi trong `ControlMessage::Kicked` khi host không ghi
+const DEFAULT_KICK_REASON: &str = "Removed from room by host";
+
+const ROOM_METRICS_RECONCILE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Lấy danh sách phòng từ room-manager rồi cập nhật gauges; room-manager không tới được thì giữ giá trị cũ
+async fn update_room_gauges(room_manager: &room_client::RoomManagerClient) {
+    // Gauges đếm cả phòng private
+    let request = room_manager::ListRoomsRequest {
+        game_mode: None,
+        status: None,
+        include_private: true,
+        admin: true,
+        ..Default::default()
+    };
+    match room_manager.list_rooms(&request).await {
+        Ok(response) => apply_room_gauges(&response.rooms),
+        Err(e) => tracing::debug!(error = %e, "gateway: room gauges not refreshed"),
+    }
+}
 
-static HTTP_REQUESTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
-    register_int_counter_vec!(
-        "gateway_http_requests_total",
-        "Tß╗òng sß╗æ HTTP request theo route",
-        &["path"]
-    )
-    .expect("register gateway_http_requests_total")
-});
-
-static TRANSPORT_CONNECTIONS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
-    register_int_counter_vec!(
-        "gateway_transport_connections_total",
-        "Tß╗òng sß╗æ kß║┐t nß╗æi transport theo loß║íi",
-        &["transport_type", "fallback_used"]
-    )
-    .expect("register gateway_transport_connections_total")
-});
-
-static WEBRTC_CONNECTIONS_CURRENT: Lazy<IntGaugeVec> = Lazy::new(|| {
-    register_int_gauge_vec!(
-        "gateway_webrtc_connections_current",
-        "Sß╗æ kß║┐t nß╗æi WebRTC hiß╗çn tß║íi",
-        &["status"]
-    )
-    .expect("register gateway_webrtc_connections_current")
-});
+/// Cập nhật gauge phòng / người chơi / gateway_room_players từ danh sách phòng của room manager.
+/// Phòng đã Closed/Finished (hoặc bị xoá) bị bỏ khỏi gateway_room_players để tránh label tăng không giới hạn.
+fn apply_room_gauges(all_rooms: &[Room]) {
+    let active: HashMap<String, u32> = all_rooms
+        .iter()
+        .filter(|room| !matches!(room.status, RoomStatus::Closed | RoomStatus::Finished))
+        .map(|room| (room.id.clone(), room.current_players))
+        .collect();
+    let (rooms, players) = room_manager::room_counts(all_rooms);
+    metrics::record_room_occupancy(rooms, players, active);
+}
 
-static ROOMS_ACTIVE: Lazy<IntGauge> = Lazy::new(|| {
-    register_int_gauge!(
-        "gateway_rooms_active",
-        "Số lượng phòng chơi đang hoạt động"
-    )
-    .expect("register gateway_rooms_active")
-});
+/// Reconcile gauges định kỳ phòng khi handler bỏ sót (heartbeat cleanup, lỗi giữa chừng, ...)
+fn spawn_room_metrics_reconciler(room_manager: room_client::RoomManagerClient) {
+    tokio::spawn(async move {
+        let start = tokio::time::Instant::now() + ROOM_METRICS_RECONCILE_INTERVAL;
+        let mut ticker = tokio::time::interval_at(start, ROOM_METRICS_RECONCILE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            update_room_gauges(&room_manager).await;
+        }
+    });
+}
 
-static PLAYERS_IN_ROOMS: Lazy<IntGauge> = Lazy::new(|| {
-    register_int_gauge!(
-        "gateway_players_in_rooms",
-        "Số lượng người chơi đang ở trong phòng"
-    )
-    .expect("register gateway_players_in_rooms")
-});
+/// Session WebRTC/signaling không có hoạt động lâu hơn TTL này thì bị xoá
+const DEFAULT_RTC_SESSION_TTL: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// TTL đọc từ GATEWAY_RTC_SESSION_TTL_SECS
+fn rtc_session_ttl_from_env() -> std::time::Duration {
+    std::env::var("GATEWAY_RTC_SESSION_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|secs| *secs > 0)
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(DEFAULT_RTC_SESSION_TTL)
+}
 
-// CORS helper function
-fn add_cors_headers(response: impl IntoResponse) -> axum::response::Response {
-    use axum::response::{Response, IntoResponse};
-    use axum::http::{HeaderMap, HeaderValue};
+/// Xoá session WebRTC/signaling có `last_activity` cũ hơn `ttl`, trả về số session bị xoá.
+/// Session đang Connected bị xoá thì giảm gauge gateway_webrtc_connections_current tương ứng.
+async fn reap_idle_sessions(
+    signaling_sessions: &SignalingSessions,
+    webrtc_sessions: &WebRTCSessionRegistry,
+    ttl: std::time::Duration,
+) -> usize {
+    // TTL quá lớn (tràn thời gian) thì không có session nào hết hạn
+    let Some(cutoff) = chrono::Duration::from_std(ttl).ok().and_then(|ttl| Utc::now().checked_sub_signed(ttl)) else {
+        return 0;
+    };
+    let mut reaped = 0;
 
-    let mut response = response.into_response();
-    let headers = response.headers_mut();
+    webrtc_sessions.retain(|_, session| {
+        if session.last_activity >= cutoff {
+            return true;
+        }
+        if session.status == WebRTCSessionStatus::Connected {
+            metrics::record_webrtc_disconnected();
+        }
+        reaped += 1;
+        false
+    });
 
-    headers.insert("Access-Control-Allow-Origin", HeaderValue::from_static("*"));
-    headers.insert("Access-Control-Allow-Methods", HeaderValue::from_static("GET, POST, PUT, DELETE, OPTIONS"));
-    headers.insert("Access-Control-Allow-Headers", HeaderValue::from_static("Content-Type, Authorization, Accept"));
+    {
+        let mut sessions = signaling_sessions.write().await;
+        let before = sessions.len();
+        sessions.retain(|_, session| session.last_activity >= cutoff);
+        reaped += before - sessions.len();
+    }
 
-    response
+    if reaped > 0 {
+        metrics::record_webrtc_sessions_reaped(reaped as u64);
+        tracing::info!(reaped, "reaped idle webrtc/signaling sessions");
+    }
+    reaped
 }
 
-// Handle CORS preflight requests
-async fn handle_cors_preflight() -> impl IntoResponse {
-    use axum::http::{HeaderMap, HeaderValue, StatusCode};
-    let mut headers = HeaderMap::new();
-    headers.insert("Access-Control-Allow-Origin", HeaderValue::from_static("*"));
-    headers.insert("Access-Control-Allow-Methods", HeaderValue::from_static("GET, POST, PUT, DELETE, OPTIONS"));
-    headers.insert("Access-Control-Allow-Headers", HeaderValue::from_static("Content-Type, Authorization, Accept"));
-    (StatusCode::OK, headers)
+fn spawn_session_reaper(signaling_sessions: SignalingSessions, webrtc_sessions: WebRTCSessionRegistry, ttl: std::time::Duration) {
+    // Quét vài lần trong một TTL để session không sống quá TTL quá lâu
+    let sweep_interval = (ttl / 4).clamp(std::time::Duration::from_secs(1), std::time::Duration::from_secs(60));
+    tokio::spawn(async move {
+        let start = tokio::time::Instant::now() + sweep_interval;
+        let mut ticker = tokio::time::interval_at(start, sweep_interval);
+        loop {
+            ticker.tick().await;
+            reap_idle_sessions(&signaling_sessions, &webrtc_sessions, ttl).await;
+        }
+    });
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct GatewaySettings {
     pub bind_addr: SocketAddr,
     pub worker_endpoint: String,
+    /// Origin trình duyệt được gọi HTTP và mở /ws; `*` cho phép tất cả
+    #[serde(default = "default_allowed_origins")]
+    pub allowed_origins: Vec<String>,
+    /// Địa chỉ UDP cho listener QUIC; không đặt thì gateway chỉ có WebRTC/WebSocket
+    #[serde(default)]
+    pub quic_bind_addr: Option<SocketAddr>,
+    /// Số HTTP request tối đa mỗi giây của một IP client; 0 là không giới hạn
+    #[serde(default)]
+    pub rate_limit_per_second: u32,
+    /// STUN server cho peer connection WebRTC của gateway (`stun:host:port`); rỗng thì chỉ có host candidate
+    #[serde(default)]
+    pub stun_servers: Vec<String>,
+    /// Token tĩnh cho `/admin/*`; None thì chỉ JWT role admin được gọi
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Chu kỳ Ping đo RTT của WS connection (ms); None thì theo GATEWAY_WS_PING_INTERVAL_MS hoặc mặc định 2s
+    #[serde(default)]
+    pub ws_ping_interval_ms: Option<u64>,
+    /// Token gửi trong `x-internal-token` tới worker; None thì theo WORKER_RPC_TOKEN
+    #[serde(default)]
+    pub worker_token: Option<String>,
+    /// TLS/mTLS tới worker; None là plaintext, chỉ dựa vào token
+    #[serde(default)]
+    pub worker_tls: Option<worker_client::WorkerTlsSettings>,
+}
+
+fn default_allowed_origins() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+/// GATEWAY_STUN_SERVERS: danh sách cách nhau bởi dấu phẩy
+fn stun_servers_from_env() -> Vec<String> {
+    std::env::var("GATEWAY_STUN_SERVERS")
+        .map(|raw| raw.split(',').map(|url| url.trim().to_string()).filter(|url| !url.is_empty()).collect())
+        .unwrap_or_default()
 }
 
 impl GatewaySettings {
@@ -141,9 +322,36 @@ impl GatewaySettings {
             .map_err(|e| Box::new(e) as BoxError)?;
         let worker_endpoint = std::env::var("WORKER_ENDPOINT")
             .unwrap_or_else(|_| "http://127.0.0.1:50051".to_string());
+        let allowed_origins = std::env::var("GATEWAY_ALLOWED_ORIGINS")
+            .map(|raw| raw.split(',').map(|origin| origin.trim().to_string()).filter(|o| !o.is_empty()).collect())
+            .unwrap_or_else(|_| default_allowed_origins());
+        let quic_bind_addr = std::env::var("GATEWAY_QUIC_BIND_ADDR")
+            .ok()
+            .map(|raw| raw.parse())
+            .transpose()
+            .map_err(|e| Box::new(e) as BoxError)?;
+        let rate_limit_per_second = std::env::var("GATEWAY_RATE_LIMIT_PER_SECOND")
+            .ok()
+            .map(|raw| raw.parse())
+            .transpose()
+            .map_err(|e| Box::new(e) as BoxError)?
+            .unwrap_or(0);
+        let ws_ping_interval_ms = std::env::var("GATEWAY_WS_PING_INTERVAL_MS")
+            .ok()
+            .map(|raw| raw.parse())
+            .transpose()
+            .map_err(|e| Box::new(e) as BoxError)?;
         Ok(Self {
             bind_addr,
             worker_endpoint,
+            allowed_origins,
+            quic_bind_addr,
+            rate_limit_per_second,
+            stun_servers: stun_servers_from_env(),
+            admin_token: std::env::var("GATEWAY_ADMIN_TOKEN").ok().filter(|token| !token.is_empty()),
+            ws_ping_interval_ms,
+            worker_token: None,
+            worker_tls: worker_client::WorkerTlsSettings::from_env(),
         })
     }
 }
@@ -152,7 +360,24 @@ impl GatewaySettings {
 pub struct GatewayConfig {
     pub bind_addr: SocketAddr,
     pub worker_endpoint: String,
+    pub allowed_origins: cors::AllowedOrigins,
+    pub quic_bind_addr: Option<SocketAddr>,
+    pub rate_limit_per_second: u32,
+    pub stun_servers: Vec<String>,
+    pub admin_token: Option<String>,
+    pub ws_ping_interval: Option<std::time::Duration>,
+    /// Token nội bộ tới worker; None thì theo WORKER_RPC_TOKEN
+    pub worker_token: Option<String>,
+    pub worker_tls: Option<worker_client::WorkerTlsSettings>,
+    /// REST API của room-manager; None thì đọc ROOM_MANAGER_URL
+    pub room_manager_url: Option<String>,
+    /// Shared secret với room-manager, cũng là secret worker gửi điểm lên; None thì theo ROOM_MANAGER_INTERNAL_SECRET
+    pub internal_secret: Option<String>,
     pub ready_tx: Option<oneshot::Sender<SocketAddr>>,
+    /// Settings mới khi reload config; chỉ origin, rate limit, admin token và chu kỳ Ping được áp dụng lúc đang chạy
+    pub reload_rx: Option<tokio::sync::watch::Receiver<GatewaySettings>>,
+    /// Tín hiệu bắt đầu drain; None thì gateway chỉ dừng khi nhận shutdown
+    pub drain_rx: Option<common_net::shutdown::ShutdownReceiver>,
 }
 
 impl GatewayConfig {
@@ -160,7 +385,19 @@ impl GatewayConfig {
         Self {
             bind_addr: s.bind_addr,
             worker_endpoint: s.worker_endpoint,
+            allowed_origins: cors::AllowedOrigins::new(s.allowed_origins),
+            quic_bind_addr: s.quic_bind_addr,
+            rate_limit_per_second: s.rate_limit_per_second,
+            stun_servers: s.stun_servers,
+            admin_token: s.admin_token,
+            ws_ping_interval: s.ws_ping_interval_ms.map(std::time::Duration::from_millis),
+            worker_token: s.worker_token,
+            worker_tls: s.worker_tls,
+            room_manager_url: None,
+            internal_secret: None,
             ready_tx: None,
+            reload_rx: None,
+            drain_rx: None,
         }
     }
 }
@@ -299,24 +536,88 @@ pub enum PeerConnectionState {
     Failed,
 }
 
-type SignalingState = Arc<RwLock<HashMap<String, RoomSignaling>>>;
 type SignalingSessions = Arc<RwLock<HashMap<String, crate::types::SignalingSession>>>;
-type WebRTCSessionRegistry = Arc<RwLock<HashMap<String, WebRTCSession>>>;
 
 #[derive(Debug)]
 pub struct WebSocketConnection {
     pub peer_id: String,
     pub room_id: String,
-    pub sender: tokio::sync::mpsc::UnboundedSender<axum::extract::ws::Message>,
+    pub outbound: OutboundSequence,
+    pub outbox: outbox::WsOutbox,
+    pub latency: latency::LatencyTracker,
 }
 
-pub type WebSocketRegistry = Arc<RwLock<HashMap<String, WebSocketConnection>>>; // key: connection_id
-
 pub struct TransportConnection {
     pub peer_id: String,
     pub room_id: String,
-    pub transport: Box<dyn GameTransport + Send + Sync>,
+    pub outbound: OutboundSequence,
+    pub transport: registry::SharedTransport,
+    pub selection: TransportSelection,
+    /// Cấp lúc join; connection mới phải đưa đúng nonce này trong `MigrateTransport` mới lấy được room binding
+    pub migration_nonce: Option<String>,
+}
+
+/// Nhãn transport dùng trong metrics
+fn transport_label(kind: TransportKind) -> &'static str {
+    match kind {
+        TransportKind::Quic => "quic",
+        TransportKind::WebRtc => "webrtc",
+        TransportKind::WebSocket | TransportKind::WebTransport => "websocket",
+        TransportKind::LongPoll => "longpoll",
+    }
+}
+
+/// Kết quả chọn transport của một connection, client nhận được qua `ControlMessage::TransportSelected`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransportSelection {
+    pub kind: TransportKind,
     pub fallback_used: bool,
+    pub reason: &'static str,
+}
+
+impl TransportSelection {
+    /// Peer đã có QUIC connection xác thực sẵn
+    pub const QUIC_AUTHENTICATED: &'static str = "quic_authenticated";
+    /// DataChannel WebRTC lập được
+    pub const WEBRTC_CONNECTED: &'static str = "webrtc_connected";
+    /// Không có QUIC, peer chưa gửi offer WebRTC: dùng chính WebSocket này
+    pub const WEBRTC_UNAVAILABLE: &'static str = "webrtc_unavailable";
+    /// Đã trả lời offer nhưng DataChannel không mở kịp timeout: dùng chính WebSocket này
+    pub const WEBRTC_FAILED: &'static str = "webrtc_failed";
+    /// Client mở connection qua `/poll/join` vì không dùng được WS lẫn UDP
+    pub const LONGPOLL_REQUESTED: &'static str = "longpoll_requested";
+
+    fn control_message(&self) -> ControlMessage {
+        ControlMessage::TransportSelected {
+            kind: self.kind,
+            fallback_used: self.fallback_used,
+            reason: self.reason.to_string(),
+        }
+    }
+}
+
+impl TransportConnection {
+    /// Connection chưa join room nào
+    pub fn new(
+        peer_id: String,
+        outbound: OutboundSequence,
+        transport: Box<dyn GameTransport + Send + Sync>,
+        selection: TransportSelection,
+    ) -> Self {
+        Self {
+            peer_id,
+            room_id: "unknown".to_string(),
+            outbound,
+            transport: Arc::new(tokio::sync::Mutex::new(transport)),
+            selection,
+            migration_nonce: None,
+        }
+    }
+
+    /// Kind của transport đã chọn, đọc không cần khoá transport
+    pub fn kind(&self) -> TransportKind {
+        self.selection.kind
+    }
 }
 
 impl std::fmt::Debug for TransportConnection {
@@ -324,29 +625,113 @@ impl std::fmt::Debug for TransportConnection {
         f.debug_struct("TransportConnection")
             .field("peer_id", &self.peer_id)
             .field("room_id", &self.room_id)
-            .field("transport_kind", &self.transport.kind())
-            .field("fallback_used", &self.fallback_used)
+            .field("transport_kind", &self.kind())
+            .field("selection", &self.selection)
             .finish()
     }
 }
 
-pub type TransportRegistry = Arc<RwLock<HashMap<String, TransportConnection>>>; // key: connection_id
+/// Độ dài tối đa (ký tự) của một tin chat gửi qua WS
+const MAX_WS_CHAT_CHARS: usize = 256;
+
+/// Số (peer_id, seq) gần nhất mỗi connection nhớ để bỏ frame trùng
+const INBOUND_DEDUPE_WINDOW: usize = 64;
+
+/// Bộ đếm sequence outbound của một connection. Mọi frame gateway gửi cho connection
+/// đều được đóng số ở đây để client phát hiện frame trùng hoặc sai thứ tự.
+/// Kèm theo là sequence cao nhất đã nhận từ client (gửi lại trong `ack`) và các control frame chờ client ack
+/// qua `acks`.
+#[derive(Debug, Clone, Default)]
+pub struct OutboundSequence {
+    next: Arc<std::sync::atomic::AtomicU32>,
+    received: Arc<std::sync::atomic::AtomicU32>,
+    pub unacked: reliable::ControlRetransmitter,
+}
+
+impl OutboundSequence {
+    /// Gán sequence kế tiếp (bắt đầu từ 1) và ack cho frame; timestamp giữ nguyên nếu đã có
+    /// (giờ worker tạo snapshot), còn 0 thì lấy giờ hiện tại. Frame đi qua WS outbox của session
+    pub fn stamp(&self, frame: Frame) -> Frame {
+        self.stamp_via(frame, reliable::SentVia::Outbox)
+    }
+
+    /// Như `stamp`, control frame cần ack được gửi lại qua `via`
+    pub fn stamp_via(&self, mut frame: Frame, via: reliable::SentVia) -> Frame {
+        frame.sequence = self.next.fetch_add(1, std::sync::atomic::Ordering::Relaxed).wrapping_add(1);
+        frame.ack = self.received.load(std::sync::atomic::Ordering::Relaxed);
+        if frame.timestamp_ms == 0 {
+            frame.timestamp_ms = now_millis();
+        }
+        self.unacked.track(&frame, via);
+        frame
+    }
+
+    /// Frame client gửi lên: ghi nhận sequence của nó và xử lý `acks` cho các control frame đang chờ
+    pub fn on_receive(&self, frame: &Frame) {
+        self.received.fetch_max(frame.sequence, std::sync::atomic::Ordering::Relaxed);
+        self.unacked.on_acks(&frame.acks);
+    }
+}
+
+/// Cửa sổ (peer_id, seq) đã nhận gần đây của một connection
+#[derive(Debug)]
+pub struct FrameDedupe {
+    capacity: usize,
+    order: std::collections::VecDeque<(String, u32)>,
+    seen: std::collections::HashSet<(String, u32)>,
+}
+
+impl FrameDedupe {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: std::collections::VecDeque::with_capacity(capacity),
+            seen: std::collections::HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// true nếu frame này đã thấy trong cửa sổ. Sequence 0 là client cũ không đánh số nên luôn cho qua.
+    pub fn is_duplicate(&mut self, peer_id: &str, sequence: u32) -> bool {
+        if sequence == 0 {
+            return false;
+        }
+        let key = (peer_id.to_string(), sequence);
+        if self.seen.contains(&key) {
+            return true;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(key.clone());
+        self.seen.insert(key);
+        false
+    }
+}
 
 // Helper function to extract user_id from JWT token in Authorization header
-async fn extract_user_id_from_request(
-    request: &axum::http::Request<axum::body::Body>,
+fn extract_user_id_from_headers(
+    headers: &HeaderMap,
     auth_service: &auth::AuthService,
-) -> Result<String, String> {
-    let headers = request.headers();
+) -> Result<String, GatewayError> {
+    extract_claims_from_headers(headers, auth_service).map(|claims| claims.sub)
+}
+
+/// Claims của Bearer token, cho handler cần cả role
+fn extract_claims_from_headers(
+    headers: &HeaderMap,
+    auth_service: &auth::AuthService,
+) -> Result<auth::Claims, GatewayError> {
     let auth_header = headers
         .get(AUTHORIZATION)
         .and_then(|h| h.to_str().ok())
-        .and_then(|s| if s.starts_with("Bearer ") { Some(&s[7..]) } else { None });
+        .and_then(|s| s.strip_prefix("Bearer "));
 
     if let Some(token) = auth_header {
         match auth_service.verify_token(token) {
             Ok(token_data) => {
-                return Ok(token_data.claims.sub);
+                return Ok(token_data.claims);
             }
             Err(e) => {
                 tracing::warn!("Invalid token: {}", e);
@@ -354,20 +739,58 @@ async fn extract_user_id_from_request(
         }
     }
 
-    Err("No valid token found".to_string())
+    Err(authentication_failed())
+}
+
+fn authentication_failed() -> GatewayError {
+    GatewayError::Unauthorized("Authentication failed".to_string())
+}
+
+/// peer_id trong body phải khớp với user trong JWT, tránh giả mạo peer khác
+fn peer_mismatch() -> GatewayError {
+    GatewayError::Forbidden("peer_id does not match token".to_string())
+}
+
+/// Lỗi gọi room-manager/worker: log rồi trả 502 kèm việc đang làm dở
+fn upstream_error(action: &str, err: impl std::fmt::Display) -> GatewayError {
+    error!("Failed to {}: {}", action, err);
+    GatewayError::Upstream(format!("Failed to {}: {}", action, err))
+}
+
+/// Lỗi gọi worker; worker từ chối credentials của gateway (token, TLS/mTLS) thì tách khỏi 502 thường
+fn worker_error(action: &str, status: &tonic::Status) -> GatewayError {
+    if worker_client::is_auth_failure(status) {
+        error!(%status, "gateway: worker rejected gateway credentials while trying to {}", action);
+        return GatewayError::UpstreamAuth(format!("Worker rejected gateway credentials, cannot {action}"));
+    }
+    error!(error = %status, "gateway: failed to {}", action);
+    GatewayError::Upstream(format!("Failed to {action}"))
+}
+
+fn now_millis() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
 }
 
-// Handler cho /rtc/offer (c├│ state)
+// Handler cho /rtc/offer
 async fn handle_rtc_offer(
     State(state): State<AppState>,
-    request: axum::http::Request<axum::body::Body>,
+    headers: HeaderMap,
     Json(req): Json<RtcOfferRequest>,
-) -> Json<RtcOfferResponse> {
-    // Extract user_id from JWT token
-    let user_id = "anonymous".to_string();
+) -> Result<Json<RtcOfferResponse>, GatewayError> {
+    let user_id = extract_user_id_from_headers(&headers, &state.auth_service)?;
+    if req.peer_id != user_id {
+        return Err(peer_mismatch());
+    }
+
+    // Offer gửi cho gateway: answer của peer connection phía server, ws session mở sau đó nhận DataChannel
+    let answer = state
+        .rtc_peers
+        .accept_offer(&user_id, &req.sdp)
+        .await
+        .map_err(|err| GatewayError::BadRequest(err.to_string()))?;
 
     // Create or update WebRTC session
-    let session_id = format!("webrtc_{}", chrono::Utc::now().timestamp_millis());
+    let session_id = format!("webrtc_{}", uuid::Uuid::new_v4());
     let webrtc_session = WebRTCSession {
         session_id: session_id.clone(),
         room_id: req.room_id.clone(),
@@ -379,286 +802,409 @@ async fn handle_rtc_offer(
     };
 
     // Store WebRTC session
-    {
-        let mut sessions = state.webrtc_sessions.write().await;
-        sessions.insert(session_id.clone(), webrtc_session);
-    }
+    state.webrtc_sessions.insert(webrtc_session);
 
     // Update legacy signaling state for compatibility
-    let mut map = state.signaling.write().await;
-    let room = map.entry(req.room_id.clone()).or_default();
-    let peer = room.peers.entry(req.peer_id.clone()).or_insert_with(|| PeerConnection::new(req.peer_id.clone()));
-    peer.offer = Some(req.sdp.clone());
+    state.signaling.update_peer(&req.room_id, &user_id, |peer| peer.offer = Some(req.sdp.clone()));
+    metrics::record_webrtc_signal(metrics::WebRtcSignal::Offer);
+    metrics::record_webrtc_signal(metrics::WebRtcSignal::Answer);
 
-    // TODO: Relay offer tß╗¢i c├íc peers kh├íc trong room qua transport abstraction
-    Json(RtcOfferResponse {
+    Ok(Json(RtcOfferResponse {
         success: true,
         session_id: Some(session_id),
-        sdp: Some(req.sdp),
+        sdp: Some(answer),
         error: None,
-    })
+    }))
 }
 
-// Handler cho /rtc/ice (c├│ state)
+// Handler cho /rtc/ice
 async fn handle_rtc_ice(
     State(state): State<AppState>,
-    request: axum::http::Request<axum::body::Body>,
+    headers: HeaderMap,
     Json(ice): Json<RtcIceCandidate>,
-) -> Json<RtcAnswerResponse> {
-    // Extract user_id from JWT token
-    let user_id = "anonymous".to_string();
+) -> Result<Json<RtcAnswerResponse>, GatewayError> {
+    let user_id = extract_user_id_from_headers(&headers, &state.auth_service)?;
+    if ice.peer_id != user_id {
+        return Err(peer_mismatch());
+    }
 
-    // Update WebRTC session activity
-    {
-        let mut sessions = state.webrtc_sessions.write().await;
-        // Find session by room_id and user_id (ICE candidates are associated with sessions)
-        for session in sessions.values_mut() {
-            if session.room_id == ice.room_id && session.user_id == user_id {
-                session.last_activity = chrono::Utc::now();
-                break;
-            }
-        }
+    // Update WebRTC session activity (ICE candidates are associated with sessions by room_id and user_id)
+    state.webrtc_sessions.touch(&ice.room_id, &user_id);
+
+    let candidate = IceCandidate {
+        candidate: ice.candidate.clone(),
+        sdp_mid: ice.sdp_mid.clone(),
+        sdp_mline_index: ice.sdp_mline_index,
+    };
+    if let Some(Err(err)) = state.rtc_peers.add_ice_candidate(&user_id, candidate).await {
+        return Err(GatewayError::BadRequest(err.to_string()));
     }
 
     // Update legacy signaling state for compatibility
-    let mut map = state.signaling.write().await;
-    let room = map.entry(ice.room_id.clone()).or_default();
-    let peer = room.peers.entry(ice.peer_id.clone()).or_insert_with(|| PeerConnection::new(ice.peer_id.clone()));
-    peer.ice_candidates.push(ice);
+    let room_id = ice.room_id.clone();
+    state.signaling.update_peer(&room_id, &user_id, |peer| peer.ice_candidates.push(ice));
+    metrics::record_webrtc_signal(metrics::WebRtcSignal::IceCandidate);
 
-    Json(RtcAnswerResponse {
+    Ok(Json(RtcAnswerResponse {
         success: true,
         error: None,
-    })
+    }))
 }
 
-// Handler cho /rtc/answer (c├│ state)
+// Handler cho /rtc/answer
 async fn handle_rtc_answer(
     State(state): State<AppState>,
-    request: axum::http::Request<axum::body::Body>,
+    headers: HeaderMap,
     Json(req): Json<RtcAnswerRequest>,
-) -> Json<RtcAnswerResponse> {
-    // Extract user_id from JWT token
-    let user_id = "anonymous".to_string();
+) -> Result<Json<RtcAnswerResponse>, GatewayError> {
+    let user_id = extract_user_id_from_headers(&headers, &state.auth_service)?;
+    if req.peer_id != user_id {
+        return Err(peer_mismatch());
+    }
+
+    // Update legacy signaling state for compatibility
+    let target_found = state
+        .signaling
+        .update_existing_peer(&req.room_id, &req.target_peer_id, |target_peer| target_peer.answer = Some(req.sdp.clone()))
+        .is_some();
+
+    if !target_found {
+        return Err(GatewayError::NotFound("Target peer not found".to_string()));
+    }
 
     // Update WebRTC session status
-    {
-        let mut sessions = state.webrtc_sessions.write().await;
-        if let Some(session) = sessions.get_mut(&req.session_id) {
-            session.status = WebRTCSessionStatus::Connected;
-            session.last_activity = chrono::Utc::now();
-        }
+    if state.webrtc_sessions.mark_connected(&req.session_id) {
+        metrics::record_webrtc_connected();
     }
 
-    // Update legacy signaling state for compatibility
-    let mut map = state.signaling.write().await;
-    if let Some(room) = map.get_mut(&req.room_id) {
-        if let Some(target_peer) = room.peers.get_mut(&req.target_peer_id) {
-            target_peer.answer = Some(req.sdp);
-            // TODO: Relay answer tß╗¢i target peer
-            return Json(RtcAnswerResponse {
-                success: true,
-                error: None,
-            });
+    // Relay answer tới target peer
+    send_to_transport(&state.transport_registry, &state.bandwidth, &req.target_peer_id, message::Frame::control(
+        0, now_millis(), ControlMessage::WebRtcAnswer {
+            room_id: req.room_id.clone(),
+            peer_id: user_id,
+            target_peer_id: req.target_peer_id.clone(),
+            sdp: req.sdp,
         }
-    }
+    )).await;
+    metrics::record_webrtc_signal(metrics::WebRtcSignal::Answer);
 
-    Json(RtcAnswerResponse {
-        success: false,
-        error: Some("Target peer not found".to_string()),
-    })
+    Ok(Json(RtcAnswerResponse {
+        success: true,
+        error: None,
+    }))
 }
 
-// CORS middleware layer
-#[derive(Clone)]
-pub struct CorsMiddleware;
-
-impl<S> Layer<S> for CorsMiddleware {
-    type Service = CorsService<S>;
+pub async fn build_router(worker_endpoint: String) -> Router {
+    build_router_with_state(build_app_state(worker_endpoint).await)
+}
 
-    fn layer(&self, inner: S) -> Self::Service {
-        CorsService { inner }
-    }
+pub async fn build_app_state(worker_endpoint: String) -> AppState {
+    build_app_state_with(worker_endpoint, room_client::RoomManagerClient::from_env()).await
 }
 
-#[derive(Clone)]
-pub struct CorsService<S> {
-    inner: S,
-}
-
-impl<S> Service<axum::http::Request<axum::body::Body>> for CorsService<S>
-where
-    S: Service<axum::http::Request<axum::body::Body>, Response = axum::http::Response<axum::body::Body>> + Send + 'static,
-    S::Future: Send + 'static,
-{
-    type Response = S::Response;
-    type Error = S::Error;
-    type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
-
-    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
-        self.inner.poll_ready(cx)
-    }
-
-    fn call(&mut self, request: axum::http::Request<axum::body::Body>) -> Self::Future {
-        // Handle preflight requests
-        if request.method() == axum::http::Method::OPTIONS {
-            let mut response = axum::http::Response::builder()
-                .status(axum::http::StatusCode::OK)
-                .header("Access-Control-Allow-Origin", "*")
-                .header("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS")
-                .header("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept")
-                .header("Access-Control-Max-Age", "86400")
-                .body(axum::body::Body::empty())
-                .unwrap();
-            return Box::pin(async move { Ok(response) });
-        }
-
-        let future = self.inner.call(request);
-        Box::pin(async move {
-            let mut response = future.await?;
-            let headers = response.headers_mut();
-            headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-            headers.insert("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS".parse().unwrap());
-            headers.insert("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept".parse().unwrap());
-            Ok(response)
-        })
-    }
+/// Như `build_app_state` nhưng room-manager do caller chỉ định thay vì đọc ROOM_MANAGER_URL
+pub async fn build_app_state_with(worker_endpoint: String, room_manager: room_client::RoomManagerClient) -> AppState {
+    let worker_client = lazy_worker_client(&worker_endpoint, None, proto::auth::internal_token_from_env().as_deref());
+    build_app_state_with_worker(worker_client, room_manager).await
 }
 
-// Helper function to add CORS headers to responses (deprecated - using CORS middleware instead)
-// Helper function to create CORS-enabled response
-fn cors_response<T: IntoResponse>(response: T) -> impl IntoResponse {
-    let mut resp = response.into_response();
-    let headers = resp.headers_mut();
-    headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-    headers.insert("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS".parse().unwrap());
-    headers.insert("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept".parse().unwrap());
-    resp
+/// Kết nối lazy: worker chưa lên thì gateway vẫn chạy, lời gọi worker lỗi tới khi worker sẵn sàng
+fn lazy_worker_client(
+    worker_endpoint: &str,
+    tls: Option<&worker_client::WorkerTlsSettings>,
+    token: Option<&str>,
+) -> worker_client::WorkerRpcClient {
+    match worker_client::endpoint(worker_endpoint, tls) {
+        Ok(endpoint) => worker_client::with_token(endpoint.connect_lazy(), token),
+        Err(err) => {
+            tracing::warn!(%err, "gateway: worker endpoint không hợp lệ, mọi lời gọi worker sẽ lỗi");
+            worker_client::with_token(Endpoint::from_static("http://127.0.0.1:0").connect_lazy(), token)
+        }
+    }
 }
 
-pub async fn build_router(worker_endpoint: String) -> Router {
-    let signaling_state: SignalingState = Arc::new(RwLock::new(HashMap::new()));
+/// Như `build_app_state_with` với client worker đã dựng sẵn (token, TLS)
+pub async fn build_app_state_with_worker(
+    worker_client: worker_client::WorkerRpcClient,
+    room_manager: room_client::RoomManagerClient,
+) -> AppState {
+    let signaling_state = SignalingState::new();
     let signaling_sessions: SignalingSessions = Arc::new(RwLock::new(HashMap::new()));
-    let webrtc_sessions: WebRTCSessionRegistry = Arc::new(RwLock::new(HashMap::new()));
-    let ws_registry: WebSocketRegistry = Arc::new(RwLock::new(HashMap::new()));
-    let transport_registry: TransportRegistry = Arc::new(RwLock::new(HashMap::new()));
-    let auth_service = auth::AuthService::new().expect("Failed to create auth service");
-
-    // Initialize Room Manager
-    let pocketbase_url = std::env::var("POCKETBASE_URL").unwrap_or_else(|_| "http://localhost:8090".to_string());
-    let room_manager = std::sync::Arc::new(tokio::sync::RwLock::new(
-        RoomManagerState::new(&pocketbase_url).expect("Failed to create room manager")
-    ));
-
-    // Configure CORS layer - allow all origins for development
-    // let cors_layer = CorsLayer::new()
-    //     .allow_origin(Any)
-    //     .allow_methods(Any)
-    //     .allow_headers(Any)
-    //     .allow_credentials(true);
-
-    // Create worker client - temporarily disabled for authentication testing
-    // TODO: Re-enable when worker is available
-    let worker_client = {
-        tracing::warn!("Worker client disabled for authentication-only testing");
-        // For now, create a dummy client that will fail on use but allows gateway to run
-        // This is a temporary solution to allow authentication testing
-
-        // Create a non-functional client for testing
-        tracing::warn!("Creating dummy worker client for authentication-only mode");
-        // Use a dummy endpoint that won't work but allows compilation
-        let dummy_endpoint = Endpoint::from_static("http://127.0.0.1:0");
-        // Create a dummy channel that won't actually connect
-        let dummy_channel = dummy_endpoint.connect_lazy();
-        WorkerClient::new(dummy_channel)
-    };
-
-    let state = AppState {
+    let webrtc_sessions = WebRTCSessionRegistry::new();
+    let ws_registry = WebSocketRegistry::new();
+    let transport_registry = TransportRegistry::new();
+    let auth_config = auth::AuthConfig::from_env();
+    let auth_service = auth::AuthService::from_config(&auth_config);
+
+    // Room Manager chạy như service riêng, gateway chỉ gọi REST API của nó
+    spawn_room_metrics_reconciler(room_manager.clone());
+    spawn_session_reaper(signaling_sessions.clone(), webrtc_sessions.clone(), rtc_session_ttl_from_env());
+
+    let bandwidth = bandwidth::BandwidthTracker::new();
+    bandwidth::spawn_flusher(bandwidth.clone());
+    let snapshots = snapshots::SnapshotBroadcaster::new(worker_client.clone(), ws_registry.clone())
+        .with_bandwidth(bandwidth.clone());
+    let latency = latency::LatencyReporter::new(worker_client.clone(), ws_registry.clone()).with_intervals(
+        std::env::var("GATEWAY_WS_PING_INTERVAL_MS")
+            .ok()
+            .and_then(|raw| raw.parse().ok())
+            .map_or(latency::DEFAULT_PING_INTERVAL, std::time::Duration::from_millis),
+        latency::DEFAULT_REPORT_INTERVAL,
+    );
+    let user_store = auth_config
+        .pocketbase_users
+        .then(|| pocketbase::PocketBaseClient::new(&auth_config.pocketbase_url));
+    let readiness = readiness(worker_client.clone(), room_manager.clone(), user_store);
+    let leaderboard = auth_config
+        .pocketbase_users
+        .then(|| services::persistence::PocketBaseStore::new(&auth_config.pocketbase_url));
+
+    AppState {
         signaling: signaling_state,
         signaling_sessions,
         webrtc_sessions,
         ws_registry,
         transport_registry,
+        poll_registry: PollRegistry::new(),
         worker_client,
+        auth_config,
         auth_service,
         room_manager,
-    };
+        ws_echo: std::env::var("GATEWAY_WS_ECHO").ok().as_deref() == Some("1"),
+        ws_outbox: outbox::OutboxConfig::from_env(),
+        snapshots,
+        latency,
+        bandwidth,
+        quic_sessions: quic::QuicSessions::default(),
+        allowed_origins: std::env::var("GATEWAY_ALLOWED_ORIGINS")
+            .map(|raw| cors::AllowedOrigins::parse(&raw))
+            .unwrap_or_default(),
+        rate_limiter: rate_limit::RateLimiter::default(),
+        admin_token: auth::AdminToken::new(std::env::var("GATEWAY_ADMIN_TOKEN").ok()),
+        rtc_peers: rtc::ServerPeers::new(stun_servers_from_env()),
+        room_details: room_detail::RoomDetailCache::new(),
+        room_creates: idempotency::IdempotencyCache::new(),
+        drain: drain::DrainState::new(),
+        readiness,
+        leaderboard,
+        memory_leaderboard: scores::MemoryLeaderboard::default(),
+        score_limiter: scores::SubmitLimiter::from_env(),
+        analytics: analytics::SessionAnalytics::spawn(analytics::AnalyticsConfig::from_env()),
+    }
+}
+
+/// Worker được ping bằng ListActiveRooms. User lưu trong PocketBase thì gateway tự gọi `/api/health` và PocketBase
+/// là bắt buộc; không thì trạng thái PocketBase lấy từ `/readyz` của room-manager và chỉ là phụ
+fn readiness(
+    worker_client: worker_client::WorkerRpcClient,
+    room_manager: room_client::RoomManagerClient,
+    user_store: Option<pocketbase::PocketBaseClient>,
+) -> health::Readiness {
+    let pocketbase_client = room_manager.clone();
+    let readiness = health::Readiness::new("gateway")
+        .required("worker", move || {
+            let mut worker = worker_client.clone();
+            async move {
+                match worker.list_active_rooms(proto::worker::v1::ListActiveRoomsRequest {}).await {
+                    Ok(_) => DependencyStatus::Ok,
+                    Err(_) => DependencyStatus::Down,
+                }
+            }
+        })
+        .required("room_manager", move || {
+            let room_manager = room_manager.clone();
+            async move {
+                match room_manager.readiness().await {
+                    Ok((true, _)) => DependencyStatus::Ok,
+                    _ => DependencyStatus::Down,
+                }
+            }
+        });
+    match user_store {
+        Some(user_store) => readiness.required("pocketbase", move || {
+            let user_store = user_store.clone();
+            async move {
+                match user_store.health().await {
+                    Ok(_) => DependencyStatus::Ok,
+                    Err(_) => DependencyStatus::Down,
+                }
+            }
+        }),
+        None => readiness.optional("pocketbase", move || {
+            let room_manager = pocketbase_client.clone();
+            async move {
+                match room_manager.readiness().await {
+                    Ok((_, dependencies)) if dependencies.get("pocketbase").map(String::as_str) == Some("ok") => {
+                        DependencyStatus::Ok
+                    }
+                    _ => DependencyStatus::Down,
+                }
+            }
+        }),
+    }
+}
 
+pub fn build_router_with_state(state: AppState) -> Router {
+    metrics::install();
+    let cors = cors::CorsMiddleware::new(state.allowed_origins.clone());
+    let rate_limit = rate_limit::RateLimitLayer::new(state.rate_limiter.clone());
+    let drain = drain::DrainLayer::new(state.drain.clone());
     Router::new()
         .route(HEALTHZ_PATH, get(healthz))
+        .route(READYZ_PATH, get(readyz))
         .route(VERSION_PATH, get(version))
         .route(METRICS_PATH, get(metrics))
         .route(WS_PATH, get(ws_handler))
+        .route(POLL_JOIN_PATH, post(longpoll::poll_join_handler))
+        .route(POLL_RECV_PATH, get(longpoll::poll_recv_handler))
+        .route(POLL_SEND_PATH, post(longpoll::poll_send_handler))
         .route("/auth/login", post(auth_login))
+        .route("/auth/register", post(auth_register))
         // Room management routes (v2 - using Room Manager)
         .route(ROOMS_CREATE_PATH, post(create_room_v2_handler))
         .route(ROOMS_LIST_PATH, get(list_rooms_v2_handler))
         .route(ROOMS_JOIN_PATH, post(join_room_v2_handler))
         .route(ROOMS_ASSIGN_PATH, post(assign_room_v2_handler))
+        .route(ROOMS_LEAVE_PATH, post(leave_room_v2_handler))
+        .route(ROOMS_PLAYERS_PATH, get(list_room_players_handler))
+        .route(ROOMS_RESOLVE_INVITE_PATH, post(resolve_invite_handler))
+        .route(ROOMS_INVITE_PATH, post(regenerate_invite_handler).delete(revoke_invite_handler))
+        .route(ROOMS_KICK_PATH, post(kick_player_handler))
+        .route(ROOMS_READY_PATH, post(set_ready_handler))
+        .route(ROOM_DETAIL_PATH, get(get_room_info_handler))
+        .route(PARTY_CREATE_PATH, post(create_party_handler))
+        .route(PARTY_INVITE_PATH, post(invite_to_party_handler))
+        .route(PARTY_JOIN_PATH, post(join_party_handler))
+        .route(PARTY_LEAVE_PATH, post(leave_party_handler))
+        .route(PARTY_QUEUE_PATH, post(queue_party_handler))
+        .route(TOURNAMENTS_CREATE_PATH, post(create_tournament_handler))
+        .route(TOURNAMENTS_REGISTER_PATH, post(register_tournament_handler))
+        .route(TOURNAMENTS_START_PATH, post(start_tournament_handler))
+        .route(TOURNAMENTS_REPORT_PATH, post(report_tournament_match_handler))
+        .route(TOURNAMENT_DETAIL_PATH, get(get_tournament_handler))
         .route("/auth/refresh", post(auth_refresh))
+        .route("/auth/logout", post(auth_logout))
         .route("/inputs", post(post_inputs))
-        // TODO: Uncomment when axum version conflicts are resolved
-        // .route("/rtc/offer", post(handle_rtc_offer))
-        // .route("/rtc/answer", post(handle_rtc_answer))
-        // .route("/rtc/ice", post(handle_rtc_ice))
-        // .route("/rtc/sessions", get(list_webrtc_sessions))
-        // .route("/rtc/sessions/:session_id", delete(close_webrtc_session))
+        .route(RTC_OFFER_PATH, post(handle_rtc_offer))
+        .route(RTC_ANSWER_PATH, post(handle_rtc_answer))
+        .route(RTC_ICE_PATH, post(handle_rtc_ice))
+        .route(RTC_SESSIONS_PATH, get(list_webrtc_sessions))
+        .route(ADMIN_BANDWIDTH_PATH, get(admin_bandwidth_handler))
+        .route(ADMIN_ANNOUNCE_PATH, post(admin_announce_handler))
+        .route("/rtc/sessions/:session_id", delete(close_webrtc_session))
         .route("/test", get(test_handler))
         .route("/api/leaderboard", get(leaderboard_handler))
         .route("/api/leaderboard/submit", post(submit_score_handler))
+        .route(common_net::scores::SCORES_PATH, post(worker_score_handler))
         .route(GAME_JOIN_PATH, post(game_join_handler))
         .route(GAME_LEAVE_PATH, post(game_leave_handler))
         .route(GAME_INPUT_PATH, post(game_input_handler))
-        // TODO: Uncomment when axum version conflicts are resolved
-        // .route(CHAT_SEND_PATH, post(chat_send_handler))
-        // .route(CHAT_HISTORY_PATH, post(chat_history_handler))
+        .route(CHAT_SEND_PATH, post(chat_send_handler))
+        .route(CHAT_HISTORY_PATH, post(chat_history_handler))
+        .layer(drain)
+        .layer(rate_limit)
+        .layer(cors)
+        .layer(request_id::RequestIdLayer)
         .with_state(state)
 }
 
 // ===== ROOM MANAGEMENT HANDLERS =====
 
+/// Header `Idempotency-Key` nếu client gửi; rỗng, không phải ASCII hoặc quá dài thì 422
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, GatewayError> {
+    let Some(value) = headers.get(idempotency::IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    match value.to_str().map(str::trim) {
+        Ok(key) if !key.is_empty() && key.len() <= idempotency::MAX_KEY_LEN => Ok(Some(key.to_string())),
+        _ => Err(GatewayError::Validation(vec![types::FieldError::new(
+            "Idempotency-Key",
+            format!("must be 1-{} ASCII characters", idempotency::MAX_KEY_LEN),
+        )])),
+    }
+}
+
 // Create a new room (Room Manager integration)
 async fn create_room_v2_handler(
     State(state): State<AppState>,
-    Json(create_req): Json<room_manager::CreateRoomRequest>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&[ROOMS_CREATE_PATH]).inc();
-
-    match room_manager::create_room(state.room_manager, create_req).await {
-        Ok(response) => {
-            counter!("gateway.rooms.created").increment(1);
-            Json(response).into_response()
-        }
-        Err(e) => {
-            error!("Failed to create room: {}", e);
-            counter!("gateway.rooms.create_failed").increment(1);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": format!("Failed to create room: {}", e)
-                }))
-            ).into_response()
+    headers: HeaderMap,
+    body: Result<Json<room_manager::CreateRoomRequest>, JsonRejection>,
+) -> Result<Json<room_manager::CreateRoomResponse>, GatewayError> {
+    metrics::record_http_request(ROOMS_CREATE_PATH);
+
+    // game_mode lạ bị từ chối lúc deserialize (422 kèm danh sách id hợp lệ)
+    let create_req = validated_body(body, |_| Ok(()))?;
+
+    let pending = match idempotency_key(&headers)? {
+        Some(key) => {
+            // Có token thì key tính theo user của token, không thì theo host trong body
+            let scope = extract_user_id_from_headers(&headers, &state.auth_service)
+                .unwrap_or_else(|_| create_req.host_player_id.clone());
+            let fingerprint = serde_json::to_string(&create_req).unwrap_or_default();
+            match state.room_creates.begin(&scope, &key, &fingerprint, std::time::Instant::now()) {
+                idempotency::Claim::New(pending) => Some(pending),
+                idempotency::Claim::Replay(response) => {
+                    tracing::info!("Replaying room {} for Idempotency-Key {}", response.room_id, key);
+                    return Ok(Json(response));
+                }
+                idempotency::Claim::InProgress => {
+                    return Err(GatewayError::Conflict(
+                        "A request with this Idempotency-Key is still in progress".to_string(),
+                    ))
+                }
+                idempotency::Claim::Mismatch => {
+                    return Err(GatewayError::Validation(vec![types::FieldError::new(
+                        "Idempotency-Key",
+                        "was already used with a different request body",
+                    )]))
+                }
+            }
         }
+        None => None,
+    };
+
+    let response = state.room_manager.create_room(&create_req).await.map_err(|e| {
+        metrics::record_room_event(metrics::RoomEvent::CreateFailed);
+        upstream_error("create room", e)
+    })?;
+    metrics::record_room_event(metrics::RoomEvent::Created);
+    update_room_gauges(&state.room_manager).await;
+    if !response.success {
+        return Err(GatewayError::Conflict(response.error.unwrap_or_else(|| "Failed to create room".to_string())));
     }
+    if let Some(pending) = pending {
+        pending.complete(&response);
+    }
+    metrics::record_room_lifecycle(metrics::RoomLifecycle::Created);
+    Ok(Json(response))
+}
+
+/// Số nguyên không âm trong query string, sai định dạng thì 422 theo tên field
+fn query_number(params: &serde_json::Value, field: &str) -> Result<Option<usize>, GatewayError> {
+    params
+        .get(field)
+        .and_then(|v| v.as_str())
+        .map(|raw| {
+            raw.parse()
+                .map_err(|_| GatewayError::Validation(vec![types::FieldError::new(field, "must be a non-negative integer")]))
+        })
+        .transpose()
 }
 
 // List available rooms (Room Manager integration)
 async fn list_rooms_v2_handler(
     State(state): State<AppState>,
     Query(params): Query<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&[ROOMS_LIST_PATH]).inc();
+) -> Result<Json<room_manager::ListRoomsResponse>, GatewayError> {
+    metrics::record_http_request(ROOMS_LIST_PATH);
 
     // Parse optional query parameters
-    let game_mode = params.get("game_mode")
+    let game_mode = params
+        .get("game_mode")
         .and_then(|v| v.as_str())
-        .and_then(|s| match s {
-            "deathmatch" => Some(GameMode::Deathmatch),
-            "team_deathmatch" => Some(GameMode::TeamDeathmatch),
-            "capture_the_flag" => Some(GameMode::CaptureTheFlag),
-            _ => None,
-        });
+        .map(GameMode::parse)
+        .transpose()
+        .map_err(|err| GatewayError::Validation(vec![types::FieldError::new("game_mode", err.to_string())]))?;
 
     let status = params.get("status")
         .and_then(|v| v.as_str())
@@ -671,192 +1217,563 @@ async fn list_rooms_v2_handler(
             _ => None,
         });
 
-    let list_req = room_manager::ListRoomsRequest { game_mode, status };
-
-    match room_manager::list_rooms(state.room_manager, list_req).await {
-        Ok(response) => {
-            Json(response).into_response()
-        }
-        Err(e) => {
-            error!("Failed to list rooms: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "rooms": [],
-                    "error": format!("Failed to list rooms: {}", e)
-                }))
-            ).into_response()
-        }
+    let sort = match params.get("sort").and_then(|v| v.as_str()) {
+        Some(raw) => room_manager::RoomSort::parse(raw).ok_or_else(|| {
+            let valid: Vec<&str> = room_manager::RoomSort::ALL.iter().map(|sort| sort.as_str()).collect();
+            GatewayError::Validation(vec![types::FieldError::new("sort", format!("must be one of {}", valid.join(", ")))])
+        })?,
+        None => room_manager::RoomSort::default(),
+    };
+    let limit = query_number(&params, "limit")?.unwrap_or(ROOMS_LIST_DEFAULT_LIMIT);
+    if !(1..=ROOMS_LIST_MAX_LIMIT).contains(&limit) {
+        return Err(GatewayError::Validation(vec![types::FieldError::new(
+            "limit",
+            format!("must be between 1 and {ROOMS_LIST_MAX_LIMIT}"),
+        )]));
     }
+    let has_space = match params.get("has_space").and_then(|v| v.as_str()) {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(_) => {
+            return Err(GatewayError::Validation(vec![types::FieldError::new("has_space", "must be true or false")]))
+        }
+    };
+
+    // Client không bao giờ thấy phòng private qua gateway
+    let list_req = room_manager::ListRoomsRequest {
+        game_mode,
+        status,
+        include_private: false,
+        admin: false,
+        has_space,
+        sort,
+        limit: Some(limit),
+        offset: query_number(&params, "offset")?.unwrap_or(0),
+    };
+
+    let response = state.room_manager.list_rooms(&list_req).await.map_err(|e| upstream_error("list rooms", e))?;
+    Ok(Json(response))
 }
 
 // Join a specific room (Room Manager integration)
 async fn join_room_v2_handler(
     State(state): State<AppState>,
-    Path(room_id): Path<String>,
-    Json(join_req): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&[ROOMS_JOIN_PATH]).inc();
-
-    let player_id = join_req.get("player_id")
-        .and_then(|v| v.as_str())
-        .unwrap_or("anonymous")
-        .to_string();
+    body: Result<Json<types::JoinRoomBody>, JsonRejection>,
+) -> Result<Json<room_manager::JoinRoomResponse>, GatewayError> {
+    metrics::record_http_request(ROOMS_JOIN_PATH);
 
-    let player_name = join_req.get("player_name")
-        .and_then(|v| v.as_str())
-        .unwrap_or(&format!("Player_{}", &player_id[..8]))
-        .to_string();
+    let join_req = validated_body(body, types::JoinRoomBody::validate)?;
 
     let request = room_manager::JoinRoomRequest {
-        room_id,
-        player_id,
-        player_name,
+        player_name: join_req.display_name(),
+        room_id: join_req.room_id,
+        player_id: join_req.player_id,
+        invite_code: join_req.invite_code,
     };
 
-    match room_manager::join_room(state.room_manager, request).await {
-        Ok(response) => {
-            counter!("gateway.rooms.player_joined").increment(1);
-            Json(response).into_response()
-        }
-        Err(e) => {
-            error!("Failed to join room: {}", e);
-            counter!("gateway.rooms.join_failed").increment(1);
-            (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": format!("Failed to join room: {}", e)
-                }))
-            ).into_response()
-        }
+    let response = state.room_manager.join_room(&request).await.map_err(|e| {
+        metrics::record_room_event(metrics::RoomEvent::JoinFailed);
+        upstream_error("join room", e)
+    })?;
+    metrics::record_room_event(metrics::RoomEvent::PlayerJoined);
+    update_room_gauges(&state.room_manager).await;
+    if !response.success {
+        return Err(GatewayError::Conflict(response.error.unwrap_or_else(|| "Failed to join room".to_string())));
     }
+    metrics::record_room_lifecycle(metrics::RoomLifecycle::Joined);
+    Ok(Json(response))
 }
 
-// Assign player to an appropriate room (auto-matchmaking) (Room Manager integration)
-async fn assign_room_v2_handler(
+// Leave a room (Room Manager integration)
+async fn leave_room_v2_handler(
     State(state): State<AppState>,
-    Json(assign_req): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&[ROOMS_ASSIGN_PATH]).inc();
+    body: Result<Json<types::LeaveRoomBody>, JsonRejection>,
+) -> Result<Json<room_manager::LeaveRoomResponse>, GatewayError> {
+    metrics::record_http_request(ROOMS_LEAVE_PATH);
 
-    let player_id = assign_req.get("player_id")
-        .and_then(|v| v.as_str())
-        .unwrap_or("anonymous")
-        .to_string();
+    let leave_req = validated_body(body, types::LeaveRoomBody::validate)?;
 
-    let game_mode = assign_req.get("game_mode")
-        .and_then(|v| v.as_str())
-        .and_then(|s| match s {
-            "deathmatch" => Some(GameMode::Deathmatch),
-            "team_deathmatch" => Some(GameMode::TeamDeathmatch),
-            "capture_the_flag" => Some(GameMode::CaptureTheFlag),
-            _ => None,
-        });
+    let request = room_manager::LeaveRoomRequest {
+        room_id: leave_req.room_id,
+        player_id: leave_req.player_id,
+    };
 
-    let request = room_manager::AssignRoomRequest { player_id, game_mode };
+    let response = state.room_manager.leave_room(&request).await.map_err(|e| {
+        metrics::record_room_event(metrics::RoomEvent::LeaveFailed);
+        upstream_error("leave room", e)
+    })?;
+    update_room_gauges(&state.room_manager).await;
+    if !response.success {
+        metrics::record_room_event(metrics::RoomEvent::LeaveFailed);
+        return Err(GatewayError::Conflict(response.error.unwrap_or_else(|| "Failed to leave room".to_string())));
+    }
+    metrics::record_room_event(metrics::RoomEvent::PlayerLeft);
+    metrics::record_room_lifecycle(metrics::RoomLifecycle::Left);
+    Ok(Json(response))
+}
 
-    match room_manager::assign_room(state.room_manager, request).await {
-        Ok(response) => {
-            counter!("gateway.rooms.player_assigned").increment(1);
-            Json(response).into_response()
-        }
-        Err(e) => {
-            error!("Failed to assign room: {}", e);
-            counter!("gateway.rooms.assign_failed").increment(1);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "room_id": null,
-                    "worker_endpoint": null,
-                    "error": format!("Failed to assign room: {}", e)
-                }))
-            ).into_response()
-        }
+// List the players of a room (Room Manager integration)
+async fn list_room_players_handler(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+) -> Result<Json<room_manager::ListPlayersResponse>, GatewayError> {
+    metrics::record_http_request(ROOMS_PLAYERS_PATH);
+
+    let response = state.room_manager.list_players(&room_id).await.map_err(|e| upstream_error("list room players", e))?;
+    if !response.success {
+        return Err(GatewayError::NotFound(response.error.unwrap_or_else(|| "Room not found".to_string())));
     }
+    Ok(Json(response))
 }
 
-// List WebRTC sessions for user
-async fn list_webrtc_sessions(
+// Assign player to an appropriate room (auto-matchmaking) (Room Manager integration)
+async fn assign_room_v2_handler(
     State(state): State<AppState>,
-    request: axum::http::Request<axum::body::Body>,
-) -> Json<serde_json::Value> {
-    // Extract user_id from JWT token
-    let user_id = match extract_user_id_from_request(&request, &state.auth_service).await {
-        Ok(id) => id,
-        Err(_) => {
-            return Json(serde_json::json!({
-                "error": "Authentication failed",
-                "sessions": []
-            }));
-        }
-    };
+    body: Result<Json<types::AssignRoomBody>, JsonRejection>,
+) -> Result<Json<room_manager::AssignRoomResponse>, GatewayError> {
+    metrics::record_http_request(ROOMS_ASSIGN_PATH);
 
-    let sessions: Vec<_> = {
-        let sessions_map = state.webrtc_sessions.read().await;
-        sessions_map.values()
-            .filter(|s| s.user_id == user_id)
-            .cloned()
-            .collect()
+    let assign_req = validated_body(body, types::AssignRoomBody::validate)?;
+
+    let request = room_manager::AssignRoomRequest {
+        player_id: assign_req.player_id,
+        game_mode: assign_req.game_mode,
     };
 
-    Json(serde_json::json!({
-        "sessions": sessions,
-        "total": sessions.len()
-    }))
+    let response = state.room_manager.assign_room(&request).await.map_err(|e| {
+        metrics::record_room_event(metrics::RoomEvent::AssignFailed);
+        upstream_error("assign room", e)
+    })?;
+    metrics::record_room_event(metrics::RoomEvent::PlayerAssigned);
+    if response.room_id.is_some() {
+        metrics::record_room_lifecycle(metrics::RoomLifecycle::Joined);
+    }
+    update_room_gauges(&state.room_manager).await;
+    Ok(Json(response))
 }
 
-// Close WebRTC session
-async fn close_webrtc_session(
-    State(mut state): State<AppState>,
-    request: axum::http::Request<axum::body::Body>,
-    Path(session_id): Path<String>,
-) -> Json<serde_json::Value> {
-    // Extract user_id from JWT token
-    let user_id = match extract_user_id_from_request(&request, &state.auth_service).await {
-        Ok(id) => id,
-        Err(_) => {
-            return Json(serde_json::json!({"error": "Authentication failed"}));
-        }
-    };
+// Look up the room behind an invite code so the client can confirm before joining
+async fn resolve_invite_handler(
+    State(state): State<AppState>,
+    body: Result<Json<types::ResolveInviteBody>, JsonRejection>,
+) -> Result<Json<room_manager::ResolveInviteResponse>, GatewayError> {
+    metrics::record_http_request(ROOMS_RESOLVE_INVITE_PATH);
 
-    {
-        let mut sessions = state.webrtc_sessions.write().await;
-        if let Some(session) = sessions.get(&session_id) {
-            if session.user_id == user_id {
-                sessions.remove(&session_id);
-                counter!("gw.webrtc.sessions_closed").increment(1);
-                return Json(serde_json::json!({"status": "session_closed"}));
-            }
-        }
-    }
+    let resolve_req = validated_body(body, types::ResolveInviteBody::validate)?;
 
-    Json(serde_json::json!({"error": "Session not found"}))
+    let request = room_manager::ResolveInviteRequest { code: resolve_req.code };
+    let response = state.room_manager.resolve_invite(&request).await.map_err(|e| upstream_error("resolve invite", e))?;
+    if !response.success {
+        return Err(GatewayError::NotFound(response.error.unwrap_or_else(|| "Invite code not found".to_string())));
+    }
+    Ok(Json(response))
 }
 
-// ===== CHAT HANDLERS =====
+async fn regenerate_invite_handler(
+    State(state): State<AppState>,
+    body: Result<Json<types::RoomInviteBody>, JsonRejection>,
+) -> Result<Json<room_manager::RoomInviteResponse>, GatewayError> {
+    manage_invite(state, body, true).await
+}
 
-async fn chat_send_handler(
+async fn revoke_invite_handler(
     State(state): State<AppState>,
-    request: axum::http::Request<axum::body::Body>,
-    Json(chat_req): Json<ChatSendRequest>,
-) -> Json<ChatSendResponse> {
-    // Extract user_id from JWT token
-    let user_id = match extract_user_id_from_request(&request, &state.auth_service).await {
-        Ok(id) => id,
-        Err(_) => {
-            return Json(ChatSendResponse {
-                success: false,
-                message_id: None,
-                error: Some("Authentication failed".to_string()),
-            });
-        }
-    };
+    body: Result<Json<types::RoomInviteBody>, JsonRejection>,
+) -> Result<Json<room_manager::RoomInviteResponse>, GatewayError> {
+    manage_invite(state, body, false).await
+}
 
-    // TODO: Get player name from user_id (could be stored in database or cache)
-    let player_name = format!("Player_{}", &user_id[..8]);
+/// `regenerate` = false là thu hồi code; room-manager kiểm tra player có phải host không
+async fn manage_invite(
+    state: AppState,
+    body: Result<Json<types::RoomInviteBody>, JsonRejection>,
+    regenerate: bool,
+) -> Result<Json<room_manager::RoomInviteResponse>, GatewayError> {
+    metrics::record_http_request(ROOMS_INVITE_PATH);
 
-    // Create chat message
+    let invite_req = validated_body(body, types::RoomInviteBody::validate)?;
+
+    let request = room_manager::RoomInviteRequest {
+        room_id: invite_req.room_id,
+        player_id: invite_req.player_id,
+    };
+    let result = if regenerate {
+        state.room_manager.regenerate_invite(&request).await
+    } else {
+        state.room_manager.revoke_invite(&request).await
+    };
+
+    let response = result.map_err(|e| upstream_error("update invite code", e))?;
+    if !response.success {
+        return Err(GatewayError::Forbidden(response.error.unwrap_or_else(|| "Failed to update invite code".to_string())));
+    }
+    Ok(Json(response))
+}
+
+async fn create_party_handler(State(state): State<AppState>, headers: HeaderMap) -> Result<Response, GatewayError> {
+    metrics::record_http_request(PARTY_CREATE_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+
+    let request = room_manager::party::CreatePartyRequest { player_id: claims.sub };
+    party_response(state.room_manager.create_party(&request).await, "create party")
+}
+
+/// room-manager kiểm tra người gọi có phải leader không
+async fn invite_to_party_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<types::PartyInviteBody>, JsonRejection>,
+) -> Result<Response, GatewayError> {
+    metrics::record_http_request(PARTY_INVITE_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+    let invite_req = validated_body(body, types::PartyInviteBody::validate)?;
+
+    let request = room_manager::party::PartyInviteRequest {
+        party_id: invite_req.party_id,
+        player_id: claims.sub,
+    };
+    party_response(state.room_manager.invite_to_party(&request).await, "create party invite")
+}
+
+async fn join_party_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<types::JoinPartyBody>, JsonRejection>,
+) -> Result<Response, GatewayError> {
+    metrics::record_http_request(PARTY_JOIN_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+    let join_req = validated_body(body, types::JoinPartyBody::validate)?;
+
+    let request = room_manager::party::JoinPartyRequest {
+        player_id: claims.sub,
+        invite_code: join_req.invite_code,
+    };
+    party_response(state.room_manager.join_party(&request).await, "join party")
+}
+
+async fn leave_party_handler(State(state): State<AppState>, headers: HeaderMap) -> Result<Response, GatewayError> {
+    metrics::record_http_request(PARTY_LEAVE_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+
+    let request = room_manager::party::LeavePartyRequest { player_id: claims.sub };
+    party_response(state.room_manager.leave_party(&request).await, "leave party")
+}
+
+/// Chỉ leader được xếp hàng: assign của room-manager từ chối member khác và xếp cả party một lần
+async fn queue_party_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<types::QueuePartyBody>, JsonRejection>,
+) -> Result<Response, GatewayError> {
+    metrics::record_http_request(PARTY_QUEUE_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+    let queue_req = validated_body(body, |_| Ok(()))?;
+
+    let request = room_manager::AssignRoomRequest {
+        player_id: claims.sub,
+        game_mode: queue_req.game_mode,
+    };
+    let response = state.room_manager.assign_room(&request).await.map_err(|e| {
+        metrics::record_room_event(metrics::RoomEvent::AssignFailed);
+        upstream_error("queue party", e)
+    })?;
+    if response.room_id.is_none() {
+        return Err(GatewayError::BadRequest(response.error.unwrap_or_else(|| "Failed to queue party".to_string())));
+    }
+    metrics::record_room_event(metrics::RoomEvent::PlayerAssigned);
+    metrics::record_room_lifecycle(metrics::RoomLifecycle::Joined);
+    update_room_gauges(&state.room_manager).await;
+    Ok(Json(response).into_response())
+}
+
+fn party_response(result: Result<room_manager::party::PartyResponse, BoxError>, action: &str) -> Result<Response, GatewayError> {
+    let response = result.map_err(|e| upstream_error(action, e))?;
+    if !response.success {
+        return Err(GatewayError::BadRequest(response.error.unwrap_or_else(|| format!("Failed to {}", action))));
+    }
+    Ok(Json(response).into_response())
+}
+
+async fn create_tournament_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<types::CreateTournamentBody>, JsonRejection>,
+) -> Result<Response, GatewayError> {
+    metrics::record_http_request(TOURNAMENTS_CREATE_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+    let create_req = validated_body(body, types::CreateTournamentBody::validate)?;
+
+    let request = room_manager::tournament::CreateTournamentRequest {
+        name: create_req.name,
+        game_mode: create_req.game_mode,
+        max_participants: create_req.max_participants,
+        host_player_id: claims.sub,
+        format: create_req.format,
+    };
+    tournament_response(state.room_manager.create_tournament(&request).await, "create tournament")
+}
+
+/// Player trong JWT tự đăng ký, tên hiển thị là username
+async fn register_tournament_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<types::TournamentBody>, JsonRejection>,
+) -> Result<Response, GatewayError> {
+    metrics::record_http_request(TOURNAMENTS_REGISTER_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+    let register_req = validated_body(body, types::TournamentBody::validate)?;
+
+    let request = room_manager::tournament::RegisterParticipantRequest {
+        tournament_id: register_req.tournament_id,
+        player_id: claims.sub,
+        player_name: claims.username,
+    };
+    tournament_response(state.room_manager.register_participant(&request).await, "register for tournament")
+}
+
+async fn start_tournament_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<types::TournamentBody>, JsonRejection>,
+) -> Result<Response, GatewayError> {
+    metrics::record_http_request(TOURNAMENTS_START_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+    let start_req = validated_body(body, types::TournamentBody::validate)?;
+
+    let request = room_manager::tournament::StartTournamentRequest {
+        tournament_id: start_req.tournament_id,
+        admin: claims.role == auth::ADMIN_ROLE,
+        requesting_player_id: claims.sub,
+    };
+    let response = tournament_response(state.room_manager.start_tournament(&request).await, "start tournament");
+    // Mỗi trận vòng 1 vừa có phòng mới
+    update_room_gauges(&state.room_manager).await;
+    response
+}
+
+async fn report_tournament_match_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<types::ReportMatchBody>, JsonRejection>,
+) -> Result<Response, GatewayError> {
+    metrics::record_http_request(TOURNAMENTS_REPORT_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+    let report_req = validated_body(body, types::ReportMatchBody::validate)?;
+
+    let request = room_manager::tournament::ReportMatchResultRequest {
+        tournament_id: report_req.tournament_id,
+        match_id: report_req.match_id,
+        winner_player_id: report_req.winner_player_id,
+        admin: claims.role == auth::ADMIN_ROLE,
+        requesting_player_id: claims.sub,
+    };
+    let response = tournament_response(state.room_manager.report_match_result(&request).await, "report match result");
+    update_room_gauges(&state.room_manager).await;
+    response
+}
+
+async fn get_tournament_handler(
+    State(state): State<AppState>,
+    Path(tournament_id): Path<String>,
+) -> Result<Response, GatewayError> {
+    metrics::record_http_request(TOURNAMENT_DETAIL_PATH);
+
+    let response = state.room_manager.get_tournament(&tournament_id).await.map_err(|e| upstream_error("get tournament", e))?;
+    if !response.success {
+        return Err(GatewayError::NotFound(response.error.unwrap_or_else(|| "Tournament not found".to_string())));
+    }
+    Ok(Json(response).into_response())
+}
+
+/// Room-manager từ chối (không phải host, giải đầy, sai trận...) thì trả 400 kèm lý do
+fn tournament_response(
+    result: Result<room_manager::tournament::TournamentResponse, BoxError>,
+    action: &str,
+) -> Result<Response, GatewayError> {
+    let response = result.map_err(|e| upstream_error(action, e))?;
+    if !response.success {
+        return Err(GatewayError::BadRequest(response.error.unwrap_or_else(|| format!("Failed to {}", action))));
+    }
+    Ok(Json(response).into_response())
+}
+
+/// Host (hoặc admin) đuổi player: room-manager trả slot/ghi ban, worker despawn entity,
+/// WS của player nhận `Kicked` rồi bị đóng
+async fn kick_player_handler(
+    State(mut state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<types::KickPlayerBody>, JsonRejection>,
+) -> Result<Response, GatewayError> {
+    metrics::record_http_request(ROOMS_KICK_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+    let kick_req = validated_body(body, types::KickPlayerBody::validate)?;
+
+    let request = room_manager::KickPlayerRequest {
+        room_id: kick_req.room_id.clone(),
+        requesting_player_id: claims.sub,
+        target_player_id: kick_req.target_player_id.clone(),
+        ban: kick_req.ban,
+        admin: claims.role == auth::ADMIN_ROLE,
+    };
+    let response = state.room_manager.kick_player(&request).await.map_err(|e| upstream_error("kick player", e))?;
+    if !response.success {
+        return Err(GatewayError::Forbidden(response.error.unwrap_or_else(|| "Failed to kick player".to_string())));
+    }
+
+    let reason = kick_req.reason.unwrap_or_else(|| DEFAULT_KICK_REASON.to_string());
+    // Player có thể chưa vào simulation (chỉ join qua room-manager) nên worker báo không có cũng không sao
+    let remove = proto::worker::v1::RemovePlayerRequest {
+        room_id: kick_req.room_id.clone(),
+        player_id: kick_req.target_player_id.clone(),
+        reason: reason.clone(),
+    };
+    match state.worker_client.remove_player(remove).await {
+        Ok(removed) if !removed.get_ref().ok => {
+            tracing::debug!(error = %removed.get_ref().error, "gateway: worker had nothing to remove");
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!(error = %e, room_id = %kick_req.room_id, "gateway: worker remove_player failed"),
+    }
+
+    let disconnected = disconnect_peer(&state.ws_registry, &kick_req.target_player_id, &reason).await;
+    tracing::info!(
+        room_id = %kick_req.room_id,
+        target = %kick_req.target_player_id,
+        ban = kick_req.ban,
+        disconnected,
+        "gateway: player kicked"
+    );
+
+    if response.removed {
+        metrics::record_room_lifecycle(metrics::RoomLifecycle::Left);
+    }
+    update_room_gauges(&state.room_manager).await;
+    Ok(Json(response).into_response())
+}
+
+/// Gửi `Kicked` rồi close frame cho mọi WS của peer; trả về số connection bị đóng
+async fn disconnect_peer(registry: &WebSocketRegistry, peer_id: &str, reason: &str) -> usize {
+    use axum::extract::ws::{close_code, CloseFrame, Message};
+
+    let connections = registry.for_peer(peer_id, |conn| (conn.outbound.clone(), conn.outbox.clone()));
+    for (outbound, outbox) in &connections {
+        let frame = outbound.stamp(Frame::control(0, 0, ControlMessage::Kicked {
+            reason: reason.to_string(),
+        }));
+        if let Ok(bytes) = message::encode(&frame) {
+            outbox.push(outbox::OutboundKind::Control, Message::Binary(bytes));
+        }
+        outbox.push(outbox::OutboundKind::Control, Message::Close(Some(CloseFrame {
+            code: close_code::POLICY,
+            reason: "kicked".into(),
+        })));
+    }
+    connections.len()
+}
+
+/// Top-K room/connection theo byte trong interval flush gần nhất (`?limit=`, mặc định 10)
+async fn admin_bandwidth_handler(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    metrics::record_http_request(ADMIN_BANDWIDTH_PATH);
+
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(10);
+    Json(state.bandwidth.report(limit))
+}
+
+async fn admin_announce_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<types::AnnounceBody>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request(ADMIN_ANNOUNCE_PATH);
+
+    // Token tĩnh của ops không gắn với user nào
+    let admin = if state.admin_token.matches(&headers) {
+        "admin_token".to_string()
+    } else {
+        let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+        if claims.role != auth::ADMIN_ROLE {
+            return Err(GatewayError::Forbidden("Admin role required".to_string()));
+        }
+        claims.sub
+    };
+    let announce_req = validated_body(body, types::AnnounceBody::validate)?;
+
+    let request = proto::worker::v1::AnnounceRequest {
+        room_id: announce_req.room_id.unwrap_or_default(),
+        message: announce_req.message,
+    };
+    let response = state.worker_client.clone().announce(request).await.map_err(|status| worker_error("send announcement", &status))?;
+    let response = response.into_inner();
+    if !response.ok {
+        return Err(GatewayError::NotFound(response.error));
+    }
+    tracing::info!(admin = %admin, rooms = response.rooms, "gateway: announcement sent");
+    Ok(Json(serde_json::json!({ "success": true, "rooms": response.rooms })))
+}
+
+// List WebRTC sessions for user
+async fn list_webrtc_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let user_id = extract_user_id_from_headers(&headers, &state.auth_service)?;
+
+    let sessions = state.webrtc_sessions.for_user(&user_id);
+
+    Ok(Json(serde_json::json!({
+        "sessions": sessions,
+        "total": sessions.len()
+    })))
+}
+
+// Close WebRTC session
+async fn close_webrtc_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let user_id = extract_user_id_from_headers(&headers, &state.auth_service)?;
+
+    let session = state
+        .webrtc_sessions
+        .remove_owned(&session_id, &user_id)
+        .ok_or_else(|| GatewayError::NotFound("Session not found".to_string()))?;
+    if session.status == WebRTCSessionStatus::Connected {
+        metrics::record_webrtc_disconnected();
+    }
+    metrics::record_webrtc_signal(metrics::WebRtcSignal::SessionClosed);
+    Ok(Json(serde_json::json!({"status": "session_closed"})))
+}
+
+// ===== CHAT HANDLERS =====
+
+async fn chat_send_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(chat_req): Json<ChatSendRequest>,
+) -> Result<Json<ChatSendResponse>, GatewayError> {
+    let user_id = extract_user_id_from_headers(&headers, &state.auth_service)?;
+
+    // TODO: Get player name from user_id (could be stored in database or cache)
+    let player_name = format!("Player_{}", user_id.chars().take(8).collect::<String>());
+
+    // Create chat message
     let message_id = format!("msg_{}", chrono::Utc::now().timestamp_millis());
     let chat_message = ChatMessage {
         id: message_id.clone(),
@@ -871,38 +1788,82 @@ async fn chat_send_handler(
     // For now, just return success
     tracing::info!("Chat message sent: {:?}", chat_message);
 
-    Json(ChatSendResponse {
+    Ok(Json(ChatSendResponse {
         success: true,
         message_id: Some(message_id),
         error: None,
-    })
+    }))
 }
 
 async fn chat_history_handler(
-    State(_state): State<AppState>,
-    request: axum::http::Request<axum::body::Body>,
-    Json(history_req): Json<ChatHistoryRequest>,
-) -> Json<ChatHistoryResponse> {
-    // Extract user_id from JWT token
-    let _user_id = "anonymous".to_string();
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(_history_req): Json<ChatHistoryRequest>,
+) -> Result<Json<ChatHistoryResponse>, GatewayError> {
+    extract_user_id_from_headers(&headers, &state.auth_service)?;
 
     // TODO: Get chat history from worker via gRPC
     // For now, return empty history
-    Json(ChatHistoryResponse {
+    Ok(Json(ChatHistoryResponse {
         messages: Vec::new(),
         total: 0,
-    })
+    }))
 }
 
 // Auth handlers
 async fn auth_login(
     State(state): State<AppState>,
-    Json(login_req): Json<auth::AuthRequest>,
-) -> impl IntoResponse {
-    match auth::login_handler(Json(login_req)).await {
-        response => {
-            counter!("gw.auth.login.success").increment(1);
-            response
+    Json(login_req): Json<auth::EmailLoginRequest>,
+) -> Result<Json<auth::AuthResponse>, GatewayError> {
+    match auth::email_login_handler(&state.auth_service, login_req).await {
+        Ok(response) => {
+            metrics::record_auth(metrics::AuthAction::Login, true);
+            Ok(Json(response))
+        }
+        Err(auth::AuthError::InvalidCredentials(e)) => {
+            metrics::record_auth(metrics::AuthAction::Login, false);
+            tracing::warn!("Login failed: {}", e);
+            Err(GatewayError::Unauthorized("Invalid credentials".to_string()))
+        }
+        // PocketBase lỗi hoặc không gọi được: không phải lỗi của player nên không đếm là login thất bại
+        Err(auth::AuthError::Upstream(e)) => {
+            error!("Login failed, PocketBase error: {}", e);
+            Err(GatewayError::Upstream("Authentication service error".to_string()))
+        }
+        Err(auth::AuthError::Unavailable(e)) => {
+            error!("Login failed, PocketBase unreachable: {}", e);
+            Err(GatewayError::Unavailable("Authentication service unavailable".to_string()))
+        }
+        Err(e) => {
+            metrics::record_auth(metrics::AuthAction::Login, false);
+            error!("Login failed: {}", e);
+            Err(GatewayError::Internal("Authentication service error".to_string()))
+        }
+    }
+}
+
+async fn auth_register(
+    State(state): State<AppState>,
+    Json(register_req): Json<auth::RegisterRequest>,
+) -> Result<Response, GatewayError> {
+    match auth::register_user(&state.auth_service, register_req).await {
+        Ok(response) => {
+            metrics::record_auth(metrics::AuthAction::Register, true);
+            Ok((StatusCode::CREATED, Json(response)).into_response())
+        }
+        Err(auth::AuthError::UserExists(email)) => {
+            metrics::record_auth(metrics::AuthAction::Register, false);
+            tracing::warn!("Register rejected, email already exists: {}", email);
+            Err(GatewayError::Conflict("Email already registered".to_string()))
+        }
+        Err(auth::AuthError::InvalidRequest(e)) => {
+            metrics::record_auth(metrics::AuthAction::Register, false);
+            Err(GatewayError::BadRequest(e))
+        }
+        Err(e) => {
+            metrics::record_auth(metrics::AuthAction::Register, false);
+            error!("Register failed: {}", e);
+            Err(GatewayError::Internal("Authentication service error".to_string()))
         }
     }
 }
@@ -910,15 +1871,37 @@ async fn auth_login(
 async fn auth_refresh(
     State(state): State<AppState>,
     Json(refresh_req): Json<auth::RefreshRequest>,
-) -> impl IntoResponse {
-    match auth::refresh_handler(Json(refresh_req)).await {
-        response => {
-            counter!("gw.auth.refresh.success").increment(1);
-            response
+) -> Result<Json<auth::AuthResponse>, GatewayError> {
+    match state.auth_service.rotate_refresh_token(&refresh_req.refresh_token) {
+        Ok(response) => {
+            metrics::record_auth(metrics::AuthAction::Refresh, true);
+            Ok(Json(response))
+        }
+        Err(auth::AuthError::TokenGeneration(e)) => {
+            metrics::record_auth(metrics::AuthAction::Refresh, false);
+            error!("Token refresh failed: {}", e);
+            Err(GatewayError::Internal("Token generation error".to_string()))
+        }
+        Err(e) => {
+            metrics::record_auth(metrics::AuthAction::Refresh, false);
+            tracing::warn!("Token refresh rejected: {}", e);
+            Err(GatewayError::Unauthorized("Invalid refresh token".to_string()))
         }
     }
 }
 
+async fn auth_logout(
+    State(state): State<AppState>,
+    Json(logout_req): Json<auth::RefreshRequest>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    state.auth_service.revoke_refresh_token(&logout_req.refresh_token).map_err(|e| {
+        tracing::warn!("Logout rejected: {}", e);
+        GatewayError::Unauthorized("Invalid refresh token".to_string())
+    })?;
+    metrics::record_logout();
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
 // Game input handler
 async fn post_inputs(
     State(mut state): State<AppState>,
@@ -934,255 +1917,402 @@ async fn post_inputs(
 
     match state.worker_client.push_input(req).await {
         Ok(_) => {
-            histogram!("gw.inputs.push_ms").record(t0.elapsed().as_secs_f64() * 1000.0);
-            counter!("gw.inputs.ok").increment(1);
+            metrics::record_input_push(true, t0.elapsed());
             axum::http::StatusCode::OK
         }
         Err(e) => {
-            error!(error=?e, "push_input failed");
-            counter!("gw.inputs.err").increment(1);
-            axum::http::StatusCode::BAD_GATEWAY
+            metrics::record_input_push(false, t0.elapsed());
+            worker_error("push input", &e).status()
         }
     }
 }
 
 async fn healthz() -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&[HEALTHZ_PATH]).inc();
-
-    let mut response = axum::http::Response::builder()
-        .status(axum::http::StatusCode::OK)
-        .header("Access-Control-Allow-Origin", "*")
-        .header("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS")
-        .header("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept, Cache-Control, Pragma")
-        .header("Access-Control-Max-Age", "86400")
-        .header("Vary", "Origin")
-        .body(axum::body::Body::empty())
-        .unwrap();
+    metrics::record_http_request(HEALTHZ_PATH);
+    StatusCode::OK
+}
 
-    response
+async fn readyz(State(state): State<AppState>) -> health::ReadinessReport {
+    metrics::record_http_request(READYZ_PATH);
+    let report = state.readiness.report().await;
+    metrics::record_readiness(&report);
+    report
 }
 
 async fn test_handler() -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/test"]).inc();
-
-    let mut response = Json(serde_json::json!({"message": "test endpoint works"})).into_response();
-    let headers = response.headers_mut();
-    headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-    headers.insert("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS".parse().unwrap());
-    headers.insert("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept, Cache-Control, Pragma".parse().unwrap());
-    headers.insert("Access-Control-Max-Age", "86400".parse().unwrap());
-    headers.insert("Vary", "Origin".parse().unwrap());
-    response
+    metrics::record_http_request("/test");
+    Json(serde_json::json!({"message": "test endpoint works"}))
 }
 
 async fn version() -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&[VERSION_PATH]).inc();
+    metrics::record_http_request(VERSION_PATH);
     let body = serde_json::json!({
         "name": "gateway",
         "version": env!("CARGO_PKG_VERSION"),
+        "git_sha": env!("GATEWAY_GIT_SHA"),
+        "built_at": env!("GATEWAY_BUILT_AT"),
+        "protocol_version": common_net::message::PROTOCOL_VERSION,
     });
 
-    let mut response = Json(body).into_response();
-    let headers = response.headers_mut();
-    headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-    headers.insert("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS".parse().unwrap());
-    headers.insert("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept, Cache-Control, Pragma".parse().unwrap());
-    headers.insert("Access-Control-Max-Age", "86400".parse().unwrap());
-    headers.insert("Vary", "Origin".parse().unwrap());
-    response
+    Json(body)
 }
 
 async fn metrics() -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&[METRICS_PATH]).inc();
-    let metric_families = prometheus::gather();
-    let mut buffer = Vec::new();
-    let encoder = TextEncoder::new();
-    if let Err(err) = encoder.encode(&metric_families, &mut buffer) {
-        error!(%err, "metrics encode failed");
-        let mut response = (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            "metrics encode failed"
-        ).into_response();
-        let headers = response.headers_mut();
-        headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-        headers.insert("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS".parse().unwrap());
-        headers.insert("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept, Cache-Control, Pragma".parse().unwrap());
-        headers.insert("Access-Control-Max-Age", "86400".parse().unwrap());
-        headers.insert("Vary", "Origin".parse().unwrap());
-        return response;
-    }
-    let body = String::from_utf8(buffer).unwrap_or_default();
-    let mut response = (
-        axum::http::StatusCode::OK,
-        [(axum::http::header::CONTENT_TYPE, encoder.format_type())],
-        body
-    ).into_response();
-    let headers = response.headers_mut();
-    headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-    headers.insert("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS".parse().unwrap());
-    headers.insert("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept, Cache-Control, Pragma".parse().unwrap());
-    headers.insert("Access-Control-Max-Age", "86400".parse().unwrap());
-    headers.insert("Vary", "Origin".parse().unwrap());
-    response
+    metrics::record_http_request(METRICS_PATH);
+    (
+        StatusCode::OK,
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(),
+    )
+}
+
+/// Subprotocol client gửi kèm token: `Sec-WebSocket-Protocol: bearer, <jwt>`
+const WS_BEARER_PROTOCOL: &str = "bearer";
+
+/// Lấy JWT từ `Sec-WebSocket-Protocol` (bearer, <jwt>) hoặc query `?token=`
+fn ws_token(headers: &HeaderMap, params: &HashMap<String, String>) -> Option<String> {
+    let from_protocol = headers
+        .get("sec-websocket-protocol")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .find(|p| !p.is_empty() && !p.eq_ignore_ascii_case(WS_BEARER_PROTOCOL))
+                .map(str::to_string)
+        });
+
+    from_protocol.or_else(|| params.get("token").filter(|t| !t.is_empty()).cloned())
 }
 
 async fn ws_handler(
     ws: axum::extract::ws::WebSocketUpgrade,
     State(state): State<AppState>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| ws_session(socket, state.ws_registry, state.transport_registry))
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, GatewayError> {
+    // Trình duyệt gửi cookie/token của user cho mọi site, nên chặn origin lạ trước khi upgrade
+    let origin = headers.get(axum::http::header::ORIGIN);
+    if !state.allowed_origins.allows_request(origin) {
+        tracing::warn!(origin = ?origin, "gateway: websocket upgrade rejected, origin not allowed");
+        metrics::record_ws_origin_rejected();
+        return Err(GatewayError::Forbidden("Origin not allowed".to_string()));
+    }
+
+    let peer_id = match ws_token(&headers, &params).map(|token| state.auth_service.verify_token(&token)) {
+        Some(Ok(token_data)) => token_data.claims.sub,
+        Some(Err(e)) => {
+            tracing::warn!("gateway: websocket upgrade rejected, invalid token: {}", e);
+            metrics::record_ws_auth_failure();
+            return Err(GatewayError::Unauthorized("Invalid token".to_string()));
+        }
+        None => {
+            metrics::record_ws_auth_failure();
+            return Err(GatewayError::Unauthorized("Missing token".to_string()));
+        }
+    };
+
+    Ok(ws.protocols([WS_BEARER_PROTOCOL])
+        .on_upgrade(move |socket| {
+            // Span sống theo connection; room_id được ghi khi client Join (hoặc migrate vào room)
+            let connection_id = uuid::Uuid::new_v4().to_string();
+            let span = tracing::info_span!(
+                "ws_session",
+                connection_id = %connection_id,
+                peer_id = %peer_id,
+                room_id = tracing::field::Empty,
+            );
+            ws_session(socket, connection_id, peer_id, state).instrument(span)
+        })
+        .into_response())
 }
 
-async fn ws_session(
-    mut socket: axum::extract::ws::WebSocket,
-    ws_registry: WebSocketRegistry,
-    transport_registry: TransportRegistry,
-) {
-    // Generate unique connection ID
-    let connection_id = uuid::Uuid::new_v4().to_string();
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<axum::extract::ws::Message>();
+/// Thời gian chờ `ControlMessage::Hello` sau khi upgrade
+const WS_HELLO_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Frame Hello đầu tiên của client nếu khớp `PROTOCOL_VERSION`. Thiếu Hello (client cũ), lệch version hay quá hạn thì
+/// gửi `VersionMismatch` rồi đóng, trước mọi frame game; None cả khi client tự đóng
+async fn accept_hello(socket: &mut axum::extract::ws::WebSocket) -> Option<Frame> {
+    use axum::extract::ws::Message;
+
+    let first = tokio::time::timeout(WS_HELLO_TIMEOUT, async {
+        loop {
+            match socket.recv().await? {
+                Ok(Message::Binary(bytes)) => return Some(message::decode(&bytes).ok()),
+                Ok(Message::Text(text)) => return Some(parse_text_frame(&text).ok()),
+                Ok(Message::Close(_)) | Err(_) => return None,
+                Ok(_) => continue,
+            }
+        }
+    })
+    .await;
+    let client_version = match first {
+        Ok(None) => return None,
+        Ok(Some(Some(frame))) => match &frame.payload {
+            FramePayload::Control { message: ControlMessage::Hello { protocol_version } } => {
+                if *protocol_version == message::PROTOCOL_VERSION {
+                    return Some(frame);
+                }
+                Some(*protocol_version)
+            }
+            _ => None,
+        },
+        Ok(Some(None)) | Err(_) => None,
+    };
+
+    tracing::warn!(
+        client_version,
+        server_version = message::PROTOCOL_VERSION,
+        "gateway: đóng connection, client không gửi Hello đúng protocol version"
+    );
+    metrics::record_ws_version_mismatch();
+    let mismatch = Frame::control(1, now_millis(), ControlMessage::VersionMismatch { server_version: message::PROTOCOL_VERSION });
+    if let Ok(bytes) = message::encode(&mismatch) {
+        let _ = socket.send(Message::Binary(bytes)).await;
+    }
+    let _ = socket.send(Message::Close(None)).await;
+    None
+}
+
+async fn ws_session(mut socket: axum::extract::ws::WebSocket, connection_id: String, peer_id: String, state: AppState) {
+    use bandwidth::{Direction, MessageKind};
+
+    // Chế độ echo để debug không nói protocol game nên không cần Hello
+    let hello = if state.ws_echo {
+        Frame::control(0, 0, ControlMessage::Hello { protocol_version: message::PROTOCOL_VERSION })
+    } else {
+        match accept_hello(&mut socket).await {
+            Some(hello) => hello,
+            None => return,
+        }
+    };
 
-    // Try WebRTC first, fallback to WebSocket
-    let mut webrtc_transport = WebRtcTransport::new("default_room".to_string(), connection_id.clone());
-    let webrtc_connected = try_establish_webrtc(&mut webrtc_transport).await;
+    let AppState {
+        ws_registry,
+        transport_registry,
+        snapshots,
+        latency,
+        ws_outbox: outbox_config,
+        ws_echo,
+        bandwidth,
+        quic_sessions,
+        rtc_peers,
+        mut worker_client,
+        room_manager,
+        analytics,
+        ..
+    } = state;
+
+    let outbox = outbox::WsOutbox::new(outbox_config);
+    bandwidth.connect(&connection_id);
+    let session_started = std::time::Instant::now();
+
+    let (transport, selection, mut rtc_inbound) = select_transport(&quic_sessions, &rtc_peers, &peer_id, &connection_id).await;
+
+    let mut started = analytics.event(analytics::SESSION_STARTED, &connection_id, &peer_id);
+    started.transport = transport_label(transport.kind()).to_string();
+    analytics.emit(started);
+    // Client không thử WebRTC thì WebSocket là lựa chọn bình thường, chỉ đếm fallback khi WebRTC đã thử mà hỏng
+    if selection.reason == TransportSelection::WEBRTC_FAILED {
+        let mut fallback = analytics.event(analytics::TRANSPORT_FALLBACK_USED, &connection_id, &peer_id);
+        fallback.transport = transport_label(transport.kind()).to_string();
+        fallback.reason = selection.reason.to_string();
+        analytics.emit(fallback);
+    }
 
     // Update metrics
-    let transport_type = if webrtc_connected { "webrtc" } else { "websocket" };
-    let fallback_used = if !webrtc_connected { "true" } else { "false" };
-    TRANSPORT_CONNECTIONS_TOTAL.with_label_values(&[transport_type, fallback_used]).inc();
+    metrics::record_transport_connection(transport_label(transport.kind()), selection.fallback_used);
 
-    if webrtc_connected {
-        WEBRTC_CONNECTIONS_CURRENT.with_label_values(&["connected"]).inc();
+    if transport.kind() == TransportKind::WebRtc {
+        metrics::record_webrtc_connected();
     }
 
-    // Register WebSocket connection
-    {
-        let mut ws_reg = ws_registry.write().await;
-        ws_reg.insert(connection_id.clone(), WebSocketConnection {
-            peer_id: "unknown".to_string(), // TODO: Get from handshake
-            room_id: "unknown".to_string(), // TODO: Get from handshake
-            sender: tx.clone(),
-        });
+    let outbound = OutboundSequence::default();
+    outbound.on_receive(&hello);
+    let rtt = latency::LatencyTracker::default();
+
+    // Báo client transport nào thắng trước mọi frame khác, để client chỉnh nhịp gửi khi phải fallback
+    let selected = outbound.stamp(Frame::control(0, 0, selection.control_message()));
+    if let Ok(bytes) = message::encode(&selected) {
+        outbox.push(outbox::OutboundKind::Control, axum::extract::ws::Message::Binary(bytes));
     }
 
+    // Register WebSocket connection; room_id được gán khi client gửi JoinRoom
+    ws_registry.insert(connection_id.clone(), WebSocketConnection {
+        peer_id: peer_id.clone(),
+        room_id: "unknown".to_string(),
+        outbound: outbound.clone(),
+        outbox: outbox.clone(),
+        latency: rtt.clone(),
+    });
+    latency.ensure_running();
+
     // Register transport connection
-    {
-        let mut transport_reg = transport_registry.write().await;
-        transport_reg.insert(connection_id.clone(), TransportConnection {
-            peer_id: "unknown".to_string(),
-            room_id: "unknown".to_string(),
-            transport: if webrtc_connected {
-                Box::new(webrtc_transport)
-            } else {
-                // Fallback to WebSocket transport - mark as fallback
-                // We'll use the existing WebSocket connection for transport
-                let mut fallback_transport = WebRtcTransport::new("unknown".to_string(), "unknown".to_string());
-                fallback_transport.fallback_to_websocket().await.unwrap();
-                Box::new(fallback_transport)
-            },
-            fallback_used: !webrtc_connected,
-        });
-    }
+    transport_registry.insert(
+        connection_id.clone(),
+        TransportConnection::new(peer_id.clone(), outbound.clone(), transport, selection),
+    );
+
+    let mut inbound = InboundSession {
+        peer_id: peer_id.clone(),
+        connection_id: connection_id.clone(),
+        ws_registry: ws_registry.clone(),
+        transport_registry: transport_registry.clone(),
+        outbound,
+        dedupe: FrameDedupe::new(INBOUND_DEDUPE_WINDOW),
+        snapshots,
+        bandwidth: bandwidth.clone(),
+        latency: rtt.clone(),
+        worker_client: worker_client.clone(),
+        room_manager,
+        rtc_peers: rtc_peers.clone(),
+        rtc_offer_pending: false,
+        analytics: analytics.clone(),
+    };
+    // Lý do đóng gửi kèm event `disconnect`
+    let mut close_reason = analytics::CLIENT_CLOSED;
+    // Offer gửi qua WS trong lúc đang chạy: chờ DataChannel mở rồi chuyển transport của connection sang WebRTC
+    let mut rtc_upgrade: Option<RtcUpgrade> = None;
+
+    // Ping đầu tiên sau một chu kỳ, không chen vào lúc client đang join
+    let ping_interval = latency.ping_interval();
+    let mut ping_ticker = tokio::time::interval_at(tokio::time::Instant::now() + ping_interval, ping_interval);
+    ping_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut retransmit_ticker = tokio::time::interval(reliable::RETRANSMIT_INTERVAL / 2);
+    retransmit_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
     loop {
+        if std::mem::take(&mut inbound.rtc_offer_pending) {
+            let (rtc_peers, peer_id) = (rtc_peers.clone(), peer_id.clone());
+            rtc_upgrade = Some(Box::pin(async move { rtc_peers.connect(&peer_id, "unknown").await }));
+        }
+
         tokio::select! {
+            // Ping đo RTT, nonce là thời điểm gửi; Pong về được xử lý trong handle_inbound_frame
+            _ = ping_ticker.tick() => {
+                let frame = inbound.outbound.stamp(Frame::control(0, 0, ControlMessage::Ping { nonce: rtt.next_ping_nonce() }));
+                if let Ok(bytes) = message::encode(&frame) {
+                    outbox.push(outbox::OutboundKind::Control, axum::extract::ws::Message::Binary(bytes));
+                }
+                // Chu kỳ Ping đổi khi reload config
+                let current_interval = latency.ping_interval();
+                if current_interval != ping_ticker.period() {
+                    ping_ticker = tokio::time::interval_at(tokio::time::Instant::now() + current_interval, current_interval);
+                    ping_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                }
+            }
+
+            // Control frame client chưa ack: gửi lại nguyên frame (cùng sequence) trên đường đã gửi lần đầu
+            _ = retransmit_ticker.tick() => {
+                for (frame, via) in inbound.outbound.unacked.due(std::time::Instant::now()) {
+                    metrics::record_control_retransmit();
+                    match via {
+                        reliable::SentVia::Outbox => {
+                            if let Ok(bytes) = message::encode(&frame) {
+                                outbox.push(outbox::OutboundKind::Control, axum::extract::ws::Message::Binary(bytes));
+                            }
+                        }
+                        reliable::SentVia::Transport(transport) => {
+                            if let Err(err) = transport.lock().await.send_frame(frame).await {
+                                tracing::debug!(%err, %connection_id, "gateway: gửi lại control frame qua transport lỗi");
+                            }
+                        }
+                    }
+                }
+            }
+
+
+            result = async { rtc_upgrade.as_mut().expect("guarded by is_some").await }, if rtc_upgrade.is_some() => {
+                rtc_upgrade = None;
+                match result {
+                    Some(Ok(mut webrtc_transport)) => {
+                        let selection = TransportSelection {
+                            kind: TransportKind::WebRtc,
+                            fallback_used: false,
+                            reason: TransportSelection::WEBRTC_CONNECTED,
+                        };
+                        let inbound_channels = webrtc_transport.take_inbound();
+                        if transport_registry.upgrade(&connection_id, Box::new(webrtc_transport), selection) {
+                            rtc_inbound = inbound_channels;
+                            metrics::record_webrtc_connected();
+                            metrics::record_transport_connection(transport_label(TransportKind::WebRtc), false);
+                            let selected = inbound.outbound.stamp(Frame::control(0, 0, selection.control_message()));
+                            if let Ok(bytes) = message::encode(&selected) {
+                                outbox.push(outbox::OutboundKind::Control, axum::extract::ws::Message::Binary(bytes));
+                            }
+                        }
+                    }
+                    Some(Err(err)) => tracing::warn!(%err, %peer_id, %connection_id, "gateway: webrtc không lập được, giữ websocket"),
+                    None => {}
+                }
+            }
+
+            // Frame client gửi qua DataChannel xử lý như frame WS, reply đi qua WS outbox
+            frame = recv_rtc_frame(&mut rtc_inbound), if rtc_inbound.is_some() => {
+                match frame {
+                    Some(frame) => {
+                        if let Ok(bytes) = message::encode(&frame) {
+                            bandwidth.record(&connection_id, Direction::Received, MessageKind::of(&frame), bytes.len() as u64);
+                        }
+                        if let Some(reply) = handle_inbound_frame(&mut inbound, frame).await {
+                            if let Ok(encoded) = message::encode(&reply) {
+                                bandwidth.record(&connection_id, Direction::Sent, MessageKind::of(&reply), encoded.len() as u64);
+                                let _ = socket.send(axum::extract::ws::Message::Binary(encoded)).await;
+                            }
+                        }
+                    }
+                    None => rtc_inbound = None,
+                }
+            }
+
             // Handle incoming messages from WebSocket
             msg = socket.recv() => {
                 match msg {
                     Some(Ok(axum::extract::ws::Message::Text(text))) => {
-                        // Handle text messages (echo for now)
-                        println!("Received text message: {}", text);
-                        if let Err(e) = socket.send(axum::extract::ws::Message::Text(format!("Echo: {}", text))).await {
-                            eprintln!("Failed to send echo: {}", e);
+                        let (reply, kind) = if ws_echo {
+                            bandwidth.record(&connection_id, Direction::Received, MessageKind::Control, text.len() as u64);
+                            (Some(format!("Echo: {}", text)), MessageKind::Control)
+                        } else {
+                            let frame = match parse_text_frame(&text) {
+                                Ok(frame) => {
+                                    bandwidth.record(&connection_id, Direction::Received, MessageKind::of(&frame), text.len() as u64);
+                                    handle_inbound_frame(&mut inbound, frame).await
+                                }
+                                Err(_) => {
+                                    bandwidth.record(&connection_id, Direction::Received, MessageKind::Control, text.len() as u64);
+                                    Some(inbound.outbound.stamp(Frame::control(0, 0, ControlMessage::Error {
+                                        code: "invalid_message".to_string(),
+                                        message: "Text frame is not a valid JSON control/state message".to_string(),
+                                    })))
+                                }
+                            };
+                            let kind = frame.as_ref().map_or(MessageKind::Control, MessageKind::of);
+                            (frame.and_then(|frame| serde_json::to_string(&frame).ok()), kind)
+                        };
+                        if let Some(reply) = reply {
+                            bandwidth.record(&connection_id, Direction::Sent, kind, reply.len() as u64);
+                            if let Err(e) = socket.send(axum::extract::ws::Message::Text(reply)).await {
+                                eprintln!("Failed to send text reply: {}", e);
+                            }
                         }
                     }
                     Some(Ok(axum::extract::ws::Message::Binary(bytes))) => {
                         match message::decode(&bytes) {
-                            Ok(Frame { payload, .. }) => {
-                                match payload {
-                                    FramePayload::Control {
-                                        message: ControlMessage::Ping { nonce },
-                                    } => {
-                                        let frame = Frame::control(0, 0, ControlMessage::Pong { nonce });
-                                        if let Ok(reply) = message::encode(&frame) {
-                                            let _ = socket.send(axum::extract::ws::Message::Binary(reply)).await;
-                                        }
-                                    }
-                                    FramePayload::Control {
-                                        message: ControlMessage::WebRtcOffer { room_id, peer_id, target_peer_id, sdp },
-                                    } => {
-                                        // Update connection info
-                                        {
-                                            let mut ws_reg = ws_registry.write().await;
-                                            if let Some(conn) = ws_reg.get_mut(&connection_id) {
-                                                conn.peer_id = peer_id.clone();
-                                                conn.room_id = room_id.clone();
-                                            }
-                                        }
-
-                                // Broadcast offer to other peers in room
-                                broadcast_to_transport(&transport_registry, &room_id, &peer_id, message::Frame::control(
-                                    0, 0, ControlMessage::WebRtcOffer {
-                                        room_id: room_id.clone(),
-                                        peer_id: peer_id.clone(),
-                                        target_peer_id,
-                                        sdp,
-                                    }
-                                )).await;
-                                    }
-                                    FramePayload::Control {
-                                        message: ControlMessage::WebRtcAnswer { room_id, peer_id, target_peer_id, sdp },
-                                    } => {
-                                // Send answer to target peer
-                                send_to_transport(&transport_registry, &target_peer_id.clone(), message::Frame::control(
-                                    0, 0, ControlMessage::WebRtcAnswer {
-                                        room_id: room_id.clone(),
-                                        peer_id: peer_id.clone(),
-                                        target_peer_id: target_peer_id.clone(),
-                                        sdp,
-                                    }
-                                )).await;
-                                    }
-                                    FramePayload::Control {
-                                        message: ControlMessage::WebRtcIceCandidate { room_id, peer_id, target_peer_id, candidate, sdp_mid, sdp_mline_index },
-                                    } => {
-                                        // Broadcast ICE candidate
-                                        broadcast_to_transport(&transport_registry, &room_id, &peer_id, message::Frame::control(
-                                            0, 0, ControlMessage::WebRtcIceCandidate {
-                                                room_id: room_id.clone(),
-                                                peer_id: peer_id.clone(),
-                                                target_peer_id,
-                                                candidate,
-                                                sdp_mid,
-                                                sdp_mline_index,
-                                            }
-                                        )).await;
-                                    }
-                                    FramePayload::State { message: state_msg } => {
-                                        // Handle quantized state messages (snapshot/delta)
-                                        // For now, use default room_id since state messages don't carry room context
-                                        let default_room_id = "default_room";
-                                        match handle_quantized_state_message(&state_msg, &transport_registry, default_room_id, &connection_id).await {
-                                            Ok(response_frame) => {
-                                                if let Some(frame) = response_frame {
-                                                    broadcast_to_transport(&transport_registry, default_room_id, &connection_id, frame).await;
-                                                }
-                                            }
-                                            Err(e) => {
-                                                eprintln!("Failed to handle quantized state message: {:?}", e);
-                                            }
-                                        }
-                                    }
-                                    _ => {
-                                        // echo nguy├¬n gß╗æc nß║┐u kh├┤ng phß║úi c├íc message ─æß║╖c biß╗çt
-                                        let _ = socket.send(axum::extract::ws::Message::Binary(bytes)).await;
+                            Ok(frame) => {
+                                bandwidth.record(&connection_id, Direction::Received, MessageKind::of(&frame), bytes.len() as u64);
+                                if let Some(reply) = handle_inbound_frame(&mut inbound, frame).await {
+                                    if let Ok(encoded) = message::encode(&reply) {
+                                        bandwidth.record(&connection_id, Direction::Sent, MessageKind::of(&reply), encoded.len() as u64);
+                                        let _ = socket.send(axum::extract::ws::Message::Binary(encoded)).await;
                                     }
                                 }
                             }
                             Err(e) => {
+                                bandwidth.record(&connection_id, Direction::Received, MessageKind::Control, bytes.len() as u64);
                                 eprintln!("Failed to decode message: {:?}", e);
                                 // Send error message back to client
                                 let error_msg = format!("Error: Invalid message format (expected binary protocol)");
+                                bandwidth.record(&connection_id, Direction::Sent, MessageKind::Control, error_msg.len() as u64);
                                 if let Err(send_err) = socket.send(axum::extract::ws::Message::Text(error_msg)).await {
                                     eprintln!("Failed to send error message: {}", send_err);
                                 }
@@ -1190,6 +2320,8 @@ async fn ws_session(
                         }
                     }
                     Some(Ok(axum::extract::ws::Message::Ping(p))) => {
+                        bandwidth.record(&connection_id, Direction::Received, MessageKind::Control, p.len() as u64);
+                        bandwidth.record(&connection_id, Direction::Sent, MessageKind::Control, p.len() as u64);
                         let _ = socket.send(axum::extract::ws::Message::Pong(p)).await;
                     }
                     Some(Ok(axum::extract::ws::Message::Pong(_))) => {
@@ -1200,34 +2332,479 @@ async fn ws_session(
                 }
             }
 
-            // Handle outgoing messages from channel
-            Some(msg) = rx.recv() => {
-                if socket.send(msg).await.is_err() {
-                    break;
+            // Handle outgoing messages from outbox
+            msg = outbox.recv_tagged() => {
+                match msg {
+                    Some((kind, msg)) => {
+                        bandwidth.record_message(&connection_id, Direction::Sent, MessageKind::of_outbound(kind), &msg);
+                        // Close frame do server đẩy vào (kick) là frame cuối của connection
+                        let closing = matches!(msg, axum::extract::ws::Message::Close(_));
+                        if closing {
+                            close_reason = analytics::SERVER_CLOSED;
+                        }
+                        if socket.send(msg).await.is_err() || closing {
+                            break;
+                        }
+                    }
+                    None => {
+                        tracing::warn!(%peer_id, %connection_id, "closing saturated websocket");
+                        close_reason = analytics::SATURATED;
+                        break;
+                    }
                 }
             }
         }
     }
 
     // Cleanup
-    {
-        let mut ws_reg = ws_registry.write().await;
-        ws_reg.remove(&connection_id);
+    outbox.close();
+    bandwidth.disconnect(&connection_id);
+    let joined_room = ws_registry
+        .remove(&connection_id)
+        .map(|conn| conn.room_id)
+        .filter(|room_id| room_id != "unknown");
+    let mut disconnect = analytics.event(analytics::DISCONNECT, &connection_id, &peer_id);
+    disconnect.room_id = joined_room.clone().unwrap_or_default();
+    disconnect.reason = close_reason.to_string();
+    disconnect.duration_ms = Some(session_started.elapsed().as_millis() as u64);
+    analytics.emit(disconnect);
+    if let Some(room_id) = joined_room {
+        // Worker giữ entity của player trong rejoin grace thay vì xoá ngay
+        let player_id = peer_id.clone();
+        tokio::spawn(async move {
+            let request = proto::worker::v1::NotifyDisconnectRequest { room_id, player_id };
+            if let Err(e) = worker_client.notify_disconnect(request).await {
+                tracing::debug!(error = %e, "gateway: notify_disconnect failed");
+            }
+        });
     }
 
-    {
-        let mut transport_reg = transport_registry.write().await;
-        if let Some(transport_conn) = transport_reg.remove(&connection_id) {
-            // Update metrics on disconnect
-            if transport_conn.transport.kind() == TransportKind::WebRtc {
-                WEBRTC_CONNECTIONS_CURRENT.with_label_values(&["connected"]).dec();
-            }
+    if let Some(transport_conn) = transport_registry.remove(&connection_id) {
+        // Update metrics on disconnect
+        if transport_conn.kind() == TransportKind::WebRtc {
+            metrics::record_webrtc_disconnected();
         }
     }
 
     let _ = socket.close().await;
 }
 
+/// Text frame là JSON: một `Frame` đầy đủ, hoặc chỉ `ControlMessage`/`StateMessage` (sequence 0)
+fn parse_text_frame(text: &str) -> Result<Frame, serde_json::Error> {
+    if let Ok(frame) = message::decode(text.as_bytes()) {
+        return Ok(frame);
+    }
+    if let Ok(control) = serde_json::from_str::<ControlMessage>(text) {
+        return Ok(Frame::control(0, 0, control));
+    }
+    serde_json::from_str::<StateMessage>(text).map(|state| Frame::state(0, 0, state))
+}
+
+/// Trạng thái nhận frame của một ws session
+struct InboundSession {
+    peer_id: String,
+    connection_id: String,
+    ws_registry: WebSocketRegistry,
+    transport_registry: TransportRegistry,
+    outbound: OutboundSequence,
+    dedupe: FrameDedupe,
+    snapshots: snapshots::SnapshotBroadcaster,
+    bandwidth: bandwidth::BandwidthTracker,
+    latency: latency::LatencyTracker,
+    worker_client: worker_client::WorkerRpcClient,
+    /// Lấy tên hiển thị đã chốt khi player join room
+    room_manager: room_client::RoomManagerClient,
+    rtc_peers: rtc::ServerPeers,
+    /// Vừa trả lời offer WebRTC gửi qua WS; ws session bắt đầu chờ DataChannel
+    rtc_offer_pending: bool,
+    analytics: analytics::SessionAnalytics,
+}
+
+/// Tên hiển thị room-manager đã chốt (qua name policy) cho player trong room. Room không qua room-manager
+/// hoặc không gọi được thì trả chuỗi rỗng để worker tự chọn tên, tên thô của client không bao giờ được gửi đi
+async fn resolved_player_name(room_manager: &room_client::RoomManagerClient, room_id: &str, player_id: &str) -> String {
+    match room_manager.list_players(room_id).await {
+        Ok(response) => response
+            .players
+            .into_iter()
+            .find(|player| player.id == player_id)
+            .map(|player| player.name)
+            .unwrap_or_default(),
+        Err(e) => {
+            tracing::debug!(error = %e, room_id, player_id, "gateway: could not look up player name");
+            String::new()
+        }
+    }
+}
+
+impl InboundSession {
+    /// Gán room cho connection trong cả ws registry lẫn transport registry
+    async fn set_room(&self, room_id: &str) {
+        self.ws_registry.set_room(&self.connection_id, room_id);
+        self.transport_registry.set_room(&self.connection_id, room_id);
+        self.bandwidth.set_room(&self.connection_id, room_id);
+        self.latency.reset_room();
+        tracing::Span::current().record("room_id", room_id);
+    }
+
+    /// Room connection đang ở, None khi chưa join
+    fn room(&self) -> Option<String> {
+        self.ws_registry.room(&self.connection_id).filter(|room_id| room_id != "unknown")
+    }
+
+    /// Event analytics `room_joined`/`room_left` cho room hiện tại; chưa ở room nào thì không gửi
+    fn emit_room_event(&self, event_type: &str) {
+        if let Some(room_id) = self.room() {
+            let mut event = self.analytics.event(event_type, &self.connection_id, &self.peer_id);
+            event.room_id = room_id;
+            self.analytics.emit(event);
+        }
+    }
+
+    /// Spawn (hoặc nối lại entity trong rejoin grace) player của connection trong worker.
+    /// Worker không tới được thì connection vẫn join room ở gateway, chỉ không có entity
+    async fn join_worker(&mut self, room_id: &str) {
+        let request = proto::worker::v1::JoinRoomRequest {
+            room_id: room_id.to_string(),
+            player_id: self.peer_id.clone(),
+            player_name: resolved_player_name(&self.room_manager, room_id, &self.peer_id).await,
+        };
+        if let Err(e) = self.worker_client.join_room(request).await {
+            tracing::debug!(error = %e, peer_id = %self.peer_id, room_id, "gateway: ws join_room failed");
+        }
+    }
+
+    /// Đẩy input của client vào worker; player_id luôn lấy từ JWT, không tin payload
+    async fn push_input(&mut self, room_id: String, seq: u32, payload: serde_json::Value) -> Result<(), String> {
+        let mut payload = match payload {
+            serde_json::Value::Object(fields) => fields,
+            _ => return Err("Input payload must be a JSON object".to_string()),
+        };
+        payload.insert("player_id".to_string(), serde_json::json!(self.peer_id));
+        payload.insert("input_sequence".to_string(), serde_json::json!(seq));
+        let request = proto::worker::v1::PushInputRequest {
+            room_id,
+            sequence: seq,
+            payload_json: serde_json::Value::Object(payload).to_string(),
+        };
+        match self.worker_client.push_input(request).await {
+            Ok(response) if response.get_ref().ok => Ok(()),
+            Ok(response) => Err(response.into_inner().error),
+            Err(status) => Err(status.message().to_string()),
+        }
+    }
+
+    /// Chat đi qua worker để mọi gateway có client trong room đều nhận qua event stream; room chưa có stream
+    /// hoặc worker không nhận thì gateway tự gửi cho các connection của mình
+    async fn send_chat(&mut self, room_id: &str, text: &str) {
+        if self.snapshots.has_event_stream(room_id).await {
+            let request = proto::worker::v1::SendChatRequest {
+                room_id: room_id.to_string(),
+                player_id: self.peer_id.clone(),
+                message: text.to_string(),
+            };
+            match self.worker_client.send_chat(request).await {
+                Ok(response) if response.get_ref().ok => return,
+                Ok(response) => tracing::debug!(error = %response.get_ref().error, room_id, "gateway: worker rejected chat"),
+                Err(status) => tracing::debug!(%status, room_id, "gateway: worker send_chat failed"),
+            }
+        }
+        self.broadcast_chat(room_id, text);
+    }
+
+    /// Gửi chat cho mọi connection trong room, kể cả người gửi
+    fn broadcast_chat(&self, room_id: &str, text: &str) {
+        let state = StateMessage::Event {
+            name: "chat".to_string(),
+            data: serde_json::json!({ "player_id": self.peer_id, "message": text, "timestamp_ms": now_millis() }),
+        };
+        let members = self.ws_registry.filter_map(|connection_id, conn| {
+            (conn.room_id == room_id).then(|| (connection_id.to_string(), conn.outbound.clone(), conn.outbox.clone()))
+        });
+        for (connection_id, outbound, outbox) in members {
+            let frame = outbound.stamp(Frame::state(0, 0, state.clone()));
+            if let Ok(bytes) = message::encode(&frame) {
+                self.bandwidth.record(&connection_id, bandwidth::Direction::Sent, bandwidth::MessageKind::Chat, bytes.len() as u64);
+                outbox.push(outbox::OutboundKind::Control, axum::extract::ws::Message::Binary(bytes));
+            }
+        }
+    }
+
+    /// Nonce mới cho connection này, gửi cho client trong `MigrationToken`
+    async fn issue_migration_token(&self) -> Frame {
+        let auth_nonce = uuid::Uuid::new_v4().simple().to_string();
+        self.transport_registry.set_migration_nonce(&self.connection_id, auth_nonce.clone());
+        self.outbound.stamp(Frame::control(0, 0, ControlMessage::MigrationToken {
+            connection_id: self.connection_id.clone(),
+            auth_nonce,
+        }))
+    }
+
+    /// Chuyển room binding của `from_connection_id` sang connection này rồi đóng connection cũ.
+    /// Frame còn chờ gửi ở connection cũ được đóng lại sequence của connection mới và gửi tiếp, frame không
+    /// chuyển được thì đếm là dropped. Worker không thấy gì: player vẫn ở trong room, không có leave/join.
+    /// Trả về room_id đã chuyển
+    async fn migrate_from(&self, from_connection_id: &str, auth_nonce: &str) -> Result<String, &'static str> {
+        use axum::extract::ws::Message;
+
+        if from_connection_id == self.connection_id {
+            return Err("Cannot migrate a connection onto itself");
+        }
+
+        let Some(old) = self.transport_registry.take_for_migration(from_connection_id, &self.peer_id, auth_nonce) else {
+            return Err("Unknown connection or invalid migration nonce");
+        };
+        let room_id = old.room_id.clone();
+
+        // Gỡ connection cũ khỏi ws registry rồi mới gán room cho connection mới: snapshot broadcast có thể lỡ
+        // một tick (caller gọi lại ensure_room) nhưng không bao giờ thấy room ở cả hai connection
+        let old_ws = self.ws_registry.remove(from_connection_id);
+        let to_kind = self
+            .transport_registry
+            .set_room(&self.connection_id, &room_id)
+            .unwrap_or(TransportKind::WebSocket);
+        self.ws_registry.set_room(&self.connection_id, &room_id);
+        let new_outbox = self.ws_registry.outbox(&self.connection_id);
+
+        let mut dropped = 0;
+        if let Some(old_ws) = old_ws {
+            for (kind, msg) in old_ws.outbox.take_queued() {
+                let restamped = match (&msg, &new_outbox) {
+                    (Message::Binary(bytes), Some(outbox)) => message::decode(bytes)
+                        .ok()
+                        .and_then(|frame| message::encode(&self.outbound.stamp(frame)).ok())
+                        .map(|bytes| outbox.push(kind, Message::Binary(bytes))),
+                    _ => None,
+                };
+                if restamped.is_none() {
+                    dropped += 1;
+                }
+            }
+            // Session cũ gửi close frame rồi thoát; registry không còn connection nên không báo disconnect cho worker
+            old_ws.outbox.push(outbox::OutboundKind::Control, Message::Close(None));
+        }
+
+        if old.kind() == TransportKind::WebRtc {
+            metrics::record_webrtc_disconnected();
+        }
+        metrics::record_transport_migration(transport_label(old.kind()), transport_label(to_kind), dropped);
+        self.bandwidth.set_room(&self.connection_id, &room_id);
+        self.latency.reset_room();
+        tracing::Span::current().record("room_id", room_id.as_str());
+        Ok(room_id)
+    }
+}
+
+/// Xử lý một frame client gửi lên; trả về frame (đã đóng sequence) cần gửi lại cho chính client đó
+async fn handle_inbound_frame(session: &mut InboundSession, frame: Frame) -> Option<Frame> {
+    // Frame trùng vẫn mang ack mới nhất của client
+    session.outbound.on_receive(&frame);
+    if session.dedupe.is_duplicate(&session.peer_id, frame.sequence) {
+        metrics::record_frame_deduped();
+        return None;
+    }
+
+    let peer_id = session.peer_id.clone();
+    let transport_registry = &session.transport_registry;
+
+    match frame.payload {
+        FramePayload::Control {
+            message: ControlMessage::Ping { nonce },
+        } => Some(session.outbound.stamp(Frame::control(0, 0, ControlMessage::Pong { nonce }))),
+        // Frame chỉ mang `acks`, đã xử lý ở `on_receive`
+        FramePayload::Control {
+            message: ControlMessage::Ack,
+        } => None,
+        FramePayload::Control {
+            message: ControlMessage::Pong { nonce },
+        } => {
+            // Nonce lạ (không phải Ping của gateway) bỏ qua
+            session.latency.on_pong(nonce);
+            None
+        }
+        FramePayload::Control {
+            message: ControlMessage::JoinRoom { room_id, .. },
+        } => {
+            if session.room().is_some_and(|previous| previous != room_id) {
+                session.emit_room_event(analytics::ROOM_LEFT);
+            }
+            session.set_room(&room_id).await;
+            session.emit_room_event(analytics::ROOM_JOINED);
+            session.join_worker(&room_id).await;
+            session.snapshots.ensure_room(&room_id).await;
+            Some(session.issue_migration_token().await)
+        }
+        FramePayload::Control {
+            message: ControlMessage::Input { seq, payload },
+        } => {
+            let Some(room_id) = session.room() else {
+                return Some(session.outbound.stamp(Frame::control(0, 0, ControlMessage::Error {
+                    code: "not_in_room".to_string(),
+                    message: "Join a room before sending input".to_string(),
+                })));
+            };
+            match session.push_input(room_id, seq, payload).await {
+                Ok(()) => None,
+                Err(reason) => Some(session.outbound.stamp(Frame::control(0, 0, ControlMessage::Error {
+                    code: "input_rejected".to_string(),
+                    message: reason,
+                }))),
+            }
+        }
+        FramePayload::State {
+            message: StateMessage::Event { name, data },
+        } if name == "chat" => {
+            let text = data.get("message").and_then(|v| v.as_str()).unwrap_or_default().trim();
+            let error = match session.room() {
+                None => Some(("not_in_room", "Join a room before chatting".to_string())),
+                Some(_) if text.is_empty() || text.chars().count() > MAX_WS_CHAT_CHARS => {
+                    Some(("invalid_chat", format!("Chat message must be 1-{MAX_WS_CHAT_CHARS} characters")))
+                }
+                Some(room_id) => {
+                    session.send_chat(&room_id, text).await;
+                    None
+                }
+            };
+            error.map(|(code, message)| {
+                session.outbound.stamp(Frame::control(0, 0, ControlMessage::Error {
+                    code: code.to_string(),
+                    message,
+                }))
+            })
+        }
+        FramePayload::Control {
+            message: ControlMessage::MigrateTransport { from_connection_id, auth_nonce },
+        } => match session.migrate_from(&from_connection_id, &auth_nonce).await {
+            Ok(room_id) => {
+                tracing::info!(%peer_id, from = %from_connection_id, to = %session.connection_id, %room_id, "gateway: transport migrated");
+                session.snapshots.ensure_room(&room_id).await;
+                Some(session.issue_migration_token().await)
+            }
+            Err(reason) => Some(session.outbound.stamp(Frame::control(0, 0, ControlMessage::Error {
+                code: "migration_rejected".to_string(),
+                message: reason.to_string(),
+            }))),
+        },
+        FramePayload::Control {
+            message: ControlMessage::RequestKeyframe { room_id, .. },
+        } => {
+            // Chỉ resync stream của chính connection này, không tin player_id client gửi
+            session
+                .snapshots
+                .request_keyframe(&room_id, &peer_id)
+                .await
+                .map(|frame| session.outbound.stamp(frame))
+        }
+        FramePayload::Control {
+            message: ControlMessage::LeaveRoom,
+        } => {
+            // Task broadcast của room cũ tự dừng khi không còn connection
+            session.emit_room_event(analytics::ROOM_LEFT);
+            session.set_room("unknown").await;
+            None
+        }
+        FramePayload::Control {
+            message: ControlMessage::WebRtcOffer { room_id, target_peer_id: Some(target), sdp, .. },
+        } if target == rtc::GATEWAY_PEER_ID => {
+            // Offer gửi cho chính gateway: trả answer ngay, ws session chờ DataChannel mở rồi chuyển transport
+            match session.rtc_peers.accept_offer(&peer_id, &sdp).await {
+                Ok(answer) => {
+                    session.rtc_offer_pending = true;
+                    metrics::record_webrtc_signal(metrics::WebRtcSignal::Answer);
+                    Some(session.outbound.stamp(Frame::control(0, 0, ControlMessage::WebRtcAnswer {
+                        room_id,
+                        peer_id: rtc::GATEWAY_PEER_ID.to_string(),
+                        target_peer_id: peer_id,
+                        sdp: answer,
+                    })))
+                }
+                Err(err) => Some(session.outbound.stamp(Frame::control(0, 0, ControlMessage::Error {
+                    code: "invalid_offer".to_string(),
+                    message: err.to_string(),
+                }))),
+            }
+        }
+        FramePayload::Control {
+            message: ControlMessage::WebRtcOffer { room_id, target_peer_id, sdp, .. },
+        } => {
+            // Update connection info (peer_id đã cố định từ JWT lúc upgrade)
+            session.set_room(&room_id).await;
+
+            // Broadcast offer to other peers in room
+            broadcast_to_transport(transport_registry, &session.bandwidth, &room_id, &peer_id, Frame::control(
+                0, 0, ControlMessage::WebRtcOffer {
+                    room_id: room_id.clone(),
+                    peer_id: peer_id.clone(),
+                    target_peer_id,
+                    sdp,
+                }
+            )).await;
+            None
+        }
+        FramePayload::Control {
+            message: ControlMessage::WebRtcAnswer { room_id, target_peer_id, sdp, .. },
+        } => {
+            // Send answer to target peer
+            send_to_transport(transport_registry, &session.bandwidth, &target_peer_id, Frame::control(
+                0, 0, ControlMessage::WebRtcAnswer {
+                    room_id,
+                    peer_id,
+                    target_peer_id: target_peer_id.clone(),
+                    sdp,
+                }
+            )).await;
+            None
+        }
+        FramePayload::Control {
+            message: ControlMessage::WebRtcIceCandidate { target_peer_id: Some(target), candidate, sdp_mid, sdp_mline_index, .. },
+        } if target == rtc::GATEWAY_PEER_ID => {
+            let candidate = IceCandidate { candidate, sdp_mid, sdp_mline_index };
+            match session.rtc_peers.add_ice_candidate(&peer_id, candidate).await {
+                Some(Err(err)) => Some(session.outbound.stamp(Frame::control(0, 0, ControlMessage::Error {
+                    code: "invalid_ice_candidate".to_string(),
+                    message: err.to_string(),
+                }))),
+                Some(Ok(())) | None => None,
+            }
+        }
+        FramePayload::Control {
+            message: ControlMessage::WebRtcIceCandidate { room_id, target_peer_id, candidate, sdp_mid, sdp_mline_index, .. },
+        } => {
+            // Broadcast ICE candidate
+            broadcast_to_transport(transport_registry, &session.bandwidth, &room_id, &peer_id, Frame::control(
+                0, 0, ControlMessage::WebRtcIceCandidate {
+                    room_id: room_id.clone(),
+                    peer_id: peer_id.clone(),
+                    target_peer_id,
+                    candidate,
+                    sdp_mid,
+                    sdp_mline_index,
+                }
+            )).await;
+            None
+        }
+        FramePayload::State { message: state_msg } => {
+            // Handle quantized state messages (snapshot/delta)
+            // For now, use default room_id since state messages don't carry room context
+            let default_room_id = "default_room";
+            match handle_quantized_state_message(&state_msg, transport_registry, default_room_id, &session.connection_id).await {
+                Ok(Some(frame)) => {
+                    broadcast_to_transport(transport_registry, &session.bandwidth, default_room_id, &session.connection_id, frame).await;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Failed to handle quantized state message: {:?}", e);
+                }
+            }
+            None
+        }
+        payload => {
+            // echo nguyên gốc nếu không phải các message đặc biệt, timestamp là giờ gateway chứ không phải của client
+            Some(session.outbound.stamp(Frame { payload, timestamp_ms: 0, ..frame }))
+        }
+    }
+}
+
 // Handle quantized state messages (snapshot/delta encoding)
 async fn handle_quantized_state_message(
     state_msg: &StateMessage,
@@ -1238,7 +2815,7 @@ async fn handle_quantized_state_message(
     let quantization_config = QuantizationConfig::default();
 
     match state_msg {
-        StateMessage::Snapshot { tick, entities } => {
+        StateMessage::Snapshot { tick, entities, .. } => {
             // TODO: Implement quantized snapshot encoding when binary protocol is ready
             // For now, forward as regular event for testing
             let event_frame = Frame::state(
@@ -1255,7 +2832,7 @@ async fn handle_quantized_state_message(
             );
             Ok(Some(event_frame))
         }
-        StateMessage::Delta { tick, changes } => {
+        StateMessage::Delta { tick, changes, .. } => {
             // TODO: Implement quantized delta encoding when binary protocol is ready
             // For now, forward as regular event for testing
             let event_frame = Frame::state(
@@ -1288,53 +2865,106 @@ async fn handle_quantized_state_message(
     }
 }
 
-// Helper function to establish WebRTC connection with fallback
-async fn try_establish_webrtc(transport: &mut WebRtcTransport) -> bool {
-    // In a real implementation, this would:
-    // 1. Wait for WebRTC signaling to complete
-    // 2. Establish DataChannels
-    // 3. Return true if successful
+/// Transport cho ws session theo thứ tự QUIC (peer đã dial và xác thực) → WebRTC → WebSocket.
+/// Trả về transport, lựa chọn kèm lý do, và phía nhận của DataChannel khi chọn được WebRTC.
+async fn select_transport(
+    quic_sessions: &quic::QuicSessions,
+    rtc_peers: &rtc::ServerPeers,
+    peer_id: &str,
+    connection_id: &str,
+) -> (Box<dyn GameTransport + Send + Sync>, TransportSelection, Option<WebRtcInbound>) {
+    if let Some(quic_transport) = quic_sessions.take(peer_id) {
+        let selection = TransportSelection {
+            kind: TransportKind::Quic,
+            fallback_used: false,
+            reason: TransportSelection::QUIC_AUTHENTICATED,
+        };
+        return (Box::new(quic_transport), selection, None);
+    }
+
+    let reason = match try_establish_webrtc(rtc_peers, peer_id).await {
+        Some(Ok(mut webrtc_transport)) => {
+            let inbound = webrtc_transport.take_inbound();
+            let selection = TransportSelection {
+                kind: TransportKind::WebRtc,
+                fallback_used: false,
+                reason: TransportSelection::WEBRTC_CONNECTED,
+            };
+            return (Box::new(webrtc_transport), selection, inbound);
+        }
+        Some(Err(err)) => {
+            tracing::warn!(%err, %peer_id, %connection_id, "gateway: webrtc không lập được, dùng websocket");
+            TransportSelection::WEBRTC_FAILED
+        }
+        None => TransportSelection::WEBRTC_UNAVAILABLE,
+    };
+
+    // Fallback to WebSocket transport: dùng chính ws connection hiện tại
+    let mut fallback_transport = WebRtcTransport::new("unknown".to_string(), "unknown".to_string());
+    fallback_transport.fallback_to_websocket().await.unwrap();
+    let selection = TransportSelection {
+        kind: TransportKind::WebSocket,
+        fallback_used: true,
+        reason,
+    };
+    (Box::new(fallback_transport), selection, None)
+}
+
+/// Peer đã gửi offer (qua `/rtc/offer`) trước khi mở WS: chờ DataChannel mở, quá timeout thì lỗi để fallback.
+/// None khi peer không thương lượng WebRTC với gateway
+async fn try_establish_webrtc(rtc_peers: &rtc::ServerPeers, peer_id: &str) -> Option<Result<WebRtcTransport, TransportError>> {
+    rtc_peers.connect(peer_id, "unknown").await
+}
+
+/// Chờ DataChannel của offer gửi qua WS mở, chạy song song với vòng lặp của ws session
+type RtcUpgrade = std::pin::Pin<Box<dyn std::future::Future<Output = Option<Result<WebRtcTransport, TransportError>>> + Send>>;
 
-    // For now, we'll simulate a successful connection
-    // In production, this should implement actual WebRTC negotiation
-    transport.set_connected(true).await;
-    true
+/// Frame tiếp theo client gửi qua DataChannel; không có DataChannel thì chờ mãi (nhánh select không bao giờ chạy)
+async fn recv_rtc_frame(inbound: &mut Option<WebRtcInbound>) -> Option<Frame> {
+    match inbound {
+        Some(inbound) => inbound.recv().await,
+        None => std::future::pending().await,
+    }
 }
 
 // Helper functions for transport-based message relay
+/// Lấy danh sách connection trong room ra khỏi registry rồi mới gửi, không giữ shard lock nào khi `.await`
 async fn broadcast_to_transport(
     transport_registry: &TransportRegistry,
+    bandwidth: &bandwidth::BandwidthTracker,
     room_id: &str,
     sender_peer_id: &str,
     frame: message::Frame,
 ) {
-    let mut reg = transport_registry.write().await;
-
-    for (_conn_id, transport_conn) in reg.iter_mut() {
-        if transport_conn.room_id == room_id && transport_conn.peer_id != sender_peer_id {
-            // Send frame through transport abstraction
-            if let Err(e) = transport_conn.transport.send_frame(frame.clone()).await {
-                eprintln!("Failed to send frame via transport: {:?}", e);
-            }
-        }
+    for target in transport_registry.room_targets(room_id, sender_peer_id) {
+        send_via_target(bandwidth, &target, frame.clone()).await;
     }
 }
 
 async fn send_to_transport(
     transport_registry: &TransportRegistry,
+    bandwidth: &bandwidth::BandwidthTracker,
     target_peer_id: &str,
     frame: message::Frame,
 ) {
-    let mut reg = transport_registry.write().await;
+    if let Some(target) = transport_registry.peer_target(target_peer_id) {
+        send_via_target(bandwidth, &target, frame).await;
+    }
+}
 
-    for (_conn_id, transport_conn) in reg.iter_mut() {
-        if transport_conn.peer_id == target_peer_id {
-            // Send frame through transport abstraction
-            if let Err(e) = transport_conn.transport.send_frame(frame.clone()).await {
-                eprintln!("Failed to send frame via transport: {:?}", e);
-            }
-            break;
-        }
+async fn send_via_target(bandwidth: &bandwidth::BandwidthTracker, target: &registry::TransportTarget, frame: message::Frame) {
+    // Send frame through transport abstraction
+    let (frame, result) = target.send(frame).await;
+    record_relayed(bandwidth, &target.connection_id, &frame);
+    if let Err(e) = result {
+        eprintln!("Failed to send frame via transport: {:?}", e);
+    }
+}
+
+/// Transport tự encode frame nên ở đây đếm theo kích thước JSON của frame
+fn record_relayed(bandwidth: &bandwidth::BandwidthTracker, connection_id: &str, frame: &message::Frame) {
+    if let Ok(bytes) = message::encode(frame) {
+        bandwidth.record(connection_id, bandwidth::Direction::Sent, bandwidth::MessageKind::of(frame), bytes.len() as u64);
     }
 }
 
@@ -1345,15 +2975,15 @@ async fn broadcast_webrtc_message(
     sender_peer_id: &str,
     frame: message::Frame,
 ) {
-    let reg = registry.read().await;
     let encoded = message::encode(&frame);
 
     match encoded {
         Ok(bytes) => {
-            for (_conn_id, conn) in reg.iter() {
-                if conn.room_id == room_id && conn.peer_id != sender_peer_id {
-                    let _ = conn.sender.send(axum::extract::ws::Message::Binary(bytes.clone()));
-                }
+            let outboxes = registry.filter_map(|_, conn| {
+                (conn.room_id == room_id && conn.peer_id != sender_peer_id).then(|| conn.outbox.clone())
+            });
+            for outbox in outboxes {
+                outbox.push(outbox::OutboundKind::Control, axum::extract::ws::Message::Binary(bytes.clone()));
             }
         }
         Err(e) => {
@@ -1367,16 +2997,12 @@ async fn send_to_peer(
     target_peer_id: &str,
     frame: message::Frame,
 ) {
-    let reg = registry.read().await;
     let encoded = message::encode(&frame);
 
     match encoded {
         Ok(bytes) => {
-            for (_conn_id, conn) in reg.iter() {
-                if conn.peer_id == target_peer_id {
-                    let _ = conn.sender.send(axum::extract::ws::Message::Binary(bytes.clone()));
-                    break;
-                }
+            if let Some(outbox) = registry.for_peer(target_peer_id, |conn| conn.outbox.clone()).into_iter().next() {
+                outbox.push(outbox::OutboundKind::Control, axum::extract::ws::Message::Binary(bytes));
             }
         }
         Err(e) => {
@@ -1391,103 +3017,211 @@ async fn send_to_peer(
 async fn leaderboard_handler(
     State(state): State<AppState>,
     Query(params): Query<std::collections::HashMap<String, String>>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/leaderboard"]).inc();
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request("/api/leaderboard");
 
     let game_mode = params.get("game_mode").map(|s| s.as_str());
+    if let Some(game_mode) = game_mode {
+        check_game_mode(game_mode)?;
+    }
     let time_range = params.get("time_range").map(|s| s.as_str()).unwrap_or("all_time");
     let limit = params.get("limit")
         .and_then(|s| s.parse::<usize>().ok())
         .unwrap_or(10);
 
-    // For now, return mock leaderboard data since we don't have PocketBase integration yet
-    // In a real implementation, this would query PocketBase for actual leaderboard data
-    let leaderboard_data = match game_mode {
-        Some("endless_runner") | None => {
-            vec![
-                serde_json::json!({
-                    "rank": 1,
-                    "player_id": "player_001",
-                    "player_name": "Speed Demon",
-                    "score": 15420,
-                    "game_mode": "endless_runner",
-                    "timestamp": chrono::Utc::now().timestamp()
-                }),
-                serde_json::json!({
-                    "rank": 2,
-                    "player_id": "player_002",
-                    "player_name": "Track Master",
-                    "score": 12850,
-                    "game_mode": "endless_runner",
-                    "timestamp": chrono::Utc::now().timestamp()
-                }),
-                serde_json::json!({
-                    "rank": 3,
-                    "player_id": "player_003",
-                    "player_name": "Jump King",
-                    "score": 11200,
-                    "game_mode": "endless_runner",
-                    "timestamp": chrono::Utc::now().timestamp()
-                }),
-            ]
-        }
-        _ => Vec::new(),
-    };
+    let game_mode = game_mode.unwrap_or(game_modes::ENDLESS_RUNNER);
+    if let Some(store) = state.leaderboard.as_ref() {
+        let selector = services::seasons::SeasonSelector::parse(params.get("season").map(String::as_str));
+        return season_leaderboard_response(store, game_mode, &selector, limit.min(LEADERBOARD_MAX_LIMIT)).await;
+    }
+
+    // Không có PocketBase: bảng trong memory chỉ gồm điểm đã qua kiểm tra của process này
+    let leaderboard_data: Vec<serde_json::Value> = state
+        .memory_leaderboard
+        .standings(game_mode, limit.min(LEADERBOARD_MAX_LIMIT))
+        .iter()
+        .map(|standing| serde_json::json!({
+            "rank": standing.rank,
+            "player_id": standing.user_id,
+            "player_name": standing.username,
+            "score": standing.score,
+            "game_mode": game_mode,
+        }))
+        .collect();
 
     let response = serde_json::json!({
         "success": true,
         "leaderboard": leaderboard_data,
-        "game_mode": game_mode.unwrap_or("all"),
+        "game_mode": game_mode,
         "time_range": time_range,
         "total": leaderboard_data.len()
     });
 
-    Json(response).into_response()
+    Ok(Json(response))
+}
+
+/// 422 kèm danh sách id hợp lệ nếu `game_mode` chưa đăng ký trong registry
+fn check_game_mode(game_mode: &str) -> Result<(), GatewayError> {
+    GameMode::parse(game_mode)
+        .map(|_| ())
+        .map_err(|err| GatewayError::Validation(vec![types::FieldError::new("game_mode", err.to_string())]))
+}
+
+/// Bảng xếp hạng của season được chọn trong PocketBase; season id lạ (hoặc của game mode khác) trả 404
+async fn season_leaderboard_response(
+    store: &services::persistence::PocketBaseStore,
+    game_mode: &str,
+    selector: &services::seasons::SeasonSelector,
+    limit: usize,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    match services::seasons::standings(store, game_mode, selector, limit).await {
+        Ok(Some(standings)) => {
+            let entries: Vec<serde_json::Value> = standings
+                .standings
+                .iter()
+                .map(|standing| serde_json::json!({
+                    "rank": standing.rank,
+                    "player_id": standing.user_id,
+                    "player_name": standing.username,
+                    "score": standing.score,
+                    "game_mode": game_mode,
+                }))
+                .collect();
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "leaderboard": entries,
+                "game_mode": game_mode,
+                "season": standings.season.as_ref().map_or(services::seasons::ALL_TIME_SEASON, |season| season.id.as_str()),
+                "season_active": standings.season.as_ref().map(|season| season.active),
+                "total": entries.len()
+            })))
+        }
+        Ok(None) => Err(GatewayError::NotFound("Unknown season".to_string())),
+        Err(e) => {
+            tracing::error!(error = %e, game_mode, "failed to load leaderboard");
+            Err(GatewayError::Internal("Failed to load leaderboard".to_string()))
+        }
+    }
 }
 
-// Submit score to leaderboard
+/// Submit điểm từ client, chỉ còn trong giai đoạn chuyển tiếp: player lấy từ JWT, điểm phải dưới trần của mode,
+/// trong hạn mức mỗi giờ và player phải có room gần đây cùng mode (`room_id`) theo room-manager
 async fn submit_score_handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/leaderboard/submit"]).inc();
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request("/api/leaderboard/submit");
 
-    let player_id = request.get("player_id").and_then(|v| v.as_str()).unwrap_or("anonymous");
-    let player_name = request.get("player_name").and_then(|v| v.as_str()).unwrap_or("Anonymous");
+    let player_id = extract_user_id_from_headers(&headers, &state.auth_service)?;
+    if request.get("player_id").and_then(|v| v.as_str()).is_some_and(|claimed| claimed != player_id) {
+        return Err(GatewayError::Forbidden("player_id does not match token".to_string()));
+    }
+    let player_name = request.get("player_name").and_then(|v| v.as_str()).unwrap_or(&player_id);
     let score = request.get("score").and_then(|v| v.as_u64()).unwrap_or(0);
-    let game_mode = request.get("game_mode").and_then(|v| v.as_str()).unwrap_or("endless_runner");
+    let game_mode = request.get("game_mode").and_then(|v| v.as_str()).unwrap_or(game_modes::ENDLESS_RUNNER);
+    let room_id = request.get("room_id").and_then(|v| v.as_str());
+    check_game_mode(game_mode)?;
 
     // Validate inputs
     if score == 0 {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Score must be greater than 0"
-        })).into_response();
+        return Err(GatewayError::Validation(vec![types::FieldError::new("score", "must be greater than 0")]));
+    }
+
+    let checked = match scores::check_max_score(game_mode, score) {
+        Ok(()) => scores::check_room_membership(&state.room_manager, room_id, &player_id, game_mode).await,
+        Err(rejection) => Err(rejection),
+    };
+    if let Err(rejection) = checked.and_then(|()| state.score_limiter.check(&player_id, std::time::Instant::now())) {
+        return Err(score_rejected(rejection, "client", &player_id, game_mode, score));
     }
 
-    if player_id.trim().is_empty() {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Player ID is required"
-        })).into_response();
+    record_score(&state, game_mode, &player_id, player_name, score).await
+}
+
+/// Điểm do worker chốt từ simulation lúc hết trận (hoặc lúc chết ở endless runner). Chỉ nhận request có shared secret
+/// nội bộ và player phải thuộc room theo room-manager; không áp trần điểm hay hạn mức như submit của client
+async fn worker_score_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(submission): Json<common_net::scores::ScoreSubmission>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request(common_net::scores::SCORES_PATH);
+
+    // Chưa cấu hình secret thì không có gì để so: từ chối mọi request thay vì tin một giá trị mặc định
+    let Some(secret) = state.room_manager.secret() else {
+        return Err(authentication_failed());
+    };
+    let authorized = headers
+        .get(room_manager::api::INTERNAL_SECRET_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value == secret);
+    if !authorized {
+        return Err(authentication_failed());
+    }
+    check_game_mode(&submission.game_mode)?;
+    if let Err(rejection) = scores::check_room_membership(
+        &state.room_manager,
+        Some(&submission.room_id),
+        &submission.player_id,
+        &submission.game_mode,
+    )
+    .await
+    {
+        return Err(score_rejected(rejection, "worker", &submission.player_id, &submission.game_mode, submission.score));
     }
 
-    // For now, just log the score submission since we don't have PocketBase integration yet
-    // In a real implementation, this would save the score to PocketBase
     tracing::info!(
-        player_id,
-        player_name,
-        score,
-        game_mode,
-        "Score submitted to leaderboard"
+        room_id = %submission.room_id,
+        tick = submission.tick,
+        player_id = %submission.player_id,
+        score = submission.score,
+        "gateway: nhận điểm từ worker"
     );
+    record_score(&state, &submission.game_mode, &submission.player_id, &submission.player_name, submission.score).await
+}
+
+/// Submit bị từ chối được log kèm lý do và đếm vào metric thay vì bỏ qua im lặng
+fn score_rejected(rejection: scores::Rejection, source: &str, player_id: &str, game_mode: &str, score: u64) -> GatewayError {
+    tracing::warn!(reason = rejection.reason(), source, player_id, game_mode, score, "gateway: từ chối điểm leaderboard");
+    metrics::record_score_rejected(rejection.reason());
+    GatewayError::ScoreRejected(rejection)
+}
+
+/// Ghi điểm đã qua kiểm tra vào leaderboard theo season trong PocketBase, hoặc bảng trong memory khi không có PocketBase
+async fn record_score(
+    state: &AppState,
+    game_mode: &str,
+    player_id: &str,
+    player_name: &str,
+    score: u64,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    // Không có season active cho game_mode thì submit_score chỉ ghi bảng all-time
+    if let Some(store) = state.leaderboard.as_ref() {
+        let submitted = services::seasons::submit_score(store, game_mode, player_id, player_name, score)
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, player_id, game_mode, "failed to submit leaderboard score");
+                GatewayError::Internal("Failed to submit score".to_string())
+            })?;
+        return Ok(Json(serde_json::json!({
+            "success": true,
+            "message": "Score submitted successfully",
+            "rank": submitted.rank,
+            "score": score,
+            "best_score": submitted.best_score,
+            "season": submitted.season_id.as_deref().unwrap_or(services::seasons::ALL_TIME_SEASON)
+        })));
+    }
 
-    Json(serde_json::json!({
+    let (rank, best_score) = state.memory_leaderboard.record(game_mode, player_id, player_name, score);
+    Ok(Json(serde_json::json!({
         "success": true,
         "message": "Score submitted successfully",
-        "rank": 1, // Mock rank - in reality would be calculated based on other scores
-        "score": score
-    })).into_response()
+        "rank": rank,
+        "score": score,
+        "best_score": best_score
+    })))
 }
 
 pub async fn run(
@@ -1502,11 +3236,53 @@ pub async fn run(
         let _ = tx.send(local_addr);
     }
 
-    let app = build_router(config.worker_endpoint.clone()).await;
+    let room_manager = room_client::RoomManagerClient::with_secret(
+        config.room_manager_url.unwrap_or_else(room_client::url_from_env),
+        config.internal_secret.or_else(room_manager::api::internal_secret_from_env),
+    );
+    // TLS cấu hình sai (file thiếu, endpoint http://) thì dừng thay vì gọi worker bằng plaintext
+    if let Some(tls) = &config.worker_tls {
+        worker_client::endpoint(&config.worker_endpoint, Some(tls))?;
+    }
+    let worker_token = config.worker_token.clone().or_else(proto::auth::internal_token_from_env);
+    let worker_client = lazy_worker_client(&config.worker_endpoint, config.worker_tls.as_ref(), worker_token.as_deref());
+    let mut state = build_app_state_with_worker(worker_client, room_manager).await;
+    state.allowed_origins = config.allowed_origins;
+    state.rate_limiter.set_limit(config.rate_limit_per_second);
+    if config.admin_token.is_some() {
+        state.admin_token.set(config.admin_token);
+    }
+    if let Some(ping_interval) = config.ws_ping_interval {
+        state.latency.set_ping_interval(ping_interval);
+    }
+    state.rtc_peers = rtc::ServerPeers::new(config.stun_servers);
+    let reload = config.reload_rx.map(|reload_rx| tokio::spawn(apply_reloads(reload_rx, state.clone())));
+    let drain = config.drain_rx.map(|drain_rx| {
+        let drain = state.drain.clone();
+        tokio::spawn(async move {
+            common_net::shutdown::wait(drain_rx).await;
+            tracing::info!("gateway: bắt đầu drain, từ chối room và connection mới");
+            drain.begin();
+        })
+    });
+    let quic_listener = match config.quic_bind_addr {
+        Some(addr) => {
+            // Chưa có cấu hình certificate: dùng cert self-signed, client phải pin cert này
+            let listener = common_net::transport::QuicListener::bind_self_signed(addr, "localhost")
+                .map_err(|e| Box::new(e) as BoxError)?;
+            let quic_addr = listener.local_addr().map_err(|e| Box::new(e) as BoxError)?;
+            tracing::info!(addr = %quic_addr, "gateway quic listening (self-signed)");
+            let auth_service = state.auth_service.clone();
+            let sessions = state.quic_sessions.clone();
+            Some(tokio::spawn(quic::accept_loop(listener, auth_service, sessions)))
+        }
+        None => None,
+    };
+    let app = build_router_with_state(state);
     let server = tokio::spawn(async move {
         let incoming = AddrIncoming::from_listener(listener).expect("failed to create incoming");
         if let Err(err) = hyper::Server::builder(incoming)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await
         {
             error!(%err, "gateway server stopped unexpectedly");
@@ -1515,64 +3291,102 @@ pub async fn run(
 
     common_net::shutdown::wait(shutdown_rx).await;
     server.abort();
+    if let Some(reload) = reload {
+        reload.abort();
+    }
+    if let Some(drain) = drain {
+        drain.abort();
+    }
+    if let Some(quic_listener) = quic_listener {
+        quic_listener.abort();
+    }
     Ok(())
 }
 
+/// Áp dụng origin, rate limit, admin token và chu kỳ Ping từ settings mới; connection đang mở không bị đóng,
+/// bind address giữ nguyên
+async fn apply_reloads(mut reload_rx: tokio::sync::watch::Receiver<GatewaySettings>, state: AppState) {
+    while reload_rx.changed().await.is_ok() {
+        let settings = reload_rx.borrow_and_update().clone();
+        state.allowed_origins.replace(&cors::AllowedOrigins::new(settings.allowed_origins.iter().cloned()));
+        state.rate_limiter.set_limit(settings.rate_limit_per_second);
+        state.admin_token.set(settings.admin_token.clone());
+        if let Some(ping_interval_ms) = settings.ws_ping_interval_ms {
+            state.latency.set_ping_interval(std::time::Duration::from_millis(ping_interval_ms));
+        }
+        tracing::info!(
+            allowed_origins = ?settings.allowed_origins,
+            rate_limit_per_second = settings.rate_limit_per_second,
+            admin_token_set = state.admin_token.is_set(),
+            ws_ping_interval_ms = state.latency.ping_interval().as_millis() as u64,
+            "gateway: đã áp dụng config mới"
+        );
+    }
+}
+
+/// Trả về 422 kèm danh sách lỗi theo field
+/// Unwrap body JSON đã typed và chạy validate(); lỗi schema/validate map sang 422, body không phải JSON thì 400
+fn validated_body<T>(
+    body: Result<Json<T>, JsonRejection>,
+    validate: impl FnOnce(&T) -> Result<(), Vec<types::FieldError>>,
+) -> Result<T, GatewayError> {
+    let Json(value) = body.map_err(|rejection| match rejection {
+        JsonRejection::JsonDataError(err) => {
+            GatewayError::Validation(vec![types::FieldError::from_serde_message(&err.body_text())])
+        }
+        other => GatewayError::BadRequest(other.body_text()),
+    })?;
+    validate(&value).map_err(GatewayError::Validation)?;
+    Ok(value)
+}
+
 // Game handlers
 async fn game_join_handler(
     State(mut state): State<AppState>,
-    Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&[GAME_JOIN_PATH]).inc();
+    body: Result<Json<types::GameJoinRequest>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request(GAME_JOIN_PATH);
 
-    let room_id = request.get("room_id").and_then(|v| v.as_str()).unwrap_or("default");
-    let player_id = request.get("player_id").and_then(|v| v.as_str()).unwrap_or("anonymous");
+    let request = validated_body(body, types::GameJoinRequest::validate)?;
+    let room_id = request.room_id.as_str();
+    let player_id = request.player_id.as_str();
 
     tracing::info!(room_id, player_id, "gateway: player joining game");
 
     // Call worker to join room
+    let player_name = resolved_player_name(&state.room_manager, room_id, player_id).await;
     match state.worker_client.join_room(proto::worker::v1::JoinRoomRequest {
         room_id: room_id.to_string(),
         player_id: player_id.to_string(),
+        player_name,
     }).await {
         Ok(response) => {
             let response_inner = response.into_inner();
             if response_inner.ok {
                 tracing::info!(room_id, player_id, "gateway: player joined game successfully");
-                Json(serde_json::json!({
+                Ok(Json(serde_json::json!({
                     "success": true,
                     "room_id": room_id,
                     "player_id": player_id,
                     "snapshot": response_inner.snapshot.map(|s| s.payload_json).unwrap_or_else(|| "{}".to_string())
-                })).into_response()
+                })))
             } else {
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": "Failed to join room"
-                })).into_response()
+                Err(GatewayError::Conflict("Failed to join room".to_string()))
             }
         }
-        Err(e) => {
-            tracing::error!(error = %e, "gateway: failed to join room");
-            Json(serde_json::json!({
-                "success": false,
-                "error": format!("Worker error: {}", e)
-            })).into_response()
-        }
+        Err(e) => Err(worker_error("join room", &e)),
     }
 }
 
 async fn game_leave_handler(
     State(mut state): State<AppState>,
-    Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&[GAME_LEAVE_PATH]).inc();
-
-    // Room management endpoints
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/rooms/create"]).inc();
+    body: Result<Json<types::GameLeaveRequest>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request(GAME_LEAVE_PATH);
 
-    let room_id = request.get("room_id").and_then(|v| v.as_str()).unwrap_or("default");
-    let player_id = request.get("player_id").and_then(|v| v.as_str()).unwrap_or("anonymous");
+    let request = validated_body(body, types::GameLeaveRequest::validate)?;
+    let room_id = request.room_id.as_str();
+    let player_id = request.player_id.as_str();
 
     tracing::info!(room_id, player_id, "gateway: player leaving game");
 
@@ -1583,69 +3397,65 @@ async fn game_leave_handler(
         Ok(response) => {
             if response.into_inner().ok {
                 tracing::info!(room_id, player_id, "gateway: player left game successfully");
-                Json(serde_json::json!({
+                metrics::record_room_lifecycle(metrics::RoomLifecycle::Left);
+                // Player vào qua room manager (v2 create/join/assign) thì trả lại slot; không có thì bỏ qua
+                let leave = room_manager::LeaveRoomRequest {
+                    room_id: room_id.to_string(),
+                    player_id: player_id.to_string(),
+                };
+                if let Err(e) = state.room_manager.leave_room(&leave).await {
+                    tracing::warn!(error = %e, room_id, player_id, "gateway: room manager leave failed");
+                }
+                update_room_gauges(&state.room_manager).await;
+                Ok(Json(serde_json::json!({
                     "success": true,
                     "room_id": room_id,
                     "player_id": player_id
-                })).into_response()
+                })))
             } else {
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": "Failed to leave room"
-                })).into_response()
+                Err(GatewayError::Conflict("Failed to leave room".to_string()))
             }
         }
-        Err(e) => {
-            tracing::error!(error = %e, "gateway: failed to leave room");
-            Json(serde_json::json!({
-                "success": false,
-                "error": format!("Worker error: {}", e)
-            })).into_response()
-        }
+        Err(e) => Err(worker_error("leave room", &e)),
     }
 }
 
 async fn game_input_handler(
     State(mut state): State<AppState>,
-    Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&[GAME_INPUT_PATH]).inc();
+    body: Result<Json<types::GameInputRequest>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request(GAME_INPUT_PATH);
 
-    let room_id = request.get("room_id").and_then(|v| v.as_str()).unwrap_or("default");
-    let player_id = request.get("player_id").and_then(|v| v.as_str()).unwrap_or("anonymous");
-    let sequence = request.get("sequence").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
-    let input_json = request.get("input").map(|v| v.to_string()).unwrap_or_default();
+    let request = validated_body(body, types::GameInputRequest::validate)?;
+    let room_id = request.room_id.as_str();
+    let player_id = request.player_id.as_str();
+    let sequence = request.sequence;
+    let input_json = request.worker_payload_json();
 
     tracing::debug!(room_id, player_id, sequence, "gateway: processing game input");
 
     // Call worker to push input
-    match state.worker_client.push_input(proto::worker::v1::PushInputRequest {
+    let t0 = std::time::Instant::now();
+    let pushed = state.worker_client.push_input(proto::worker::v1::PushInputRequest {
         room_id: room_id.to_string(),
         sequence,
         payload_json: input_json,
-    }).await {
+    }).await;
+    metrics::record_input_push(pushed.is_ok(), t0.elapsed());
+    match pushed {
         Ok(response) => {
             let response_inner = response.into_inner();
             if response_inner.ok {
                 tracing::debug!(room_id, player_id, sequence, tick = %response_inner.snapshot.as_ref().map(|s| s.tick).unwrap_or(0), "gateway: input processed");
-                Json(serde_json::json!({
+                Ok(Json(serde_json::json!({
                     "success": true,
                     "snapshot": response_inner.snapshot.map(|s| s.payload_json).unwrap_or_else(|| "{}".to_string())
-                })).into_response()
+                })))
             } else {
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": response_inner.error
-                })).into_response()
+                Err(GatewayError::Conflict(response_inner.error))
             }
         }
-        Err(e) => {
-            tracing::error!(error = %e, "gateway: failed to push input");
-            Json(serde_json::json!({
-                "success": false,
-                "error": format!("Worker error: {}", e)
-            })).into_response()
-        }
+        Err(e) => Err(worker_error("push input", &e)),
     }
 }
 
@@ -1654,8 +3464,8 @@ async fn game_input_handler(
 async fn create_room_handler(
     State(mut state): State<AppState>,
     Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/rooms/create"]).inc();
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request("/api/rooms/create");
 
     let room_name = request.get("room_name").and_then(|v| v.as_str()).unwrap_or("New Room");
     let host_id = request.get("host_id").and_then(|v| v.as_str()).unwrap_or("anonymous");
@@ -1663,28 +3473,29 @@ async fn create_room_handler(
 
     // Validate inputs
     if room_name.trim().is_empty() || room_name.len() > 50 {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Room name must be between 1 and 50 characters"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Room name must be between 1 and 50 characters".to_string()));
     }
 
     if host_id.trim().is_empty() || host_id.len() > 50 {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Host ID must be between 1 and 50 characters"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Host ID must be between 1 and 50 characters".to_string()));
     }
 
     if host_name.trim().is_empty() || host_name.len() > 50 {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Host name must be between 1 and 50 characters"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Host name must be between 1 and 50 characters".to_string()));
     }
 
     // Parse room settings - for now use default settings
-    let settings = proto::worker::v1::RoomSettings::default();
+    let settings = proto::worker::v1::RoomSettings {
+        backfill_with_bots: request.get("backfill_with_bots").and_then(|v| v.as_bool()).unwrap_or(false),
+        seed: request.get("seed").and_then(|v| v.as_u64()),
+        motd: request.get("motd").and_then(|v| v.as_str()).map(str::to_string),
+        checkpoint_interval_seconds: request.get("checkpoint_interval_seconds").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+        resume_progress: request.get("resume_progress").and_then(|v| v.as_bool()).unwrap_or(false),
+        tick_hz: request.get("tick_hz").and_then(|v| v.as_u64()).unwrap_or(0).min(u32::MAX as u64) as u32,
+        // Settings JSON tự do của room (spawn_points, ...), worker kiểm tra
+        settings_json: request.get("settings").filter(|v| v.is_object()).map(|v| v.to_string()).unwrap_or_default(),
+        ..Default::default()
+    };
 
     tracing::info!(room_name, host_id, "gateway: creating room");
 
@@ -1699,174 +3510,67 @@ async fn create_room_handler(
             let response_inner = response.into_inner();
             if response_inner.success {
                 tracing::info!(room_id = %response_inner.room_id, "gateway: room created successfully");
-                Json(serde_json::json!({
+                Ok(Json(serde_json::json!({
                     "success": true,
                     "room_id": response_inner.room_id,
                     "room_name": room_name
-                })).into_response()
+                })))
             } else {
                 tracing::error!(error = %response_inner.error, "gateway: failed to create room");
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": response_inner.error
-                })).into_response()
-            }
-        }
-        Err(e) => {
-            tracing::error!(error = %e, "gateway: failed to create room");
-            Json(serde_json::json!({
-                "success": false,
-                "error": "Failed to create room"
-            })).into_response()
-        }
-    }
-}
-
-async fn list_rooms_handler(
-    State(mut state): State<AppState>,
-    Query(query): Query<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/rooms/list"]).inc();
-
-    // For now, use default filter - can be extended later
-    let filter = proto::worker::v1::RoomListFilter::default();
-
-    tracing::info!("gateway: listing rooms");
-
-    // Call worker to list rooms
-    match state.worker_client.list_rooms(proto::worker::v1::ListRoomsRequest {
-        filter: Some(filter),
-    }).await {
-        Ok(response) => {
-            let response_inner = response.into_inner();
-            if response_inner.success {
-                let rooms_json: Vec<serde_json::Value> = response_inner.rooms.iter().map(|room| {
-                    serde_json::json!({
-                        "id": room.id,
-                        "name": room.name,
-                        "settings": room.settings.as_ref().map(|s| serde_json::json!({
-                            "max_players": s.max_players,
-                            "game_mode": s.game_mode,
-                            "map_name": s.map_name,
-                            "time_limit_seconds": s.time_limit_seconds,
-                            "has_password": s.has_password,
-                            "is_private": s.is_private,
-                            "allow_spectators": s.allow_spectators,
-                            "auto_start": s.auto_start,
-                            "min_players_to_start": s.min_players_to_start,
-                        })).unwrap_or_default(),
-                        "state": room.state,
-                        "player_count": room.player_count,
-                        "spectator_count": room.spectator_count,
-                        "max_players": room.max_players,
-                        "has_password": room.has_password,
-                        "game_mode": room.game_mode,
-                        "created_at_seconds_ago": room.created_at_seconds_ago,
-                    })
-                }).collect();
-
-                let mut response = Json(serde_json::json!({
-                    "success": true,
-                    "rooms": rooms_json
-                })).into_response();
-                let headers = response.headers_mut();
-                headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-                headers.insert("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS".parse().unwrap());
-                headers.insert("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept".parse().unwrap());
-                response
-            } else {
-                let mut response = Json(serde_json::json!({
-                    "success": false,
-                    "error": response_inner.error
-                })).into_response();
-                let headers = response.headers_mut();
-                headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-                headers.insert("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS".parse().unwrap());
-                headers.insert("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept".parse().unwrap());
-                response
+                Err(GatewayError::Conflict(response_inner.error))
             }
         }
-        Err(e) => {
-            tracing::error!(error = %e, "gateway: failed to list rooms");
-            let mut response = Json(serde_json::json!({
-                "success": false,
-                "error": "Failed to list rooms"
-            })).into_response();
-            let headers = response.headers_mut();
-            headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-            headers.insert("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS".parse().unwrap());
-            headers.insert("Access-Control-Allow-Headers", "Content-Type, Authorization, Accept".parse().unwrap());
-            response
-        }
+        Err(e) => Err(worker_error("create room", &e)),
     }
 }
 
+/// Phòng của room-manager kèm player live từ worker; worker lỗi thì vẫn trả phần room-manager với `partial: true`
 async fn get_room_info_handler(
     State(mut state): State<AppState>,
     Path(room_id): Path<String>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/rooms/info"]).inc();
-
-    tracing::info!(room_id, "gateway: getting room info");
+    headers: HeaderMap,
+) -> Result<Json<room_detail::RoomDetail>, GatewayError> {
+    metrics::record_http_request(ROOM_DETAIL_PATH);
+
+    // Không có token vẫn xem được, chỉ không thấy field riêng của host
+    let requester_id = extract_user_id_from_headers(&headers, &state.auth_service).ok();
+    let now = std::time::Instant::now();
+    if let Some(detail) = state.room_details.get(&room_id, now) {
+        return Ok(Json(detail.for_requester(requester_id.as_deref())));
+    }
 
-    // Call worker to get room info
-    match state.worker_client.get_room_info(proto::worker::v1::GetRoomInfoRequest {
-        room_id: room_id.clone(),
-    }).await {
-        Ok(response) => {
-            let response_inner = response.into_inner();
-            if response_inner.success {
-                let room_json = response_inner.room.as_ref().map(|room| {
-                    serde_json::json!({
-                        "id": room.id,
-                        "name": room.name,
-                        "settings": room.settings.as_ref().map(|s| serde_json::json!({
-                            "max_players": s.max_players,
-                            "game_mode": s.game_mode,
-                            "map_name": s.map_name,
-                            "time_limit_seconds": s.time_limit_seconds,
-                            "has_password": s.has_password,
-                            "is_private": s.is_private,
-                            "allow_spectators": s.allow_spectators,
-                            "auto_start": s.auto_start,
-                            "min_players_to_start": s.min_players_to_start,
-                        })).unwrap_or_default(),
-                        "state": room.state,
-                        "player_count": room.player_count,
-                        "spectator_count": room.spectator_count,
-                        "max_players": room.max_players,
-                        "has_password": room.has_password,
-                        "game_mode": room.game_mode,
-                        "created_at_seconds_ago": room.created_at_seconds_ago,
-                    })
-                });
+    let room = match state.room_manager.get_room(&room_id).await.map_err(|e| upstream_error("get room", e))? {
+        room_manager::GetRoomResponse { room: Some(room), .. } => room,
+        response => return Err(GatewayError::NotFound(response.error.unwrap_or_else(|| "Room not found".to_string()))),
+    };
 
-                Json(serde_json::json!({
-                    "success": true,
-                    "room": room_json
-                })).into_response()
-            } else {
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": response_inner.error
-                })).into_response()
-            }
+    let request = proto::worker::v1::GetRoomPlayersRequest { room_id: room_id.clone() };
+    let players = match tokio::time::timeout(room_detail::WORKER_TIMEOUT, state.worker_client.get_room_players(request)).await {
+        Ok(Ok(response)) if response.get_ref().ok => Some(response.into_inner().players),
+        Ok(Ok(response)) => {
+            tracing::warn!(%room_id, error = %response.get_ref().error, "gateway: worker rejected get_room_players");
+            None
         }
-        Err(e) => {
-            tracing::error!(error = %e, "gateway: failed to get room info");
-            Json(serde_json::json!({
-                "success": false,
-                "error": "Failed to get room info"
-            })).into_response()
+        Ok(Err(e)) => {
+            tracing::warn!(%room_id, error = %e, "gateway: worker unreachable for room players");
+            None
         }
-    }
+        Err(_) => {
+            tracing::warn!(%room_id, "gateway: get_room_players timed out");
+            None
+        }
+    };
+
+    let detail = room_detail::RoomDetail::new(room, players);
+    state.room_details.insert(&room_id, detail.clone(), now);
+    Ok(Json(detail.for_requester(requester_id.as_deref())))
 }
 
 async fn join_room_as_player_handler(
     State(mut state): State<AppState>,
     Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/rooms/join-player"]).inc();
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request("/api/rooms/join-player");
 
     let room_id = request.get("room_id").and_then(|v| v.as_str()).unwrap_or("default");
     let player_id = request.get("player_id").and_then(|v| v.as_str()).unwrap_or("anonymous");
@@ -1874,24 +3578,15 @@ async fn join_room_as_player_handler(
 
     // Validate inputs
     if room_id.trim().is_empty() {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Room ID is required"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Room ID is required".to_string()));
     }
 
     if player_id.trim().is_empty() || player_id.len() > 50 {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Player ID must be between 1 and 50 characters"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Player ID must be between 1 and 50 characters".to_string()));
     }
 
     if player_name.trim().is_empty() || player_name.len() > 50 {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Player name must be between 1 and 50 characters"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Player name must be between 1 and 50 characters".to_string()));
     }
 
     tracing::info!(room_id, player_id, "gateway: player joining room");
@@ -1906,50 +3601,69 @@ async fn join_room_as_player_handler(
             let response_inner = response.into_inner();
             if response_inner.success {
                 tracing::info!("Player joined room successfully");
-                Json(serde_json::json!({
+                Ok(Json(serde_json::json!({
                     "success": true,
                     "room_id": room_id,
                     "player_id": player_id
-                })).into_response()
+                })))
             } else {
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": response_inner.error
-                })).into_response()
+                Err(GatewayError::Conflict(response_inner.error))
             }
         }
-        Err(e) => {
-            tracing::error!(error = %e, "gateway: failed to join room as player");
-            Json(serde_json::json!({
-                "success": false,
-                "error": "Failed to join room"
-            })).into_response()
-        }
+        Err(e) => Err(worker_error("join room as player", &e)),
+    }
+}
+
+/// Player (lấy từ JWT) bật/tắt ready; worker tự đếm ngược khi mọi người trong room auto-start đã ready
+async fn set_ready_handler(
+    State(mut state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<types::SetReadyBody>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request(ROOMS_READY_PATH);
+
+    let claims = extract_claims_from_headers(&headers, &state.auth_service)?;
+    let ready_req = validated_body(body, types::SetReadyBody::validate)?;
+
+    let request = proto::worker::v1::SetPlayerReadyRequest {
+        room_id: ready_req.room_id.clone(),
+        player_id: claims.sub.clone(),
+        ready: ready_req.ready,
+    };
+    let response = state
+        .worker_client
+        .set_player_ready(request)
+        .await
+        .map_err(|e| upstream_error("set player ready", e))?
+        .into_inner();
+    if !response.success {
+        return Err(GatewayError::BadRequest(response.error));
     }
+    tracing::info!(room_id = %ready_req.room_id, player_id = %claims.sub, ready = ready_req.ready, "gateway: player ready updated");
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "room_id": ready_req.room_id,
+        "player_id": claims.sub,
+        "ready": ready_req.ready
+    })))
 }
 
 async fn start_game_handler(
     State(mut state): State<AppState>,
     Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/rooms/start-game"]).inc();
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request("/api/rooms/start-game");
 
     let room_id = request.get("room_id").and_then(|v| v.as_str()).unwrap_or("default");
     let player_id = request.get("player_id").and_then(|v| v.as_str()).unwrap_or("anonymous");
 
     // Validate inputs
     if room_id.trim().is_empty() {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Room ID is required"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Room ID is required".to_string()));
     }
 
     if player_id.trim().is_empty() || player_id.len() > 50 {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Player ID must be between 1 and 50 characters"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Player ID must be between 1 and 50 characters".to_string()));
     }
 
     tracing::info!(room_id, player_id, "gateway: starting game");
@@ -1963,24 +3677,15 @@ async fn start_game_handler(
             let response_inner = response.into_inner();
             if response_inner.success {
                 tracing::info!("Game started successfully");
-                Json(serde_json::json!({
+                Ok(Json(serde_json::json!({
                     "success": true,
                     "room_id": room_id
-                })).into_response()
+                })))
             } else {
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": response_inner.error
-                })).into_response()
+                Err(GatewayError::Conflict(response_inner.error))
             }
         }
-        Err(e) => {
-            tracing::error!(error = %e, "gateway: failed to start game");
-            Json(serde_json::json!({
-                "success": false,
-                "error": "Failed to start game"
-            })).into_response()
-        }
+        Err(e) => Err(worker_error("start game", &e)),
     }
 }
 
@@ -1989,60 +3694,47 @@ async fn join_room_handler(
     State(mut state): State<AppState>,
     Path(room_id): Path<String>,
     Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/rooms/{room_id}/join"]).inc();
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request("/api/rooms/{room_id}/join");
 
     let player_id = request.get("player_id").and_then(|v| v.as_str()).unwrap_or("anonymous");
     let player_name = request.get("player_name").and_then(|v| v.as_str()).unwrap_or(&player_id);
 
     // Validate inputs
     if room_id.trim().is_empty() {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Room ID is required"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Room ID is required".to_string()));
     }
 
     if player_id.trim().is_empty() || player_id.len() > 50 {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Player ID must be between 1 and 50 characters"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Player ID must be between 1 and 50 characters".to_string()));
     }
 
     tracing::info!(room_id, player_id, "gateway: joining room");
 
     // Call worker to join room
+    let resolved_name = resolved_player_name(&state.room_manager, &room_id, player_id).await;
     match state.worker_client.join_room(proto::worker::v1::JoinRoomRequest {
         room_id: room_id.clone(),
         player_id: player_id.to_string(),
+        player_name: resolved_name,
     }).await {
         Ok(response) => {
             let response_inner = response.into_inner();
             if response_inner.ok {
                 tracing::info!("Player joined room successfully");
-                Json(serde_json::json!({
+                Ok(Json(serde_json::json!({
                     "success": true,
                     "room_id": room_id,
                     "snapshot": response_inner.snapshot.map(|s| {
                         // Parse the snapshot JSON
                         serde_json::from_str::<serde_json::Value>(&s.payload_json).unwrap_or_default()
                     }).unwrap_or_default()
-                })).into_response()
+                })))
             } else {
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": response_inner.error
-                })).into_response()
+                Err(GatewayError::Conflict(response_inner.error))
             }
         }
-        Err(e) => {
-            tracing::error!(error = %e, "gateway: failed to join room");
-            Json(serde_json::json!({
-                "success": false,
-                "error": "Failed to join room"
-            })).into_response()
-        }
+        Err(e) => Err(worker_error("join room", &e)),
     }
 }
 
@@ -2051,30 +3743,27 @@ async fn get_room_snapshot_handler(
     State(state): State<AppState>,
     Path(room_id): Path<String>,
     Query(params): Query<std::collections::HashMap<String, String>>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/rooms/{room_id}/snapshot"]).inc();
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request("/api/rooms/{room_id}/snapshot");
 
     let player_id = params.get("player_id").map(|s| s.as_str()).unwrap_or("anonymous");
 
     // Validate inputs
     if room_id.trim().is_empty() {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Room ID is required"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Room ID is required".to_string()));
     }
 
     tracing::debug!(room_id, player_id, "gateway: getting room snapshot");
 
     // For now, return a mock snapshot since we don't have a direct snapshot API in worker
     // In a real implementation, this would call a worker RPC to get the current snapshot
-    Json(serde_json::json!({
+    Ok(Json(serde_json::json!({
         "success": true,
         "tick": 0,
         "entities": [],
         "chat_messages": [],
         "spectators": []
-    })).into_response()
+    })))
 }
 
 // Send room input handler
@@ -2082,8 +3771,8 @@ async fn send_room_input_handler(
     State(mut state): State<AppState>,
     Path(room_id): Path<String>,
     Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    HTTP_REQUESTS_TOTAL.with_label_values(&["/api/rooms/{room_id}/input"]).inc();
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    metrics::record_http_request("/api/rooms/{room_id}/input");
 
     let player_id = request.get("player_id").and_then(|v| v.as_str()).unwrap_or("anonymous");
     let input_sequence = request.get("input_sequence").and_then(|v| v.as_u64()).unwrap_or(0);
@@ -2092,50 +3781,45 @@ async fn send_room_input_handler(
 
     // Validate inputs
     if room_id.trim().is_empty() {
-        return Json(serde_json::json!({
-            "success": false,
-            "error": "Room ID is required"
-        })).into_response();
+        return Err(GatewayError::BadRequest("Room ID is required".to_string()));
     }
 
     tracing::debug!(room_id, player_id, input_sequence, "gateway: processing room input");
 
+    // Payload khớp với `PlayerInput` bên worker; actions chỉ gửi khi client có gửi
+    let mut payload = serde_json::json!({
+        "player_id": player_id,
+        "input_sequence": input_sequence,
+        "movement": movement_value,
+        "timestamp": timestamp
+    });
+    if let Some(actions) = request.get("actions") {
+        payload["actions"] = actions.clone();
+    }
+
     // Call worker to push input
     match state.worker_client.push_input(proto::worker::v1::PushInputRequest {
         room_id: room_id.clone(),
         sequence: input_sequence as u32,
-        payload_json: serde_json::json!({
-            "player_id": player_id,
-            "movement": movement_value,
-            "timestamp": timestamp
-        }).to_string(),
+        payload_json: payload.to_string(),
     }).await {
         Ok(response) => {
             let response_inner = response.into_inner();
             if response_inner.ok {
                 tracing::debug!("Room input processed successfully");
-                Json(serde_json::json!({
+                Ok(Json(serde_json::json!({
                     "success": true,
                     "room_id": room_id,
                     "snapshot": response_inner.snapshot.map(|s| {
                         // Parse the snapshot JSON
                         serde_json::from_str::<serde_json::Value>(&s.payload_json).unwrap_or_default()
                     }).unwrap_or_default()
-                })).into_response()
+                })))
             } else {
-                Json(serde_json::json!({
-                    "success": false,
-                    "error": response_inner.error
-                })).into_response()
+                Err(GatewayError::Conflict(response_inner.error))
             }
         }
-        Err(e) => {
-            tracing::error!(error = %e, "gateway: failed to push room input");
-            Json(serde_json::json!({
-                "success": false,
-                "error": "Failed to send input"
-            })).into_response()
-        }
+        Err(e) => Err(worker_error("push room input", &e)),
     }
 }
 
@@ -2144,3 +3828,2173 @@ impl GatewayConfig {
         GatewaySettings::from_env().map(Self::from_settings)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_tungstenite::tungstenite::{client::IntoClientRequest, http::HeaderValue as WsHeaderValue};
+
+    async fn spawn_gateway() -> (SocketAddr, AppState) {
+        spawn_gateway_with(build_app_state("http://127.0.0.1:0".to_string()).await).await
+    }
+
+    async fn spawn_gateway_with(state: AppState) -> (SocketAddr, AppState) {
+        let app = build_router_with_state(state.clone());
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("local addr");
+        let server = axum::Server::from_tcp(listener).expect("server").serve(app.into_make_service());
+        tokio::spawn(server);
+
+        (addr, state)
+    }
+
+    fn test_token(auth_service: &auth::AuthService, user_id: &str) -> String {
+        let user = auth::User {
+            id: user_id.to_string(),
+            username: "ws-user".to_string(),
+            email: "ws@example.com".to_string(),
+            role: "user".to_string(),
+        };
+        auth_service.generate_token(&user).expect("token")
+    }
+
+    #[tokio::test]
+    async fn ws_upgrade_requires_token() {
+        let (addr, _state) = spawn_gateway().await;
+
+        let err = tokio_tungstenite::connect_async(format!("ws://{addr}{WS_PATH}"))
+            .await
+            .expect_err("upgrade without token must fail");
+        match err {
+            tokio_tungstenite::tungstenite::Error::Http(resp) => {
+                assert_eq!(resp.status().as_u16(), StatusCode::UNAUTHORIZED.as_u16());
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+
+        let err = tokio_tungstenite::connect_async(format!("ws://{addr}{WS_PATH}?token=not-a-jwt"))
+            .await
+            .expect_err("upgrade with invalid token must fail");
+        assert!(matches!(err, tokio_tungstenite::tungstenite::Error::Http(_)));
+    }
+
+    #[tokio::test]
+    async fn ws_upgrade_with_token_sets_peer_id() {
+        let (addr, state) = spawn_gateway().await;
+        let token = test_token(&state.auth_service, "user-42");
+
+        let mut request = format!("ws://{addr}{WS_PATH}").into_client_request().expect("request");
+        request.headers_mut().insert(
+            "sec-websocket-protocol",
+            WsHeaderValue::from_str(&format!("{WS_BEARER_PROTOCOL}, {token}")).expect("header"),
+        );
+        let (mut socket, response) = tokio_tungstenite::connect_async(request).await.expect("upgrade");
+        assert_eq!(
+            response.headers().get("sec-websocket-protocol").and_then(|v| v.to_str().ok()),
+            Some(WS_BEARER_PROTOCOL)
+        );
+        send_hello(&mut socket).await;
+
+        // ws_session đăng ký connection sau khi upgrade, chờ một chút
+        let mut peer_ids = Vec::new();
+        for _ in 0..50 {
+            peer_ids = state.ws_registry.filter_map(|_, c| Some(c.peer_id.clone()));
+            if !peer_ids.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(peer_ids, vec!["user-42".to_string()]);
+
+        let (_socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}{WS_PATH}?token={token}"))
+            .await
+            .expect("upgrade with query token");
+    }
+
+    #[tokio::test]
+    async fn ws_session_prefers_an_authenticated_quic_connection() {
+        use common_net::transport::{QuicListener, QuicTransport};
+
+        let (addr, state) = spawn_gateway().await;
+        let listener = QuicListener::bind_self_signed("127.0.0.1:0".parse().unwrap(), "localhost").expect("bind quic");
+        let quic_addr = listener.local_addr().expect("quic addr");
+        let certificate = listener.certificate_der().to_vec();
+        tokio::spawn(quic::accept_loop(listener, state.auth_service.clone(), state.quic_sessions.clone()));
+
+        let token = test_token(&state.auth_service, "quic-user");
+        let mut client = QuicTransport::connect(quic_addr, "localhost", &certificate).await.expect("dial quic");
+        client
+            .send_frame(Frame::control(1, 0, ControlMessage::AuthToken { jwt: token.clone() }))
+            .await
+            .expect("auth");
+        for _ in 0..100 {
+            if state.quic_sessions.contains("quic-user") {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(state.quic_sessions.contains("quic-user"));
+
+        let _socket = ws_connect(addr, &token).await;
+        let mut kind = None;
+        for _ in 0..100 {
+            kind = state
+                .transport_registry
+                .filter_map(|_, conn| (conn.peer_id == "quic-user").then(|| (conn.kind(), conn.selection.reason)))
+                .pop();
+            if kind.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(kind, Some((TransportKind::Quic, TransportSelection::QUIC_AUTHENTICATED)));
+        assert!(!state.quic_sessions.contains("quic-user"), "the ws session took the quic connection");
+
+        // Frame gateway relay cho peer đi qua QUIC
+        send_to_transport(&state.transport_registry, &state.bandwidth, "quic-user", Frame::control(0, 0, ControlMessage::Kicked {
+            reason: "test".to_string(),
+        }))
+        .await;
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), client.recv_frame())
+            .await
+            .expect("frame in time")
+            .expect("frame");
+        assert!(matches!(frame.payload, FramePayload::Control { message: ControlMessage::Kicked { .. } }));
+    }
+
+    #[tokio::test]
+    async fn ws_upgrade_and_cors_honor_allowed_origins() {
+        let mut state = build_app_state("http://127.0.0.1:0".to_string()).await;
+        state.allowed_origins = cors::AllowedOrigins::parse("https://play.example.com");
+        let (addr, state) = spawn_gateway_with(state).await;
+        let token = test_token(&state.auth_service, "user-origin");
+        let ws_request = |origin: &str| {
+            let mut request = format!("ws://{addr}{WS_PATH}?token={token}").into_client_request().expect("request");
+            request.headers_mut().insert("origin", WsHeaderValue::from_str(origin).expect("header"));
+            request
+        };
+
+        let err = tokio_tungstenite::connect_async(ws_request("https://evil.example.com"))
+            .await
+            .expect_err("foreign origin must not upgrade");
+        match err {
+            tokio_tungstenite::tungstenite::Error::Http(resp) => {
+                assert_eq!(resp.status().as_u16(), StatusCode::FORBIDDEN.as_u16());
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+        let rendered = metrics::render();
+        let rejected = rendered
+            .lines()
+            .find_map(|line| line.strip_prefix("gateway_ws_origin_rejected_total "))
+            .and_then(|value| value.trim().parse::<f64>().ok());
+        assert!(rejected.is_some_and(|count| count >= 1.0), "{rendered}");
+
+        let (_socket, response) = tokio_tungstenite::connect_async(ws_request("https://play.example.com"))
+            .await
+            .expect("allowed origin upgrades");
+        assert_eq!(response.status().as_u16(), StatusCode::SWITCHING_PROTOCOLS.as_u16());
+
+        let client = reqwest::Client::new();
+        let allowed = client
+            .get(format!("http://{addr}{HEALTHZ_PATH}"))
+            .header("origin", "https://play.example.com")
+            .send()
+            .await
+            .expect("healthz");
+        assert_eq!(
+            allowed.headers().get("access-control-allow-origin").and_then(|v| v.to_str().ok()),
+            Some("https://play.example.com")
+        );
+        assert_eq!(allowed.headers().get("vary").and_then(|v| v.to_str().ok()), Some("Origin"));
+
+        let foreign = client
+            .get(format!("http://{addr}{VERSION_PATH}"))
+            .header("origin", "https://evil.example.com")
+            .send()
+            .await
+            .expect("version");
+        assert_eq!(foreign.status(), reqwest::StatusCode::OK);
+        assert!(foreign.headers().get("access-control-allow-origin").is_none());
+
+        let preflight = client
+            .request(reqwest::Method::OPTIONS, format!("http://{addr}{ROOMS_CREATE_PATH}"))
+            .header("origin", "https://play.example.com")
+            .send()
+            .await
+            .expect("preflight");
+        assert_eq!(preflight.status(), reqwest::StatusCode::OK);
+        assert!(preflight.headers().contains_key("access-control-max-age"));
+        assert!(preflight.headers().contains_key("access-control-allow-methods"));
+    }
+
+    #[tokio::test]
+    async fn version_reports_the_build_and_protocol() {
+        let (addr, _state) = spawn_gateway().await;
+        let body: serde_json::Value = reqwest::get(format!("http://{addr}{VERSION_PATH}"))
+            .await
+            .expect("version")
+            .json()
+            .await
+            .expect("json");
+
+        assert_eq!(body["name"], "gateway");
+        assert!(body["git_sha"].as_str().is_some_and(|sha| !sha.is_empty()));
+        assert!(body["built_at"].as_str().is_some_and(|at| !at.is_empty()));
+        assert_eq!(body["protocol_version"], common_net::message::PROTOCOL_VERSION);
+    }
+
+    #[tokio::test]
+    async fn first_ws_frame_reports_the_selected_transport() {
+        use futures::StreamExt;
+
+        let (addr, state) = spawn_gateway().await;
+        let token = test_token(&state.auth_service, "user-selected");
+        let mut socket = ws_connect(addr, &token).await;
+
+        let first = socket.next().await.expect("first frame").expect("ws message");
+        let frame = message::decode(&first.into_data()).expect("json frame");
+        let FramePayload::Control { message: ControlMessage::TransportSelected { kind, fallback_used, reason } } = frame.payload
+        else {
+            panic!("first frame is not TransportSelected: {:?}", frame.payload);
+        };
+        assert_eq!(frame.sequence, 1);
+
+        let (transport, selection) = state
+            .transport_registry
+            .filter_map(|_, conn| (conn.peer_id == "user-selected").then(|| (conn.transport.clone(), conn.selection)))
+            .pop()
+            .expect("registered");
+        assert_eq!(kind, transport.lock().await.kind());
+        assert_eq!(kind, selection.kind);
+        assert_eq!(fallback_used, selection.fallback_used);
+        assert_eq!(reason, selection.reason);
+    }
+
+    /// Bỏ frame `TransportSelected` server luôn gửi đầu tiên, trả về số byte của nó
+    type TestWs = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+    async fn send_hello(socket: &mut TestWs) {
+        use futures::SinkExt;
+
+        let hello = serde_json::to_string(&ControlMessage::Hello { protocol_version: message::PROTOCOL_VERSION }).expect("hello");
+        socket.send(tokio_tungstenite::tungstenite::Message::Text(hello)).await.expect("send hello");
+    }
+
+    /// Upgrade bằng query token rồi gửi Hello như client thật
+    async fn ws_connect(addr: SocketAddr, token: &str) -> TestWs {
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}{WS_PATH}?token={token}"))
+            .await
+            .expect("upgrade");
+        send_hello(&mut socket).await;
+        socket
+    }
+
+    #[tokio::test]
+    async fn ws_requires_hello_with_the_server_protocol_version() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (addr, state) = spawn_gateway().await;
+        let token = test_token(&state.auth_service, "user-version");
+
+        let mut matching = ws_connect(addr, &token).await;
+        skip_transport_selected(&mut matching).await;
+
+        let newer = serde_json::to_string(&ControlMessage::Hello { protocol_version: message::PROTOCOL_VERSION + 1 }).expect("hello");
+        let legacy = r#"{"type":"join_room","room_id":"room-1","reconnect_token":null}"#.to_string();
+        for first in [newer, legacy] {
+            let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}{WS_PATH}?token={token}"))
+                .await
+                .expect("upgrade");
+            socket.send(WsMessage::Text(first)).await.expect("send first frame");
+
+            let reply = socket.next().await.expect("reply").expect("ws message").into_data();
+            let frame = message::decode(&reply).expect("json frame");
+            assert!(matches!(
+                frame.payload,
+                FramePayload::Control { message: ControlMessage::VersionMismatch { server_version } }
+                    if server_version == message::PROTOCOL_VERSION
+            ));
+            assert!(matches!(socket.next().await, Some(Ok(WsMessage::Close(_))) | None));
+        }
+    }
+
+    #[tokio::test]
+    async fn ws_session_lifecycle_posts_analytics_events() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        // Services giả: giữ lại mọi event nhận được qua `POST /api/ingest/events`
+        let received: Arc<std::sync::Mutex<Vec<serde_json::Value>>> = Arc::default();
+        let ingest = axum::Router::new()
+            .route(analytics::INGEST_PATH, post(|State(received): State<Arc<std::sync::Mutex<Vec<serde_json::Value>>>>, Json(body): Json<serde_json::Value>| async move {
+                received.lock().unwrap().extend(body["events"].as_array().cloned().unwrap_or_default());
+                Json(serde_json::json!({ "accepted": 0, "rejected": [] }))
+            }))
+            .with_state(received.clone());
+        let ingest_server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(ingest.into_make_service());
+        let ingest_url = format!("http://{}", ingest_server.local_addr());
+        tokio::spawn(ingest_server);
+
+        let mut state = build_app_state("http://127.0.0.1:0".to_string()).await;
+        state.analytics = analytics::SessionAnalytics::spawn(analytics::AnalyticsConfig {
+            ingest_url: Some(ingest_url),
+            flush_interval: std::time::Duration::from_millis(20),
+            ..Default::default()
+        });
+        let (addr, state) = spawn_gateway_with(state).await;
+
+        let token = test_token(&state.auth_service, "funnel-alice");
+        let mut socket = ws_connect(addr, &token).await;
+        skip_transport_selected(&mut socket).await;
+        let join = r#"{"type":"join_room","room_id":"funnel-room","reconnect_token":null}"#.to_string();
+        socket.send(WsMessage::Text(join)).await.expect("send join");
+        socket.next().await.expect("join reply").expect("ws message");
+        socket.close(None).await.expect("close");
+
+        let events = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                let events = received.lock().unwrap().clone();
+                if events.iter().any(|event| event["event_type"] == analytics::DISCONNECT) {
+                    return events;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("disconnect event");
+
+        let types: Vec<&str> = events.iter().map(|event| event["event_type"].as_str().unwrap()).collect();
+        assert_eq!(types, vec![analytics::SESSION_STARTED, analytics::ROOM_JOINED, analytics::DISCONNECT]);
+        assert!(events.iter().all(|event| event["player_id"] == "funnel-alice"));
+        assert_eq!(events[0]["transport"], "websocket");
+        assert_eq!(events[1]["room_id"], "funnel-room");
+        assert_eq!(events[2]["room_id"], "funnel-room");
+        assert_eq!(events[2]["reason"], analytics::CLIENT_CLOSED);
+        assert!(events[2]["duration_ms"].is_u64());
+    }
+
+    async fn skip_transport_selected<S>(socket: &mut S) -> u64
+    where
+        S: futures::Stream<Item = Result<tokio_tungstenite::tungstenite::Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+    {
+        use futures::StreamExt;
+
+        let first = socket.next().await.expect("first frame").expect("ws message").into_data();
+        let frame = message::decode(&first).expect("json frame");
+        assert!(matches!(frame.payload, FramePayload::Control { message: ControlMessage::TransportSelected { .. } }));
+        first.len() as u64
+    }
+
+    #[tokio::test]
+    async fn ws_text_ping_gets_pong_not_echo() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (addr, state) = spawn_gateway().await;
+        assert!(!state.ws_echo);
+        let token = test_token(&state.auth_service, "user-text");
+        let mut socket = ws_connect(addr, &token).await;
+        skip_transport_selected(&mut socket).await;
+
+        socket.send(WsMessage::Text(r#"{"type":"ping","nonce":7}"#.to_string())).await.expect("send ping");
+        let reply = socket.next().await.expect("reply").expect("ws message");
+        let frame = message::decode(reply.to_text().expect("text reply").as_bytes()).expect("json frame");
+        assert!(matches!(frame.payload, FramePayload::Control { message: ControlMessage::Pong { nonce: 7 } }));
+        assert!(frame.sequence > 0);
+
+        socket.send(WsMessage::Text("hello".to_string())).await.expect("send garbage");
+        let reply = socket.next().await.expect("reply").expect("ws message");
+        let frame = message::decode(reply.to_text().expect("text reply").as_bytes()).expect("json frame");
+        assert!(matches!(frame.payload, FramePayload::Control { message: ControlMessage::Error { .. } }));
+    }
+
+    #[tokio::test]
+    async fn unacked_control_frame_is_retransmitted_until_acked() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (addr, state) = spawn_gateway().await;
+        let token = test_token(&state.auth_service, "user-ack");
+        let mut socket = ws_connect(addr, &token).await;
+        skip_transport_selected(&mut socket).await;
+
+        // Frame kế tiếp không phải Ping của gateway
+        async fn next_frame(
+            socket: &mut (impl futures::Stream<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin),
+        ) -> Frame {
+            loop {
+                let reply = socket.next().await.expect("reply").expect("ws message");
+                let frame = message::decode(&reply.into_data()).expect("frame");
+                if !matches!(frame.payload, FramePayload::Control { message: ControlMessage::Ping { .. } }) {
+                    return frame;
+                }
+            }
+        }
+        let send = |sequence: u32, ack: u32, acks: Vec<u32>| {
+            let frame = Frame { ack, acks, ..Frame::control(sequence, 0, ControlMessage::Ack) };
+            WsMessage::Binary(message::encode(&frame).expect("encode"))
+        };
+
+        // Client mới: ack TransportSelected (sequence 1) để gateway biết nó ack từng frame
+        socket.send(send(1, 1, vec![1])).await.expect("ack transport selected");
+
+        // Error là control frame tin cậy; client "làm rơi" nó
+        socket.send(WsMessage::Text("not a frame".to_string())).await.expect("send garbage");
+        let error = next_frame(&mut socket).await;
+        assert!(matches!(error.payload, FramePayload::Control { message: ControlMessage::Error { .. } }));
+
+        // State frame tới sau (chat broadcast) đẩy sequence cao nhất client đã nhận vượt qua Error
+        let (outbound, outbox) = state
+            .ws_registry
+            .for_peer("user-ack", |connection| (connection.outbound.clone(), connection.outbox.clone()))
+            .pop()
+            .expect("connection registered");
+        let chat = StateMessage::Event { name: "chat".to_string(), data: serde_json::json!({ "message": "gg" }) };
+        let later = outbound.stamp(Frame::state(0, 0, chat));
+        outbox.push(outbox::OutboundKind::Control, axum::extract::ws::Message::Binary(message::encode(&later).expect("encode")));
+        let delivered = next_frame(&mut socket).await;
+        assert!(matches!(delivered.payload, FramePayload::State { .. }));
+        assert!(delivered.sequence > error.sequence);
+        socket.send(send(2, delivered.sequence, Vec::new())).await.expect("cumulative ack only");
+
+        let retransmitted = tokio::time::timeout(reliable::RETRANSMIT_INTERVAL * 4, next_frame(&mut socket))
+            .await
+            .expect("control frame retransmitted");
+        assert_eq!(retransmitted.sequence, error.sequence);
+        assert!(metrics::sample("gateway_control_retransmits_total", &[]).is_some_and(|count| count > 0.0));
+
+        socket.send(send(3, delivered.sequence, vec![error.sequence])).await.expect("ack");
+        // Bản gửi lại đã nằm trong outbox trước khi ack tới thì vẫn có thể đến, sau đó là hết
+        let mut late_copies = 0;
+        while let Ok(frame) = tokio::time::timeout(reliable::RETRANSMIT_INTERVAL * 3, next_frame(&mut socket)).await {
+            if frame.sequence == error.sequence {
+                late_copies += 1;
+            }
+        }
+        assert!(late_copies <= 1, "retransmitted {late_copies} times after ack");
+    }
+
+    #[tokio::test]
+    async fn ws_bandwidth_matches_frame_sizes() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (addr, state) = spawn_gateway().await;
+        let token = test_token(&state.auth_service, "user-bandwidth");
+        let mut socket = ws_connect(addr, &token).await;
+        let selected = skip_transport_selected(&mut socket).await;
+
+        let room_id = format!("bw-room-{}", uuid::Uuid::new_v4());
+        let join = format!(r#"{{"type":"join_room","room_id":"{room_id}","reconnect_token":null}}"#);
+        socket.send(WsMessage::Text(join.clone())).await.expect("join");
+        // Join trả về MigrationToken, đã tính vào room
+        let token = socket.next().await.expect("token").expect("ws message").to_text().expect("text reply").len() as u64;
+
+        let mut pings = 0;
+        let mut pongs = token;
+        for nonce in 1000..1005u64 {
+            let ping = format!(r#"{{"type":"ping","nonce":{nonce}}}"#);
+            pings += ping.len() as u64;
+            socket.send(WsMessage::Text(ping)).await.expect("ping");
+            let reply = socket.next().await.expect("reply").expect("ws message");
+            pongs += reply.to_text().expect("text reply").len() as u64;
+        }
+
+        // Flusher nền có thể chạy giữa chừng nên so theo total thay vì interval
+        state.bandwidth.flush();
+        let report = state.bandwidth.report(100);
+        let connection = report
+            .connections
+            .iter()
+            .find(|c| c.room_id.as_deref() == Some(room_id.as_str()))
+            .expect("connection entry");
+        // Counter tính payload, không tính header framing của WebSocket
+        assert_eq!(connection.total_bytes_received, join.len() as u64 + pings);
+        assert_eq!(connection.total_bytes_sent, selected + pongs);
+
+        // Join frame được đếm trước khi connection vào room
+        let room = report.rooms.iter().find(|r| r.id == room_id).expect("room entry");
+        assert_eq!((room.total_bytes_received, room.total_bytes_sent), (pings, pongs));
+        assert_eq!(metrics::sample("gateway_bytes_sent_total", &[("direction", "received"), ("room_id", &room_id)]), Some(pings as f64));
+
+        let response = reqwest::get(format!("http://{addr}{ADMIN_BANDWIDTH_PATH}?limit=1")).await.expect("admin");
+        let body: serde_json::Value = response.json().await.expect("json");
+        assert!(body["connections"].as_array().expect("connections").len() <= 1);
+    }
+
+    /// Giữ field của các span `worker_rpc` để đối chiếu request id giữa gateway và worker
+    #[derive(Clone, Default)]
+    struct WorkerSpanCapture {
+        spans: Arc<std::sync::Mutex<HashMap<u64, HashMap<String, String>>>>,
+    }
+
+    struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name().to_string(), format!("{value:?}"));
+        }
+
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for WorkerSpanCapture {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if attrs.metadata().name() != "worker_rpc" {
+                return;
+            }
+            let mut fields = HashMap::new();
+            attrs.record(&mut FieldVisitor(&mut fields));
+            self.spans.lock().unwrap().insert(id.into_u64(), fields);
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if let Some(fields) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+                values.record(&mut FieldVisitor(fields));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn worker_span_carries_request_id_from_gateway_response() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let capture = WorkerSpanCapture::default();
+        let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint.clone()).await;
+        state.worker_client = worker_client::new(worker::rpc::channel(&worker_endpoint).expect("worker channel"));
+
+        let app = build_router_with_state(state);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("local addr");
+        tokio::spawn(axum::Server::from_tcp(listener).expect("server").serve(app.into_make_service()));
+
+        let client = reqwest::Client::new();
+        let body = serde_json::json!({ "room_id": "trace-room", "player_id": "trace-player" });
+        // Worker test server có thể chưa listen xong
+        let response = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                let response = client
+                    .post(format!("http://{addr}{GAME_JOIN_PATH}"))
+                    .json(&body)
+                    .send()
+                    .await
+                    .expect("join request");
+                let request_id = response.headers().get(common_net::telemetry::REQUEST_ID_HEADER).cloned();
+                let json: serde_json::Value = response.json().await.expect("json");
+                if json["success"] == true {
+                    return request_id;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("join succeeds");
+        let request_id = response.expect("request id header").to_str().expect("ascii").to_string();
+
+        {
+            let spans = capture.spans.lock().unwrap();
+            let span = spans
+                .values()
+                .find(|fields| fields.get("request_id") == Some(&request_id))
+                .expect("worker span with gateway request id");
+            assert!(span["method"].ends_with("/JoinRoom"));
+            assert_eq!(span["room_id"], "trace-room");
+            assert_eq!(span["player_id"], "trace-player");
+        }
+
+        // Id client tự gửi được giữ nguyên
+        let response = client
+            .post(format!("http://{addr}{GAME_JOIN_PATH}"))
+            .header(common_net::telemetry::REQUEST_ID_HEADER, "client-chosen-id")
+            .json(&body)
+            .send()
+            .await
+            .expect("join request");
+        assert_eq!(response.headers()[common_net::telemetry::REQUEST_ID_HEADER], "client-chosen-id");
+        assert!(capture
+            .spans
+            .lock()
+            .unwrap()
+            .values()
+            .any(|fields| fields.get("request_id").map(String::as_str) == Some("client-chosen-id")));
+    }
+
+    #[tokio::test]
+    async fn ws_clients_in_room_receive_worker_snapshots() {
+        use futures::StreamExt;
+
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint.clone()).await;
+        state.worker_client = worker_client::new(worker::rpc::channel(&worker_endpoint).expect("worker channel"));
+        state.snapshots = snapshots::SnapshotBroadcaster::new(state.worker_client.clone(), state.ws_registry.clone())
+            .with_interval(std::time::Duration::from_millis(20));
+        let (addr, state) = spawn_gateway_with(state).await;
+
+        let mut clients = Vec::new();
+        for user in ["snap-alice", "snap-bob"] {
+            let token = test_token(&state.auth_service, user);
+            let client = client_sdk::GameClient::connect(&format!("ws://{addr}"), &token).await.expect("connect");
+            client.join_room("snap-room").await.expect("join");
+            clients.push(client);
+        }
+
+        // Join qua WS spawn player trong worker: snapshot dựng lại có entity của chính client
+        for client in &clients {
+            let mut snapshots = client.snapshots();
+            tokio::time::timeout(std::time::Duration::from_secs(10), async {
+                while let Some(snapshot) = snapshots.next().await {
+                    if snapshot.score(client.player_id()).is_some() {
+                        return;
+                    }
+                }
+                panic!("snapshot stream ended");
+            })
+            .await
+            .expect("snapshot with own player");
+        }
+        assert!(state.snapshots.is_broadcasting("snap-room").await);
+
+        // Chat tới mọi connection trong room, kể cả người gửi
+        let (mut alice_chat, mut bob_chat) = (clients[0].chat(), clients[1].chat());
+        alice_chat.send("gg").expect("send chat");
+        for chat in [&mut alice_chat, &mut bob_chat] {
+            let message = tokio::time::timeout(std::time::Duration::from_secs(5), chat.recv())
+                .await
+                .expect("chat delivered")
+                .expect("chat open");
+            assert_eq!((message.player_id.as_str(), message.message.as_str()), ("snap-alice", "gg"));
+        }
+
+        // Hết client thì task của room tự dừng
+        for client in clients {
+            client.close().await;
+        }
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while state.snapshots.is_broadcasting("snap-room").await {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("broadcast stops");
+    }
+
+    #[tokio::test]
+    async fn ws_input_reaches_the_player_and_is_rejected_outside_a_room() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint.clone()).await;
+        state.worker_client = worker_client::new(worker::rpc::channel(&worker_endpoint).expect("worker channel"));
+        state.snapshots = snapshots::SnapshotBroadcaster::new(state.worker_client.clone(), state.ws_registry.clone())
+            .with_interval(std::time::Duration::from_millis(20));
+        let (addr, state) = spawn_gateway_with(state).await;
+        let token = test_token(&state.auth_service, "input-alice");
+
+        // Chưa join thì input bị từ chối thay vì bị echo lại
+        let mut socket = ws_connect(addr, &token).await;
+        skip_transport_selected(&mut socket).await;
+        socket
+            .send(WsMessage::Text(r#"{"type":"input","seq":1,"payload":{"movement":[1.0,0.0,0.0],"timestamp":0}}"#.to_string()))
+            .await
+            .expect("input");
+        let reply = loop {
+            if let WsMessage::Text(text) = socket.next().await.expect("message").expect("ws message") {
+                break message::decode(text.as_bytes()).expect("frame");
+            }
+        };
+        assert!(matches!(
+            reply.payload,
+            FramePayload::Control { message: ControlMessage::Error { ref code, .. } } if code == "not_in_room"
+        ));
+        socket.close(None).await.expect("close");
+
+        let client = client_sdk::GameClient::connect(&format!("ws://{addr}"), &token).await.expect("connect");
+        client.join_room("input-room").await.expect("join");
+        // Lane snapping giữ x ở tâm lane nên chỉ vận tốc x cho thấy input đã tới simulation
+        let velocity_x = |snapshot: &client_sdk::GameSnapshot| {
+            snapshot.player("input-alice").and_then(|player| player.pointer("/velocity/velocity/0")?.as_i64())
+        };
+        let mut snapshots = client.snapshots();
+        let idle = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                if let Some(x) = velocity_x(&snapshots.next().await.expect("snapshot")) {
+                    return x;
+                }
+            }
+        })
+        .await
+        .expect("own player in snapshot");
+        assert_eq!(idle, 0);
+
+        tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                client.send_input(client_sdk::PlayerInput::movement(1.0, 0.0, 0.0)).expect("input");
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                if client.latest_snapshot().as_ref().and_then(velocity_x).is_some_and(|x| x > 0) {
+                    return;
+                }
+            }
+        })
+        .await
+        .expect("input reaches the player");
+        client.close().await;
+    }
+
+    /// Frame của một lần `/poll/recv` (hoặc reply của `/poll/send`)
+    async fn poll_frames(request: reqwest::RequestBuilder) -> Vec<Frame> {
+        let response = request.send().await.expect("poll request");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: longpoll::PollFrames = response.json().await.expect("poll body");
+        body.frames.into_iter().map(|frame| serde_json::from_value(frame).expect("frame json")).collect()
+    }
+
+    #[tokio::test]
+    async fn long_poll_client_joins_sends_input_and_receives_snapshots() {
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint.clone()).await;
+        state.worker_client = worker_client::new(worker::rpc::channel(&worker_endpoint).expect("worker channel"));
+        state.snapshots = snapshots::SnapshotBroadcaster::new(state.worker_client.clone(), state.ws_registry.clone())
+            .with_interval(std::time::Duration::from_millis(20));
+        let (addr, state) = spawn_gateway_with(state).await;
+        let http = reqwest::Client::new();
+        let longpoll_connections = || {
+            metrics::sample("gateway_transport_connections_total", &[("transport_type", "longpoll"), ("fallback_used", "true")])
+                .unwrap_or_default()
+        };
+        let before = longpoll_connections();
+
+        let joined: longpoll::PollJoinResponse = http
+            .post(format!("http://{addr}{POLL_JOIN_PATH}"))
+            .bearer_auth(test_token(&state.auth_service, "poll-alice"))
+            .send()
+            .await
+            .expect("poll join")
+            .json()
+            .await
+            .expect("join body");
+        assert!(longpoll_connections() > before);
+        let recv = || http.get(format!("http://{addr}{POLL_RECV_PATH}?token={}&wait_ms=1000", joined.poll_token));
+        let send = |frame: Frame| {
+            http.post(format!("http://{addr}{POLL_SEND_PATH}?token={}", joined.poll_token)).json(&vec![frame])
+        };
+
+        let first = poll_frames(recv()).await;
+        assert!(matches!(
+            first[0].payload,
+            FramePayload::Control { message: ControlMessage::TransportSelected { kind: TransportKind::LongPoll, .. } }
+        ));
+
+        let reply = poll_frames(send(Frame::control(1, 0, ControlMessage::JoinRoom {
+            room_id: "poll-room".to_string(),
+            reconnect_token: None,
+        })))
+        .await;
+        assert!(matches!(reply[0].payload, FramePayload::Control { message: ControlMessage::MigrationToken { ref connection_id, .. } } if *connection_id == joined.connection_id));
+
+        // Snapshot tới qua recv như qua WS; input gửi qua send làm player di chuyển
+        let velocity_x = |snapshot: &client_sdk::GameSnapshot| {
+            snapshot.player("poll-alice").and_then(|player| player.pointer("/velocity/velocity/0")?.as_i64())
+        };
+        let mut snapshot = client_sdk::GameSnapshot::default();
+        let mut seq = 1;
+        tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                for frame in poll_frames(recv()).await {
+                    if let FramePayload::State { message } = &frame.payload {
+                        snapshot.apply(message);
+                    }
+                }
+                if velocity_x(&snapshot).is_some_and(|x| x > 0) {
+                    return;
+                }
+                if velocity_x(&snapshot).is_some() {
+                    seq += 1;
+                    // Worker chỉ nhận input có timestamp tăng dần
+                    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                    let input = serde_json::json!({ "movement": [1.0, 0.0, 0.0], "timestamp": now_millis() });
+                    let rejected = poll_frames(send(Frame::control(seq, 0, ControlMessage::Input { seq, payload: input }))).await;
+                    assert!(rejected.is_empty(), "{rejected:?}");
+                }
+            }
+        })
+        .await
+        .expect("input reaches the player and shows up in snapshots");
+
+        // Không recv quá ngưỡng idle thì connection bị dọn khỏi mọi registry
+        assert_eq!(longpoll::reap_idle(&state, std::time::Duration::ZERO), 1);
+        assert!(state.poll_registry.is_empty());
+        assert!(state.ws_registry.room(&joined.connection_id).is_none());
+        assert!(state.transport_registry.kind(&joined.connection_id).is_none());
+        let expired = recv().send().await.expect("recv after reap");
+        assert_eq!(expired.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn migrated_session_keeps_receiving_snapshots_on_new_socket() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint.clone()).await;
+        state.worker_client = worker_client::new(worker::rpc::channel(&worker_endpoint).expect("worker channel"));
+        state.snapshots = snapshots::SnapshotBroadcaster::new(state.worker_client.clone(), state.ws_registry.clone())
+            .with_interval(std::time::Duration::from_millis(20));
+        let (addr, state) = spawn_gateway_with(state).await;
+        let token = test_token(&state.auth_service, "mig-alice");
+
+        async fn next_control<S>(socket: &mut S) -> ControlMessage
+        where
+            S: futures::Stream<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+        {
+            loop {
+                if let WsMessage::Text(text) = socket.next().await.expect("message").expect("ws message") {
+                    if let FramePayload::Control { message } = message::decode(text.as_bytes()).expect("frame").payload {
+                        return message;
+                    }
+                }
+            }
+        }
+
+        async fn next_snapshot_tick<S>(socket: &mut S) -> u64
+        where
+            S: futures::Stream<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+        {
+            loop {
+                if let WsMessage::Binary(bytes) = socket.next().await.expect("message").expect("ws message") {
+                    if let FramePayload::State {
+                        message: StateMessage::Snapshot { tick, .. } | StateMessage::Delta { tick, .. },
+                    } = message::decode(&bytes).expect("frame").payload
+                    {
+                        return tick;
+                    }
+                }
+            }
+        }
+
+        let mut old_socket = ws_connect(addr, &token).await;
+        skip_transport_selected(&mut old_socket).await;
+        old_socket
+            .send(WsMessage::Text(r#"{"type":"join_room","room_id":"mig-room","reconnect_token":null}"#.to_string()))
+            .await
+            .expect("join");
+        let ControlMessage::MigrationToken { connection_id, auth_nonce } = next_control(&mut old_socket).await else {
+            panic!("join must hand out a migration token");
+        };
+        let from_label = transport_label(state.transport_registry.kind(&connection_id).expect("old connection"));
+        let last_old_tick = tokio::time::timeout(std::time::Duration::from_secs(10), next_snapshot_tick(&mut old_socket))
+            .await
+            .expect("snapshot on old socket");
+
+        let mut new_socket = ws_connect(addr, &token).await;
+        skip_transport_selected(&mut new_socket).await;
+        let to_label = state
+            .transport_registry
+            .filter_map(|id, conn| (id != connection_id).then(|| transport_label(conn.kind())))
+            .pop()
+            .expect("new connection");
+        let migrations = || metrics::sample("gateway_transport_migrations_total", &[("from", from_label), ("to", to_label)]);
+        let migrations_before = migrations().unwrap_or_default();
+
+        // Nonce sai bị từ chối, connection cũ giữ nguyên room
+        let forged = serde_json::json!({ "type": "migrate_transport", "from_connection_id": connection_id, "auth_nonce": "forged" });
+        new_socket.send(WsMessage::Text(forged.to_string())).await.expect("forged migrate");
+        assert!(matches!(next_control(&mut new_socket).await, ControlMessage::Error { ref code, .. } if code == "migration_rejected"));
+
+        let migrate = serde_json::json!({ "type": "migrate_transport", "from_connection_id": connection_id, "auth_nonce": auth_nonce });
+        new_socket.send(WsMessage::Text(migrate.to_string())).await.expect("migrate");
+        let ControlMessage::MigrationToken { connection_id: new_connection_id, .. } = next_control(&mut new_socket).await else {
+            panic!("migration must hand out a fresh token");
+        };
+        assert_ne!(new_connection_id, connection_id);
+
+        let mut previous = last_old_tick;
+        for _ in 0..5 {
+            let tick = tokio::time::timeout(std::time::Duration::from_secs(10), next_snapshot_tick(&mut new_socket))
+                .await
+                .expect("snapshot on new socket");
+            assert!(tick >= previous && tick - previous <= 1, "tick gap {previous} -> {tick}");
+            previous = tick;
+        }
+
+        // Socket cũ bị đóng; registry chỉ còn connection mới trong room, không có leave nào
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while let Some(Ok(msg)) = old_socket.next().await {
+                if matches!(msg, WsMessage::Close(_)) {
+                    break;
+                }
+            }
+        })
+        .await
+        .expect("old socket closed");
+        let rooms = state.ws_registry.filter_map(|id, conn| Some((id.to_string(), conn.room_id.clone())));
+        assert_eq!(rooms, vec![(new_connection_id, "mig-room".to_string())]);
+        assert_eq!(migrations(), Some(migrations_before + 1.0));
+    }
+
+    /// Room-manager thật (REST API) trên PocketBase giả chỉ nhận create/update/delete record
+    async fn spawn_room_manager() -> room_client::RoomManagerClient {
+        async fn record(Json(mut body): Json<serde_json::Value>) -> Json<serde_json::Value> {
+            body["created"] = serde_json::json!("");
+            body["updated"] = serde_json::json!("");
+            Json(body)
+        }
+
+        let pocketbase = Router::new()
+            .route("/api/collections/:collection/records", post(record))
+            .route("/api/collections/:collection/records/:id", axum::routing::patch(record).delete(|| async {}));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let pocketbase_url = format!("http://{}", listener.local_addr().expect("local addr"));
+        tokio::spawn(axum::Server::from_tcp(listener).expect("server").serve(pocketbase.into_make_service()));
+
+        let rooms = Arc::new(RwLock::new(room_manager::RoomManagerState::new(&pocketbase_url).expect("room state")));
+        let api = room_manager::api::router(rooms, "kick-secret");
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let api_url = format!("http://{}", listener.local_addr().expect("local addr"));
+        tokio::spawn(axum::Server::from_tcp(listener).expect("server").serve(api.into_make_service()));
+        room_client::RoomManagerClient::new(api_url, "kick-secret")
+    }
+
+    #[tokio::test]
+    async fn kicked_player_receives_kicked_frame_and_is_disconnected() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint.clone()).await;
+        state.worker_client = worker_client::new(worker::rpc::channel(&worker_endpoint).expect("worker channel"));
+        state.room_manager = spawn_room_manager().await;
+        let (addr, state) = spawn_gateway_with(state).await;
+
+        let created = state
+            .room_manager
+            .create_room(&room_manager::CreateRoomRequest {
+                name: "kick-room".to_string(),
+                game_mode: GameMode::deathmatch(),
+                max_players: 4,
+                host_player_id: "kick-host".to_string(),
+                settings: None,
+                backfill_with_bots: false,
+                is_private: false,
+            })
+            .await
+            .expect("create room");
+        let room_id = created.room_id;
+        for player_id in ["kick-target", "kick-bystander"] {
+            let joined = state
+                .room_manager
+                .join_room(&room_manager::JoinRoomRequest {
+                    room_id: room_id.clone(),
+                    player_id: player_id.to_string(),
+                    player_name: player_id.to_string(),
+                    invite_code: None,
+                })
+                .await
+                .expect("join room");
+            assert!(joined.success, "{:?}", joined.error);
+        }
+
+        let token = test_token(&state.auth_service, "kick-target");
+        let mut socket = ws_connect(addr, &token).await;
+        socket
+            .send(WsMessage::Text(format!(r#"{{"type":"join_room","room_id":"{room_id}","reconnect_token":null}}"#)))
+            .await
+            .expect("join");
+
+        let client = reqwest::Client::new();
+        let kick = |user: &str| {
+            client
+                .post(format!("http://{addr}{ROOMS_KICK_PATH}"))
+                .bearer_auth(test_token(&state.auth_service, user))
+                .json(&serde_json::json!({ "room_id": room_id, "target_player_id": "kick-target", "reason": "spam" }))
+                .send()
+        };
+
+        let anonymous = client
+            .post(format!("http://{addr}{ROOMS_KICK_PATH}"))
+            .json(&serde_json::json!({ "room_id": room_id, "target_player_id": "kick-target" }))
+            .send()
+            .await
+            .expect("kick");
+        assert_eq!(anonymous.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let by_bystander = kick("kick-bystander").await.expect("kick");
+        assert_eq!(by_bystander.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let by_host = kick("kick-host").await.expect("kick");
+        assert_eq!(by_host.status(), reqwest::StatusCode::OK);
+
+        let (kicked, closed) = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            let mut kicked = None;
+            while let Some(Ok(message)) = socket.next().await {
+                match message {
+                    WsMessage::Binary(bytes) => {
+                        if let FramePayload::Control { message: ControlMessage::Kicked { reason } } =
+                            message::decode(&bytes).expect("frame").payload
+                        {
+                            kicked = Some(reason);
+                        }
+                    }
+                    WsMessage::Close(_) => return (kicked, true),
+                    _ => {}
+                }
+            }
+            (kicked, false)
+        })
+        .await
+        .expect("kick delivered");
+        assert_eq!(kicked.as_deref(), Some("spam"));
+        assert!(closed);
+    }
+
+    #[tokio::test]
+    async fn ready_endpoint_shows_readiness_and_starts_countdown_when_everyone_is_ready() {
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint.clone()).await;
+        state.worker_client = worker_client::new(worker::rpc::channel(&worker_endpoint).expect("worker channel"));
+        let (addr, mut state) = spawn_gateway_with(state).await;
+
+        let created = state
+            .worker_client
+            .create_room(proto::worker::v1::CreateRoomRequest {
+                room_name: "ready-room".to_string(),
+                host_id: "ready-host".to_string(),
+                host_name: "Host".to_string(),
+                settings: Some(proto::worker::v1::RoomSettings {
+                    max_players: 4,
+                    auto_start: true,
+                    min_players_to_start: 2,
+                    ..Default::default()
+                }),
+            })
+            .await
+            .expect("create room")
+            .into_inner();
+        assert!(created.success, "{}", created.error);
+        let room_id = created.room_id;
+        let joined = state
+            .worker_client
+            .join_room_as_player(proto::worker::v1::JoinRoomAsPlayerRequest {
+                room_id: room_id.clone(),
+                player_id: "ready-guest".to_string(),
+                player_name: "Guest".to_string(),
+            })
+            .await
+            .expect("join room")
+            .into_inner();
+        assert!(joined.success, "{}", joined.error);
+
+        let client = reqwest::Client::new();
+        let set_ready = |user: &str| {
+            client
+                .post(format!("http://{addr}{ROOMS_READY_PATH}"))
+                .bearer_auth(test_token(&state.auth_service, user))
+                .json(&serde_json::json!({ "room_id": room_id, "ready": true }))
+                .send()
+        };
+        // Trạng thái lobby (ready, countdown) do worker giữ
+        let room_info = || {
+            let mut worker = state.worker_client.clone();
+            let request = proto::worker::v1::GetRoomInfoRequest { room_id: room_id.clone() };
+            async move { worker.get_room_info(request).await.expect("room info").into_inner().room.expect("room") }
+        };
+
+        let anonymous = client
+            .post(format!("http://{addr}{ROOMS_READY_PATH}"))
+            .json(&serde_json::json!({ "room_id": room_id, "ready": true }))
+            .send()
+            .await
+            .expect("ready");
+        assert_eq!(anonymous.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let outsider = set_ready("ready-outsider").await.expect("ready");
+        assert_eq!(outsider.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        assert_eq!(set_ready("ready-host").await.expect("ready").status(), reqwest::StatusCode::OK);
+        let room = room_info().await;
+        let ready: Vec<(&str, bool)> = room.players.iter().map(|player| (player.id.as_str(), player.is_ready)).collect();
+        assert_eq!(ready.len(), 2);
+        assert!(ready.contains(&("ready-host", true)) && ready.contains(&("ready-guest", false)));
+        assert_eq!(room.state, proto::worker::v1::RoomState::Waiting as i32);
+
+        assert_eq!(set_ready("ready-guest").await.expect("ready").status(), reqwest::StatusCode::OK);
+        let room = room_info().await;
+        assert_eq!(room.state, proto::worker::v1::RoomState::Starting as i32);
+        assert!(room.countdown_seconds_left > 0);
+    }
+
+    #[tokio::test]
+    async fn unknown_game_modes_are_rejected_with_the_valid_ids() {
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint).await;
+        state.room_manager = spawn_room_manager().await;
+        let (addr, _state) = spawn_gateway_with(state).await;
+        let client = reqwest::Client::new();
+
+        let created = client
+            .post(format!("http://{addr}{ROOMS_CREATE_PATH}"))
+            .json(&serde_json::json!({
+                "name": "tag-room", "game_mode": "tag", "max_players": 4, "host_player_id": "tag-host"
+            }))
+            .send()
+            .await
+            .expect("create");
+        assert_eq!(created.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let body: serde_json::Value = created.json().await.expect("json");
+        assert_eq!(body["error"]["code"], "validation_failed");
+        assert_eq!(body["error"]["fields"][0]["field"], "game_mode");
+        let message = body["error"]["fields"][0]["message"].as_str().expect("message");
+        assert!(message.contains("'tag'") && message.contains(game_modes::ENDLESS_RUNNER), "{message}");
+
+        let listed = client.get(format!("http://{addr}{ROOMS_LIST_PATH}?game_mode=tag")).send().await.expect("list");
+        assert_eq!(listed.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+        let runner_room = client
+            .post(format!("http://{addr}{ROOMS_CREATE_PATH}"))
+            .json(&serde_json::json!({
+                "name": "runner-room", "game_mode": game_modes::ENDLESS_RUNNER, "max_players": 4, "host_player_id": "runner-host"
+            }))
+            .send()
+            .await
+            .expect("create");
+        assert_eq!(runner_room.status(), reqwest::StatusCode::OK);
+        let listed: serde_json::Value = client
+            .get(format!("http://{addr}{ROOMS_LIST_PATH}?game_mode={}", game_modes::ENDLESS_RUNNER))
+            .send()
+            .await
+            .expect("list")
+            .json()
+            .await
+            .expect("json");
+        let names: Vec<&str> = listed["rooms"].as_array().expect("rooms").iter().filter_map(|room| room["name"].as_str()).collect();
+        assert_eq!(names, vec!["runner-room"]);
+    }
+
+    #[tokio::test]
+    async fn retried_room_creates_with_one_idempotency_key_create_a_single_room() {
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint).await;
+        state.room_manager = spawn_room_manager().await;
+        let (addr, state) = spawn_gateway_with(state).await;
+        let client = reqwest::Client::new();
+        let body = serde_json::json!({
+            "name": "retry-room", "game_mode": "deathmatch", "max_players": 4, "host_player_id": "retry-host"
+        });
+        let create = |key: &str, user: &str, body: &serde_json::Value| {
+            client
+                .post(format!("http://{addr}{ROOMS_CREATE_PATH}"))
+                .header(idempotency::IDEMPOTENCY_KEY_HEADER, key)
+                .bearer_auth(test_token(&state.auth_service, user))
+                .json(body)
+                .send()
+        };
+        let room_id = |response: reqwest::Response| async move {
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+            let body: serde_json::Value = response.json().await.expect("json");
+            body["room_id"].as_str().expect("room id").to_string()
+        };
+
+        let first = room_id(create("create-1", "retry-host", &body).await.expect("create")).await;
+        let retried = room_id(create("create-1", "retry-host", &body).await.expect("create")).await;
+        assert_eq!(first, retried);
+        let listed: serde_json::Value = client
+            .get(format!("http://{addr}{ROOMS_LIST_PATH}"))
+            .send()
+            .await
+            .expect("list")
+            .json()
+            .await
+            .expect("json");
+        let rooms = listed["rooms"].as_array().expect("rooms");
+        assert_eq!(rooms.iter().filter(|room| room["name"] == "retry-room").count(), 1, "{listed}");
+
+        // Key tính riêng theo user, và không dùng lại được cho body khác
+        let other_user = room_id(create("create-1", "someone-else", &body).await.expect("create")).await;
+        assert_ne!(other_user, first);
+        let changed = serde_json::json!({
+            "name": "other-room", "game_mode": "deathmatch", "max_players": 4, "host_player_id": "retry-host"
+        });
+        let reused = create("create-1", "retry-host", &changed).await.expect("create");
+        assert_eq!(reused.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let error: serde_json::Value = reused.json().await.expect("json");
+        assert_eq!(error["error"]["fields"][0]["field"], "Idempotency-Key");
+    }
+
+    #[tokio::test]
+    async fn worker_rejecting_the_internal_token_surfaces_as_an_upstream_auth_error() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind worker");
+        let worker_endpoint = format!("http://{}", listener.local_addr().expect("addr"));
+        let svc = worker::rpc::WorkerService::new(std::sync::Arc::new(worker::rpc::WorkerState::default()));
+        let _worker = tokio::spawn(worker::rpc::serve_rpc_on(listener, svc, worker::rpc::RpcSecurity::new("worker-only-token")));
+        let (addr, _state) = spawn_gateway_with(build_app_state(worker_endpoint).await).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{addr}{GAME_JOIN_PATH}"))
+            .json(&serde_json::json!({ "room_id": "auth-room", "player_id": "auth-player" }))
+            .send()
+            .await
+            .expect("join request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_GATEWAY);
+        let body: serde_json::Value = response.json().await.expect("json");
+        assert_eq!(body["error"]["code"], "upstream_auth_failed");
+    }
+
+    #[tokio::test]
+    async fn room_list_pages_with_limit_offset_and_sort_and_reports_the_total() {
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint).await;
+        state.room_manager = spawn_room_manager().await;
+        let (addr, _state) = spawn_gateway_with(state).await;
+        let client = reqwest::Client::new();
+        for name in ["page-c", "page-a", "page-b"] {
+            let created = client
+                .post(format!("http://{addr}{ROOMS_CREATE_PATH}"))
+                .json(&serde_json::json!({
+                    "name": name, "game_mode": "deathmatch", "max_players": 4, "host_player_id": format!("{name}-host")
+                }))
+                .send()
+                .await
+                .expect("create");
+            assert_eq!(created.status(), reqwest::StatusCode::OK);
+        }
+        let list = |query: &str| client.get(format!("http://{addr}{ROOMS_LIST_PATH}?{query}")).send();
+
+        let page: serde_json::Value = list("sort=name&limit=2").await.expect("list").json().await.expect("json");
+        let names: Vec<&str> = page["rooms"].as_array().expect("rooms").iter().filter_map(|room| room["name"].as_str()).collect();
+        assert_eq!((names, page["total"].as_u64()), (vec!["page-a", "page-b"], Some(3)));
+        let page: serde_json::Value =
+            list("sort=-name&limit=2&offset=2&has_space=true").await.expect("list").json().await.expect("json");
+        let names: Vec<&str> = page["rooms"].as_array().expect("rooms").iter().filter_map(|room| room["name"].as_str()).collect();
+        assert_eq!((names, page["total"].as_u64()), (vec!["page-a"], Some(3)));
+
+        for (query, field) in [("sort=players", "sort"), ("limit=0", "limit"), ("offset=-1", "offset"), ("has_space=yes", "has_space")] {
+            let rejected = list(query).await.expect("list");
+            assert_eq!(rejected.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY, "{query}");
+            let body: serde_json::Value = rejected.json().await.expect("json");
+            assert_eq!(body["error"]["fields"][0]["field"], field, "{query}");
+        }
+    }
+
+    #[tokio::test]
+    async fn tournament_endpoints_run_a_bracket_to_a_champion() {
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint).await;
+        state.room_manager = spawn_room_manager().await;
+        let (addr, state) = spawn_gateway_with(state).await;
+
+        let client = reqwest::Client::new();
+        let post = |path: &str, user: &str, body: serde_json::Value| {
+            client
+                .post(format!("http://{addr}{path}"))
+                .bearer_auth(test_token(&state.auth_service, user))
+                .json(&body)
+                .send()
+        };
+
+        let anonymous = client
+            .post(format!("http://{addr}{TOURNAMENTS_CREATE_PATH}"))
+            .json(&serde_json::json!({ "name": "cup", "game_mode": "deathmatch", "max_participants": 4 }))
+            .send()
+            .await
+            .expect("create");
+        assert_eq!(anonymous.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let too_small = post(TOURNAMENTS_CREATE_PATH, "cup-host", serde_json::json!({
+            "name": "cup", "game_mode": "deathmatch", "max_participants": 1
+        }))
+        .await
+        .expect("create");
+        assert_eq!(too_small.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+        let created: serde_json::Value = post(TOURNAMENTS_CREATE_PATH, "cup-host", serde_json::json!({
+            "name": "cup", "game_mode": "deathmatch", "max_participants": 3
+        }))
+        .await
+        .expect("create")
+        .json()
+        .await
+        .expect("json");
+        let tournament_id = created["tournament"]["id"].as_str().expect("tournament id").to_string();
+        assert_eq!(created["tournament"]["host_player_id"], "cup-host");
+
+        for player in ["cup-a", "cup-b", "cup-c"] {
+            let registered = post(TOURNAMENTS_REGISTER_PATH, player, serde_json::json!({ "tournament_id": tournament_id }))
+                .await
+                .expect("register");
+            assert_eq!(registered.status(), reqwest::StatusCode::OK);
+        }
+
+        let body = serde_json::json!({ "tournament_id": tournament_id });
+        let by_player = post(TOURNAMENTS_START_PATH, "cup-a", body.clone()).await.expect("start");
+        assert_eq!(by_player.status(), reqwest::StatusCode::BAD_REQUEST);
+        let started = post(TOURNAMENTS_START_PATH, "cup-host", body).await.expect("start");
+        assert_eq!(started.status(), reqwest::StatusCode::OK);
+
+        // 3 người: hạt giống 1 được bye, chơi xong trận còn lại rồi chung kết
+        let detail_url = format!("http://{addr}{}", TOURNAMENT_DETAIL_PATH.replace(":id", &tournament_id));
+        let mut champion = serde_json::Value::Null;
+        for _ in 0..2 {
+            let detail: serde_json::Value = client.get(&detail_url).send().await.expect("get").json().await.expect("json");
+            let bracket = detail["tournament"]["brackets"].as_array().and_then(|b| b.last()).expect("bracket").clone();
+            let game = bracket["matches"]
+                .as_array()
+                .expect("matches")
+                .iter()
+                .find(|game| game["winner"].is_null())
+                .expect("pending match")
+                .clone();
+            assert!(detail["tournament"]["match_rooms"][game["match_id"].as_str().unwrap()].is_string());
+
+            let reported: serde_json::Value = post(TOURNAMENTS_REPORT_PATH, "cup-host", serde_json::json!({
+                "tournament_id": tournament_id,
+                "match_id": game["match_id"],
+                "winner_player_id": game["players"][0],
+            }))
+            .await
+            .expect("report")
+            .json()
+            .await
+            .expect("json");
+            assert_eq!(reported["success"], true, "{reported}");
+            champion = reported["tournament"]["champion"].clone();
+        }
+        assert!(champion.is_string(), "final decides the champion");
+
+        let missing = client
+            .get(format!("http://{addr}{}", TOURNAMENT_DETAIL_PATH.replace(":id", "missing")))
+            .send()
+            .await
+            .expect("get");
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn party_endpoints_require_auth_and_only_leader_can_queue() {
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint).await;
+        state.room_manager = spawn_room_manager().await;
+        let (addr, state) = spawn_gateway_with(state).await;
+
+        let client = reqwest::Client::new();
+        let post = |path: &str, user: &str, body: serde_json::Value| {
+            client
+                .post(format!("http://{addr}{path}"))
+                .bearer_auth(test_token(&state.auth_service, user))
+                .json(&body)
+                .send()
+        };
+
+        let anonymous = client.post(format!("http://{addr}{PARTY_CREATE_PATH}")).send().await.expect("create");
+        assert_eq!(anonymous.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let created: serde_json::Value =
+            post(PARTY_CREATE_PATH, "party-lead", serde_json::json!({})).await.expect("create").json().await.expect("json");
+        let party_id = created["party"]["id"].as_str().expect("party id").to_string();
+
+        let by_member = post(PARTY_INVITE_PATH, "party-b", serde_json::json!({ "party_id": party_id })).await.expect("invite");
+        assert_eq!(by_member.status(), reqwest::StatusCode::BAD_REQUEST);
+        let invited: serde_json::Value = post(PARTY_INVITE_PATH, "party-lead", serde_json::json!({ "party_id": party_id }))
+            .await
+            .expect("invite")
+            .json()
+            .await
+            .expect("json");
+        let code = invited["party"]["invite_code"].as_str().expect("invite code").to_string();
+
+        for friend in ["party-b", "party-c"] {
+            let joined = post(PARTY_JOIN_PATH, friend, serde_json::json!({ "invite_code": code })).await.expect("join");
+            assert_eq!(joined.status(), reqwest::StatusCode::OK);
+        }
+
+        let body = serde_json::json!({ "game_mode": "deathmatch" });
+        let by_friend = post(PARTY_QUEUE_PATH, "party-b", body.clone()).await.expect("queue");
+        assert_eq!(by_friend.status(), reqwest::StatusCode::BAD_REQUEST);
+        let queued: serde_json::Value = post(PARTY_QUEUE_PATH, "party-lead", body).await.expect("queue").json().await.expect("json");
+        assert!(queued["room_id"].is_string(), "{queued}");
+        assert_eq!(queued["party_members"], serde_json::json!(["party-lead", "party-b", "party-c"]));
+        assert!(queued["team"].is_string());
+
+        let left: serde_json::Value =
+            post(PARTY_LEAVE_PATH, "party-lead", serde_json::json!({})).await.expect("leave").json().await.expect("json");
+        assert_eq!(left["success"], true);
+        assert!(left["party"].is_null(), "leader leaving disbands the party");
+    }
+
+    #[tokio::test]
+    async fn peer_rtt_from_ping_pong_reaches_worker_snapshot() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        const CLIENT_DELAY: std::time::Duration = std::time::Duration::from_millis(150);
+
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let mut state = build_app_state(worker_endpoint.clone()).await;
+        state.worker_client = worker_client::new(worker::rpc::channel(&worker_endpoint).expect("worker channel"));
+        state.latency = latency::LatencyReporter::new(state.worker_client.clone(), state.ws_registry.clone())
+            .with_intervals(std::time::Duration::from_millis(100), std::time::Duration::from_millis(100));
+        let (addr, state) = spawn_gateway_with(state).await;
+
+        let mut worker_client = state.worker_client.clone();
+        let room_id = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                let request = proto::worker::v1::CreateRoomRequest {
+                    room_name: "rtt-room".to_string(),
+                    host_id: "rtt-host".to_string(),
+                    host_name: "rtt-host".to_string(),
+                    settings: None,
+                };
+                match worker_client.create_room(request).await {
+                    Ok(created) => break created.into_inner().room_id,
+                    Err(_) => tokio::time::sleep(std::time::Duration::from_millis(50)).await,
+                }
+            }
+        })
+        .await
+        .expect("worker reachable");
+        let joined = worker_client
+            .join_room(proto::worker::v1::JoinRoomRequest { room_id: room_id.clone(), player_id: "rtt-player".to_string(), player_name: String::new() })
+            .await
+            .expect("join worker room")
+            .into_inner();
+        assert!(joined.ok, "{}", joined.error);
+
+        let token = test_token(&state.auth_service, "rtt-player");
+        let socket = ws_connect(addr, &token).await;
+        let (mut sink, mut stream) = socket.split();
+        sink.send(WsMessage::Text(format!(r#"{{"type":"join_room","room_id":"{room_id}","reconnect_token":null}}"#)))
+            .await
+            .expect("join");
+        // Pong với nonce gateway chưa từng gửi: không được tính
+        sink.send(WsMessage::Text(r#"{"type":"pong","nonce":42}"#.to_string())).await.expect("bogus pong");
+
+        // Client chậm: trả Pong sau CLIENT_DELAY
+        let client = tokio::spawn(async move {
+            while let Some(Ok(message)) = stream.next().await {
+                let WsMessage::Binary(bytes) = message else { continue };
+                let Ok(frame) = message::decode(&bytes) else { continue };
+                if let FramePayload::Control { message: ControlMessage::Ping { nonce } } = frame.payload {
+                    tokio::time::sleep(CLIENT_DELAY).await;
+                    let pong = message::encode(&Frame::control(0, 0, ControlMessage::Pong { nonce })).expect("encode");
+                    if sink.send(WsMessage::Binary(pong)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let rtt_ms = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                let snapshot = worker_client
+                    .request_keyframe(proto::worker::v1::GetPlayerSnapshotRequest {
+                        room_id: room_id.clone(),
+                        player_id: "rtt-player".to_string(),
+                    })
+                    .await
+                    .expect("keyframe")
+                    .into_inner()
+                    .snapshot
+                    .expect("snapshot");
+                let payload: serde_json::Value = serde_json::from_str(&snapshot.payload_json).expect("snapshot json");
+                let rtt_ms = payload["Full"]["entities"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|entity| entity["player"]["id"] == "rtt-player")
+                    .and_then(|entity| entity["player"]["rtt_ms"].as_u64())
+                    .unwrap_or_default();
+                if rtt_ms > 0 {
+                    break rtt_ms;
+                }
+            }
+        })
+        .await
+        .expect("rtt reported to worker");
+        assert!(rtt_ms >= CLIENT_DELAY.as_millis() as u64, "rtt {rtt_ms}ms shorter than the client delay");
+        assert!(rtt_ms < latency::MAX_RTT_MS as u64);
+        assert!(metrics::render().contains("gateway_peer_rtt_ms"));
+
+        // Spectator không có player entity: worker trả lại id để gateway thôi gửi
+        let spectator = worker_client
+            .update_player_latency(proto::worker::v1::UpdatePlayerLatencyRequest {
+                latencies: vec![proto::worker::v1::PlayerLatency {
+                    room_id,
+                    player_id: "rtt-watcher".to_string(),
+                    rtt_ms: 80,
+                }],
+            })
+            .await
+            .expect("update latency")
+            .into_inner();
+        assert_eq!((spectator.updated, spectator.not_players), (0, vec!["rtt-watcher".to_string()]));
+        client.abort();
+    }
+
+    #[tokio::test]
+    async fn request_keyframe_replies_with_full_snapshot() {
+        let (worker_endpoint, _worker) = worker::rpc::spawn_test_server().await;
+        let worker_client = worker_client::new(worker::rpc::channel(&worker_endpoint).expect("worker channel"));
+        let ws_registry = WebSocketRegistry::new();
+        let mut session = InboundSession {
+            peer_id: "kf-player".to_string(),
+            connection_id: "kf-conn".to_string(),
+            ws_registry: ws_registry.clone(),
+            transport_registry: TransportRegistry::new(),
+            outbound: OutboundSequence::default(),
+            dedupe: FrameDedupe::new(INBOUND_DEDUPE_WINDOW),
+            snapshots: snapshots::SnapshotBroadcaster::new(worker_client.clone(), ws_registry),
+            bandwidth: bandwidth::BandwidthTracker::new(),
+            latency: latency::LatencyTracker::default(),
+            worker_client,
+            room_manager: room_client::RoomManagerClient::new("http://127.0.0.1:9", "secret"),
+            rtc_peers: rtc::ServerPeers::default(),
+            rtc_offer_pending: false,
+            analytics: analytics::SessionAnalytics::disabled(),
+        };
+
+        // Worker test server có thể chưa listen ngay nên thử lại vài lần
+        let reply = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                let request = Frame::control(0, 0, ControlMessage::RequestKeyframe {
+                    room_id: "kf-room".to_string(),
+                    player_id: "someone-else".to_string(),
+                });
+                if let Some(reply) = handle_inbound_frame(&mut session, request).await {
+                    return reply;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("keyframe reply");
+
+        assert!(matches!(reply.payload, FramePayload::State { message: StateMessage::Snapshot { .. } }));
+        assert!(reply.sequence > 0);
+        assert!(reply.timestamp_ms > 0, "state frames carry the worker's snapshot time");
+    }
+
+    #[test]
+    fn worker_snapshot_json_maps_to_state_messages() {
+        let full = proto::worker::v1::Snapshot {
+            tick: 3,
+            payload_json: r#"{"Full":{"tick":3,"entities":[{"id":1},{"id":2}],"chat_messages":[],"spectators":[]}}"#.to_string(),
+        };
+        match snapshots::state_message_from_snapshot(&full) {
+            Some(StateMessage::Snapshot { tick, entities, last_acked_sequence, .. }) => {
+                assert_eq!(tick, 3);
+                assert_eq!(last_acked_sequence, None);
+                assert_eq!(entities.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), vec!["1", "2"]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let delta = proto::worker::v1::Snapshot {
+            tick: 4,
+            payload_json: r#"{"Delta":{"tick":4,"base_tick":3,"created_entities":[],"updated_entities":[{"id":1}],"deleted_entities":[2],"server_time_ms":1700000000123,"tick_duration_ms":16.5,"last_acked_sequence":9}}"#.to_string(),
+        };
+        match snapshots::state_message_from_snapshot(&delta) {
+            Some(StateMessage::Delta { tick, changes, tick_duration_ms, last_acked_sequence }) => {
+                assert_eq!(tick, 4);
+                assert_eq!(changes.len(), 2);
+                assert_eq!(changes[1].changes["deleted"], true);
+                assert_eq!((tick_duration_ms, last_acked_sequence), (16.5, Some(9)));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn snapshot_frames_keep_the_worker_server_time() {
+        let outbound = OutboundSequence::default();
+        let timed = proto::worker::v1::Snapshot {
+            tick: 4,
+            payload_json: r#"{"Delta":{"tick":4,"base_tick":3,"created_entities":[],"updated_entities":[],"deleted_entities":[],"server_time_ms":1700000000123}}"#.to_string(),
+        };
+        let frame = outbound.stamp(snapshots::snapshot_frame(&timed).expect("delta frame"));
+        assert_eq!(frame.timestamp_ms, 1_700_000_000_123);
+
+        // Worker cũ không gửi server_time_ms: gateway vẫn đóng giờ của mình thay vì để 0
+        let untimed = proto::worker::v1::Snapshot {
+            tick: 3,
+            payload_json: r#"{"Full":{"tick":3,"entities":[],"chat_messages":[],"spectators":[]}}"#.to_string(),
+        };
+        let frame = outbound.stamp(snapshots::snapshot_frame(&untimed).expect("full frame"));
+        assert!(frame.timestamp_ms > 0);
+    }
+
+    /// PocketBase giả: chỉ chấp nhận player@example.com / secret; outage@example.com giả lập PocketBase lỗi 500
+    async fn spawn_mock_pocketbase() -> String {
+        async fn auth_with_password(Json(body): Json<serde_json::Value>) -> Response {
+            if body["identity"] == "outage@example.com" {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "code": 500, "message": "Something went wrong." })),
+                )
+                    .into_response()
+            } else if body["identity"] == "player@example.com" && body["password"] == "secret" {
+                Json(serde_json::json!({
+                    "token": "pb-token",
+                    "record": {
+                        "id": "pb-user-1",
+                        "created": "",
+                        "updated": "",
+                        "email": "player@example.com",
+                        "username": "player"
+                    }
+                }))
+                .into_response()
+            } else {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({ "code": 400, "message": "Failed to authenticate." })),
+                )
+                    .into_response()
+            }
+        }
+
+        // player@example.com đã tồn tại, email khác tạo được record mới
+        async fn create_user(Json(body): Json<serde_json::Value>) -> Response {
+            if body["email"] == "player@example.com" {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "code": 400,
+                        "message": "Failed to create record.",
+                        "data": { "email": { "code": "validation_not_unique", "message": "Value must be unique." } }
+                    })),
+                )
+                    .into_response()
+            } else {
+                Json(serde_json::json!({
+                    "id": "pb-user-2",
+                    "created": "",
+                    "updated": "",
+                    "email": body["email"],
+                    "name": body["name"]
+                }))
+                .into_response()
+            }
+        }
+
+        let app = Router::new()
+            .route("/api/collections/users/auth-with-password", post(auth_with_password))
+            .route("/api/collections/users/records", post(create_user));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("local addr");
+        tokio::spawn(axum::Server::from_tcp(listener).expect("server").serve(app.into_make_service()));
+        format!("http://{addr}")
+    }
+
+    fn counter_value(rendered: &str, name: &str) -> u64 {
+        rendered
+            .lines()
+            .find_map(|line| line.strip_prefix(name).and_then(|rest| rest.trim().parse().ok()))
+            .unwrap_or(0)
+    }
+
+    async fn login(state: &AppState, email: &str, password: &str) -> Response {
+        auth_login(
+            State(state.clone()),
+            Json(auth::EmailLoginRequest {
+                email: email.to_string(),
+                password: password.to_string(),
+            }),
+        )
+        .await
+        .into_response()
+    }
+
+    #[tokio::test]
+    async fn login_success_counts_success_only() {
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let _guard = ::metrics::set_default_local_recorder(&recorder);
+
+        let mut state = build_app_state("http://127.0.0.1:0".to_string()).await;
+        state.auth_service = state.auth_service.clone().with_pocketbase_url(spawn_mock_pocketbase().await);
+
+        let response = login(&state, "player@example.com", "secret").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.expect("body");
+        let body: auth::AuthResponse = serde_json::from_slice(&body).expect("auth response");
+        assert_eq!(body.user.id, "pb-user-1");
+        assert_eq!(state.auth_service.verify_token(&body.access_token).expect("jwt").claims.sub, "pb-user-1");
+
+        let rendered = handle.render();
+        assert_eq!(counter_value(&rendered, "gw_auth_login_success"), 1);
+        assert_eq!(counter_value(&rendered, "gw_auth_login_failed"), 0);
+    }
+
+    #[tokio::test]
+    async fn login_failure_returns_401_and_counts_failure() {
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let _guard = ::metrics::set_default_local_recorder(&recorder);
+
+        let mut state = build_app_state("http://127.0.0.1:0".to_string()).await;
+        state.auth_service = state.auth_service.clone().with_pocketbase_url(spawn_mock_pocketbase().await);
+
+        let response = login(&state, "player@example.com", "wrong").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = hyper::body::to_bytes(response.into_body()).await.expect("body");
+        let body: serde_json::Value = serde_json::from_slice(&body).expect("json body");
+        assert_eq!(body, serde_json::json!({ "error": { "code": "unauthorized", "message": "Invalid credentials" } }));
+
+        let rendered = handle.render();
+        assert_eq!(counter_value(&rendered, "gw_auth_login_success"), 0);
+        assert_eq!(counter_value(&rendered, "gw_auth_login_failed"), 1);
+    }
+
+    #[tokio::test]
+    async fn pocketbase_outage_is_an_upstream_error_not_a_failed_login() {
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let _guard = ::metrics::set_default_local_recorder(&recorder);
+
+        let mut state = build_app_state("http://127.0.0.1:0".to_string()).await;
+        state.auth_service = state.auth_service.clone().with_pocketbase_url(spawn_mock_pocketbase().await);
+        assert_eq!(login(&state, "outage@example.com", "secret").await.status(), StatusCode::BAD_GATEWAY);
+
+        // Port không ai listen: connection refused
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").expect("bind").local_addr().expect("addr");
+        state.auth_service = state.auth_service.clone().with_pocketbase_url(format!("http://{closed}"));
+        assert_eq!(login(&state, "player@example.com", "secret").await.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let rendered = handle.render();
+        assert_eq!(counter_value(&rendered, "gw_auth_login_success"), 0);
+        assert_eq!(counter_value(&rendered, "gw_auth_login_failed"), 0);
+    }
+
+    async fn register(state: &AppState, email: &str, password: &str) -> Response {
+        auth_register(
+            State(state.clone()),
+            Json(auth::RegisterRequest {
+                email: email.to_string(),
+                password: password.to_string(),
+                display_name: Some("New Player".to_string()),
+            }),
+        )
+        .await
+        .into_response()
+    }
+
+    #[tokio::test]
+    async fn register_creates_pocketbase_user_and_rejects_duplicate_email() {
+        let mut state = build_app_state("http://127.0.0.1:0".to_string()).await;
+        state.auth_service = state.auth_service.clone().with_pocketbase_url(spawn_mock_pocketbase().await);
+
+        let response = register(&state, "new@example.com", "password123").await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = hyper::body::to_bytes(response.into_body()).await.expect("body");
+        let body: auth::AuthResponse = serde_json::from_slice(&body).expect("auth response");
+        assert_eq!(body.user.username, "New Player");
+        assert_eq!(state.auth_service.verify_token(&body.access_token).expect("jwt").claims.sub, "pb-user-2");
+
+        let response = register(&state, "player@example.com", "password123").await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let response = register(&state, "short@example.com", "short").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn register_then_login_without_pocketbase() {
+        let mut state = build_app_state("http://127.0.0.1:0".to_string()).await;
+        state.auth_service = auth::AuthService::from_config(&auth::AuthConfig {
+            pocketbase_users: false,
+            ..state.auth_config.clone()
+        });
+
+        let response = register(&state, "local@example.com", "password123").await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = hyper::body::to_bytes(response.into_body()).await.expect("body");
+        let registered: auth::AuthResponse = serde_json::from_slice(&body).expect("auth response");
+
+        assert_eq!(register(&state, "LOCAL@example.com", "password123").await.status(), StatusCode::CONFLICT);
+        assert_eq!(login(&state, "local@example.com", "wrong-password").await.status(), StatusCode::UNAUTHORIZED);
+
+        let response = login(&state, "local@example.com", "password123").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.expect("body");
+        let logged_in: auth::AuthResponse = serde_json::from_slice(&body).expect("auth response");
+        assert_eq!(logged_in.user.id, registered.user.id);
+    }
+
+    #[tokio::test]
+    async fn idle_rtc_sessions_are_reaped() {
+        let state = build_app_state("http://127.0.0.1:0".to_string()).await;
+        let now = Utc::now();
+        let stale = now - chrono::Duration::minutes(10);
+
+        let webrtc_session = |session_id: &str, last_activity: DateTime<Utc>| WebRTCSession {
+            session_id: session_id.to_string(),
+            room_id: "room".to_string(),
+            user_id: "user".to_string(),
+            peer_connections: HashMap::new(),
+            status: WebRTCSessionStatus::Connected,
+            created_at: last_activity,
+            last_activity,
+        };
+        let signaling_session = |session_id: &str, last_activity: DateTime<Utc>| types::SignalingSession {
+            session_id: session_id.to_string(),
+            user_id: "user".to_string(),
+            peer_user_id: None,
+            status: "active".to_string(),
+            created_at: last_activity,
+            last_activity,
+            transport_type: "webrtc".to_string(),
+        };
+
+        state.webrtc_sessions.insert(webrtc_session("stale", stale));
+        state.webrtc_sessions.insert(webrtc_session("fresh", now));
+        {
+            let mut signaling = state.signaling_sessions.write().await;
+            signaling.insert("stale".to_string(), signaling_session("stale", stale));
+            signaling.insert("fresh".to_string(), signaling_session("fresh", now));
+        }
+        // Session stale đã Connected nên đang được tính trong gauge
+        let connected = || metrics::sample("gateway_webrtc_connections_current", &[("status", "connected")]);
+        metrics::record_webrtc_connected();
+        let before = connected().unwrap_or_default() - 1.0;
+
+        let reaped = reap_idle_sessions(
+            &state.signaling_sessions,
+            &state.webrtc_sessions,
+            std::time::Duration::from_secs(300),
+        )
+        .await;
+
+        assert_eq!(reaped, 2);
+        assert_eq!(connected(), Some(before));
+        let webrtc: Vec<String> = state.webrtc_sessions.for_user("user").into_iter().map(|s| s.session_id).collect();
+        assert_eq!(webrtc, vec!["fresh".to_string()]);
+        let signaling: Vec<String> = state.signaling_sessions.read().await.keys().cloned().collect();
+        assert_eq!(signaling, vec!["fresh".to_string()]);
+    }
+
+    #[test]
+    fn closed_room_label_is_removed() {
+        let room_id = format!("metrics-room-{}", uuid::Uuid::new_v4());
+        let now = Utc::now();
+
+        let mut rooms = vec![room_manager::Room {
+            id: room_id.clone(),
+            name: "metrics".to_string(),
+            game_mode: GameMode::deathmatch(),
+            max_players: 4,
+            current_players: 2,
+            status: RoomStatus::Waiting,
+            created_at: now,
+            updated_at: now,
+            host_player_id: "host".to_string(),
+            worker_endpoint: None,
+            settings: serde_json::json!({}),
+            backfill_with_bots: false,
+            is_private: false,
+            invite_code: None,
+            banned_players: Vec::new(),
+            starts_at: None,
+        }];
+
+        apply_room_gauges(&rooms);
+        let room_players = || metrics::sample("gateway_room_players", &[("room_id", &room_id)]);
+        let closed = || metrics::sample("gateway_room_lifecycle_total", &[("event", "closed")]).unwrap_or_default();
+        assert_eq!(room_players(), Some(2.0));
+
+        let closed_before = closed();
+        rooms[0].status = RoomStatus::Closed;
+        apply_room_gauges(&rooms);
+
+        assert_eq!(room_players(), None);
+        assert!(closed() > closed_before);
+    }
+
+    async fn refresh(state: &AppState, refresh_token: &str) -> Response {
+        auth_refresh(
+            State(state.clone()),
+            Json(auth::RefreshRequest { refresh_token: refresh_token.to_string() }),
+        )
+        .await
+        .into_response()
+    }
+
+    fn test_user() -> auth::User {
+        auth::User {
+            id: "refresh-user".to_string(),
+            username: "refresh".to_string(),
+            email: "refresh@example.com".to_string(),
+            role: "user".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh_token_is_single_use() {
+        let state = build_app_state("http://127.0.0.1:0".to_string()).await;
+        let tokens = state.auth_service.issue_tokens(test_user()).expect("tokens");
+
+        let response = refresh(&state, &tokens.refresh_token).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.expect("body");
+        let rotated: auth::AuthResponse = serde_json::from_slice(&body).expect("auth response");
+        assert_ne!(rotated.refresh_token, tokens.refresh_token);
+
+        // Token cũ đã dùng thì bị từ chối, token mới vẫn dùng được
+        assert_eq!(refresh(&state, &tokens.refresh_token).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(refresh(&state, &rotated.refresh_token).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn refresh_token_is_rejected_as_bearer_and_ws_token() {
+        let (addr, state) = spawn_gateway().await;
+        let tokens = state.auth_service.issue_tokens(test_user()).expect("tokens");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, format!("Bearer {}", tokens.refresh_token).parse().unwrap());
+        assert!(matches!(
+            extract_claims_from_headers(&headers, &state.auth_service),
+            Err(GatewayError::Unauthorized(_))
+        ));
+        headers.insert(AUTHORIZATION, format!("Bearer {}", tokens.access_token).parse().unwrap());
+        assert_eq!(extract_claims_from_headers(&headers, &state.auth_service).expect("access token").sub, "refresh-user");
+
+        let err = tokio_tungstenite::connect_async(format!("ws://{addr}{WS_PATH}?token={}", tokens.refresh_token))
+            .await
+            .expect_err("upgrade with a refresh token must fail");
+        match err {
+            tokio_tungstenite::tungstenite::Error::Http(resp) => {
+                assert_eq!(resp.status().as_u16(), StatusCode::UNAUTHORIZED.as_u16());
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn logout_revokes_refresh_token() {
+        let state = build_app_state("http://127.0.0.1:0".to_string()).await;
+        let tokens = state.auth_service.issue_tokens(test_user()).expect("tokens");
+
+        let response = auth_logout(
+            State(state.clone()),
+            Json(auth::RefreshRequest { refresh_token: tokens.refresh_token.clone() }),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        assert_eq!(refresh(&state, &tokens.refresh_token).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn readyz_fails_when_the_worker_is_down_while_healthz_stays_ok() {
+        let (addr, _state) = spawn_gateway().await;
+        let client = reqwest::Client::new();
+
+        let health = client.get(format!("http://{}{}", addr, HEALTHZ_PATH)).send().await.expect("healthz");
+        assert_eq!(health.status(), reqwest::StatusCode::OK);
+
+        let ready = client.get(format!("http://{}{}", addr, READYZ_PATH)).send().await.expect("readyz");
+        assert_eq!(ready.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = ready.json().await.expect("readyz json");
+        assert_eq!(body["worker"], "down");
+        assert!(body.get("room_manager").is_some());
+        assert!(body.get("pocketbase").is_some());
+
+        let rendered = client.get(format!("http://{}{}", addr, METRICS_PATH)).send().await.expect("metrics");
+        let rendered = rendered.text().await.expect("metrics body");
+        assert!(rendered.contains(r#"service_dependency_status{dependency="worker",service="gateway"} 0"#));
+    }
+
+    #[tokio::test]
+    async fn worker_scores_need_an_explicitly_configured_secret() {
+        let url = |addr: SocketAddr| format!("http://{}{}", addr, common_net::scores::SCORES_PATH);
+        let submission = serde_json::json!({
+            "room_id": "room-1",
+            "tick": 600,
+            "game_mode": "deathmatch",
+            "player_id": "alice",
+            "player_name": "ALICE",
+            "score": 10,
+        });
+        let client = reqwest::Client::new();
+
+        // Không đặt secret: cả secret mặc định cũ lẫn header rỗng đều bị từ chối
+        let unconfigured = room_client::RoomManagerClient::with_secret("http://127.0.0.1:9", None);
+        let (addr, _state) = spawn_gateway_with(build_app_state_with("http://127.0.0.1:0".to_string(), unconfigured).await).await;
+        for secret in ["room-manager-internal-secret-change-in-production", ""] {
+            let response = client
+                .post(url(addr))
+                .header(room_manager::api::INTERNAL_SECRET_HEADER, secret)
+                .json(&submission)
+                .send()
+                .await
+                .expect("submit");
+            assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED, "{secret:?}");
+        }
+
+        // Secret rỗng trong config cũng coi như chưa đặt
+        let empty = room_client::RoomManagerClient::new("http://127.0.0.1:9", "");
+        assert_eq!(empty.secret(), None);
+        let (addr, _state) = spawn_gateway_with(build_app_state_with("http://127.0.0.1:0".to_string(), empty).await).await;
+        let response = client
+            .post(url(addr))
+            .header(room_manager::api::INTERNAL_SECRET_HEADER, "")
+            .json(&submission)
+            .send()
+            .await
+            .expect("submit");
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn pocketbase_is_required_only_when_users_live_there() {
+        let unreachable = "http://127.0.0.1:1";
+        let worker = worker_client::new(Endpoint::from_static("http://127.0.0.1:1").connect_lazy());
+        let room_manager = room_client::RoomManagerClient::new(unreachable, "secret");
+
+        let shared = readiness(worker.clone(), room_manager.clone(), None).report().await;
+        assert_eq!(shared.dependencies["pocketbase"], DependencyStatus::Degraded);
+
+        let user_store = pocketbase::PocketBaseClient::new(unreachable);
+        let owned = readiness(worker, room_manager, Some(user_store)).report().await;
+        assert_eq!(owned.dependencies["pocketbase"], DependencyStatus::Down);
+        assert!(!owned.ready);
+    }
+
+    #[tokio::test]
+    async fn main_binary_routes_are_registered() {
+        let (addr, _state) = spawn_gateway().await;
+        let client = reqwest::Client::new();
+
+        // Các route main.rs từng tự định nghĩa, nay phục vụ bởi build_router
+        let routes = [
+            (reqwest::Method::GET, HEALTHZ_PATH),
+            (reqwest::Method::GET, READYZ_PATH),
+            (reqwest::Method::GET, VERSION_PATH),
+            (reqwest::Method::GET, METRICS_PATH),
+            (reqwest::Method::GET, WS_PATH),
+            (reqwest::Method::POST, "/auth/login"),
+            (reqwest::Method::POST, "/auth/refresh"),
+            (reqwest::Method::POST, "/auth/logout"),
+            (reqwest::Method::POST, "/inputs"),
+        ];
+
+        for (method, path) in routes {
+            let response = client
+                .request(method.clone(), format!("http://{}{}", addr, path))
+                .send()
+                .await
+                .expect("request");
+            assert_ne!(response.status(), reqwest::StatusCode::NOT_FOUND, "{} {} not registered", method, path);
+            assert_ne!(response.status(), reqwest::StatusCode::METHOD_NOT_ALLOWED, "{} {} wrong method", method, path);
+        }
+    }
+
+    async fn connected_transport(room_id: &str, peer_id: &str) -> TransportConnection {
+        let transport = WebRtcTransport::new(room_id.to_string(), peer_id.to_string());
+        transport.set_connected(true).await;
+        let mut connection = TransportConnection::new(
+            peer_id.to_string(),
+            OutboundSequence::default(),
+            Box::new(transport),
+            TransportSelection {
+                kind: TransportKind::WebRtc,
+                fallback_used: false,
+                reason: TransportSelection::WEBRTC_CONNECTED,
+            },
+        );
+        connection.room_id = room_id.to_string();
+        connection
+    }
+
+    /// Lấy hết frame đã gửi qua transport của peer (WebRtcTransport giả lập loopback)
+    async fn drain_frames(registry: &TransportRegistry, connection_id: &str) -> Vec<Frame> {
+        let transport = registry.transport(connection_id).expect("connection");
+        let mut transport = transport.lock().await;
+        let mut frames = Vec::new();
+        while let Ok(frame) = transport.recv_frame().await {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    #[tokio::test]
+    async fn duplicate_inbound_frames_are_delivered_once() {
+        let transport_registry = TransportRegistry::new();
+        transport_registry.insert("bob-conn".to_string(), connected_transport("room-1", "bob").await);
+
+        let ws_registry = WebSocketRegistry::new();
+        let worker_client = worker_client::new(Endpoint::from_static("http://127.0.0.1:0").connect_lazy());
+        let mut session = InboundSession {
+            peer_id: "alice".to_string(),
+            connection_id: "alice-conn".to_string(),
+            ws_registry: ws_registry.clone(),
+            transport_registry: transport_registry.clone(),
+            outbound: OutboundSequence::default(),
+            dedupe: FrameDedupe::new(INBOUND_DEDUPE_WINDOW),
+            snapshots: snapshots::SnapshotBroadcaster::new(worker_client.clone(), ws_registry),
+            bandwidth: bandwidth::BandwidthTracker::new(),
+            latency: latency::LatencyTracker::default(),
+            worker_client,
+            room_manager: room_client::RoomManagerClient::new("http://127.0.0.1:9", "secret"),
+            rtc_peers: rtc::ServerPeers::default(),
+            rtc_offer_pending: false,
+            analytics: analytics::SessionAnalytics::disabled(),
+        };
+
+        let frame = Frame::control(7, 1, ControlMessage::WebRtcIceCandidate {
+            room_id: "room-1".to_string(),
+            peer_id: "alice".to_string(),
+            target_peer_id: None,
+            candidate: "candidate:1".to_string(),
+            sdp_mid: "0".to_string(),
+            sdp_mline_index: 0,
+        });
+        let deduped = || metrics::sample("gateway_frames_deduped_total", &[]).unwrap_or_default();
+        let deduped_before = deduped();
+        handle_inbound_frame(&mut session, frame.clone()).await;
+        handle_inbound_frame(&mut session, frame).await;
+
+        assert_eq!(drain_frames(&transport_registry, "bob-conn").await.len(), 1);
+        assert!(deduped() > deduped_before);
+    }
+
+    #[tokio::test]
+    async fn outbound_sequences_increase_per_connection() {
+        let transport_registry = TransportRegistry::new();
+        transport_registry.insert("bob-conn".to_string(), connected_transport("room-1", "bob").await);
+        transport_registry.insert("carol-conn".to_string(), connected_transport("room-1", "carol").await);
+        let bandwidth = bandwidth::BandwidthTracker::new();
+
+        for _ in 0..3 {
+            let frame = Frame::control(0, 0, ControlMessage::Ping { nonce: 1 });
+            broadcast_to_transport(&transport_registry, &bandwidth, "room-1", "alice", frame).await;
+        }
+        send_to_transport(&transport_registry, &bandwidth, "bob", Frame::control(0, 0, ControlMessage::Ping { nonce: 2 })).await;
+
+        let bob: Vec<u32> = drain_frames(&transport_registry, "bob-conn").await.iter().map(|f| f.sequence).collect();
+        let carol: Vec<u32> = drain_frames(&transport_registry, "carol-conn").await.iter().map(|f| f.sequence).collect();
+        assert_eq!(bob, vec![1, 2, 3, 4]);
+        assert_eq!(carol, vec![1, 2, 3]);
+
+        let session_outbound = OutboundSequence::default();
+        let first = session_outbound.stamp(Frame::control(0, 0, ControlMessage::Pong { nonce: 1 }));
+        let second = session_outbound.stamp(Frame::control(0, 0, ControlMessage::Pong { nonce: 2 }));
+        assert!(second.sequence > first.sequence);
+        assert!(first.timestamp_ms > 0);
+    }
+}
@@ -0,0 +1,437 @@
+//! Transport HTTP long-poll cho mạng chặn cả WS upgrade lẫn UDP. `POST /poll/join` tạo connection ảo nằm trong
+//! ws registry và transport registry như một WS, nên snapshot/chat/kick/ping tới được mà không cần nhánh riêng.
+//! `GET /poll/recv` chờ frame trong outbox, `POST /poll/send` đưa frame của client vào `handle_inbound_frame`.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{ws::Message, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use common_net::{
+    compression::CompressionConfig,
+    message::{self, ControlMessage, Frame, FramePayload, StateMessage},
+    transport::{GameTransport, TransportError, TransportErrorKind, TransportKind},
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    bandwidth::{Direction, MessageKind},
+    metrics,
+    outbox::{OutboundKind, PushOutcome, WsOutbox},
+    AppState, GatewayError, InboundSession, OutboundSequence, TransportConnection, TransportSelection, WebSocketConnection,
+};
+
+/// Thời gian tối đa một lần recv được giữ khi chưa có frame
+pub const MAX_POLL_WAIT: Duration = Duration::from_secs(25);
+/// Connection không recv lâu hơn ngưỡng này thì bị dọn
+pub const POLL_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const REAP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Connection long-poll; session giữ trạng thái nhận frame (dedupe, ack) giữa các lần send
+#[derive(Clone)]
+pub struct PollConnection {
+    pub connection_id: String,
+    pub peer_id: String,
+    pub outbound: OutboundSequence,
+    pub outbox: WsOutbox,
+    pub last_recv: Instant,
+    session: Arc<tokio::sync::Mutex<InboundSession>>,
+}
+
+impl std::fmt::Debug for PollConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PollConnection")
+            .field("connection_id", &self.connection_id)
+            .field("peer_id", &self.peer_id)
+            .field("queued", &self.outbox.len())
+            .field("last_recv", &self.last_recv)
+            .finish()
+    }
+}
+
+/// Transport của connection long-poll: frame relay qua transport registry cũng vào outbox, chờ lần recv kế
+pub struct LongPollTransport {
+    outbox: WsOutbox,
+    compression_config: CompressionConfig,
+}
+
+impl LongPollTransport {
+    pub fn new(outbox: WsOutbox) -> Self {
+        Self { outbox, compression_config: CompressionConfig::default() }
+    }
+}
+
+#[async_trait]
+impl GameTransport for LongPollTransport {
+    fn kind(&self) -> TransportKind {
+        TransportKind::LongPoll
+    }
+
+    async fn send_frame(&mut self, frame: Frame) -> Result<(), TransportError> {
+        let bytes = message::encode(&frame)
+            .map_err(|err| TransportError::new(TransportErrorKind::EncodingFailure, err.to_string()))?;
+        match self.outbox.push(outbound_kind(&frame), Message::Binary(bytes)) {
+            PushOutcome::Closed => Err(TransportError::new(TransportErrorKind::ConnectionClosed, "poll connection closed")),
+            PushOutcome::Queued | PushOutcome::Shed => Ok(()),
+        }
+    }
+
+    async fn recv_frame(&mut self) -> Result<Frame, TransportError> {
+        Err(TransportError::new(TransportErrorKind::Unsupported, "long-poll frames arrive through /poll/send"))
+    }
+
+    async fn close(&mut self) -> Result<(), TransportError> {
+        self.outbox.close();
+        Ok(())
+    }
+
+    fn set_compression_config(&mut self, config: CompressionConfig) {
+        self.compression_config = config;
+    }
+
+    fn get_compression_config(&self) -> &CompressionConfig {
+        &self.compression_config
+    }
+}
+
+/// Full snapshot là keyframe, state khác bỏ được khi outbox đầy, control luôn giữ
+fn outbound_kind(frame: &Frame) -> OutboundKind {
+    match &frame.payload {
+        FramePayload::Control { .. } => OutboundKind::Control,
+        FramePayload::State { message: StateMessage::Snapshot { .. } } => OutboundKind::Keyframe,
+        FramePayload::State { .. } => OutboundKind::State,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PollJoinResponse {
+    pub poll_token: String,
+    pub connection_id: String,
+    /// Recv giữ tối đa chừng này nếu chưa có frame
+    pub max_wait_ms: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollRecvQuery {
+    pub token: String,
+    /// Chờ ngắn hơn `MAX_POLL_WAIT` nếu client muốn
+    #[serde(default)]
+    pub wait_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollSendQuery {
+    pub token: String,
+}
+
+/// Frame (JSON như text frame của WS) gửi cho client; `closed` là lần recv cuối của connection
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PollFrames {
+    pub frames: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub closed: bool,
+}
+
+fn unknown_token() -> GatewayError {
+    GatewayError::NotFound("Unknown or expired poll token".to_string())
+}
+
+/// Mở connection ảo cho user trong Bearer token
+pub(crate) async fn poll_join_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<PollJoinResponse>, GatewayError> {
+    metrics::record_http_request(crate::POLL_JOIN_PATH);
+
+    let peer_id = crate::extract_user_id_from_headers(&headers, &state.auth_service).inspect_err(|_| {
+        metrics::record_ws_auth_failure();
+    })?;
+
+    let (poll_token, connection_id) = open(&state, peer_id);
+    Ok(Json(PollJoinResponse { poll_token, connection_id, max_wait_ms: MAX_POLL_WAIT.as_millis() as u64 }))
+}
+
+/// Đăng ký connection như `ws_session`, trả về (poll_token, connection_id)
+fn open(state: &AppState, peer_id: String) -> (String, String) {
+    let connection_id = uuid::Uuid::new_v4().to_string();
+    let poll_token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+    let outbox = WsOutbox::new(state.ws_outbox);
+    state.bandwidth.connect(&connection_id);
+
+    let selection = TransportSelection {
+        kind: TransportKind::LongPoll,
+        fallback_used: true,
+        reason: TransportSelection::LONGPOLL_REQUESTED,
+    };
+    crate::metrics::record_transport_connection(crate::transport_label(selection.kind), selection.fallback_used);
+
+    let outbound = OutboundSequence::default();
+    let rtt = crate::latency::LatencyTracker::default();
+    let selected = outbound.stamp(Frame::control(0, 0, selection.control_message()));
+    if let Ok(bytes) = message::encode(&selected) {
+        outbox.push(OutboundKind::Control, Message::Binary(bytes));
+    }
+
+    state.ws_registry.insert(connection_id.clone(), WebSocketConnection {
+        peer_id: peer_id.clone(),
+        room_id: "unknown".to_string(),
+        outbound: outbound.clone(),
+        outbox: outbox.clone(),
+        latency: rtt.clone(),
+    });
+    state.latency.ensure_running();
+    state.transport_registry.insert(
+        connection_id.clone(),
+        TransportConnection::new(peer_id.clone(), outbound.clone(), Box::new(LongPollTransport::new(outbox.clone())), selection),
+    );
+
+    let session = InboundSession {
+        peer_id: peer_id.clone(),
+        connection_id: connection_id.clone(),
+        ws_registry: state.ws_registry.clone(),
+        transport_registry: state.transport_registry.clone(),
+        outbound: outbound.clone(),
+        dedupe: crate::FrameDedupe::new(crate::INBOUND_DEDUPE_WINDOW),
+        snapshots: state.snapshots.clone(),
+        bandwidth: state.bandwidth.clone(),
+        latency: rtt,
+        worker_client: state.worker_client.clone(),
+        room_manager: state.room_manager.clone(),
+        rtc_peers: state.rtc_peers.clone(),
+        rtc_offer_pending: false,
+        // Session event analytics hiện chỉ phát cho ws session
+        analytics: crate::analytics::SessionAnalytics::disabled(),
+    };
+    state.poll_registry.insert(poll_token.clone(), PollConnection {
+        connection_id: connection_id.clone(),
+        peer_id,
+        outbound,
+        outbox,
+        last_recv: Instant::now(),
+        session: Arc::new(tokio::sync::Mutex::new(session)),
+    });
+    ensure_reaper(state);
+    (poll_token, connection_id)
+}
+
+/// Chờ tới khi có frame (tối đa `MAX_POLL_WAIT`) rồi trả mọi frame đang chờ
+pub(crate) async fn poll_recv_handler(State(state): State<AppState>, Query(query): Query<PollRecvQuery>) -> Result<Json<PollFrames>, GatewayError> {
+    metrics::record_http_request(crate::POLL_RECV_PATH);
+
+    let Some(connection) = state.poll_registry.touch(&query.token) else {
+        return Err(unknown_token());
+    };
+
+    // Control frame client chưa ack được gửi lại trong lần recv này, như retransmit ticker của ws session
+    for (frame, _) in connection.outbound.unacked.due(Instant::now()) {
+        if let Ok(bytes) = message::encode(&frame) {
+            metrics::record_control_retransmit();
+            connection.outbox.push(OutboundKind::Control, Message::Binary(bytes));
+        }
+    }
+
+    let wait = query.wait_ms.map_or(MAX_POLL_WAIT, Duration::from_millis).min(MAX_POLL_WAIT);
+    let first = match tokio::time::timeout(wait, connection.outbox.recv_tagged()).await {
+        Ok(Some(first)) => Some(first),
+        // Outbox đã đóng (đầy quá lâu): connection kết thúc
+        Ok(None) => {
+            close(&state, &query.token);
+            return Ok(Json(PollFrames { frames: Vec::new(), closed: true }));
+        }
+        Err(_) => None,
+    };
+    state.poll_registry.touch(&query.token);
+
+    let queued: Vec<_> = first.into_iter().chain(connection.outbox.take_queued()).collect();
+    let (messages, closed) = coalesce(queued);
+    let mut frames = Vec::with_capacity(messages.len());
+    for (kind, msg) in messages {
+        state
+            .bandwidth
+            .record_message(&connection.connection_id, Direction::Sent, MessageKind::of_outbound(kind), &msg);
+        let json = match &msg {
+            Message::Binary(bytes) => serde_json::from_slice(bytes).ok(),
+            Message::Text(text) => serde_json::from_str(text).ok(),
+            _ => None,
+        };
+        frames.extend(json);
+    }
+    if closed {
+        close(&state, &query.token);
+    }
+    Ok(Json(PollFrames { frames, closed }))
+}
+
+/// Snapshot cũ hơn keyframe mới nhất bị bỏ vì client chỉ cần keyframe đó và state sau nó; dừng ở close frame
+/// (kick) và báo connection đã đóng
+fn coalesce(queued: Vec<(OutboundKind, Message)>) -> (Vec<(OutboundKind, Message)>, bool) {
+    let close_at = queued.iter().position(|(_, msg)| matches!(msg, Message::Close(_)));
+    let mut queued = queued;
+    if let Some(index) = close_at {
+        queued.truncate(index);
+    }
+    let last_keyframe = queued.iter().rposition(|(kind, _)| *kind == OutboundKind::Keyframe);
+    let messages = queued
+        .into_iter()
+        .enumerate()
+        .filter(|(index, (kind, _))| {
+            *kind == OutboundKind::Control || last_keyframe.is_none_or(|keyframe| *index >= keyframe)
+        })
+        .map(|(_, entry)| entry)
+        .collect();
+    (messages, close_at.is_some())
+}
+
+/// Nhận một frame hoặc mảng frame (JSON như text frame của WS); trả về các frame trả lời ngay cho client
+pub(crate) async fn poll_send_handler(
+    State(state): State<AppState>,
+    Query(query): Query<PollSendQuery>,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Json<PollFrames>, GatewayError> {
+    metrics::record_http_request(crate::POLL_SEND_PATH);
+
+    let Some(connection) = state.poll_registry.get(&query.token) else {
+        return Err(unknown_token());
+    };
+    let inbound = match body {
+        serde_json::Value::Array(frames) => frames,
+        frame => vec![frame],
+    };
+
+    let mut session = connection.session.lock().await;
+    let mut replies = Vec::new();
+    for raw in inbound {
+        let text = raw.to_string();
+        let reply = match crate::parse_text_frame(&text) {
+            Ok(frame) => {
+                state
+                    .bandwidth
+                    .record(&connection.connection_id, Direction::Received, MessageKind::of(&frame), text.len() as u64);
+                crate::handle_inbound_frame(&mut session, frame).await
+            }
+            Err(err) => {
+                debug!(connection_id = %connection.connection_id, %err, "gateway: invalid poll frame");
+                state.bandwidth.record(&connection.connection_id, Direction::Received, MessageKind::Control, text.len() as u64);
+                Some(session.outbound.stamp(Frame::control(0, 0, ControlMessage::Error {
+                    code: "invalid_message".to_string(),
+                    message: "Frame is not a valid JSON control/state message".to_string(),
+                })))
+            }
+        };
+        if let Some(frame) = reply {
+            if let Ok(bytes) = message::encode(&frame) {
+                state.bandwidth.record(&connection.connection_id, Direction::Sent, MessageKind::of(&frame), bytes.len() as u64);
+                replies.extend(serde_json::from_slice::<serde_json::Value>(&bytes).ok());
+            }
+        }
+    }
+    Ok(Json(PollFrames { frames: replies, closed: false }))
+}
+
+fn close(state: &AppState, poll_token: &str) {
+    if let Some(connection) = state.poll_registry.remove(poll_token) {
+        teardown(state, connection);
+    }
+}
+
+/// Gỡ connection khỏi mọi registry như cuối `ws_session`; worker giữ entity của player trong rejoin grace
+fn teardown(state: &AppState, connection: PollConnection) {
+    connection.outbox.close();
+    state.bandwidth.disconnect(&connection.connection_id);
+    state.transport_registry.remove(&connection.connection_id);
+    let joined_room = state
+        .ws_registry
+        .remove(&connection.connection_id)
+        .map(|conn| conn.room_id)
+        .filter(|room_id| room_id != "unknown");
+    if let Some(room_id) = joined_room {
+        let mut worker_client = state.worker_client.clone();
+        let player_id = connection.peer_id;
+        tokio::spawn(async move {
+            let request = proto::worker::v1::NotifyDisconnectRequest { room_id, player_id };
+            if let Err(e) = worker_client.notify_disconnect(request).await {
+                tracing::debug!(error = %e, "gateway: notify_disconnect failed");
+            }
+        });
+    }
+}
+
+/// Đóng các connection không recv trong `idle`, trả về số connection đã đóng
+pub fn reap_idle(state: &AppState, idle: Duration) -> usize {
+    let idle_connections = state.poll_registry.take_idle(idle);
+    let reaped = idle_connections.len();
+    for connection in idle_connections {
+        teardown(state, connection);
+    }
+    if reaped > 0 {
+        debug!(reaped, "gateway: closed idle poll connections");
+    }
+    reaped
+}
+
+/// Task dọn connection idle chạy khi còn connection long-poll, tự dừng khi hết
+fn ensure_reaper(state: &AppState) {
+    if !state.poll_registry.claim_reaper() {
+        return;
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + REAP_INTERVAL, REAP_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            reap_idle(&state, POLL_IDLE_TIMEOUT);
+            if state.poll_registry.is_empty() {
+                state.poll_registry.release_reaper();
+                // Connection mở ngay trước khi tắt cờ đã thấy cờ còn bật nên không bật task mới: kiểm tra lại
+                if state.poll_registry.is_empty() || !state.poll_registry.claim_reaper() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(body: &str) -> Message {
+        Message::Text(body.to_string())
+    }
+
+    #[test]
+    fn snapshots_before_the_latest_keyframe_are_coalesced() {
+        let queued = vec![
+            (OutboundKind::State, text("s1")),
+            (OutboundKind::Keyframe, text("k1")),
+            (OutboundKind::Control, text("c1")),
+            (OutboundKind::State, text("s2")),
+            (OutboundKind::Keyframe, text("k2")),
+            (OutboundKind::State, text("s3")),
+        ];
+        let (messages, closed) = coalesce(queued);
+        let bodies: Vec<_> = messages.into_iter().filter_map(|(_, msg)| msg.into_text().ok()).collect();
+        assert_eq!(bodies, vec!["c1", "k2", "s3"]);
+        assert!(!closed);
+    }
+
+    #[test]
+    fn close_frame_ends_the_batch() {
+        let queued = vec![
+            (OutboundKind::Control, text("kicked")),
+            (OutboundKind::Control, Message::Close(None)),
+            (OutboundKind::State, text("late")),
+        ];
+        let (messages, closed) = coalesce(queued);
+        assert_eq!(messages.len(), 1);
+        assert!(closed);
+    }
+}
@@ -0,0 +1,418 @@
+//! Counter/gauge/histogram của gateway. Mọi call site đi qua các hàm có kiểu ở đây, backend là `metrics` crate;
+//! recorder Prometheus được cài trong `build_router_with_state` và `/metrics` chỉ render registry này.
+//! Series theo room (`gateway_room_players`, `gateway_bytes_sent_total`) được giữ riêng ở đây vì recorder không
+//! xoá được label: room đóng thì series biến mất khỏi `/metrics` ngay.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use ::metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit};
+use common_net::health::ReadinessReport;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+const HTTP_REQUESTS: &str = "gateway_http_requests_total";
+const INPUT_PUSH_MS: &str = "gw.inputs.push_ms";
+const INPUT_PUSH_OK: &str = "gw.inputs.ok";
+const INPUT_PUSH_ERR: &str = "gw.inputs.err";
+const WS_AUTH_FAILED: &str = "gw.ws.auth.failed";
+const WS_ORIGIN_REJECTED: &str = "gateway_ws_origin_rejected_total";
+const WS_VERSION_MISMATCH: &str = "gateway_ws_version_mismatch_total";
+const HTTP_RATE_LIMITED: &str = "gateway_http_rate_limited_total";
+const AUTH_LOGOUT: &str = "gw.auth.logout";
+const WEBRTC_SESSIONS_REAPED: &str = "gw.webrtc.sessions_reaped";
+const INVALID_REQUESTS: &str = "gateway.requests.invalid";
+const PEER_RTT_MS: &str = "gateway_peer_rtt_ms";
+const SCORE_SUBMISSIONS_REJECTED: &str = "gateway_score_submissions_rejected_total";
+const CONFIG_RELOADS: &str = "gateway_config_reloads_total";
+const ANALYTICS_EVENTS_DROPPED: &str = "gateway_analytics_events_dropped_total";
+const CONTROL_RETRANSMITS: &str = "gateway_control_retransmits_total";
+const CONTROL_RETRANSMIT_GIVEUPS: &str = "gateway_control_retransmit_giveups_total";
+const TRANSPORT_MIGRATIONS: &str = "gateway_transport_migrations_total";
+const TRANSPORT_MIGRATION_DROPPED_FRAMES: &str = "gateway_transport_migration_dropped_frames_total";
+const TRANSPORT_CONNECTIONS: &str = "gateway_transport_connections_total";
+const WEBRTC_CONNECTIONS_CURRENT: &str = "gateway_webrtc_connections_current";
+const FRAMES_DEDUPED: &str = "gateway_frames_deduped_total";
+const WS_FRAMES_SHED: &str = "gateway_ws_frames_shed_total";
+const WS_SATURATED_DISCONNECTS: &str = "gateway_ws_saturated_disconnects_total";
+const BYTES_BY_KIND: &str = "gateway_bytes_by_kind_total";
+const ROOMS_ACTIVE: &str = "gateway_rooms_active";
+const PLAYERS_IN_ROOMS: &str = "gateway_players_in_rooms";
+const ROOM_LIFECYCLE: &str = "gateway_room_lifecycle_total";
+const DEPENDENCY_STATUS: &str = "service_dependency_status";
+const ROOM_PLAYERS: &str = "gateway_room_players";
+const ROOM_BYTES: &str = "gateway_bytes_sent_total";
+
+/// Bucket (ms) cho latency gọi PushInput lên worker
+const INPUT_PUSH_BUCKETS_MS: &[f64] = &[1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0];
+/// Bucket (ms) cho RTT Ping/Pong của client, trần 10s
+const PEER_RTT_BUCKETS_MS: &[f64] = &[10.0, 25.0, 50.0, 75.0, 100.0, 150.0, 250.0, 500.0, 1000.0, 2500.0, 10000.0];
+
+static PROMETHEUS: OnceLock<Option<PrometheusHandle>> = OnceLock::new();
+
+/// Series theo room, render cùng recorder trong `render`
+#[derive(Default)]
+struct RoomSeries {
+    /// room_id -> số người chơi, chỉ phòng đang hoạt động
+    players: BTreeMap<String, u32>,
+    /// (room_id, direction) -> tổng byte từ lúc room có connection đầu tiên
+    bytes: BTreeMap<(String, &'static str), u64>,
+}
+
+static ROOM_SERIES: Mutex<RoomSeries> = Mutex::new(RoomSeries { players: BTreeMap::new(), bytes: BTreeMap::new() });
+
+fn room_series() -> std::sync::MutexGuard<'static, RoomSeries> {
+    ROOM_SERIES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Cài recorder Prometheus làm global recorder. Gọi lại (nhiều router trong cùng process) là no-op;
+/// process đã có recorder khác thì giữ nguyên recorder đó và `render` trả về rỗng.
+pub fn install() {
+    PROMETHEUS.get_or_init(|| {
+        let recorder = PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Full(INPUT_PUSH_MS.to_string()), INPUT_PUSH_BUCKETS_MS)
+            .expect("input push buckets are not empty")
+            .set_buckets_for_metric(Matcher::Full(PEER_RTT_MS.to_string()), PEER_RTT_BUCKETS_MS)
+            .expect("peer rtt buckets are not empty")
+            .build_recorder();
+        let handle = recorder.handle();
+        if ::metrics::set_global_recorder(recorder).is_err() {
+            tracing::warn!("gateway: đã có metrics recorder khác, /metrics chỉ còn series theo room");
+            return None;
+        }
+        describe();
+        Some(handle)
+    });
+}
+
+/// Text format Prometheus của các metric trong module này
+pub fn render() -> String {
+    let mut body = PROMETHEUS
+        .get()
+        .and_then(Option::as_ref)
+        .map(PrometheusHandle::render)
+        .unwrap_or_default();
+
+    let series = room_series();
+    if !series.players.is_empty() {
+        body.push_str("# HELP gateway_room_players Số người chơi trong từng phòng đang hoạt động\n");
+        body.push_str("# TYPE gateway_room_players gauge\n");
+        for (room_id, players) in &series.players {
+            let _ = writeln!(body, "{ROOM_PLAYERS}{{room_id=\"{}\"}} {players}", escape_label(room_id));
+        }
+    }
+    if !series.bytes.is_empty() {
+        body.push_str("# HELP gateway_bytes_sent_total Số byte WS gửi/nhận theo room\n");
+        body.push_str("# TYPE gateway_bytes_sent_total counter\n");
+        for ((room_id, direction), bytes) in &series.bytes {
+            let _ = writeln!(body, "{ROOM_BYTES}{{direction=\"{direction}\",room_id=\"{}\"}} {bytes}", escape_label(room_id));
+        }
+    }
+    body
+}
+
+/// Escape giá trị label theo text format Prometheus
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Mô tả metric và tạo sẵn các counter auth ở 0 để chúng có trên /metrics trước lần tăng đầu tiên
+fn describe() {
+    describe_counter!(HTTP_REQUESTS, "Tổng số HTTP request theo route");
+    describe_histogram!(INPUT_PUSH_MS, Unit::Milliseconds, "Latency gọi PushInput lên worker");
+    describe_counter!(INPUT_PUSH_OK, "Số input đẩy lên worker thành công");
+    describe_counter!(INPUT_PUSH_ERR, "Số input đẩy lên worker thất bại");
+    describe_counter!(WS_AUTH_FAILED, "Số WebSocket upgrade bị từ chối vì token");
+    describe_counter!(WS_ORIGIN_REJECTED, "Số WebSocket upgrade bị từ chối vì Origin không nằm trong allowed_origins");
+    describe_counter!(WS_VERSION_MISMATCH, "Số WS connection bị đóng vì thiếu Hello hoặc lệch protocol version");
+    describe_counter!(HTTP_RATE_LIMITED, "Số HTTP request bị trả 429 vì client vượt rate limit");
+    describe_counter!(WebRtcSignal::Offer.metric(), "Number of WebRTC offers received");
+    describe_counter!(WebRtcSignal::Answer.metric(), "Number of WebRTC answers received");
+    describe_counter!(WebRtcSignal::IceCandidate.metric(), "Number of ICE candidates received");
+    describe_counter!(WebRtcSignal::SessionClosed.metric(), "Number of WebRTC sessions closed");
+    describe_counter!(WEBRTC_SESSIONS_REAPED, "Số signaling session hết hạn bị dọn");
+    describe_counter!(INVALID_REQUESTS, "Số request bị từ chối vì body không hợp lệ");
+    describe_counter!(SCORE_SUBMISSIONS_REJECTED, "Số lần submit điểm leaderboard bị từ chối theo lý do");
+    describe_counter!(CONFIG_RELOADS, "Số lần reload config theo kết quả (applied, unchanged, rejected)");
+    describe_counter!(ANALYTICS_EVENTS_DROPPED, "Số session event analytics bị bỏ theo lý do (backpressure, ingest_failed)");
+    describe_counter!(CONTROL_RETRANSMITS, "Số control frame gửi lại vì client chưa ack");
+    describe_counter!(CONTROL_RETRANSMIT_GIVEUPS, "Số control frame bị bỏ sau khi gửi lại hết số lần cho phép");
+    describe_counter!(TRANSPORT_MIGRATIONS, "Số lần client chuyển room binding sang connection khác, theo transport cũ và mới");
+    describe_counter!(
+        TRANSPORT_MIGRATION_DROPPED_FRAMES,
+        "Số frame đang chờ gửi trên connection cũ không chuyển được sang connection mới"
+    );
+    describe_counter!(TRANSPORT_CONNECTIONS, "Tổng số kết nối transport theo loại và có phải fallback không");
+    describe_gauge!(WEBRTC_CONNECTIONS_CURRENT, "Số kết nối WebRTC hiện tại");
+    describe_counter!(FRAMES_DEDUPED, "Số frame trùng (peer_id, seq) bị bỏ ở phía nhận");
+    describe_counter!(WS_FRAMES_SHED, "Số state frame bị bỏ vì hàng đợi gửi WS đầy");
+    describe_counter!(WS_SATURATED_DISCONNECTS, "Số WS connection bị ngắt vì hàng đợi gửi đầy quá lâu");
+    describe_counter!(BYTES_BY_KIND, Unit::Bytes, "Số byte WS gửi/nhận theo loại message (control/state/chat)");
+    describe_gauge!(ROOMS_ACTIVE, "Số lượng phòng chơi đang hoạt động");
+    describe_gauge!(PLAYERS_IN_ROOMS, "Số lượng người chơi đang ở trong phòng");
+    describe_counter!(ROOM_LIFECYCLE, "Tổng số sự kiện vòng đời phòng (created/joined/left/closed)");
+    describe_gauge!(DEPENDENCY_STATUS, "Trạng thái dependency trong /readyz: 2 = ok, 1 = degraded, 0 = down");
+    describe_histogram!(PEER_RTT_MS, Unit::Milliseconds, "RTT Ping/Pong giữa gateway và WS client");
+
+    for action in [AuthAction::Login, AuthAction::Register, AuthAction::Refresh] {
+        counter!(action.metric(true)).increment(0);
+        counter!(action.metric(false)).increment(0);
+    }
+    counter!(AUTH_LOGOUT).increment(0);
+    counter!(WS_AUTH_FAILED).increment(0);
+    counter!(WS_ORIGIN_REJECTED).increment(0);
+    counter!(WS_VERSION_MISMATCH).increment(0);
+    counter!(HTTP_RATE_LIMITED).increment(0);
+}
+
+/// Một mẫu RTT từ Pong của client (đã chặn trần)
+pub fn record_peer_rtt(rtt_ms: u32) {
+    histogram!(PEER_RTT_MS).record(rtt_ms as f64);
+}
+
+pub fn record_http_request(path: &'static str) {
+    counter!(HTTP_REQUESTS, "path" => path).increment(1);
+}
+
+/// Một lần đẩy input lên worker (`/inputs`, `/game/input`), `ok` là kết quả RPC
+pub fn record_input_push(ok: bool, latency: Duration) {
+    histogram!(INPUT_PUSH_MS).record(latency.as_secs_f64() * 1000.0);
+    counter!(if ok { INPUT_PUSH_OK } else { INPUT_PUSH_ERR }).increment(1);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomEvent {
+    Created,
+    CreateFailed,
+    PlayerJoined,
+    JoinFailed,
+    PlayerAssigned,
+    AssignFailed,
+    PlayerLeft,
+    LeaveFailed,
+}
+
+impl RoomEvent {
+    fn metric(self) -> &'static str {
+        match self {
+            RoomEvent::Created => "gateway.rooms.created",
+            RoomEvent::CreateFailed => "gateway.rooms.create_failed",
+            RoomEvent::PlayerJoined => "gateway.rooms.player_joined",
+            RoomEvent::JoinFailed => "gateway.rooms.join_failed",
+            RoomEvent::PlayerAssigned => "gateway.rooms.player_assigned",
+            RoomEvent::AssignFailed => "gateway.rooms.assign_failed",
+            RoomEvent::PlayerLeft => "gateway.rooms.player_left",
+            RoomEvent::LeaveFailed => "gateway.rooms.leave_failed",
+        }
+    }
+}
+
+pub fn record_room_event(event: RoomEvent) {
+    counter!(event.metric()).increment(1);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthAction {
+    Login,
+    Register,
+    Refresh,
+}
+
+impl AuthAction {
+    fn metric(self, success: bool) -> &'static str {
+        match (self, success) {
+            (AuthAction::Login, true) => "gw.auth.login.success",
+            (AuthAction::Login, false) => "gw.auth.login.failed",
+            (AuthAction::Register, true) => "gw.auth.register.success",
+            (AuthAction::Register, false) => "gw.auth.register.failed",
+            (AuthAction::Refresh, true) => "gw.auth.refresh.success",
+            (AuthAction::Refresh, false) => "gw.auth.refresh.failed",
+        }
+    }
+}
+
+pub fn record_auth(action: AuthAction, success: bool) {
+    counter!(action.metric(success)).increment(1);
+}
+
+pub fn record_logout() {
+    counter!(AUTH_LOGOUT).increment(1);
+}
+
+pub fn record_ws_auth_failure() {
+    counter!(WS_AUTH_FAILED).increment(1);
+}
+
+pub fn record_ws_version_mismatch() {
+    counter!(WS_VERSION_MISMATCH).increment(1);
+}
+
+pub fn record_ws_origin_rejected() {
+    counter!(WS_ORIGIN_REJECTED).increment(1);
+}
+
+pub fn record_http_rate_limited() {
+    counter!(HTTP_RATE_LIMITED).increment(1);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebRtcSignal {
+    Offer,
+    Answer,
+    IceCandidate,
+    SessionClosed,
+}
+
+impl WebRtcSignal {
+    fn metric(self) -> &'static str {
+        match self {
+            WebRtcSignal::Offer => "gw.webrtc.offers",
+            WebRtcSignal::Answer => "gw.webrtc.answers",
+            WebRtcSignal::IceCandidate => "gw.webrtc.ice_candidates",
+            WebRtcSignal::SessionClosed => "gw.webrtc.sessions_closed",
+        }
+    }
+}
+
+pub fn record_webrtc_signal(signal: WebRtcSignal) {
+    counter!(signal.metric()).increment(1);
+}
+
+pub fn record_webrtc_sessions_reaped(count: u64) {
+    counter!(WEBRTC_SESSIONS_REAPED).increment(count);
+}
+
+pub fn record_invalid_request() {
+    counter!(INVALID_REQUESTS).increment(1);
+}
+
+pub fn record_score_rejected(reason: &'static str) {
+    counter!(SCORE_SUBMISSIONS_REJECTED, "reason" => reason).increment(1);
+}
+
+pub fn record_config_reload(result: &'static str) {
+    counter!(CONFIG_RELOADS, "result" => result).increment(1);
+}
+
+/// Một control frame được gửi lại vì client chưa ack
+pub fn record_control_retransmit() {
+    counter!(CONTROL_RETRANSMITS).increment(1);
+}
+
+/// Control frame bị bỏ: hết số lần gửi lại, hoặc hàng chờ ack đầy
+pub fn record_control_retransmit_giveup() {
+    counter!(CONTROL_RETRANSMIT_GIVEUPS).increment(1);
+}
+
+/// Room binding chuyển từ connection transport `from` sang connection transport `to`; `dropped_frames` là số frame
+/// trong outbox cũ không đưa được sang outbox mới
+pub fn record_transport_migration(from: &'static str, to: &'static str, dropped_frames: u64) {
+    counter!(TRANSPORT_MIGRATIONS, "from" => from, "to" => to).increment(1);
+    counter!(TRANSPORT_MIGRATION_DROPPED_FRAMES).increment(dropped_frames);
+}
+
+/// Một connection mới của ws session; `fallback_used` khi client không dùng được transport ưu tiên
+pub fn record_transport_connection(transport: &'static str, fallback_used: bool) {
+    let fallback_used = if fallback_used { "true" } else { "false" };
+    counter!(TRANSPORT_CONNECTIONS, "transport_type" => transport, "fallback_used" => fallback_used).increment(1);
+}
+
+pub fn record_webrtc_connected() {
+    gauge!(WEBRTC_CONNECTIONS_CURRENT, "status" => "connected").increment(1.0);
+}
+
+pub fn record_webrtc_disconnected() {
+    gauge!(WEBRTC_CONNECTIONS_CURRENT, "status" => "connected").decrement(1.0);
+}
+
+pub fn record_frame_deduped() {
+    counter!(FRAMES_DEDUPED).increment(1);
+}
+
+pub fn record_ws_frame_shed() {
+    counter!(WS_FRAMES_SHED).increment(1);
+}
+
+pub fn record_ws_saturated_disconnect() {
+    counter!(WS_SATURATED_DISCONNECTS).increment(1);
+}
+
+pub fn record_bytes_by_kind(direction: &'static str, kind: &'static str, bytes: u64) {
+    counter!(BYTES_BY_KIND, "direction" => direction, "kind" => kind).increment(bytes);
+}
+
+/// Byte WS của một room trong interval vừa flush
+pub fn record_room_bytes(direction: &'static str, room_id: &str, bytes: u64) {
+    *room_series().bytes.entry((room_id.to_string(), direction)).or_default() += bytes;
+}
+
+/// Room không còn connection nào: bỏ series byte của nó khỏi `/metrics`
+pub fn forget_room_bytes(room_id: &str) {
+    room_series().bytes.retain(|(room, _), _| room != room_id);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomLifecycle {
+    Created,
+    Joined,
+    Left,
+    Closed,
+}
+
+impl RoomLifecycle {
+    fn label(self) -> &'static str {
+        match self {
+            RoomLifecycle::Created => "created",
+            RoomLifecycle::Joined => "joined",
+            RoomLifecycle::Left => "left",
+            RoomLifecycle::Closed => "closed",
+        }
+    }
+}
+
+pub fn record_room_lifecycle(event: RoomLifecycle) {
+    counter!(ROOM_LIFECYCLE, "event" => event.label()).increment(1);
+}
+
+/// Gauge phòng từ danh sách của room manager. `active` thay toàn bộ `gateway_room_players`; phòng trước đó
+/// có series mà nay không còn trong `active` bị bỏ khỏi `/metrics` và đếm là `closed`
+pub fn record_room_occupancy(rooms: usize, players: usize, active: HashMap<String, u32>) {
+    gauge!(ROOMS_ACTIVE).set(rooms as f64);
+    gauge!(PLAYERS_IN_ROOMS).set(players as f64);
+
+    let mut series = room_series();
+    let closed = series.players.keys().filter(|room_id| !active.contains_key(*room_id)).count();
+    series.players = active.into_iter().collect();
+    drop(series);
+    counter!(ROOM_LIFECYCLE, "event" => RoomLifecycle::Closed.label()).increment(closed as u64);
+}
+
+/// Trạng thái từng dependency của lần `/readyz` gần nhất
+pub fn record_readiness(report: &ReadinessReport) {
+    for (dependency, status) in &report.dependencies {
+        gauge!(DEPENDENCY_STATUS, "dependency" => *dependency, "service" => "gateway").set(status.gauge_value() as f64);
+    }
+}
+
+pub fn record_analytics_dropped(reason: &'static str, count: u64) {
+    counter!(ANALYTICS_EVENTS_DROPPED, "reason" => reason).increment(count);
+}
+
+/// Giá trị hiện tại của series `name` có đủ các label cho trước, đọc từ `render()`
+#[cfg(test)]
+pub(crate) fn sample(name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+    install();
+    render().lines().filter(|line| !line.starts_with('#')).find_map(|line| {
+        let (series, value) = line.rsplit_once(' ')?;
+        let (series_name, series_labels) = match series.split_once('{') {
+            Some((series_name, rest)) => (series_name, rest.trim_end_matches('}')),
+            None => (series, ""),
+        };
+        let matches = series_name == name
+            && labels
+                .iter()
+                .all(|(key, value)| series_labels.split(',').any(|pair| pair == format!("{key}=\"{value}\"")));
+        if matches { value.parse().ok() } else { None }
+    })
+}
@@ -0,0 +1,272 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::extract::ws::Message;
+use tokio::sync::Notify;
+
+/// Số frame tối đa chờ gửi cho một WS connection
+pub const DEFAULT_OUTBOX_CAPACITY: usize = 256;
+/// Connection bị đầy liên tục lâu hơn ngưỡng này thì ngắt
+pub const DEFAULT_SATURATION_LIMIT: Duration = Duration::from_secs(5);
+
+/// Cấu hình hàng đợi gửi của WS, đọc từ GATEWAY_WS_OUTBOX_CAPACITY / GATEWAY_WS_SATURATION_MS
+#[derive(Debug, Clone, Copy)]
+pub struct OutboxConfig {
+    pub capacity: usize,
+    pub saturation_limit: Duration,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_OUTBOX_CAPACITY,
+            saturation_limit: DEFAULT_SATURATION_LIMIT,
+        }
+    }
+}
+
+impl OutboxConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            capacity: std::env::var("GATEWAY_WS_OUTBOX_CAPACITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|capacity| *capacity > 0)
+                .unwrap_or(defaults.capacity),
+            saturation_limit: std::env::var("GATEWAY_WS_SATURATION_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.saturation_limit),
+        }
+    }
+}
+
+/// Loại frame quyết định có được bỏ khi hàng đợi đầy hay không
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboundKind {
+    /// Control/signaling - không bao giờ bị bỏ
+    Control,
+    /// Full snapshot - client cần để resync nên giữ lại
+    Keyframe,
+    /// Delta/state thường - frame mới hơn thay thế được nên bỏ frame cũ nhất trước
+    State,
+}
+
+/// Kết quả đẩy một frame vào outbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    /// Hàng đợi đầy, đã bỏ một state frame (frame cũ nhất, hoặc chính frame mới)
+    Shed,
+    /// Connection đã bị đóng vì đầy quá lâu
+    Closed,
+}
+
+#[derive(Debug)]
+struct OutboxState {
+    queue: VecDeque<(OutboundKind, Message)>,
+    saturated_since: Option<Instant>,
+    closed: bool,
+}
+
+/// Hàng đợi gửi có giới hạn cho một WS connection. Producer không bao giờ bị block:
+/// khi đầy thì bỏ state frame cũ nhất, connection đầy liên tục quá `saturation_limit` thì bị đóng.
+#[derive(Debug, Clone)]
+pub struct WsOutbox {
+    config: OutboxConfig,
+    state: Arc<Mutex<OutboxState>>,
+    notify: Arc<Notify>,
+}
+
+impl WsOutbox {
+    pub fn new(config: OutboxConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(OutboxState {
+                queue: VecDeque::with_capacity(config.capacity),
+                saturated_since: None,
+                closed: false,
+            })),
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn push(&self, kind: OutboundKind, message: Message) -> PushOutcome {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return PushOutcome::Closed;
+        }
+
+        if state.queue.len() < self.config.capacity {
+            state.queue.push_back((kind, message));
+            drop(state);
+            self.notify.notify_one();
+            return PushOutcome::Queued;
+        }
+
+        let now = Instant::now();
+        let saturated_since = *state.saturated_since.get_or_insert(now);
+        if now.duration_since(saturated_since) > self.config.saturation_limit {
+            state.closed = true;
+            state.queue.clear();
+            drop(state);
+            crate::metrics::record_ws_saturated_disconnect();
+            self.notify.notify_one();
+            return PushOutcome::Closed;
+        }
+
+        let oldest_state = state.queue.iter().position(|(queued, _)| *queued == OutboundKind::State);
+        let outcome = match (oldest_state, kind) {
+            (Some(index), _) => {
+                state.queue.remove(index);
+                state.queue.push_back((kind, message));
+                PushOutcome::Shed
+            }
+            // Không còn state frame nào để bỏ: bỏ chính frame state mới
+            (None, OutboundKind::State) => PushOutcome::Shed,
+            // Control/keyframe vẫn phải tới client, cho vượt capacity
+            (None, _) => {
+                state.queue.push_back((kind, message));
+                PushOutcome::Queued
+            }
+        };
+        drop(state);
+
+        if outcome == PushOutcome::Shed {
+            crate::metrics::record_ws_frame_shed();
+        }
+        self.notify.notify_one();
+        outcome
+    }
+
+    /// Frame kế tiếp cần gửi; `None` khi outbox đã đóng
+    pub async fn recv(&self) -> Option<Message> {
+        self.recv_tagged().await.map(|(_, message)| message)
+    }
+
+    /// Như `recv` nhưng kèm loại frame (để đếm bandwidth theo loại)
+    pub async fn recv_tagged(&self) -> Option<(OutboundKind, Message)> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.closed {
+                    return None;
+                }
+                if let Some((kind, message)) = state.queue.pop_front() {
+                    if state.queue.len() < self.config.capacity {
+                        state.saturated_since = None;
+                    }
+                    return Some((kind, message));
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Lấy ra mọi frame còn trong hàng đợi (dùng khi chuyển connection sang transport khác)
+    pub fn take_queued(&self) -> Vec<(OutboundKind, Message)> {
+        let mut state = self.state.lock().unwrap();
+        state.saturated_since = None;
+        state.queue.drain(..).collect()
+    }
+
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.notify.notify_one();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outbox(capacity: usize, saturation_ms: u64) -> WsOutbox {
+        WsOutbox::new(OutboxConfig {
+            capacity,
+            saturation_limit: Duration::from_millis(saturation_ms),
+        })
+    }
+
+    fn text(body: &str) -> Message {
+        Message::Text(body.to_string())
+    }
+
+    #[tokio::test]
+    async fn full_outbox_sheds_oldest_state_frame_first() {
+        let outbox = outbox(3, 10_000);
+        assert_eq!(outbox.push(OutboundKind::State, text("s1")), PushOutcome::Queued);
+        assert_eq!(outbox.push(OutboundKind::Control, text("c1")), PushOutcome::Queued);
+        assert_eq!(outbox.push(OutboundKind::State, text("s2")), PushOutcome::Queued);
+        assert_eq!(outbox.push(OutboundKind::State, text("s3")), PushOutcome::Shed);
+        assert_eq!(outbox.len(), 3);
+
+        let mut received = Vec::new();
+        while !outbox.is_empty() {
+            if let Some(Message::Text(body)) = outbox.recv().await {
+                received.push(body);
+            }
+        }
+        assert_eq!(received, vec!["c1", "s2", "s3"]);
+    }
+
+    #[tokio::test]
+    async fn control_frames_are_never_shed() {
+        let outbox = outbox(2, 10_000);
+        outbox.push(OutboundKind::Control, text("c1"));
+        outbox.push(OutboundKind::Keyframe, text("k1"));
+        assert_eq!(outbox.push(OutboundKind::State, text("s1")), PushOutcome::Shed);
+        assert_eq!(outbox.push(OutboundKind::Control, text("c2")), PushOutcome::Queued);
+        assert_eq!(outbox.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn stalled_receiver_is_disconnected_after_saturation_limit() {
+        let outbox = outbox(4, 100);
+        let shed = || crate::metrics::sample("gateway_ws_frames_shed_total", &[]).unwrap_or_default();
+        let shed_before = shed();
+
+        // Receiver không đọc: state frame bị bỏ nhưng connection vẫn sống
+        for i in 0..20 {
+            let outcome = outbox.push(OutboundKind::State, text(&format!("s{i}")));
+            assert_ne!(outcome, PushOutcome::Closed);
+        }
+        assert_eq!(outbox.len(), 4);
+        assert!(!outbox.is_closed());
+        assert!(shed() >= shed_before + 16.0);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(outbox.push(OutboundKind::State, text("late")), PushOutcome::Closed);
+        assert!(outbox.is_closed());
+        assert!(outbox.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn draining_clears_saturation() {
+        let outbox = outbox(1, 50);
+        outbox.push(OutboundKind::State, text("s1"));
+        assert_eq!(outbox.push(OutboundKind::State, text("s2")), PushOutcome::Shed);
+        assert!(outbox.recv().await.is_some());
+
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert_eq!(outbox.push(OutboundKind::State, text("s3")), PushOutcome::Queued);
+        assert_eq!(outbox.push(OutboundKind::State, text("s4")), PushOutcome::Shed);
+        assert!(!outbox.is_closed());
+    }
+}
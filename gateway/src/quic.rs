@@ -0,0 +1,90 @@
+//! Listener QUIC của gateway. Client dial QUIC trước rồi mới mở /ws: frame đầu tiên trên control
+//! stream phải là `AuthToken`, connection hợp lệ được giữ theo peer_id cho tới khi ws session của
+//! peer đó lấy làm transport (ưu tiên trước WebRTC và WebSocket).
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common_net::{
+    message::{ControlMessage, FramePayload},
+    transport::{GameTransport, QuicListener, QuicTransport},
+};
+use tracing::{debug, info};
+
+use crate::auth::AuthService;
+
+/// Thời gian chờ frame `AuthToken` sau khi QUIC handshake xong
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// QUIC connection đã xác thực, chờ ws session của peer nhận
+#[derive(Debug, Clone, Default)]
+pub struct QuicSessions(Arc<Mutex<HashMap<String, QuicTransport>>>);
+
+impl QuicSessions {
+    /// Peer dial lại thì connection cũ bị thay (và đóng khi drop)
+    fn park(&self, peer_id: String, transport: QuicTransport) {
+        self.0.lock().unwrap().insert(peer_id, transport);
+    }
+
+    pub fn take(&self, peer_id: &str) -> Option<QuicTransport> {
+        self.0.lock().unwrap().remove(peer_id)
+    }
+
+    pub fn contains(&self, peer_id: &str) -> bool {
+        self.0.lock().unwrap().contains_key(peer_id)
+    }
+}
+
+/// Nhận connection cho tới khi listener đóng; mỗi connection xác thực trong task riêng
+pub async fn accept_loop(listener: QuicListener, auth_service: AuthService, sessions: QuicSessions) {
+    while let Some(accepted) = listener.accept().await {
+        let mut transport = match accepted {
+            Ok(transport) => transport,
+            Err(e) => {
+                debug!(error = %e, "gateway: quic handshake failed");
+                continue;
+            }
+        };
+        let auth_service = auth_service.clone();
+        let sessions = sessions.clone();
+        tokio::spawn(async move {
+            match authenticate(&mut transport, &auth_service).await {
+                Some(peer_id) => {
+                    info!(%peer_id, remote = %transport.remote_address(), "gateway: quic connection authenticated");
+                    sessions.park(peer_id, transport);
+                }
+                None => {
+                    let _ = transport.close().await;
+                }
+            }
+        });
+    }
+}
+
+async fn authenticate(transport: &mut QuicTransport, auth_service: &AuthService) -> Option<String> {
+    let frame = match tokio::time::timeout(AUTH_TIMEOUT, transport.recv_frame()).await {
+        Ok(Ok(frame)) => frame,
+        Ok(Err(e)) => {
+            debug!(error = %e, "gateway: quic connection closed before auth");
+            return None;
+        }
+        Err(_) => {
+            debug!("gateway: quic auth timed out");
+            return None;
+        }
+    };
+    let FramePayload::Control { message: ControlMessage::AuthToken { jwt } } = frame.payload else {
+        debug!("gateway: first quic frame is not an auth token");
+        return None;
+    };
+    match auth_service.verify_token(&jwt) {
+        Ok(token) => Some(token.claims.sub),
+        Err(e) => {
+            debug!(error = %e, "gateway: quic auth token rejected");
+            None
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Giới hạn số HTTP request mỗi giây theo IP client (cửa sổ cố định 1 giây), vượt quá thì trả 429.
+//! Limit đổi được lúc đang chạy qua `set_limit`; probe `/healthz`, `/readyz`, `/metrics` không bị tính.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use dashmap::DashMap;
+use tower::{Layer, Service};
+
+const WINDOW: Duration = Duration::from_secs(1);
+/// Dọn cửa sổ của IP không còn gửi request khi map vượt ngần này
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Request tính vào limit khi path không thuộc danh sách probe
+fn is_exempt(path: &str) -> bool {
+    matches!(path, crate::HEALTHZ_PATH | crate::READYZ_PATH | crate::METRICS_PATH)
+}
+
+/// Các bản clone dùng chung limit và bộ đếm
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    /// Số request tối đa mỗi giây của một IP; 0 là không giới hạn
+    limit: Arc<AtomicU32>,
+    windows: Arc<DashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: u32) -> Self {
+        let limiter = Self::default();
+        limiter.set_limit(requests_per_second);
+        limiter
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    pub fn set_limit(&self, requests_per_second: u32) {
+        self.limit.store(requests_per_second, Ordering::Relaxed);
+    }
+
+    /// Tính một request của `ip` lúc `now`; false nếu đã hết lượt trong cửa sổ hiện tại
+    pub fn check(&self, ip: IpAddr, now: Instant) -> bool {
+        let limit = self.limit();
+        if limit == 0 {
+            return true;
+        }
+        if self.windows.len() > PRUNE_THRESHOLD {
+            self.windows.retain(|_, (started, _)| now.duration_since(*started) < WINDOW);
+        }
+        let mut window = self.windows.entry(ip).or_insert((now, 0));
+        let (started, count) = window.value_mut();
+        if now.duration_since(*started) >= WINDOW {
+            *started = now;
+            *count = 0;
+        }
+        if *count >= limit {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+/// Layer trả 429 khi client vượt limit; request không có `ConnectInfo` (router gọi trực tiếp trong test) không bị giới hạn
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: RateLimiter,
+}
+
+impl RateLimitLayer {
+    pub fn new(limiter: RateLimiter) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService { inner, limiter: self.limiter.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: RateLimiter,
+}
+
+impl<S, B> Service<Request<B>> for RateLimitService<S>
+where
+    S: Service<Request<B>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let client = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip());
+        if let Some(ip) = client.filter(|_| !is_exempt(request.uri().path())) {
+            if !self.limiter.check(ip, Instant::now()) {
+                crate::metrics::record_http_rate_limited();
+                return Box::pin(async { Ok((StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded").into_response()) });
+            }
+        }
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_applies_per_ip_per_window_and_changes_live() {
+        let limiter = RateLimiter::new(2);
+        let (alice, bob) = (IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2]));
+        let now = Instant::now();
+
+        assert!(limiter.check(alice, now));
+        assert!(limiter.check(alice, now));
+        assert!(!limiter.check(alice, now));
+        assert!(limiter.check(bob, now));
+        assert!(limiter.check(alice, now + WINDOW));
+
+        limiter.clone().set_limit(0);
+        assert!((0..10).all(|_| limiter.check(alice, now + WINDOW)));
+    }
+}
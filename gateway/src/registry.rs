@@ -0,0 +1,457 @@
+//! Registry connection/session của gateway, chia shard bằng DashMap thay vì một `RwLock<HashMap>` toàn cục.
+//! Call site chỉ đi qua API ở đây. Closure truyền vào chạy khi đang giữ shard lock nên phải đồng bộ và ngắn;
+//! cần gửi gì thì lấy bản clone (outbox, sequence, transport) ra rồi mới gửi, không giữ shard lock qua `.await`.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use common_net::{
+    message::Frame,
+    transport::{GameTransport, TransportError, TransportKind},
+};
+use dashmap::DashMap;
+
+use crate::{
+    longpoll::PollConnection, outbox::WsOutbox, reliable::SentVia, OutboundSequence, PeerConnection, RoomSignaling,
+    TransportConnection, TransportSelection, WebRTCSession, WebRTCSessionStatus, WebSocketConnection,
+};
+
+/// Transport của một connection; lock riêng từng connection nên gửi frame không khoá registry
+pub type SharedTransport = Arc<tokio::sync::Mutex<Box<dyn GameTransport + Send + Sync>>>;
+
+/// WS connection đang mở, key là connection_id
+#[derive(Debug, Clone, Default)]
+pub struct WebSocketRegistry {
+    connections: Arc<DashMap<String, WebSocketConnection>>,
+}
+
+impl WebSocketRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, connection_id: String, connection: WebSocketConnection) {
+        self.connections.insert(connection_id, connection);
+    }
+
+    pub fn remove(&self, connection_id: &str) -> Option<WebSocketConnection> {
+        self.connections.remove(connection_id).map(|(_, connection)| connection)
+    }
+
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Gán room cho connection; false nếu connection không còn trong registry
+    pub fn set_room(&self, connection_id: &str, room_id: &str) -> bool {
+        match self.connections.get_mut(connection_id) {
+            Some(mut connection) => {
+                connection.room_id = room_id.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Room hiện tại của connection ("unknown" khi chưa join)
+    pub fn room(&self, connection_id: &str) -> Option<String> {
+        self.connections.get(connection_id).map(|connection| connection.room_id.clone())
+    }
+
+    pub fn outbox(&self, connection_id: &str) -> Option<WsOutbox> {
+        self.connections.get(connection_id).map(|connection| connection.outbox.clone())
+    }
+
+    /// Duyệt mọi connection (kèm connection_id), giữ kết quả `Some` của `f`
+    pub fn filter_map<T>(&self, mut f: impl FnMut(&str, &WebSocketConnection) -> Option<T>) -> Vec<T> {
+        self.connections.iter().filter_map(|entry| f(entry.key(), entry.value())).collect()
+    }
+
+    pub fn for_room<T>(&self, room_id: &str, mut f: impl FnMut(&WebSocketConnection) -> T) -> Vec<T> {
+        self.filter_map(|_, connection| (connection.room_id == room_id).then(|| f(connection)))
+    }
+
+    pub fn for_peer<T>(&self, peer_id: &str, mut f: impl FnMut(&WebSocketConnection) -> T) -> Vec<T> {
+        self.filter_map(|_, connection| (connection.peer_id == peer_id).then(|| f(connection)))
+    }
+}
+
+/// Connection cần gửi frame tới, lấy ra khỏi registry trước khi gửi
+#[derive(Clone)]
+pub struct TransportTarget {
+    pub connection_id: String,
+    pub outbound: OutboundSequence,
+    pub transport: SharedTransport,
+}
+
+impl TransportTarget {
+    /// Đóng sequence và gửi khi đang giữ lock transport, để thứ tự sequence trùng thứ tự gửi.
+    /// Trả về frame đã gửi (kể cả khi transport báo lỗi) để caller đếm bandwidth
+    pub async fn send(&self, frame: Frame) -> (Frame, Result<(), TransportError>) {
+        let mut transport = self.transport.lock().await;
+        let frame = self.outbound.stamp_via(frame, SentVia::Transport(self.transport.clone()));
+        let result = transport.send_frame(frame.clone()).await;
+        (frame, result)
+    }
+}
+
+/// Transport của từng connection, key là connection_id
+#[derive(Debug, Clone, Default)]
+pub struct TransportRegistry {
+    connections: Arc<DashMap<String, TransportConnection>>,
+}
+
+impl TransportRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, connection_id: String, connection: TransportConnection) {
+        self.connections.insert(connection_id, connection);
+    }
+
+    pub fn remove(&self, connection_id: &str) -> Option<TransportConnection> {
+        self.connections.remove(connection_id).map(|(_, connection)| connection)
+    }
+
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Gán room cho connection, trả về transport kind của nó; None nếu connection không còn
+    pub fn set_room(&self, connection_id: &str, room_id: &str) -> Option<TransportKind> {
+        self.connections.get_mut(connection_id).map(|mut connection| {
+            connection.room_id = room_id.to_string();
+            connection.kind()
+        })
+    }
+
+    pub fn set_migration_nonce(&self, connection_id: &str, nonce: String) {
+        if let Some(mut connection) = self.connections.get_mut(connection_id) {
+            connection.migration_nonce = Some(nonce);
+        }
+    }
+
+    /// Gỡ connection nếu nó thuộc `peer_id` và nonce khớp; kiểm tra và gỡ là một bước nên hai lần migrate
+    /// cùng nonce chỉ một lần thắng
+    pub fn take_for_migration(&self, connection_id: &str, peer_id: &str, nonce: &str) -> Option<TransportConnection> {
+        self.connections
+            .remove_if(connection_id, |_, connection| {
+                connection.peer_id == peer_id && connection.migration_nonce.as_deref() == Some(nonce)
+            })
+            .map(|(_, connection)| connection)
+    }
+
+    /// Thay transport của connection đang chạy (WS → WebRTC khi DataChannel mở), sequence và room giữ nguyên.
+    /// false nếu connection không còn
+    pub fn upgrade(
+        &self,
+        connection_id: &str,
+        transport: Box<dyn GameTransport + Send + Sync>,
+        selection: TransportSelection,
+    ) -> bool {
+        let Some(mut connection) = self.connections.get_mut(connection_id) else {
+            return false;
+        };
+        connection.transport = Arc::new(tokio::sync::Mutex::new(transport));
+        connection.selection = selection;
+        true
+    }
+
+    pub fn kind(&self, connection_id: &str) -> Option<TransportKind> {
+        self.connections.get(connection_id).map(|connection| connection.kind())
+    }
+
+    pub fn transport(&self, connection_id: &str) -> Option<SharedTransport> {
+        self.connections.get(connection_id).map(|connection| connection.transport.clone())
+    }
+
+    /// Duyệt mọi connection (kèm connection_id), giữ kết quả `Some` của `f`
+    pub fn filter_map<T>(&self, mut f: impl FnMut(&str, &TransportConnection) -> Option<T>) -> Vec<T> {
+        self.connections.iter().filter_map(|entry| f(entry.key(), entry.value())).collect()
+    }
+
+    /// Connection trong room, trừ của người gửi
+    pub fn room_targets(&self, room_id: &str, except_peer_id: &str) -> Vec<TransportTarget> {
+        self.filter_map(|connection_id, connection| {
+            (connection.room_id == room_id && connection.peer_id != except_peer_id)
+                .then(|| target(connection_id, connection))
+        })
+    }
+
+    /// Một connection bất kỳ của peer
+    pub fn peer_target(&self, peer_id: &str) -> Option<TransportTarget> {
+        self.connections
+            .iter()
+            .find(|entry| entry.peer_id == peer_id)
+            .map(|entry| target(entry.key(), entry.value()))
+    }
+}
+
+fn target(connection_id: &str, connection: &TransportConnection) -> TransportTarget {
+    TransportTarget {
+        connection_id: connection_id.to_string(),
+        outbound: connection.outbound.clone(),
+        transport: connection.transport.clone(),
+    }
+}
+
+/// Connection long-poll, key là poll_token client gửi kèm mỗi lần recv/send
+#[derive(Debug, Clone, Default)]
+pub struct PollRegistry {
+    connections: Arc<DashMap<String, PollConnection>>,
+    reaper_running: Arc<AtomicBool>,
+}
+
+impl PollRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, poll_token: String, connection: PollConnection) {
+        self.connections.insert(poll_token, connection);
+    }
+
+    pub fn remove(&self, poll_token: &str) -> Option<PollConnection> {
+        self.connections.remove(poll_token).map(|(_, connection)| connection)
+    }
+
+    pub fn get(&self, poll_token: &str) -> Option<PollConnection> {
+        self.connections.get(poll_token).map(|connection| connection.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Ghi nhận một lần recv; None nếu token không còn
+    pub fn touch(&self, poll_token: &str) -> Option<PollConnection> {
+        self.connections.get_mut(poll_token).map(|mut connection| {
+            connection.last_recv = Instant::now();
+            connection.clone()
+        })
+    }
+
+    /// Gỡ các connection không recv trong `idle`; kiểm tra và gỡ là một bước nên recv chen vào giữa thì giữ lại
+    pub fn take_idle(&self, idle: Duration) -> Vec<PollConnection> {
+        let now = Instant::now();
+        let is_idle = |connection: &PollConnection| now.duration_since(connection.last_recv) >= idle;
+        let tokens: Vec<String> = self
+            .connections
+            .iter()
+            .filter(|entry| is_idle(entry.value()))
+            .map(|entry| entry.key().clone())
+            .collect();
+        tokens
+            .iter()
+            .filter_map(|token| self.connections.remove_if(token, |_, connection| is_idle(connection)))
+            .map(|(_, connection)| connection)
+            .collect()
+    }
+
+    /// true nếu caller vừa bật cờ và phải chạy task dọn connection idle
+    pub(crate) fn claim_reaper(&self) -> bool {
+        !self.reaper_running.swap(true, Ordering::SeqCst)
+    }
+
+    pub(crate) fn release_reaper(&self) {
+        self.reaper_running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Offer/answer/ICE của legacy signaling theo room
+#[derive(Debug, Clone, Default)]
+pub struct SignalingState {
+    rooms: Arc<DashMap<String, RoomSignaling>>,
+}
+
+impl SignalingState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sửa peer trong room, tạo room/peer nếu chưa có
+    pub fn update_peer<T>(&self, room_id: &str, peer_id: &str, f: impl FnOnce(&mut PeerConnection) -> T) -> T {
+        let mut room = self.rooms.entry(room_id.to_string()).or_default();
+        let peer = room
+            .peers
+            .entry(peer_id.to_string())
+            .or_insert_with(|| PeerConnection::new(peer_id.to_string()));
+        f(peer)
+    }
+
+    /// Sửa peer đã có; None nếu room hoặc peer chưa tồn tại
+    pub fn update_existing_peer<T>(
+        &self,
+        room_id: &str,
+        peer_id: &str,
+        f: impl FnOnce(&mut PeerConnection) -> T,
+    ) -> Option<T> {
+        let mut room = self.rooms.get_mut(room_id)?;
+        room.peers.get_mut(peer_id).map(f)
+    }
+}
+
+/// WebRTC session theo session_id
+#[derive(Debug, Clone, Default)]
+pub struct WebRTCSessionRegistry {
+    sessions: Arc<DashMap<String, WebRTCSession>>,
+}
+
+impl WebRTCSessionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, session: WebRTCSession) {
+        self.sessions.insert(session.session_id.clone(), session);
+    }
+
+    /// Cập nhật `last_activity` của session đầu tiên khớp room và user
+    pub fn touch(&self, room_id: &str, user_id: &str) {
+        if let Some(mut session) = self
+            .sessions
+            .iter_mut()
+            .find(|session| session.room_id == room_id && session.user_id == user_id)
+        {
+            session.last_activity = chrono::Utc::now();
+        }
+    }
+
+    /// Đánh dấu Connected; true nếu session vừa chuyển sang Connected
+    pub fn mark_connected(&self, session_id: &str) -> bool {
+        let Some(mut session) = self.sessions.get_mut(session_id) else {
+            return false;
+        };
+        let newly_connected = session.status != WebRTCSessionStatus::Connected;
+        session.status = WebRTCSessionStatus::Connected;
+        session.last_activity = chrono::Utc::now();
+        newly_connected
+    }
+
+    pub fn for_user(&self, user_id: &str) -> Vec<WebRTCSession> {
+        self.sessions
+            .iter()
+            .filter(|session| session.user_id == user_id)
+            .map(|session| session.clone())
+            .collect()
+    }
+
+    /// Gỡ session nếu nó thuộc `user_id`
+    pub fn remove_owned(&self, session_id: &str, user_id: &str) -> Option<WebRTCSession> {
+        self.sessions
+            .remove_if(session_id, |_, session| session.user_id == user_id)
+            .map(|(_, session)| session)
+    }
+
+    pub fn retain(&self, f: impl FnMut(&String, &mut WebRTCSession) -> bool) {
+        self.sessions.retain(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{latency::LatencyTracker, TransportSelection};
+    use common_net::{message::ControlMessage, transport::WebRtcTransport};
+    use std::time::Duration;
+
+    async fn transport_connection(room_id: &str, peer_id: &str) -> TransportConnection {
+        let transport = WebRtcTransport::new(room_id.to_string(), peer_id.to_string());
+        transport.set_connected(true).await;
+        let mut connection = TransportConnection::new(
+            peer_id.to_string(),
+            OutboundSequence::default(),
+            Box::new(transport),
+            TransportSelection {
+                kind: TransportKind::WebRtc,
+                fallback_used: false,
+                reason: TransportSelection::WEBRTC_CONNECTED,
+            },
+        );
+        connection.room_id = room_id.to_string();
+        connection
+    }
+
+    fn ws_connection(room_id: &str, peer_id: &str) -> WebSocketConnection {
+        WebSocketConnection {
+            peer_id: peer_id.to_string(),
+            room_id: room_id.to_string(),
+            outbound: OutboundSequence::default(),
+            outbox: WsOutbox::new(Default::default()),
+            latency: LatencyTracker::default(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_broadcast_insert_remove_does_not_deadlock() {
+        let transports = TransportRegistry::new();
+        let sockets = WebSocketRegistry::new();
+        for i in 0..50 {
+            transports.insert(format!("seed-{i}"), transport_connection("room", &format!("seed-{i}")).await);
+            sockets.insert(format!("seed-{i}"), ws_connection("room", &format!("seed-{i}")));
+        }
+
+        let mut tasks = Vec::new();
+        for i in 0..1000 {
+            let (transports, sockets) = (transports.clone(), sockets.clone());
+            tasks.push(tokio::spawn(async move {
+                let connection_id = format!("conn-{}", i / 3);
+                match i % 3 {
+                    0 => {
+                        let frame = Frame::control(0, 0, ControlMessage::Ping { nonce: i });
+                        for target in transports.room_targets("room", "nobody") {
+                            let _ = target.send(frame.clone()).await;
+                        }
+                        sockets.for_room("room", |connection| connection.outbox.clone()).len();
+                    }
+                    1 => {
+                        transports.insert(connection_id.clone(), transport_connection("room", &connection_id).await);
+                        sockets.insert(connection_id.clone(), ws_connection("room", &connection_id));
+                        transports.set_room(&connection_id, "room");
+                    }
+                    _ => {
+                        transports.remove(&connection_id);
+                        sockets.remove(&connection_id);
+                    }
+                }
+            }));
+        }
+
+        tokio::time::timeout(Duration::from_secs(30), futures::future::join_all(tasks))
+            .await
+            .expect("registry operations deadlocked")
+            .into_iter()
+            .for_each(|result| result.expect("task panicked"));
+        assert!(transports.len() >= 50 && sockets.len() >= 50);
+    }
+
+    #[tokio::test]
+    async fn migration_nonce_is_taken_once() {
+        let transports = TransportRegistry::new();
+        transports.insert("old".to_string(), transport_connection("room", "alice").await);
+        transports.set_migration_nonce("old", "nonce".to_string());
+
+        assert!(transports.take_for_migration("old", "mallory", "nonce").is_none());
+        assert!(transports.take_for_migration("old", "alice", "forged").is_none());
+        assert!(transports.take_for_migration("old", "alice", "nonce").is_some());
+        assert!(transports.take_for_migration("old", "alice", "nonce").is_none());
+    }
+}
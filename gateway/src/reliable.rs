@@ -0,0 +1,196 @@
+//! Control frame tin cậy theo từng connection: gateway giữ control frame chưa được client ack
+//! và gửi lại (cùng sequence, trên đúng đường đã gửi lần đầu) tối đa `MAX_RETRANSMITS` lần.
+//! State frame vẫn gửi kiểu best-effort.
+//!
+//! Client ack từng control frame qua `Frame::acks`; `ack` cumulative không dùng được vì sequence đánh chung
+//! với state frame, state frame tới sau sẽ "ack" luôn control frame đã mất. Client cũ không gửi `acks`
+//! nên chỉ bật gửi lại sau khi peer đã ack ít nhất một lần.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use common_net::message::Frame;
+
+use crate::registry::SharedTransport;
+
+/// Khoảng chờ ack trước khi gửi lại một control frame
+pub const RETRANSMIT_INTERVAL: Duration = Duration::from_millis(250);
+/// Số lần gửi lại tối đa, hết thì bỏ frame
+pub const MAX_RETRANSMITS: u32 = 5;
+/// Số frame chờ ack tối đa mỗi connection; vượt thì bỏ frame cũ nhất
+const MAX_PENDING: usize = 64;
+
+/// Đường frame được gửi lần đầu; bản gửi lại đi đúng đường đó
+#[derive(Clone)]
+pub enum SentVia {
+    /// WS outbox (hoặc hàng đợi long-poll) của session
+    Outbox,
+    /// Transport đã chọn của connection (WebRTC, QUIC...)
+    Transport(SharedTransport),
+}
+
+impl std::fmt::Debug for SentVia {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Outbox => f.write_str("Outbox"),
+            Self::Transport(_) => f.write_str("Transport"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Pending {
+    frame: Frame,
+    via: SentVia,
+    sent_at: Instant,
+    retransmits: u32,
+}
+
+#[derive(Debug, Default)]
+struct RetransmitState {
+    /// Peer đã từng gửi `acks`, tức là hiểu ack từng frame
+    peer_acks: bool,
+    pending: BTreeMap<u32, Pending>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ControlRetransmitter(Arc<Mutex<RetransmitState>>);
+
+impl ControlRetransmitter {
+    /// Ghi nhận frame vừa đóng sequence và sắp gửi qua `via`; chỉ giữ control frame cần ack của peer đã biết ack
+    pub fn track(&self, frame: &Frame, via: SentVia) {
+        if !frame.needs_ack() {
+            return;
+        }
+        let mut state = self.0.lock().unwrap();
+        if !state.peer_acks {
+            return;
+        }
+        state.pending.insert(frame.sequence, Pending {
+            frame: frame.clone(),
+            via,
+            sent_at: Instant::now(),
+            retransmits: 0,
+        });
+        if state.pending.len() > MAX_PENDING {
+            state.pending.pop_first();
+            crate::metrics::record_control_retransmit_giveup();
+        }
+    }
+
+    /// `acks` của frame client gửi lên: đúng các control frame đó đã tới, frame khác vẫn chờ
+    pub fn on_acks(&self, acks: &[u32]) {
+        if acks.is_empty() {
+            return;
+        }
+        let mut state = self.0.lock().unwrap();
+        state.peer_acks = true;
+        for sequence in acks {
+            state.pending.remove(sequence);
+        }
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.0.lock().unwrap().pending.len()
+    }
+
+    /// Frame đã chờ ack quá `RETRANSMIT_INTERVAL`, cần gửi lại ngay qua đường kèm theo
+    pub fn due(&self, now: Instant) -> Vec<(Frame, SentVia)> {
+        let mut state = self.0.lock().unwrap();
+        let mut due = Vec::new();
+        state.pending.retain(|_, pending| {
+            if now.duration_since(pending.sent_at) < RETRANSMIT_INTERVAL {
+                return true;
+            }
+            if pending.retransmits == MAX_RETRANSMITS {
+                crate::metrics::record_control_retransmit_giveup();
+                return false;
+            }
+            pending.retransmits += 1;
+            pending.sent_at = now;
+            due.push((pending.frame.clone(), pending.via.clone()));
+            true
+        });
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common_net::message::{ControlMessage, StateMessage};
+
+    use super::*;
+
+    fn error_frame(sequence: u32) -> Frame {
+        Frame::control(sequence, 0, ControlMessage::Error {
+            code: "test".to_string(),
+            message: String::new(),
+        })
+    }
+
+    fn sequences(due: &[(Frame, SentVia)]) -> Vec<u32> {
+        due.iter().map(|(frame, _)| frame.sequence).collect()
+    }
+
+    #[test]
+    fn unacked_frame_is_retransmitted_a_bounded_number_of_times() {
+        let retransmitter = ControlRetransmitter::default();
+        // Peer chưa từng ack: client cũ, không giữ gì
+        retransmitter.track(&error_frame(1), SentVia::Outbox);
+        assert_eq!(retransmitter.pending_len(), 0);
+
+        retransmitter.on_acks(&[1]);
+        retransmitter.track(&error_frame(2), SentVia::Outbox);
+        retransmitter.track(&Frame::control(3, 0, ControlMessage::Ping { nonce: 1 }), SentVia::Outbox);
+        assert_eq!(retransmitter.pending_len(), 1);
+
+        let mut now = Instant::now();
+        assert!(retransmitter.due(now).is_empty());
+        for _ in 0..MAX_RETRANSMITS {
+            now += RETRANSMIT_INTERVAL;
+            assert_eq!(sequences(&retransmitter.due(now)), [2]);
+        }
+        now += RETRANSMIT_INTERVAL;
+        assert!(retransmitter.due(now).is_empty());
+        assert_eq!(retransmitter.pending_len(), 0);
+    }
+
+    #[test]
+    fn later_state_frame_does_not_ack_a_lost_control_frame() {
+        let retransmitter = ControlRetransmitter::default();
+        retransmitter.on_acks(&[1]);
+
+        // Control frame 2 mất, state frame 3 tới: client ack cumulative 3 nhưng `acks` không có 2
+        let lost = error_frame(2);
+        let state = Frame::state(3, 0, StateMessage::Event { name: "chat".to_string(), data: serde_json::json!({}) });
+        retransmitter.track(&lost, SentVia::Outbox);
+        retransmitter.track(&state, SentVia::Outbox);
+        let client_frame = Frame { ack: 3, ..Frame::control(7, 0, ControlMessage::Ack) };
+        retransmitter.on_acks(&client_frame.acks);
+
+        let now = Instant::now() + RETRANSMIT_INTERVAL;
+        assert_eq!(sequences(&retransmitter.due(now)), [2]);
+
+        retransmitter.on_acks(&[2]);
+        assert_eq!(retransmitter.pending_len(), 0);
+    }
+
+    #[tokio::test]
+    async fn retransmit_goes_back_over_the_transport_of_the_first_send() {
+        let retransmitter = ControlRetransmitter::default();
+        retransmitter.on_acks(&[1]);
+        let transport: SharedTransport = Arc::new(tokio::sync::Mutex::new(Box::new(
+            common_net::transport::WebRtcTransport::new("room".to_string(), "peer".to_string()),
+        )));
+
+        retransmitter.track(&error_frame(2), SentVia::Transport(transport.clone()));
+        retransmitter.track(&error_frame(3), SentVia::Outbox);
+        let due = retransmitter.due(Instant::now() + RETRANSMIT_INTERVAL);
+        assert_eq!(sequences(&due), [2, 3]);
+        assert!(matches!(&due[0].1, SentVia::Transport(via) if Arc::ptr_eq(via, &transport)));
+        assert!(matches!(due[1].1, SentVia::Outbox));
+    }
+}
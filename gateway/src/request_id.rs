@@ -0,0 +1,88 @@
+//! Correlation id cho mỗi request HTTP: lấy từ header `x-request-id` hoặc sinh mới, mở span bọc handler,
+//! trả lại trong response và đi theo các lời gọi gRPC sang worker (xem `worker_client`).
+
+use std::{future::Future, pin::Pin};
+
+use axum::http::{HeaderValue, Request};
+use axum::response::Response;
+use common_net::telemetry::{new_request_id, REQUEST_ID_HEADER};
+use tower::{Layer, Service};
+use tracing::Instrument;
+
+/// Id client gửi dài hơn thế này thì bỏ, sinh id mới
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Request id của request HTTP đang xử lý trên task hiện tại
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+fn incoming_request_id(value: Option<&HeaderValue>) -> Option<String> {
+    value
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<Request<B>> for RequestIdService<S>
+where
+    S: Service<Request<B>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        let request_id =
+            incoming_request_id(request.headers().get(REQUEST_ID_HEADER)).unwrap_or_else(new_request_id);
+        let header = HeaderValue::from_str(&request_id).expect("request id is a visible ASCII header value");
+        request.headers_mut().insert(REQUEST_ID_HEADER, header.clone());
+
+        let span = tracing::info_span!(
+            "http_request",
+            request_id = %request_id,
+            method = %request.method(),
+            path = %request.uri().path(),
+        );
+        let future = {
+            let _entered = span.enter();
+            self.inner.call(request)
+        };
+
+        Box::pin(REQUEST_ID.scope(
+            request_id,
+            async move {
+                let mut response = future.await?;
+                response.headers_mut().insert(REQUEST_ID_HEADER, header);
+                Ok(response)
+            }
+            .instrument(span),
+        ))
+    }
+}
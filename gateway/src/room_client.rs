@@ -0,0 +1,220 @@
+use std::{collections::HashMap, time::Duration};
+
+use common_net::health;
+
+use room_manager::{
+    api::{self, INTERNAL_SECRET_HEADER},
+    party::{CreatePartyRequest, JoinPartyRequest, LeavePartyRequest, PartyInviteRequest, PartyResponse},
+    tournament::{
+        CreateTournamentRequest, RegisterParticipantRequest, ReportMatchResultRequest, StartTournamentRequest,
+        TournamentResponse,
+    },
+    AssignRoomRequest, AssignRoomResponse, CreateRoomRequest, CreateRoomResponse, JoinRoomRequest, JoinRoomResponse,
+    GetRoomResponse, KickPlayerRequest, KickPlayerResponse, LeaveRoomRequest, LeaveRoomResponse, ListPlayersResponse, ListRoomsRequest, ListRoomsResponse, ResolveInviteRequest,
+    ResolveInviteResponse, RoomInviteRequest, RoomInviteResponse,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::BoxError;
+
+const DEFAULT_ROOM_MANAGER_URL: &str = "http://127.0.0.1:3200";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// HTTP client gọi REST API của room-manager; gateway không giữ state phòng
+#[derive(Clone)]
+pub struct RoomManagerClient {
+    http: reqwest::Client,
+    base_url: String,
+    /// None khi chưa đặt ROOM_MANAGER_INTERNAL_SECRET: room-manager khi đó chỉ nghe trên loopback
+    secret: Option<String>,
+}
+
+impl RoomManagerClient {
+    pub fn new(base_url: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self::with_secret(base_url, Some(secret.into()))
+    }
+
+    /// `secret` None thì không gửi header; chỉ room-manager nghe trên loopback mới chấp nhận
+    pub fn with_secret(base_url: impl Into<String>, secret: Option<String>) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            secret: secret.filter(|secret| !secret.is_empty()),
+        }
+    }
+
+    /// Đọc ROOM_MANAGER_URL và ROOM_MANAGER_INTERNAL_SECRET
+    pub fn from_env() -> Self {
+        Self::with_secret(url_from_env(), api::internal_secret_from_env())
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Shared secret nội bộ; worker gửi điểm lên gateway cũng dùng secret này
+    pub fn secret(&self) -> Option<&str> {
+        self.secret.as_deref()
+    }
+
+    pub async fn create_room(&self, request: &CreateRoomRequest) -> Result<CreateRoomResponse, BoxError> {
+        self.send(self.http.post(self.url(api::ROOMS_PATH)).json(request)).await
+    }
+
+    pub async fn list_rooms(&self, request: &ListRoomsRequest) -> Result<ListRoomsResponse, BoxError> {
+        self.send(self.http.get(self.url(api::ROOMS_PATH)).query(request)).await
+    }
+
+    pub async fn join_room(&self, request: &JoinRoomRequest) -> Result<JoinRoomResponse, BoxError> {
+        let url = self.id_url(api::ROOM_JOIN_PATH, &request.room_id)?;
+        self.post(url, request).await
+    }
+
+    pub async fn leave_room(&self, request: &LeaveRoomRequest) -> Result<LeaveRoomResponse, BoxError> {
+        let url = self.id_url(api::ROOM_LEAVE_PATH, &request.room_id)?;
+        self.post(url, request).await
+    }
+
+    pub async fn get_room(&self, room_id: &str) -> Result<GetRoomResponse, BoxError> {
+        let url = self.id_url(api::ROOM_PATH, room_id)?;
+        self.send(self.http.get(url)).await
+    }
+
+    pub async fn list_players(&self, room_id: &str) -> Result<ListPlayersResponse, BoxError> {
+        let url = self.id_url(api::ROOM_PLAYERS_PATH, room_id)?;
+        self.send(self.http.get(url)).await
+    }
+
+    pub async fn kick_player(&self, request: &KickPlayerRequest) -> Result<KickPlayerResponse, BoxError> {
+        let url = self.id_url(api::ROOM_KICK_PATH, &request.room_id)?;
+        self.post(url, request).await
+    }
+
+    pub async fn assign_room(&self, request: &AssignRoomRequest) -> Result<AssignRoomResponse, BoxError> {
+        self.post(self.url(api::ASSIGN_PATH), request).await
+    }
+
+    pub async fn resolve_invite(&self, request: &ResolveInviteRequest) -> Result<ResolveInviteResponse, BoxError> {
+        self.post(self.url(api::INVITE_RESOLVE_PATH), request).await
+    }
+
+    pub async fn regenerate_invite(&self, request: &RoomInviteRequest) -> Result<RoomInviteResponse, BoxError> {
+        let url = self.id_url(api::ROOM_INVITE_PATH, &request.room_id)?;
+        self.post(url, request).await
+    }
+
+    pub async fn revoke_invite(&self, request: &RoomInviteRequest) -> Result<RoomInviteResponse, BoxError> {
+        let url = self.id_url(api::ROOM_INVITE_PATH, &request.room_id)?;
+        self.send(self.http.delete(url).json(request)).await
+    }
+
+    pub async fn create_party(&self, request: &CreatePartyRequest) -> Result<PartyResponse, BoxError> {
+        self.post(self.url(api::PARTIES_PATH), request).await
+    }
+
+    pub async fn invite_to_party(&self, request: &PartyInviteRequest) -> Result<PartyResponse, BoxError> {
+        let url = self.id_url(api::PARTY_INVITE_PATH, &request.party_id)?;
+        self.post(url, request).await
+    }
+
+    pub async fn join_party(&self, request: &JoinPartyRequest) -> Result<PartyResponse, BoxError> {
+        self.post(self.url(api::PARTY_JOIN_PATH), request).await
+    }
+
+    pub async fn leave_party(&self, request: &LeavePartyRequest) -> Result<PartyResponse, BoxError> {
+        self.post(self.url(api::PARTY_LEAVE_PATH), request).await
+    }
+
+    pub async fn create_tournament(&self, request: &CreateTournamentRequest) -> Result<TournamentResponse, BoxError> {
+        self.post(self.url(api::TOURNAMENTS_PATH), request).await
+    }
+
+    pub async fn get_tournament(&self, tournament_id: &str) -> Result<TournamentResponse, BoxError> {
+        let url = self.id_url(api::TOURNAMENT_PATH, tournament_id)?;
+        self.send(self.http.get(url)).await
+    }
+
+    pub async fn register_participant(&self, request: &RegisterParticipantRequest) -> Result<TournamentResponse, BoxError> {
+        let url = self.id_url(api::TOURNAMENT_REGISTER_PATH, &request.tournament_id)?;
+        self.post(url, request).await
+    }
+
+    pub async fn start_tournament(&self, request: &StartTournamentRequest) -> Result<TournamentResponse, BoxError> {
+        let url = self.id_url(api::TOURNAMENT_START_PATH, &request.tournament_id)?;
+        self.post(url, request).await
+    }
+
+    pub async fn report_match_result(&self, request: &ReportMatchResultRequest) -> Result<TournamentResponse, BoxError> {
+        let url = self.id_url(api::TOURNAMENT_REPORT_PATH, &request.tournament_id)?;
+        self.post(url, request).await
+    }
+
+    /// `/readyz` của room-manager: (ready, trạng thái từng dependency). Body 503 vẫn được đọc
+    pub async fn readiness(&self) -> Result<(bool, HashMap<String, String>), BoxError> {
+        let response = self.http.get(self.url(health::READYZ_PATH)).send().await?;
+        let ready = response.status().is_success();
+        Ok((ready, response.json().await?))
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Thay `:id` trong path; id ngoài `[A-Za-z0-9_-]` bị từ chối để không đổi được route (`../`, `?`, `#`)
+    fn id_url(&self, template: &str, id: &str) -> Result<String, BoxError> {
+        if !is_path_id(id) {
+            return Err(format!("invalid id {id:?}").into());
+        }
+        Ok(self.url(&template.replace(":id", id)))
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(&self, url: String, body: &B) -> Result<T, BoxError> {
+        self.send(self.http.post(url).json(body)).await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, BoxError> {
+        let request = match &self.secret {
+            Some(secret) => request.header(INTERNAL_SECRET_HEADER, secret),
+            None => request,
+        };
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body: serde_json::Value = response.json().await.unwrap_or_default();
+            let message = body["error"].as_str().unwrap_or("unknown error");
+            return Err(format!("room-manager returned {status}: {message}").into());
+        }
+        Ok(response.json().await?)
+    }
+}
+
+/// ROOM_MANAGER_URL, mặc định room-manager local
+pub fn url_from_env() -> String {
+    std::env::var("ROOM_MANAGER_URL").unwrap_or_else(|_| DEFAULT_ROOM_MANAGER_URL.to_string())
+}
+
+fn is_path_id(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn ids_that_could_change_the_route_are_rejected_before_sending() {
+        // Port 9 không có ai nghe: id hợp lệ mới tới bước gửi và lỗi kết nối
+        let client = RoomManagerClient::new("http://127.0.0.1:9", "secret");
+
+        for id in ["", "../rooms", "%2e%2e", "room?admin=true", "room#frag", "room/finish"] {
+            let error = client.get_room(id).await.expect_err("id phải bị từ chối").to_string();
+            assert!(error.starts_with("invalid id"), "{id:?}: {error}");
+        }
+        let error = client.get_room("0b7c-room_1").await.expect_err("không có room-manager").to_string();
+        assert!(!error.starts_with("invalid id"), "{error}");
+    }
+}
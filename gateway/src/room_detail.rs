@@ -0,0 +1,146 @@
+//! `GET /rooms/:room_id`: record phòng của room-manager ghép với player live từ worker (`GetRoomPlayers`).
+//! Kết quả ghép được cache ngắn theo room để màn hình lobby poll liên tục không dồn tải lên worker.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use proto::worker::v1::LiveRoomPlayer;
+use room_manager::Room;
+use serde::Serialize;
+
+pub const CACHE_TTL: Duration = Duration::from_secs(1);
+/// Worker không trả lời trong ngần này thì trả nửa room-manager với `partial: true`
+pub const WORKER_TIMEOUT: Duration = Duration::from_secs(1);
+/// Dọn entry hết hạn khi cache vượt ngần này room
+const PRUNE_THRESHOLD: usize = 1_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomDetailPlayer {
+    pub id: String,
+    pub name: String,
+    pub score: u32,
+    pub team: Option<String>,
+    pub connected: bool,
+    pub is_bot: bool,
+    pub is_ready: bool,
+    pub latency_ms: Option<u32>,
+}
+
+impl From<LiveRoomPlayer> for RoomDetailPlayer {
+    fn from(player: LiveRoomPlayer) -> Self {
+        Self {
+            id: player.id,
+            name: player.name,
+            score: player.score,
+            team: Some(player.team).filter(|team| !team.is_empty()),
+            connected: player.connected,
+            is_bot: player.is_bot,
+            is_ready: player.is_ready,
+            latency_ms: player.latency_ms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomDetail {
+    pub success: bool,
+    pub room: Room,
+    /// None khi không lấy được từ worker
+    pub players: Option<Vec<RoomDetailPlayer>>,
+    /// Chỉ có phần của room-manager vì worker không tới được
+    pub partial: bool,
+}
+
+impl RoomDetail {
+    pub fn new(room: Room, players: Option<Vec<LiveRoomPlayer>>) -> Self {
+        Self {
+            success: true,
+            room,
+            partial: players.is_none(),
+            players: players.map(|players| players.into_iter().map(RoomDetailPlayer::from).collect()),
+        }
+    }
+
+    /// Invite code và danh sách ban chỉ host mới thấy
+    pub fn for_requester(mut self, requester_id: Option<&str>) -> Self {
+        if requester_id != Some(self.room.host_player_id.as_str()) {
+            self.room.invite_code = None;
+            self.room.banned_players.clear();
+        }
+        self
+    }
+}
+
+/// Các bản clone dùng chung cache
+#[derive(Debug, Clone, Default)]
+pub struct RoomDetailCache {
+    entries: Arc<DashMap<String, (Instant, RoomDetail)>>,
+}
+
+impl RoomDetailCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kết quả ghép còn mới (chưa quá `CACHE_TTL` tính tới `now`) của room
+    pub fn get(&self, room_id: &str, now: Instant) -> Option<RoomDetail> {
+        self.entries
+            .get(room_id)
+            .filter(|entry| now.duration_since(entry.0) < CACHE_TTL)
+            .map(|entry| entry.1.clone())
+    }
+
+    pub fn insert(&self, room_id: &str, detail: RoomDetail, now: Instant) {
+        if self.entries.len() > PRUNE_THRESHOLD {
+            self.entries.retain(|_, (cached_at, _)| now.duration_since(*cached_at) < CACHE_TTL);
+        }
+        self.entries.insert(room_id.to_string(), (now, detail));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room() -> Room {
+        serde_json::from_value(serde_json::json!({
+            "id": "room-1",
+            "name": "lobby",
+            "game_mode": "deathmatch",
+            "max_players": 4,
+            "current_players": 2,
+            "status": "waiting",
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z",
+            "host_player_id": "host",
+            "worker_endpoint": null,
+            "settings": null,
+            "is_private": true,
+            "invite_code": "ABCDEF",
+            "banned_players": ["griefer"]
+        }))
+        .expect("room")
+    }
+
+    #[test]
+    fn host_fields_are_hidden_from_everyone_else_and_cache_expires() {
+        let detail = RoomDetail::new(room(), None);
+        assert!(detail.partial);
+
+        let host = detail.clone().for_requester(Some("host"));
+        assert_eq!((host.room.invite_code.as_deref(), host.room.banned_players.len()), (Some("ABCDEF"), 1));
+        for requester in [Some("player-1"), None] {
+            let other = detail.clone().for_requester(requester);
+            assert!(other.room.invite_code.is_none() && other.room.banned_players.is_empty());
+        }
+
+        let cache = RoomDetailCache::new();
+        let now = Instant::now();
+        cache.insert("room-1", detail, now);
+        assert!(cache.get("room-1", now + CACHE_TTL / 2).is_some());
+        assert!(cache.get("room-1", now + CACHE_TTL).is_none());
+    }
+}
@@ -0,0 +1,124 @@
+//! WebRTC phía gateway: trả lời offer của client bằng peer connection webrtc-rs, nhận ICE candidate trickle
+//! từ `/rtc/ice` hoặc signaling qua WS, rồi giao transport DataChannel cho ws session của peer khi kênh đã mở.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common_net::transport::{
+    webrtc::DEFAULT_NEGOTIATION_TIMEOUT, IceCandidate, IceCandidateSink, TransportError, WebRtcNegotiation,
+    WebRtcTransport,
+};
+use dashmap::DashMap;
+
+/// `target_peer_id` của signaling qua WS khi offer/candidate gửi cho chính gateway thay vì peer khác trong room
+pub const GATEWAY_PEER_ID: &str = "gateway";
+
+/// Offer đã trả lời mà chưa ws session nào nhận quá lâu thì bị đóng
+const PENDING_TTL: Duration = Duration::from_secs(60);
+
+struct Pending {
+    created: Instant,
+    negotiation: WebRtcNegotiation,
+}
+
+/// Các peer connection gateway đang trao đổi, key là peer_id (mỗi peer một negotiation, offer mới thay offer cũ)
+#[derive(Clone)]
+pub struct ServerPeers {
+    stun_servers: Arc<[String]>,
+    timeout: Duration,
+    pending: Arc<DashMap<String, Pending>>,
+    /// Còn nhận candidate trong lúc ws session đang chờ DataChannel mở
+    ice: Arc<DashMap<String, IceCandidateSink>>,
+}
+
+impl std::fmt::Debug for ServerPeers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerPeers")
+            .field("stun_servers", &self.stun_servers)
+            .field("timeout", &self.timeout)
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
+
+impl Default for ServerPeers {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl ServerPeers {
+    pub fn new(stun_servers: Vec<String>) -> Self {
+        Self {
+            stun_servers: stun_servers.into(),
+            timeout: DEFAULT_NEGOTIATION_TIMEOUT,
+            pending: Arc::default(),
+            ice: Arc::default(),
+        }
+    }
+
+    /// Thời gian chờ DataChannel mở trước khi ws session fallback về WebSocket
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn stun_servers(&self) -> &[String] {
+        &self.stun_servers
+    }
+
+    /// Tạo peer connection cho offer của `peer_id` và trả về SDP answer (đã gồm candidate của gateway)
+    pub async fn accept_offer(&self, peer_id: &str, offer_sdp: &str) -> Result<String, TransportError> {
+        self.close_stale().await;
+        let negotiation = WebRtcNegotiation::new(&self.stun_servers).await?;
+        let answer = match negotiation.accept_offer(offer_sdp).await {
+            Ok(answer) => answer,
+            Err(err) => {
+                negotiation.close().await;
+                return Err(err);
+            }
+        };
+        self.ice.insert(peer_id.to_string(), negotiation.ice_sink());
+        let replaced = self.pending.insert(peer_id.to_string(), Pending { created: Instant::now(), negotiation });
+        if let Some(replaced) = replaced {
+            replaced.negotiation.close().await;
+        }
+        Ok(answer)
+    }
+
+    /// Đưa candidate của client vào peer connection của nó; None nếu peer không có negotiation nào với gateway
+    pub async fn add_ice_candidate(&self, peer_id: &str, candidate: IceCandidate) -> Option<Result<(), TransportError>> {
+        let sink = self.ice.get(peer_id).map(|sink| sink.clone())?;
+        Some(sink.add(candidate).await)
+    }
+
+    pub fn has_pending(&self, peer_id: &str) -> bool {
+        self.pending.contains_key(peer_id)
+    }
+
+    /// Lấy negotiation của peer và chờ DataChannel mở; None nếu peer chưa gửi offer
+    pub async fn connect(&self, peer_id: &str, room_id: &str) -> Option<Result<WebRtcTransport, TransportError>> {
+        let (_, pending) = self.pending.remove(peer_id)?;
+        let sink = pending.negotiation.ice_sink();
+        let result = pending.negotiation.connect(room_id.to_string(), peer_id.to_string(), self.timeout).await;
+        self.ice.remove_if(peer_id, |_, current| current.same_peer(&sink));
+        Some(result)
+    }
+
+    async fn close_stale(&self) {
+        let stale: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|entry| entry.created.elapsed() > PENDING_TTL)
+            .map(|entry| entry.key().clone())
+            .collect();
+        for peer_id in stale {
+            if let Some((_, pending)) = self.pending.remove_if(&peer_id, |_, pending| pending.created.elapsed() > PENDING_TTL) {
+                self.ice.remove(&peer_id);
+                pending.negotiation.close().await;
+            }
+        }
+    }
+}
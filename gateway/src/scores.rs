@@ -0,0 +1,218 @@
+//! Chống gian lận điểm leaderboard. Điểm tin cậy do worker gửi qua route nội bộ (shared secret) sau khi simulation
+//! chốt điểm; submit từ client chỉ còn trong giai đoạn chuyển tiếp và phải qua các check: trần điểm theo game mode,
+//! số lần submit mỗi giờ của player, và player phải đang (hoặc vừa) ở trong một room cùng mode theo room-manager.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::http::StatusCode;
+use dashmap::DashMap;
+use services::seasons::Standing;
+
+use crate::room_client::RoomManagerClient;
+
+/// Số lần submit tối đa mỗi giờ của một player khi không cấu hình `GATEWAY_SCORE_SUBMITS_PER_HOUR`
+pub const DEFAULT_SUBMITS_PER_HOUR: u32 = 20;
+/// Key trong `default_settings` của game mode chứa trần điểm một lần submit
+pub const MAX_SCORE_SETTING: &str = "max_score";
+
+const SUBMIT_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Lý do một submit bị từ chối, cũng là label `reason` của metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    AboveMaxScore,
+    RateLimited,
+    NoRecentRoom,
+}
+
+impl Rejection {
+    pub fn reason(self) -> &'static str {
+        match self {
+            Rejection::AboveMaxScore => "above_max_score",
+            Rejection::RateLimited => "rate_limited",
+            Rejection::NoRecentRoom => "no_recent_room",
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            Rejection::AboveMaxScore => StatusCode::UNPROCESSABLE_ENTITY,
+            Rejection::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Rejection::NoRecentRoom => StatusCode::FORBIDDEN,
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Rejection::AboveMaxScore => "Score is above the maximum for this game mode",
+            Rejection::RateLimited => "Too many score submissions, try again later",
+            Rejection::NoRecentRoom => "Player has no recent room in this game mode",
+        }
+    }
+}
+
+/// Trần điểm của game mode theo `max_score` trong settings mặc định; mode không khai báo thì không giới hạn
+pub fn max_score(game_mode: &str) -> Option<u64> {
+    common_net::game_modes::from_id(game_mode)?
+        .default_setting(MAX_SCORE_SETTING)
+        .map(u64::from)
+}
+
+pub fn check_max_score(game_mode: &str, score: u64) -> Result<(), Rejection> {
+    match max_score(game_mode) {
+        Some(max) if score > max => Err(Rejection::AboveMaxScore),
+        _ => Ok(()),
+    }
+}
+
+/// Player là host hoặc member của `room_id` và room chơi đúng `game_mode`. Room-manager dọn room đã kết thúc sau
+/// ít phút nên đây cũng là giới hạn "gần đây"; không hỏi được room-manager thì coi như không có room
+pub async fn check_room_membership(
+    room_manager: &RoomManagerClient,
+    room_id: Option<&str>,
+    player_id: &str,
+    game_mode: &str,
+) -> Result<(), Rejection> {
+    let room_id = room_id.filter(|room_id| !room_id.is_empty()).ok_or(Rejection::NoRecentRoom)?;
+    let room = match room_manager.get_room(room_id).await {
+        Ok(response) => response.room.ok_or(Rejection::NoRecentRoom)?,
+        Err(err) => {
+            tracing::warn!(%err, room_id, "gateway: không hỏi được room-manager khi kiểm tra điểm");
+            return Err(Rejection::NoRecentRoom);
+        }
+    };
+    if room.game_mode.as_str() != game_mode {
+        return Err(Rejection::NoRecentRoom);
+    }
+    if room.host_player_id == player_id {
+        return Ok(());
+    }
+    match room_manager.list_players(room_id).await {
+        Ok(response) if response.players.iter().any(|player| player.id == player_id) => Ok(()),
+        Ok(_) => Err(Rejection::NoRecentRoom),
+        Err(err) => {
+            tracing::warn!(%err, room_id, "gateway: không hỏi được room-manager khi kiểm tra điểm");
+            Err(Rejection::NoRecentRoom)
+        }
+    }
+}
+
+/// Số submit của client theo player trong cửa sổ trượt 1 giờ; các bản clone dùng chung bộ đếm
+#[derive(Debug, Clone, Default)]
+pub struct SubmitLimiter {
+    /// 0 là không giới hạn
+    limit: Arc<AtomicU32>,
+    submits: Arc<DashMap<String, VecDeque<Instant>>>,
+}
+
+impl SubmitLimiter {
+    pub fn new(submits_per_hour: u32) -> Self {
+        let limiter = Self::default();
+        limiter.limit.store(submits_per_hour, Ordering::Relaxed);
+        limiter
+    }
+
+    /// Đọc GATEWAY_SCORE_SUBMITS_PER_HOUR, mặc định `DEFAULT_SUBMITS_PER_HOUR`
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("GATEWAY_SCORE_SUBMITS_PER_HOUR")
+                .ok()
+                .and_then(|raw| raw.parse().ok())
+                .unwrap_or(DEFAULT_SUBMITS_PER_HOUR),
+        )
+    }
+
+    /// Tính một submit của `player_id` lúc `now`; hết lượt trong giờ vừa qua thì từ chối và không tính
+    pub fn check(&self, player_id: &str, now: Instant) -> Result<(), Rejection> {
+        let limit = self.limit.load(Ordering::Relaxed) as usize;
+        if limit == 0 {
+            return Ok(());
+        }
+        let mut submits = self.submits.entry(player_id.to_string()).or_default();
+        while submits.front().is_some_and(|at| now.duration_since(*at) >= SUBMIT_WINDOW) {
+            submits.pop_front();
+        }
+        if submits.len() >= limit {
+            return Err(Rejection::RateLimited);
+        }
+        submits.push_back(now);
+        Ok(())
+    }
+}
+
+/// player_id -> (tên, điểm tốt nhất) của một game mode
+type ModeScores = HashMap<String, (String, u64)>;
+
+/// Điểm tốt nhất theo (game mode, player) giữ trong memory khi gateway không có PocketBase
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLeaderboard {
+    best: Arc<Mutex<HashMap<String, ModeScores>>>,
+}
+
+impl MemoryLeaderboard {
+    /// Giữ điểm tốt nhất của player; trả về (hạng, điểm tốt nhất) sau khi ghi
+    pub fn record(&self, game_mode: &str, player_id: &str, player_name: &str, score: u64) -> (u32, u64) {
+        let mut best = self.best.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let players = best.entry(game_mode.to_string()).or_default();
+        let entry = players.entry(player_id.to_string()).or_insert_with(|| (player_name.to_string(), 0));
+        entry.1 = entry.1.max(score);
+        let best_score = entry.1;
+        let rank = players.values().filter(|(_, score)| *score > best_score).count() as u32 + 1;
+        (rank, best_score)
+    }
+
+    /// Điểm cao nhất trước; bằng điểm thì theo player id
+    pub fn standings(&self, game_mode: &str, limit: usize) -> Vec<Standing> {
+        let best = self.best.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut rows: Vec<(&String, &(String, u64))> = best.get(game_mode).map(|players| players.iter().collect()).unwrap_or_default();
+        rows.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then_with(|| a.0.cmp(b.0)));
+        rows.into_iter()
+            .take(limit)
+            .enumerate()
+            .map(|(index, (player_id, (player_name, score)))| Standing {
+                rank: index as u32 + 1,
+                user_id: player_id.clone(),
+                username: player_name.clone(),
+                score: *score,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_come_from_game_mode_settings_and_limits_slide_per_player() {
+        assert_eq!(max_score(common_net::game_modes::ENDLESS_RUNNER), Some(1_000_000));
+        assert_eq!(check_max_score(common_net::game_modes::DEATHMATCH, 10_001), Err(Rejection::AboveMaxScore));
+        assert_eq!(check_max_score(common_net::game_modes::DEATHMATCH, 10_000), Ok(()));
+
+        let limiter = SubmitLimiter::new(2);
+        let start = Instant::now();
+        assert_eq!(limiter.check("alice", start), Ok(()));
+        assert_eq!(limiter.check("alice", start), Ok(()));
+        assert_eq!(limiter.check("alice", start), Err(Rejection::RateLimited));
+        assert_eq!(limiter.check("bob", start), Ok(()));
+        assert_eq!(limiter.check("alice", start + SUBMIT_WINDOW), Ok(()));
+
+        let board = MemoryLeaderboard::default();
+        assert_eq!(board.record("endless_runner", "alice", "Alice", 500), (1, 500));
+        assert_eq!(board.record("endless_runner", "bob", "Bob", 900), (1, 900));
+        assert_eq!(board.record("endless_runner", "alice", "Alice", 100), (2, 500));
+        let ranked: Vec<(u32, String)> = board
+            .standings("endless_runner", 10)
+            .into_iter()
+            .map(|standing| (standing.rank, standing.user_id))
+            .collect();
+        assert_eq!(ranked, vec![(1, "bob".to_string()), (2, "alice".to_string())]);
+    }
+}
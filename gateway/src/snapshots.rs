@@ -0,0 +1,369 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use axum::extract::ws::Message;
+use common_net::message::{self, EntityDelta, EntitySnapshot, Frame, FramePayload, StateMessage};
+use proto::worker::v1::{
+    room_event::Event, GetPlayerSnapshotRequest, GetPlayerSnapshotResponse, RoomEvent, Snapshot, StreamRoomEventsRequest,
+};
+use tokio::{sync::Mutex, time::Instant};
+use tonic::Streaming;
+use tracing::debug;
+
+use crate::{
+    bandwidth::{BandwidthTracker, Direction, MessageKind},
+    outbox::{OutboundKind, WsOutbox},
+    worker_client::WorkerRpcClient,
+    OutboundSequence, WebSocketRegistry,
+};
+
+/// Chu kỳ pull snapshot từ worker cho mỗi room (20Hz)
+pub const SNAPSHOT_BROADCAST_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Room có event stream mà worker lâu không báo snapshot mới thì vẫn pull một lần sau khoảng này
+pub const IDLE_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Thời gian chờ worker mở `StreamRoomEvents` trước khi room chuyển sang chỉ pull theo chu kỳ
+const EVENT_STREAM_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Một WS connection đang ở trong room tại thời điểm broadcast
+struct RoomMember {
+    connection_id: String,
+    peer_id: String,
+    outbound: OutboundSequence,
+    outbox: WsOutbox,
+}
+
+/// Mỗi room có WS client thì chạy một task subscribe `StreamRoomEvents` của worker, fan-out event xuống từng
+/// connection và pull snapshot khi worker báo tick mới. Worker không mở được stream thì pull theo chu kỳ.
+/// Task tự dừng khi room không còn connection nào.
+#[derive(Clone)]
+pub struct SnapshotBroadcaster {
+    worker_client: WorkerRpcClient,
+    ws_registry: WebSocketRegistry,
+    active_rooms: Arc<Mutex<HashSet<String>>>,
+    /// Room đang nhận event stream từ worker
+    streamed_rooms: Arc<Mutex<HashSet<String>>>,
+    bandwidth: Option<BandwidthTracker>,
+    interval: Duration,
+}
+
+impl SnapshotBroadcaster {
+    pub fn new(worker_client: WorkerRpcClient, ws_registry: WebSocketRegistry) -> Self {
+        Self {
+            worker_client,
+            ws_registry,
+            active_rooms: Arc::new(Mutex::new(HashSet::new())),
+            streamed_rooms: Arc::new(Mutex::new(HashSet::new())),
+            bandwidth: None,
+            interval: SNAPSHOT_BROADCAST_INTERVAL,
+        }
+    }
+
+    /// Ghi bandwidth của event fan-out (chat, player_joined...) theo connection
+    pub fn with_bandwidth(mut self, bandwidth: BandwidthTracker) -> Self {
+        self.bandwidth = Some(bandwidth);
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Bật task broadcast cho room nếu chưa chạy. Gọi sau khi connection đã được gán `room_id` trong registry.
+    pub async fn ensure_room(&self, room_id: &str) {
+        let mut active = self.active_rooms.lock().await;
+        if active.insert(room_id.to_string()) {
+            let broadcaster = self.clone();
+            let room_id = room_id.to_string();
+            tokio::spawn(async move { broadcaster.run_room(room_id).await });
+        }
+    }
+
+    pub async fn is_broadcasting(&self, room_id: &str) -> bool {
+        self.active_rooms.lock().await.contains(room_id)
+    }
+
+    /// Room đang nhận event stream từ worker: chat gửi qua worker sẽ quay lại qua stream này
+    pub async fn has_event_stream(&self, room_id: &str) -> bool {
+        self.streamed_rooms.lock().await.contains(room_id)
+    }
+
+    async fn run_room(self, room_id: String) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut events = self.subscribe(&room_id).await;
+        // Có stream thì chỉ pull khi worker đã chạy tick mới hoặc room có thay đổi
+        let mut dirty = true;
+        let mut last_pull = Instant::now();
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                event = next_event(&mut events) => {
+                    match event {
+                        Some(event) => {
+                            dirty = true;
+                            let recipient = event_recipient(&event);
+                            if let Some(state) = room_event_message(event) {
+                                self.fan_out(&room_id, recipient.as_deref(), state).await;
+                            }
+                        }
+                        None => {
+                            // Worker dọn room hoặc mất kết nối
+                            events = None;
+                            self.streamed_rooms.lock().await.remove(&room_id);
+                            debug!(%room_id, "room event stream ended, falling back to polling");
+                        }
+                    }
+                    continue;
+                }
+            }
+
+            // Giữ lock active_rooms khi kiểm tra room rỗng để không bỏ sót connection vừa join
+            let members = {
+                let mut active = self.active_rooms.lock().await;
+                let members = self.room_members(&room_id).await;
+                if members.is_empty() {
+                    active.remove(&room_id);
+                    self.streamed_rooms.lock().await.remove(&room_id);
+                    debug!(%room_id, "snapshot broadcast stopped, room has no ws clients");
+                    // Drop `events` huỷ stream ở worker
+                    return;
+                }
+                members
+            };
+
+            if events.is_some() && !dirty && last_pull.elapsed() < IDLE_SNAPSHOT_INTERVAL {
+                continue;
+            }
+            dirty = false;
+            last_pull = Instant::now();
+            for member in members {
+                self.push_snapshot(&room_id, member).await;
+            }
+        }
+    }
+
+    async fn subscribe(&self, room_id: &str) -> Option<Streaming<RoomEvent>> {
+        let request = StreamRoomEventsRequest { room_id: room_id.to_string() };
+        let mut client = self.worker_client.clone();
+        match tokio::time::timeout(EVENT_STREAM_CONNECT_TIMEOUT, client.stream_room_events(request)).await {
+            Ok(Ok(response)) => {
+                self.streamed_rooms.lock().await.insert(room_id.to_string());
+                Some(response.into_inner())
+            }
+            Ok(Err(status)) => {
+                debug!(%room_id, %status, "worker room event stream unavailable, polling snapshots");
+                None
+            }
+            Err(_) => {
+                debug!(%room_id, "worker room event stream timed out, polling snapshots");
+                None
+            }
+        }
+    }
+
+    /// Đẩy event của room xuống mọi connection (hoặc chỉ connection của `recipient`); event là control nên
+    /// không bị bỏ khi client chậm
+    async fn fan_out(&self, room_id: &str, recipient: Option<&str>, state: StateMessage) {
+        let members = self.room_members(room_id).await;
+        for member in members.into_iter().filter(|member| recipient.is_none_or(|id| id == member.peer_id)) {
+            let frame = member.outbound.stamp(Frame::state(0, 0, state.clone()));
+            if let Ok(bytes) = message::encode(&frame) {
+                if let Some(bandwidth) = &self.bandwidth {
+                    bandwidth.record(&member.connection_id, Direction::Sent, MessageKind::of(&frame), bytes.len() as u64);
+                }
+                member.outbox.push(OutboundKind::Control, Message::Binary(bytes));
+            }
+        }
+    }
+
+    async fn room_members(&self, room_id: &str) -> Vec<RoomMember> {
+        self.ws_registry.filter_map(|connection_id, conn| {
+            (conn.room_id == room_id).then(|| RoomMember {
+                connection_id: connection_id.to_string(),
+                peer_id: conn.peer_id.clone(),
+                outbound: conn.outbound.clone(),
+                outbox: conn.outbox.clone(),
+            })
+        })
+    }
+
+    /// Snapshot lấy theo từng connection để worker áp AOI của đúng player đó
+    async fn push_snapshot(&self, room_id: &str, member: RoomMember) {
+        let Some(frame) = self.fetch(room_id, &member.peer_id, false).await else {
+            return;
+        };
+
+        // Full snapshot là keyframe, không bị bỏ khi client chậm
+        let kind = match frame.payload {
+            FramePayload::State { message: StateMessage::Snapshot { .. } } => OutboundKind::Keyframe,
+            _ => OutboundKind::State,
+        };
+        let frame = member.outbound.stamp(frame);
+        if let Ok(bytes) = message::encode(&frame) {
+            // Outbox đã đóng thì bỏ qua, lần tick sau registry sẽ không còn connection này
+            member.outbox.push(kind, Message::Binary(bytes));
+        }
+    }
+
+    /// Xin worker một Full snapshot cho player (client resync sau khi mất delta)
+    pub async fn request_keyframe(&self, room_id: &str, player_id: &str) -> Option<Frame> {
+        self.fetch(room_id, player_id, true).await
+    }
+
+    async fn fetch(&self, room_id: &str, player_id: &str, keyframe: bool) -> Option<Frame> {
+        let request = GetPlayerSnapshotRequest {
+            room_id: room_id.to_string(),
+            player_id: player_id.to_string(),
+        };
+        let mut client = self.worker_client.clone();
+        let response = if keyframe {
+            client.request_keyframe(request).await
+        } else {
+            client.get_player_snapshot(request).await
+        };
+
+        let snapshot = match response {
+            Ok(response) => match response.into_inner() {
+                GetPlayerSnapshotResponse { ok: true, snapshot: Some(snapshot), .. } => snapshot,
+                other => {
+                    debug!(%room_id, %player_id, error = %other.error, "worker returned no snapshot");
+                    return None;
+                }
+            },
+            Err(status) => {
+                debug!(%room_id, %player_id, %status, keyframe, "worker snapshot request failed");
+                return None;
+            }
+        };
+
+        let frame = snapshot_frame(&snapshot);
+        if frame.is_none() {
+            debug!(%room_id, tick = snapshot.tick, "snapshot payload is not an encoded snapshot");
+        }
+        frame
+    }
+}
+
+/// Event kế tiếp của stream; chưa có stream (hoặc stream đã hỏng) thì chờ mãi để `select!` chỉ chạy theo ticker
+async fn next_event(events: &mut Option<Streaming<RoomEvent>>) -> Option<RoomEvent> {
+    match events {
+        Some(stream) => stream.message().await.ok().flatten(),
+        None => std::future::pending().await,
+    }
+}
+
+/// Player duy nhất được nhận event (MOTD), None là cả room
+fn event_recipient(event: &RoomEvent) -> Option<String> {
+    match &event.event {
+        Some(Event::SystemMessage(system)) if !system.recipient_id.is_empty() => Some(system.recipient_id.clone()),
+        _ => None,
+    }
+}
+
+/// `RoomEvent` của worker thành `StateMessage::Event` cho client; `snapshot_ready` chỉ báo gateway pull snapshot
+fn room_event_message(event: RoomEvent) -> Option<StateMessage> {
+    let (name, data) = match event.event? {
+        Event::SnapshotReady(_) => return None,
+        Event::PlayerJoined(joined) => (
+            "player_joined",
+            serde_json::json!({ "player_id": joined.player_id, "resumed": joined.resumed }),
+        ),
+        Event::PlayerLeft(left) => (
+            "player_left",
+            serde_json::json!({ "player_id": left.player_id, "reason": left.reason }),
+        ),
+        Event::Chat(chat) => (
+            "chat",
+            serde_json::json!({
+                "player_id": chat.player_id,
+                "player_name": chat.player_name,
+                "message": chat.message,
+                "timestamp_ms": chat.timestamp_ms
+            }),
+        ),
+        Event::SystemMessage(system) => {
+            let params: std::collections::BTreeMap<_, _> = system.params.into_iter().collect();
+            ("system_message", serde_json::json!({ "key": system.key, "params": params }))
+        }
+        Event::MatchEnded(ended) => {
+            let scoreboard: Vec<_> = ended
+                .scoreboard
+                .iter()
+                .map(|entry| {
+                    serde_json::json!({ "player_id": entry.player_id, "score": entry.score, "placement": entry.placement })
+                })
+                .collect();
+            ("match_ended", serde_json::json!({ "reason": ended.reason, "scoreboard": scoreboard }))
+        }
+    };
+    Some(StateMessage::Event { name: name.to_string(), data })
+}
+
+/// Frame State cho snapshot của worker, timestamp là `server_time_ms` lúc worker tạo snapshot
+/// (0 với worker cũ, `OutboundSequence::stamp` sẽ điền giờ gateway)
+pub fn snapshot_frame(snapshot: &Snapshot) -> Option<Frame> {
+    let (server_time_ms, state) = decode_snapshot(snapshot)?;
+    Some(Frame::state(0, server_time_ms, state))
+}
+
+/// Đổi `EncodedSnapshot` JSON của worker (`{"Full": ..}` / `{"Delta": ..}`) sang `StateMessage`
+pub fn state_message_from_snapshot(snapshot: &Snapshot) -> Option<StateMessage> {
+    decode_snapshot(snapshot).map(|(_, state)| state)
+}
+
+fn decode_snapshot(snapshot: &Snapshot) -> Option<(u64, StateMessage)> {
+    let payload: serde_json::Value = serde_json::from_str(&snapshot.payload_json).ok()?;
+    let timing = |encoded: &serde_json::Value| {
+        (
+            encoded.get("server_time_ms").and_then(|v| v.as_u64()).unwrap_or(0),
+            encoded.get("tick_duration_ms").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32,
+            encoded.get("last_acked_sequence").and_then(|v| v.as_u64()).map(|sequence| sequence as u32),
+        )
+    };
+
+    if let Some(full) = payload.get("Full") {
+        let entities = full
+            .get("entities")?
+            .as_array()?
+            .iter()
+            .map(|entity| EntitySnapshot {
+                id: entity_id(entity),
+                components: entity.clone(),
+            })
+            .collect();
+        let (server_time_ms, tick_duration_ms, last_acked_sequence) = timing(full);
+        return Some((
+            server_time_ms,
+            StateMessage::Snapshot { tick: snapshot.tick, entities, tick_duration_ms, last_acked_sequence },
+        ));
+    }
+
+    let delta = payload.get("Delta")?;
+    let mut changes: Vec<EntityDelta> = ["created_entities", "updated_entities"]
+        .iter()
+        .filter_map(|key| delta.get(*key).and_then(|v| v.as_array()))
+        .flatten()
+        .map(|entity| EntityDelta {
+            id: entity_id(entity),
+            changes: entity.clone(),
+        })
+        .collect();
+    if let Some(deleted) = delta.get("deleted_entities").and_then(|v| v.as_array()) {
+        changes.extend(deleted.iter().map(|id| EntityDelta {
+            id: id.to_string(),
+            changes: serde_json::json!({ "deleted": true }),
+        }));
+    }
+    let (server_time_ms, tick_duration_ms, last_acked_sequence) = timing(delta);
+    Some((
+        server_time_ms,
+        StateMessage::Delta { tick: snapshot.tick, changes, tick_duration_ms, last_acked_sequence },
+    ))
+}
+
+fn entity_id(entity: &serde_json::Value) -> String {
+    entity.get("id").map(|id| id.to_string()).unwrap_or_default()
+}
@@ -6,10 +6,6 @@ use axum::{
 use common_net::message::Channel;
 use dashmap::DashMap;
 use futures::{SinkExt, StreamExt};
-use once_cell::sync::Lazy;
-use prometheus::{
-    register_int_counter, register_int_gauge, IntCounter, IntGauge,
-};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
@@ -208,23 +204,6 @@ impl Transport for QuicTransport {
 */
 
 
-static STATE_BUFFER_DROPPED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
-    register_int_counter!(
-        "gateway_state_dropped_total",
-        "So goi state bi drop do backpressure"
-    )
-    .expect("register gateway_state_dropped_total")
-});
-
-static STATE_BUFFER_DEPTH: Lazy<IntGauge> = Lazy::new(|| {
-    register_int_gauge!(
-        "gateway_state_buffer_depth",
-        "Do sau buffer state hien tai"
-    )
-    .expect("register gateway_state_buffer_depth")
-});
-
-
 pub fn timestamp_ms() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
@@ -18,5 +18,491 @@ pub struct SignalingSession {
     pub peer_user_id: Option<String>,
     pub status: String,
     pub created_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
     pub transport_type: String,
 }
+
+/// Giới hạn độ dài cho room_id / player_id
+pub const MAX_ID_LEN: usize = 64;
+/// Giới hạn độ dài cho tên hiển thị của player
+pub const MAX_NAME_LEN: usize = 32;
+/// Invite code người chơi gõ tay, có thể kèm khoảng trắng/gạch nối
+pub const MAX_INVITE_CODE_LEN: usize = 16;
+/// Lý do kick host/admin nhập, gửi nguyên văn xuống client
+pub const MAX_KICK_REASON_LEN: usize = 200;
+/// Tên giải đấu hiện trong lobby và tên phòng của từng trận
+pub const MAX_TOURNAMENT_NAME_LEN: usize = 64;
+/// Announcement admin gửi, worker cũng giới hạn đúng số ký tự này
+pub const MAX_ANNOUNCEMENT_LEN: usize = 512;
+/// Số người tối đa một giải loại trực tiếp
+pub const MAX_TOURNAMENT_PARTICIPANTS: u32 = 128;
+
+/// Lỗi validate cho một field cụ thể, trả về client trong body 422
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Map lỗi deserialize của serde (missing/unknown field, game mode lạ) sang FieldError.
+    pub fn from_serde_message(message: &str) -> Self {
+        if let Some(start) = message.find("unknown game mode") {
+            let reason = &message[start..];
+            let reason = reason.find(" at line").map_or(reason, |end| &reason[..end]);
+            return Self::new("game_mode", reason);
+        }
+        for marker in ["missing field `", "unknown field `"] {
+            if let Some(start) = message.find(marker) {
+                let rest = &message[start + marker.len()..];
+                if let Some(end) = rest.find('`') {
+                    let field = &rest[..end];
+                    let reason = if marker.starts_with("missing") {
+                        "is required".to_string()
+                    } else {
+                        "is not allowed".to_string()
+                    };
+                    return Self::new(field, reason);
+                }
+            }
+        }
+        Self::new("body", message)
+    }
+}
+
+fn check_len(errors: &mut Vec<FieldError>, field: &str, value: &str, max: usize) {
+    if value.trim().is_empty() {
+        errors.push(FieldError::new(field, "must not be empty"));
+    } else if value.chars().count() > max {
+        errors.push(FieldError::new(field, format!("must be at most {} characters", max)));
+    }
+}
+
+fn into_result(errors: Vec<FieldError>) -> Result<(), Vec<FieldError>> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Body cho POST /game/join
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GameJoinRequest {
+    pub room_id: String,
+    pub player_id: String,
+}
+
+impl GameJoinRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "room_id", &self.room_id, MAX_ID_LEN);
+        check_len(&mut errors, "player_id", &self.player_id, MAX_ID_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /game/leave
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GameLeaveRequest {
+    pub room_id: String,
+    pub player_id: String,
+}
+
+impl GameLeaveRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "room_id", &self.room_id, MAX_ID_LEN);
+        check_len(&mut errors, "player_id", &self.player_id, MAX_ID_LEN);
+        into_result(errors)
+    }
+}
+
+/// Input của player trong POST /game/input
+#[derive(Debug, Clone, Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlayerInputPayload {
+    pub movement: [f32; 3], // x, y, z movement
+    pub timestamp: u64,     // client timestamp (ms)
+    #[serde(default)]
+    pub actions: InputActionsPayload,
+}
+
+/// Nút hành động kèm theo input (khớp với `InputActions` bên worker)
+#[derive(Debug, Clone, Default, Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputActionsPayload {
+    pub jump: bool,
+    pub slide: bool,
+    pub use_item: Option<String>,
+}
+
+/// Body cho POST /game/input
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GameInputRequest {
+    pub room_id: String,
+    pub player_id: String,
+    pub sequence: u32,
+    pub input: PlayerInputPayload,
+}
+
+impl GameInputRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "room_id", &self.room_id, MAX_ID_LEN);
+        check_len(&mut errors, "player_id", &self.player_id, MAX_ID_LEN);
+        if self.input.movement.iter().any(|v| !v.is_finite()) {
+            errors.push(FieldError::new("input.movement", "must contain finite numbers"));
+        }
+        if let Some(item) = &self.input.actions.use_item {
+            check_len(&mut errors, "input.actions.use_item", item, MAX_ID_LEN);
+        }
+        into_result(errors)
+    }
+
+    /// JSON payload gửi sang worker (khớp với `PlayerInput` bên worker)
+    pub fn worker_payload_json(&self) -> String {
+        serde_json::json!({
+            "player_id": self.player_id,
+            "input_sequence": self.sequence,
+            "movement": self.input.movement,
+            "timestamp": self.input.timestamp,
+            "actions": self.input.actions,
+        })
+        .to_string()
+    }
+}
+
+/// Body cho POST /rooms/assign
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssignRoomBody {
+    pub player_id: String,
+    #[serde(default)]
+    pub game_mode: Option<room_manager::GameMode>,
+}
+
+impl AssignRoomBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "player_id", &self.player_id, MAX_ID_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /rooms/join
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JoinRoomBody {
+    pub room_id: String,
+    pub player_id: String,
+    #[serde(default)]
+    pub player_name: Option<String>,
+    /// Bắt buộc khi phòng là private
+    #[serde(default)]
+    pub invite_code: Option<String>,
+}
+
+impl JoinRoomBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "room_id", &self.room_id, MAX_ID_LEN);
+        check_len(&mut errors, "player_id", &self.player_id, MAX_ID_LEN);
+        if let Some(name) = &self.player_name {
+            check_len(&mut errors, "player_name", name, MAX_NAME_LEN);
+        }
+        if let Some(code) = &self.invite_code {
+            check_len(&mut errors, "invite_code", code, MAX_INVITE_CODE_LEN);
+        }
+        into_result(errors)
+    }
+
+    /// Tên hiển thị, mặc định là `Player_<8 ký tự đầu của player_id>`
+    pub fn display_name(&self) -> String {
+        match &self.player_name {
+            Some(name) => name.clone(),
+            None => format!("Player_{}", self.player_id.chars().take(8).collect::<String>()),
+        }
+    }
+}
+
+/// Body cho POST /rooms/leave
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaveRoomBody {
+    pub room_id: String,
+    pub player_id: String,
+}
+
+impl LeaveRoomBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "room_id", &self.room_id, MAX_ID_LEN);
+        check_len(&mut errors, "player_id", &self.player_id, MAX_ID_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /rooms/resolve_invite
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResolveInviteBody {
+    pub code: String,
+}
+
+impl ResolveInviteBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "code", &self.code, MAX_INVITE_CODE_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST/DELETE /rooms/invite, `player_id` phải là host của phòng
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoomInviteBody {
+    pub room_id: String,
+    pub player_id: String,
+}
+
+impl RoomInviteBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "room_id", &self.room_id, MAX_ID_LEN);
+        check_len(&mut errors, "player_id", &self.player_id, MAX_ID_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /rooms/ready; player lấy từ JWT
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetReadyBody {
+    pub room_id: String,
+    pub ready: bool,
+}
+
+impl SetReadyBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "room_id", &self.room_id, MAX_ID_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /rooms/kick; người gọi lấy từ JWT, phải là host hoặc admin
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KickPlayerBody {
+    pub room_id: String,
+    pub target_player_id: String,
+    #[serde(default)]
+    pub ban: bool,
+    /// Lý do hiện cho player bị kick
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl KickPlayerBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "room_id", &self.room_id, MAX_ID_LEN);
+        check_len(&mut errors, "target_player_id", &self.target_player_id, MAX_ID_LEN);
+        if let Some(reason) = &self.reason {
+            check_len(&mut errors, "reason", reason, MAX_KICK_REASON_LEN);
+        }
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /admin/announce; không có room_id thì gửi tới mọi room
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnnounceBody {
+    #[serde(default)]
+    pub room_id: Option<String>,
+    pub message: String,
+}
+
+impl AnnounceBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if let Some(room_id) = &self.room_id {
+            check_len(&mut errors, "room_id", room_id, MAX_ID_LEN);
+        }
+        check_len(&mut errors, "message", &self.message, MAX_ANNOUNCEMENT_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /tournaments/create; người tạo (host) lấy từ JWT
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateTournamentBody {
+    pub name: String,
+    pub game_mode: room_manager::GameMode,
+    pub max_participants: u32,
+    #[serde(default)]
+    pub format: Option<common_net::matchmaking::TournamentFormat>,
+}
+
+impl CreateTournamentBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "name", &self.name, MAX_TOURNAMENT_NAME_LEN);
+        if !(2..=MAX_TOURNAMENT_PARTICIPANTS).contains(&self.max_participants) {
+            errors.push(FieldError::new(
+                "max_participants",
+                format!("must be between 2 and {MAX_TOURNAMENT_PARTICIPANTS}"),
+            ));
+        }
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /tournaments/register và /tournaments/start; player lấy từ JWT
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TournamentBody {
+    pub tournament_id: String,
+}
+
+impl TournamentBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "tournament_id", &self.tournament_id, MAX_ID_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /tournaments/report; người gọi phải là host của giải hoặc admin
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReportMatchBody {
+    pub tournament_id: String,
+    pub match_id: String,
+    pub winner_player_id: String,
+}
+
+impl ReportMatchBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "tournament_id", &self.tournament_id, MAX_ID_LEN);
+        // match_id = "{tournament_id}-r{round}-m{index}"
+        check_len(&mut errors, "match_id", &self.match_id, MAX_ID_LEN * 2);
+        check_len(&mut errors, "winner_player_id", &self.winner_player_id, MAX_ID_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /party/invite; leader lấy từ JWT
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartyInviteBody {
+    pub party_id: String,
+}
+
+impl PartyInviteBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "party_id", &self.party_id, MAX_ID_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /party/join
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JoinPartyBody {
+    pub invite_code: String,
+}
+
+impl JoinPartyBody {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        check_len(&mut errors, "invite_code", &self.invite_code, MAX_INVITE_CODE_LEN);
+        into_result(errors)
+    }
+}
+
+/// Body cho POST /party/queue; chỉ leader của party gọi được
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueuePartyBody {
+    #[serde(default)]
+    pub game_mode: Option<room_manager::GameMode>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_player_id_is_reported_as_field_error() {
+        let err = serde_json::from_str::<GameJoinRequest>(r#"{"room_id":"room-1"}"#).unwrap_err();
+        let field_error = FieldError::from_serde_message(&err.to_string());
+        assert_eq!(field_error.field, "player_id");
+        assert_eq!(field_error.message, "is required");
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let err = serde_json::from_str::<GameLeaveRequest>(
+            r#"{"room_id":"room-1","player_id":"p1","extra":true}"#,
+        )
+        .unwrap_err();
+        assert_eq!(FieldError::from_serde_message(&err.to_string()).field, "extra");
+    }
+
+    #[test]
+    fn oversized_names_fail_validation() {
+        let body = JoinRoomBody {
+            room_id: "room-1".to_string(),
+            player_id: "x".repeat(MAX_ID_LEN + 1),
+            player_name: Some("n".repeat(MAX_NAME_LEN + 1)),
+            invite_code: None,
+        };
+        let errors = body.validate().unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["player_id", "player_name"]);
+    }
+
+    #[test]
+    fn input_payload_maps_to_worker_format() {
+        let req: GameInputRequest = serde_json::from_str(
+            r#"{"room_id":"r","player_id":"p","sequence":3,"input":{"movement":[1.0,0.0,0.0],"timestamp":42}}"#,
+        )
+        .unwrap();
+        assert!(req.validate().is_ok());
+
+        let payload: serde_json::Value = serde_json::from_str(&req.worker_payload_json()).unwrap();
+        assert_eq!(payload["input_sequence"], 3);
+        assert_eq!(payload["player_id"], "p");
+        assert_eq!(payload["actions"]["jump"], false);
+    }
+
+    #[test]
+    fn input_actions_are_passed_through() {
+        let req: GameInputRequest = serde_json::from_str(
+            r#"{"room_id":"r","player_id":"p","sequence":4,"input":{"movement":[0.0,0.0,0.0],"timestamp":42,"actions":{"jump":true,"use_item":"shield"}}}"#,
+        )
+        .unwrap();
+        assert!(req.validate().is_ok());
+
+        let payload: serde_json::Value = serde_json::from_str(&req.worker_payload_json()).unwrap();
+        assert_eq!(payload["actions"]["jump"], true);
+        assert_eq!(payload["actions"]["slide"], false);
+        assert_eq!(payload["actions"]["use_item"], "shield");
+    }
+}
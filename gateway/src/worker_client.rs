@@ -0,0 +1,116 @@
+//! Client gRPC tới worker, gắn request id của request HTTP hiện tại và token nội bộ vào metadata mỗi lời gọi.
+//! Kênh dùng TLS (mTLS khi có cert/key của gateway) nếu cấu hình `worker_tls`.
+
+use std::path::PathBuf;
+
+use common_net::telemetry::REQUEST_ID_HEADER;
+use proto::{auth::AttachInternalToken, worker::v1::worker_client::WorkerClient};
+use tonic::{
+    metadata::MetadataValue,
+    service::interceptor::InterceptedService,
+    transport::{Channel, Endpoint},
+    Code, Request, Status,
+};
+
+use crate::{request_id, BoxError};
+
+pub type WorkerRpcClient = WorkerClient<InterceptedService<Channel, WorkerInterceptor>>;
+
+/// Token lấy từ WORKER_RPC_TOKEN; chưa đặt thì gọi không kèm token (worker dev trên loopback)
+pub fn new(channel: Channel) -> WorkerRpcClient {
+    with_token(channel, proto::auth::internal_token_from_env().as_deref())
+}
+
+pub fn with_token(channel: Channel, token: Option<&str>) -> WorkerRpcClient {
+    WorkerClient::with_interceptor(channel, WorkerInterceptor { token: AttachInternalToken::optional(token) })
+}
+
+/// Lời gọi ngoài request HTTP (snapshot, latency, WS) không có id thì để worker tự sinh
+#[derive(Debug, Clone)]
+pub struct WorkerInterceptor {
+    token: AttachInternalToken,
+}
+
+impl tonic::service::Interceptor for WorkerInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(value) = request_id::current().and_then(|id| MetadataValue::try_from(id).ok()) {
+            request.metadata_mut().insert(REQUEST_ID_HEADER, value);
+        }
+        self.token.apply(&mut request);
+        Ok(request)
+    }
+}
+
+/// TLS tới worker: `ca_path` xác thực cert của worker, `cert_path` + `key_path` là cert client cho mTLS
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WorkerTlsSettings {
+    pub ca_path: PathBuf,
+    #[serde(default)]
+    pub cert_path: Option<PathBuf>,
+    #[serde(default)]
+    pub key_path: Option<PathBuf>,
+    /// Tên trong cert của worker khi khác host của `worker_endpoint`
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+impl WorkerTlsSettings {
+    /// GATEWAY_WORKER_TLS_CA, GATEWAY_WORKER_TLS_CERT, GATEWAY_WORKER_TLS_KEY, GATEWAY_WORKER_TLS_DOMAIN; không có CA thì None
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Some(Self {
+            ca_path: var("GATEWAY_WORKER_TLS_CA")?.into(),
+            cert_path: var("GATEWAY_WORKER_TLS_CERT").map(PathBuf::from),
+            key_path: var("GATEWAY_WORKER_TLS_KEY").map(PathBuf::from),
+            domain: var("GATEWAY_WORKER_TLS_DOMAIN"),
+        })
+    }
+}
+
+/// Endpoint lazy tới worker; có `tls` thì endpoint phải là https:// vì tonic bỏ qua TLS với http://
+pub fn endpoint(url: &str, tls: Option<&WorkerTlsSettings>) -> Result<Endpoint, BoxError> {
+    let endpoint = Endpoint::from_shared(url.to_string())?;
+    let Some(tls) = tls else {
+        return Ok(endpoint);
+    };
+    if endpoint.uri().scheme_str() != Some("https") {
+        return Err(format!("worker endpoint {url} must use https:// when worker TLS is configured").into());
+    }
+    #[cfg(feature = "tls")]
+    {
+        use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+
+        let read = |path: &std::path::Path| std::fs::read(path).map_err(|err| format!("cannot read {}: {err}", path.display()));
+        let mut config = ClientTlsConfig::new().ca_certificate(Certificate::from_pem(read(&tls.ca_path)?));
+        match (&tls.cert_path, &tls.key_path) {
+            (Some(cert), Some(key)) => config = config.identity(Identity::from_pem(read(cert)?, read(key)?)),
+            (None, None) => {}
+            _ => return Err("worker TLS client certificate needs both cert_path and key_path".into()),
+        }
+        if let Some(domain) = &tls.domain {
+            config = config.domain_name(domain.clone());
+        }
+        Ok(endpoint.tls_config(config)?)
+    }
+    #[cfg(not(feature = "tls"))]
+    {
+        let _ = tls;
+        Err("gateway was built without the `tls` feature, worker TLS cannot be used".into())
+    }
+}
+
+/// Worker từ chối gateway vì token sai hoặc TLS/mTLS không khớp: lỗi cấu hình, khác với worker không tới được
+pub fn is_auth_failure(status: &Status) -> bool {
+    if matches!(status.code(), Code::Unauthenticated | Code::PermissionDenied) {
+        return true;
+    }
+    // Handshake TLS lỗi (cert không tin được, worker đòi cert client) tới từ tokio-rustls dưới dạng io::Error InvalidData
+    let mut source = std::error::Error::source(status);
+    while let Some(err) = source {
+        if err.downcast_ref::<std::io::Error>().is_some_and(|io| io.kind() == std::io::ErrorKind::InvalidData) {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
@@ -7,7 +7,12 @@ use reqwest::StatusCode;
 use tokio::{sync::oneshot, task::JoinHandle};
 use worker::rpc;
 
-use gateway::build_router;
+use gateway::{
+    auth::{AuthService, User},
+    build_app_state, build_router_with_state,
+    room_client::RoomManagerClient,
+    AppState,
+};
 
 type BoxError = common_net::metrics::BoxError;
 
@@ -17,13 +22,29 @@ async fn spawn_gateway() -> Result<
         oneshot::Sender<()>,
         JoinHandle<()>,
         JoinHandle<()>,
+        AuthService,
+    ),
+    BoxError,
+> {
+    spawn_gateway_with(|state| state).await
+}
+
+async fn spawn_gateway_with(configure: impl FnOnce(AppState) -> AppState) -> Result<
+    (
+        SocketAddr,
+        oneshot::Sender<()>,
+        JoinHandle<()>,
+        JoinHandle<()>,
+        AuthService,
     ),
     BoxError,
 > {
     telemetry::init("gateway-test");
 
     let (worker_endpoint, worker_handle) = rpc::spawn_test_server().await;
-    let app = build_router(&worker_endpoint)?;
+    let state = configure(build_app_state(worker_endpoint).await);
+    let auth_service = state.auth_service.clone();
+    let app = build_router_with_state(state);
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
     let addr = listener.local_addr()?;
@@ -44,12 +65,56 @@ async fn spawn_gateway() -> Result<
         }
     });
 
-    Ok((addr, shutdown_tx, server, worker_handle))
+    Ok((addr, shutdown_tx, server, worker_handle, auth_service))
+}
+
+const ROOM_MANAGER_SECRET: &str = "test-internal-secret";
+
+/// Chạy REST API của room-manager trên port ngẫu nhiên, trả về base URL
+async fn spawn_room_manager(pocketbase_url: &str) -> Result<(String, JoinHandle<()>), BoxError> {
+    let state = room_manager::RoomManagerState::new(pocketbase_url)?;
+    let app = room_manager::api::router(
+        std::sync::Arc::new(tokio::sync::RwLock::new(state)),
+        ROOM_MANAGER_SECRET,
+    );
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let handle = tokio::spawn(async move {
+        if let Err(err) = axum::Server::from_tcp(listener).expect("server").serve(app.into_make_service()).await {
+            tracing::error!(%err, "room-manager test server failed");
+        }
+    });
+    Ok((format!("http://{addr}"), handle))
+}
+
+/// PocketBase giả cho room-manager: lưu record nào cũng thành công
+async fn spawn_mock_pocketbase() -> String {
+    async fn create_record(axum::Json(mut record): axum::Json<serde_json::Value>) -> axum::Json<serde_json::Value> {
+        record["created"] = serde_json::json!("");
+        record["updated"] = serde_json::json!("");
+        axum::Json(record)
+    }
+
+    let app = axum::Router::new().route("/api/collections/:collection/records", axum::routing::post(create_record));
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(axum::Server::from_tcp(listener).expect("server").serve(app.into_make_service()));
+    format!("http://{addr}")
+}
+
+fn bearer(auth_service: &AuthService, user_id: &str) -> String {
+    let user = User {
+        id: user_id.to_string(),
+        username: user_id.to_string(),
+        email: format!("{user_id}@example.com"),
+        role: "user".to_string(),
+    };
+    format!("Bearer {}", auth_service.generate_token(&user).expect("token"))
 }
 
 #[tokio::test]
 async fn http_endpoints_work() -> Result<(), BoxError> {
-    let (addr, shutdown_tx, server, worker_handle) = spawn_gateway().await?;
+    let (addr, shutdown_tx, server, worker_handle, _auth) = spawn_gateway().await?;
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(2))
         .build()?;
@@ -57,6 +122,12 @@ async fn http_endpoints_work() -> Result<(), BoxError> {
 
     let health = client.get(format!("{base}/healthz")).send().await?;
     assert_eq!(StatusCode::OK, health.status());
+    // allowed_origins mặc định là "*"
+    assert_eq!(
+        Some("*"),
+        health.headers().get("access-control-allow-origin").and_then(|v| v.to_str().ok())
+    );
+    assert!(health.headers().contains_key("access-control-allow-methods"));
 
     let version_resp = client.get(format!("{base}/version")).send().await?;
     assert_eq!(StatusCode::OK, version_resp.status());
@@ -75,41 +146,567 @@ async fn http_endpoints_work() -> Result<(), BoxError> {
     Ok(())
 }
 
+#[tokio::test]
+async fn metrics_expose_input_latency_and_auth_counters() -> Result<(), BoxError> {
+    let (addr, shutdown_tx, server, worker_handle, _auth) = spawn_gateway().await?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()?;
+    let base = format!("http://{}", addr);
+
+    let input = client
+        .post(format!("{base}/game/input"))
+        .json(&serde_json::json!({
+            "room_id": "metrics-room",
+            "player_id": "metrics-player",
+            "sequence": 1,
+            "input": { "movement": [1.0, 0.0, 0.0], "timestamp": 0 }
+        }))
+        .send()
+        .await?;
+    // Room chưa tồn tại nên worker từ chối input, nhưng lần push vẫn được đo
+    assert_eq!(StatusCode::CONFLICT, input.status());
+
+    let metrics_text = client.get(format!("{base}/metrics")).send().await?.text().await?;
+    assert!(metrics_text.contains("gw_inputs_push_ms_bucket"), "{metrics_text}");
+    assert!(metrics_text.contains("gw_inputs_push_ms_count"));
+    assert!(metrics_text.contains("gw_auth_login_success"));
+    assert!(metrics_text.contains("gw_ws_auth_failed"));
+    assert!(metrics_text.contains("path=\"/game/input\""));
+
+    shutdown_tx.send(()).ok();
+    let _ = server.await;
+    worker_handle.abort();
+    let _ = worker_handle.await;
+    Ok(())
+}
+
 #[tokio::test]
 async fn signaling_end_to_end() -> Result<(), BoxError> {
-    let (addr, shutdown_tx, server, worker_handle) = spawn_gateway().await?;
+    let (addr, shutdown_tx, server, worker_handle, auth) = spawn_gateway().await?;
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(2))
         .build()?;
     let base = format!("http://{}", addr);
     let room_id = "room-signaling-test";
+    let peer1 = bearer(&auth, "peer1");
+    let peer2 = bearer(&auth, "peer2");
 
-    // Peer 1 gửi offer
-    let offer = "sdp-offer-peer1";
+    // SDP không hợp lệ bị từ chối
+    let bad_offer = serde_json::json!({"sdp": "sdp-offer-peer1", "room_id": room_id, "peer_id": "peer1"});
+    let bad_resp = client.post(format!("{base}/rtc/offer")).header("authorization", &peer1).json(&bad_offer).send().await?;
+    assert_eq!(StatusCode::BAD_REQUEST, bad_resp.status());
+
+    // Peer 1 gửi offer thật, gateway trả answer của peer connection phía server
+    let negotiation = common_net::transport::WebRtcNegotiation::new(&[]).await?;
+    let offer = negotiation.create_offer().await?;
     let offer_req = serde_json::json!({"sdp": offer, "room_id": room_id, "peer_id": "peer1"});
-    let offer_resp = client.post(format!("{base}/rtc/offer")).json(&offer_req).send().await?;
+    let offer_resp = client.post(format!("{base}/rtc/offer")).header("authorization", &peer1).json(&offer_req).send().await?;
     assert_eq!(StatusCode::OK, offer_resp.status());
     let offer_body: serde_json::Value = offer_resp.json().await?;
-    assert_eq!(offer, offer_body["sdp"]);
+    let answer_sdp = offer_body["sdp"].as_str().expect("answer sdp");
+    assert!(answer_sdp.starts_with("v=0") && answer_sdp != offer);
+    let session_id = offer_body["session_id"].as_str().expect("session_id").to_string();
+    negotiation.close().await;
+
+    // Candidate sai định dạng bị từ chối
+    let bad_ice = serde_json::json!({
+        "candidate": "ice-candidate-peer1",
+        "sdp_mid": "0",
+        "sdp_mline_index": 0,
+        "room_id": room_id,
+        "peer_id": "peer1"
+    });
+    let bad_resp = client.post(format!("{base}/rtc/ice")).header("authorization", &peer1).json(&bad_ice).send().await?;
+    assert_eq!(StatusCode::BAD_REQUEST, bad_resp.status());
 
     // Peer 1 gửi ICE
     let ice = serde_json::json!({
-        "candidate": "ice-candidate-peer1",
+        "candidate": "candidate:1 1 udp 2130706431 192.0.2.2 50000 typ host",
         "sdp_mid": "0",
         "sdp_mline_index": 0,
         "room_id": room_id,
         "peer_id": "peer1"
     });
-    let ice_resp = client.post(format!("{base}/rtc/ice")).json(&ice).send().await?;
+    let ice_resp = client.post(format!("{base}/rtc/ice")).header("authorization", &peer1).json(&ice).send().await?;
     assert_eq!(StatusCode::OK, ice_resp.status());
 
-    // Kiểm tra rằng offer và ICE đã được lưu vào state (không test GET endpoint vì đã bị loại bỏ)
-    // Có thể thêm lại GET endpoint sau khi fix lỗi handler với axum 0.6
-    tracing::info!("Signaling data đã được lưu vào state thành công");
+    // Peer 2 trả lời offer của peer 1
+    let answer = serde_json::json!({
+        "sdp": "sdp-answer-peer2",
+        "session_id": session_id,
+        "room_id": room_id,
+        "peer_id": "peer2",
+        "target_peer_id": "peer1"
+    });
+    let answer_resp = client.post(format!("{base}/rtc/answer")).header("authorization", &peer2).json(&answer).send().await?;
+    assert_eq!(StatusCode::OK, answer_resp.status());
+    let answer_body: serde_json::Value = answer_resp.json().await?;
+    assert_eq!(true, answer_body["success"]);
+
+    // Session chỉ hiện với owner
+    let sessions: serde_json::Value = client.get(format!("{base}/rtc/sessions")).header("authorization", &peer1).send().await?.json().await?;
+    assert_eq!(1, sessions["total"]);
+    assert_eq!("Connected", sessions["sessions"][0]["status"]);
+    let others: serde_json::Value = client.get(format!("{base}/rtc/sessions")).header("authorization", &peer2).send().await?.json().await?;
+    assert_eq!(0, others["total"]);
+
+    // Peer 2 không đóng được session của peer 1
+    let close_other = client.delete(format!("{base}/rtc/sessions/{session_id}")).header("authorization", &peer2).send().await?;
+    assert_eq!(StatusCode::NOT_FOUND, close_other.status());
+    let close_resp = client.delete(format!("{base}/rtc/sessions/{session_id}")).header("authorization", &peer1).send().await?;
+    assert_eq!(StatusCode::OK, close_resp.status());
+
+    shutdown_tx.send(()).ok();
+    let _ = server.await;
+    worker_handle.abort();
+    let _ = worker_handle.await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn rtc_and_chat_routes_require_token() -> Result<(), BoxError> {
+    let (addr, shutdown_tx, server, worker_handle, auth) = spawn_gateway().await?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()?;
+    let base = format!("http://{}", addr);
+
+    let offer_req = serde_json::json!({"sdp": "sdp", "room_id": "room", "peer_id": "peer1"});
+    let resp = client.post(format!("{base}/rtc/offer")).json(&offer_req).send().await?;
+    assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
+
+    // peer_id khác với user trong token
+    let resp = client.post(format!("{base}/rtc/offer")).header("authorization", bearer(&auth, "peer2")).json(&offer_req).send().await?;
+    assert_eq!(StatusCode::FORBIDDEN, resp.status());
+
+    let resp = client.get(format!("{base}/rtc/sessions")).send().await?;
+    assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
+
+    let chat_req = serde_json::json!({"room_id": "room", "message": "hi", "message_type": "global", "target_player_id": null});
+    let resp = client.post(format!("{base}/chat/send")).json(&chat_req).send().await?;
+    assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
+
+    shutdown_tx.send(()).ok();
+    let _ = server.await;
+    worker_handle.abort();
+    let _ = worker_handle.await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn chat_routes_work() -> Result<(), BoxError> {
+    let (addr, shutdown_tx, server, worker_handle, auth) = spawn_gateway().await?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()?;
+    let base = format!("http://{}", addr);
+    let token = bearer(&auth, "chat-user");
+
+    let chat_req = serde_json::json!({"room_id": "room", "message": "hi", "message_type": "global", "target_player_id": null});
+    let resp = client.post(format!("{base}/chat/send")).header("authorization", &token).json(&chat_req).send().await?;
+    assert_eq!(StatusCode::OK, resp.status());
+    let body: serde_json::Value = resp.json().await?;
+    assert_eq!(true, body["success"]);
+    assert!(body["message_id"].is_string());
+
+    let history_req = serde_json::json!({"room_id": "room", "count": 10});
+    let resp = client.post(format!("{base}/chat/history")).header("authorization", &token).json(&history_req).send().await?;
+    assert_eq!(StatusCode::OK, resp.status());
+    let body: serde_json::Value = resp.json().await?;
+    assert!(body["messages"].is_array());
+
+    shutdown_tx.send(()).ok();
+    let _ = server.await;
+    worker_handle.abort();
+    let _ = worker_handle.await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn announcements_require_the_admin_role() -> Result<(), BoxError> {
+    // build_app_state để worker client giả, announce cần worker thật
+    let (worker_endpoint, announce_worker) = rpc::spawn_test_server().await;
+    let channel = rpc::channel(&worker_endpoint)?;
+    let (addr, shutdown_tx, server, worker_handle, auth) = spawn_gateway_with(move |mut state| {
+        state.worker_client = gateway::worker_client::new(channel);
+        state
+    })
+    .await?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()?;
+    let url = format!("http://{addr}{}", gateway::ADMIN_ANNOUNCE_PATH);
+    let admin = User {
+        id: "ops".to_string(),
+        username: "ops".to_string(),
+        email: "ops@example.com".to_string(),
+        role: gateway::auth::ADMIN_ROLE.to_string(),
+    };
+    let admin_token = format!("Bearer {}", auth.generate_token(&admin).expect("token"));
+    let announcement = serde_json::json!({ "message": "maintenance at 02:00" });
+
+    let resp = client.post(&url).json(&announcement).send().await?;
+    assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
+    let resp = client.post(&url).header("authorization", bearer(&auth, "player")).json(&announcement).send().await?;
+    assert_eq!(StatusCode::FORBIDDEN, resp.status());
+
+    let resp = client.post(&url).header("authorization", &admin_token).json(&announcement).send().await?;
+    assert_eq!(StatusCode::OK, resp.status());
+    let body: serde_json::Value = resp.json().await?;
+    assert_eq!(body["success"], true);
+
+    let to_missing_room = serde_json::json!({ "room_id": "missing", "message": "hello" });
+    let resp = client.post(&url).header("authorization", &admin_token).json(&to_missing_room).send().await?;
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+    shutdown_tx.send(()).ok();
+    let _ = server.await;
+    worker_handle.abort();
+    announce_worker.abort();
+    let _ = worker_handle.await;
+    Ok(())
+}
+
+/// Cần PocketBase thật (có users collection): POCKETBASE_TEST_URL=http://127.0.0.1:8090
+#[tokio::test]
+async fn pocketbase_register_login_create_room() -> Result<(), BoxError> {
+    let Ok(pocketbase_url) = std::env::var("POCKETBASE_TEST_URL") else {
+        eprintln!("POCKETBASE_TEST_URL not set, skipping PocketBase auth flow");
+        return Ok(());
+    };
+
+    let (room_manager_url, room_manager) = spawn_room_manager(&pocketbase_url).await?;
+    let (addr, shutdown_tx, server, worker_handle, _auth) = spawn_gateway_with(|mut state| {
+        state.auth_service = state.auth_service.clone().with_pocketbase_url(pocketbase_url.clone());
+        state.room_manager = RoomManagerClient::new(room_manager_url, ROOM_MANAGER_SECRET);
+        state
+    })
+    .await?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()?;
+    let base = format!("http://{}", addr);
+
+    let nonce = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_millis();
+    let email = format!("it-{nonce}@example.com");
+    let credentials = serde_json::json!({ "email": email, "password": "integration-pass", "display_name": "Integration" });
+
+    let register = client.post(format!("{base}/auth/register")).json(&credentials).send().await?;
+    assert_eq!(StatusCode::CREATED, register.status());
+    let registered: serde_json::Value = register.json().await?;
+    let user_id = registered["user"]["id"].as_str().expect("user id").to_string();
+
+    let duplicate = client.post(format!("{base}/auth/register")).json(&credentials).send().await?;
+    assert_eq!(StatusCode::CONFLICT, duplicate.status());
+
+    let login = client.post(format!("{base}/auth/login")).json(&credentials).send().await?;
+    assert_eq!(StatusCode::OK, login.status());
+    let login_body: serde_json::Value = login.json().await?;
+    assert_eq!(user_id, login_body["user"]["id"]);
+    let access_token = login_body["access_token"].as_str().expect("access token");
+
+    let create = client
+        .post(format!("{base}/rooms/create"))
+        .header("authorization", format!("Bearer {access_token}"))
+        .json(&serde_json::json!({
+            "name": "integration room",
+            "game_mode": "deathmatch",
+            "max_players": 4,
+            "host_player_id": user_id,
+            "settings": null
+        }))
+        .send()
+        .await?;
+    assert_eq!(StatusCode::OK, create.status());
+    let created: serde_json::Value = create.json().await?;
+    assert_eq!(true, created["success"]);
+
+    shutdown_tx.send(()).ok();
+    let _ = server.await;
+    worker_handle.abort();
+    let _ = worker_handle.await;
+    room_manager.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn leaving_a_room_gives_back_the_slot_and_migrates_the_host() -> Result<(), BoxError> {
+    let (room_manager_url, room_manager) = spawn_room_manager(&spawn_mock_pocketbase().await).await?;
+    let (addr, shutdown_tx, server, worker_handle, _auth) = spawn_gateway_with(|mut state| {
+        state.room_manager = RoomManagerClient::new(room_manager_url, ROOM_MANAGER_SECRET);
+        state
+    })
+    .await?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()?;
+    let base = format!("http://{}", addr);
+
+    let created: serde_json::Value = client
+        .post(format!("{base}/rooms/create"))
+        .json(&serde_json::json!({
+            "name": "revolving door",
+            "game_mode": "deathmatch",
+            "max_players": 4,
+            "host_player_id": "host",
+            "settings": null
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(true, created["success"], "{created}");
+    let room_id = created["room_id"].as_str().expect("room id").to_string();
+
+    for player_id in ["player-1", "player-2"] {
+        let joined: serde_json::Value = client
+            .post(format!("{base}/rooms/join"))
+            .json(&serde_json::json!({ "room_id": room_id, "player_id": player_id }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        assert_eq!(true, joined["success"], "{joined}");
+    }
+
+    let leave = |player_id: &str| {
+        client
+            .post(format!("{base}/rooms/leave"))
+            .json(&serde_json::json!({ "room_id": room_id, "player_id": player_id }))
+            .send()
+    };
+    let left: serde_json::Value = leave("player-1").await?.json().await?;
+    assert_eq!(true, left["success"], "{left}");
+    // Rời lần nữa không làm lệch số đếm
+    let again = leave("player-1").await?;
+    assert_eq!(StatusCode::CONFLICT, again.status());
+    let again: serde_json::Value = again.json().await?;
+    assert_eq!("conflict", again["error"]["code"], "{again}");
+
+    let players: serde_json::Value = client.get(format!("{base}/rooms/{room_id}/players")).send().await?.json().await?;
+    assert_eq!(2, players["current_players"], "{players}");
+    assert_eq!("host", players["host_player_id"]);
+    let ids: Vec<_> = players["players"].as_array().expect("players").iter().map(|p| p["id"].clone()).collect();
+    assert_eq!(ids, [serde_json::json!("player-2")]);
+
+    let left: serde_json::Value = leave("host").await?.json().await?;
+    assert_eq!(true, left["success"], "{left}");
+    let listed: serde_json::Value = client.get(format!("{base}/rooms/list")).send().await?.json().await?;
+    assert_eq!(1, listed["rooms"][0]["current_players"], "{listed}");
+    assert_eq!("player-2", listed["rooms"][0]["host_player_id"]);
+
+    let missing = client.get(format!("{base}/rooms/no-such-room/players")).send().await?;
+    assert_eq!(StatusCode::NOT_FOUND, missing.status());
+
+    shutdown_tx.send(()).ok();
+    let _ = server.await;
+    worker_handle.abort();
+    let _ = worker_handle.await;
+    room_manager.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn private_rooms_are_joined_through_invite_codes() -> Result<(), BoxError> {
+    let (room_manager_url, room_manager) = spawn_room_manager(&spawn_mock_pocketbase().await).await?;
+    let (addr, shutdown_tx, server, worker_handle, _auth) = spawn_gateway_with(|mut state| {
+        state.room_manager = RoomManagerClient::new(room_manager_url, ROOM_MANAGER_SECRET);
+        state
+    })
+    .await?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()?;
+    let base = format!("http://{}", addr);
+
+    let created: serde_json::Value = client
+        .post(format!("{base}/rooms/create"))
+        .json(&serde_json::json!({
+            "name": "friends only",
+            "game_mode": "deathmatch",
+            "max_players": 4,
+            "host_player_id": "host",
+            "settings": null,
+            "is_private": true
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(true, created["success"], "{created}");
+    let room_id = created["room_id"].as_str().expect("room id").to_string();
+    let code = created["invite_code"].as_str().expect("invite code").to_string();
+
+    let listed: serde_json::Value = client.get(format!("{base}/rooms/list")).send().await?.json().await?;
+    assert_eq!(0, listed["rooms"].as_array().expect("rooms").len(), "{listed}");
+
+    let resolved = client
+        .post(format!("{base}/rooms/resolve_invite"))
+        .json(&serde_json::json!({ "code": code.to_lowercase() }))
+        .send()
+        .await?;
+    assert_eq!(StatusCode::OK, resolved.status());
+    let resolved: serde_json::Value = resolved.json().await?;
+    assert_eq!(room_id, resolved["room"]["room_id"]);
+    assert_eq!("friends only", resolved["room"]["name"]);
+
+    let unknown = client
+        .post(format!("{base}/rooms/resolve_invite"))
+        .json(&serde_json::json!({ "code": "ABCDEF" }))
+        .send()
+        .await?;
+    assert_eq!(StatusCode::NOT_FOUND, unknown.status());
+
+    let without_code = client
+        .post(format!("{base}/rooms/join"))
+        .json(&serde_json::json!({ "room_id": room_id, "player_id": "player-1" }))
+        .send()
+        .await?;
+    assert_eq!(StatusCode::CONFLICT, without_code.status());
+    let without_code: serde_json::Value = without_code.json().await?;
+    assert_eq!("conflict", without_code["error"]["code"], "{without_code}");
+
+    let joined: serde_json::Value = client
+        .post(format!("{base}/rooms/join"))
+        .json(&serde_json::json!({ "room_id": room_id, "player_id": "player-1", "invite_code": code }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(true, joined["success"], "{joined}");
+    assert_eq!(2, joined["room"]["current_players"]);
+
+    shutdown_tx.send(()).ok();
+    let _ = server.await;
+    worker_handle.abort();
+    let _ = worker_handle.await;
+    room_manager.abort();
+    Ok(())
+}
+
+/// Tạo phòng private qua gateway với `host`, trả về (room_id, invite_code)
+async fn create_private_room(client: &reqwest::Client, base: &str, host: &str) -> Result<(String, String), BoxError> {
+    let created: serde_json::Value = client
+        .post(format!("{base}/rooms/create"))
+        .json(&serde_json::json!({
+            "name": "lobby",
+            "game_mode": "deathmatch",
+            "max_players": 4,
+            "host_player_id": host,
+            "settings": null,
+            "is_private": true
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(true, created["success"], "{created}");
+    Ok((
+        created["room_id"].as_str().expect("room id").to_string(),
+        created["invite_code"].as_str().expect("invite code").to_string(),
+    ))
+}
+
+#[tokio::test]
+async fn room_detail_merges_live_worker_players_and_hides_host_fields() -> Result<(), BoxError> {
+    let (room_manager_url, room_manager) = spawn_room_manager(&spawn_mock_pocketbase().await).await?;
+    // build_app_state dùng worker client giả nên nối thẳng tới một worker thật
+    let (worker_endpoint, live_worker) = rpc::spawn_test_server().await;
+    let channel = tonic::transport::Endpoint::from_shared(worker_endpoint)?.connect_lazy();
+    let (addr, shutdown_tx, server, worker_handle, auth) = spawn_gateway_with(|mut state| {
+        state.room_manager = RoomManagerClient::new(room_manager_url, ROOM_MANAGER_SECRET);
+        state.worker_client = gateway::worker_client::new(channel);
+        state
+    })
+    .await?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()?;
+    let base = format!("http://{}", addr);
+
+    let (room_id, code) = create_private_room(&client, &base, "host").await?;
+    let joined: serde_json::Value = client
+        .post(format!("{base}/rooms/join"))
+        .json(&serde_json::json!({ "room_id": room_id, "player_id": "player-1", "invite_code": code }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(true, joined["success"], "{joined}");
+    for player_id in ["host", "player-1"] {
+        let join = client
+            .post(format!("{base}/game/join"))
+            .json(&serde_json::json!({ "room_id": room_id, "player_id": player_id }))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, join.status());
+    }
+
+    let detail = client.get(format!("{base}/rooms/{room_id}")).header("authorization", bearer(&auth, "host")).send().await?;
+    assert_eq!(StatusCode::OK, detail.status());
+    let detail: serde_json::Value = detail.json().await?;
+    assert_eq!(false, detail["partial"], "{detail}");
+    assert_eq!("lobby", detail["room"]["name"]);
+    assert_eq!("host", detail["room"]["host_player_id"]);
+    assert_eq!(code, detail["room"]["invite_code"]);
+    let mut ids: Vec<_> = detail["players"].as_array().expect("players").iter().map(|p| p["id"].clone()).collect();
+    ids.sort_by_key(|id| id.to_string());
+    assert_eq!(ids, [serde_json::json!("host"), serde_json::json!("player-1")]);
+    assert!(detail["players"].as_array().unwrap().iter().all(|p| p["connected"] == true && p["is_bot"] == false));
+
+    // Người khác (hoặc không có token) không thấy invite code
+    for authorization in [Some(bearer(&auth, "player-1")), None] {
+        let mut request = client.get(format!("{base}/rooms/{room_id}"));
+        if let Some(authorization) = authorization {
+            request = request.header("authorization", authorization);
+        }
+        let detail: serde_json::Value = request.send().await?.json().await?;
+        assert!(detail["room"].get("invite_code").is_none(), "{detail}");
+        assert_eq!(2, detail["players"].as_array().expect("players").len());
+    }
+
+    let missing = client.get(format!("{base}/rooms/no-such-room")).send().await?;
+    assert_eq!(StatusCode::NOT_FOUND, missing.status());
+
+    shutdown_tx.send(()).ok();
+    let _ = server.await;
+    worker_handle.abort();
+    let _ = worker_handle.await;
+    room_manager.abort();
+    live_worker.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn room_detail_is_partial_when_the_worker_is_down() -> Result<(), BoxError> {
+    let (room_manager_url, room_manager) = spawn_room_manager(&spawn_mock_pocketbase().await).await?;
+    let (addr, shutdown_tx, server, worker_handle, auth) = spawn_gateway_with(|mut state| {
+        state.room_manager = RoomManagerClient::new(room_manager_url, ROOM_MANAGER_SECRET);
+        // Port không có gì listen
+        let channel = tonic::transport::Endpoint::from_static("http://127.0.0.1:1").connect_lazy();
+        state.worker_client = gateway::worker_client::new(channel);
+        state
+    })
+    .await?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()?;
+    let base = format!("http://{}", addr);
+
+    let (room_id, code) = create_private_room(&client, &base, "host").await?;
+    let detail = client.get(format!("{base}/rooms/{room_id}")).header("authorization", bearer(&auth, "host")).send().await?;
+    assert_eq!(StatusCode::OK, detail.status());
+    let detail: serde_json::Value = detail.json().await?;
+    assert_eq!(true, detail["partial"], "{detail}");
+    assert!(detail["players"].is_null());
+    assert_eq!("lobby", detail["room"]["name"]);
+    assert_eq!(code, detail["room"]["invite_code"]);
 
     shutdown_tx.send(()).ok();
     let _ = server.await;
     worker_handle.abort();
     let _ = worker_handle.await;
+    room_manager.abort();
     Ok(())
 }
@@ -0,0 +1,110 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common_net::{
+    message::{Frame, StateMessage},
+    transport::{GameTransport, TransportKind, WebRtcTransport},
+};
+use gateway::{OutboundSequence, TransportConnection, TransportRegistry, TransportSelection};
+use tokio::sync::RwLock;
+
+const CONNECTIONS: usize = 500;
+/// Mỗi room một task broadcast, 500 connection chia đều cho các room
+const ROOMS: usize = 8;
+const BROADCASTS_PER_ROOM: usize = 50;
+
+type Connection = (String, OutboundSequence, Box<dyn GameTransport + Send + Sync>);
+/// Registry cũ: một RwLock toàn cục, broadcast giữ write lock suốt lúc gửi
+type LockedRegistry = Arc<RwLock<HashMap<String, Connection>>>;
+
+fn room(connection: usize) -> String {
+    format!("room-{}", connection % ROOMS)
+}
+
+async fn transport() -> Box<dyn GameTransport + Send + Sync> {
+    let transport = WebRtcTransport::new("bench-room".to_string(), "peer".to_string());
+    transport.set_connected(true).await;
+    Box::new(transport)
+}
+
+fn frame(n: usize) -> Frame {
+    Frame::state(0, 0, StateMessage::Event { name: "bench".to_string(), data: serde_json::json!({ "n": n }) })
+}
+
+/// p50/p99 độ trễ một lần broadcast khi mọi room broadcast cùng lúc
+fn percentiles(mut samples: Vec<Duration>) -> (Duration, Duration) {
+    samples.sort();
+    (samples[samples.len() / 2], samples[samples.len() * 99 / 100])
+}
+
+async fn bench_locked() -> (Duration, Duration) {
+    let registry: LockedRegistry = Arc::new(RwLock::new(HashMap::new()));
+    for i in 0..CONNECTIONS {
+        let connection = (room(i), OutboundSequence::default(), transport().await);
+        registry.write().await.insert(format!("conn-{i}"), connection);
+    }
+
+    let tasks = (0..ROOMS).map(|r| {
+        let registry = registry.clone();
+        tokio::spawn(async move {
+            let room_id = room(r);
+            let mut samples = Vec::with_capacity(BROADCASTS_PER_ROOM);
+            for n in 0..BROADCASTS_PER_ROOM {
+                let started = Instant::now();
+                let mut reg = registry.write().await;
+                for (_, outbound, transport) in reg.values_mut().filter(|(conn_room, ..)| *conn_room == room_id) {
+                    let _ = transport.send_frame(outbound.stamp(frame(n))).await;
+                }
+                drop(reg);
+                samples.push(started.elapsed());
+            }
+            samples
+        })
+    });
+    percentiles(futures::future::join_all(tasks).await.into_iter().flat_map(|r| r.expect("task")).collect())
+}
+
+async fn bench_sharded() -> (Duration, Duration) {
+    let registry = TransportRegistry::new();
+    for i in 0..CONNECTIONS {
+        let mut connection = TransportConnection::new(
+            format!("peer-{i}"),
+            OutboundSequence::default(),
+            transport().await,
+            TransportSelection { kind: TransportKind::WebRtc, fallback_used: false, reason: TransportSelection::WEBRTC_CONNECTED },
+        );
+        connection.room_id = room(i);
+        registry.insert(format!("conn-{i}"), connection);
+    }
+
+    let tasks = (0..ROOMS).map(|r| {
+        let registry = registry.clone();
+        tokio::spawn(async move {
+            let room_id = room(r);
+            let mut samples = Vec::with_capacity(BROADCASTS_PER_ROOM);
+            for n in 0..BROADCASTS_PER_ROOM {
+                let started = Instant::now();
+                for target in registry.room_targets(&room_id, "nobody") {
+                    let _ = target.send(frame(n)).await;
+                }
+                samples.push(started.elapsed());
+            }
+            samples
+        })
+    });
+    percentiles(futures::future::join_all(tasks).await.into_iter().flat_map(|r| r.expect("task")).collect())
+}
+
+/// So sánh độ trễ broadcast khi có 500 connection giữa RwLock<HashMap> cũ và registry chia shard. Chạy bằng
+/// `cargo test -p gateway --release --test registry_broadcast -- --ignored --nocapture`
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[ignore]
+async fn broadcast_latency_at_500_connections() {
+    let (locked_p50, locked_p99) = bench_locked().await;
+    let (sharded_p50, sharded_p99) = bench_sharded().await;
+    println!("RwLock<HashMap>: p50 {locked_p50:?}, p99 {locked_p99:?}");
+    println!("TransportRegistry: p50 {sharded_p50:?}, p99 {sharded_p99:?}");
+}
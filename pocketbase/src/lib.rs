@@ -5,6 +5,8 @@ use std::collections::HashMap;
 use thiserror::Error;
 use tracing::{debug, error, info};
 
+pub mod migrations;
+
 pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
 #[derive(Error, Debug)]
@@ -17,6 +19,10 @@ pub enum PocketBaseError {
     Json(#[from] serde_json::Error),
     #[error("Invalid URL: {0}")]
     Url(String),
+    #[error("Record already exists: {0}")]
+    Conflict(String),
+    #[error("PocketBase is missing collections: {}; start with AUTO_MIGRATE=1 to create them", .0.join(", "))]
+    MissingCollections(Vec<String>),
 }
 
 #[derive(Debug, Clone)]
@@ -26,7 +32,7 @@ pub struct PocketBaseClient {
     admin_token: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Collection {
     pub id: String,
     pub name: String,
@@ -37,7 +43,7 @@ pub struct Collection {
     pub updated: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FieldSchema {
     pub name: String,
     pub field_type: String,
@@ -45,7 +51,7 @@ pub struct FieldSchema {
     pub options: Option<Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollectionRules {
     pub create: Option<String>,
     pub update: Option<String>,
@@ -76,6 +82,11 @@ pub struct CollectionCreateRequest {
     pub rules: Option<CollectionRules>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionUpdateRequest {
+    pub schema: Vec<FieldSchema>,
+}
+
 impl PocketBaseClient {
     pub fn new(base_url: &str) -> Self {
         Self {
@@ -147,6 +158,31 @@ impl PocketBaseClient {
         }
     }
 
+    /// Update collection schema
+    pub async fn update_collection(&self, name: &str, update: CollectionUpdateRequest) -> Result<Collection, PocketBaseError> {
+        let url = format!("{}/api/collections/{}", self.base_url, name);
+        let response = self
+            .client
+            .patch(&url)
+            .headers(self.get_auth_headers())
+            .json(&update)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let collection: Collection = response.json().await?;
+            info!("Updated collection: {}", collection.name);
+            Ok(collection)
+        } else {
+            let status = response.status();
+            let error: Value = response.json().await.unwrap_or_default();
+            Err(PocketBaseError::Api {
+                message: error["message"].as_str().unwrap_or("Unknown error").to_string(),
+                code: status.to_string(),
+            })
+        }
+    }
+
     /// Get collection
     pub async fn get_collection(&self, name: &str) -> Result<Collection, PocketBaseError> {
         let url = format!("{}/api/collections/{}", self.base_url, name);
@@ -393,6 +429,41 @@ impl PocketBaseClient {
         }
     }
 
+    /// Tạo user mới trong collection `users` - PocketBase tự hash password
+    pub async fn create_user(&self, email: &str, password: &str, name: Option<&str>) -> Result<Record, PocketBaseError> {
+        let url = format!("{}/api/collections/users/records", self.base_url);
+        let user_data = json!({
+            "email": email,
+            "password": password,
+            "passwordConfirm": password,
+            "name": name.unwrap_or_default(),
+        });
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&user_data)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let record: Record = response.json().await?;
+            info!("User registered: {}", record.id);
+            Ok(record)
+        } else {
+            let status = response.status();
+            let error: Value = response.json().await.unwrap_or_default();
+            // Email trùng: 400 với data.email.code = "validation_not_unique"
+            if error["data"]["email"]["code"] == "validation_not_unique" {
+                return Err(PocketBaseError::Conflict(email.to_string()));
+            }
+            Err(PocketBaseError::Api {
+                message: error["message"].as_str().unwrap_or("Registration failed").to_string(),
+                code: status.to_string(),
+            })
+        }
+    }
+
     /// Refresh user auth token
     pub async fn refresh_user_token(&self, refresh_token: &str) -> Result<AuthRecord, PocketBaseError> {
         let url = format!("{}/api/collections/users/auth-refresh", self.base_url);
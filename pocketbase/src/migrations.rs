@@ -0,0 +1,561 @@
+//! Declarative schema for every PocketBase collection the workspace uses.
+//! `ensure_collections` creates missing collections and appends missing fields, `verify_collections` only reports
+//! what is missing. The applied version is stored in the `_meta` collection so later changes can be applied
+//! incrementally on top of it.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::{
+    CollectionCreateRequest, CollectionRules, CollectionUpdateRequest, FieldSchema, PocketBaseClient, PocketBaseError,
+};
+
+/// Bump whenever a collection or field is added below
+pub const SCHEMA_VERSION: u32 = 2;
+pub const META_COLLECTION: &str = "_meta";
+const META_KEY: &str = "schema";
+/// `AUTO_MIGRATE=1` lets services create the schema on startup instead of failing fast
+pub const AUTO_MIGRATE_ENV: &str = "AUTO_MIGRATE";
+
+#[derive(Debug, Clone)]
+pub struct FieldDefinition {
+    pub name: &'static str,
+    pub field_type: &'static str,
+    pub required: bool,
+    pub options: Option<Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectionDefinition {
+    pub name: &'static str,
+    pub fields: Vec<FieldDefinition>,
+    pub indexes: Vec<String>,
+    /// None keeps the collection admin-only
+    pub rules: Option<CollectionRules>,
+}
+
+impl FieldDefinition {
+    fn to_schema(&self) -> FieldSchema {
+        FieldSchema {
+            name: self.name.to_string(),
+            field_type: self.field_type.to_string(),
+            required: self.required,
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl CollectionDefinition {
+    fn to_create_request(&self) -> CollectionCreateRequest {
+        CollectionCreateRequest {
+            name: self.name.to_string(),
+            schema: self.fields.iter().map(FieldDefinition::to_schema).collect(),
+            indexes: Some(self.indexes.clone()),
+            rules: self.rules.clone(),
+        }
+    }
+}
+
+/// What `ensure_collections` changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub created_collections: Vec<String>,
+    /// (collection, field)
+    pub added_fields: Vec<(String, String)>,
+    pub previous_version: Option<u32>,
+    pub schema_version: u32,
+}
+
+impl MigrationReport {
+    pub fn is_noop(&self) -> bool {
+        self.created_collections.is_empty()
+            && self.added_fields.is_empty()
+            && self.previous_version == Some(self.schema_version)
+    }
+}
+
+fn field(name: &'static str, field_type: &'static str, required: bool) -> FieldDefinition {
+    FieldDefinition { name, field_type, required, options: None }
+}
+
+fn text(name: &'static str, required: bool) -> FieldDefinition {
+    field(name, "text", required)
+}
+
+fn number(name: &'static str, required: bool) -> FieldDefinition {
+    field(name, "number", required)
+}
+
+fn date(name: &'static str, required: bool) -> FieldDefinition {
+    field(name, "date", required)
+}
+
+fn json_field(name: &'static str, required: bool) -> FieldDefinition {
+    field(name, "json", required)
+}
+
+fn boolean(name: &'static str) -> FieldDefinition {
+    field(name, "bool", false)
+}
+
+fn select(name: &'static str, values: &[&str]) -> FieldDefinition {
+    FieldDefinition { options: Some(json!(values)), ..field(name, "select", true) }
+}
+
+fn relation(name: &'static str, collection: &str) -> FieldDefinition {
+    FieldDefinition { options: Some(json!({ "collectionName": collection })), ..field(name, "relation", true) }
+}
+
+fn collection(name: &'static str, fields: Vec<FieldDefinition>, indexed: &[&str]) -> CollectionDefinition {
+    CollectionDefinition {
+        name,
+        fields,
+        indexes: indexed
+            .iter()
+            .map(|column| format!("CREATE INDEX idx_{name}_{column} ON {name} ({column})"))
+            .collect(),
+        rules: Some(public_rules()),
+    }
+}
+
+/// Services write without an admin token, so game collections stay open like the existing ones
+fn public_rules() -> CollectionRules {
+    CollectionRules {
+        create: Some(String::new()),
+        update: Some(String::new()),
+        delete: Some(String::new()),
+        view: Some(String::new()),
+    }
+}
+
+fn meta_collection() -> CollectionDefinition {
+    CollectionDefinition {
+        name: META_COLLECTION,
+        fields: vec![text("key", true), number("schema_version", true)],
+        indexes: vec![format!("CREATE UNIQUE INDEX idx_{META_COLLECTION}_key ON {META_COLLECTION} (key)")],
+        rules: None,
+    }
+}
+
+/// Every collection of the current schema version, relation targets before the collections pointing at them
+pub fn collections() -> Vec<CollectionDefinition> {
+    vec![
+        collection(
+            "users",
+            vec![
+                field("email", "email", true),
+                text("username", true),
+                text("display_name", true),
+                field("avatar", "file", false),
+                number("level", false),
+                number("xp", false),
+                number("total_games_played", false),
+                number("total_wins", false),
+                number("total_score", false),
+            ],
+            &[],
+        ),
+        // room-manager
+        collection(
+            "rooms",
+            vec![
+                text("name", true),
+                text("game_mode", true),
+                number("max_players", true),
+                number("current_players", false),
+                text("status", true),
+                number("created_at", false),
+                number("updated_at", false),
+                text("host_player_id", false),
+                text("worker_endpoint", false),
+                json_field("settings", false),
+                boolean("backfill_with_bots"),
+                boolean("is_private"),
+                text("invite_code", false),
+            ],
+            &["status"],
+        ),
+        collection(
+            "players",
+            vec![
+                text("name", true),
+                text("room_id", true),
+                number("joined_at", false),
+                number("last_seen", false),
+                text("status", true),
+                text("team", false),
+            ],
+            &["room_id"],
+        ),
+        collection(
+            "parties",
+            vec![
+                text("leader_id", true),
+                json_field("members", true),
+                text("invite_code", false),
+                date("created_at", true),
+                date("updated_at", true),
+            ],
+            &[],
+        ),
+        collection(
+            "tournaments",
+            vec![
+                text("name", true),
+                json_field("status", true),
+                text("host_player_id", true),
+                text("game_mode", true),
+                json_field("match_rooms", false),
+                json_field("state", true),
+            ],
+            &[],
+        ),
+        collection(
+            "player_ratings",
+            vec![
+                number("skill_rating", true),
+                number("rating_deviation", true),
+                number("volatility", true),
+                number("games_played", false),
+                number("wins", false),
+                number("losses", false),
+                number("draws", false),
+                number("win_streak", false),
+                number("best_streak", false),
+                number("last_updated", false),
+                text("rank", false),
+                text("tier", false),
+            ],
+            &[],
+        ),
+        // worker
+        collection(
+            "games",
+            vec![text("name", true), number("max_players", true), select("status", &["waiting", "playing", "finished"])],
+            &[],
+        ),
+        collection(
+            "match_results",
+            vec![
+                text("room_id", true),
+                text("game_mode", true),
+                json_field("players", true),
+                text("player_ids", true),
+                number("duration_seconds", true),
+                date("finished_at", true),
+            ],
+            &["finished_at"],
+        ),
+        collection(
+            "player_checkpoints",
+            vec![
+                text("player_id", true),
+                text("room_id", true),
+                text("game_mode", true),
+                number("score", true),
+                number("distance", false),
+                number("tick", true),
+                number("saved_at", true),
+            ],
+            &["player_id", "room_id"],
+        ),
+        // services
+        collection(
+            "matches",
+            vec![
+                text("room_id", true),
+                text("game_mode", true),
+                text("map_name", true),
+                number("max_players", true),
+                select("status", &["waiting", "starting", "in_progress", "finished", "cancelled"]),
+                date("start_time", false),
+                date("end_time", false),
+                number("duration_seconds", false),
+                text("winner_team", false),
+                number("total_score", false),
+                json_field("settings", false),
+            ],
+            &[],
+        ),
+        collection(
+            "participants",
+            vec![
+                relation("match_id", "matches"),
+                relation("user_id", "users"),
+                text("username", true),
+                text("team", false),
+                number("position", false),
+                number("score", false),
+                number("kills", false),
+                number("deaths", false),
+                number("assists", false),
+                number("accuracy", false),
+                number("playtime_seconds", false),
+                date("joined_at", true),
+                date("left_at", false),
+                boolean("is_winner"),
+                json_field("stats", false),
+            ],
+            &[],
+        ),
+        collection(
+            "leaderboard",
+            vec![
+                relation("user_id", "users"),
+                text("username", true),
+                number("rank", true),
+                number("score", true),
+                number("games_played", false),
+                number("win_rate", false),
+                number("avg_score", false),
+                number("best_score", false),
+                number("streak_current", false),
+                number("streak_best", false),
+                date("last_played", true),
+                select("tier", &["bronze", "silver", "gold", "platinum", "diamond", "master"]),
+                text("season", true),
+                text("game_mode", false),
+            ],
+            &["season"],
+        ),
+        collection(
+            "inventory",
+            vec![
+                relation("user_id", "users"),
+                select("item_type", &["skin", "weapon", "consumable", "currency"]),
+                text("item_id", true),
+                number("quantity", true),
+                select("rarity", &["common", "rare", "epic", "legendary"]),
+                date("acquired_at", true),
+                date("expires_at", false),
+                json_field("metadata", false),
+            ],
+            &[],
+        ),
+        collection(
+            "achievements",
+            vec![
+                relation("user_id", "users"),
+                text("achievement_id", true),
+                text("name", true),
+                text("description", true),
+                text("icon", true),
+                select("category", &["gameplay", "social", "progression"]),
+                select("rarity", &["common", "rare", "epic", "legendary"]),
+                number("points", true),
+                date("unlocked_at", true),
+                json_field("progress", false),
+            ],
+            &[],
+        ),
+        collection(
+            "user_stats",
+            vec![
+                relation("user_id", "users"),
+                text("date", true),
+                number("games_played", false),
+                number("total_score", false),
+                number("total_playtime_seconds", false),
+                number("avg_accuracy", false),
+                number("best_streak", false),
+                number("achievements_unlocked", false),
+                number("items_acquired", false),
+            ],
+            &[],
+        ),
+        collection(
+            "player_daily_stats",
+            vec![
+                text("player_id", true),
+                text("date", true),
+                number("games", true),
+                number("wins", true),
+                number("total_score", true),
+                number("best_score", true),
+            ],
+            &["date"],
+        ),
+        collection(
+            "player_stats",
+            vec![
+                text("player_id", true),
+                number("games", true),
+                number("wins", true),
+                number("total_score", true),
+                number("avg_score", true),
+                number("best_score", true),
+                text("last_aggregated_date", false),
+            ],
+            &["player_id"],
+        ),
+        collection("job_checkpoints", vec![text("job", true), text("last_completed_date", true)], &[]),
+        collection(
+            "seasons",
+            vec![text("game_mode", true), date("starts_at", true), date("ends_at", true), boolean("active")],
+            &[],
+        ),
+        collection(
+            "season_results",
+            vec![
+                text("season_id", true),
+                text("game_mode", true),
+                number("rank", true),
+                text("user_id", true),
+                text("username", true),
+                number("score", true),
+            ],
+            &["season_id"],
+        ),
+        collection(
+            "season_rewards",
+            vec![text("season_id", true), text("user_id", true), number("rank", true), text("reward", true)],
+            &[],
+        ),
+        collection(
+            "analytics_events",
+            vec![
+                text("event_type", true),
+                text("player_id", true),
+                text("session_id", true),
+                text("room_id", false),
+                text("transport", false),
+                text("reason", false),
+                number("duration_ms", false),
+                date("occurred_at", true),
+                text("date", true),
+            ],
+            &["date"],
+        ),
+        collection(
+            "analytics_daily",
+            vec![text("date", true), text("event_type", true), number("count", true)],
+            &["date"],
+        ),
+    ]
+}
+
+/// Names of declared collections that do not exist yet
+pub async fn missing_collections(client: &PocketBaseClient) -> Result<Vec<String>, PocketBaseError> {
+    let existing = client.list_collections().await?;
+    Ok(std::iter::once(meta_collection())
+        .chain(collections())
+        .filter(|definition| !existing.iter().any(|collection| collection.name == definition.name))
+        .map(|definition| definition.name.to_string())
+        .collect())
+}
+
+/// Fails with the list of missing collections instead of touching the schema
+pub async fn verify_collections(client: &PocketBaseClient) -> Result<(), PocketBaseError> {
+    let missing = missing_collections(client).await?;
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(PocketBaseError::MissingCollections(missing))
+    }
+}
+
+/// Creates missing collections, appends missing fields to existing ones and records `SCHEMA_VERSION` in `_meta`.
+/// Running it again on an up-to-date database writes nothing
+pub async fn ensure_collections(client: &PocketBaseClient) -> Result<MigrationReport, PocketBaseError> {
+    let existing: HashMap<String, Vec<FieldSchema>> = client
+        .list_collections()
+        .await?
+        .into_iter()
+        .map(|collection| (collection.name, collection.schema))
+        .collect();
+
+    let meta_record = if existing.contains_key(META_COLLECTION) {
+        client
+            .list_records(META_COLLECTION, Some(&format!("key='{META_KEY}'")), None)
+            .await?
+            .into_iter()
+            .next()
+    } else {
+        None
+    };
+    let previous_version = meta_record
+        .as_ref()
+        .and_then(|record| record.fields.get("schema_version"))
+        .and_then(Value::as_u64)
+        .map(|version| version as u32);
+
+    let mut report = MigrationReport { previous_version, schema_version: SCHEMA_VERSION, ..Default::default() };
+    if previous_version.is_some_and(|version| version > SCHEMA_VERSION) {
+        warn!(
+            "PocketBase schema version {:?} is newer than {}, leaving it untouched",
+            previous_version, SCHEMA_VERSION
+        );
+        report.schema_version = previous_version.unwrap_or(SCHEMA_VERSION);
+        return Ok(report);
+    }
+
+    for definition in std::iter::once(meta_collection()).chain(collections()) {
+        let Some(fields) = existing.get(definition.name) else {
+            client.create_collection(definition.to_create_request()).await?;
+            info!("Migration: created collection {}", definition.name);
+            report.created_collections.push(definition.name.to_string());
+            continue;
+        };
+
+        let missing: Vec<&FieldDefinition> = definition
+            .fields
+            .iter()
+            .filter(|wanted| !fields.iter().any(|field| field.name == wanted.name))
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        let mut schema = fields.clone();
+        schema.extend(missing.iter().map(|field| field.to_schema()));
+        client.update_collection(definition.name, CollectionUpdateRequest { schema }).await?;
+        for field in missing {
+            info!("Migration: added field {}.{}", definition.name, field.name);
+            report.added_fields.push((definition.name.to_string(), field.name.to_string()));
+        }
+    }
+
+    if previous_version != Some(SCHEMA_VERSION) {
+        let data = json!({ "key": META_KEY, "schema_version": SCHEMA_VERSION });
+        match meta_record {
+            Some(record) => client.update_record(META_COLLECTION, &record.id, data).await?,
+            None => client.create_record(META_COLLECTION, data).await?,
+        };
+    }
+
+    if report.is_noop() {
+        debug!("PocketBase schema is up to date (version {})", SCHEMA_VERSION);
+    } else {
+        info!(
+            "PocketBase schema migrated from {:?} to {}: {} collections created, {} fields added",
+            previous_version,
+            SCHEMA_VERSION,
+            report.created_collections.len(),
+            report.added_fields.len()
+        );
+    }
+    Ok(report)
+}
+
+/// Startup check shared by services: migrates with `AUTO_MIGRATE=1`, otherwise fails on missing collections.
+/// An unreachable PocketBase is only logged, services already degrade without it
+pub async fn bootstrap(client: &PocketBaseClient) -> Result<Option<MigrationReport>, PocketBaseError> {
+    if let Err(e) = client.health().await {
+        warn!("PocketBase not reachable, skipping schema check: {}", e);
+        return Ok(None);
+    }
+
+    let mut client = client.clone();
+    if let (Ok(email), Ok(password)) =
+        (std::env::var("POCKETBASE_ADMIN_EMAIL"), std::env::var("POCKETBASE_ADMIN_PASSWORD"))
+    {
+        client.auth_admin(&email, &password).await?;
+    }
+
+    if std::env::var(AUTO_MIGRATE_ENV).ok().as_deref() == Some("1") {
+        ensure_collections(&client).await.map(Some)
+    } else {
+        verify_collections(&client).await.map(|_| None)
+    }
+}
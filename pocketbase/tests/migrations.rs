@@ -0,0 +1,152 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, patch},
+    Json, Router,
+};
+use pocketbase::{
+    migrations::{self, META_COLLECTION, SCHEMA_VERSION},
+    PocketBaseClient, PocketBaseError,
+};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+/// Disposable in-memory PocketBase: collections, records and a count of every write request
+#[derive(Clone, Default)]
+struct MockPocketBase {
+    collections: Arc<Mutex<BTreeMap<String, Value>>>,
+    records: Arc<Mutex<BTreeMap<String, Vec<Value>>>>,
+    writes: Arc<AtomicUsize>,
+}
+
+async fn list_collections(State(pb): State<MockPocketBase>) -> Json<Vec<Value>> {
+    Json(pb.collections.lock().await.values().cloned().collect())
+}
+
+async fn create_collection(State(pb): State<MockPocketBase>, Json(mut body): Json<Value>) -> Json<Value> {
+    pb.writes.fetch_add(1, Ordering::SeqCst);
+    let name = body["name"].as_str().expect("collection name").to_string();
+    body["id"] = json!(format!("col_{name}"));
+    body["created"] = json!("");
+    body["updated"] = json!("");
+    pb.collections.lock().await.insert(name, body.clone());
+    Json(body)
+}
+
+async fn update_collection(
+    State(pb): State<MockPocketBase>,
+    Path(name): Path<String>,
+    Json(body): Json<Value>,
+) -> Result<Json<Value>, StatusCode> {
+    pb.writes.fetch_add(1, Ordering::SeqCst);
+    let mut collections = pb.collections.lock().await;
+    let collection = collections.get_mut(&name).ok_or(StatusCode::NOT_FOUND)?;
+    collection["schema"] = body["schema"].clone();
+    Ok(Json(collection.clone()))
+}
+
+async fn list_records(
+    State(pb): State<MockPocketBase>,
+    Path(collection): Path<String>,
+    Query(query): Query<BTreeMap<String, String>>,
+) -> Json<Value> {
+    let records = pb.records.lock().await.get(&collection).cloned().unwrap_or_default();
+    let items: Vec<Value> = match query.get("filter").and_then(|filter| filter.split_once('=')) {
+        Some((field, value)) => {
+            let value = value.trim_matches('\'');
+            records.into_iter().filter(|record| record[field] == json!(value)).collect()
+        }
+        None => records,
+    };
+    Json(json!({ "items": items }))
+}
+
+async fn create_record(
+    State(pb): State<MockPocketBase>,
+    Path(collection): Path<String>,
+    Json(mut body): Json<Value>,
+) -> Json<Value> {
+    pb.writes.fetch_add(1, Ordering::SeqCst);
+    let mut records = pb.records.lock().await;
+    let records = records.entry(collection).or_default();
+    body["id"] = json!(format!("rec{}", records.len()));
+    body["created"] = json!("");
+    body["updated"] = json!("");
+    records.push(body.clone());
+    Json(body)
+}
+
+async fn spawn_mock_pocketbase(pb: MockPocketBase) -> PocketBaseClient {
+    let app = Router::new()
+        .route("/api/health", get(|| async { Json(json!({ "code": 200 })) }))
+        .route("/api/collections", get(list_collections).post(create_collection))
+        .route("/api/collections/:name", patch(update_collection))
+        .route("/api/collections/:name/records", get(list_records).post(create_record))
+        .with_state(pb);
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(axum::Server::from_tcp(listener).expect("server").serve(app.into_make_service()));
+    PocketBaseClient::new(&format!("http://{addr}"))
+}
+
+#[tokio::test]
+async fn fresh_database_gets_every_collection_and_second_run_is_a_noop() {
+    let pb = MockPocketBase::default();
+    let client = spawn_mock_pocketbase(pb.clone()).await;
+
+    match migrations::verify_collections(&client).await {
+        Err(PocketBaseError::MissingCollections(missing)) => {
+            assert!(missing.iter().any(|name| name == "rooms"), "{missing:?}");
+            let message = PocketBaseError::MissingCollections(missing).to_string();
+            assert!(message.contains("AUTO_MIGRATE=1"), "{message}");
+        }
+        other => panic!("expected missing collections, got {other:?}"),
+    }
+
+    let report = migrations::ensure_collections(&client).await.expect("first migration");
+    let expected: Vec<&str> =
+        std::iter::once(META_COLLECTION).chain(migrations::collections().iter().map(|c| c.name)).collect();
+    assert_eq!(report.created_collections, expected);
+    assert_eq!(report.previous_version, None);
+    assert!(!report.is_noop());
+    let meta = pb.records.lock().await[META_COLLECTION].clone();
+    assert_eq!(meta.len(), 1);
+    assert_eq!(meta[0]["schema_version"], SCHEMA_VERSION);
+    migrations::verify_collections(&client).await.expect("schema complete");
+
+    let writes = pb.writes.load(Ordering::SeqCst);
+    let report = migrations::ensure_collections(&client).await.expect("second migration");
+    assert!(report.is_noop(), "{report:?}");
+    assert_eq!(pb.writes.load(Ordering::SeqCst), writes);
+}
+
+#[tokio::test]
+async fn missing_fields_are_appended_to_existing_collections() {
+    let pb = MockPocketBase::default();
+    let client = spawn_mock_pocketbase(pb.clone()).await;
+    pb.collections.lock().await.insert(
+        "rooms".to_string(),
+        json!({
+            "id": "col_rooms", "name": "rooms", "indexes": [], "rules": null, "created": "", "updated": "",
+            "schema": [{ "name": "name", "field_type": "text", "required": true, "options": null }]
+        }),
+    );
+
+    let report = migrations::ensure_collections(&client).await.expect("migration");
+    assert!(!report.created_collections.iter().any(|name| name == "rooms"));
+    assert!(report.added_fields.contains(&("rooms".to_string(), "worker_endpoint".to_string())));
+    assert!(!report.added_fields.contains(&("rooms".to_string(), "name".to_string())));
+
+    let rooms = pb.collections.lock().await["rooms"].clone();
+    let fields: Vec<&str> = rooms["schema"].as_array().unwrap().iter().map(|f| f["name"].as_str().unwrap()).collect();
+    assert_eq!(fields[0], "name");
+    assert!(fields.contains(&"status") && fields.contains(&"invite_code"), "{fields:?}");
+}
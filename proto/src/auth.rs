@@ -0,0 +1,46 @@
+//! Token nội bộ trên kênh gRPC tới worker: client (gateway, room-manager) gắn vào metadata `x-internal-token`,
+//! worker từ chối lời gọi thiếu hoặc sai token bằng `Unauthenticated`.
+
+use tonic::{metadata::AsciiMetadataValue, service::Interceptor, Request, Status};
+
+pub const INTERNAL_TOKEN_HEADER: &str = "x-internal-token";
+
+/// Token đọc từ WORKER_RPC_TOKEN; worker và mọi client của nó phải dùng cùng giá trị. Không có mặc định:
+/// worker chưa đặt token chỉ được nghe trên loopback
+pub fn internal_token_from_env() -> Option<String> {
+    std::env::var("WORKER_RPC_TOKEN").ok().filter(|token| !token.is_empty())
+}
+
+/// Interceptor phía client gắn token vào mọi lời gọi
+#[derive(Debug, Clone, Default)]
+pub struct AttachInternalToken {
+    /// None khi chưa cấu hình token hoặc token không phải ASCII: lời gọi đi không kèm token
+    token: Option<AsciiMetadataValue>,
+}
+
+impl AttachInternalToken {
+    pub fn new(token: &str) -> Self {
+        Self { token: token.parse().ok() }
+    }
+
+    pub fn optional(token: Option<&str>) -> Self {
+        token.map(Self::new).unwrap_or_default()
+    }
+
+    pub fn from_env() -> Self {
+        Self::optional(internal_token_from_env().as_deref())
+    }
+
+    pub fn apply<T>(&self, request: &mut Request<T>) {
+        if let Some(token) = &self.token {
+            request.metadata_mut().insert(INTERNAL_TOKEN_HEADER, token.clone());
+        }
+    }
+}
+
+impl Interceptor for AttachInternalToken {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        self.apply(&mut request);
+        Ok(request)
+    }
+}
@@ -1,3 +1,5 @@
+pub mod auth;
+
 pub mod worker {
     pub mod v1 {
         tonic::include_proto!("worker.v1");
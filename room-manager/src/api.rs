@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use tokio::sync::RwLock;
+use tracing::error;
+
+use crate::{
+    party::{CreatePartyRequest, JoinPartyRequest, LeavePartyRequest, PartyInviteRequest},
+    tournament::{
+        CreateTournamentRequest, RegisterParticipantRequest, ReportMatchResultRequest, StartTournamentRequest,
+        TournamentManager,
+    },
+    AssignRoomRequest, BoxError, CreateRoomRequest, JoinRoomRequest, KickPlayerRequest, LeaveRoomRequest,
+    ListRoomsRequest, ResolveInviteRequest, RoomInviteRequest, RoomManagerState,
+};
+
+pub const ROOMS_PATH: &str = "/v1/rooms";
+pub const ROOM_JOIN_PATH: &str = "/v1/rooms/:id/join";
+pub const ROOM_LEAVE_PATH: &str = "/v1/rooms/:id/leave";
+/// GET record của một phòng
+pub const ROOM_PATH: &str = "/v1/rooms/:id";
+/// GET player trong phòng (host và số slot đang dùng đi kèm)
+pub const ROOM_PLAYERS_PATH: &str = "/v1/rooms/:id/players";
+/// Host/admin đuổi (và tuỳ chọn ban) player
+pub const ROOM_KICK_PATH: &str = "/v1/rooms/:id/kick";
+/// Worker báo trận đã kết thúc, phòng chuyển Finished
+pub const ROOM_FINISH_PATH: &str = "/v1/rooms/:id/finish";
+pub const ASSIGN_PATH: &str = "/v1/assign";
+/// POST tạo invite code mới, DELETE thu hồi; chỉ host
+pub const ROOM_INVITE_PATH: &str = "/v1/rooms/:id/invite";
+pub const INVITE_RESOLVE_PATH: &str = "/v1/invites/resolve";
+/// POST tạo party, người gọi là leader
+pub const PARTIES_PATH: &str = "/v1/parties";
+/// Leader tạo invite code mới cho party
+pub const PARTY_INVITE_PATH: &str = "/v1/parties/:id/invite";
+pub const PARTY_JOIN_PATH: &str = "/v1/parties/join";
+/// Leader rời thì party giải tán
+pub const PARTY_LEAVE_PATH: &str = "/v1/parties/leave";
+/// POST tạo giải đấu
+pub const TOURNAMENTS_PATH: &str = "/v1/tournaments";
+pub const TOURNAMENT_PATH: &str = "/v1/tournaments/:id";
+pub const TOURNAMENT_REGISTER_PATH: &str = "/v1/tournaments/:id/register";
+/// Host/admin chia nhánh và mở phòng vòng 1
+pub const TOURNAMENT_START_PATH: &str = "/v1/tournaments/:id/start";
+/// Host/admin báo người thắng một trận
+pub const TOURNAMENT_REPORT_PATH: &str = "/v1/tournaments/:id/report";
+
+/// Header chứa shared secret giữa các service nội bộ (gateway, services, admin tooling)
+pub const INTERNAL_SECRET_HEADER: &str = "x-internal-secret";
+
+/// Secret đọc từ ROOM_MANAGER_INTERNAL_SECRET; gateway, worker và room-manager phải dùng cùng giá trị.
+/// Không có mặc định: room-manager chưa đặt secret chỉ được nghe trên loopback (dev/test)
+pub fn internal_secret_from_env() -> Option<String> {
+    std::env::var("ROOM_MANAGER_INTERNAL_SECRET").ok().filter(|secret| !secret.is_empty())
+}
+
+/// Không có secret thì từ chối địa chỉ ngoài loopback: ai trong mạng cũng gọi được API quản lý phòng
+pub fn check_bind(secret: Option<&str>, addr: std::net::SocketAddr) -> Result<(), BoxError> {
+    if secret.is_none() && !addr.ip().is_loopback() {
+        return Err(format!("room-manager API on {addr} needs ROOM_MANAGER_INTERNAL_SECRET; only loopback may run without one").into());
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
+struct ApiState {
+    rooms: Arc<RwLock<RoomManagerState>>,
+    tournaments: Arc<TournamentManager>,
+    /// None khi chưa cấu hình secret (chỉ loopback): mọi request được cho qua
+    secret: Option<Arc<str>>,
+}
+
+/// REST API quản lý phòng; mọi route yêu cầu header `x-internal-secret` khớp `secret`
+pub fn router(rooms: Arc<RwLock<RoomManagerState>>, secret: impl Into<Arc<str>>) -> Router {
+    let tournaments = Arc::new(TournamentManager::new(rooms.clone()));
+    router_with_tournaments(rooms, tournaments, Some(secret.into()))
+}
+
+/// Như `router` nhưng dùng `TournamentManager` có sẵn (đã nạp giải từ database); `secret` None chỉ dùng
+/// sau khi `check_bind` đã cho phép
+pub fn router_with_tournaments(
+    rooms: Arc<RwLock<RoomManagerState>>,
+    tournaments: Arc<TournamentManager>,
+    secret: Option<Arc<str>>,
+) -> Router {
+    let state = ApiState {
+        rooms,
+        tournaments,
+        secret,
+    };
+
+    Router::new()
+        .route(ROOMS_PATH, post(create_room).get(list_rooms))
+        .route(ROOM_PATH, get(get_room))
+        .route(ROOM_JOIN_PATH, post(join_room))
+        .route(ROOM_LEAVE_PATH, post(leave_room))
+        .route(ROOM_PLAYERS_PATH, get(list_players))
+        .route(ROOM_KICK_PATH, post(kick_player))
+        .route(ROOM_FINISH_PATH, post(finish_room))
+        .route(ASSIGN_PATH, post(assign_room))
+        .route(ROOM_INVITE_PATH, post(regenerate_invite).delete(revoke_invite))
+        .route(INVITE_RESOLVE_PATH, post(resolve_invite))
+        .route(PARTIES_PATH, post(create_party))
+        .route(PARTY_INVITE_PATH, post(invite_to_party))
+        .route(PARTY_JOIN_PATH, post(join_party))
+        .route(PARTY_LEAVE_PATH, post(leave_party))
+        .route(TOURNAMENTS_PATH, post(create_tournament))
+        .route(TOURNAMENT_PATH, get(get_tournament))
+        .route(TOURNAMENT_REGISTER_PATH, post(register_participant))
+        .route(TOURNAMENT_START_PATH, post(start_tournament))
+        .route(TOURNAMENT_REPORT_PATH, post(report_match_result))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_internal_secret))
+        .with_state(state)
+}
+
+async fn require_internal_secret<B>(State(state): State<ApiState>, request: Request<B>, next: Next<B>) -> Response {
+    let Some(secret) = state.secret.as_deref() else {
+        return next.run(request).await;
+    };
+    let authorized = request
+        .headers()
+        .get(INTERNAL_SECRET_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value == secret);
+    if !authorized {
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "invalid internal secret" }))).into_response();
+    }
+    next.run(request).await
+}
+
+fn respond<T: serde::Serialize>(result: Result<T, BoxError>) -> Response {
+    match result {
+        Ok(body) => Json(body).into_response(),
+        Err(e) => {
+            error!("Room manager API request failed: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn create_room(State(state): State<ApiState>, Json(request): Json<CreateRoomRequest>) -> Response {
+    respond(crate::create_room(state.rooms, request).await)
+}
+
+async fn list_rooms(State(state): State<ApiState>, Query(request): Query<ListRoomsRequest>) -> Response {
+    respond(crate::list_rooms(state.rooms, request).await)
+}
+
+/// room_id trên path là nguồn đúng, body chỉ cần player
+async fn join_room(
+    State(state): State<ApiState>,
+    Path(room_id): Path<String>,
+    Json(mut request): Json<JoinRoomRequest>,
+) -> Response {
+    request.room_id = room_id;
+    respond(crate::join_room(state.rooms, request).await)
+}
+
+async fn leave_room(
+    State(state): State<ApiState>,
+    Path(room_id): Path<String>,
+    Json(mut request): Json<LeaveRoomRequest>,
+) -> Response {
+    request.room_id = room_id;
+    respond(crate::leave_room(state.rooms, request).await)
+}
+
+async fn get_room(State(state): State<ApiState>, Path(room_id): Path<String>) -> Response {
+    respond(crate::get_room(state.rooms, room_id).await)
+}
+
+async fn list_players(State(state): State<ApiState>, Path(room_id): Path<String>) -> Response {
+    respond(crate::list_players(state.rooms, room_id).await)
+}
+
+async fn kick_player(
+    State(state): State<ApiState>,
+    Path(room_id): Path<String>,
+    Json(mut request): Json<KickPlayerRequest>,
+) -> Response {
+    request.room_id = room_id;
+    respond(crate::kick_player(state.rooms, request).await)
+}
+
+async fn finish_room(State(state): State<ApiState>, Path(room_id): Path<String>) -> Response {
+    respond(crate::finish_room(state.rooms, room_id).await)
+}
+
+async fn assign_room(State(state): State<ApiState>, Json(request): Json<AssignRoomRequest>) -> Response {
+    respond(crate::assign_room(state.rooms, request).await)
+}
+
+async fn resolve_invite(State(state): State<ApiState>, Json(request): Json<ResolveInviteRequest>) -> Response {
+    respond(crate::resolve_invite(state.rooms, request).await)
+}
+
+async fn regenerate_invite(
+    State(state): State<ApiState>,
+    Path(room_id): Path<String>,
+    Json(mut request): Json<RoomInviteRequest>,
+) -> Response {
+    request.room_id = room_id;
+    respond(crate::regenerate_invite(state.rooms, request).await)
+}
+
+async fn revoke_invite(
+    State(state): State<ApiState>,
+    Path(room_id): Path<String>,
+    Json(mut request): Json<RoomInviteRequest>,
+) -> Response {
+    request.room_id = room_id;
+    respond(crate::revoke_invite(state.rooms, request).await)
+}
+
+async fn create_party(State(state): State<ApiState>, Json(request): Json<CreatePartyRequest>) -> Response {
+    respond(crate::create_party(state.rooms, request).await)
+}
+
+async fn invite_to_party(
+    State(state): State<ApiState>,
+    Path(party_id): Path<String>,
+    Json(mut request): Json<PartyInviteRequest>,
+) -> Response {
+    request.party_id = party_id;
+    respond(crate::invite_to_party(state.rooms, request).await)
+}
+
+async fn join_party(State(state): State<ApiState>, Json(request): Json<JoinPartyRequest>) -> Response {
+    respond(crate::join_party(state.rooms, request).await)
+}
+
+async fn leave_party(State(state): State<ApiState>, Json(request): Json<LeavePartyRequest>) -> Response {
+    respond(crate::leave_party(state.rooms, request).await)
+}
+
+async fn create_tournament(State(state): State<ApiState>, Json(request): Json<CreateTournamentRequest>) -> Response {
+    respond(state.tournaments.create_tournament(request).await)
+}
+
+async fn get_tournament(State(state): State<ApiState>, Path(tournament_id): Path<String>) -> Response {
+    Json(state.tournaments.get_tournament(&tournament_id).await).into_response()
+}
+
+async fn register_participant(
+    State(state): State<ApiState>,
+    Path(tournament_id): Path<String>,
+    Json(mut request): Json<RegisterParticipantRequest>,
+) -> Response {
+    request.tournament_id = tournament_id;
+    respond(state.tournaments.register_participant(request).await)
+}
+
+async fn start_tournament(
+    State(state): State<ApiState>,
+    Path(tournament_id): Path<String>,
+    Json(mut request): Json<StartTournamentRequest>,
+) -> Response {
+    request.tournament_id = tournament_id;
+    respond(state.tournaments.start_tournament(request).await)
+}
+
+async fn report_match_result(
+    State(state): State<ApiState>,
+    Path(tournament_id): Path<String>,
+    Json(mut request): Json<ReportMatchResultRequest>,
+) -> Response {
+    request.tournament_id = tournament_id;
+    respond(state.tournaments.report_match_result(request).await)
+}
@@ -0,0 +1,149 @@
+//! Tự bắt đầu phòng: phòng bật `auto_start` đủ `min_players_to_start` thì chuyển Starting, được gán worker và hẹn giờ,
+//! hết countdown thì chuyển InProgress. Mọi lần chuyển trạng thái đều lưu vào PocketBase.
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::{Room, RoomManagerState, RoomStatus};
+
+const DEFAULT_COUNTDOWN: Duration = Duration::from_secs(10);
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct AutoStartSettings {
+    /// Thời gian từ lúc đủ người tới lúc vào trận, player vẫn join được trong lúc này
+    pub countdown: Duration,
+}
+
+impl AutoStartSettings {
+    /// Đọc ROOM_MANAGER_START_COUNTDOWN_SECS
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            countdown: std::env::var("ROOM_MANAGER_START_COUNTDOWN_SECS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .map_or(defaults.countdown, Duration::from_secs),
+        }
+    }
+}
+
+impl Default for AutoStartSettings {
+    fn default() -> Self {
+        Self { countdown: DEFAULT_COUNTDOWN }
+    }
+}
+
+impl RoomManagerState {
+    /// Số player để phòng tự bắt đầu; None nếu settings tắt `auto_start`.
+    /// Settings không ghi `min_players_to_start` thì dùng mặc định của game mode
+    pub fn start_threshold(&self, room: &Room) -> Option<u32> {
+        if !room.settings.get("auto_start").and_then(|value| value.as_bool()).unwrap_or(true) {
+            return None;
+        }
+        let min_players = room
+            .settings
+            .get("min_players_to_start")
+            .and_then(|value| value.as_u64())
+            .map_or(self.capacity.for_mode(&room.game_mode).min_players_to_start, |value| value as u32);
+        Some(min_players.max(1))
+    }
+
+    /// Gọi sau khi số player của phòng đổi: Waiting đủ người thì sang Starting và hẹn `starts_at`,
+    /// Starting bị rời tới dưới ngưỡng thì quay về Waiting
+    pub(crate) async fn update_start_schedule(&mut self, room_id: &str) {
+        let Some(room) = self.rooms.get(room_id) else {
+            return;
+        };
+        let enough_players = self.start_threshold(room).is_some_and(|threshold| room.current_players >= threshold);
+        let now = Utc::now();
+        let starts_at = match (&room.status, enough_players) {
+            (RoomStatus::Waiting, true) => now + chrono::Duration::from_std(self.auto_start.countdown).unwrap_or_default(),
+            (RoomStatus::Starting, false) => {
+                self.set_start_status(room_id, RoomStatus::Waiting, None, now).await;
+                info!(%room_id, "Not enough players anymore, start cancelled");
+                return;
+            }
+            _ => return,
+        };
+        let worker_endpoint = self.assign_worker(room_id);
+        self.set_start_status(room_id, RoomStatus::Starting, Some(starts_at), now).await;
+        info!(%room_id, %starts_at, ?worker_endpoint, "Room has enough players, starting after countdown");
+    }
+
+    /// Lượt kiểm tra định kỳ: xếp lịch cho phòng đủ người mà chưa qua `join_room` (vd. nạp lại từ database)
+    /// và cho phòng hết countdown vào trận. Trả về id các phòng vừa vào trận, sắp theo id
+    pub async fn check_room_starts(&mut self) -> Vec<String> {
+        let open: Vec<String> = self
+            .rooms
+            .values()
+            .filter(|room| room.status.accepts_players())
+            .map(|room| room.id.clone())
+            .collect();
+        for room_id in &open {
+            self.update_start_schedule(room_id).await;
+        }
+
+        let now = Utc::now();
+        let mut due: Vec<String> = self
+            .rooms
+            .values()
+            .filter(|room| room.status == RoomStatus::Starting && room.starts_at.is_some_and(|starts_at| starts_at <= now))
+            .map(|room| room.id.clone())
+            .collect();
+        due.sort();
+
+        // Worker đã gán lúc Starting mà giờ down thì gán lại; hết worker khoẻ thì phòng chờ lượt sau
+        due.retain(|room_id| {
+            let assigned = self.assign_worker(room_id).is_some();
+            if !assigned {
+                warn!(%room_id, "No healthy worker, room start postponed");
+            }
+            assigned
+        });
+        for room_id in &due {
+            self.set_start_status(room_id, RoomStatus::InProgress, None, now).await;
+            info!(%room_id, "Countdown finished, room in progress");
+        }
+        if !due.is_empty() {
+            self.refresh_gauges();
+        }
+        due
+    }
+
+    async fn set_start_status(&mut self, room_id: &str, status: RoomStatus, starts_at: Option<DateTime<Utc>>, now: DateTime<Utc>) {
+        let Some(room) = self.rooms.get_mut(room_id) else {
+            return;
+        };
+        room.status = status;
+        room.starts_at = starts_at;
+        room.updated_at = now;
+
+        let update = serde_json::json!({
+            "status": serde_json::to_string(&room.status).unwrap_or_default(),
+            "starts_at": room.starts_at,
+            "worker_endpoint": room.worker_endpoint,
+            "updated_at": now,
+        });
+        if let Err(e) = self.pocketbase.update_record("rooms", room_id, update).await {
+            warn!("Failed to persist start status of room {}: {}", room_id, e);
+        }
+    }
+}
+
+pub fn spawn(state: Arc<RwLock<RoomManagerState>>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let started = state.write().await.check_room_starts().await;
+            if !started.is_empty() {
+                info!(?started, "auto-start moved rooms in progress");
+            }
+        }
+    })
+}
@@ -0,0 +1,133 @@
+//! Sức chứa phòng theo game mode: matchmaking tự tạo phòng với `default_max_players` của mode,
+//! còn `create_room` từ chối `max_players` ngoài khoảng cho phép (team mode phải chẵn để chia đều hai đội).
+//! Mặc định lấy từ descriptor trong registry game mode, env/override chỉ đổi phần mặc định.
+
+use std::collections::BTreeMap;
+
+use common_net::game_modes::{self, GameModeDescriptor};
+use serde::{Deserialize, Serialize};
+
+use crate::GameMode;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModeCapacity {
+    /// Sức chứa của phòng matchmaking tự tạo
+    pub default_max_players: u32,
+    /// Ghi vào settings của phòng tự tạo, worker chờ đủ số này mới bắt đầu
+    pub min_players_to_start: u32,
+    /// Khoảng `max_players` hợp lệ khi tạo phòng
+    pub min_capacity: u32,
+    pub max_capacity: u32,
+    /// Hai đội bằng nhau: `max_players` phải chẵn
+    pub even_teams: bool,
+}
+
+impl ModeCapacity {
+    /// Lý do `max_players` không hợp lệ với mode, None nếu hợp lệ
+    pub fn check(&self, max_players: u32) -> Option<String> {
+        if max_players < self.min_capacity || max_players > self.max_capacity {
+            return Some(format!(
+                "max_players must be between {} and {}",
+                self.min_capacity, self.max_capacity
+            ));
+        }
+        if self.even_teams && !max_players.is_multiple_of(2) {
+            return Some("max_players must be even so teams are balanced".to_string());
+        }
+        None
+    }
+
+    /// Đưa default về khoảng hợp lệ (env có thể đặt sai), team mode làm tròn lên số chẵn
+    fn normalized(mut self) -> Self {
+        if self.even_teams {
+            self.min_capacity += self.min_capacity % 2;
+            self.max_capacity -= self.max_capacity % 2;
+            self.default_max_players += self.default_max_players % 2;
+        }
+        self.default_max_players = self.default_max_players.clamp(self.min_capacity, self.max_capacity);
+        self.min_players_to_start = self.min_players_to_start.clamp(1, self.default_max_players);
+        self
+    }
+}
+
+impl From<&GameModeDescriptor> for ModeCapacity {
+    fn from(mode: &GameModeDescriptor) -> Self {
+        Self {
+            default_max_players: mode.default_setting("max_players").unwrap_or(mode.max_players),
+            min_players_to_start: mode.default_setting("min_players_to_start").unwrap_or(mode.min_players),
+            min_capacity: mode.min_players,
+            max_capacity: mode.max_players,
+            even_teams: mode.team_based,
+        }
+        .normalized()
+    }
+}
+
+/// Sức chứa đã chỉnh theo mode id; mode không có ở đây dùng descriptor của registry
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CapacityConfig {
+    pub overrides: BTreeMap<String, ModeCapacity>,
+}
+
+impl CapacityConfig {
+    pub fn for_mode(&self, mode: &GameMode) -> ModeCapacity {
+        if let Some(capacity) = self.overrides.get(mode.as_str()) {
+            return capacity.clone();
+        }
+        mode.descriptor().as_ref().map(ModeCapacity::from).unwrap_or_else(|| {
+            // Id đã parse luôn có trong registry; phòng hờ thì chỉ cho phép phòng 1v1
+            ModeCapacity { default_max_players: 2, min_players_to_start: 2, min_capacity: 2, max_capacity: 2, even_teams: false }
+        })
+    }
+
+    pub fn set(&mut self, mode: &GameMode, capacity: ModeCapacity) {
+        self.overrides.insert(mode.as_str().to_string(), capacity.normalized());
+    }
+
+    /// Chồng override từ file config lên `self`; mode id lạ hoặc khoảng sức chứa rỗng thì trả lỗi, `self` giữ nguyên
+    pub fn with_overrides(&self, overrides: &BTreeMap<String, ModeCapacity>) -> Result<Self, String> {
+        let mut config = self.clone();
+        for (id, capacity) in overrides {
+            let mode = GameMode::parse(id).map_err(|err| err.to_string())?;
+            let (mut min_capacity, mut max_capacity) = (capacity.min_capacity, capacity.max_capacity);
+            if capacity.even_teams {
+                min_capacity += min_capacity % 2;
+                max_capacity -= max_capacity % 2;
+            }
+            if min_capacity == 0 || min_capacity > max_capacity {
+                return Err(format!(
+                    "capacity of mode '{id}' has empty range {}..={}",
+                    capacity.min_capacity, capacity.max_capacity
+                ));
+            }
+            config.set(&mode, capacity.clone());
+        }
+        Ok(config)
+    }
+
+    /// Đọc ROOM_MANAGER_<MODE>_MAX_PLAYERS và ROOM_MANAGER_<MODE>_MIN_PLAYERS_TO_START cho mọi mode
+    /// đã đăng ký (MODE là id viết hoa, vd. TEAM_DEATHMATCH), thiếu thì dùng mặc định của descriptor
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        for mode in game_modes::all() {
+            let capacity = ModeCapacity::from(&mode);
+            let var = |key: &str| {
+                std::env::var(format!("ROOM_MANAGER_{}_{key}", mode.id.to_uppercase()))
+                    .ok()
+                    .and_then(|value| value.parse::<u32>().ok())
+            };
+            let (max_players, min_players_to_start) = (var("MAX_PLAYERS"), var("MIN_PLAYERS_TO_START"));
+            if max_players.is_none() && min_players_to_start.is_none() {
+                continue;
+            }
+            let capacity = ModeCapacity {
+                default_max_players: max_players.unwrap_or(capacity.default_max_players),
+                min_players_to_start: min_players_to_start.unwrap_or(capacity.min_players_to_start),
+                ..capacity
+            }
+            .normalized();
+            config.overrides.insert(mode.id, capacity);
+        }
+        config
+    }
+}
@@ -0,0 +1,25 @@
+//! Invite code của phòng private: ngắn để đọc cho nhau, bỏ các ký tự dễ nhầm (0/O, 1/I/L)
+
+use uuid::Uuid;
+
+pub const INVITE_CODE_LEN: usize = 6;
+
+const ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+
+/// Code ngẫu nhiên; 6 byte đầu của UUID v4 đều là random nên lấy làm nguồn entropy
+pub fn generate() -> String {
+    Uuid::new_v4()
+        .as_bytes()
+        .iter()
+        .take(INVITE_CODE_LEN)
+        .map(|byte| ALPHABET[*byte as usize % ALPHABET.len()] as char)
+        .collect()
+}
+
+/// Chuẩn hoá code người chơi nhập: bỏ khoảng trắng/gạch nối, viết hoa
+pub fn normalize(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
@@ -1,11 +1,12 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env,
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use common_net::{
+    health::{self, DependencyStatus},
     metrics::{self, MatchmakingMetrics},
     shutdown,
 };
@@ -15,6 +16,16 @@ use tokio::{sync::{oneshot, RwLock}, time::interval};
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+pub mod api;
+pub mod autostart;
+pub mod capacity;
+pub mod invite;
+pub mod names;
+pub mod party;
+pub mod reconcile;
+pub mod tournament;
+pub mod workers;
+
 pub type BoxError = metrics::BoxError;
 
 const DEFAULT_METRICS_ADDR: &str = "127.0.0.1:3200";
@@ -35,17 +46,23 @@ pub struct Room {
     pub host_player_id: String,
     pub worker_endpoint: Option<String>, // Worker được assign để chạy game này
     pub settings: serde_json::Value,
+    #[serde(default)]
+    pub backfill_with_bots: bool, // Worker lấp chỗ trống bằng bot
+    /// Phòng private không xuất hiện trong list/assign, join phải kèm `invite_code`
+    #[serde(default)]
+    pub is_private: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invite_code: Option<String>,
+    /// Player bị host/admin ban, `join_room` từ chối cho tới khi phòng đóng
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub banned_players: Vec<String>,
+    /// Phòng Starting: thời điểm hết countdown và vào trận
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum GameMode {
-    #[serde(rename = "deathmatch")]
-    Deathmatch,
-    #[serde(rename = "team_deathmatch")]
-    TeamDeathmatch,
-    #[serde(rename = "capture_the_flag")]
-    CaptureTheFlag,
-}
+/// Id game mode trong registry của common-net; JSON là chuỗi id, id chưa đăng ký bị từ chối
+pub use common_net::game_modes::GameModeId as GameMode;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum RoomStatus {
@@ -61,6 +78,13 @@ pub enum RoomStatus {
     Closed,
 }
 
+impl RoomStatus {
+    /// Phòng còn nhận player: đang chờ, hoặc đang đếm ngược trước khi vào trận
+    pub fn accepts_players(&self) -> bool {
+        matches!(self, RoomStatus::Waiting | RoomStatus::Starting)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub id: String,
@@ -82,14 +106,38 @@ pub enum PlayerStatus {
     Left,
 }
 
+/// Đếm như `RoomManagerState::counts`, dùng được cho danh sách phòng lấy qua REST API
+pub fn room_counts<'a>(rooms: impl IntoIterator<Item = &'a Room>) -> (usize, usize) {
+    rooms
+        .into_iter()
+        .filter(|room| !matches!(room.status, RoomStatus::Closed | RoomStatus::Finished))
+        .fold((0, 0), |(rooms, players), room| (rooms + 1, players + room.current_players as usize))
+}
+
 // Room Manager state
 #[derive(Debug)]
 pub struct RoomManagerState {
     pub rooms: HashMap<String, Room>,
     pub players: HashMap<String, Player>,
+    /// invite code (đã normalize) -> room_id của phòng private
+    pub invite_codes: HashMap<String, String>,
+    /// party_id -> Party
+    pub parties: HashMap<String, party::Party>,
+    /// invite code (đã normalize) -> party_id
+    pub party_invites: HashMap<String, String>,
     pub pocketbase: PocketBaseClient,
     pub heartbeat_interval: Duration,
     pub room_ttl: Duration,
+    /// Party không hoạt động quá lâu thì heartbeat giải tán
+    pub party_ttl: Duration,
+    /// Sức chứa mặc định và khoảng max_players hợp lệ theo game mode
+    pub capacity: capacity::CapacityConfig,
+    /// Countdown cho phòng tự bắt đầu
+    pub auto_start: autostart::AutoStartSettings,
+    /// Worker chạy sim được gán cho phòng khi bắt đầu
+    pub workers: workers::WorkerPool,
+    /// Kiểm tra tên hiển thị lúc join
+    pub names: names::NamePolicy,
 }
 
 impl RoomManagerState {
@@ -99,16 +147,87 @@ impl RoomManagerState {
         Ok(Self {
             rooms: HashMap::new(),
             players: HashMap::new(),
+            invite_codes: HashMap::new(),
+            parties: HashMap::new(),
+            party_invites: HashMap::new(),
             pocketbase,
             heartbeat_interval: Duration::from_secs(30),
             room_ttl: Duration::from_secs(300), // 5 minutes
+            party_ttl: Duration::from_secs(600), // 10 minutes
+            capacity: capacity::CapacityConfig::default(),
+            auto_start: autostart::AutoStartSettings::default(),
+            workers: workers::WorkerPool::default(),
+            names: names::NamePolicy::default(),
         })
     }
 
+    /// (số phòng đang hoạt động, tổng player trong các phòng đó) - phòng Closed/Finished không tính
+    pub fn counts(&self) -> (usize, usize) {
+        room_counts(self.rooms.values())
+    }
+
+    /// Số player trong các phòng chưa vào trận (chờ đủ người / đang đếm ngược)
+    pub fn waiting_players(&self) -> usize {
+        self.rooms
+            .values()
+            .filter(|room| room.status.accepts_players())
+            .map(|room| room.current_players as usize)
+            .sum()
+    }
+
+    /// Tính lại gauges từ state hiện tại sau mỗi thay đổi, thay vì inc/dec dễ lệch
+    fn refresh_gauges(&self) {
+        let (rooms, players) = self.counts();
+        matchmaking_metrics().set_active_rooms(rooms as i64);
+        matchmaking_metrics().set_players_in_rooms(players as i64);
+        matchmaking_metrics().set_waiting_players(self.waiting_players() as i64);
+    }
+
+    /// Phòng matchmaking tự tạo: sức chứa và số người bắt đầu theo cấu hình của mode
+    fn auto_room_request(&self, game_mode: GameMode, host_player_id: &str) -> CreateRoomRequest {
+        let capacity = self.capacity.for_mode(&game_mode);
+        CreateRoomRequest {
+            name: format!("Auto Room {}", &Uuid::new_v4().to_string()[..8]),
+            max_players: capacity.default_max_players,
+            settings: Some(serde_json::json!({ "min_players_to_start": capacity.min_players_to_start })),
+            game_mode,
+            host_player_id: host_player_id.to_string(),
+            backfill_with_bots: false,
+            is_private: false,
+        }
+    }
+
+    /// Invite code chưa được phòng nào dùng
+    fn unused_invite_code(&self) -> String {
+        loop {
+            let code = invite::generate();
+            if !self.invite_codes.contains_key(&code) {
+                return code;
+            }
+        }
+    }
+
+    /// Bỏ phòng khỏi memory cùng invite code của nó
+    fn remove_room(&mut self, room_id: &str) {
+        if let Some(code) = self.rooms.remove(room_id).and_then(|room| room.invite_code) {
+            self.invite_codes.remove(&code);
+        }
+    }
+
     // Tạo phòng mới
     pub async fn create_room(&mut self, req: CreateRoomRequest) -> Result<CreateRoomResponse, BoxError> {
+        if let Some(error) = self.capacity.for_mode(&req.game_mode).check(req.max_players) {
+            return Ok(CreateRoomResponse {
+                room_id: String::new(),
+                success: false,
+                error: Some(error),
+                invite_code: None,
+            });
+        }
+
         let room_id = Uuid::new_v4().to_string();
         let now = chrono::Utc::now();
+        let invite_code = req.is_private.then(|| self.unused_invite_code());
 
         let room = Room {
             id: room_id.clone(),
@@ -122,6 +241,11 @@ impl RoomManagerState {
             host_player_id: req.host_player_id.clone(),
             worker_endpoint: None,
             settings: req.settings.unwrap_or(serde_json::json!({})),
+            backfill_with_bots: req.backfill_with_bots,
+            is_private: req.is_private,
+            invite_code: invite_code.clone(),
+            banned_players: Vec::new(),
+            starts_at: None,
         };
 
         // Lưu vào PocketBase
@@ -137,19 +261,28 @@ impl RoomManagerState {
             "host_player_id": room.host_player_id,
             "worker_endpoint": room.worker_endpoint,
             "settings": room.settings,
+            "backfill_with_bots": room.backfill_with_bots,
+            "is_private": room.is_private,
+            "invite_code": room.invite_code,
+            "banned_players": room.banned_players,
         });
 
         match self.pocketbase.create_record("rooms", room_data).await {
             Ok(_) => {
+                if let Some(code) = &invite_code {
+                    self.invite_codes.insert(code.clone(), room_id.clone());
+                }
                 self.rooms.insert(room_id.clone(), room);
 
                 matchmaking_metrics().inc_rooms_created();
+                self.refresh_gauges();
                 info!("Created room: {}", room_id);
 
                 Ok(CreateRoomResponse {
                     room_id,
                     success: true,
                     error: None,
+                    invite_code,
                 })
             }
             Err(e) => {
@@ -158,6 +291,7 @@ impl RoomManagerState {
                     room_id: String::new(),
                     success: false,
                     error: Some(format!("Database error: {}", e)),
+                    invite_code: None,
                 })
             }
         }
@@ -165,76 +299,91 @@ impl RoomManagerState {
 
     // Join phòng
     pub async fn join_room(&mut self, req: JoinRoomRequest) -> Result<JoinRoomResponse, BoxError> {
-        if let Some(room) = self.rooms.get_mut(&req.room_id) {
-            if room.current_players >= room.max_players {
-                return Ok(JoinRoomResponse {
-                    success: false,
-                    error: Some("Room is full".to_string()),
-                    room: None,
-                });
-            }
+        let Some(room) = self.rooms.get_mut(&req.room_id) else {
+            return Ok(JoinRoomResponse::failed("Room not found"));
+        };
+        if room.banned_players.contains(&req.player_id) {
+            return Ok(JoinRoomResponse::failed("You are banned from this room"));
+        }
 
-            if room.status != RoomStatus::Waiting {
-                return Ok(JoinRoomResponse {
-                    success: false,
-                    error: Some("Room is not accepting new players".to_string()),
-                    room: None,
-                });
+        if room.is_private {
+            let error = match req.invite_code.as_deref().map(invite::normalize) {
+                None => Some("Invite code required"),
+                Some(code) if room.invite_code.as_ref() != Some(&code) => Some("Invalid invite code"),
+                Some(_) => None,
+            };
+            if let Some(error) = error {
+                return Ok(JoinRoomResponse::failed(error));
             }
+        }
 
-            let now = chrono::Utc::now();
-            let player = Player {
-                id: req.player_id.clone(),
-                name: req.player_name,
-                room_id: req.room_id.clone(),
-                joined_at: now,
-                last_seen: now,
-                status: PlayerStatus::Connected,
-                team: None,
-            };
+        if room.current_players >= room.max_players {
+            return Ok(JoinRoomResponse::failed("Room is full"));
+        }
 
-            room.current_players += 1;
-            room.updated_at = now;
+        if !room.status.accepts_players() {
+            return Ok(JoinRoomResponse::failed("Room is not accepting new players"));
+        }
 
-            // Lưu player vào database
-            let player_data = serde_json::json!({
-                "id": player.id,
-                "name": player.name,
-                "room_id": player.room_id,
-                "joined_at": player.joined_at,
-                "last_seen": player.last_seen,
-                "status": serde_json::to_string(&player.status)?,
-                "team": player.team,
-            });
+        // Tên được chốt ở đây rồi mới tới worker, nên tên bị chặn không bao giờ vào game
+        let taken = self
+            .players
+            .values()
+            .filter(|player| player.room_id == req.room_id && player.id != req.player_id)
+            .map(|player| player.name.as_str());
+        let policy = names::NameCollisionPolicy::from_settings(&room.settings);
+        let player_name = match self.names.resolve(&req.player_name, taken, policy) {
+            Ok(name) => name,
+            Err(e) => {
+                info!(room_id = %req.room_id, player_id = %req.player_id, error = %e, "Rejected player name");
+                return Ok(JoinRoomResponse::failed(&e.to_string()));
+            }
+        };
 
-            match self.pocketbase.create_record("players", player_data).await {
-                Ok(_) => {
-                    self.players.insert(req.player_id.clone(), player);
-                    // Player joined - we could add a counter for this in the future
+        let now = chrono::Utc::now();
+        let player = Player {
+            id: req.player_id.clone(),
+            name: player_name.clone(),
+            room_id: req.room_id.clone(),
+            joined_at: now,
+            last_seen: now,
+            status: PlayerStatus::Connected,
+            team: None,
+        };
 
-                    Ok(JoinRoomResponse {
-                        success: true,
-                        error: None,
-                        room: Some(room.clone()),
-                    })
-                }
-                Err(e) => {
-                    // Rollback room state
-                    room.current_players -= 1;
-                    error!("Failed to save player to database: {}", e);
-                    Ok(JoinRoomResponse {
-                        success: false,
-                        error: Some(format!("Database error: {}", e)),
-                        room: None,
-                    })
-                }
+        room.current_players += 1;
+        room.updated_at = now;
+
+        // Lưu player vào database
+        let player_data = serde_json::json!({
+            "id": player.id,
+            "name": player.name,
+            "room_id": player.room_id,
+            "joined_at": player.joined_at,
+            "last_seen": player.last_seen,
+            "status": serde_json::to_string(&player.status)?,
+            "team": player.team,
+        });
+
+        match self.pocketbase.create_record("players", player_data).await {
+            Ok(_) => {
+                self.players.insert(req.player_id.clone(), player);
+                self.update_start_schedule(&req.room_id).await;
+                self.refresh_gauges();
+
+                Ok(JoinRoomResponse {
+                    success: true,
+                    error: None,
+                    room: self.rooms.get(&req.room_id).cloned(),
+                    player_name: Some(player_name),
+                })
+            }
+            Err(e) => {
+                // Rollback room state
+                room.current_players -= 1;
+                error!("Failed to save player to database: {}", e);
+                Ok(JoinRoomResponse::failed(&format!("Database error: {}", e)))
             }
-        } else {
-            Ok(JoinRoomResponse {
-                success: false,
-                error: Some("Room not found".to_string()),
-                room: None,
-            })
         }
     }
 
@@ -242,6 +391,11 @@ impl RoomManagerState {
     pub async fn list_rooms(&self, req: ListRoomsRequest) -> Result<ListRoomsResponse, BoxError> {
         let mut rooms: Vec<Room> = self.rooms.values().cloned().collect();
 
+        // Phòng private chỉ hiện cho admin và khi hỏi rõ
+        if !(req.include_private && req.admin) {
+            rooms.retain(|room| !room.is_private);
+        }
+
         // Filter theo game_mode nếu có
         if let Some(game_mode) = req.game_mode {
             rooms.retain(|room| room.game_mode == game_mode);
@@ -252,17 +406,34 @@ impl RoomManagerState {
             rooms.retain(|room| room.status == status);
         }
 
-        Ok(ListRoomsResponse { rooms })
+        if req.has_space {
+            rooms.retain(|room| room.current_players < room.max_players);
+        }
+
+        rooms.sort_by(|a, b| req.sort.compare(a, b));
+        let total = rooms.len();
+        let rooms = rooms.into_iter().skip(req.offset).take(req.limit.unwrap_or(usize::MAX)).collect();
+
+        Ok(ListRoomsResponse { rooms, total })
     }
 
     // Assign player vào phòng phù hợp
     pub async fn assign_room(&mut self, req: AssignRoomRequest) -> Result<AssignRoomResponse, BoxError> {
+        // Player trong party đi cùng cả party, và chỉ leader được xếp hàng
+        if let Some(party) = self.party_of(&req.player_id) {
+            if party.leader_id != req.player_id {
+                return Ok(AssignRoomResponse::failed("Only the party leader can start queueing"));
+            }
+            let party_id = party.id.clone();
+            return self.assign_party(&party_id, req.game_mode).await;
+        }
+
         let mut best_room_id: Option<String> = None;
         let mut best_player_count = u32::MAX;
 
         // Tìm phòng phù hợp
         for (room_id, room) in &self.rooms {
-            if room.status != RoomStatus::Waiting {
+            if !room.status.accepts_players() || room.is_private {
                 continue;
             }
 
@@ -301,12 +472,14 @@ impl RoomManagerState {
                 room.updated_at = now;
 
                 self.players.insert(req.player_id.clone(), player);
-
-                // Player assigned - we could add a counter for this in the future
+                // Phòng vừa đủ người thì được gán worker ở đây, response mang endpoint đó
+                self.update_start_schedule(&room_id).await;
+                self.refresh_gauges();
 
                 Ok(AssignRoomResponse {
-                    room_id: Some(room.id.clone()),
-                    worker_endpoint: room.worker_endpoint.clone(),
+                    room_id: Some(room_id.clone()),
+                    worker_endpoint: self.rooms.get(&room_id).and_then(|room| room.worker_endpoint.clone()),
+                    ..Default::default()
                 })
             } else {
                 Err(Box::new(std::io::Error::new(
@@ -316,13 +489,7 @@ impl RoomManagerState {
             }
         } else {
             // Không tìm thấy phòng phù hợp, tạo phòng mới
-            let create_req = CreateRoomRequest {
-                name: format!("Auto Room {}", Uuid::new_v4().to_string()[..8].to_string()),
-                game_mode: req.game_mode.unwrap_or(GameMode::Deathmatch),
-                max_players: 4,
-                host_player_id: req.player_id.clone(),
-                settings: Some(serde_json::json!({})),
-            };
+            let create_req = self.auto_room_request(req.game_mode.unwrap_or_else(GameMode::deathmatch), &req.player_id);
 
             match self.create_room(create_req).await {
                 Ok(create_resp) => {
@@ -332,12 +499,13 @@ impl RoomManagerState {
                             room_id: create_resp.room_id.clone(),
                             player_id: req.player_id.clone(),
                             player_name: format!("Player_{}", &req.player_id[..8]),
+                            invite_code: None,
                         };
 
                         match self.join_room(join_req).await {
                             Ok(_) => Ok(AssignRoomResponse {
                                 room_id: Some(create_resp.room_id),
-                                worker_endpoint: None,
+                                ..Default::default()
                             }),
                             Err(e) => Err(e),
                         }
@@ -353,6 +521,242 @@ impl RoomManagerState {
         }
     }
 
+    // Rời phòng; host không có record Player nhưng vẫn chiếm một slot nên cũng rời được
+    pub async fn leave_room(&mut self, req: LeaveRoomRequest) -> Result<LeaveRoomResponse, BoxError> {
+        let Some(room) = self.rooms.get(&req.room_id) else {
+            return Ok(LeaveRoomResponse {
+                success: false,
+                error: Some("Room not found".to_string()),
+            });
+        };
+        let in_room = room.host_player_id == req.player_id
+            || self
+                .players
+                .get(&req.player_id)
+                .is_some_and(|player| player.room_id == req.room_id);
+        if !in_room {
+            return Ok(LeaveRoomResponse {
+                success: false,
+                error: Some("Player is not in this room".to_string()),
+            });
+        }
+
+        self.remove_member(&req.room_id, &req.player_id).await;
+
+        Ok(LeaveRoomResponse {
+            success: true,
+            error: None,
+        })
+    }
+
+    /// Host (hoặc admin qua gateway) đuổi player khỏi phòng; `ban` thì player không join lại được
+    pub async fn kick_player(&mut self, req: KickPlayerRequest) -> Result<KickPlayerResponse, BoxError> {
+        let Some(room) = self.rooms.get_mut(&req.room_id) else {
+            return Ok(KickPlayerResponse::failed("Room not found"));
+        };
+        if !req.admin && room.host_player_id != req.requesting_player_id {
+            return Ok(KickPlayerResponse::failed("Only the host can kick players"));
+        }
+        if room.host_player_id == req.target_player_id {
+            return Ok(KickPlayerResponse::failed("The host cannot be kicked"));
+        }
+
+        let in_room = self
+            .players
+            .get(&req.target_player_id)
+            .is_some_and(|player| player.room_id == req.room_id);
+        // Ban được cả player chưa/không còn trong phòng, kick thường thì phải đang ở trong
+        if !in_room && !req.ban {
+            return Ok(KickPlayerResponse::failed("Player is not in this room"));
+        }
+
+        if req.ban && !room.banned_players.contains(&req.target_player_id) {
+            room.banned_players.push(req.target_player_id.clone());
+            room.updated_at = chrono::Utc::now();
+
+            // Lưu vào record của phòng để ban còn hiệu lực sau khi room-manager restart
+            let update = serde_json::json!({ "banned_players": room.banned_players });
+            if let Err(e) = self.pocketbase.update_record("rooms", &req.room_id, update).await {
+                warn!("Failed to persist ban list of room {}: {}", req.room_id, e);
+            }
+        }
+
+        if in_room {
+            self.remove_member(&req.room_id, &req.target_player_id).await;
+        }
+        info!(
+            room_id = %req.room_id,
+            target = %req.target_player_id,
+            ban = req.ban,
+            "Kicked player from room"
+        );
+
+        Ok(KickPlayerResponse {
+            success: true,
+            error: None,
+            removed: in_room,
+        })
+    }
+
+    /// Player của phòng theo thứ tự vào phòng (host không có record Player)
+    pub fn room_players(&self, room_id: &str) -> Vec<Player> {
+        let mut players: Vec<Player> =
+            self.players.values().filter(|player| player.room_id == room_id).cloned().collect();
+        players.sort_by(|a, b| a.joined_at.cmp(&b.joined_at).then_with(|| a.id.cmp(&b.id)));
+        players
+    }
+
+    pub fn list_players(&self, room_id: &str) -> ListPlayersResponse {
+        match self.rooms.get(room_id) {
+            Some(room) => ListPlayersResponse {
+                success: true,
+                error: None,
+                host_player_id: Some(room.host_player_id.clone()),
+                current_players: room.current_players,
+                players: self.room_players(room_id),
+            },
+            None => ListPlayersResponse {
+                success: false,
+                error: Some("Room not found".to_string()),
+                host_player_id: None,
+                current_players: 0,
+                players: Vec::new(),
+            },
+        }
+    }
+
+    /// Bỏ player khỏi phòng (leave hoặc kick). Host rời thì player vào sớm nhất lên làm host,
+    /// phòng không còn ai thì đóng; số player/host/trạng thái mới được lưu vào record phòng
+    async fn remove_member(&mut self, room_id: &str, player_id: &str) {
+        let had_record = self.players.remove(player_id).is_some();
+        let next_host = self.room_players(room_id).into_iter().next().map(|player| player.id);
+        if let Some(room) = self.rooms.get_mut(room_id) {
+            let now = chrono::Utc::now();
+            room.current_players = room.current_players.saturating_sub(1);
+            room.updated_at = now;
+            if room.host_player_id == player_id {
+                if let Some(next_host) = next_host {
+                    info!(%room_id, old_host = %player_id, new_host = %next_host, "Host left, migrated host");
+                    room.host_player_id = next_host;
+                }
+            }
+            if room.current_players == 0 {
+                room.status = RoomStatus::Closed;
+                info!(%room_id, "Last player left, closing room");
+            }
+
+            let update = serde_json::json!({
+                "current_players": room.current_players,
+                "host_player_id": room.host_player_id,
+                "status": serde_json::to_string(&room.status).unwrap_or_default(),
+                "updated_at": now,
+            });
+            if let Err(e) = self.pocketbase.update_record("rooms", room_id, update).await {
+                warn!("Failed to persist membership of room {}: {}", room_id, e);
+            }
+        }
+        self.update_start_schedule(room_id).await;
+        self.refresh_gauges();
+
+        // Record player trong database chỉ là bản ghi phụ, xoá lỗi thì heartbeat/sync xử lý sau
+        if !had_record {
+            return;
+        }
+        if let Err(e) = self.pocketbase.delete_record("players", player_id).await {
+            warn!("Failed to delete player record {}: {}", player_id, e);
+        }
+    }
+
+    /// Đánh dấu phòng đã đấu xong (trận giải đấu đã có kết quả), không còn tính vào gauge
+    pub(crate) async fn finish_room(&mut self, room_id: &str) {
+        let Some(room) = self.rooms.get_mut(room_id) else {
+            return;
+        };
+        let now = chrono::Utc::now();
+        room.status = RoomStatus::Finished;
+        room.updated_at = now;
+        self.refresh_gauges();
+
+        let Ok(status) = serde_json::to_string(&RoomStatus::Finished) else {
+            return;
+        };
+        let update = serde_json::json!({ "status": status, "updated_at": now });
+        if let Err(e) = self.pocketbase.update_record("rooms", room_id, update).await {
+            warn!("Failed to persist finished status of room {}: {}", room_id, e);
+        }
+    }
+
+    /// Thông tin tối thiểu của phòng ứng với invite code, để client hiện màn hình xác nhận
+    pub fn resolve_invite(&self, req: ResolveInviteRequest) -> ResolveInviteResponse {
+        let room = self
+            .invite_codes
+            .get(&invite::normalize(&req.code))
+            .and_then(|room_id| self.rooms.get(room_id))
+            .filter(|room| !matches!(room.status, RoomStatus::Closed | RoomStatus::Finished));
+        match room {
+            Some(room) => ResolveInviteResponse {
+                success: true,
+                error: None,
+                room: Some(InviteRoomInfo {
+                    room_id: room.id.clone(),
+                    name: room.name.clone(),
+                    game_mode: room.game_mode.clone(),
+                    current_players: room.current_players,
+                    max_players: room.max_players,
+                }),
+            },
+            None => ResolveInviteResponse {
+                success: false,
+                error: Some("Invite code not found".to_string()),
+                room: None,
+            },
+        }
+    }
+
+    /// Host tạo invite code mới cho phòng private, code cũ hết hiệu lực
+    pub async fn regenerate_invite(&mut self, req: RoomInviteRequest) -> Result<RoomInviteResponse, BoxError> {
+        let code = self.unused_invite_code();
+        self.set_invite_code(req, Some(code)).await
+    }
+
+    /// Host thu hồi invite code; phòng vẫn private nên không ai join được cho tới khi tạo code mới
+    pub async fn revoke_invite(&mut self, req: RoomInviteRequest) -> Result<RoomInviteResponse, BoxError> {
+        self.set_invite_code(req, None).await
+    }
+
+    async fn set_invite_code(&mut self, req: RoomInviteRequest, code: Option<String>) -> Result<RoomInviteResponse, BoxError> {
+        let Some(room) = self.rooms.get_mut(&req.room_id) else {
+            return Ok(RoomInviteResponse::failed("Room not found"));
+        };
+        if room.host_player_id != req.player_id {
+            return Ok(RoomInviteResponse::failed("Only the host can manage invite codes"));
+        }
+        if !room.is_private {
+            return Ok(RoomInviteResponse::failed("Room is not private"));
+        }
+
+        let previous = std::mem::replace(&mut room.invite_code, code.clone());
+        room.updated_at = chrono::Utc::now();
+        if let Some(previous) = previous {
+            self.invite_codes.remove(&previous);
+        }
+        if let Some(code) = &code {
+            self.invite_codes.insert(code.clone(), req.room_id.clone());
+        }
+
+        // Memory là nguồn đúng cho join, database chỉ để khôi phục
+        let update = serde_json::json!({ "invite_code": code });
+        if let Err(e) = self.pocketbase.update_record("rooms", &req.room_id, update).await {
+            warn!("Failed to persist invite code of room {}: {}", req.room_id, e);
+        }
+
+        Ok(RoomInviteResponse {
+            success: true,
+            error: None,
+            invite_code: code,
+        })
+    }
+
     // Heartbeat để cleanup
     pub async fn heartbeat(&mut self) -> Result<(), BoxError> {
         let now = chrono::Utc::now();
@@ -385,6 +789,7 @@ impl RoomManagerState {
                 RoomStatus::Finished => {
                     (now - room.updated_at).num_seconds() > 60 // 1 minute for finished rooms
                 }
+                RoomStatus::Closed => true, // Đã đóng (vd. bởi reconciler), database giữ bản ghi
                 _ => false,
             };
 
@@ -394,10 +799,15 @@ impl RoomManagerState {
         }
 
         for room_id in rooms_to_remove {
-            self.rooms.remove(&room_id);
+            self.remove_room(&room_id);
             // Room removed - we could add a counter for this in the future
         }
 
+        self.expire_parties(now).await;
+
+        // Heartbeat chạy định kỳ nên cũng là chỗ reconcile gauges
+        self.refresh_gauges();
+
         Ok(())
     }
 
@@ -407,22 +817,51 @@ impl RoomManagerState {
         match self.pocketbase.list_records("rooms", None, None).await {
             Ok(records) => {
                 for record in records {
-                    let room_id = record.fields.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                    if !room_id.is_empty() {
-                        // Convert database record to Room struct
-                        // TODO: Implement proper conversion
+                    let Some(room) = room_from_record(record) else {
+                        continue;
+                    };
+                    if matches!(room.status, RoomStatus::Closed | RoomStatus::Finished) {
+                        continue;
+                    }
+                    if let Some(code) = &room.invite_code {
+                        self.invite_codes.insert(code.clone(), room.id.clone());
                     }
+                    self.rooms.insert(room.id.clone(), room);
                 }
+                self.refresh_gauges();
             }
             Err(e) => {
                 warn!("Failed to sync rooms from database: {}", e);
             }
         }
+        self.sync_parties().await;
 
         Ok(())
     }
 }
 
+/// Record `rooms` trong PocketBase -> Room. `game_mode`/`status` được lưu dạng JSON string
+/// (`"\"deathmatch\""`) nên phải parse thêm một lớp.
+fn room_from_record(record: pocketbase::Record) -> Option<Room> {
+    let mut fields: serde_json::Map<String, serde_json::Value> = record.fields.into_iter().collect();
+    for key in ["game_mode", "status"] {
+        if let Some(serde_json::Value::String(raw)) = fields.get(key) {
+            if let Ok(value) = serde_json::from_str::<serde_json::Value>(raw) {
+                fields.insert(key.to_string(), value);
+            }
+        }
+    }
+    fields.insert("id".to_string(), serde_json::Value::String(record.id.clone()));
+
+    match serde_json::from_value(serde_json::Value::Object(fields)) {
+        Ok(room) => Some(room),
+        Err(e) => {
+            warn!("Skipping room record {}: {}", record.id, e);
+            None
+        }
+    }
+}
+
 // API Request/Response types
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateRoomRequest {
@@ -431,20 +870,31 @@ pub struct CreateRoomRequest {
     pub max_players: u32,
     pub host_player_id: String,
     pub settings: Option<serde_json::Value>,
+    #[serde(default)]
+    pub backfill_with_bots: bool,
+    #[serde(default)]
+    pub is_private: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateRoomResponse {
     pub room_id: String,
     pub success: bool,
     pub error: Option<String>,
+    /// Chỉ có khi tạo phòng private, host chia sẻ cho người chơi khác
+    #[serde(default)]
+    pub invite_code: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JoinRoomRequest {
-    pub room_id: String,
+    #[serde(default)]
+    pub room_id: String, // REST API lấy từ path
     pub player_id: String,
     pub player_name: String,
+    /// Bắt buộc với phòng private
+    #[serde(default)]
+    pub invite_code: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -452,17 +902,220 @@ pub struct JoinRoomResponse {
     pub success: bool,
     pub error: Option<String>,
     pub room: Option<Room>,
+    /// Tên hiển thị đã chuẩn hoá (có thể kèm hậu tố khi trùng), worker và chat dùng đúng chuỗi này
+    #[serde(default)]
+    pub player_name: Option<String>,
+}
+
+impl JoinRoomResponse {
+    fn failed(error: &str) -> Self {
+        Self {
+            success: false,
+            error: Some(error.to_string()),
+            room: None,
+            player_name: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LeaveRoomRequest {
+    #[serde(default)]
+    pub room_id: String, // REST API lấy từ path
+    pub player_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LeaveRoomResponse {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetRoomResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    /// Record đầy đủ kể cả invite code và danh sách ban; caller tự ẩn với người không phải host
+    pub room: Option<Room>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListPlayersResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    pub host_player_id: Option<String>,
+    /// Tính cả host, nên có thể lớn hơn `players.len()` một
+    pub current_players: u32,
+    pub players: Vec<Player>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+pub struct FinishRoomResponse {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KickPlayerRequest {
+    #[serde(default)]
+    pub room_id: String, // REST API lấy từ path
+    pub requesting_player_id: String,
+    pub target_player_id: String,
+    #[serde(default)]
+    pub ban: bool,
+    /// Gateway đã xác thực admin token: bỏ qua kiểm tra host
+    #[serde(default)]
+    pub admin: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KickPlayerResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    /// true nếu player đang ở trong phòng và đã bị bỏ ra (ban trước khi join thì false)
+    #[serde(default)]
+    pub removed: bool,
+}
+
+impl KickPlayerResponse {
+    fn failed(error: &str) -> Self {
+        Self {
+            success: false,
+            error: Some(error.to_string()),
+            removed: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ListRoomsRequest {
     pub game_mode: Option<GameMode>,
     pub status: Option<RoomStatus>,
+    /// Kèm phòng private, chỉ có tác dụng cùng `admin`
+    #[serde(default)]
+    pub include_private: bool,
+    #[serde(default)]
+    pub admin: bool,
+    /// Bỏ phòng đã đủ người
+    #[serde(default)]
+    pub has_space: bool,
+    #[serde(default)]
+    pub sort: RoomSort,
+    /// Số phòng tối đa trả về sau `offset`; None là tất cả
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// Thứ tự của `list_rooms`, tiền tố `-` là giảm dần. Trùng khoá thì xếp theo id để phân trang ổn định
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoomSort {
+    #[serde(rename = "created_at")]
+    CreatedAt,
+    #[default]
+    #[serde(rename = "-created_at")]
+    CreatedAtDesc,
+    #[serde(rename = "current_players")]
+    CurrentPlayers,
+    #[serde(rename = "-current_players")]
+    CurrentPlayersDesc,
+    #[serde(rename = "name")]
+    Name,
+    #[serde(rename = "-name")]
+    NameDesc,
+}
+
+impl RoomSort {
+    pub const ALL: [RoomSort; 6] = [
+        RoomSort::CreatedAt,
+        RoomSort::CreatedAtDesc,
+        RoomSort::CurrentPlayers,
+        RoomSort::CurrentPlayersDesc,
+        RoomSort::Name,
+        RoomSort::NameDesc,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RoomSort::CreatedAt => "created_at",
+            RoomSort::CreatedAtDesc => "-created_at",
+            RoomSort::CurrentPlayers => "current_players",
+            RoomSort::CurrentPlayersDesc => "-current_players",
+            RoomSort::Name => "name",
+            RoomSort::NameDesc => "-name",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|sort| sort.as_str() == value)
+    }
+
+    fn compare(self, a: &Room, b: &Room) -> std::cmp::Ordering {
+        let ordering = match self {
+            RoomSort::CreatedAt => a.created_at.cmp(&b.created_at),
+            RoomSort::CreatedAtDesc => b.created_at.cmp(&a.created_at),
+            RoomSort::CurrentPlayers => a.current_players.cmp(&b.current_players),
+            RoomSort::CurrentPlayersDesc => b.current_players.cmp(&a.current_players),
+            RoomSort::Name => a.name.cmp(&b.name),
+            RoomSort::NameDesc => b.name.cmp(&a.name),
+        };
+        ordering.then_with(|| a.id.cmp(&b.id))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListRoomsResponse {
     pub rooms: Vec<Room>,
+    /// Số phòng khớp filter trước khi cắt theo limit/offset, client dùng để phân trang
+    #[serde(default)]
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResolveInviteRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResolveInviteResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    pub room: Option<InviteRoomInfo>,
+}
+
+/// Những gì người có invite code được xem trước khi join
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InviteRoomInfo {
+    pub room_id: String,
+    pub name: String,
+    pub game_mode: GameMode,
+    pub current_players: u32,
+    pub max_players: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoomInviteRequest {
+    #[serde(default)]
+    pub room_id: String, // REST API lấy từ path
+    pub player_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoomInviteResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    pub invite_code: Option<String>,
+}
+
+impl RoomInviteResponse {
+    fn failed(error: &str) -> Self {
+        Self {
+            success: false,
+            error: Some(error.to_string()),
+            invite_code: None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -471,15 +1124,36 @@ pub struct AssignRoomRequest {
     pub game_mode: Option<GameMode>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct AssignRoomResponse {
     pub room_id: Option<String>,
     pub worker_endpoint: Option<String>,
+    /// Đội được xếp khi assign cả party, mọi member chung đội này
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    /// Mọi member (leader đầu tiên) đã vào phòng cùng nhau
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub party_members: Vec<String>,
+    /// Lý do không xếp được, khi đó `room_id` là None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AssignRoomResponse {
+    fn failed(error: &str) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct RoomManagerSettings {
     pub metrics_addr: std::net::SocketAddr,
+    /// Sức chứa theo mode id, chồng lên cấu hình ROOM_MANAGER_<MODE>_*; đổi được lúc đang chạy
+    #[serde(default)]
+    pub capacity_overrides: BTreeMap<String, capacity::ModeCapacity>,
 }
 
 impl RoomManagerSettings {
@@ -489,7 +1163,7 @@ impl RoomManagerSettings {
         let metrics_addr = metrics_addr
             .parse()
             .map_err(|err| Box::new(err) as BoxError)?;
-        Ok(Self { metrics_addr })
+        Ok(Self { metrics_addr, capacity_overrides: BTreeMap::new() })
     }
 }
 
@@ -499,6 +1173,7 @@ impl Default for RoomManagerSettings {
             metrics_addr: DEFAULT_METRICS_ADDR
                 .parse()
                 .expect("default room-manager metrics addr"),
+            capacity_overrides: BTreeMap::new(),
         }
     }
 }
@@ -506,14 +1181,25 @@ impl Default for RoomManagerSettings {
 #[derive(Debug)]
 pub struct RoomManagerConfig {
     pub metrics_addr: std::net::SocketAddr,
+    pub capacity_overrides: BTreeMap<String, capacity::ModeCapacity>,
     pub ready_tx: Option<oneshot::Sender<std::net::SocketAddr>>,
+    /// PocketBase lưu room; None thì đọc POCKETBASE_URL
+    pub pocketbase_url: Option<String>,
+    /// Secret của REST API; None thì theo ROOM_MANAGER_INTERNAL_SECRET
+    pub internal_secret: Option<String>,
+    /// Settings mới khi file config đổi; chỉ `capacity_overrides` được áp dụng lúc đang chạy
+    pub reload_rx: Option<tokio::sync::watch::Receiver<RoomManagerSettings>>,
 }
 
 impl RoomManagerConfig {
     pub fn from_settings(settings: RoomManagerSettings) -> Self {
         Self {
             metrics_addr: settings.metrics_addr,
+            capacity_overrides: settings.capacity_overrides,
             ready_tx: None,
+            pocketbase_url: None,
+            internal_secret: None,
+            reload_rx: None,
         }
     }
 
@@ -526,6 +1212,29 @@ pub fn matchmaking_metrics() -> &'static MatchmakingMetrics {
     metrics::matchmaking_metrics()
 }
 
+/// `/readyz` của room-manager: lock state lấy được là bắt buộc, PocketBase không tới được chỉ là degraded
+pub fn readiness(room_state: Arc<RwLock<RoomManagerState>>) -> health::Readiness {
+    let lock_state = room_state.clone();
+    health::Readiness::new("room-manager")
+        .required("state", move || {
+            let state = lock_state.clone();
+            async move {
+                drop(state.read().await);
+                DependencyStatus::Ok
+            }
+        })
+        .optional("pocketbase", move || {
+            let state = room_state.clone();
+            async move {
+                let pocketbase = state.read().await.pocketbase.clone();
+                match pocketbase.health().await {
+                    Ok(_) => DependencyStatus::Ok,
+                    Err(_) => DependencyStatus::Down,
+                }
+            }
+        })
+}
+
 pub async fn run_with_ctrl_c(config: RoomManagerConfig) -> Result<(), BoxError> {
     let (shutdown_tx, shutdown_rx) = shutdown::channel();
 
@@ -554,16 +1263,32 @@ pub async fn run(
     let local_addr = listener
         .local_addr()
         .map_err(|err| Box::new(err) as BoxError)?;
+    // Không có secret mà API nghe ngoài loopback thì dừng thay vì mở API quản lý phòng cho cả mạng
+    let internal_secret = config.internal_secret.clone().or_else(api::internal_secret_from_env);
+    api::check_bind(internal_secret.as_deref(), local_addr)?;
+    if internal_secret.is_none() {
+        warn!("room-manager: ROOM_MANAGER_INTERNAL_SECRET chưa đặt, REST API trên loopback nhận request không cần secret");
+    }
 
     if let Some(tx) = config.ready_tx {
         let _ = tx.send(local_addr);
     }
 
-    info!(%local_addr, path = METRICS_PATH, "room-manager metrics exporter dang lang nghe");
+    info!(%local_addr, path = METRICS_PATH, api = api::ROOMS_PATH, "room-manager metrics exporter + REST API đang lắng nghe");
 
     // Initialize Room Manager state
-    let pocketbase_url = std::env::var("POCKETBASE_URL").unwrap_or_else(|_| "http://localhost:8090".to_string());
-    let room_state = Arc::new(RwLock::new(RoomManagerState::new(&pocketbase_url)?));
+    let pocketbase_url = config
+        .pocketbase_url
+        .or_else(|| std::env::var("POCKETBASE_URL").ok())
+        .unwrap_or_else(|| "http://localhost:8090".to_string());
+    let mut room_state = RoomManagerState::new(&pocketbase_url)?;
+    pocketbase::migrations::bootstrap(&room_state.pocketbase).await?;
+    let env_capacity = capacity::CapacityConfig::from_env();
+    room_state.capacity = env_capacity.with_overrides(&config.capacity_overrides).map_err(BoxError::from)?;
+    room_state.auto_start = autostart::AutoStartSettings::from_env();
+    room_state.workers = workers::WorkerPool::from_env();
+    room_state.names = names::NamePolicy::from_env();
+    let room_state = Arc::new(RwLock::new(room_state));
 
     // Sync với database khi khởi động
     {
@@ -573,6 +1298,12 @@ pub async fn run(
         }
     }
 
+    // Giải đấu chưa xong được nạp lại để báo kết quả tiếp sau restart
+    let tournaments = Arc::new(tournament::TournamentManager::new(room_state.clone()));
+    if let Err(e) = tournaments.sync_with_database().await {
+        error!("Failed to sync tournaments with database: {}", e);
+    }
+
     // Background heartbeat task
     let heartbeat_state = room_state.clone();
     let heartbeat_task = tokio::spawn(async move {
@@ -586,9 +1317,36 @@ pub async fn run(
         }
     });
 
+    // Đóng phòng ma khi worker không còn sim tương ứng
+    let reconcile_task = reconcile::spawn(room_state.clone(), reconcile::ReconcileSettings::from_env())?;
+
+    // Phòng đủ người tự vào trận sau countdown
+    let auto_start_task = autostart::spawn(room_state.clone());
+
+    // Worker không trả lời thì không được gán phòng mới
+    let worker_health_task = workers::spawn(room_state.clone());
+
+    // Sức chứa matchmaking đổi theo file config, phòng đang mở giữ nguyên
+    let reload_task = config
+        .reload_rx
+        .map(|reload_rx| tokio::spawn(apply_capacity_reloads(reload_rx, env_capacity, room_state.clone())));
+
+    // REST API quản lý phòng dùng chung listener với metrics
+    let app = metrics::metrics_router(METRICS_PATH)
+        .merge(readiness(room_state.clone()).router())
+        .merge(api::router_with_tournaments(
+            room_state.clone(),
+            tournaments,
+            internal_secret.map(Arc::from),
+        ));
+    let std_listener = listener.into_std().map_err(|err| Box::new(err) as BoxError)?;
     let server = tokio::spawn(async move {
-        if let Err(err) = metrics::serve_metrics(listener, METRICS_PATH).await {
-            error!(%err, "room-manager metrics exporter dung bat thuong");
+        let result = match axum::Server::from_tcp(std_listener) {
+            Ok(builder) => builder.serve(app.into_make_service()).await.map_err(|err| Box::new(err) as BoxError),
+            Err(err) => Err(Box::new(err) as BoxError),
+        };
+        if let Err(err) = result {
+            error!(%err, "room-manager http server dừng bất thường");
         }
     });
 
@@ -601,11 +1359,35 @@ pub async fn run(
 
     // Cleanup
     heartbeat_task.abort();
+    reconcile_task.abort();
+    auto_start_task.abort();
+    worker_health_task.abort();
+    if let Some(reload_task) = reload_task {
+        reload_task.abort();
+    }
     server.abort();
 
     Ok(())
 }
 
+/// Override sai (mode lạ, khoảng rỗng) bị bỏ qua và giữ sức chứa đang chạy
+async fn apply_capacity_reloads(
+    mut reload_rx: tokio::sync::watch::Receiver<RoomManagerSettings>,
+    env_capacity: capacity::CapacityConfig,
+    room_state: Arc<RwLock<RoomManagerState>>,
+) {
+    while reload_rx.changed().await.is_ok() {
+        let overrides = reload_rx.borrow_and_update().capacity_overrides.clone();
+        match env_capacity.with_overrides(&overrides) {
+            Ok(capacity) => {
+                room_state.write().await.capacity = capacity;
+                info!(modes = overrides.len(), "room-manager: đã áp dụng capacity mới");
+            }
+            Err(err) => error!(%err, "room-manager: capacity mới không hợp lệ, giữ cấu hình cũ"),
+        }
+    }
+}
+
 // Helper functions để expose Room Manager functionality
 pub async fn create_room(
     state: Arc<RwLock<RoomManagerState>>,
@@ -623,6 +1405,53 @@ pub async fn join_room(
     state.join_room(request).await
 }
 
+pub async fn leave_room(
+    state: Arc<RwLock<RoomManagerState>>,
+    request: LeaveRoomRequest,
+) -> Result<LeaveRoomResponse, BoxError> {
+    let mut state = state.write().await;
+    state.leave_room(request).await
+}
+
+pub async fn get_room(state: Arc<RwLock<RoomManagerState>>, room_id: String) -> Result<GetRoomResponse, BoxError> {
+    let state = state.read().await;
+    let room = state.rooms.get(&room_id).cloned();
+    Ok(GetRoomResponse {
+        success: room.is_some(),
+        error: room.is_none().then(|| "Room not found".to_string()),
+        room,
+    })
+}
+
+pub async fn list_players(state: Arc<RwLock<RoomManagerState>>, room_id: String) -> Result<ListPlayersResponse, BoxError> {
+    let state = state.read().await;
+    Ok(state.list_players(&room_id))
+}
+
+pub async fn kick_player(
+    state: Arc<RwLock<RoomManagerState>>,
+    request: KickPlayerRequest,
+) -> Result<KickPlayerResponse, BoxError> {
+    let mut state = state.write().await;
+    state.kick_player(request).await
+}
+
+/// Worker báo trận của phòng đã kết thúc; phòng Finished không nhận thêm player
+pub async fn finish_room(
+    state: Arc<RwLock<RoomManagerState>>,
+    room_id: String,
+) -> Result<FinishRoomResponse, BoxError> {
+    let mut state = state.write().await;
+    if !state.rooms.contains_key(&room_id) {
+        return Ok(FinishRoomResponse {
+            success: false,
+            error: Some("Room not found".to_string()),
+        });
+    }
+    state.finish_room(&room_id).await;
+    Ok(FinishRoomResponse { success: true, error: None })
+}
+
 pub async fn list_rooms(
     state: Arc<RwLock<RoomManagerState>>,
     request: ListRoomsRequest,
@@ -631,10 +1460,68 @@ pub async fn list_rooms(
     state.list_rooms(request).await
 }
 
+pub async fn resolve_invite(
+    state: Arc<RwLock<RoomManagerState>>,
+    request: ResolveInviteRequest,
+) -> Result<ResolveInviteResponse, BoxError> {
+    let state = state.read().await;
+    Ok(state.resolve_invite(request))
+}
+
+pub async fn regenerate_invite(
+    state: Arc<RwLock<RoomManagerState>>,
+    request: RoomInviteRequest,
+) -> Result<RoomInviteResponse, BoxError> {
+    let mut state = state.write().await;
+    state.regenerate_invite(request).await
+}
+
+pub async fn revoke_invite(
+    state: Arc<RwLock<RoomManagerState>>,
+    request: RoomInviteRequest,
+) -> Result<RoomInviteResponse, BoxError> {
+    let mut state = state.write().await;
+    state.revoke_invite(request).await
+}
+
 pub async fn assign_room(
     state: Arc<RwLock<RoomManagerState>>,
     request: AssignRoomRequest,
 ) -> Result<AssignRoomResponse, BoxError> {
+    let started = Instant::now();
+    let response = state.write().await.assign_room(request).await;
+    matchmaking_metrics().observe_assign_room(started.elapsed());
+    response
+}
+
+pub async fn create_party(
+    state: Arc<RwLock<RoomManagerState>>,
+    request: party::CreatePartyRequest,
+) -> Result<party::PartyResponse, BoxError> {
+    let mut state = state.write().await;
+    state.create_party(request).await
+}
+
+pub async fn invite_to_party(
+    state: Arc<RwLock<RoomManagerState>>,
+    request: party::PartyInviteRequest,
+) -> Result<party::PartyResponse, BoxError> {
+    let mut state = state.write().await;
+    state.invite_to_party(request).await
+}
+
+pub async fn join_party(
+    state: Arc<RwLock<RoomManagerState>>,
+    request: party::JoinPartyRequest,
+) -> Result<party::PartyResponse, BoxError> {
+    let mut state = state.write().await;
+    state.join_party(request).await
+}
+
+pub async fn leave_party(
+    state: Arc<RwLock<RoomManagerState>>,
+    request: party::LeavePartyRequest,
+) -> Result<party::PartyResponse, BoxError> {
     let mut state = state.write().await;
-    state.assign_room(request).await
+    state.leave_party(request).await
 }
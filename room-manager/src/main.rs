@@ -44,13 +44,15 @@ async fn test_room_manager() {
     println!("📝 Testing room creation...");
     let create_req = CreateRoomRequest {
         name: "Test Room".to_string(),
-        game_mode: GameMode::Deathmatch,
+        game_mode: GameMode::deathmatch(),
         max_players: 4,
         host_player_id: "player_123".to_string(),
         settings: Some(serde_json::json!({
             "difficulty": "normal",
             "time_limit": 300
         })),
+        backfill_with_bots: false,
+        is_private: false,
     };
 
     match room_manager::create_room(room_state.clone(), create_req).await {
@@ -64,6 +66,7 @@ async fn test_room_manager() {
                     room_id: resp.room_id.clone(),
                     player_id: "player_456".to_string(),
                     player_name: "Test Player".to_string(),
+                    invite_code: None,
                 };
 
                 match room_manager::join_room(room_state.clone(), join_req).await {
@@ -74,8 +77,11 @@ async fn test_room_manager() {
                             // Test list rooms
                             println!("📋 Testing room listing...");
                             let list_req = room_manager::ListRoomsRequest {
-                                game_mode: Some(GameMode::Deathmatch),
+                                game_mode: Some(GameMode::deathmatch()),
                                 status: Some(room_manager::RoomStatus::Waiting),
+                                include_private: false,
+                                admin: false,
+                                ..Default::default()
                             };
 
                             match room_manager::list_rooms(room_state.clone(), list_req).await {
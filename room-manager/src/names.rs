@@ -0,0 +1,149 @@
+//! Tên hiển thị của player trong phòng: chuẩn hoá khoảng trắng, giới hạn độ dài và ký tự, chặn từ trong blocklist,
+//! trùng tên trong cùng phòng thì thêm hậu tố ("Alice (2)") hoặc từ chối tuỳ `name_collision_policy` của phòng.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+pub const MIN_NAME_CHARS: usize = 1;
+pub const MAX_NAME_CHARS: usize = 24;
+
+/// Từ dành riêng cho hệ thống, luôn bị chặn kể cả khi env không cấu hình blocklist
+const RESERVED_WORDS: &[&str] = &["admin", "moderator", "server", "system"];
+
+/// Key trong `Room::settings`
+pub const COLLISION_POLICY_SETTING: &str = "name_collision_policy";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameCollisionPolicy {
+    /// Tên trùng được thêm " (2)", " (3)"... cho tới khi không trùng
+    #[default]
+    AutoSuffix,
+    /// Tên trùng bị từ chối, player phải chọn tên khác
+    Reject,
+}
+
+impl NameCollisionPolicy {
+    /// Đọc từ settings của phòng; không có hoặc giá trị lạ thì dùng mặc định
+    pub fn from_settings(settings: &serde_json::Value) -> Self {
+        settings
+            .get(COLLISION_POLICY_SETTING)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    Length,
+    InvalidCharacters,
+    Blocked,
+    Taken,
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameError::Length => write!(f, "Player name must be {MIN_NAME_CHARS}-{MAX_NAME_CHARS} characters"),
+            NameError::InvalidCharacters => {
+                write!(f, "Player name may only contain letters, digits, spaces and - _ .")
+            }
+            NameError::Blocked => write!(f, "Player name is not allowed"),
+            NameError::Taken => write!(f, "Player name is already taken in this room"),
+        }
+    }
+}
+
+impl std::error::Error for NameError {}
+
+/// Gộp mọi chuỗi khoảng trắng thành một dấu cách và bỏ khoảng trắng hai đầu
+pub fn normalize(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn allowed_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.')
+}
+
+/// Blocklist từ tục/từ dành riêng. So theo từng từ (không phân biệt hoa thường) nên "Badminton" không bị chặn vì "admin"
+#[derive(Debug, Clone)]
+pub struct NamePolicy {
+    blocked_words: Vec<String>,
+}
+
+impl Default for NamePolicy {
+    fn default() -> Self {
+        Self::with_blocklist(std::iter::empty::<&str>())
+    }
+}
+
+impl NamePolicy {
+    /// Blocklist thêm vào các từ dành riêng
+    pub fn with_blocklist<S: AsRef<str>>(words: impl IntoIterator<Item = S>) -> Self {
+        let mut blocked_words: Vec<String> = RESERVED_WORDS.iter().map(|word| word.to_string()).collect();
+        blocked_words.extend(
+            words
+                .into_iter()
+                .map(|word| word.as_ref().trim().to_lowercase())
+                .filter(|word| !word.is_empty()),
+        );
+        blocked_words.sort();
+        blocked_words.dedup();
+        Self { blocked_words }
+    }
+
+    /// Đọc ROOM_MANAGER_NAME_BLOCKLIST (các từ cách nhau bởi dấu phẩy)
+    pub fn from_env() -> Self {
+        let words = std::env::var("ROOM_MANAGER_NAME_BLOCKLIST").unwrap_or_default();
+        Self::with_blocklist(words.split(','))
+    }
+
+    /// Hook kiểm tra một tên (lúc join hoặc khi đổi tên): trả về tên đã chuẩn hoá
+    pub fn check(&self, name: &str) -> Result<String, NameError> {
+        let name = normalize(name);
+        let chars = name.chars().count();
+        if !(MIN_NAME_CHARS..=MAX_NAME_CHARS).contains(&chars) {
+            return Err(NameError::Length);
+        }
+        if !name.chars().all(allowed_char) {
+            return Err(NameError::InvalidCharacters);
+        }
+        let blocked = name
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .any(|word| self.blocked_words.iter().any(|blocked| word.to_lowercase() == *blocked));
+        if blocked {
+            return Err(NameError::Blocked);
+        }
+        Ok(name)
+    }
+
+    /// Tên hiển thị cuối cùng trong phòng; `taken` là tên của những player khác đang ở trong phòng
+    pub fn resolve<'a>(
+        &self,
+        requested: &str,
+        taken: impl IntoIterator<Item = &'a str>,
+        policy: NameCollisionPolicy,
+    ) -> Result<String, NameError> {
+        let name = self.check(requested)?;
+        let taken: Vec<String> = taken.into_iter().map(str::to_lowercase).collect();
+        let is_taken = |candidate: &str| taken.contains(&candidate.to_lowercase());
+        if !is_taken(&name) {
+            return Ok(name);
+        }
+        if policy == NameCollisionPolicy::Reject {
+            return Err(NameError::Taken);
+        }
+        (2..)
+            .map(|n| {
+                let suffix = format!(" ({n})");
+                // Cắt bớt tên gốc để hậu tố không đẩy tên quá giới hạn
+                let base: String = name.chars().take(MAX_NAME_CHARS - suffix.len()).collect();
+                format!("{}{suffix}", base.trim_end())
+            })
+            .find(|candidate| !is_taken(candidate))
+            .ok_or(NameError::Taken)
+    }
+}
+
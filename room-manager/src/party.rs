@@ -0,0 +1,342 @@
+//! Party: nhóm tối đa `MAX_PARTY_SIZE` người chơi cùng xếp trận. Leader tạo party và phát invite
+//! code, bạn bè join bằng code; chỉ leader được assign, và cả party vào cùng một phòng, cùng đội
+//! hoặc không ai vào. Party giải tán khi leader rời hoặc không hoạt động quá `party_ttl`.
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{invite, AssignRoomResponse, BoxError, GameMode, Player, PlayerStatus, RoomManagerState};
+
+/// Collection PocketBase giữ party, record id = party_id
+pub const PARTIES_COLLECTION: &str = "parties";
+
+pub const MAX_PARTY_SIZE: usize = 4;
+
+/// Đội trong phòng; party được xếp vào đội đang ít người hơn
+pub const TEAMS: [&str; 2] = ["red", "blue"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Party {
+    pub id: String,
+    pub leader_id: String,
+    /// Leader luôn đứng đầu
+    pub members: Vec<String>,
+    /// Code leader chia sẻ để bạn bè join, tạo mới qua `/invite`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invite_code: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Lần cuối party có hoạt động (thay đổi member, assign, có member đang trong phòng)
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl RoomManagerState {
+    /// Party mà player đang là thành viên
+    pub fn party_of(&self, player_id: &str) -> Option<&Party> {
+        self.parties.values().find(|party| party.members.iter().any(|member| member == player_id))
+    }
+
+    pub async fn create_party(&mut self, req: CreatePartyRequest) -> Result<PartyResponse, BoxError> {
+        if self.party_of(&req.player_id).is_some() {
+            return Ok(PartyResponse::failed("Player is already in a party"));
+        }
+
+        let now = chrono::Utc::now();
+        let party = Party {
+            id: Uuid::new_v4().to_string(),
+            leader_id: req.player_id.clone(),
+            members: vec![req.player_id],
+            invite_code: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        if let Err(e) = self.pocketbase.create_record(PARTIES_COLLECTION, serde_json::to_value(&party)?).await {
+            return Ok(PartyResponse::failed(&format!("Database error: {}", e)));
+        }
+        self.parties.insert(party.id.clone(), party.clone());
+        info!(party_id = %party.id, leader = %party.leader_id, "Created party");
+        Ok(PartyResponse::ok(party))
+    }
+
+    /// Leader tạo invite code mới, code cũ hết hiệu lực
+    pub async fn invite_to_party(&mut self, req: PartyInviteRequest) -> Result<PartyResponse, BoxError> {
+        let code = self.unused_party_code();
+        let Some(party) = self.parties.get_mut(&req.party_id) else {
+            return Ok(PartyResponse::failed("Party not found"));
+        };
+        if party.leader_id != req.player_id {
+            return Ok(PartyResponse::failed("Only the party leader can invite players"));
+        }
+
+        if let Some(previous) = party.invite_code.replace(code.clone()) {
+            self.party_invites.remove(&previous);
+        }
+        party.updated_at = chrono::Utc::now();
+        self.party_invites.insert(code, req.party_id.clone());
+        let party = party.clone();
+        self.persist_party(&party).await;
+        Ok(PartyResponse::ok(party))
+    }
+
+    pub async fn join_party(&mut self, req: JoinPartyRequest) -> Result<PartyResponse, BoxError> {
+        if self.party_of(&req.player_id).is_some() {
+            return Ok(PartyResponse::failed("Player is already in a party"));
+        }
+        let Some(party) = self
+            .party_invites
+            .get(&invite::normalize(&req.invite_code))
+            .and_then(|party_id| self.parties.get_mut(party_id))
+        else {
+            return Ok(PartyResponse::failed("Invite code not found"));
+        };
+        if party.members.len() >= MAX_PARTY_SIZE {
+            return Ok(PartyResponse::failed("Party is full"));
+        }
+
+        party.members.push(req.player_id);
+        party.updated_at = chrono::Utc::now();
+        let party = party.clone();
+        self.persist_party(&party).await;
+        Ok(PartyResponse::ok(party))
+    }
+
+    /// Member rời party; leader rời thì cả party giải tán
+    pub async fn leave_party(&mut self, req: LeavePartyRequest) -> Result<PartyResponse, BoxError> {
+        let Some(party) = self.party_of(&req.player_id).cloned() else {
+            return Ok(PartyResponse::failed("Player is not in a party"));
+        };
+        if party.leader_id == req.player_id {
+            self.disband_party(&party.id).await;
+            return Ok(PartyResponse { success: true, error: None, party: None });
+        }
+
+        let Some(party) = self.parties.get_mut(&party.id) else {
+            return Ok(PartyResponse::failed("Party not found"));
+        };
+        party.members.retain(|member| *member != req.player_id);
+        party.updated_at = chrono::Utc::now();
+        let party = party.clone();
+        self.persist_party(&party).await;
+        Ok(PartyResponse::ok(party))
+    }
+
+    /// Xếp cả party vào phòng public đang chờ còn đủ chỗ cho mọi member, không có thì mở phòng mới.
+    /// Mọi member chung một đội; thiếu chỗ cho dù một người thì không ai được xếp.
+    pub(crate) async fn assign_party(
+        &mut self,
+        party_id: &str,
+        game_mode: Option<GameMode>,
+    ) -> Result<AssignRoomResponse, BoxError> {
+        let Some(party) = self.parties.get(party_id).cloned() else {
+            return Ok(AssignRoomResponse::failed("Party not found"));
+        };
+        if let Some(busy) = party.members.iter().find(|member| self.players.contains_key(*member)) {
+            return Ok(AssignRoomResponse::failed(&format!("Party member {} is already in a room", busy)));
+        }
+
+        let size = party.members.len() as u32;
+        let open_room = self
+            .rooms
+            .values()
+            .filter(|room| room.status.accepts_players() && !room.is_private)
+            .filter(|room| room.current_players + size <= room.max_players)
+            .filter(|room| game_mode.as_ref().is_none_or(|mode| room.game_mode == *mode))
+            .min_by_key(|room| room.current_players)
+            .map(|room| room.id.clone());
+
+        let room_id = match open_room {
+            Some(room_id) => room_id,
+            None => {
+                let request = self.auto_room_request(game_mode.unwrap_or_else(GameMode::deathmatch), &party.leader_id);
+                let created = self.create_room(request).await?;
+                if !created.success {
+                    return Ok(AssignRoomResponse::failed(
+                        &created.error.unwrap_or_else(|| "Failed to create room".to_string()),
+                    ));
+                }
+                // create_room đã tính host; bên dưới đếm lại cả party
+                if let Some(room) = self.rooms.get_mut(&created.room_id) {
+                    room.current_players = 0;
+                }
+                created.room_id
+            }
+        };
+
+        let team = self.smaller_team(&room_id);
+        let Some(room) = self.rooms.get_mut(&room_id) else {
+            return Ok(AssignRoomResponse::failed("Room not found after assignment"));
+        };
+        let now = chrono::Utc::now();
+        room.current_players += size;
+        room.updated_at = now;
+
+        for member in &party.members {
+            self.players.insert(
+                member.clone(),
+                Player {
+                    id: member.clone(),
+                    name: format!("Player_{}", member.chars().take(8).collect::<String>()),
+                    room_id: room_id.clone(),
+                    joined_at: now,
+                    last_seen: now,
+                    status: PlayerStatus::Connected,
+                    team: Some(team.to_string()),
+                },
+            );
+        }
+        if let Some(party) = self.parties.get_mut(party_id) {
+            party.updated_at = now;
+        }
+        self.update_start_schedule(&room_id).await;
+        self.refresh_gauges();
+        info!(party_id, room_id = %room_id, team, size, "Assigned party to room");
+        let response = AssignRoomResponse {
+            room_id: Some(room_id.clone()),
+            worker_endpoint: self.rooms.get(&room_id).and_then(|room| room.worker_endpoint.clone()),
+            team: Some(team.to_string()),
+            party_members: party.members.clone(),
+            error: None,
+        };
+
+        Ok(response)
+    }
+
+    /// Đội ít người hơn trong phòng, hoà thì đội đầu
+    fn smaller_team(&self, room_id: &str) -> &'static str {
+        TEAMS
+            .into_iter()
+            .min_by_key(|team| {
+                self.players
+                    .values()
+                    .filter(|player| player.room_id == room_id && player.team.as_deref() == Some(*team))
+                    .count()
+            })
+            .unwrap_or(TEAMS[0])
+    }
+
+    /// Heartbeat: party có member đang trong phòng vẫn tính là hoạt động, idle quá `party_ttl` thì giải tán
+    pub(crate) async fn expire_parties(&mut self, now: chrono::DateTime<chrono::Utc>) {
+        let mut idle = Vec::new();
+        for party in self.parties.values_mut() {
+            if party.members.iter().any(|member| self.players.contains_key(member)) {
+                party.updated_at = now;
+            } else if (now - party.updated_at).to_std().unwrap_or_default() > self.party_ttl {
+                idle.push(party.id.clone());
+            }
+        }
+        for party_id in idle {
+            info!(party_id = %party_id, "Disbanding idle party");
+            self.disband_party(&party_id).await;
+        }
+    }
+
+    async fn disband_party(&mut self, party_id: &str) {
+        let Some(party) = self.parties.remove(party_id) else {
+            return;
+        };
+        if let Some(code) = &party.invite_code {
+            self.party_invites.remove(code);
+        }
+        if let Err(e) = self.pocketbase.delete_record(PARTIES_COLLECTION, party_id).await {
+            warn!("Failed to delete party record {}: {}", party_id, e);
+        }
+    }
+
+    /// Memory là nguồn đúng, database chỉ để khôi phục sau restart
+    async fn persist_party(&self, party: &Party) {
+        let update = match serde_json::to_value(party) {
+            Ok(update) => update,
+            Err(e) => {
+                warn!("Failed to encode party {}: {}", party.id, e);
+                return;
+            }
+        };
+        if let Err(e) = self.pocketbase.update_record(PARTIES_COLLECTION, &party.id, update).await {
+            warn!("Failed to persist party {}: {}", party.id, e);
+        }
+    }
+
+    /// Code chưa được party nào dùng; khác bảng với invite code của phòng
+    fn unused_party_code(&self) -> String {
+        loop {
+            let code = invite::generate();
+            if !self.party_invites.contains_key(&code) {
+                return code;
+            }
+        }
+    }
+
+    /// Nạp lại party từ database khi khởi động
+    pub(crate) async fn sync_parties(&mut self) {
+        let records = match self.pocketbase.list_records(PARTIES_COLLECTION, None, None).await {
+            Ok(records) => records,
+            Err(e) => {
+                warn!("Failed to sync parties from database: {}", e);
+                return;
+            }
+        };
+        for record in records {
+            let mut fields: serde_json::Map<String, serde_json::Value> = record.fields.into_iter().collect();
+            fields.insert("id".to_string(), serde_json::Value::String(record.id.clone()));
+            match serde_json::from_value::<Party>(serde_json::Value::Object(fields)) {
+                Ok(party) => {
+                    if let Some(code) = &party.invite_code {
+                        self.party_invites.insert(code.clone(), party.id.clone());
+                    }
+                    self.parties.insert(party.id.clone(), party);
+                }
+                Err(e) => warn!("Skipping party record {}: {}", record.id, e),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePartyRequest {
+    pub player_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PartyInviteRequest {
+    #[serde(default)]
+    pub party_id: String, // REST API lấy từ path
+    pub player_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JoinPartyRequest {
+    pub player_id: String,
+    pub invite_code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LeavePartyRequest {
+    pub player_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PartyResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    /// None khi thất bại hoặc party vừa giải tán
+    pub party: Option<Party>,
+}
+
+impl PartyResponse {
+    fn ok(party: Party) -> Self {
+        Self {
+            success: true,
+            error: None,
+            party: Some(party),
+        }
+    }
+
+    fn failed(error: &str) -> Self {
+        Self {
+            success: false,
+            error: Some(error.to_string()),
+            party: None,
+        }
+    }
+}
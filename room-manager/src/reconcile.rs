@@ -0,0 +1,237 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use pocketbase::Record;
+use proto::{
+    auth::AttachInternalToken,
+    worker::v1::{worker_client::WorkerClient, ListActiveRoomsRequest},
+};
+use tokio::sync::RwLock;
+use tonic::{
+    service::interceptor::InterceptedService,
+    transport::{Channel, Endpoint},
+};
+use tracing::{info, warn};
+
+use crate::{matchmaking_metrics, workers::WorkerPool, BoxError, RoomManagerState, RoomStatus};
+
+pub(crate) const DEFAULT_WORKER_ENDPOINT: &str = "http://127.0.0.1:50051";
+const DEFAULT_RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone)]
+pub struct ReconcileSettings {
+    /// Mọi worker trong pool; phòng chỉ là ma khi không worker nào chạy sim của nó
+    pub worker_endpoints: Vec<String>,
+    pub interval: Duration,
+    /// Phòng mới tạo/mới có người join trong khoảng này chưa bị đóng dù worker chưa có sim
+    pub grace_period: Duration,
+}
+
+impl ReconcileSettings {
+    /// Đọc WORKER_ENDPOINTS/WORKER_ENDPOINT, ROOM_MANAGER_RECONCILE_INTERVAL_SECS và ROOM_MANAGER_RECONCILE_GRACE_SECS
+    pub fn from_env() -> Self {
+        let secs = |key: &str, default: Duration| {
+            std::env::var(key)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .map_or(default, Duration::from_secs)
+        };
+        Self {
+            worker_endpoints: WorkerPool::from_env().endpoints().to_vec(),
+            interval: secs("ROOM_MANAGER_RECONCILE_INTERVAL_SECS", DEFAULT_RECONCILE_INTERVAL),
+            grace_period: secs("ROOM_MANAGER_RECONCILE_GRACE_SECS", DEFAULT_GRACE_PERIOD),
+        }
+    }
+}
+
+impl Default for ReconcileSettings {
+    fn default() -> Self {
+        Self {
+            worker_endpoints: vec![DEFAULT_WORKER_ENDPOINT.to_string()],
+            interval: DEFAULT_RECONCILE_INTERVAL,
+            grace_period: DEFAULT_GRACE_PERIOD,
+        }
+    }
+}
+
+/// Kết quả một lượt reconcile
+#[derive(Debug, Default, Clone)]
+pub struct ReconcileReport {
+    pub live_rooms: usize,
+    /// Phòng đã bị đóng trong lượt này, sắp theo id
+    pub closed: Vec<String>,
+}
+
+/// Đối chiếu phòng của room-manager (memory + PocketBase) với sim đang chạy trên worker.
+/// Worker crash để lại phòng "ma" trong database; reconciler đóng chúng sau grace period.
+pub struct Reconciler {
+    state: Arc<RwLock<RoomManagerState>>,
+    /// Gắn token nội bộ (WORKER_RPC_TOKEN) như gateway, worker từ chối lời gọi không có token
+    workers: Vec<WorkerClient<InterceptedService<Channel, AttachInternalToken>>>,
+    grace_period: Duration,
+}
+
+impl Reconciler {
+    pub fn new(
+        state: Arc<RwLock<RoomManagerState>>,
+        worker_endpoint: &str,
+        grace_period: Duration,
+    ) -> Result<Self, BoxError> {
+        Self::for_pool(state, &[worker_endpoint.to_string()], grace_period)
+    }
+
+    pub fn for_pool(
+        state: Arc<RwLock<RoomManagerState>>,
+        worker_endpoints: &[String],
+        grace_period: Duration,
+    ) -> Result<Self, BoxError> {
+        let workers = worker_endpoints
+            .iter()
+            .map(|endpoint| {
+                let channel = Endpoint::from_shared(endpoint.clone())?.connect_lazy();
+                Ok(WorkerClient::with_interceptor(channel, AttachInternalToken::from_env()))
+            })
+            .collect::<Result<_, BoxError>>()?;
+        Ok(Self {
+            state,
+            workers,
+            grace_period,
+        })
+    }
+
+    /// Không lấy được danh sách từ một worker bất kỳ thì không đóng gì, tránh worker restart làm mất hết phòng
+    pub async fn run_once(&mut self) -> Result<ReconcileReport, BoxError> {
+        let live = self.live_rooms().await?;
+        let closed = reconcile(&self.state, &live, self.grace_period).await?;
+        Ok(ReconcileReport {
+            live_rooms: live.len(),
+            closed,
+        })
+    }
+
+    async fn live_rooms(&mut self) -> Result<HashSet<String>, BoxError> {
+        let mut live = HashSet::new();
+        for worker in &mut self.workers {
+            let response = worker.list_active_rooms(ListActiveRoomsRequest {}).await?;
+            live.extend(response.into_inner().rooms.into_iter().map(|room| room.room_id));
+        }
+        Ok(live)
+    }
+}
+
+pub fn spawn(state: Arc<RwLock<RoomManagerState>>, settings: ReconcileSettings) -> Result<tokio::task::JoinHandle<()>, BoxError> {
+    let mut reconciler = Reconciler::for_pool(state, &settings.worker_endpoints, settings.grace_period)?;
+    Ok(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(settings.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            match reconciler.run_once().await {
+                Ok(report) if !report.closed.is_empty() => {
+                    info!(closed = ?report.closed, live_rooms = report.live_rooms, "reconciliation closed ghost rooms");
+                }
+                Ok(_) => {}
+                Err(e) => warn!(endpoints = ?settings.worker_endpoints, "reconciliation skipped, worker unavailable: {}", e),
+            }
+        }
+    }))
+}
+
+/// Đóng các phòng không có trong `live` và không thay đổi trong `grace_period`.
+/// Idempotent: phòng đã Closed/Finished bị bỏ qua, nên chạy lại không đếm trùng.
+pub async fn reconcile(
+    state: &Arc<RwLock<RoomManagerState>>,
+    live: &HashSet<String>,
+    grace_period: Duration,
+) -> Result<Vec<String>, BoxError> {
+    let now = Utc::now();
+    let cutoff = now - chrono::Duration::from_std(grace_period)?;
+
+    // Đọc database ngoài write lock để join/create không phải chờ
+    let pocketbase = state.read().await.pocketbase.clone();
+    let db_ghosts: HashSet<String> = match pocketbase.list_records("rooms", None, None).await {
+        Ok(records) => records
+            .iter()
+            .filter(|record| !live.contains(&record.id) && is_stale_open(record, cutoff))
+            .map(|record| record.id.clone())
+            .collect(),
+        Err(e) => {
+            warn!("Reconciliation could not list rooms from database: {}", e);
+            HashSet::new()
+        }
+    };
+
+    let mut memory_closed = HashSet::new();
+    let mut to_close: Vec<String> = {
+        let mut state = state.write().await;
+        let ghosts: Vec<String> = state
+            .rooms
+            .values()
+            .filter(|room| !matches!(room.status, RoomStatus::Closed | RoomStatus::Finished))
+            .filter(|room| !live.contains(&room.id) && room.updated_at < cutoff)
+            .map(|room| room.id.clone())
+            .collect();
+
+        for room_id in &ghosts {
+            if let Some(room) = state.rooms.get_mut(room_id) {
+                room.status = RoomStatus::Closed;
+                room.current_players = 0;
+                room.updated_at = now;
+            }
+            state.players.retain(|_, player| &player.room_id != room_id);
+            memory_closed.insert(room_id.clone());
+        }
+        if !ghosts.is_empty() {
+            state.refresh_gauges();
+        }
+
+        // Phòng có trong memory thì memory quyết định (có thể vừa có join); chỉ đóng record mồ côi
+        db_ghosts
+            .into_iter()
+            .filter(|room_id| !state.rooms.contains_key(room_id))
+            .chain(ghosts)
+            .collect()
+    };
+    to_close.sort();
+
+    let closed_status = serde_json::to_string(&RoomStatus::Closed)?;
+    let mut closed = Vec::new();
+    for room_id in to_close {
+        let update = serde_json::json!({ "status": closed_status, "updated_at": now });
+        match pocketbase.update_record("rooms", &room_id, update).await {
+            Ok(_) => closed.push(room_id),
+            Err(e) => {
+                warn!("Failed to close ghost room {} in database: {}", room_id, e);
+                // Memory đã đóng thì vẫn tính là đã sửa, database thử lại ở lượt sau
+                if memory_closed.contains(&room_id) {
+                    closed.push(room_id);
+                }
+            }
+        }
+    }
+
+    if !closed.is_empty() {
+        matchmaking_metrics().inc_reconciliation_fixed(closed.len() as u64);
+    }
+    Ok(closed)
+}
+
+/// Record còn mở và `updated_at` cũ hơn `cutoff`; không đọc được thời gian thì coi như còn mới
+fn is_stale_open(record: &Record, cutoff: DateTime<Utc>) -> bool {
+    let status = record
+        .fields
+        .get("status")
+        .and_then(|value| value.as_str())
+        .and_then(|value| serde_json::from_str::<RoomStatus>(value).ok());
+    if matches!(status, Some(RoomStatus::Closed | RoomStatus::Finished)) {
+        return false;
+    }
+
+    record
+        .fields
+        .get("updated_at")
+        .and_then(|value| value.as_str())
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .is_some_and(|updated_at| updated_at < cutoff)
+}
@@ -0,0 +1,419 @@
+//! Giải đấu loại trực tiếp chạy trọn vẹn: đăng ký, chia nhánh theo rating, mỗi trận một phòng
+//! (tạo qua `create_room` như phòng thường), báo kết quả để đi tiếp tới khi có nhà vô địch.
+//! Logic nhánh đấu nằm trong `common_net::matchmaking`, module này lo phòng và lưu PocketBase.
+
+use std::{collections::HashMap, sync::Arc};
+
+use common_net::matchmaking::{
+    MatchStatus, MatchmakingConfig, MatchmakingSystem, PlayerRating, Tournament, TournamentFormat, TournamentRules,
+    TournamentStatus, PLAYER_RATINGS_COLLECTION,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{matchmaking_metrics, BoxError, CreateRoomRequest, GameMode, JoinRoomRequest, RoomManagerState};
+
+/// Collection PocketBase giữ trạng thái giải, record id = tournament_id
+pub const TOURNAMENTS_COLLECTION: &str = "tournaments";
+
+/// Thời gian tối đa mỗi vòng (giây), ghi vào luật giải
+const DEFAULT_ROUND_TIME_SECS: u64 = 600;
+
+/// Phần room-manager giữ thêm cho mỗi giải ngoài `Tournament`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TournamentMeta {
+    host_player_id: String,
+    game_mode: GameMode,
+    /// match_id -> room_id, trận bye không có phòng
+    #[serde(default)]
+    match_rooms: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub struct TournamentManager {
+    rooms: Arc<RwLock<RoomManagerState>>,
+    engine: MatchmakingSystem,
+    /// Giữ suốt mỗi thao tác để hai kết quả báo cùng lúc không cùng mở vòng mới
+    meta: Mutex<HashMap<String, TournamentMeta>>,
+}
+
+impl TournamentManager {
+    pub fn new(rooms: Arc<RwLock<RoomManagerState>>) -> Self {
+        Self {
+            rooms,
+            engine: MatchmakingSystem::new(MatchmakingConfig::default()),
+            meta: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn create_tournament(&self, req: CreateTournamentRequest) -> Result<TournamentResponse, BoxError> {
+        if req.format.as_ref().is_some_and(|format| *format != TournamentFormat::SingleElimination) {
+            return Ok(TournamentResponse::failed("Only single elimination tournaments are supported"));
+        }
+        if req.max_participants < 2 {
+            return Ok(TournamentResponse::failed("A tournament needs at least 2 participants"));
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let tournament = Tournament {
+            id: Uuid::new_v4().to_string(),
+            name: req.name,
+            game_mode: serde_json::to_value(&req.game_mode)?.as_str().unwrap_or_default().to_string(),
+            format: TournamentFormat::SingleElimination,
+            max_participants: req.max_participants,
+            current_participants: 0,
+            status: TournamentStatus::Registration,
+            start_time: 0,
+            end_time: 0,
+            prize_pool: Vec::new(),
+            brackets: Vec::new(),
+            participants: Vec::new(),
+            rules: TournamentRules {
+                max_round_time: DEFAULT_ROUND_TIME_SECS,
+                allow_rematches: false,
+                skill_range: (f32::MIN, f32::MAX),
+                region_restriction: None,
+            },
+            created_at: now,
+        };
+        let meta = TournamentMeta {
+            host_player_id: req.host_player_id,
+            game_mode: req.game_mode,
+            match_rooms: HashMap::new(),
+        };
+
+        let mut all_meta = self.meta.lock().await;
+        let record = tournament_record(&tournament, &meta)?;
+        if let Err(e) = self.pocketbase().await.create_record(TOURNAMENTS_COLLECTION, record).await {
+            return Ok(TournamentResponse::failed(&format!("Database error: {}", e)));
+        }
+        self.engine.create_tournament(tournament.clone()).await?;
+        all_meta.insert(tournament.id.clone(), meta.clone());
+
+        matchmaking_metrics().inc_tournaments_created();
+        info!(tournament_id = %tournament.id, "Created tournament");
+        Ok(TournamentResponse::ok(tournament, meta))
+    }
+
+    /// Đăng ký khi giải còn mở; rating lấy từ `player_ratings` để chia hạt giống lúc bắt đầu
+    pub async fn register_participant(&self, req: RegisterParticipantRequest) -> Result<TournamentResponse, BoxError> {
+        let all_meta = self.meta.lock().await;
+        let Some(meta) = all_meta.get(&req.tournament_id) else {
+            return Ok(TournamentResponse::failed("Tournament not found"));
+        };
+        let Some(tournament) = self.engine.get_tournament(&req.tournament_id).await else {
+            return Ok(TournamentResponse::failed("Tournament not found"));
+        };
+        if tournament.participants.iter().any(|p| p.player_id == req.player_id) {
+            return Ok(TournamentResponse::failed("Player is already registered"));
+        }
+
+        if let Some(rating) = self.load_rating(&req.player_id).await {
+            self.engine.cache_player_rating(rating).await;
+        }
+        if let Err(e) = self
+            .engine
+            .register_player_for_tournament(&req.tournament_id, &req.player_id, &req.player_name)
+            .await
+        {
+            return Ok(TournamentResponse::failed(&e.to_string()));
+        }
+
+        self.persisted(&req.tournament_id, meta).await
+    }
+
+    /// Host (hoặc admin) chốt danh sách: chia nhánh vòng 1 và mở phòng cho từng trận
+    pub async fn start_tournament(&self, req: StartTournamentRequest) -> Result<TournamentResponse, BoxError> {
+        let mut all_meta = self.meta.lock().await;
+        let Some(meta) = all_meta.get_mut(&req.tournament_id) else {
+            return Ok(TournamentResponse::failed("Tournament not found"));
+        };
+        if !req.admin && meta.host_player_id != req.requesting_player_id {
+            return Ok(TournamentResponse::failed("Only the host can start the tournament"));
+        }
+
+        if let Err(e) = self.engine.seed_bracket(&req.tournament_id).await {
+            return Ok(TournamentResponse::failed(&e.to_string()));
+        }
+        self.open_round_rooms(&req.tournament_id, meta).await;
+
+        info!(tournament_id = %req.tournament_id, "Tournament started");
+        self.persisted(&req.tournament_id, meta).await
+    }
+
+    /// Ghi người thắng một trận; xong cả vòng thì mở phòng vòng sau, xong chung kết thì giải kết thúc
+    pub async fn report_match_result(&self, req: ReportMatchResultRequest) -> Result<TournamentResponse, BoxError> {
+        let mut all_meta = self.meta.lock().await;
+        let Some(meta) = all_meta.get_mut(&req.tournament_id) else {
+            return Ok(TournamentResponse::failed("Tournament not found"));
+        };
+        if !req.admin && meta.host_player_id != req.requesting_player_id {
+            return Ok(TournamentResponse::failed("Only the host can report match results"));
+        }
+
+        let rounds_before = self
+            .engine
+            .get_tournament(&req.tournament_id)
+            .await
+            .map_or(0, |tournament| tournament.brackets.len());
+        let champion = match self
+            .engine
+            .report_match(&req.tournament_id, &req.match_id, &req.winner_player_id)
+            .await
+        {
+            Ok(champion) => champion,
+            Err(e) => return Ok(TournamentResponse::failed(&e.to_string())),
+        };
+
+        if let Some(room_id) = meta.match_rooms.get(&req.match_id) {
+            self.rooms.write().await.finish_room(room_id).await;
+        }
+        if let Some(champion) = champion {
+            matchmaking_metrics().inc_tournaments_completed();
+            info!(tournament_id = %req.tournament_id, %champion, "Tournament completed");
+        } else {
+            let rounds_after = self
+                .engine
+                .get_tournament(&req.tournament_id)
+                .await
+                .map_or(0, |tournament| tournament.brackets.len());
+            if rounds_after > rounds_before {
+                self.open_round_rooms(&req.tournament_id, meta).await;
+            }
+        }
+
+        self.persisted(&req.tournament_id, meta).await
+    }
+
+    pub async fn get_tournament(&self, tournament_id: &str) -> TournamentResponse {
+        let all_meta = self.meta.lock().await;
+        match (self.engine.get_tournament(tournament_id).await, all_meta.get(tournament_id)) {
+            (Some(tournament), Some(meta)) => TournamentResponse::ok(tournament, meta.clone()),
+            _ => TournamentResponse::failed("Tournament not found"),
+        }
+    }
+
+    /// Nạp lại các giải chưa kết thúc từ PocketBase sau khi room-manager khởi động lại
+    pub async fn sync_with_database(&self) -> Result<(), BoxError> {
+        let records = match self.pocketbase().await.list_records(TOURNAMENTS_COLLECTION, None, None).await {
+            Ok(records) => records,
+            Err(e) => {
+                warn!("Failed to sync tournaments from database: {}", e);
+                return Ok(());
+            }
+        };
+
+        let mut all_meta = self.meta.lock().await;
+        for record in records {
+            let (tournament, meta) = match tournament_from_record(record.fields) {
+                Ok(restored) => restored,
+                Err(e) => {
+                    warn!("Skipping tournament record {}: {}", record.id, e);
+                    continue;
+                }
+            };
+            if matches!(tournament.status, TournamentStatus::Completed | TournamentStatus::Cancelled) {
+                continue;
+            }
+            all_meta.insert(tournament.id.clone(), meta);
+            self.engine.create_tournament(tournament).await?;
+        }
+        Ok(())
+    }
+
+    /// Tạo phòng cho các trận chưa có phòng của vòng hiện tại; người đầu tiên làm host, những người
+    /// còn lại được xếp vào luôn nên phòng đủ người và không nhận người lạ qua assign
+    async fn open_round_rooms(&self, tournament_id: &str, meta: &mut TournamentMeta) {
+        let Some(tournament) = self.engine.get_tournament(tournament_id).await else {
+            return;
+        };
+        let Some(bracket) = tournament.brackets.last() else {
+            return;
+        };
+
+        for (index, game) in bracket.matches.iter().enumerate() {
+            if game.status != MatchStatus::Scheduled || meta.match_rooms.contains_key(&game.match_id) {
+                continue;
+            }
+            let Some((host, opponents)) = game.players.split_first() else {
+                continue;
+            };
+
+            let created = crate::create_room(self.rooms.clone(), CreateRoomRequest {
+                name: format!("{} - round {} match {}", tournament.name, bracket.round, index + 1),
+                game_mode: meta.game_mode.clone(),
+                max_players: game.players.len() as u32,
+                host_player_id: host.clone(),
+                settings: Some(serde_json::json!({
+                    "tournament_id": tournament_id,
+                    "match_id": game.match_id,
+                })),
+                backfill_with_bots: false,
+                is_private: false,
+            })
+            .await;
+            let room_id = match created {
+                Ok(created) if created.success => created.room_id,
+                Ok(created) => {
+                    warn!(match_id = %game.match_id, error = ?created.error, "Failed to create tournament match room");
+                    continue;
+                }
+                Err(e) => {
+                    warn!(match_id = %game.match_id, "Failed to create tournament match room: {}", e);
+                    continue;
+                }
+            };
+
+            for opponent in opponents {
+                let joined = crate::join_room(self.rooms.clone(), JoinRoomRequest {
+                    room_id: room_id.clone(),
+                    player_id: opponent.clone(),
+                    player_name: participant_name(&tournament, opponent),
+                    invite_code: None,
+                })
+                .await;
+                if !joined.as_ref().is_ok_and(|joined| joined.success) {
+                    warn!(%room_id, player_id = %opponent, "Failed to seat tournament player");
+                }
+            }
+            meta.match_rooms.insert(game.match_id.clone(), room_id);
+        }
+    }
+
+    /// Lưu trạng thái mới nhất (lỗi database chỉ log, memory vẫn là nguồn đúng) rồi trả về cho caller
+    async fn persisted(&self, tournament_id: &str, meta: &TournamentMeta) -> Result<TournamentResponse, BoxError> {
+        let Some(tournament) = self.engine.get_tournament(tournament_id).await else {
+            return Ok(TournamentResponse::failed("Tournament not found"));
+        };
+        let record = tournament_record(&tournament, meta)?;
+        if let Err(e) = self.pocketbase().await.update_record(TOURNAMENTS_COLLECTION, tournament_id, record).await {
+            warn!("Failed to persist tournament {}: {}", tournament_id, e);
+        }
+        Ok(TournamentResponse::ok(tournament, meta.clone()))
+    }
+
+    async fn load_rating(&self, player_id: &str) -> Option<PlayerRating> {
+        let record = self.pocketbase().await.get_record(PLAYER_RATINGS_COLLECTION, player_id).await.ok()?;
+        let mut fields: serde_json::Map<String, serde_json::Value> = record.fields.into_iter().collect();
+        fields.entry("player_id").or_insert_with(|| serde_json::json!(player_id));
+        serde_json::from_value(serde_json::Value::Object(fields)).ok()
+    }
+
+    async fn pocketbase(&self) -> pocketbase::PocketBaseClient {
+        self.rooms.read().await.pocketbase.clone()
+    }
+}
+
+fn participant_name(tournament: &Tournament, player_id: &str) -> String {
+    tournament
+        .participants
+        .iter()
+        .find(|p| p.player_id == player_id)
+        .map_or_else(|| player_id.to_string(), |p| p.player_name.clone())
+}
+
+fn tournament_record(tournament: &Tournament, meta: &TournamentMeta) -> Result<serde_json::Value, BoxError> {
+    Ok(serde_json::json!({
+        "id": tournament.id,
+        "name": tournament.name,
+        "status": tournament.status,
+        "host_player_id": meta.host_player_id,
+        "game_mode": meta.game_mode,
+        "match_rooms": meta.match_rooms,
+        "state": serde_json::to_value(tournament)?,
+    }))
+}
+
+fn tournament_from_record(mut fields: HashMap<String, serde_json::Value>) -> Result<(Tournament, TournamentMeta), BoxError> {
+    let tournament = serde_json::from_value(fields.remove("state").ok_or("missing state")?)?;
+    let meta = serde_json::from_value(serde_json::to_value(fields)?)?;
+    Ok((tournament, meta))
+}
+
+/// Giải kèm thông tin room-manager giữ thêm
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TournamentInfo {
+    #[serde(flatten)]
+    pub tournament: Tournament,
+    pub host_player_id: String,
+    /// match_id -> room_id của các trận đã mở phòng
+    pub match_rooms: HashMap<String, String>,
+    pub champion: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTournamentRequest {
+    pub name: String,
+    pub game_mode: GameMode,
+    pub max_participants: u32,
+    pub host_player_id: String,
+    /// Mặc định và hiện chỉ hỗ trợ SingleElimination
+    #[serde(default)]
+    pub format: Option<TournamentFormat>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterParticipantRequest {
+    #[serde(default)]
+    pub tournament_id: String, // REST API lấy từ path
+    pub player_id: String,
+    pub player_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartTournamentRequest {
+    #[serde(default)]
+    pub tournament_id: String, // REST API lấy từ path
+    pub requesting_player_id: String,
+    /// Gateway đã xác thực admin token: bỏ qua kiểm tra host
+    #[serde(default)]
+    pub admin: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportMatchResultRequest {
+    #[serde(default)]
+    pub tournament_id: String, // REST API lấy từ path
+    pub match_id: String,
+    pub winner_player_id: String,
+    pub requesting_player_id: String,
+    #[serde(default)]
+    pub admin: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TournamentResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    pub tournament: Option<TournamentInfo>,
+}
+
+impl TournamentResponse {
+    fn ok(tournament: Tournament, meta: TournamentMeta) -> Self {
+        let champion = (tournament.status == TournamentStatus::Completed)
+            .then(|| tournament.brackets.last())
+            .flatten()
+            .and_then(|bracket| bracket.matches.first())
+            .and_then(|game| game.winner.clone());
+        Self {
+            success: true,
+            error: None,
+            tournament: Some(TournamentInfo {
+                tournament,
+                host_player_id: meta.host_player_id,
+                match_rooms: meta.match_rooms,
+                champion,
+            }),
+        }
+    }
+
+    fn failed(error: &str) -> Self {
+        Self {
+            success: false,
+            error: Some(error.to_string()),
+            tournament: None,
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Pool worker chạy sim: phòng chuyển Starting thì được gán worker khoẻ đang có ít phòng nhất, endpoint lưu trên phòng
+//! và trả về trong response join/assign. Task nền định kỳ hỏi từng worker, worker không trả lời bị bỏ qua khi gán.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use proto::{
+    auth::AttachInternalToken,
+    worker::v1::{worker_client::WorkerClient, ListActiveRoomsRequest},
+};
+use tokio::sync::RwLock;
+use tonic::transport::Endpoint;
+use tracing::{info, warn};
+
+use crate::{reconcile, RoomManagerState, RoomStatus};
+
+const DEFAULT_HEALTH_INTERVAL: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct WorkerPool {
+    /// Theo thứ tự cấu hình; tải bằng nhau thì worker đứng trước được chọn
+    endpoints: Vec<String>,
+    unhealthy: HashSet<String>,
+}
+
+impl WorkerPool {
+    /// Bỏ endpoint rỗng và trùng; mọi worker được coi là khoẻ cho tới lần kiểm tra đầu
+    pub fn new<I, S>(endpoints: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut unique: Vec<String> = Vec::new();
+        for endpoint in endpoints {
+            let endpoint = endpoint.into().trim().to_string();
+            if !endpoint.is_empty() && !unique.contains(&endpoint) {
+                unique.push(endpoint);
+            }
+        }
+        Self { endpoints: unique, unhealthy: HashSet::new() }
+    }
+
+    /// Đọc WORKER_ENDPOINTS (phân cách bằng dấu phẩy), không có thì WORKER_ENDPOINT
+    pub fn from_env() -> Self {
+        let pool = match std::env::var("WORKER_ENDPOINTS") {
+            Ok(list) => Self::new(list.split(',')),
+            Err(_) => Self::new(std::env::var("WORKER_ENDPOINT").ok()),
+        };
+        if pool.endpoints.is_empty() {
+            Self::default()
+        } else {
+            pool
+        }
+    }
+
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+
+    pub fn is_healthy(&self, endpoint: &str) -> bool {
+        self.endpoints.iter().any(|known| known == endpoint) && !self.unhealthy.contains(endpoint)
+    }
+
+    /// Trả về true nếu trạng thái đổi
+    pub fn set_healthy(&mut self, endpoint: &str, healthy: bool) -> bool {
+        if healthy {
+            self.unhealthy.remove(endpoint)
+        } else {
+            self.unhealthy.insert(endpoint.to_string())
+        }
+    }
+
+    /// Worker khoẻ có ít phòng nhất theo `load` (endpoint -> số phòng); None nếu không còn worker khoẻ
+    pub fn least_loaded(&self, load: &HashMap<String, usize>) -> Option<&str> {
+        self.endpoints
+            .iter()
+            .filter(|endpoint| !self.unhealthy.contains(*endpoint))
+            .min_by_key(|endpoint| load.get(*endpoint).copied().unwrap_or(0))
+            .map(String::as_str)
+    }
+}
+
+impl Default for WorkerPool {
+    fn default() -> Self {
+        Self::new([reconcile::DEFAULT_WORKER_ENDPOINT])
+    }
+}
+
+impl RoomManagerState {
+    /// endpoint -> số phòng chưa kết thúc đang được gán cho worker đó
+    pub fn worker_load(&self) -> HashMap<String, usize> {
+        let mut load = HashMap::new();
+        for room in self.rooms.values() {
+            if matches!(room.status, RoomStatus::Closed | RoomStatus::Finished) {
+                continue;
+            }
+            if let Some(endpoint) = &room.worker_endpoint {
+                *load.entry(endpoint.clone()).or_insert(0) += 1;
+            }
+        }
+        load
+    }
+
+    /// Gán worker cho phòng nếu chưa có hoặc worker đã gán không còn khoẻ; None nếu không còn worker khoẻ
+    pub(crate) fn assign_worker(&mut self, room_id: &str) -> Option<String> {
+        let current = self.rooms.get(room_id)?.worker_endpoint.clone();
+        if let Some(endpoint) = current.filter(|endpoint| self.workers.is_healthy(endpoint)) {
+            return Some(endpoint);
+        }
+        let endpoint = self.workers.least_loaded(&self.worker_load())?.to_string();
+        if let Some(room) = self.rooms.get_mut(room_id) {
+            room.worker_endpoint = Some(endpoint.clone());
+        }
+        Some(endpoint)
+    }
+}
+
+/// Worker khoẻ nếu trả lời ListActiveRooms trong `PROBE_TIMEOUT`
+pub async fn probe(endpoint: &str) -> bool {
+    let Ok(endpoint) = Endpoint::from_shared(endpoint.to_string()) else {
+        return false;
+    };
+    let request = async {
+        let channel = endpoint.connect_timeout(PROBE_TIMEOUT).connect().await?;
+        WorkerClient::with_interceptor(channel, AttachInternalToken::from_env()).list_active_rooms(ListActiveRoomsRequest {}).await?;
+        Ok::<_, crate::BoxError>(())
+    };
+    matches!(tokio::time::timeout(PROBE_TIMEOUT, request).await, Ok(Ok(())))
+}
+
+/// Kiểm tra mọi worker trong pool ngoài lock rồi cập nhật trạng thái khoẻ
+pub async fn check_health(state: &Arc<RwLock<RoomManagerState>>) {
+    let endpoints = state.read().await.workers.endpoints().to_vec();
+    let mut results = Vec::with_capacity(endpoints.len());
+    for endpoint in endpoints {
+        let healthy = probe(&endpoint).await;
+        results.push((endpoint, healthy));
+    }
+
+    let mut state = state.write().await;
+    for (endpoint, healthy) in results {
+        if state.workers.set_healthy(&endpoint, healthy) {
+            if healthy {
+                info!(%endpoint, "worker healthy again");
+            } else {
+                warn!(%endpoint, "worker unhealthy, skipped for new rooms");
+            }
+        }
+    }
+}
+
+pub fn spawn(state: Arc<RwLock<RoomManagerState>>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(DEFAULT_HEALTH_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            check_health(&state).await;
+        }
+    })
+}
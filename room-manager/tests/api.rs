@@ -0,0 +1,13 @@
+use std::net::SocketAddr;
+
+use room_manager::api;
+
+#[test]
+fn api_without_secret_only_binds_to_loopback() {
+    let loopback: SocketAddr = ([127, 0, 0, 1], 3200).into();
+    let public: SocketAddr = ([0, 0, 0, 0], 3200).into();
+
+    assert!(api::check_bind(None, loopback).is_ok());
+    assert!(api::check_bind(None, public).is_err());
+    assert!(api::check_bind(Some("secret"), public).is_ok());
+}
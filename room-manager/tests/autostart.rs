@@ -0,0 +1,66 @@
+mod support;
+
+use room_manager::{workers::WorkerPool, CreateRoomRequest, LeaveRoomRequest, RoomStatus};
+use support::{join, stored_room_status, SharedState};
+
+async fn create_room(state: &SharedState, settings: serde_json::Value) -> String {
+    let request = CreateRoomRequest { settings: Some(settings), ..support::create_request("autostart") };
+    support::create_room(state, request).await.room_id
+}
+
+#[tokio::test]
+async fn nth_join_flips_the_room_to_starting_and_schedules_the_start() -> Result<(), room_manager::BoxError> {
+    let (state, pocketbase) = support::state();
+    state.write().await.workers = WorkerPool::new(["http://worker-7:50051"]);
+    let countdown = state.read().await.auto_start.countdown;
+    let room_id = create_room(&state, serde_json::json!({ "min_players_to_start": 3 })).await;
+
+    let second = join(&state, &room_id, "player-2").await;
+    assert_eq!(second.room.expect("room").status, RoomStatus::Waiting);
+
+    let before = chrono::Utc::now();
+    let third = join(&state, &room_id, "player-3").await.room.expect("room");
+    assert_eq!(third.status, RoomStatus::Starting);
+    assert_eq!(third.worker_endpoint.as_deref(), Some("http://worker-7:50051"));
+    let starts_at = third.starts_at.expect("start scheduled");
+    assert!(starts_at >= before + chrono::Duration::from_std(countdown)?);
+    assert_eq!(stored_room_status(&pocketbase, &room_id), "starting");
+
+    // Countdown chưa hết: vẫn nhận player và chưa vào trận
+    assert!(join(&state, &room_id, "player-4").await.success);
+    assert!(state.write().await.check_room_starts().await.is_empty());
+
+    // Rời tới dưới ngưỡng thì huỷ lịch, đủ lại thì hẹn lại
+    let leave = |player_id: &str| LeaveRoomRequest { room_id: room_id.clone(), player_id: player_id.to_string() };
+    assert!(room_manager::leave_room(state.clone(), leave("player-4")).await?.success);
+    assert!(room_manager::leave_room(state.clone(), leave("player-3")).await?.success);
+    assert_eq!(state.read().await.rooms[&room_id].status, RoomStatus::Waiting);
+    assert_eq!(state.read().await.rooms[&room_id].starts_at, None);
+    assert_eq!(stored_room_status(&pocketbase, &room_id), "waiting");
+    assert_eq!(join(&state, &room_id, "player-3").await.room.expect("room").status, RoomStatus::Starting);
+
+    // Hết countdown: lượt kiểm tra định kỳ cho phòng vào trận trên worker đã gán
+    state.write().await.rooms.get_mut(&room_id).expect("room").starts_at = Some(chrono::Utc::now() - chrono::Duration::seconds(1));
+    assert_eq!(state.write().await.check_room_starts().await, vec![room_id.clone()]);
+    let room = state.read().await.rooms[&room_id].clone();
+    assert_eq!(room.status, RoomStatus::InProgress);
+    assert_eq!(room.worker_endpoint.as_deref(), Some("http://worker-7:50051"));
+    assert_eq!(stored_room_status(&pocketbase, &room_id), "in_progress");
+    assert_eq!(pocketbase.record("rooms", &room_id).expect("room record")["worker_endpoint"], "http://worker-7:50051");
+    assert!(!join(&state, &room_id, "late-player").await.success);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rooms_with_auto_start_disabled_keep_waiting() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    let room_id = create_room(&state, serde_json::json!({ "auto_start": false, "min_players_to_start": 2 })).await;
+
+    assert!(join(&state, &room_id, "player-2").await.success);
+    assert!(join(&state, &room_id, "player-3").await.success);
+    assert!(state.write().await.check_room_starts().await.is_empty());
+    assert_eq!(state.read().await.rooms[&room_id].status, RoomStatus::Waiting);
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+mod support;
+
+use room_manager::{AssignRoomRequest, CreateRoomRequest, GameMode};
+
+fn create_request(game_mode: GameMode, max_players: u32) -> CreateRoomRequest {
+    CreateRoomRequest { game_mode, max_players, ..support::create_request("capacity") }
+}
+
+#[tokio::test]
+async fn auto_created_rooms_use_the_capacity_of_their_mode() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    {
+        let mut guard = state.write().await;
+        let deathmatch = room_manager::capacity::ModeCapacity {
+            default_max_players: 6,
+            min_players_to_start: 3,
+            ..guard.capacity.for_mode(&GameMode::deathmatch())
+        };
+        guard.capacity.set(&GameMode::deathmatch(), deathmatch);
+    }
+
+    let auto_room = |player_id: &'static str, game_mode: GameMode| {
+        let state = state.clone();
+        async move {
+            let assigned = room_manager::assign_room(
+                state.clone(),
+                AssignRoomRequest { player_id: player_id.to_string(), game_mode: Some(game_mode) },
+            )
+            .await?;
+            let room_id = assigned.room_id.expect("room assigned");
+            Ok::<_, room_manager::BoxError>(state.read().await.rooms[&room_id].clone())
+        }
+    };
+
+    let deathmatch = auto_room("deathmatch-player", GameMode::deathmatch()).await?;
+    assert_eq!(deathmatch.max_players, 6);
+    assert_eq!(deathmatch.settings["min_players_to_start"], 3);
+
+    let ctf = auto_room("flag-runner-1", GameMode::capture_the_flag()).await?;
+    let expected = state.read().await.capacity.for_mode(&GameMode::capture_the_flag()).default_max_players;
+    assert_eq!(ctf.max_players, expected);
+    assert_eq!(ctf.max_players % 2, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_room_rejects_capacities_outside_the_mode_range() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+
+    for (game_mode, max_players) in [
+        (GameMode::capture_the_flag(), 5),
+        (GameMode::team_deathmatch(), 7),
+        (GameMode::deathmatch(), 1),
+        (GameMode::deathmatch(), 64),
+    ] {
+        let created = room_manager::create_room(state.clone(), create_request(game_mode.clone(), max_players)).await?;
+        assert!(!created.success, "{game_mode:?} accepted max_players {max_players}");
+        assert!(created.error.is_some_and(|error| error.contains("max_players")));
+    }
+    assert!(state.read().await.rooms.is_empty());
+
+    let created = room_manager::create_room(state.clone(), create_request(GameMode::capture_the_flag(), 6)).await?;
+    assert!(created.success, "{:?}", created.error);
+
+    Ok(())
+}
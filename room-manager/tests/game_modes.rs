@@ -0,0 +1,73 @@
+mod support;
+
+use common_net::game_modes::{self, GameModeDescriptor, ScoringType};
+use room_manager::{AssignRoomRequest, CreateRoomRequest, GameMode, ListRoomsRequest};
+use support::SharedState;
+
+fn create_request(name: &str, game_mode: GameMode, max_players: u32) -> CreateRoomRequest {
+    CreateRoomRequest { game_mode, max_players, host_player_id: format!("{name}-host"), ..support::create_request(name) }
+}
+
+async fn room_names(state: &SharedState, game_mode: &str) -> Vec<String> {
+    let request = ListRoomsRequest {
+        game_mode: Some(GameMode::parse(game_mode).expect("registered mode")),
+        status: None,
+        include_private: false,
+        admin: false,
+        ..Default::default()
+    };
+    let listed = room_manager::list_rooms(state.clone(), request).await.expect("list rooms");
+    listed.rooms.into_iter().map(|room| room.name).collect()
+}
+
+#[tokio::test]
+async fn rooms_are_listed_by_registry_mode_id() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+
+    for (name, game_mode) in [("runner", GameMode::endless_runner()), ("arena", GameMode::deathmatch())] {
+        support::create_room(&state, create_request(name, game_mode, 4)).await;
+    }
+
+    assert_eq!(room_names(&state, game_modes::ENDLESS_RUNNER).await, vec!["runner"]);
+    assert_eq!(room_names(&state, game_modes::DEATHMATCH).await, vec!["arena"]);
+
+    let unknown = serde_json::from_value::<CreateRoomRequest>(serde_json::json!({
+        "name": "tag", "game_mode": "tag", "max_players": 4, "host_player_id": "tag-host"
+    }))
+    .unwrap_err();
+    assert!(unknown.to_string().contains("unknown game mode 'tag'"), "{unknown}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn modes_registered_at_runtime_work_without_handler_changes() -> Result<(), room_manager::BoxError> {
+    game_modes::register(GameModeDescriptor {
+        id: "test_relay".to_string(),
+        display_name: "Relay".to_string(),
+        min_players: 2,
+        max_players: 6,
+        team_based: true,
+        default_settings: serde_json::json!({ "max_players": 4, "min_players_to_start": 4 }),
+        scoring: ScoringType::Distance,
+    });
+    let relay: GameMode = serde_json::from_str("\"test_relay\"")?;
+
+    let (state, _pocketbase) = support::state();
+
+    let odd = room_manager::create_room(state.clone(), create_request("relay-odd", relay.clone(), 5)).await?;
+    assert!(odd.error.is_some_and(|error| error.contains("even")));
+
+    let assigned = room_manager::assign_room(
+        state.clone(),
+        AssignRoomRequest { player_id: "relay-runner".to_string(), game_mode: Some(relay.clone()) },
+    )
+    .await?;
+    let room = state.read().await.rooms[&assigned.room_id.expect("room assigned")].clone();
+    assert_eq!(room.game_mode, relay);
+    assert_eq!(room.max_players, 4);
+    assert_eq!(room.settings["min_players_to_start"], 4);
+    assert_eq!(room_names(&state, "test_relay").await, vec![room.name]);
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+mod support;
+
+use room_manager::{matchmaking_metrics, AssignRoomRequest, GameMode, LeaveRoomRequest};
+
+/// (phòng hoạt động, player trong phòng, player trong phòng Waiting)
+fn gauges() -> (i64, i64, i64) {
+    let metrics = matchmaking_metrics();
+    (metrics.active_rooms.get(), metrics.players_in_rooms.get(), metrics.waiting_players.get())
+}
+
+#[tokio::test]
+async fn gauges_track_create_join_leave() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+
+    let created = support::create_room(&state, support::create_request("gauges")).await;
+    assert_eq!(state.read().await.counts(), (1, 1));
+    assert_eq!(gauges(), (1, 1, 1));
+
+    let joined = support::join(&state, &created.room_id, "player-1").await;
+    assert!(joined.success, "{:?}", joined.error);
+    assert_eq!(state.read().await.counts(), (1, 2));
+    assert_eq!(gauges(), (1, 2, 2));
+
+    // Matchmaking xếp vào phòng Waiting còn chỗ, và ghi lại thời gian xếp
+    let assigned_before = matchmaking_metrics().assign_room_duration_ms.get_sample_count();
+    let assigned = room_manager::assign_room(
+        state.clone(),
+        AssignRoomRequest { player_id: "queued-player".to_string(), game_mode: Some(GameMode::deathmatch()) },
+    )
+    .await?;
+    assert_eq!(assigned.room_id.as_deref(), Some(created.room_id.as_str()));
+    assert_eq!(gauges(), (1, 3, 3));
+    assert_eq!(matchmaking_metrics().assign_room_duration_ms.get_sample_count(), assigned_before + 1);
+
+    let leave = |player_id: &str| LeaveRoomRequest {
+        room_id: created.room_id.clone(),
+        player_id: player_id.to_string(),
+    };
+    assert!(room_manager::leave_room(state.clone(), leave("player-1")).await?.success);
+    assert_eq!(gauges(), (1, 2, 2));
+
+    // Player không ở trong phòng thì không làm lệch số đếm
+    assert!(!room_manager::leave_room(state.clone(), leave("player-1")).await?.success);
+    assert_eq!(gauges(), (1, 2, 2));
+
+    // Phòng vào trận thì player không còn tính là đang chờ
+    state.write().await.rooms.get_mut(&created.room_id).expect("room").status = room_manager::RoomStatus::InProgress;
+    state.write().await.heartbeat().await?;
+    assert_eq!(gauges(), (1, 2, 0));
+
+    state.write().await.rooms.clear();
+    state.write().await.heartbeat().await?;
+    assert_eq!(state.read().await.counts(), (0, 0));
+    assert_eq!(gauges(), (0, 0, 0));
+
+    Ok(())
+}
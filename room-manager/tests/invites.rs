@@ -0,0 +1,153 @@
+mod support;
+
+use room_manager::{
+    invite, AssignRoomRequest, CreateRoomRequest, GameMode, JoinRoomRequest, ListRoomsRequest, ResolveInviteRequest,
+    RoomInviteRequest,
+};
+use support::SharedState;
+
+async fn create(state: &SharedState, name: &str, is_private: bool) -> room_manager::CreateRoomResponse {
+    support::create_room(state, CreateRoomRequest { is_private, ..support::create_request(name) }).await
+}
+
+async fn join(
+    state: &SharedState,
+    room_id: &str,
+    player_id: &str,
+    invite_code: Option<&str>,
+) -> room_manager::JoinRoomResponse {
+    let request = JoinRoomRequest { invite_code: invite_code.map(str::to_string), ..support::join_request(room_id, player_id) };
+    support::join_with(state, request).await
+}
+
+async fn listed(state: &SharedState, include_private: bool, admin: bool) -> Vec<String> {
+    let mut names: Vec<String> = room_manager::list_rooms(
+        state.clone(),
+        ListRoomsRequest {
+            game_mode: None,
+            status: None,
+            include_private,
+            admin,
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("list rooms")
+    .rooms
+    .into_iter()
+    .map(|room| room.name)
+    .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn private_room_requires_invite_code_and_stays_out_of_listings() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+
+    let public = create(&state, "public", false).await;
+    assert_eq!(public.invite_code, None);
+    let private = create(&state, "private", true).await;
+    let code = private.invite_code.clone().expect("private room gets an invite code");
+    assert_eq!(code.len(), invite::INVITE_CODE_LEN);
+    assert!(!code.contains(['0', 'O', '1', 'I', 'L']), "ambiguous characters in {code}");
+
+    assert_eq!(listed(&state, false, false).await, ["public"]);
+    assert_eq!(listed(&state, true, false).await, ["public"]);
+    assert_eq!(listed(&state, true, true).await, ["private", "public"]);
+
+    let without_code = join(&state, &private.room_id, "p1", None).await;
+    assert!(!without_code.success);
+    assert_eq!(without_code.error.as_deref(), Some("Invite code required"));
+
+    let wrong_code = join(&state, &private.room_id, "p1", Some("ZZZZZZ")).await;
+    assert!(!wrong_code.success);
+    assert_eq!(wrong_code.error.as_deref(), Some("Invalid invite code"));
+
+    // Người chơi gõ chữ thường, có gạch nối vẫn được
+    let typed = format!("{}-{}", &code[..3], &code[3..]).to_lowercase();
+    let joined = join(&state, &private.room_id, "p1", Some(&typed)).await;
+    assert!(joined.success, "{:?}", joined.error);
+    assert_eq!(joined.room.expect("room").current_players, 2);
+
+    // Phòng public không cần code
+    assert!(join(&state, &public.room_id, "p2", None).await.success);
+    Ok(())
+}
+
+#[tokio::test]
+async fn resolve_invite_returns_room_summary() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    let private = create(&state, "friends only", true).await;
+    let code = private.invite_code.expect("invite code");
+
+    let resolved = room_manager::resolve_invite(state.clone(), ResolveInviteRequest { code: code.to_lowercase() }).await?;
+    assert!(resolved.success, "{:?}", resolved.error);
+    let room = resolved.room.expect("room info");
+    assert_eq!(room.room_id, private.room_id);
+    assert_eq!(room.name, "friends only");
+    assert_eq!((room.current_players, room.max_players), (1, 4));
+
+    let unknown = room_manager::resolve_invite(state.clone(), ResolveInviteRequest { code: "ABCDEF".to_string() }).await?;
+    assert!(!unknown.success);
+    assert!(unknown.room.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn host_can_regenerate_and_revoke_invite_codes() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    let private = create(&state, "private", true).await;
+    let old_code = private.invite_code.expect("invite code");
+    let request = |player_id: &str| RoomInviteRequest {
+        room_id: private.room_id.clone(),
+        player_id: player_id.to_string(),
+    };
+
+    let not_host = room_manager::regenerate_invite(state.clone(), request("p1")).await?;
+    assert!(!not_host.success);
+    assert!(room_manager::resolve_invite(state.clone(), ResolveInviteRequest { code: old_code.clone() }).await?.success);
+
+    let regenerated = room_manager::regenerate_invite(state.clone(), request("host")).await?;
+    assert!(regenerated.success, "{:?}", regenerated.error);
+    let new_code = regenerated.invite_code.expect("new code");
+    assert_ne!(new_code, old_code);
+    assert!(!join(&state, &private.room_id, "p1", Some(&old_code)).await.success);
+    assert!(join(&state, &private.room_id, "p1", Some(&new_code)).await.success);
+
+    let revoked = room_manager::revoke_invite(state.clone(), request("host")).await?;
+    assert!(revoked.success, "{:?}", revoked.error);
+    assert_eq!(revoked.invite_code, None);
+    assert!(!room_manager::resolve_invite(state.clone(), ResolveInviteRequest { code: new_code.clone() }).await?.success);
+    assert!(!join(&state, &private.room_id, "p2", Some(&new_code)).await.success);
+
+    let public = create(&state, "public", false).await;
+    let on_public = room_manager::regenerate_invite(
+        state.clone(),
+        RoomInviteRequest {
+            room_id: public.room_id,
+            player_id: "host".to_string(),
+        },
+    )
+    .await?;
+    assert!(!on_public.success);
+    Ok(())
+}
+
+#[tokio::test]
+async fn assign_never_places_players_into_private_rooms() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    let private = create(&state, "private", true).await;
+
+    let assigned = room_manager::assign_room(
+        state.clone(),
+        AssignRoomRequest {
+            player_id: "matchmade-player".to_string(),
+            game_mode: Some(GameMode::deathmatch()),
+        },
+    )
+    .await?;
+    assert!(assigned.room_id.is_some());
+    assert_ne!(assigned.room_id.as_deref(), Some(private.room_id.as_str()));
+    Ok(())
+}
@@ -0,0 +1,58 @@
+mod support;
+
+use room_manager::{CreateRoomRequest, ListRoomsRequest, RoomSort};
+use support::{create_request, SharedState};
+
+/// Tạo phòng `room-0`..`room-{count-1}`: phòng sau mới hơn phòng trước, phòng i có i+1 player trên 4 chỗ
+async fn state_with_rooms(count: usize) -> SharedState {
+    let (state, _pocketbase) = support::state();
+    let base = chrono::Utc::now();
+    for i in 0..count {
+        let request = CreateRoomRequest { host_player_id: format!("host-{i}"), ..create_request(&format!("room-{i}")) };
+        let room_id = support::create_room(&state, request).await.room_id;
+        let mut guard = state.write().await;
+        let room = guard.rooms.get_mut(&room_id).expect("room");
+        room.created_at = base + chrono::Duration::seconds(i as i64);
+        room.current_players = (i as u32 + 1).min(room.max_players);
+    }
+    state
+}
+
+async fn list(state: &SharedState, request: ListRoomsRequest) -> (Vec<String>, usize) {
+    let listed = room_manager::list_rooms(state.clone(), request).await.expect("list rooms");
+    (listed.rooms.into_iter().map(|room| room.name).collect(), listed.total)
+}
+
+#[tokio::test]
+async fn limit_and_offset_page_through_a_stable_order() {
+    let state = state_with_rooms(5).await;
+
+    // Mặc định phòng mới nhất trước
+    let (page, total) = list(&state, ListRoomsRequest { limit: Some(2), ..Default::default() }).await;
+    assert_eq!((page, total), (vec!["room-4".to_string(), "room-3".to_string()], 5));
+    let (page, total) = list(&state, ListRoomsRequest { limit: Some(2), offset: 2, ..Default::default() }).await;
+    assert_eq!((page, total), (vec!["room-2".to_string(), "room-1".to_string()], 5));
+    let (page, _) = list(&state, ListRoomsRequest { limit: Some(2), offset: 4, ..Default::default() }).await;
+    assert_eq!(page, vec!["room-0".to_string()]);
+    let (page, total) = list(&state, ListRoomsRequest { offset: 10, ..Default::default() }).await;
+    assert!(page.is_empty());
+    assert_eq!(total, 5);
+
+    let (page, _) = list(&state, ListRoomsRequest { sort: RoomSort::CreatedAt, limit: Some(3), ..Default::default() }).await;
+    assert_eq!(page, vec!["room-0", "room-1", "room-2"]);
+    let (page, _) = list(&state, ListRoomsRequest { sort: RoomSort::CurrentPlayers, ..Default::default() }).await;
+    // room-3 và room-4 cùng đủ 4 người, thứ tự giữa hai phòng theo id nhưng luôn đứng sau room-2
+    assert_eq!(&page[..3], ["room-0", "room-1", "room-2"]);
+    assert_eq!(RoomSort::parse("-current_players"), Some(RoomSort::CurrentPlayersDesc));
+    assert_eq!(RoomSort::parse("players"), None);
+}
+
+#[tokio::test]
+async fn has_space_excludes_full_rooms_from_the_page_and_the_total() {
+    let state = state_with_rooms(5).await;
+
+    let (page, total) = list(&state, ListRoomsRequest { has_space: true, ..Default::default() }).await;
+    assert_eq!((page, total), (vec!["room-2".to_string(), "room-1".to_string(), "room-0".to_string()], 3));
+    let (page, total) = list(&state, ListRoomsRequest { has_space: true, limit: Some(1), offset: 1, ..Default::default() }).await;
+    assert_eq!((page, total), (vec!["room-1".to_string()], 3));
+}
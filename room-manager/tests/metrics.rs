@@ -31,7 +31,10 @@ async fn metrics_endpoint_contains_room_manager_counters(
     let body = resp.text().await?;
     assert!(body.contains("room_manager_rooms_created_total"));
     assert!(body.contains("room_manager_active_rooms"));
+    assert!(body.contains("room_manager_players_in_rooms"));
+    assert!(body.contains("room_manager_waiting_players"));
     assert!(body.contains("room_manager_matchmaking_queue_depth"));
+    assert!(body.contains("room_manager_reconciliation_fixed_total"));
 
     server.abort();
     Ok(())
@@ -0,0 +1,99 @@
+mod support;
+
+use room_manager::{KickPlayerRequest, LeaveRoomRequest, RoomStatus};
+use support::{join, SharedState};
+
+async fn create_room(state: &SharedState) -> String {
+    support::create_room(state, support::create_request("moderated")).await.room_id
+}
+
+fn kick(room_id: &str, requester: &str, target: &str, ban: bool) -> KickPlayerRequest {
+    KickPlayerRequest {
+        room_id: room_id.to_string(),
+        requesting_player_id: requester.to_string(),
+        target_player_id: target.to_string(),
+        ban,
+        admin: false,
+    }
+}
+
+#[tokio::test]
+async fn only_the_host_or_an_admin_can_kick() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    let room_id = create_room(&state).await;
+    assert!(join(&state, &room_id, "p1").await.success);
+    assert!(join(&state, &room_id, "p2").await.success);
+
+    let by_player = room_manager::kick_player(state.clone(), kick(&room_id, "p1", "p2", false)).await?;
+    assert!(!by_player.success);
+    assert_eq!(by_player.error.as_deref(), Some("Only the host can kick players"));
+    assert_eq!(state.read().await.rooms[&room_id].current_players, 3);
+
+    let by_host = room_manager::kick_player(state.clone(), kick(&room_id, "host", "p2", false)).await?;
+    assert!(by_host.success && by_host.removed, "{:?}", by_host.error);
+    assert_eq!(state.read().await.rooms[&room_id].current_players, 2);
+    assert!(!state.read().await.players.contains_key("p2"));
+
+    // Kick thường không ban: join lại được
+    assert!(join(&state, &room_id, "p2").await.success);
+
+    let by_admin = room_manager::kick_player(
+        state.clone(),
+        KickPlayerRequest {
+            admin: true,
+            ..kick(&room_id, "moderator", "p1", false)
+        },
+    )
+    .await?;
+    assert!(by_admin.success, "{:?}", by_admin.error);
+    Ok(())
+}
+
+#[tokio::test]
+async fn banned_player_cannot_rejoin_even_after_restart() -> Result<(), room_manager::BoxError> {
+    let (state, pocketbase) = support::state();
+    let room_id = create_room(&state).await;
+    assert!(join(&state, &room_id, "griefer").await.success);
+
+    let banned = room_manager::kick_player(state.clone(), kick(&room_id, "host", "griefer", true)).await?;
+    assert!(banned.success && banned.removed, "{:?}", banned.error);
+
+    let rejoin = join(&state, &room_id, "griefer").await;
+    assert!(!rejoin.success);
+    assert_eq!(rejoin.error.as_deref(), Some("You are banned from this room"));
+    assert!(join(&state, &room_id, "someone-else").await.success);
+
+    // Room-manager mới đọc lại phòng từ PocketBase, ban vẫn còn
+    let restarted = support::state_on(&pocketbase);
+    restarted.write().await.sync_with_database().await?;
+    assert_eq!(restarted.read().await.rooms[&room_id].banned_players, ["griefer"]);
+    assert!(!join(&restarted, &room_id, "griefer").await.success);
+    Ok(())
+}
+
+#[tokio::test]
+async fn host_leaving_hands_the_room_to_the_earliest_player() -> Result<(), room_manager::BoxError> {
+    let (state, pocketbase) = support::state();
+    let room_id = create_room(&state).await;
+    assert!(join(&state, &room_id, "p1").await.success);
+    assert!(join(&state, &room_id, "p2").await.success);
+    let leave = |player_id: &str| LeaveRoomRequest { room_id: room_id.clone(), player_id: player_id.to_string() };
+
+    assert!(room_manager::leave_room(state.clone(), leave("host")).await?.success);
+    let players = room_manager::list_players(state.clone(), room_id.clone()).await?;
+    assert_eq!(players.host_player_id.as_deref(), Some("p1"));
+    assert_eq!(players.current_players, 2);
+    assert_eq!(players.players.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(), ["p1", "p2"]);
+
+    // Host và số player mới đã nằm trong PocketBase
+    let restarted = support::state_on(&pocketbase);
+    restarted.write().await.sync_with_database().await?;
+    assert_eq!(restarted.read().await.rooms[&room_id].host_player_id, "p1");
+    assert_eq!(restarted.read().await.rooms[&room_id].current_players, 2);
+
+    assert!(room_manager::leave_room(state.clone(), leave("p1")).await?.success);
+    assert!(room_manager::leave_room(state.clone(), leave("p2")).await?.success);
+    let room = state.read().await.rooms[&room_id].clone();
+    assert_eq!((room.current_players, room.status), (0, RoomStatus::Closed));
+    Ok(())
+}
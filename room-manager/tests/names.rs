@@ -0,0 +1,81 @@
+mod support;
+
+use room_manager::{
+    names::{self, NameCollisionPolicy, NameError, NamePolicy},
+    CreateRoomRequest, JoinRoomRequest,
+};
+use support::{create_request, join_request, SharedState};
+
+async fn create_room(state: &SharedState, settings: serde_json::Value) -> String {
+    let request = CreateRoomRequest { max_players: 8, settings: Some(settings), ..create_request("named") };
+    support::create_room(state, request).await.room_id
+}
+
+async fn join(state: &SharedState, room_id: &str, player_id: &str, player_name: &str) -> room_manager::JoinRoomResponse {
+    support::join_with(state, JoinRoomRequest { player_name: player_name.to_string(), ..join_request(room_id, player_id) }).await
+}
+
+fn state() -> SharedState {
+    let (state, _pocketbase) = support::state();
+    state.try_write().expect("fresh state").names = NamePolicy::with_blocklist(["darn"]);
+    state
+}
+
+#[test]
+fn names_are_normalized_and_checked_against_the_policy() {
+    let policy = NamePolicy::with_blocklist(["darn"]);
+    assert_eq!(policy.check("  Alice \t  Smith "), Ok("Alice Smith".to_string()));
+    assert_eq!(policy.check("   "), Err(NameError::Length));
+    assert_eq!(policy.check(&"a".repeat(names::MAX_NAME_CHARS + 1)), Err(NameError::Length));
+    assert_eq!(policy.check("<script>"), Err(NameError::InvalidCharacters));
+    assert_eq!(policy.check("Darn it"), Err(NameError::Blocked));
+    assert_eq!(policy.check("the ADMIN"), Err(NameError::Blocked));
+    assert_eq!(policy.check("Badminton"), Ok("Badminton".to_string()));
+    assert_eq!(policy.check("Nguyễn Văn A"), Ok("Nguyễn Văn A".to_string()));
+
+    // Hậu tố không đẩy tên dài quá giới hạn
+    let long = "x".repeat(names::MAX_NAME_CHARS);
+    let suffixed = policy.resolve(&long, [long.as_str()], NameCollisionPolicy::AutoSuffix).expect("suffixed");
+    assert_eq!(suffixed.chars().count(), names::MAX_NAME_CHARS);
+    assert!(suffixed.ends_with(" (2)"), "{suffixed}");
+}
+
+#[tokio::test]
+async fn duplicate_names_are_suffixed_by_default_and_rejected_under_the_strict_policy() {
+    let state = state();
+
+    let room_id = create_room(&state, serde_json::json!({})).await;
+    let first = join(&state, &room_id, "alice-1", "Alice").await;
+    assert_eq!(first.player_name.as_deref(), Some("Alice"));
+    let second = join(&state, &room_id, "alice-2", "  alice ").await;
+    assert!(second.success, "{:?}", second.error);
+    assert_eq!(second.player_name.as_deref(), Some("alice (2)"));
+    let third = join(&state, &room_id, "alice-3", "Alice").await;
+    assert_eq!(third.player_name.as_deref(), Some("Alice (3)"));
+    let names: Vec<String> = state.read().await.room_players(&room_id).into_iter().map(|player| player.name).collect();
+    assert_eq!(names.len(), 3);
+    assert!(names.contains(&"alice (2)".to_string()), "{names:?}");
+
+    let strict = create_room(&state, serde_json::json!({ names::COLLISION_POLICY_SETTING: "reject" })).await;
+    assert!(join(&state, &strict, "alice-4", "Alice").await.success);
+    let rejected = join(&state, &strict, "alice-5", "ALICE").await;
+    assert!(!rejected.success);
+    assert_eq!(rejected.error, Some(NameError::Taken.to_string()));
+    assert_eq!(state.read().await.room_players(&strict).len(), 1);
+}
+
+#[tokio::test]
+async fn blocked_or_malformed_names_never_join_the_room() {
+    let state = state();
+    let room_id = create_room(&state, serde_json::json!({})).await;
+
+    for (name, error) in [("Darn", NameError::Blocked), ("System", NameError::Blocked), ("a<b>", NameError::InvalidCharacters)] {
+        let rejected = join(&state, &room_id, "player", name).await;
+        assert!(!rejected.success, "{name}");
+        assert_eq!(rejected.error, Some(error.to_string()));
+        assert_eq!(rejected.player_name, None);
+    }
+    let guard = state.read().await;
+    assert!(guard.room_players(&room_id).is_empty());
+    assert_eq!(guard.rooms[&room_id].current_players, 1);
+}
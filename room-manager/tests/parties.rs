@@ -0,0 +1,150 @@
+mod support;
+
+use std::time::Duration;
+
+use room_manager::{
+    party::{CreatePartyRequest, JoinPartyRequest, LeavePartyRequest, PartyInviteRequest, PartyResponse},
+    AssignRoomRequest, AssignRoomResponse, GameMode,
+};
+use support::SharedState;
+
+/// Leader tạo party rồi lần lượt mời từng người bằng invite code
+async fn party_of(state: &SharedState, leader: &str, friends: &[&str]) -> PartyResponse {
+    let created = room_manager::create_party(state.clone(), CreatePartyRequest { player_id: leader.to_string() })
+        .await
+        .expect("create party");
+    assert!(created.success, "{:?}", created.error);
+    let party_id = created.party.expect("party").id;
+
+    let invited = room_manager::invite_to_party(
+        state.clone(),
+        PartyInviteRequest {
+            party_id,
+            player_id: leader.to_string(),
+        },
+    )
+    .await
+    .expect("invite");
+    let code = invited.party.as_ref().and_then(|party| party.invite_code.clone()).expect("invite code");
+
+    let mut last = None;
+    for friend in friends {
+        let joined = room_manager::join_party(
+            state.clone(),
+            JoinPartyRequest {
+                player_id: friend.to_string(),
+                invite_code: code.to_lowercase(),
+            },
+        )
+        .await
+        .expect("join party");
+        assert!(joined.success, "{:?}", joined.error);
+        last = Some(joined);
+    }
+    last.unwrap_or(invited)
+}
+
+async fn assign(state: &SharedState, player_id: &str) -> AssignRoomResponse {
+    room_manager::assign_room(
+        state.clone(),
+        AssignRoomRequest {
+            player_id: player_id.to_string(),
+            game_mode: Some(GameMode::deathmatch()),
+        },
+    )
+    .await
+    .expect("assign room")
+}
+
+#[tokio::test]
+async fn party_of_three_is_assigned_to_one_room_on_one_team() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    let party = party_of(&state, "leader", &["friend-1", "friend-2"]).await.party.expect("party");
+    assert_eq!(party.members, ["leader", "friend-1", "friend-2"]);
+
+    // Chỉ leader được xếp hàng, và member chưa bị xếp lẻ vào đâu
+    let by_member = assign(&state, "friend-1").await;
+    assert_eq!(by_member.room_id, None);
+    assert_eq!(by_member.error.as_deref(), Some("Only the party leader can start queueing"));
+    assert!(state.read().await.players.is_empty());
+
+    let assigned = assign(&state, "leader").await;
+    let room_id = assigned.room_id.expect("room");
+    let team = assigned.team.expect("team");
+    assert_eq!(assigned.party_members, ["leader", "friend-1", "friend-2"]);
+
+    let state = state.read().await;
+    for member in &party.members {
+        let player = &state.players[member];
+        assert_eq!(player.room_id, room_id);
+        assert_eq!(player.team.as_deref(), Some(team.as_str()));
+    }
+    assert_eq!(state.rooms[&room_id].current_players, 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn full_room_never_splits_a_party() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    let created = support::create_room(&state, support::create_request("almost full")).await;
+    let joined = support::join(&state, &created.room_id, "solo").await;
+    assert!(joined.success, "{:?}", joined.error);
+
+    // Phòng còn 2 chỗ, party 3 người phải sang phòng khác cùng nhau
+    party_of(&state, "leader", &["friend-1", "friend-2"]).await;
+    let assigned = assign(&state, "leader").await;
+    let room_id = assigned.room_id.expect("room");
+    assert_ne!(room_id, created.room_id);
+
+    let state = state.read().await;
+    assert_eq!(state.rooms[&created.room_id].current_players, 2);
+    assert_eq!(state.rooms[&room_id].current_players, 3);
+    assert!(["leader", "friend-1", "friend-2"].iter().all(|member| state.players[*member].room_id == room_id));
+    Ok(())
+}
+
+#[tokio::test]
+async fn party_rejects_fifth_member_and_disbands_when_leader_leaves() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    let party = party_of(&state, "leader", &["a", "b", "c"]).await.party.expect("party");
+    let code = party.invite_code.clone().expect("invite code");
+
+    let fifth = room_manager::join_party(
+        state.clone(),
+        JoinPartyRequest {
+            player_id: "d".to_string(),
+            invite_code: code.clone(),
+        },
+    )
+    .await?;
+    assert_eq!(fifth.error.as_deref(), Some("Party is full"));
+
+    let member_left = room_manager::leave_party(state.clone(), LeavePartyRequest { player_id: "c".to_string() }).await?;
+    assert_eq!(member_left.party.expect("party").members, ["leader", "a", "b"]);
+
+    let leader_left = room_manager::leave_party(state.clone(), LeavePartyRequest { player_id: "leader".to_string() }).await?;
+    assert!(leader_left.success);
+    assert!(leader_left.party.is_none());
+    let state = state.read().await;
+    assert!(state.parties.is_empty());
+    assert!(state.party_of("a").is_none());
+    assert!(state.party_invites.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn heartbeat_disbands_idle_parties_but_keeps_playing_ones() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    party_of(&state, "idle-leader", &["idle-friend"]).await;
+    party_of(&state, "playing-leader", &["playing-friend"]).await;
+    assert!(assign(&state, "playing-leader").await.room_id.is_some());
+
+    let mut state = state.write().await;
+    state.party_ttl = Duration::ZERO;
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    state.heartbeat().await?;
+
+    assert!(state.party_of("idle-leader").is_none());
+    assert!(state.party_of("playing-friend").is_some());
+    Ok(())
+}
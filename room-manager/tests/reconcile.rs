@@ -0,0 +1,107 @@
+mod support;
+
+use std::time::Duration;
+
+use proto::worker::v1::{CreateRoomRequest as WorkerCreateRoomRequest, RoomSettings};
+use room_manager::{matchmaking_metrics, reconcile::Reconciler, CreateRoomRequest, GameMode, Room, RoomStatus};
+use support::stored_room_status;
+
+fn room(id: &str, updated_at: chrono::DateTime<chrono::Utc>) -> Room {
+    Room {
+        id: id.to_string(),
+        name: id.to_string(),
+        game_mode: GameMode::deathmatch(),
+        max_players: 4,
+        current_players: 1,
+        status: RoomStatus::InProgress,
+        created_at: updated_at,
+        updated_at,
+        host_player_id: "host".to_string(),
+        worker_endpoint: None,
+        settings: serde_json::json!({}),
+        backfill_with_bots: false,
+        is_private: false,
+        invite_code: None,
+        banned_players: Vec::new(),
+        starts_at: None,
+    }
+}
+
+#[tokio::test]
+async fn reconciliation_closes_ghost_rooms() -> Result<(), room_manager::BoxError> {
+    let (worker_endpoint, worker_server) = worker::rpc::spawn_test_server().await;
+    let live_room_id = worker::rpc::client(&worker_endpoint)?
+        .create_room(WorkerCreateRoomRequest {
+            room_name: "live".to_string(),
+            host_id: "host".to_string(),
+            host_name: "Host".to_string(),
+            settings: Some(RoomSettings { max_players: 4, ..Default::default() }),
+        })
+        .await?
+        .into_inner()
+        .room_id;
+
+    let (state, pocketbase) = support::state();
+    let stale = chrono::Utc::now() - chrono::Duration::minutes(10);
+
+    // Phòng ma: có trong memory và database nhưng worker không còn sim
+    let ghost = support::create_room(&state, support::create_request("ghost")).await.room_id;
+    let joined = support::join(&state, &ghost, "ghost-player").await;
+    assert!(joined.success, "{:?}", joined.error);
+
+    // Phòng vừa tạo, chưa qua grace period
+    let request = CreateRoomRequest { host_player_id: "host-2".to_string(), ..support::create_request("fresh") };
+    let fresh = support::create_room(&state, request).await.room_id;
+
+    {
+        let mut state = state.write().await;
+        state.rooms.get_mut(&ghost).expect("ghost room").updated_at = stale;
+        state.rooms.insert(live_room_id.clone(), room(&live_room_id, stale));
+        state
+            .pocketbase
+            .update_record("rooms", &ghost, serde_json::json!({ "updated_at": stale }))
+            .await?;
+        // Record mồ côi từ lần chạy trước, memory không biết
+        state
+            .pocketbase
+            .create_record(
+                "rooms",
+                serde_json::json!({
+                    "id": "orphan",
+                    "status": serde_json::to_string(&RoomStatus::Waiting)?,
+                    "updated_at": stale,
+                }),
+            )
+            .await?;
+    }
+
+    let fixed_before = matchmaking_metrics().reconciliation_fixed_total.get();
+    let mut reconciler = Reconciler::new(state.clone(), &worker_endpoint, Duration::from_secs(60))?;
+    let report = reconciler.run_once().await?;
+    assert_eq!(report.live_rooms, 1);
+    assert_eq!(report.closed, {
+        let mut expected = vec![ghost.clone(), "orphan".to_string()];
+        expected.sort();
+        expected
+    });
+    assert_eq!(matchmaking_metrics().reconciliation_fixed_total.get(), fixed_before + 2);
+
+    {
+        let state = state.read().await;
+        assert_eq!(state.rooms[&ghost].status, RoomStatus::Closed);
+        assert!(!state.players.contains_key("ghost-player"));
+        assert_eq!(state.rooms[&fresh].status, RoomStatus::Waiting);
+        assert_eq!(state.rooms[&live_room_id].status, RoomStatus::InProgress);
+        assert_eq!(state.counts(), (2, 2));
+    }
+    assert_eq!(stored_room_status(&pocketbase, &ghost), "closed");
+    assert_eq!(stored_room_status(&pocketbase, "orphan"), "closed");
+    assert_eq!(stored_room_status(&pocketbase, &fresh), "waiting");
+
+    // Chạy lại không sửa thêm gì
+    assert!(reconciler.run_once().await?.closed.is_empty());
+    assert_eq!(matchmaking_metrics().reconciliation_fixed_total.get(), fixed_before + 2);
+
+    worker_server.abort();
+    Ok(())
+}
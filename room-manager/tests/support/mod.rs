@@ -0,0 +1,67 @@
+//! Fixture dùng chung cho integration test của room-manager: state trên PocketBase giả của test-harness và
+//! các request tạo/join phòng. Mỗi file test chỉ dùng một phần.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use room_manager::{CreateRoomRequest, CreateRoomResponse, GameMode, JoinRoomRequest, JoinRoomResponse, RoomManagerState};
+use test_harness::FakePocketBase;
+use tokio::sync::RwLock;
+
+pub type SharedState = Arc<RwLock<RoomManagerState>>;
+
+/// PocketBase giả mới cùng state room-manager trỏ vào nó
+pub fn state() -> (SharedState, FakePocketBase) {
+    let pocketbase = FakePocketBase::spawn().expect("fake pocketbase");
+    (state_on(&pocketbase), pocketbase)
+}
+
+/// State room-manager mới trên PocketBase có sẵn, vd. để giả lập restart
+pub fn state_on(pocketbase: &FakePocketBase) -> SharedState {
+    Arc::new(RwLock::new(RoomManagerState::new(&pocketbase.url).expect("state")))
+}
+
+/// Phòng deathmatch public 4 chỗ của `host`; test đổi field cần thiết bằng struct update
+pub fn create_request(name: &str) -> CreateRoomRequest {
+    CreateRoomRequest {
+        name: name.to_string(),
+        game_mode: GameMode::deathmatch(),
+        max_players: 4,
+        host_player_id: "host".to_string(),
+        settings: None,
+        backfill_with_bots: false,
+        is_private: false,
+    }
+}
+
+/// Tạo phòng và assert thành công
+pub async fn create_room(state: &SharedState, request: CreateRoomRequest) -> CreateRoomResponse {
+    let created = room_manager::create_room(state.clone(), request).await.expect("create room");
+    assert!(created.success, "{:?}", created.error);
+    created
+}
+
+/// Join với tên hiển thị là chính `player_id`, không invite code
+pub fn join_request(room_id: &str, player_id: &str) -> JoinRoomRequest {
+    JoinRoomRequest {
+        room_id: room_id.to_string(),
+        player_id: player_id.to_string(),
+        player_name: player_id.to_string(),
+        invite_code: None,
+    }
+}
+
+pub async fn join(state: &SharedState, room_id: &str, player_id: &str) -> JoinRoomResponse {
+    join_with(state, join_request(room_id, player_id)).await
+}
+
+pub async fn join_with(state: &SharedState, request: JoinRoomRequest) -> JoinRoomResponse {
+    room_manager::join_room(state.clone(), request).await.expect("join room")
+}
+
+/// Status đã lưu của phòng; `status` được lưu dạng JSON string
+pub fn stored_room_status(pocketbase: &FakePocketBase, room_id: &str) -> String {
+    let record = pocketbase.record("rooms", room_id).expect("room record");
+    let status = record["status"].as_str().unwrap_or_default().to_string();
+    serde_json::from_str::<String>(&status).unwrap_or(status)
+}
@@ -0,0 +1,214 @@
+mod support;
+
+use room_manager::{
+    tournament::{
+        CreateTournamentRequest, RegisterParticipantRequest, ReportMatchResultRequest, StartTournamentRequest,
+        TournamentInfo, TournamentManager, TOURNAMENTS_COLLECTION,
+    },
+    GameMode, RoomStatus,
+};
+use support::SharedState;
+use test_harness::FakePocketBase;
+
+fn seed_rating(pocketbase: &FakePocketBase, player_id: &str, skill_rating: f32) {
+    let rating = serde_json::json!({
+        "id": player_id,
+        "player_id": player_id,
+        "skill_rating": skill_rating,
+        "rating_deviation": 350.0,
+        "volatility": 0.06,
+        "games_played": 10,
+        "wins": 5,
+        "losses": 5,
+        "draws": 0,
+        "win_streak": 0,
+        "best_streak": 2,
+        "last_updated": 0,
+        "rank": null,
+        "tier": null,
+    });
+    pocketbase.insert("player_ratings", rating);
+}
+
+fn setup() -> (FakePocketBase, SharedState, TournamentManager) {
+    let (rooms, pocketbase) = support::state();
+    let manager = TournamentManager::new(rooms.clone());
+    (pocketbase, rooms, manager)
+}
+
+/// Tạo giải, đăng ký `players` (player `p{i}` có rating 2000 - 100*i nên p1 là hạt giống số 1) rồi bắt đầu
+async fn started_tournament(pocketbase: &FakePocketBase, manager: &TournamentManager, players: usize) -> TournamentInfo {
+    let created = manager
+        .create_tournament(CreateTournamentRequest {
+            name: "cup".to_string(),
+            game_mode: GameMode::deathmatch(),
+            max_participants: players as u32,
+            host_player_id: "host".to_string(),
+            format: None,
+        })
+        .await
+        .expect("create tournament");
+    assert!(created.success, "{:?}", created.error);
+    let tournament_id = created.tournament.expect("tournament").tournament.id;
+
+    // Đăng ký ngược thứ tự rating để chắc seed theo rating chứ không theo thứ tự đăng ký
+    for i in (1..=players).rev() {
+        let player_id = format!("p{i}");
+        seed_rating(pocketbase, &player_id, 2000.0 - 100.0 * i as f32);
+        let registered = manager
+            .register_participant(RegisterParticipantRequest {
+                tournament_id: tournament_id.clone(),
+                player_name: player_id.to_uppercase(),
+                player_id,
+            })
+            .await
+            .expect("register");
+        assert!(registered.success, "{:?}", registered.error);
+    }
+
+    let full = manager
+        .register_participant(RegisterParticipantRequest {
+            tournament_id: tournament_id.clone(),
+            player_id: "late".to_string(),
+            player_name: "late".to_string(),
+        })
+        .await
+        .expect("register");
+    assert!(!full.success, "registration is capped at max_participants");
+
+    let not_host = manager
+        .start_tournament(StartTournamentRequest {
+            tournament_id: tournament_id.clone(),
+            requesting_player_id: "p1".to_string(),
+            admin: false,
+        })
+        .await
+        .expect("start");
+    assert_eq!(not_host.error.as_deref(), Some("Only the host can start the tournament"));
+
+    let started = manager
+        .start_tournament(StartTournamentRequest {
+            tournament_id,
+            requesting_player_id: "host".to_string(),
+            admin: false,
+        })
+        .await
+        .expect("start");
+    assert!(started.success, "{:?}", started.error);
+    started.tournament.expect("tournament")
+}
+
+/// Báo kết quả từng trận (người có số nhỏ hơn thắng) cho tới khi có nhà vô địch
+async fn play_out(rooms: &SharedState, manager: &TournamentManager, mut info: TournamentInfo) -> TournamentInfo {
+    let tournament_id = info.tournament.id.clone();
+    let mut reported = 0;
+    while info.champion.is_none() {
+        let bracket = info.tournament.brackets.last().expect("bracket").clone();
+        let pending: Vec<_> = bracket.matches.iter().filter(|game| game.winner.is_none()).cloned().collect();
+        assert!(!pending.is_empty(), "round {} has nothing to report", bracket.round);
+
+        for game in pending {
+            // Trận thật có phòng riêng, đủ người, không cho ai khác vào
+            let room_id = info.match_rooms.get(&game.match_id).expect("match room").clone();
+            {
+                let state = rooms.read().await;
+                let room = &state.rooms[&room_id];
+                assert_eq!(room.current_players, 2);
+                assert_eq!(room.max_players, 2);
+                assert_eq!(room.settings["match_id"], game.match_id);
+            }
+
+            let winner = game
+                .players
+                .iter()
+                .min_by_key(|player| player[1..].parse::<u32>().unwrap())
+                .unwrap()
+                .clone();
+            let result = manager
+                .report_match_result(ReportMatchResultRequest {
+                    tournament_id: tournament_id.clone(),
+                    match_id: game.match_id.clone(),
+                    winner_player_id: winner,
+                    requesting_player_id: "host".to_string(),
+                    admin: false,
+                })
+                .await
+                .expect("report");
+            assert!(result.success, "{:?}", result.error);
+            assert_eq!(rooms.read().await.rooms[&room_id].status, RoomStatus::Finished);
+            info = result.tournament.expect("tournament");
+            reported += 1;
+        }
+    }
+    assert_eq!(reported, info.match_rooms.len(), "every played match had a room");
+    info
+}
+
+#[tokio::test]
+async fn eight_player_tournament_produces_a_single_champion() {
+    let (pocketbase, rooms, manager) = setup();
+    let before = room_manager::matchmaking_metrics().tournaments_completed_total.get();
+
+    let info = started_tournament(&pocketbase, &manager, 8).await;
+    let round_one = &info.tournament.brackets[0];
+    assert_eq!(round_one.matches.len(), 4);
+    assert_eq!(round_one.matches[0].players, ["p1", "p8"]);
+    assert_eq!(info.match_rooms.len(), 4);
+
+    let info = play_out(&rooms, &manager, info).await;
+    assert_eq!(info.champion.as_deref(), Some("p1"));
+    assert_eq!(info.tournament.brackets.len(), 3);
+    assert_eq!(info.match_rooms.len(), 7);
+    assert!(room_manager::matchmaking_metrics().tournaments_completed_total.get() > before);
+
+    let persisted = pocketbase.record(TOURNAMENTS_COLLECTION, &info.tournament.id).expect("tournament record");
+    assert_eq!(persisted["status"], "Completed");
+}
+
+#[tokio::test]
+async fn six_player_tournament_gives_top_seeds_byes() {
+    let (pocketbase, rooms, manager) = setup();
+
+    let info = started_tournament(&pocketbase, &manager, 6).await;
+    let round_one = &info.tournament.brackets[0];
+    assert_eq!(round_one.matches.len(), 4);
+    let byes: Vec<_> = round_one
+        .matches
+        .iter()
+        .filter(|game| game.players.len() == 1)
+        .map(|game| game.winner.clone().expect("bye is decided"))
+        .collect();
+    assert_eq!(byes.len(), 2);
+    assert!(byes.contains(&"p1".to_string()) && byes.contains(&"p2".to_string()));
+    // Trận bye không mở phòng
+    assert_eq!(info.match_rooms.len(), 2);
+
+    let info = play_out(&rooms, &manager, info).await;
+    assert_eq!(info.champion.as_deref(), Some("p1"));
+    assert_eq!(info.match_rooms.len(), 5);
+}
+
+#[tokio::test]
+async fn unfinished_tournament_is_restored_after_restart() {
+    let (pocketbase, rooms, manager) = setup();
+    let info = started_tournament(&pocketbase, &manager, 4).await;
+    let game = info.tournament.brackets[0].matches[0].clone();
+
+    let restarted = TournamentManager::new(rooms.clone());
+    restarted.sync_with_database().await.expect("sync");
+    let restored = restarted.get_tournament(&info.tournament.id).await;
+    assert!(restored.success, "{:?}", restored.error);
+    assert_eq!(restored.tournament.expect("tournament").match_rooms, info.match_rooms);
+
+    let result = restarted
+        .report_match_result(ReportMatchResultRequest {
+            tournament_id: info.tournament.id.clone(),
+            match_id: game.match_id,
+            winner_player_id: game.players[0].clone(),
+            requesting_player_id: "moderator".to_string(),
+            admin: true,
+        })
+        .await
+        .expect("report");
+    assert!(result.success, "{:?}", result.error);
+}
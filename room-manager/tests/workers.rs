@@ -0,0 +1,75 @@
+mod support;
+
+use room_manager::{workers::WorkerPool, AssignRoomRequest, CreateRoomRequest, GameMode};
+use support::SharedState;
+
+const WORKER_A: &str = "http://worker-a:50051";
+const WORKER_B: &str = "http://worker-b:50051";
+
+/// Phòng có host, bắt đầu khi có thêm một player
+async fn create_room(state: &SharedState, name: &str) -> String {
+    let request = CreateRoomRequest {
+        host_player_id: format!("{name}-host"),
+        settings: Some(serde_json::json!({ "min_players_to_start": 2 })),
+        ..support::create_request(name)
+    };
+    support::create_room(state, request).await.room_id
+}
+
+async fn join_worker(state: &SharedState, room_id: &str, player_id: &str) -> Option<String> {
+    let joined = support::join(state, room_id, player_id).await;
+    assert!(joined.success, "{:?}", joined.error);
+    joined.room.expect("room").worker_endpoint
+}
+
+#[tokio::test]
+async fn starting_rooms_spread_across_workers_and_return_the_endpoint() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    state.write().await.workers = WorkerPool::new([WORKER_A, WORKER_B]);
+
+    // Phòng chưa đủ người thì chưa có worker
+    let first = create_room(&state, "first").await;
+    assert_eq!(state.read().await.rooms[&first].worker_endpoint, None);
+
+    let assigned = room_manager::assign_room(
+        state.clone(),
+        AssignRoomRequest { player_id: "assigned-player".to_string(), game_mode: Some(GameMode::deathmatch()) },
+    )
+    .await?;
+    assert_eq!(assigned.room_id.as_deref(), Some(first.as_str()));
+    assert_eq!(assigned.worker_endpoint.as_deref(), Some(WORKER_A));
+
+    let second = create_room(&state, "second").await;
+    assert_eq!(join_worker(&state, &second, "second-player").await.as_deref(), Some(WORKER_B));
+
+    let load = state.read().await.worker_load();
+    assert_eq!((load[WORKER_A], load[WORKER_B]), (1, 1));
+    assert_eq!(state.read().await.rooms[&first].worker_endpoint.as_deref(), Some(WORKER_A));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unhealthy_workers_are_skipped() -> Result<(), room_manager::BoxError> {
+    let (state, _pocketbase) = support::state();
+    state.write().await.workers = WorkerPool::new([WORKER_A, WORKER_B]);
+    state.write().await.workers.set_healthy(WORKER_A, false);
+
+    for name in ["first", "second"] {
+        let room_id = create_room(&state, name).await;
+        assert_eq!(join_worker(&state, &room_id, &format!("{name}-player")).await.as_deref(), Some(WORKER_B));
+    }
+
+    // Hết worker khoẻ: phòng vẫn đếm ngược nhưng chưa được vào trận
+    state.write().await.workers.set_healthy(WORKER_B, false);
+    let stranded = create_room(&state, "stranded").await;
+    assert_eq!(join_worker(&state, &stranded, "stranded-player").await, None);
+    state.write().await.rooms.get_mut(&stranded).expect("room").starts_at = Some(chrono::Utc::now() - chrono::Duration::seconds(1));
+    assert!(!state.write().await.check_room_starts().await.contains(&stranded));
+
+    state.write().await.workers.set_healthy(WORKER_A, true);
+    assert!(state.write().await.check_room_starts().await.contains(&stranded));
+    assert_eq!(state.read().await.rooms[&stranded].worker_endpoint.as_deref(), Some(WORKER_A));
+
+    Ok(())
+}
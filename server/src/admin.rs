@@ -0,0 +1,190 @@
+//! Cổng admin của binary tổng: `/ready` gộp readiness của gateway, worker và room-manager.
+//! Service chưa gửi địa chỉ qua `ready_tx` là `starting`; đã bind thì hỏi health endpoint của nó, chỉ trả 200
+//! khi mọi service đều `ok`, không thì 503 kèm trạng thái từng service.
+//! `POST /admin/reload` đọc lại file config ngay; gateway có `admin_token` thì phải gửi kèm Bearer token đó.
+
+use std::{collections::BTreeMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use common_net::health::{HEALTHZ_PATH, READYZ_PATH};
+use serde::Serialize;
+use tokio::sync::{oneshot, RwLock};
+
+use crate::{reload, BoxError};
+
+pub const READY_PATH: &str = "/ready";
+pub const RELOAD_PATH: &str = "/admin/reload";
+pub const DEFAULT_ADMIN_ADDR: &str = "127.0.0.1:3300";
+/// Mỗi lần hỏi health của service bị cắt sau ngần này và tính là down
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    Gateway,
+    Worker,
+    RoomManager,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 3] = [Subsystem::Gateway, Subsystem::Worker, Subsystem::RoomManager];
+
+    /// `/readyz` của gateway đòi worker theo endpoint cấu hình, worker đã được gộp riêng nên gateway chỉ cần liveness
+    fn health_path(self) -> &'static str {
+        match self {
+            Subsystem::Gateway => HEALTHZ_PATH,
+            Subsystem::Worker | Subsystem::RoomManager => READYZ_PATH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceStatus {
+    /// Chưa bind xong
+    Starting,
+    Ok,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReadyReport {
+    pub ready: bool,
+    pub services: BTreeMap<Subsystem, ServiceStatus>,
+}
+
+impl IntoResponse for ReadyReport {
+    fn into_response(self) -> Response {
+        let status = if self.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        (status, Json(self)).into_response()
+    }
+}
+
+/// Địa chỉ đã bind của từng service, clone rẻ để làm state cho router
+#[derive(Debug, Clone)]
+pub struct ReadyState {
+    bound: Arc<RwLock<BTreeMap<Subsystem, SocketAddr>>>,
+    client: reqwest::Client,
+    /// None khi server chạy không có file config
+    reload: Option<reload::ReloadTrigger>,
+}
+
+impl Default for ReadyState {
+    fn default() -> Self {
+        Self {
+            bound: Arc::default(),
+            client: reqwest::Client::builder()
+                .timeout(PROBE_TIMEOUT)
+                .build()
+                .expect("build readiness http client"),
+            reload: None,
+        }
+    }
+}
+
+impl ReadyState {
+    pub fn with_reload(mut self, trigger: reload::ReloadTrigger) -> Self {
+        self.reload = Some(trigger);
+        self
+    }
+
+    pub async fn mark_bound(&self, subsystem: Subsystem, addr: SocketAddr) {
+        self.bound.write().await.insert(subsystem, addr);
+    }
+
+    /// Thay `ready_tx` của service bằng sender nối vào state; địa chỉ vẫn được chuyển tiếp cho sender cũ nếu có
+    pub fn intercept(
+        &self,
+        subsystem: Subsystem,
+        forward: Option<oneshot::Sender<SocketAddr>>,
+    ) -> oneshot::Sender<SocketAddr> {
+        let (tx, rx) = oneshot::channel();
+        let state = self.clone();
+        tokio::spawn(async move {
+            if let Ok(addr) = rx.await {
+                state.mark_bound(subsystem, addr).await;
+                if let Some(forward) = forward {
+                    let _ = forward.send(addr);
+                }
+            }
+        });
+        tx
+    }
+
+    pub async fn report(&self) -> ReadyReport {
+        let bound = self.bound.read().await.clone();
+        let statuses = futures::future::join_all(Subsystem::ALL.iter().map(|&subsystem| {
+            let addr = bound.get(&subsystem).copied();
+            async move {
+                let status = match addr {
+                    None => ServiceStatus::Starting,
+                    Some(addr) => self.probe(subsystem, addr).await,
+                };
+                (subsystem, status)
+            }
+        }))
+        .await;
+
+        let services: BTreeMap<_, _> = statuses.into_iter().collect();
+        let ready = services.values().all(|status| *status == ServiceStatus::Ok);
+        ReadyReport { ready, services }
+    }
+
+    async fn probe(&self, subsystem: Subsystem, addr: SocketAddr) -> ServiceStatus {
+        let url = format!("http://{addr}{}", subsystem.health_path());
+        match self.client.get(url).send().await {
+            Ok(resp) if resp.status().is_success() => ServiceStatus::Ok,
+            _ => ServiceStatus::Down,
+        }
+    }
+
+    pub fn router(self) -> Router {
+        Router::new()
+            .route(READY_PATH, get(ready_handler))
+            .route(RELOAD_PATH, post(reload_handler))
+            .with_state(self)
+    }
+
+    /// Phục vụ router admin trên listener đã bind, dừng khi task bị abort
+    pub fn serve(self, listener: std::net::TcpListener) -> Result<tokio::task::JoinHandle<()>, BoxError> {
+        let server = axum::Server::from_tcp(listener).map_err(|err| Box::new(err) as BoxError)?;
+        let router = self.router();
+        Ok(tokio::spawn(async move {
+            if let Err(err) = server.serve(router.into_make_service()).await {
+                tracing::error!(%err, "server: admin endpoint dừng bất thường");
+            }
+        }))
+    }
+}
+
+async fn ready_handler(State(state): State<ReadyState>) -> ReadyReport {
+    state.report().await
+}
+
+async fn reload_handler(State(state): State<ReadyState>, headers: HeaderMap) -> Response {
+    let Some(trigger) = state.reload else {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": "server was started without a config file" })),
+        )
+            .into_response();
+    };
+    if let Some(token) = trigger.admin_token() {
+        if !gateway::auth::AdminToken::new(Some(token)).matches(&headers) {
+            return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "admin token required" }))).into_response();
+        }
+    }
+
+    let report = trigger.reload().await;
+    let status = match report.result {
+        reload::ReloadResult::Rejected => StatusCode::UNPROCESSABLE_ENTITY,
+        reload::ReloadResult::Applied | reload::ReloadResult::Unchanged => StatusCode::OK,
+    };
+    (status, Json(report)).into_response()
+}
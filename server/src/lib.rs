@@ -1,12 +1,16 @@
-use std::{fs, future::Future, path::Path, pin::Pin};
+use std::{fs, net::SocketAddr, path::Path, time::Duration};
 
 use common_net::shutdown;
 use gateway::{GatewayConfig, GatewaySettings};
 use room_manager::{RoomManagerConfig, RoomManagerSettings};
-use tokio::task::JoinSet;
-use tracing::{error, info};
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
 use worker::{WorkerConfig, WorkerSettings};
 
+pub mod admin;
+pub mod reload;
+pub mod supervisor;
+
 pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -14,6 +18,22 @@ pub struct ServerSettings {
     pub gateway: GatewaySettings,
     pub worker: WorkerSettings,
     pub room_manager: RoomManagerSettings,
+    /// Cổng admin phục vụ `/ready`; None thì không mở
+    #[serde(default = "default_admin_addr")]
+    pub admin_addr: Option<SocketAddr>,
+    /// Có thì service lỗi được khởi động lại riêng; None thì một service lỗi là tắt cả server
+    #[serde(default)]
+    pub restart_policy: Option<supervisor::RestartPolicy>,
+    /// Filter log kiểu RUST_LOG (vd. `info,gateway=debug`) thay cho filter lúc khởi động; đổi được lúc đang chạy
+    #[serde(default)]
+    pub log_level: Option<String>,
+    /// Có thì shutdown qua drain: ngừng nhận room/connection mới, chờ trận đang chơi kết thúc tối đa ngần này giây
+    #[serde(default)]
+    pub drain_timeout_secs: Option<u64>,
+}
+
+fn default_admin_addr() -> Option<SocketAddr> {
+    admin::DEFAULT_ADMIN_ADDR.parse().ok()
 }
 
 impl ServerSettings {
@@ -22,6 +42,23 @@ impl ServerSettings {
             gateway: GatewaySettings::from_env()?,
             worker: WorkerSettings::from_env()?,
             room_manager: RoomManagerSettings::from_env()?,
+            admin_addr: match std::env::var("SERVER_ADMIN_ADDR") {
+                Ok(raw) if raw.is_empty() => None,
+                Ok(raw) => Some(raw.parse().map_err(|err| Box::new(err) as BoxError)?),
+                Err(_) => default_admin_addr(),
+            },
+            restart_policy: match std::env::var("SERVER_MAX_RESTARTS") {
+                Ok(raw) => Some(supervisor::RestartPolicy {
+                    max_restarts: raw.parse().map_err(|err| Box::new(err) as BoxError)?,
+                    ..Default::default()
+                }),
+                Err(_) => None,
+            },
+            log_level: None,
+            drain_timeout_secs: match std::env::var("SERVER_DRAIN_TIMEOUT_SECS") {
+                Ok(raw) => Some(raw.parse().map_err(|err| Box::new(err) as BoxError)?),
+                Err(_) => None,
+            },
         })
     }
 
@@ -41,6 +78,15 @@ pub struct ServerConfig {
     pub gateway: GatewayConfig,
     pub worker: WorkerConfig,
     pub room_manager: RoomManagerConfig,
+    pub admin_addr: Option<SocketAddr>,
+    /// Nhận địa chỉ thật của cổng admin sau khi bind
+    pub admin_ready_tx: Option<oneshot::Sender<SocketAddr>>,
+    pub restart_policy: Option<supervisor::RestartPolicy>,
+    pub log_level: Option<String>,
+    /// Có thì đọc lại file config định kỳ và áp dụng các field đổi được an toàn
+    pub config_watch: Option<reload::ConfigWatch>,
+    /// Thời gian drain tối đa trước khi dừng hẳn; None thì dừng ngay khi nhận shutdown
+    pub drain_timeout: Option<Duration>,
 }
 
 impl ServerConfig {
@@ -49,6 +95,12 @@ impl ServerConfig {
             gateway: GatewayConfig::from_settings(settings.gateway),
             worker: WorkerConfig::from_settings(settings.worker).expect("valid worker settings"),
             room_manager: RoomManagerConfig::from_settings(settings.room_manager),
+            admin_addr: settings.admin_addr,
+            admin_ready_tx: None,
+            restart_policy: settings.restart_policy,
+            log_level: settings.log_level,
+            config_watch: None,
+            drain_timeout: settings.drain_timeout_secs.map(Duration::from_secs),
         }
     }
 
@@ -82,91 +134,163 @@ pub async fn run_with_shutdown(
     config: ServerConfig,
     shutdown_rx: shutdown::ShutdownReceiver,
 ) -> Result<(), BoxError> {
-    let (service_shutdown_tx, service_shutdown_rx) = shutdown::channel();
-
     let ServerConfig {
-        gateway,
-        worker,
-        room_manager,
+        mut gateway,
+        mut worker,
+        mut room_manager,
+        admin_addr,
+        admin_ready_tx,
+        restart_policy,
+        log_level,
+        config_watch,
+        drain_timeout,
     } = config;
 
-    let mut join_set: JoinSet<Result<(), BoxError>> = JoinSet::new();
-
-    let gateway_shutdown = service_shutdown_rx.clone();
-    join_set.spawn(async move { gateway::run(gateway, gateway_shutdown).await });
+    if let Some(Err(err)) = log_level.as_deref().map(common_net::telemetry::set_log_filter) {
+        error!(%err, "server: không áp dụng được log_level");
+    }
 
-    let worker_shutdown = service_shutdown_rx.clone();
-    join_set.spawn(async move { worker::run(worker, worker_shutdown).await });
+    let (reload_task, reload_trigger) = match config_watch {
+        Some(config_watch) => {
+            info!(path = %config_watch.path.display(), "server: theo dõi file config");
+            let (channels, task) = reload::spawn(config_watch);
+            gateway.reload_rx = Some(channels.gateway);
+            room_manager.reload_rx = Some(channels.room_manager);
+            (Some(task), Some(channels.trigger))
+        }
+        None => (None, None),
+    };
+    #[cfg(unix)]
+    let sighup_task = reload_trigger.clone().and_then(reload::spawn_sighup);
 
-    let room_manager_shutdown = service_shutdown_rx;
-    join_set.spawn(async move { room_manager::run(room_manager, room_manager_shutdown).await });
+    // Có drain thì service chỉ nhận shutdown sau khi drain xong hoặc quá hạn
+    let (shutdown_rx, drain_task) = match drain_timeout {
+        Some(timeout) => {
+            let (drain_tx, drain_rx) = shutdown::channel();
+            let (drained_tx, drained_rx) = shutdown::channel();
+            let (services_tx, services_rx) = shutdown::channel();
+            gateway.drain_rx = Some(drain_rx.clone());
+            worker.drain_rx = Some(drain_rx);
+            worker.drained_tx = Some(drained_tx);
+            let task = tokio::spawn(async move {
+                shutdown::wait(shutdown_rx).await;
+                info!(?timeout, "server: bắt đầu drain trước khi dừng");
+                shutdown::trigger(&drain_tx);
+                if tokio::time::timeout(timeout, shutdown::wait(drained_rx)).await.is_err() {
+                    warn!(?timeout, "server: hết thời gian drain, dừng các trận còn đang chơi");
+                }
+                shutdown::trigger(&services_tx);
+            });
+            (services_rx, Some(task))
+        }
+        None => (shutdown_rx, None),
+    };
 
-    let mut shutdown_future: Pin<Box<dyn Future<Output = ()> + Send>> =
-        Box::pin(shutdown::wait(shutdown_rx));
-    let mut service_error: Option<BoxError> = None;
+    let ready_state = match reload_trigger {
+        Some(trigger) => admin::ReadyState::default().with_reload(trigger),
+        None => admin::ReadyState::default(),
+    };
 
-    loop {
-        tokio::select! {
-            _ = &mut shutdown_future => {
-                info!("server: nhan tin hieu shutdown tu ben ngoai");
-                shutdown::trigger(&service_shutdown_tx);
-                break;
-            }
-            maybe_task = join_set.join_next() => {
-                match maybe_task {
-                    Some(Ok(Ok(()))) => continue,
-                    Some(Ok(Err(err))) => {
-                        error!(%err, "server: mot service ket thuc voi loi");
-                        service_error = Some(err);
-                        shutdown::trigger(&service_shutdown_tx);
-                        break;
-                    }
-                    Some(Err(join_err)) => {
-                        let err: BoxError = Box::new(join_err);
-                        error!(%err, "server: join handle gap loi");
-                        service_error = Some(err);
-                        shutdown::trigger(&service_shutdown_tx);
-                        break;
-                    }
-                    None => break,
-                }
+    let admin_task = match admin_addr {
+        Some(addr) => {
+            let listener = std::net::TcpListener::bind(addr).map_err(|err| Box::new(err) as BoxError)?;
+            let local_addr = listener.local_addr().map_err(|err| Box::new(err) as BoxError)?;
+            let task = ready_state.clone().serve(listener)?;
+            info!(%local_addr, path = admin::READY_PATH, "server: admin endpoint đang lắng nghe");
+            if let Some(tx) = admin_ready_tx {
+                let _ = tx.send(local_addr);
             }
+            Some(task)
         }
-    }
-
-    shutdown::trigger(&service_shutdown_tx);
+        None => None,
+    };
 
-    let drain_result = drain_join_set(&mut join_set).await;
+    let services = vec![
+        gateway_service(gateway, ready_state.clone()),
+        worker_service(worker, ready_state.clone()),
+        room_manager_service(room_manager, ready_state),
+    ];
+    let result = supervisor::supervise(services, restart_policy, shutdown_rx).await;
 
-    if let Some(err) = service_error {
-        return Err(err);
+    if let Some(task) = admin_task {
+        task.abort();
     }
-
-    drain_result
+    if let Some(task) = reload_task {
+        task.abort();
+    }
+    #[cfg(unix)]
+    if let Some(task) = sighup_task {
+        task.abort();
+    }
+    if let Some(task) = drain_task {
+        task.abort();
+    }
+    result
 }
 
-async fn drain_join_set(join_set: &mut JoinSet<Result<(), BoxError>>) -> Result<(), BoxError> {
-    let mut first_err: Option<BoxError> = None;
+// Mỗi lần (re)start dựng lại config từ bản gốc; `ready_tx` của caller chỉ nhận địa chỉ của lần bind đầu,
+// còn cổng admin luôn được báo địa chỉ mới nhất. `reload_rx` clone từ bản gốc nên lần start sau vẫn nhận
+// config đã reload trước đó.
 
-    while let Some(task) = join_set.join_next().await {
-        match task {
-            Ok(Ok(())) => {}
-            Ok(Err(err)) => {
-                if first_err.is_none() {
-                    first_err = Some(err);
-                }
-            }
-            Err(join_err) => {
-                if first_err.is_none() {
-                    first_err = Some(Box::new(join_err) as BoxError);
-                }
-            }
-        }
-    }
+fn gateway_service(mut template: GatewayConfig, ready: admin::ReadyState) -> supervisor::Service {
+    let mut forward = template.ready_tx.take();
+    supervisor::Service::new("gateway", move |shutdown_rx| {
+        let config = GatewayConfig {
+            bind_addr: template.bind_addr,
+            worker_endpoint: template.worker_endpoint.clone(),
+            allowed_origins: template.allowed_origins.clone(),
+            quic_bind_addr: template.quic_bind_addr,
+            rate_limit_per_second: template.rate_limit_per_second,
+            stun_servers: template.stun_servers.clone(),
+            admin_token: template.admin_token.clone(),
+            ws_ping_interval: template.ws_ping_interval,
+            worker_token: template.worker_token.clone(),
+            worker_tls: template.worker_tls.clone(),
+            room_manager_url: template.room_manager_url.clone(),
+            internal_secret: template.internal_secret.clone(),
+            ready_tx: Some(ready.intercept(admin::Subsystem::Gateway, forward.take())),
+            reload_rx: template.reload_rx.clone(),
+            drain_rx: template.drain_rx.clone(),
+        };
+        gateway::run(config, shutdown_rx)
+    })
+}
 
-    if let Some(err) = first_err {
-        return Err(err);
-    }
+fn worker_service(mut template: WorkerConfig, ready: admin::ReadyState) -> supervisor::Service {
+    let mut forward = template.ready_tx.take();
+    let mut forward_rpc = template.rpc_ready_tx.take();
+    supervisor::Service::new("worker", move |shutdown_rx| {
+        let config = WorkerConfig {
+            rpc_addr: template.rpc_addr,
+            metrics_addr: template.metrics_addr,
+            fail_fast: template.fail_fast,
+            pocketbase_url: template.pocketbase_url.clone(),
+            keyframe_interval_ticks: template.keyframe_interval_ticks,
+            room_manager_url: template.room_manager_url.clone(),
+            leaderboard_url: template.leaderboard_url.clone(),
+            internal_secret: template.internal_secret.clone(),
+            rpc_token: template.rpc_token.clone(),
+            rpc_tls: template.rpc_tls.clone(),
+            ready_tx: Some(ready.intercept(admin::Subsystem::Worker, forward.take())),
+            rpc_ready_tx: forward_rpc.take(),
+            drain_rx: template.drain_rx.clone(),
+            drained_tx: template.drained_tx.clone(),
+        };
+        worker::run(config, shutdown_rx)
+    })
+}
 
-    Ok(())
+fn room_manager_service(mut template: RoomManagerConfig, ready: admin::ReadyState) -> supervisor::Service {
+    let mut forward = template.ready_tx.take();
+    supervisor::Service::new("room_manager", move |shutdown_rx| {
+        let config = RoomManagerConfig {
+            metrics_addr: template.metrics_addr,
+            capacity_overrides: template.capacity_overrides.clone(),
+            ready_tx: Some(ready.intercept(admin::Subsystem::RoomManager, forward.take())),
+            pocketbase_url: template.pocketbase_url.clone(),
+            internal_secret: template.internal_secret.clone(),
+            reload_rx: template.reload_rx.clone(),
+        };
+        room_manager::run(config, shutdown_rx)
+    })
 }
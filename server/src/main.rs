@@ -3,7 +3,7 @@ use std::{net::SocketAddr, path::PathBuf};
 use clap::Parser;
 
 use common_net::telemetry;
-use server::{BoxError, ServerConfig, ServerSettings};
+use server::{reload::ConfigWatch, BoxError, ServerConfig, ServerSettings};
 
 #[derive(Debug, Parser)]
 #[command(author, version, about = "Server orchestrator for gamev1")]
@@ -22,6 +22,17 @@ struct ServerCli {
 
     #[arg(long, action = clap::ArgAction::SetTrue)]
     worker_fail_fast: bool,
+
+    #[arg(long, value_name = "ADDR")]
+    admin_addr: Option<SocketAddr>,
+
+    /// Bật chế độ giám sát: service lỗi được khởi động lại tối đa N lần
+    #[arg(long, value_name = "N")]
+    max_restarts: Option<u32>,
+
+    /// Shutdown qua drain: chờ trận đang chơi kết thúc tối đa N giây
+    #[arg(long, value_name = "SECS")]
+    drain_timeout_secs: Option<u64>,
 }
 
 impl ServerCli {
@@ -45,19 +56,34 @@ impl ServerCli {
         if self.worker_fail_fast {
             settings.worker.fail_fast = true;
         }
+        if let Some(addr) = self.admin_addr {
+            settings.admin_addr = Some(addr);
+        }
+        if let Some(max_restarts) = self.max_restarts {
+            let policy = settings.restart_policy.unwrap_or_default();
+            settings.restart_policy = Some(server::supervisor::RestartPolicy { max_restarts, ..policy });
+        }
+        if let Some(secs) = self.drain_timeout_secs {
+            settings.drain_timeout_secs = Some(secs);
+        }
     }
 }
 
 fn build_config(cli: &ServerCli) -> Result<ServerConfig, BoxError> {
-    let mut settings = if let Some(path) = cli.resolve_config_path() {
-        ServerSettings::from_file(&path)?
+    let config_path = cli.resolve_config_path();
+    let mut settings = if let Some(path) = &config_path {
+        ServerSettings::from_file(path)?
     } else {
         ServerSettings::from_env()?
     };
+    // File được so với bản đọc lúc khởi động, không phải bản đã override bằng CLI
+    let config_watch = config_path.map(|path| ConfigWatch::new(path, settings.clone()));
 
     cli.apply_overrides(&mut settings);
 
-    Ok(settings.into_config())
+    let mut config = settings.into_config();
+    config.config_watch = config_watch;
+    Ok(config)
 }
 
 #[tokio::main]
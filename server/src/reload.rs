@@ -0,0 +1,340 @@
+//! Đọc lại file config của binary tổng (định kỳ, qua `POST /admin/reload` hoặc SIGHUP) và áp dụng lúc đang chạy
+//! các field đổi được an toàn: origin CORS, rate limit, admin token và chu kỳ Ping của gateway, log level và sức
+//! chứa matchmaking. Field như địa chỉ bind hay cấu hình worker giữ giá trị đang chạy và được báo là cần restart;
+//! file sai cú pháp hoặc giá trị không hợp lệ bị bỏ qua, server giữ config đang chạy.
+
+use std::{fmt::Debug, path::PathBuf, time::Duration};
+
+use common_net::telemetry;
+use gateway::GatewaySettings;
+use room_manager::RoomManagerSettings;
+use serde::Serialize;
+use tokio::{
+    sync::{mpsc, oneshot, watch},
+    task::JoinHandle,
+};
+use tracing::{error, info, warn};
+
+use crate::ServerSettings;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// File config cần theo dõi cùng settings đã đọc từ nó lúc khởi động (trước override của CLI)
+#[derive(Debug, Clone)]
+pub struct ConfigWatch {
+    pub path: PathBuf,
+    pub baseline: ServerSettings,
+    pub interval: Duration,
+    /// Nhận báo cáo mỗi lần file được đọc lại (poll thấy nội dung đổi hoặc trigger), kể cả lần bị từ chối
+    pub reports_tx: Option<mpsc::UnboundedSender<ReloadReport>>,
+}
+
+impl ConfigWatch {
+    pub fn new(path: PathBuf, baseline: ServerSettings) -> Self {
+        Self { path, baseline, interval: DEFAULT_POLL_INTERVAL, reports_tx: None }
+    }
+}
+
+/// Kênh đẩy settings mới tới gateway và room-manager, cùng trigger để reload ngay không chờ chu kỳ
+pub struct ReloadChannels {
+    pub gateway: watch::Receiver<GatewaySettings>,
+    pub room_manager: watch::Receiver<RoomManagerSettings>,
+    pub trigger: ReloadTrigger,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReloadResult {
+    Applied,
+    Unchanged,
+    Rejected,
+}
+
+impl ReloadResult {
+    /// Label `result` của `gateway_config_reloads_total`
+    pub fn label(self) -> &'static str {
+        match self {
+            ReloadResult::Applied => "applied",
+            ReloadResult::Unchanged => "unchanged",
+            ReloadResult::Rejected => "rejected",
+        }
+    }
+}
+
+/// Kết quả một lần reload, cũng là body của `POST /admin/reload`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReloadReport {
+    pub result: ReloadResult,
+    /// Field đã áp dụng
+    pub changed: Vec<&'static str>,
+    /// Field trong file khác config đang chạy nhưng chỉ có hiệu lực sau restart
+    pub requires_restart: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ReloadReport {
+    fn rejected(error: String) -> Self {
+        Self { result: ReloadResult::Rejected, changed: Vec::new(), requires_restart: Vec::new(), error: Some(error) }
+    }
+}
+
+/// Yêu cầu task theo dõi đọc lại file ngay; clone được cho admin endpoint và SIGHUP
+#[derive(Debug, Clone)]
+pub struct ReloadTrigger {
+    requests: mpsc::Sender<oneshot::Sender<ReloadReport>>,
+    gateway: watch::Receiver<GatewaySettings>,
+}
+
+impl ReloadTrigger {
+    pub async fn reload(&self) -> ReloadReport {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if self.requests.send(reply_tx).await.is_err() {
+            return ReloadReport::rejected("config watcher is not running".to_string());
+        }
+        reply_rx
+            .await
+            .unwrap_or_else(|_| ReloadReport::rejected("config watcher is not running".to_string()))
+    }
+
+    /// Admin token của gateway theo config đang chạy
+    pub fn admin_token(&self) -> Option<String> {
+        self.gateway.borrow().admin_token.clone()
+    }
+}
+
+/// Một field reload được đã đổi; `values` (cũ, mới) là None với field bí mật
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub values: Option<(String, String)>,
+}
+
+/// Config sẽ chạy sau reload: field cần restart giữ giá trị đang chạy, còn lại lấy từ file
+#[derive(Debug, Clone)]
+pub struct ReloadPlan {
+    pub settings: ServerSettings,
+    pub changes: Vec<FieldChange>,
+    pub requires_restart: Vec<&'static str>,
+}
+
+fn diff<T: PartialEq + Debug>(changes: &mut Vec<FieldChange>, field: &'static str, running: &T, next: &T) {
+    if running != next {
+        changes.push(FieldChange { field, values: Some((format!("{running:?}"), format!("{next:?}"))) });
+    }
+}
+
+fn diff_secret<T: PartialEq>(changes: &mut Vec<FieldChange>, field: &'static str, running: &T, next: &T) {
+    if running != next {
+        changes.push(FieldChange { field, values: None });
+    }
+}
+
+/// Tách `next` thành phần áp dụng được khi đang chạy `running` và phần cần restart; Err nếu log level hay sức
+/// chứa trong `next` không hợp lệ
+pub fn plan_reload(running: &ServerSettings, next: &ServerSettings) -> Result<ReloadPlan, String> {
+    if let Some(Err(err)) = next.log_level.as_deref().map(telemetry::parse_log_filter) {
+        return Err(err);
+    }
+    room_manager::capacity::CapacityConfig::default().with_overrides(&next.room_manager.capacity_overrides)?;
+
+    let mut requires_restart = Vec::new();
+    if next.gateway.bind_addr != running.gateway.bind_addr {
+        requires_restart.push("gateway.bind_addr");
+    }
+    if next.gateway.quic_bind_addr != running.gateway.quic_bind_addr {
+        requires_restart.push("gateway.quic_bind_addr");
+    }
+    if next.gateway.worker_endpoint != running.gateway.worker_endpoint {
+        requires_restart.push("gateway.worker_endpoint");
+    }
+    if next.gateway.stun_servers != running.gateway.stun_servers {
+        requires_restart.push("gateway.stun_servers");
+    }
+    if next.gateway.worker_token != running.gateway.worker_token {
+        requires_restart.push("gateway.worker_token");
+    }
+    if next.gateway.worker_tls != running.gateway.worker_tls {
+        requires_restart.push("gateway.worker_tls");
+    }
+    if serde_json::to_value(&next.worker).ok() != serde_json::to_value(&running.worker).ok() {
+        requires_restart.push("worker");
+    }
+    if next.room_manager.metrics_addr != running.room_manager.metrics_addr {
+        requires_restart.push("room_manager.metrics_addr");
+    }
+    if next.admin_addr != running.admin_addr {
+        requires_restart.push("admin_addr");
+    }
+    if next.restart_policy != running.restart_policy {
+        requires_restart.push("restart_policy");
+    }
+    if next.drain_timeout_secs != running.drain_timeout_secs {
+        requires_restart.push("drain_timeout_secs");
+    }
+
+    let mut changes = Vec::new();
+    diff(&mut changes, "gateway.allowed_origins", &running.gateway.allowed_origins, &next.gateway.allowed_origins);
+    diff(
+        &mut changes,
+        "gateway.rate_limit_per_second",
+        &running.gateway.rate_limit_per_second,
+        &next.gateway.rate_limit_per_second,
+    );
+    diff_secret(&mut changes, "gateway.admin_token", &running.gateway.admin_token, &next.gateway.admin_token);
+    diff(
+        &mut changes,
+        "gateway.ws_ping_interval_ms",
+        &running.gateway.ws_ping_interval_ms,
+        &next.gateway.ws_ping_interval_ms,
+    );
+    diff(&mut changes, "log_level", &running.log_level, &next.log_level);
+    diff(
+        &mut changes,
+        "room_manager.capacity_overrides",
+        &running.room_manager.capacity_overrides,
+        &next.room_manager.capacity_overrides,
+    );
+
+    let mut settings = next.clone();
+    settings.gateway.bind_addr = running.gateway.bind_addr;
+    settings.gateway.quic_bind_addr = running.gateway.quic_bind_addr;
+    settings.gateway.worker_endpoint = running.gateway.worker_endpoint.clone();
+    settings.gateway.stun_servers = running.gateway.stun_servers.clone();
+    settings.gateway.worker_token = running.gateway.worker_token.clone();
+    settings.gateway.worker_tls = running.gateway.worker_tls.clone();
+    settings.worker = running.worker.clone();
+    settings.room_manager.metrics_addr = running.room_manager.metrics_addr;
+    settings.admin_addr = running.admin_addr;
+    settings.restart_policy = running.restart_policy;
+    settings.drain_timeout_secs = running.drain_timeout_secs;
+    Ok(ReloadPlan { settings, changes, requires_restart })
+}
+
+/// Mở kênh với giá trị ban đầu là `baseline` và spawn task đọc lại file mỗi `interval` hoặc khi được trigger
+pub fn spawn(config_watch: ConfigWatch) -> (ReloadChannels, JoinHandle<()>) {
+    let (gateway_tx, gateway_rx) = watch::channel(config_watch.baseline.gateway.clone());
+    let (room_manager_tx, room_manager_rx) = watch::channel(config_watch.baseline.room_manager.clone());
+    let (requests_tx, requests_rx) = mpsc::channel(4);
+    let trigger = ReloadTrigger { requests: requests_tx, gateway: gateway_rx.clone() };
+    let task = tokio::spawn(watch_file(config_watch, gateway_tx, room_manager_tx, requests_rx));
+    (ReloadChannels { gateway: gateway_rx, room_manager: room_manager_rx, trigger }, task)
+}
+
+/// SIGHUP đọc lại file config như `POST /admin/reload`
+#[cfg(unix)]
+pub fn spawn_sighup(trigger: ReloadTrigger) -> Option<JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            error!(%err, "server: không thể lắng nghe SIGHUP");
+            return None;
+        }
+    };
+    Some(tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            let report = trigger.reload().await;
+            info!(result = report.result.label(), "server: nhận SIGHUP, đã đọc lại file config");
+        }
+    }))
+}
+
+async fn watch_file(
+    config_watch: ConfigWatch,
+    gateway_tx: watch::Sender<GatewaySettings>,
+    room_manager_tx: watch::Sender<RoomManagerSettings>,
+    mut requests: mpsc::Receiver<oneshot::Sender<ReloadReport>>,
+) {
+    let ConfigWatch { path, baseline: mut running, interval, reports_tx } = config_watch;
+    let mut last_raw = tokio::fs::read_to_string(&path).await.ok();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        // Poll định kỳ chỉ reload khi nội dung file đổi, trigger thì luôn đọc lại và trả báo cáo
+        let reply = tokio::select! {
+            _ = ticker.tick() => None,
+            Some(reply) = requests.recv() => Some(reply),
+        };
+        let report = match tokio::fs::read_to_string(&path).await {
+            Ok(raw) if reply.is_none() && last_raw.as_deref() == Some(raw.as_str()) => continue,
+            Ok(raw) => {
+                let report = reload_from(&raw, &path, &mut running, &gateway_tx, &room_manager_tx);
+                last_raw = Some(raw);
+                report
+            }
+            Err(err) => {
+                if last_raw.take().is_some() || reply.is_some() {
+                    error!(%err, path = %path.display(), "server: không đọc được file config, giữ config đang chạy");
+                }
+                if reply.is_none() {
+                    continue;
+                }
+                ReloadReport::rejected(format!("cannot read {}: {err}", path.display()))
+            }
+        };
+        gateway::metrics::record_config_reload(report.result.label());
+        if let Some(reports_tx) = &reports_tx {
+            let _ = reports_tx.send(report.clone());
+        }
+        if let Some(reply) = reply {
+            let _ = reply.send(report);
+        }
+    }
+}
+
+fn reload_from(
+    raw: &str,
+    path: &std::path::Path,
+    running: &mut ServerSettings,
+    gateway_tx: &watch::Sender<GatewaySettings>,
+    room_manager_tx: &watch::Sender<RoomManagerSettings>,
+) -> ReloadReport {
+    let next: ServerSettings = match serde_json::from_str(raw) {
+        Ok(next) => next,
+        Err(err) => {
+            error!(%err, path = %path.display(), "server: file config mới không hợp lệ, giữ config đang chạy");
+            return ReloadReport::rejected(format!("invalid config file: {err}"));
+        }
+    };
+    let plan = match plan_reload(running, &next) {
+        Ok(plan) => plan,
+        Err(reason) => {
+            error!(%reason, path = %path.display(), "server: từ chối config mới, giữ config đang chạy");
+            return ReloadReport::rejected(reason);
+        }
+    };
+
+    for field in &plan.requires_restart {
+        warn!(field, path = %path.display(), "server: field này chỉ đổi được khi restart, giữ giá trị đang chạy");
+    }
+    let changed: Vec<&'static str> = plan.changes.iter().map(|change| change.field).collect();
+    if changed.is_empty() {
+        return ReloadReport {
+            result: ReloadResult::Unchanged,
+            changed,
+            requires_restart: plan.requires_restart,
+            error: None,
+        };
+    }
+    for change in &plan.changes {
+        match &change.values {
+            Some((from, to)) => info!(field = change.field, %from, %to, "server: đổi config"),
+            None => info!(field = change.field, "server: đổi config (giá trị bí mật không ghi log)"),
+        }
+    }
+
+    if plan.settings.log_level != running.log_level {
+        if let Some(Err(err)) = plan.settings.log_level.as_deref().map(telemetry::set_log_filter) {
+            error!(%err, "server: không đổi được log level");
+        }
+    }
+    gateway_tx.send_replace(plan.settings.gateway.clone());
+    room_manager_tx.send_replace(plan.settings.room_manager.clone());
+    info!(path = %path.display(), "server: đã nạp lại file config");
+    *running = plan.settings;
+    ReloadReport { result: ReloadResult::Applied, changed, requires_restart: plan.requires_restart, error: None }
+}
+
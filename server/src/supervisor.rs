@@ -0,0 +1,203 @@
+//! Giám sát các service chạy chung trong binary tổng. Không có `RestartPolicy` thì service lỗi kéo theo tắt cả server;
+//! có policy thì chỉ service lỗi được chạy lại với backoff luỹ thừa, service khác vẫn chạy, vượt quá số lần cho phép
+//! mới tắt toàn bộ.
+
+use std::{future::Future, panic::AssertUnwindSafe, pin::Pin, time::Duration};
+
+use common_net::shutdown;
+use futures::FutureExt;
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter_vec, IntCounterVec};
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+
+use crate::BoxError;
+
+static SERVICE_RESTARTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "server_service_restarts_total",
+        "Số lần orchestrator khởi động lại service bị lỗi",
+        &["service"]
+    )
+    .expect("register server_service_restarts_total")
+});
+
+pub type ServiceFuture = Pin<Box<dyn Future<Output = Result<(), BoxError>> + Send>>;
+type StartFn = Box<dyn FnMut(shutdown::ShutdownReceiver) -> ServiceFuture + Send>;
+
+/// Service chạy được nhiều lần: mỗi lần khởi động (kể cả restart) gọi lại `start` để dựng config mới
+pub struct Service {
+    name: &'static str,
+    start: StartFn,
+}
+
+impl Service {
+    pub fn new<F, Fut>(name: &'static str, mut start: F) -> Self
+    where
+        F: FnMut(shutdown::ShutdownReceiver) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), BoxError>> + Send + 'static,
+    {
+        Self { name, start: Box::new(move |shutdown_rx| Box::pin(start(shutdown_rx)) as ServiceFuture) }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RestartPolicy {
+    /// Số lần restart tối đa của mỗi service trong suốt vòng đời server
+    pub max_restarts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self { max_restarts: 5, initial_backoff_ms: 500, max_backoff_ms: 30_000 }
+    }
+}
+
+impl RestartPolicy {
+    /// Chờ trước lần restart thứ `attempt` (tính từ 1): gấp đôi mỗi lần, không quá `max_backoff_ms`
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+        Duration::from_millis(self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms))
+    }
+}
+
+/// Số lần service đã được restart, đọc từ counter `server_service_restarts_total`
+pub fn restarts_total(service: &str) -> u64 {
+    SERVICE_RESTARTS_TOTAL.with_label_values(&[service]).get()
+}
+
+/// Chạy mọi service tới khi có tín hiệu shutdown từ ngoài hoặc một service lỗi quá số lần restart cho phép
+pub async fn supervise(
+    mut services: Vec<Service>,
+    policy: Option<RestartPolicy>,
+    shutdown_rx: shutdown::ShutdownReceiver,
+) -> Result<(), BoxError> {
+    let (service_shutdown_tx, service_shutdown_rx) = shutdown::channel();
+    let mut join_set: JoinSet<(usize, Result<(), BoxError>)> = JoinSet::new();
+    for (index, service) in services.iter_mut().enumerate() {
+        spawn_service(&mut join_set, index, service, &service_shutdown_rx, Duration::ZERO);
+    }
+
+    let mut restarts = vec![0u32; services.len()];
+    let mut shutdown_future: Pin<Box<dyn Future<Output = ()> + Send>> = Box::pin(shutdown::wait(shutdown_rx));
+    let mut service_error: Option<BoxError> = None;
+
+    loop {
+        tokio::select! {
+            _ = &mut shutdown_future => {
+                info!("server: nhận tín hiệu shutdown từ bên ngoài");
+                break;
+            }
+            maybe_task = join_set.join_next() => {
+                match maybe_task {
+                    Some(Ok((_, Ok(())))) => continue,
+                    Some(Ok((index, Err(err)))) => {
+                        let service = &mut services[index];
+                        match policy {
+                            Some(policy) if restarts[index] < policy.max_restarts => {
+                                restarts[index] += 1;
+                                let backoff = policy.backoff(restarts[index]);
+                                warn!(
+                                    %err,
+                                    service = service.name,
+                                    attempt = restarts[index],
+                                    max_restarts = policy.max_restarts,
+                                    backoff_ms = backoff.as_millis() as u64,
+                                    "server: service lỗi, khởi động lại"
+                                );
+                                SERVICE_RESTARTS_TOTAL.with_label_values(&[service.name]).inc();
+                                spawn_service(&mut join_set, index, service, &service_shutdown_rx, backoff);
+                            }
+                            Some(_) => {
+                                error!(%err, service = service.name, "server: service vượt quá số lần khởi động lại");
+                                service_error = Some(err);
+                                break;
+                            }
+                            None => {
+                                error!(%err, service = service.name, "server: một service kết thúc với lỗi");
+                                service_error = Some(err);
+                                break;
+                            }
+                        }
+                    }
+                    Some(Err(join_err)) => {
+                        let err: BoxError = Box::new(join_err);
+                        error!(%err, "server: join handle gặp lỗi");
+                        service_error = Some(err);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    shutdown::trigger(&service_shutdown_tx);
+
+    let drain_result = drain_join_set(&mut join_set).await;
+
+    if let Some(err) = service_error {
+        return Err(err);
+    }
+
+    drain_result
+}
+
+/// Panic của service được đổi thành lỗi để supervisor biết service nào cần restart
+fn spawn_service(
+    join_set: &mut JoinSet<(usize, Result<(), BoxError>)>,
+    index: usize,
+    service: &mut Service,
+    shutdown_rx: &shutdown::ShutdownReceiver,
+    delay: Duration,
+) {
+    let run = (service.start)(shutdown_rx.clone());
+    let stopped = shutdown::wait(shutdown_rx.clone());
+    let name = service.name;
+    join_set.spawn(async move {
+        if !delay.is_zero() {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = stopped => return (index, Ok(())),
+            }
+        }
+        let result = match AssertUnwindSafe(run).catch_unwind().await {
+            Ok(result) => result,
+            Err(_) => Err(format!("service {name} panicked").into()),
+        };
+        (index, result)
+    });
+}
+
+async fn drain_join_set(join_set: &mut JoinSet<(usize, Result<(), BoxError>)>) -> Result<(), BoxError> {
+    let mut first_err: Option<BoxError> = None;
+
+    while let Some(task) = join_set.join_next().await {
+        match task {
+            Ok((_, Ok(()))) => {}
+            Ok((_, Err(err))) => {
+                if first_err.is_none() {
+                    first_err = Some(err);
+                }
+            }
+            Err(join_err) => {
+                if first_err.is_none() {
+                    first_err = Some(Box::new(join_err) as BoxError);
+                }
+            }
+        }
+    }
+
+    if let Some(err) = first_err {
+        return Err(err);
+    }
+
+    Ok(())
+}
@@ -16,6 +16,18 @@ async fn orchestrator_propagates_failure() -> Result<(), server::BoxError> {
             .map_err(|err| Box::new(err) as server::BoxError)?,
         worker_endpoint: "http://127.0.0.1:50051".to_string(),
         ready_tx: Some(gateway_ready_tx),
+        allowed_origins: gateway::cors::AllowedOrigins::any(),
+        quic_bind_addr: None,
+        rate_limit_per_second: 0,
+        stun_servers: Vec::new(),
+        admin_token: None,
+        ws_ping_interval: None,
+        worker_token: None,
+        worker_tls: None,
+        room_manager_url: None,
+        internal_secret: None,
+        reload_rx: None,
+        drain_rx: None,
     };
 
     let worker_config = WorkerConfig {
@@ -26,19 +38,40 @@ async fn orchestrator_propagates_failure() -> Result<(), server::BoxError> {
             .parse()
             .map_err(|err| Box::new(err) as server::BoxError)?,
         fail_fast: true,
+        pocketbase_url: None,
+        keyframe_interval_ticks: worker::simulation::DEFAULT_KEYFRAME_INTERVAL_TICKS,
+        room_manager_url: None,
+        leaderboard_url: None,
+        internal_secret: None,
+        rpc_token: None,
+        rpc_tls: None,
+        ready_tx: None,
+        rpc_ready_tx: None,
+        drain_rx: None,
+        drained_tx: None,
     };
 
     let room_manager_config = RoomManagerConfig {
         metrics_addr: "127.0.0.1:0"
             .parse()
             .map_err(|err| Box::new(err) as server::BoxError)?,
+        capacity_overrides: Default::default(),
         ready_tx: None,
+        pocketbase_url: None,
+        internal_secret: None,
+        reload_rx: None,
     };
 
     let config = server::ServerConfig {
         gateway: gateway_config,
         worker: worker_config,
         room_manager: room_manager_config,
+        admin_addr: None,
+        admin_ready_tx: None,
+        restart_policy: None,
+        log_level: None,
+        config_watch: None,
+        drain_timeout: None,
     };
 
     let (_shutdown_tx, shutdown_rx) = shutdown::channel();
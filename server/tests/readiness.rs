@@ -0,0 +1,63 @@
+use std::net::SocketAddr;
+
+use axum::{http::StatusCode as HttpStatus, routing::get, Router};
+use common_net::health::{HEALTHZ_PATH, READYZ_PATH};
+use reqwest::StatusCode;
+use server::admin::{ReadyState, Subsystem, READY_PATH};
+
+/// Service giả trả 200 cho cả `/healthz` và `/readyz`
+fn spawn_healthy_service() -> SocketAddr {
+    let app = Router::new()
+        .route(HEALTHZ_PATH, get(|| async { HttpStatus::OK }))
+        .route(READYZ_PATH, get(|| async { HttpStatus::OK }));
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(axum::Server::from_tcp(listener).expect("server").serve(app.into_make_service()));
+    addr
+}
+
+async fn get_ready(admin_addr: SocketAddr) -> (StatusCode, serde_json::Value) {
+    let resp = reqwest::get(format!("http://{admin_addr}{READY_PATH}")).await.expect("ready");
+    let status = resp.status();
+    (status, resp.json().await.expect("ready body"))
+}
+
+#[tokio::test]
+async fn ready_is_unavailable_until_every_service_signals_ready() {
+    let state = ReadyState::default();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind admin");
+    let admin_addr = listener.local_addr().expect("admin addr");
+    let _admin = state.clone().serve(listener).expect("serve admin");
+
+    let (status, body) = get_ready(admin_addr).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["ready"], false);
+    for service in ["gateway", "worker", "room_manager"] {
+        assert_eq!(body["services"][service], "starting", "{body}");
+    }
+
+    let (forward_tx, forward_rx) = tokio::sync::oneshot::channel();
+    let gateway_tx = state.intercept(Subsystem::Gateway, Some(forward_tx));
+    let gateway_addr = spawn_healthy_service();
+    gateway_tx.send(gateway_addr).expect("signal gateway");
+    assert_eq!(forward_rx.await.expect("forwarded addr"), gateway_addr);
+
+    state.mark_bound(Subsystem::Worker, spawn_healthy_service()).await;
+    let (status, body) = get_ready(admin_addr).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["services"]["gateway"], "ok", "{body}");
+    assert_eq!(body["services"]["worker"], "ok", "{body}");
+    assert_eq!(body["services"]["room_manager"], "starting", "{body}");
+
+    // Đã bind nhưng health check không qua thì vẫn 503
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").expect("bind closed").local_addr().expect("closed addr");
+    state.mark_bound(Subsystem::RoomManager, closed).await;
+    let (status, body) = get_ready(admin_addr).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["services"]["room_manager"], "down", "{body}");
+
+    state.mark_bound(Subsystem::RoomManager, spawn_healthy_service()).await;
+    let (status, body) = get_ready(admin_addr).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["ready"], true);
+}
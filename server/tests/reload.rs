@@ -0,0 +1,215 @@
+use std::{net::SocketAddr, path::Path, time::Duration};
+
+use common_net::{shutdown, telemetry};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use server::{
+    reload::{ConfigWatch, ReloadReport, ReloadResult},
+    ServerSettings,
+};
+use tokio::sync::{mpsc, oneshot};
+
+fn settings_json(gateway_bind: &str, rate_limit_per_second: u32) -> Value {
+    json!({
+        "gateway": {
+            "bind_addr": gateway_bind,
+            "worker_endpoint": "http://127.0.0.1:50051",
+            "rate_limit_per_second": rate_limit_per_second
+        },
+        "worker": { "metrics_addr": "127.0.0.1:0", "rpc_addr": "127.0.0.1:0", "fail_fast": false },
+        "room_manager": { "metrics_addr": "127.0.0.1:0" },
+        "admin_addr": null
+    })
+}
+
+fn write_config(path: &Path, contents: &str) {
+    std::fs::write(path, contents).expect("write config");
+}
+
+/// Số response 429 trong một loạt request liên tiếp tới `/version`
+async fn rate_limited_in_burst(client: &reqwest::Client, gateway_addr: SocketAddr, burst: usize) -> usize {
+    let mut limited = 0;
+    for _ in 0..burst {
+        let response = client
+            .get(format!("http://{gateway_addr}{}", gateway::VERSION_PATH))
+            .send()
+            .await
+            .expect("gateway reachable");
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            limited += 1;
+        }
+    }
+    limited
+}
+
+/// Chờ tới báo cáo reload thỏa `done`; file có thể bị đọc lúc đang ghi dở nên bỏ qua các báo cáo trước đó
+async fn wait_for_report(
+    reports: &mut mpsc::UnboundedReceiver<ReloadReport>,
+    done: impl Fn(&ReloadReport) -> bool,
+) -> ReloadReport {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let report = reports.recv().await.expect("config watcher running");
+            if done(&report) {
+                return report;
+            }
+        }
+    })
+    .await
+    .expect("reload report")
+}
+
+#[tokio::test]
+async fn edited_config_file_changes_rate_limit_live_and_bad_edits_are_ignored() -> Result<(), server::BoxError> {
+    telemetry::init("server-reload-test");
+
+    let dir = std::env::temp_dir().join(format!("gamev1-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("server.json");
+    write_config(&path, &settings_json("127.0.0.1:0", 0).to_string());
+
+    let settings = ServerSettings::from_file(&path)?;
+    let (reports_tx, mut reports) = mpsc::unbounded_channel();
+    let config_watch = ConfigWatch {
+        interval: Duration::from_millis(50),
+        reports_tx: Some(reports_tx),
+        ..ConfigWatch::new(path.clone(), settings.clone())
+    };
+    let mut config = settings.into_config();
+    config.config_watch = Some(config_watch);
+    let (gateway_ready_tx, gateway_ready_rx) = oneshot::channel();
+    config.gateway.ready_tx = Some(gateway_ready_tx);
+
+    let (shutdown_tx, shutdown_rx) = shutdown::channel();
+    let server = tokio::spawn(server::run_with_shutdown(config, shutdown_rx));
+    let gateway_addr = tokio::time::timeout(Duration::from_secs(5), gateway_ready_rx).await??;
+    let client = reqwest::Client::new();
+
+    assert_eq!(rate_limited_in_burst(&client, gateway_addr, 10).await, 0);
+
+    write_config(&path, &settings_json("127.0.0.1:0", 2).to_string());
+    wait_for_report(&mut reports, |report| report.changed == ["gateway.rate_limit_per_second"]).await;
+    let limited = rate_limited_in_burst(&client, gateway_addr, 10).await;
+    assert!(limited >= 7, "new rate limit of 2/s applied live, got {limited} limited");
+
+    // Đổi bind address cần restart nên giữ địa chỉ đang chạy: không có field nào được áp dụng
+    write_config(&path, &settings_json("127.0.0.1:1", 2).to_string());
+    let report = wait_for_report(&mut reports, |report| report.requires_restart == ["gateway.bind_addr"]).await;
+    assert_eq!(report.result, ReloadResult::Unchanged);
+    assert!(rate_limited_in_burst(&client, gateway_addr, 10).await >= 7);
+
+    // JSON hỏng không parse được: limit 2/s giữ nguyên
+    write_config(&path, "{ not json");
+    wait_for_report(&mut reports, |report| report.result == ReloadResult::Rejected).await;
+    assert!(rate_limited_in_burst(&client, gateway_addr, 10).await >= 7);
+    assert!(!server.is_finished(), "rejected config must not stop the server");
+
+    shutdown::trigger(&shutdown_tx);
+    tokio::time::timeout(Duration::from_secs(5), server).await??.expect("clean shutdown");
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_reload_applies_the_new_rate_limit_on_the_next_request() -> Result<(), server::BoxError> {
+    telemetry::init("server-reload-test");
+
+    let dir = std::env::temp_dir().join(format!("gamev1-admin-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("server.json");
+    let mut json = settings_json("127.0.0.1:0", 0);
+    json["gateway"]["admin_token"] = json!("ops-secret");
+    write_config(&path, &json.to_string());
+
+    // Chu kỳ poll dài: chỉ trigger qua admin endpoint mới nạp được file mới trong test
+    let settings = ServerSettings::from_file(&path)?;
+    let config_watch =
+        ConfigWatch { interval: Duration::from_secs(3600), ..ConfigWatch::new(path.clone(), settings.clone()) };
+    let mut config = settings.into_config();
+    config.config_watch = Some(config_watch);
+    config.admin_addr = Some("127.0.0.1:0".parse()?);
+    let (admin_ready_tx, admin_ready_rx) = oneshot::channel();
+    config.admin_ready_tx = Some(admin_ready_tx);
+    let (gateway_ready_tx, gateway_ready_rx) = oneshot::channel();
+    config.gateway.ready_tx = Some(gateway_ready_tx);
+
+    let (shutdown_tx, shutdown_rx) = shutdown::channel();
+    let server = tokio::spawn(server::run_with_shutdown(config, shutdown_rx));
+    let admin_addr = tokio::time::timeout(Duration::from_secs(5), admin_ready_rx).await??;
+    let gateway_addr = tokio::time::timeout(Duration::from_secs(5), gateway_ready_rx).await??;
+    let client = reqwest::Client::new();
+    let reload_url = format!("http://{admin_addr}{}", server::admin::RELOAD_PATH);
+
+    assert_eq!(rate_limited_in_burst(&client, gateway_addr, 10).await, 0);
+
+    json["gateway"]["rate_limit_per_second"] = json!(2);
+    json["gateway"]["bind_addr"] = json!("127.0.0.1:1");
+    write_config(&path, &json.to_string());
+
+    let response = client.post(&reload_url).send().await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client.post(&reload_url).bearer_auth("ops-secret").send().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let report: Value = response.json().await?;
+    assert_eq!(report["result"], "applied");
+    assert_eq!(report["changed"], json!(["gateway.rate_limit_per_second"]));
+    assert_eq!(report["requires_restart"], json!(["gateway.bind_addr"]));
+
+    assert!(rate_limited_in_burst(&client, gateway_addr, 10).await >= 7, "new limit applies to the next requests");
+
+    let report: Value = client.post(&reload_url).bearer_auth("ops-secret").send().await?.json().await?;
+    assert_eq!(report["result"], "unchanged");
+
+    shutdown::trigger(&shutdown_tx);
+    tokio::time::timeout(Duration::from_secs(5), server).await??.expect("clean shutdown");
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn restart_only_fields_are_reported_and_invalid_changes_are_refused() {
+    let running: ServerSettings = serde_json::from_value(settings_json("127.0.0.1:3000", 0)).unwrap();
+
+    let mut next = running.clone();
+    next.gateway.rate_limit_per_second = 50;
+    next.gateway.allowed_origins = vec!["https://play.example.com".to_string()];
+    next.gateway.admin_token = Some("ops-secret".to_string());
+    next.log_level = Some("info,gateway=debug".to_string());
+    let plan = server::reload::plan_reload(&running, &next).expect("reloadable");
+    assert!(plan.requires_restart.is_empty());
+    let changed: Vec<&str> = plan.changes.iter().map(|change| change.field).collect();
+    assert_eq!(
+        changed,
+        ["gateway.allowed_origins", "gateway.rate_limit_per_second", "gateway.admin_token", "log_level"]
+    );
+    let admin_token = plan.changes.iter().find(|change| change.field == "gateway.admin_token").unwrap();
+    assert_eq!(admin_token.values, None, "secret values are never reported");
+
+    let mut next = running.clone();
+    next.gateway.bind_addr = "127.0.0.1:3001".parse().unwrap();
+    next.admin_addr = Some("127.0.0.1:3300".parse().unwrap());
+    next.gateway.rate_limit_per_second = 5;
+    let plan = server::reload::plan_reload(&running, &next).expect("partial reload");
+    assert_eq!(plan.requires_restart, ["gateway.bind_addr", "admin_addr"]);
+    assert_eq!(plan.settings.gateway.bind_addr, running.gateway.bind_addr);
+    assert_eq!(plan.settings.admin_addr, running.admin_addr);
+    assert_eq!(plan.settings.gateway.rate_limit_per_second, 5);
+
+    let mut next = running.clone();
+    next.log_level = Some("gateway[{=debug".to_string());
+    assert!(server::reload::plan_reload(&running, &next).is_err());
+
+    let mut next = running.clone();
+    next.room_manager.capacity_overrides.insert(
+        "no_such_mode".to_string(),
+        room_manager::capacity::ModeCapacity {
+            default_max_players: 4,
+            min_players_to_start: 2,
+            min_capacity: 2,
+            max_capacity: 8,
+            even_teams: false,
+        },
+    );
+    assert!(server::reload::plan_reload(&running, &next).is_err());
+}
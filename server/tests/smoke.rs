@@ -19,6 +19,18 @@ async fn orchestrator_runs_and_shuts_down_cleanly() -> Result<(), server::BoxErr
             .map_err(|err| Box::new(err) as server::BoxError)?,
         worker_endpoint: "http://127.0.0.1:50051".to_string(),
         ready_tx: Some(gateway_ready_tx),
+        allowed_origins: gateway::cors::AllowedOrigins::any(),
+        quic_bind_addr: None,
+        rate_limit_per_second: 0,
+        stun_servers: Vec::new(),
+        admin_token: None,
+        ws_ping_interval: None,
+        worker_token: None,
+        worker_tls: None,
+        room_manager_url: None,
+        internal_secret: None,
+        reload_rx: None,
+        drain_rx: None,
     };
 
     let worker_config = WorkerConfig {
@@ -29,19 +41,46 @@ async fn orchestrator_runs_and_shuts_down_cleanly() -> Result<(), server::BoxErr
             .parse()
             .map_err(|err| Box::new(err) as server::BoxError)?,
         fail_fast: false,
+        pocketbase_url: None,
+        keyframe_interval_ticks: worker::simulation::DEFAULT_KEYFRAME_INTERVAL_TICKS,
+        room_manager_url: None,
+        leaderboard_url: None,
+        internal_secret: None,
+        rpc_token: None,
+        rpc_tls: None,
+        ready_tx: None,
+        rpc_ready_tx: None,
+        drain_rx: None,
+        drained_tx: None,
     };
 
     let room_manager_config = RoomManagerConfig {
         metrics_addr: "127.0.0.1:0"
             .parse()
             .map_err(|err| Box::new(err) as server::BoxError)?,
+        capacity_overrides: Default::default(),
         ready_tx: None,
+        pocketbase_url: None,
+        internal_secret: None,
+        reload_rx: None,
     };
 
+    let (admin_ready_tx, admin_ready_rx) = oneshot::channel();
+
     let config = server::ServerConfig {
         gateway: gateway_config,
         worker: worker_config,
         room_manager: room_manager_config,
+        admin_addr: Some(
+            "127.0.0.1:0"
+                .parse()
+                .map_err(|err| Box::new(err) as server::BoxError)?,
+        ),
+        admin_ready_tx: Some(admin_ready_tx),
+        restart_policy: None,
+        log_level: None,
+        config_watch: None,
+        drain_timeout: None,
     };
 
     let (shutdown_tx, shutdown_rx) = shutdown::channel();
@@ -64,6 +103,24 @@ async fn orchestrator_runs_and_shuts_down_cleanly() -> Result<(), server::BoxErr
         .map_err(|err| Box::new(err) as server::BoxError)?;
     assert_eq!(StatusCode::OK, resp.status());
 
+    let admin_addr = admin_ready_rx
+        .await
+        .map_err(|err| Box::new(err) as server::BoxError)?;
+    let mut ready_status = StatusCode::SERVICE_UNAVAILABLE;
+    for _ in 0..50 {
+        ready_status = client
+            .get(format!("http://{admin_addr}{}", server::admin::READY_PATH))
+            .send()
+            .await
+            .map_err(|err| Box::new(err) as server::BoxError)?
+            .status();
+        if ready_status == StatusCode::OK {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(StatusCode::OK, ready_status);
+
     shutdown::trigger(&shutdown_tx);
 
     let orchestrator_result = orchestrator
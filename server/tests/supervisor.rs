@@ -0,0 +1,104 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use common_net::shutdown;
+use server::supervisor::{self, RestartPolicy, Service};
+
+fn fast_policy(max_restarts: u32) -> RestartPolicy {
+    RestartPolicy { max_restarts, initial_backoff_ms: 10, max_backoff_ms: 40 }
+}
+
+/// Service khoẻ: đếm số lần khởi động, chạy tới khi nhận shutdown
+fn steady_service(name: &'static str, starts: Arc<AtomicU32>, stopped: Arc<AtomicBool>) -> Service {
+    Service::new(name, move |shutdown_rx| {
+        starts.fetch_add(1, Ordering::SeqCst);
+        let stopped = stopped.clone();
+        async move {
+            shutdown::wait(shutdown_rx).await;
+            stopped.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    })
+}
+
+/// Lỗi ở `failures` lần khởi động đầu, sau đó chạy như service khoẻ
+fn failing_service(name: &'static str, failures: u32, starts: Arc<AtomicU32>) -> Service {
+    Service::new(name, move |shutdown_rx| {
+        let attempt = starts.fetch_add(1, Ordering::SeqCst) + 1;
+        async move {
+            if attempt <= failures {
+                return Err(format!("{name} crashed on attempt {attempt}").into());
+            }
+            shutdown::wait(shutdown_rx).await;
+            Ok(())
+        }
+    })
+}
+
+#[tokio::test]
+async fn crashed_service_is_restarted_while_others_keep_running() {
+    let flaky_starts = Arc::new(AtomicU32::new(0));
+    let steady_starts = Arc::new(AtomicU32::new(0));
+    let steady_stopped = Arc::new(AtomicBool::new(false));
+    let services = vec![
+        failing_service("test_flaky", 1, flaky_starts.clone()),
+        steady_service("test_steady", steady_starts.clone(), steady_stopped.clone()),
+    ];
+
+    let (shutdown_tx, shutdown_rx) = shutdown::channel();
+    let server = tokio::spawn(supervisor::supervise(services, Some(fast_policy(3)), shutdown_rx));
+
+    for _ in 0..100 {
+        if flaky_starts.load(Ordering::SeqCst) >= 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(flaky_starts.load(Ordering::SeqCst), 2);
+    assert_eq!(supervisor::restarts_total("test_flaky"), 1);
+    assert_eq!(steady_starts.load(Ordering::SeqCst), 1);
+    assert!(!steady_stopped.load(Ordering::SeqCst));
+    assert!(!server.is_finished());
+
+    shutdown::trigger(&shutdown_tx);
+    server.await.expect("join").expect("clean shutdown");
+    assert!(steady_stopped.load(Ordering::SeqCst));
+    assert_eq!(supervisor::restarts_total("test_steady"), 0);
+}
+
+#[tokio::test]
+async fn service_exceeding_restart_budget_shuts_the_server_down() {
+    let broken_starts = Arc::new(AtomicU32::new(0));
+    let steady_stopped = Arc::new(AtomicBool::new(false));
+    let services = vec![
+        failing_service("test_broken", u32::MAX, broken_starts.clone()),
+        steady_service("test_bystander", Arc::new(AtomicU32::new(0)), steady_stopped.clone()),
+    ];
+
+    let (_shutdown_tx, shutdown_rx) = shutdown::channel();
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        supervisor::supervise(services, Some(fast_policy(2)), shutdown_rx),
+    )
+    .await
+    .expect("server exits on its own");
+
+    let err = result.expect_err("restart budget exceeded");
+    assert!(err.to_string().contains("attempt 3"), "{err}");
+    assert_eq!(broken_starts.load(Ordering::SeqCst), 3);
+    assert_eq!(supervisor::restarts_total("test_broken"), 2);
+    assert!(steady_stopped.load(Ordering::SeqCst));
+}
+
+#[test]
+fn backoff_doubles_up_to_the_cap() {
+    let policy = RestartPolicy { max_restarts: 10, initial_backoff_ms: 100, max_backoff_ms: 1_000 };
+    let backoffs: Vec<u64> = (1..=6).map(|attempt| policy.backoff(attempt).as_millis() as u64).collect();
+    assert_eq!(backoffs, vec![100, 200, 400, 800, 1_000, 1_000]);
+    assert_eq!(policy.backoff(200), Duration::from_millis(1_000));
+}
@@ -0,0 +1,266 @@
+//! Session analytics for the product funnel (connected -> joined room -> played -> finished)
+//! The gateway posts lifecycle events in batches to `POST /api/ingest/events`; each valid event becomes one
+//! `analytics_events` row with an indexed `date`, and the rollup job counts them per event type per day
+
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::collections::{AnalyticsDailyCount, AnalyticsEvent, JobCheckpoint};
+use crate::persistence::{BatchRequest, PocketBaseStore, BATCH_MAX_REQUESTS};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub const SESSION_STARTED: &str = "session_started";
+pub const ROOM_JOINED: &str = "room_joined";
+pub const ROOM_LEFT: &str = "room_left";
+pub const TRANSPORT_FALLBACK_USED: &str = "transport_fallback_used";
+pub const DISCONNECT: &str = "disconnect";
+
+/// Every event type the ingest endpoint accepts
+pub const EVENT_TYPES: [&str; 5] = [SESSION_STARTED, ROOM_JOINED, ROOM_LEFT, TRANSPORT_FALLBACK_USED, DISCONNECT];
+
+/// Largest batch `POST /api/ingest/events` accepts
+pub const MAX_INGEST_BATCH: usize = 500;
+
+/// Checkpoint key of the rollup in `job_checkpoints`
+const ROLLUP_JOB: &str = "analytics_rollup";
+
+/// Longest player/session/room id stored; hashed ids are 64 hex chars
+const MAX_ID_LEN: usize = 128;
+
+/// Why an event of a batch was not stored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RejectedEvent {
+    pub index: usize,
+    pub error: String,
+}
+
+/// Check one event before it is stored
+pub fn validate_event(event: &AnalyticsEvent) -> Result<(), String> {
+    if !EVENT_TYPES.contains(&event.event_type.as_str()) {
+        return Err(format!("unknown event_type '{}'", event.event_type));
+    }
+    for (field, value) in [("player_id", &event.player_id), ("session_id", &event.session_id)] {
+        if value.is_empty() {
+            return Err(format!("{} is required", field));
+        }
+    }
+    for (field, value) in [
+        ("player_id", &event.player_id),
+        ("session_id", &event.session_id),
+        ("room_id", &event.room_id),
+    ] {
+        if value.len() > MAX_ID_LEN {
+            return Err(format!("{} is longer than {} characters", field, MAX_ID_LEN));
+        }
+    }
+    if matches!(event.event_type.as_str(), ROOM_JOINED | ROOM_LEFT) && event.room_id.is_empty() {
+        return Err(format!("{} needs a room_id", event.event_type));
+    }
+    if event.event_type == DISCONNECT && event.duration_ms.is_none() {
+        return Err("disconnect needs a duration_ms".to_string());
+    }
+    Ok(())
+}
+
+/// Store the valid events of a batch; returns how many were stored and which ones were rejected
+pub async fn ingest_events(
+    store: &PocketBaseStore,
+    events: Vec<AnalyticsEvent>,
+) -> Result<(usize, Vec<RejectedEvent>), BoxError> {
+    let mut rejected = Vec::new();
+    let mut requests = Vec::with_capacity(events.len());
+    for (index, mut event) in events.into_iter().enumerate() {
+        if let Err(error) = validate_event(&event) {
+            rejected.push(RejectedEvent { index, error });
+            continue;
+        }
+        event.id = String::new();
+        event.date = event.occurred_at.format("%Y-%m-%d").to_string();
+        requests.push(BatchRequest::create("analytics_events", serde_json::to_value(&event)?));
+    }
+
+    for chunk in requests.chunks(BATCH_MAX_REQUESTS) {
+        store.batch(chunk).await?;
+    }
+    Ok((requests.len(), rejected))
+}
+
+/// Outcome of an analytics rollup run
+#[derive(Debug, Default, Serialize)]
+pub struct RollupSummary {
+    pub days_processed: Vec<String>,
+    pub events_read: usize,
+}
+
+/// Count events per type for every day after the stored checkpoint up to `up_to`.
+/// Without a checkpoint the rollup starts at the day of the oldest stored event, so the first run backfills
+/// history; re-running a day overwrites its counts.
+pub async fn rollup_analytics_events(store: &PocketBaseStore, up_to: NaiveDate) -> Result<RollupSummary, BoxError> {
+    let checkpoint_filter = format!("job = '{}'", ROLLUP_JOB);
+    let checkpoint: Option<JobCheckpoint> = store.find_first("job_checkpoints", &checkpoint_filter).await?;
+    let last_completed = checkpoint.and_then(|c| NaiveDate::parse_from_str(&c.last_completed_date, "%Y-%m-%d").ok());
+
+    let first_day = match last_completed {
+        Some(last) if last < up_to => last.succ_opt().unwrap_or(up_to),
+        Some(_) => up_to,
+        None => oldest_event_day(store).await?.map_or(up_to, |oldest| oldest.min(up_to)),
+    };
+
+    let mut summary = RollupSummary::default();
+    let mut day = first_day;
+    while day <= up_to {
+        let date = day.format("%Y-%m-%d").to_string();
+        let events: Vec<AnalyticsEvent> =
+            store.list_all("analytics_events", &format!("date = '{}'", date), "occurred_at").await?;
+        for row in daily_counts(&date, &events) {
+            let key = format!("date = '{}' && event_type = '{}'", row.date, row.event_type);
+            store.upsert("analytics_daily", &key, &row).await?;
+        }
+        summary.events_read += events.len();
+        summary.days_processed.push(date.clone());
+
+        if last_completed.is_none_or(|last| day > last) {
+            let checkpoint = JobCheckpoint {
+                id: String::new(),
+                job: ROLLUP_JOB.to_string(),
+                last_completed_date: date,
+            };
+            store.upsert("job_checkpoints", &checkpoint_filter, &checkpoint).await?;
+        }
+
+        match day.succ_opt() {
+            Some(next) => day = next,
+            None => break,
+        }
+    }
+
+    tracing::info!(
+        "Rolled up analytics events for {} day(s): {} events",
+        summary.days_processed.len(),
+        summary.events_read
+    );
+    Ok(summary)
+}
+
+/// Day of the oldest stored event, None when nothing was ingested yet
+async fn oldest_event_day(store: &PocketBaseStore) -> Result<Option<NaiveDate>, BoxError> {
+    let page = store.list::<AnalyticsEvent>("analytics_events", "", "date", 1, 1).await?;
+    Ok(page.items.first().and_then(|event| NaiveDate::parse_from_str(&event.date, "%Y-%m-%d").ok()))
+}
+
+/// One count per event type seen on `date`; types with no events get no row
+pub fn daily_counts(date: &str, events: &[AnalyticsEvent]) -> Vec<AnalyticsDailyCount> {
+    let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+    for event in events {
+        *counts.entry(event.event_type.as_str()).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(event_type, count)| AnalyticsDailyCount {
+            id: String::new(),
+            date: date.to_string(),
+            event_type: event_type.to_string(),
+            count,
+        })
+        .collect()
+}
+
+/// Yesterday in UTC, the day the nightly rollup covers
+pub fn rollup_day() -> NaiveDate {
+    (Utc::now() - chrono::Duration::days(1)).date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::mock_pocketbase;
+
+    fn event(event_type: &str, player_id: &str, occurred_at: &str) -> AnalyticsEvent {
+        AnalyticsEvent {
+            id: String::new(),
+            event_type: event_type.to_string(),
+            player_id: player_id.to_string(),
+            session_id: format!("conn-{}", player_id),
+            room_id: if matches!(event_type, ROOM_JOINED | ROOM_LEFT) { "room-1".to_string() } else { String::new() },
+            transport: String::new(),
+            reason: String::new(),
+            duration_ms: (event_type == DISCONNECT).then_some(65_000),
+            occurred_at: occurred_at.parse().unwrap(),
+            date: String::new(),
+        }
+    }
+
+    #[test]
+    fn validation_rejects_unknown_types_and_missing_fields() {
+        assert!(validate_event(&event(SESSION_STARTED, "alice", "2024-05-01T09:00:00Z")).is_ok());
+        assert!(validate_event(&event("clicked_ad", "alice", "2024-05-01T09:00:00Z")).is_err());
+        assert!(validate_event(&event(SESSION_STARTED, "", "2024-05-01T09:00:00Z")).is_err());
+
+        let mut joined = event(ROOM_JOINED, "alice", "2024-05-01T09:00:00Z");
+        joined.room_id.clear();
+        assert!(validate_event(&joined).is_err());
+        let mut disconnect = event(DISCONNECT, "alice", "2024-05-01T09:00:00Z");
+        disconnect.duration_ms = None;
+        assert!(validate_event(&disconnect).is_err());
+    }
+
+    #[tokio::test]
+    async fn ingested_events_roll_up_per_type_per_day_starting_from_the_oldest_day() {
+        let (url, records) = mock_pocketbase::spawn().await;
+        let store = PocketBaseStore::new(&url);
+
+        let batch = vec![
+            event(SESSION_STARTED, "alice", "2024-05-01T09:00:00Z"),
+            event(ROOM_JOINED, "alice", "2024-05-01T09:00:05Z"),
+            event(DISCONNECT, "alice", "2024-05-01T09:01:10Z"),
+            event(SESSION_STARTED, "bob", "2024-05-01T23:59:59Z"),
+            event("bogus", "bob", "2024-05-02T00:00:00Z"),
+            event(SESSION_STARTED, "carol", "2024-05-03T10:00:00Z"),
+        ];
+        let app = crate::api::create_api_router(url.clone());
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let base = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let response: serde_json::Value = reqwest::Client::new()
+            .post(format!("{}/api/ingest/events", base))
+            .json(&serde_json::json!({ "events": batch }))
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(response["accepted"], 5);
+        assert_eq!(response["rejected"][0]["index"], 4);
+        assert_eq!(response["rejected"].as_array().unwrap().len(), 1);
+        assert_eq!(records.lock().unwrap()["analytics_events"][0]["date"], "2024-05-01");
+
+        // Chưa có checkpoint: bắt đầu từ ngày của event cũ nhất, không chỉ ngày `up_to`
+        let summary = rollup_analytics_events(&store, NaiveDate::from_ymd_opt(2024, 5, 3).unwrap()).await.unwrap();
+        assert_eq!(summary.days_processed, vec!["2024-05-01", "2024-05-02", "2024-05-03"]);
+        assert_eq!(summary.events_read, 5);
+
+        let counts = |records: &mock_pocketbase::Records| -> Vec<(String, String, u64)> {
+            let mut rows: Vec<_> = records.lock().unwrap()["analytics_daily"]
+                .iter()
+                .map(|row| {
+                    let text = |field: &str| row[field].as_str().unwrap().to_string();
+                    (text("date"), text("event_type"), row["count"].as_u64().unwrap())
+                })
+                .collect();
+            rows.sort();
+            rows
+        };
+        let expected = vec![
+            ("2024-05-01".to_string(), DISCONNECT.to_string(), 1),
+            ("2024-05-01".to_string(), ROOM_JOINED.to_string(), 1),
+            ("2024-05-01".to_string(), SESSION_STARTED.to_string(), 2),
+            ("2024-05-03".to_string(), SESSION_STARTED.to_string(), 1),
+        ];
+        assert_eq!(counts(&records), expected);
+
+        // Chạy lại cùng ngày ghi đè số đếm, không nhân đôi
+        let summary = rollup_analytics_events(&store, NaiveDate::from_ymd_opt(2024, 5, 3).unwrap()).await.unwrap();
+        assert_eq!(summary.days_processed, vec!["2024-05-03"]);
+        assert_eq!(counts(&records), expected);
+        assert_eq!(records.lock().unwrap()["job_checkpoints"][0]["last_completed_date"], "2024-05-03");
+    }
+}
@@ -15,12 +15,20 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::collections::{LeaderboardEntry, User, Match, Participant, InventoryItem, UserStats};
+use crate::analytics;
+use crate::collections::{pb_datetime, AnalyticsEvent, LeaderboardEntry, User, Match, MatchResult, Participant, InventoryItem, PlayerStats, Season, UserStats};
+use crate::persistence::{self, PocketBaseStore};
+use crate::seasons;
+
+/// Default and max page size for match history
+const MATCH_HISTORY_DEFAULT_LIMIT: u32 = 20;
+const MATCH_HISTORY_MAX_LIMIT: u32 = 100;
 
 /// API state containing database connections and caches
 #[derive(Clone)]
 pub struct ApiState {
     pub pocketbase_url: String,
+    pub store: PocketBaseStore,
     pub leaderboard_cache: Arc<RwLock<HashMap<String, Vec<LeaderboardEntry>>>>,
     pub user_cache: Arc<RwLock<HashMap<String, User>>>,
 }
@@ -42,6 +50,41 @@ pub struct UserStatsQuery {
     pub end_date: Option<String>,
 }
 
+/// Query parameters for player match history (`before` is a finished_at cursor)
+#[derive(Debug, Deserialize)]
+pub struct MatchHistoryQuery {
+    pub limit: Option<u32>,
+    pub before: Option<String>,
+}
+
+/// Query parameters for the season list
+#[derive(Debug, Deserialize)]
+pub struct SeasonsQuery {
+    pub game_mode: Option<String>,
+}
+
+/// Batch of session events posted by the gateway
+#[derive(Debug, Deserialize)]
+pub struct IngestEventsRequest {
+    pub events: Vec<AnalyticsEvent>,
+}
+
+/// Ingest API response; rejected events are reported by their index in the batch
+#[derive(Debug, Serialize)]
+pub struct IngestEventsResponse {
+    pub accepted: usize,
+    pub rejected: Vec<analytics::RejectedEvent>,
+}
+
+/// Player match history API response
+#[derive(Debug, Serialize)]
+pub struct MatchHistoryResponse {
+    pub player_id: String,
+    pub matches: Vec<MatchResult>,
+    /// Pass as `before` to fetch the next page; None on the last page
+    pub next_before: Option<String>,
+}
+
 /// Leaderboard API response
 #[derive(Debug, Serialize)]
 pub struct LeaderboardResponse {
@@ -74,6 +117,7 @@ pub struct UserStatsSummary {
 /// Create API router with all endpoints
 pub fn create_api_router(pocketbase_url: String) -> Router {
     let state = ApiState {
+        store: PocketBaseStore::new(&pocketbase_url),
         pocketbase_url,
         leaderboard_cache: Arc::new(RwLock::new(HashMap::new())),
         user_cache: Arc::new(RwLock::new(HashMap::new())),
@@ -87,6 +131,9 @@ pub fn create_api_router(pocketbase_url: String) -> Router {
         .route("/users/:user_id/inventory", get(get_user_inventory))
         .route("/matches/:match_id/results", get(get_match_results))
         .route("/seasons", get(get_seasons))
+        .route("/api/players/:player_id/matches", get(get_player_matches))
+        .route("/api/players/:player_id/stats", get(get_player_stats))
+        .route("/api/ingest/events", post(ingest_events))
         .with_state(state)
 }
 
@@ -294,13 +341,112 @@ async fn get_match_results(
     })))
 }
 
-/// Get available seasons
-async fn get_seasons() -> Json<Vec<String>> {
-    Json(vec![
-        "season_1".to_string(),
-        "season_2".to_string(),
-        "season_3".to_string(),
-    ])
+/// Get a player's finished matches, newest first
+async fn get_player_matches(
+    State(state): State<ApiState>,
+    Path(player_id): Path<String>,
+    Query(params): Query<MatchHistoryQuery>,
+) -> Result<Json<MatchHistoryResponse>, (StatusCode, Json<serde_json::Value>)> {
+    if !persistence::is_valid_record_key(&player_id) {
+        return Err(bad_request("Invalid player id"));
+    }
+
+    let limit = params.limit.unwrap_or(MATCH_HISTORY_DEFAULT_LIMIT).clamp(1, MATCH_HISTORY_MAX_LIMIT);
+    let before = match params.before.as_deref() {
+        Some(raw) => Some(raw.parse::<DateTime<Utc>>().map_err(|_| bad_request("Invalid 'before' timestamp"))?),
+        None => None,
+    };
+
+    match persistence::list_player_matches(&state.store, &player_id, limit, before).await {
+        Ok(matches) => {
+            let next_before = if matches.len() as u32 == limit {
+                matches.last().map(|m| pb_datetime::format(&m.finished_at))
+            } else {
+                None
+            };
+            Ok(Json(MatchHistoryResponse {
+                player_id,
+                matches,
+                next_before,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch match history for {}: {}", player_id, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Failed to fetch match history"
+            }))))
+        }
+    }
+}
+
+/// Get a player's aggregated totals (filled in by the nightly stats job)
+async fn get_player_stats(
+    State(state): State<ApiState>,
+    Path(player_id): Path<String>,
+) -> Result<Json<PlayerStats>, (StatusCode, Json<serde_json::Value>)> {
+    if !persistence::is_valid_record_key(&player_id) {
+        return Err(bad_request("Invalid player id"));
+    }
+
+    match persistence::get_player_stats(&state.store, &player_id).await {
+        Ok(Some(stats)) => Ok(Json(stats)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "No stats for player"
+        })))),
+        Err(e) => {
+            tracing::error!("Failed to fetch player stats for {}: {}", player_id, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Failed to fetch player stats"
+            }))))
+        }
+    }
+}
+
+/// Store a batch of session analytics events; invalid events are skipped and reported, not fatal
+async fn ingest_events(
+    State(state): State<ApiState>,
+    Json(request): Json<IngestEventsRequest>,
+) -> Result<Json<IngestEventsResponse>, (StatusCode, Json<serde_json::Value>)> {
+    if request.events.len() > analytics::MAX_INGEST_BATCH {
+        return Err(bad_request(&format!(
+            "At most {} events per batch",
+            analytics::MAX_INGEST_BATCH
+        )));
+    }
+
+    match analytics::ingest_events(&state.store, request.events).await {
+        Ok((accepted, rejected)) => Ok(Json(IngestEventsResponse { accepted, rejected })),
+        Err(e) => {
+            tracing::error!("Failed to store analytics events: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Failed to store analytics events"
+            }))))
+        }
+    }
+}
+
+fn bad_request(message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message })))
+}
+
+/// List seasons, newest first, optionally of one game mode
+async fn get_seasons(
+    State(state): State<ApiState>,
+    Query(params): Query<SeasonsQuery>,
+) -> Result<Json<Vec<Season>>, (StatusCode, Json<serde_json::Value>)> {
+    if params.game_mode.as_deref().is_some_and(|game_mode| !persistence::is_valid_record_key(game_mode)) {
+        return Err(bad_request("Invalid game mode"));
+    }
+
+    match seasons::list_seasons(&state.store, params.game_mode.as_deref()).await {
+        Ok(seasons) => Ok(Json(seasons)),
+        Err(e) => {
+            tracing::error!("Failed to list seasons: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Failed to list seasons"
+            }))))
+        }
+    }
 }
 
 /// Mock function to fetch leaderboard from database
@@ -386,6 +532,7 @@ mod tests {
     fn test_api_state_creation() {
         let state = ApiState {
             pocketbase_url: "http://localhost:8090".to_string(),
+            store: PocketBaseStore::new("http://localhost:8090"),
             leaderboard_cache: Arc::new(RwLock::new(HashMap::new())),
             user_cache: Arc::new(RwLock::new(HashMap::new())),
         };
@@ -419,4 +566,49 @@ mod tests {
             assert_eq!(entry.season, "season_1");
         }
     }
+
+    #[tokio::test]
+    async fn test_player_match_history_pagination() {
+        let (pocketbase_url, _records) = crate::persistence::mock_pocketbase::spawn().await;
+        let store = PocketBaseStore::new(&pocketbase_url);
+        for hour in 0..3 {
+            let result = MatchResult {
+                id: String::new(),
+                room_id: format!("room_{}", hour),
+                game_mode: "deathmatch".to_string(),
+                players: vec![crate::collections::MatchPlayerResult {
+                    player_id: "alice".to_string(),
+                    score: 10 * hour,
+                    placement: 1,
+                }],
+                player_ids: String::new(),
+                duration_seconds: 60,
+                finished_at: format!("2024-05-01T0{}:00:00Z", hour).parse().unwrap(),
+            };
+            persistence::mock_pocketbase::insert_match_result(&store, &result).await;
+        }
+
+        let app = create_api_router(pocketbase_url);
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let base = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let first: serde_json::Value = reqwest::get(format!("{}/api/players/alice/matches?limit=2", base))
+            .await.unwrap().json().await.unwrap();
+        let rooms: Vec<&str> = first["matches"].as_array().unwrap().iter().map(|m| m["room_id"].as_str().unwrap()).collect();
+        assert_eq!(rooms, vec!["room_2", "room_1"]);
+
+        let cursor = first["next_before"].as_str().unwrap();
+        let second: serde_json::Value = reqwest::Client::new()
+            .get(format!("{}/api/players/alice/matches", base))
+            .query(&[("limit", "2"), ("before", cursor)])
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(second["matches"][0]["room_id"], "room_0");
+        assert!(second["next_before"].is_null());
+
+        let status = reqwest::get(format!("{}/api/players/bob/stats", base)).await.unwrap().status();
+        assert_eq!(status, StatusCode::NOT_FOUND.as_u16());
+        let status = reqwest::get(format!("{}/api/players/a'b/matches", base)).await.unwrap().status();
+        assert_eq!(status, StatusCode::BAD_REQUEST.as_u16());
+    }
 }
@@ -260,6 +260,171 @@ impl Default for UserStats {
     }
 }
 
+/// Finished match written by the worker when a room ends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchResult {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub room_id: String,
+    pub game_mode: String,
+    pub players: Vec<MatchPlayerResult>,
+    /// `|p1|p2|` - lets PocketBase filter by player with `player_ids ~ '|p1|'`
+    #[serde(default)]
+    pub player_ids: String,
+    pub duration_seconds: u64,
+    #[serde(with = "pb_datetime")]
+    pub finished_at: DateTime<Utc>,
+}
+
+/// Per-player line of a match result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchPlayerResult {
+    pub player_id: String,
+    pub score: u64,
+    pub placement: u32, // 1 = winner
+}
+
+/// One player's totals for a single day, owned by the aggregation job.
+/// Recomputing a day overwrites its row, which keeps the job idempotent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerDailyStats {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub player_id: String,
+    pub date: String, // YYYY-MM-DD format
+    pub games: u64,
+    pub wins: u64,
+    pub total_score: u64,
+    pub best_score: u64,
+}
+
+/// All-time per-player totals served by `GET /api/players/:player_id/stats`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub player_id: String,
+    pub games: u64,
+    pub wins: u64,
+    pub total_score: u64,
+    pub avg_score: f64,
+    pub best_score: u64,
+    pub last_aggregated_date: String,
+}
+
+/// A player's presence in a running game; `updated` is bumped by PocketBase on every write
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSession {
+    pub id: String,
+    pub player_id: String,
+    pub status: String, // "active", "finished"
+    #[serde(with = "pb_datetime")]
+    pub updated: DateTime<Utc>,
+}
+
+/// A session lifecycle event posted by the gateway to `POST /api/ingest/events`.
+/// `date` (YYYY-MM-DD of `occurred_at`) is filled in on ingest and indexed for the daily rollup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyticsEvent {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub event_type: String,
+    /// Player id, or its salted hash when the gateway runs with `ANALYTICS_ANONYMIZE=1`
+    pub player_id: String,
+    /// Gateway connection the event belongs to, ties a session's events together
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub room_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub transport: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(with = "pb_datetime")]
+    pub occurred_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub date: String,
+}
+
+/// Number of events of one type on one day, owned by the analytics rollup job
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalyticsDailyCount {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub date: String, // YYYY-MM-DD format
+    pub event_type: String,
+    pub count: u64,
+}
+
+/// Progress marker so a long-running job can resume where it stopped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobCheckpoint {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub job: String,
+    pub last_completed_date: String,
+}
+
+/// A leaderboard season of one game mode; at most one per game mode is `active`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Season {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub game_mode: String,
+    #[serde(with = "pb_datetime")]
+    pub starts_at: DateTime<Utc>,
+    #[serde(with = "pb_datetime")]
+    pub ends_at: DateTime<Utc>,
+    pub active: bool,
+}
+
+/// Frozen standing of a closed season, written once by the rollover job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeasonResult {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub season_id: String,
+    pub game_mode: String,
+    pub rank: u32,
+    pub user_id: String,
+    pub username: String,
+    pub score: u64,
+}
+
+/// Placement reward granted to a top player when their season closes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeasonReward {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub season_id: String,
+    pub user_id: String,
+    pub rank: u32,
+    pub reward: String,
+}
+
+/// PocketBase stores dates as `2024-01-01 10:00:00.000Z`; filters compare them as strings,
+/// so records must use the same layout.
+pub mod pb_datetime {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub const FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3fZ";
+
+    pub fn format(value: &DateTime<Utc>) -> String {
+        value.format(FORMAT).to_string()
+    }
+
+    pub fn serialize<S: Serializer>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format(value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse::<DateTime<Utc>>().map_err(serde::de::Error::custom)
+    }
+}
+
 /// PocketBase collection configuration
 pub struct CollectionConfig {
     pub name: &'static str,
@@ -294,7 +459,7 @@ pub fn get_collection_configs() -> Vec<CollectionConfig> {
             name: "matches",
             schema: vec![
                 FieldConfig { name: "room_id", field_type: "text", required: true, options: None },
-                FieldConfig { name: "game_mode", field_type: "select", required: true, options: Some(serde_json::json!(["deathmatch", "team_deathmatch", "capture_the_flag"])) },
+                FieldConfig { name: "game_mode", field_type: "select", required: true, options: Some(serde_json::json!(common_net::game_modes::ids())) },
                 FieldConfig { name: "map_name", field_type: "text", required: true, options: None },
                 FieldConfig { name: "max_players", field_type: "number", required: true, options: None },
                 FieldConfig { name: "status", field_type: "select", required: true, options: Some(serde_json::json!(["waiting", "starting", "in_progress", "finished", "cancelled"])) },
@@ -342,6 +507,7 @@ pub fn get_collection_configs() -> Vec<CollectionConfig> {
                 FieldConfig { name: "last_played", field_type: "date", required: true, options: None },
                 FieldConfig { name: "tier", field_type: "select", required: true, options: Some(serde_json::json!(["bronze", "silver", "gold", "platinum", "diamond", "master"])) },
                 FieldConfig { name: "season", field_type: "text", required: true, options: None },
+                FieldConfig { name: "game_mode", field_type: "text", required: false, options: None },
             ],
         },
         CollectionConfig {
@@ -386,6 +552,88 @@ pub fn get_collection_configs() -> Vec<CollectionConfig> {
                 FieldConfig { name: "items_acquired", field_type: "number", required: false, options: None },
             ],
         },
+        CollectionConfig {
+            name: "match_results",
+            schema: vec![
+                FieldConfig { name: "room_id", field_type: "text", required: true, options: None },
+                FieldConfig { name: "game_mode", field_type: "text", required: true, options: None },
+                FieldConfig { name: "players", field_type: "json", required: true, options: None },
+                FieldConfig { name: "player_ids", field_type: "text", required: true, options: None },
+                FieldConfig { name: "duration_seconds", field_type: "number", required: true, options: None },
+                FieldConfig { name: "finished_at", field_type: "date", required: true, options: None },
+            ],
+        },
+        CollectionConfig {
+            name: "player_checkpoints",
+            schema: vec![
+                FieldConfig { name: "player_id", field_type: "text", required: true, options: None },
+                FieldConfig { name: "room_id", field_type: "text", required: true, options: None },
+                FieldConfig { name: "game_mode", field_type: "text", required: true, options: None },
+                FieldConfig { name: "score", field_type: "number", required: true, options: None },
+                FieldConfig { name: "distance", field_type: "number", required: false, options: None },
+                FieldConfig { name: "tick", field_type: "number", required: true, options: None },
+                FieldConfig { name: "saved_at", field_type: "number", required: true, options: None },
+            ],
+        },
+        CollectionConfig {
+            name: "player_daily_stats",
+            schema: vec![
+                FieldConfig { name: "player_id", field_type: "text", required: true, options: None },
+                FieldConfig { name: "date", field_type: "text", required: true, options: None },
+                FieldConfig { name: "games", field_type: "number", required: true, options: None },
+                FieldConfig { name: "wins", field_type: "number", required: true, options: None },
+                FieldConfig { name: "total_score", field_type: "number", required: true, options: None },
+                FieldConfig { name: "best_score", field_type: "number", required: true, options: None },
+            ],
+        },
+        CollectionConfig {
+            name: "player_stats",
+            schema: vec![
+                FieldConfig { name: "player_id", field_type: "text", required: true, options: None },
+                FieldConfig { name: "games", field_type: "number", required: true, options: None },
+                FieldConfig { name: "wins", field_type: "number", required: true, options: None },
+                FieldConfig { name: "total_score", field_type: "number", required: true, options: None },
+                FieldConfig { name: "avg_score", field_type: "number", required: true, options: None },
+                FieldConfig { name: "best_score", field_type: "number", required: true, options: None },
+                FieldConfig { name: "last_aggregated_date", field_type: "text", required: false, options: None },
+            ],
+        },
+        CollectionConfig {
+            name: "job_checkpoints",
+            schema: vec![
+                FieldConfig { name: "job", field_type: "text", required: true, options: None },
+                FieldConfig { name: "last_completed_date", field_type: "text", required: true, options: None },
+            ],
+        },
+        CollectionConfig {
+            name: "seasons",
+            schema: vec![
+                FieldConfig { name: "game_mode", field_type: "text", required: true, options: None },
+                FieldConfig { name: "starts_at", field_type: "date", required: true, options: None },
+                FieldConfig { name: "ends_at", field_type: "date", required: true, options: None },
+                FieldConfig { name: "active", field_type: "bool", required: false, options: None },
+            ],
+        },
+        CollectionConfig {
+            name: "season_results",
+            schema: vec![
+                FieldConfig { name: "season_id", field_type: "text", required: true, options: None },
+                FieldConfig { name: "game_mode", field_type: "text", required: true, options: None },
+                FieldConfig { name: "rank", field_type: "number", required: true, options: None },
+                FieldConfig { name: "user_id", field_type: "text", required: true, options: None },
+                FieldConfig { name: "username", field_type: "text", required: true, options: None },
+                FieldConfig { name: "score", field_type: "number", required: true, options: None },
+            ],
+        },
+        CollectionConfig {
+            name: "season_rewards",
+            schema: vec![
+                FieldConfig { name: "season_id", field_type: "text", required: true, options: None },
+                FieldConfig { name: "user_id", field_type: "text", required: true, options: None },
+                FieldConfig { name: "rank", field_type: "number", required: true, options: None },
+                FieldConfig { name: "reward", field_type: "text", required: true, options: None },
+            ],
+        },
     ]
 }
 
@@ -479,11 +727,35 @@ mod tests {
     #[test]
     fn test_collection_configs() {
         let configs = get_collection_configs();
-        assert_eq!(configs.len(), 7); // users, matches, participants, leaderboard, inventory, achievements, user_stats
+        assert_eq!(configs.len(), 15); // users, matches, participants, leaderboard, inventory, achievements, user_stats, match_results, player_checkpoints, player_daily_stats, player_stats, job_checkpoints, seasons, season_results, season_rewards
 
         let user_collection = configs.iter().find(|c| c.name == "users").unwrap();
         assert!(user_collection.schema.iter().any(|f| f.name == "email"));
         assert!(user_collection.schema.iter().any(|f| f.name == "username"));
+
+        // Startup migrations must create everything services persists
+        let migrated = pocketbase::migrations::collections();
+        for config in &configs {
+            let definition = migrated.iter().find(|c| c.name == config.name).unwrap_or_else(|| panic!("{} not migrated", config.name));
+            for field in &config.schema {
+                assert!(definition.fields.iter().any(|f| f.name == field.name), "{}.{} not migrated", config.name, field.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_match_result_uses_pocketbase_dates() {
+        let json = serde_json::json!({
+            "id": "abc",
+            "room_id": "room_1",
+            "game_mode": "deathmatch",
+            "players": [{ "player_id": "p1", "score": 10, "placement": 1 }],
+            "duration_seconds": 120,
+            "finished_at": "2024-05-01 10:00:00.000Z"
+        });
+        let result: MatchResult = serde_json::from_value(json).unwrap();
+        assert_eq!(result.players[0].score, 10);
+        assert_eq!(serde_json::to_value(&result).unwrap()["finished_at"], "2024-05-01 10:00:00.000Z");
     }
 
     #[test]
@@ -495,7 +767,7 @@ mod tests {
 
         let json = generate_pocketbase_collections_json();
         if let serde_json::Value::Array(collections) = json {
-            assert_eq!(collections.len(), 7);
+            assert_eq!(collections.len(), 15);
         } else {
             panic!("Expected array of collections");
         }
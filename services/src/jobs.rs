@@ -1,13 +1,105 @@
 /// Background job system for maintenance and cleanup tasks
 /// Handles periodic cleanup, leaderboard updates, and system maintenance
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
-use tokio::time::{interval, Duration};
+use tokio::time::{interval, Duration, Instant, MissedTickBehavior};
 
-use crate::persistence::{PersistenceState, cleanup_old_data, create_persistence_state};
+use crate::analytics::{rollup_analytics_events, rollup_day};
+use crate::collections::{pb_datetime, GameSession, JobCheckpoint, MatchResult, PlayerDailyStats, PlayerStats};
+use crate::persistence::{PersistenceState, PocketBaseStore, cleanup_old_data, CURRENT_SEASON};
+use crate::seasons::{roll_over_seasons, RolloverPolicy};
+
+/// Checkpoint key of the player stats aggregation in `job_checkpoints`
+const PLAYER_STATS_JOB: &str = "player_stats";
+
+/// An active game session not updated for this long is considered abandoned
+const STALE_SESSION_AGE: Duration = Duration::from_secs(15 * 60);
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Future returned by a scheduled job; the JSON value is a short summary for logs
+pub type JobFuture = Pin<Box<dyn Future<Output = Result<serde_json::Value, BoxError>> + Send>>;
+
+type JobFn = Arc<dyn Fn() -> JobFuture + Send + Sync>;
+
+/// A job run every `interval` by the scheduler
+struct ScheduledJob {
+    name: String,
+    interval: Duration,
+    job_fn: JobFn,
+    /// Set while a run is in flight; ticks that find it set are skipped instead of reentering
+    running: AtomicBool,
+    metrics: Mutex<ScheduledJobMetrics>,
+}
+
+/// Per-job counters of the scheduler
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScheduledJobMetrics {
+    pub interval_ms: u64,
+    pub running: bool,
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub success_count: u64,
+    pub failure_count: u64,
+    /// Ticks dropped because the previous run was still going
+    pub skipped_count: u64,
+}
+
+impl ScheduledJob {
+    fn metrics(&self) -> std::sync::MutexGuard<'_, ScheduledJobMetrics> {
+        self.metrics.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start one run unless the previous one is still in flight
+    fn trigger(self: &Arc<Self>) {
+        if self.running.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            self.metrics().skipped_count += 1;
+            tracing::debug!("Job {} still running, skipping this tick", self.name);
+            return;
+        }
+
+        let job = self.clone();
+        tokio::spawn(async move {
+            {
+                let mut metrics = job.metrics();
+                metrics.running = true;
+                metrics.last_run = Some(Utc::now());
+            }
+            let started = Instant::now();
+            let result = (job.job_fn)().await;
+            let duration_ms = started.elapsed().as_millis() as u64;
+
+            {
+                let mut metrics = job.metrics();
+                metrics.running = false;
+                metrics.last_duration_ms = Some(duration_ms);
+                match &result {
+                    Ok(_) => {
+                        metrics.success_count += 1;
+                        metrics.last_error = None;
+                    }
+                    Err(e) => {
+                        metrics.failure_count += 1;
+                        metrics.last_error = Some(e.to_string());
+                    }
+                }
+            }
+            match result {
+                Ok(summary) => tracing::info!("Job {} completed in {}ms: {}", job.name, duration_ms, summary),
+                Err(e) => tracing::error!("Job {} failed after {}ms: {}", job.name, duration_ms, e),
+            }
+            job.running.store(false, Ordering::Release);
+        });
+    }
+}
 
 /// Background job types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +117,10 @@ pub enum JobType {
     GenerateDailyStats {
         date: String, // YYYY-MM-DD
     },
+    /// Aggregate match_results into player_stats, catching up every day since the last checkpoint
+    AggregatePlayerStats {
+        date: String, // YYYY-MM-DD, last day to aggregate
+    },
     /// Process user achievements
     ProcessAchievements {
         user_id: String,
@@ -64,6 +160,7 @@ pub struct JobSystem {
     pub active_jobs: RwLock<HashMap<String, JobResult>>,
     pub job_history: RwLock<Vec<JobResult>>,
     pub max_concurrent_jobs: usize,
+    scheduled: Mutex<Vec<Arc<ScheduledJob>>>,
 }
 
 impl JobSystem {
@@ -74,77 +171,116 @@ impl JobSystem {
             active_jobs: RwLock::new(HashMap::new()),
             job_history: RwLock::new(Vec::new()),
             max_concurrent_jobs: 5,
+            scheduled: Mutex::new(Vec::new()),
         }
     }
 
-    /// Start the job scheduler
-    pub async fn start_scheduler(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        tracing::info!("Starting background job scheduler");
+    /// Register a job for `start_scheduler` to run every `interval`, first run right away.
+    /// Registering a name again replaces the earlier job; call before starting the scheduler.
+    pub fn register<F, Fut>(&self, name: &str, interval: Duration, job_fn: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<serde_json::Value, BoxError>> + Send + 'static,
+    {
+        let job = Arc::new(ScheduledJob {
+            name: name.to_string(),
+            interval,
+            job_fn: Arc::new(move || Box::pin(job_fn()) as JobFuture),
+            running: AtomicBool::new(false),
+            metrics: Mutex::new(ScheduledJobMetrics {
+                interval_ms: interval.as_millis() as u64,
+                ..Default::default()
+            }),
+        });
 
-        // Cleanup job - runs every hour
-        let persistence_state = self.persistence_state.clone();
-        tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(3600)); // Every hour
-            loop {
-                interval.tick().await;
-
-                let job = JobType::CleanupOldData {
-                    older_than_days: 30,
-                };
-
-                // Create a minimal job system for this task
-                let job_system = JobSystem::new(persistence_state.clone());
-                if let Err(e) = job_system.execute_job(job).await {
-                    tracing::error!("Cleanup job failed: {:?}", e);
-                }
+        let mut scheduled = self.scheduled.lock().unwrap_or_else(|e| e.into_inner());
+        scheduled.retain(|existing| existing.name != name);
+        scheduled.push(job);
+    }
+
+    /// Maintenance jobs every services instance runs
+    pub fn register_default_jobs(&self) {
+        let state = self.persistence_state.clone();
+        self.register("cleanup_old_data", Duration::from_secs(3600), move || {
+            run_job_type(state.clone(), JobType::CleanupOldData { older_than_days: 30 })
+        });
+
+        let store = self.persistence_state.store.clone();
+        self.register("leaderboard_recompute", Duration::from_secs(900), move || {
+            let store = store.clone();
+            async move {
+                let entries = recompute_leaderboard(&store, CURRENT_SEASON).await?;
+                Ok(serde_json::json!({ "season": CURRENT_SEASON, "entries": entries }))
             }
         });
 
-        // Leaderboard update job - runs every 15 minutes
-        let persistence_state = self.persistence_state.clone();
-        tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(900)); // Every 15 minutes
-            loop {
-                interval.tick().await;
-
-                // Update leaderboards for all game modes
-                for game_mode in ["deathmatch", "team_deathmatch", "capture_the_flag"] {
-                    let job = JobType::UpdateLeaderboard {
-                        game_mode: game_mode.to_string(),
-                        season: "season_1".to_string(),
-                    };
-
-                    // Create a minimal job system for this task
-                    let job_system = JobSystem::new(persistence_state.clone());
-                    if let Err(e) = job_system.execute_job(job).await {
-                        tracing::error!("Leaderboard update job failed for {}: {:?}", game_mode, e);
-                    }
+        // Seasons end at fixed times; closing one a few minutes late is fine
+        let store = self.persistence_state.store.clone();
+        self.register("season_rollover", Duration::from_secs(300), move || {
+            let store = store.clone();
+            async move {
+                let rollovers = roll_over_seasons(&store, Utc::now(), &RolloverPolicy::default()).await?;
+                Ok(serde_json::json!({ "rollovers": rollovers }))
+            }
+        });
 
-                    // Small delay between game modes
-                    tokio::time::sleep(Duration::from_secs(5)).await;
-                }
+        let store = self.persistence_state.store.clone();
+        self.register("stale_session_cleanup", Duration::from_secs(300), move || {
+            let store = store.clone();
+            async move {
+                let closed = cleanup_stale_sessions(&store, STALE_SESSION_AGE).await?;
+                Ok(serde_json::json!({ "sessions_closed": closed }))
             }
         });
 
-        // Daily stats generation - runs at midnight
-        let persistence_state = self.persistence_state.clone();
-        tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(86400)); // Every 24 hours
-            loop {
-                interval.tick().await;
-
-                let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
-                let job = JobType::GenerateDailyStats {
-                    date: today,
-                };
-
-                // Create a minimal job system for this task
-                let job_system = JobSystem::new(persistence_state.clone());
-                if let Err(e) = job_system.execute_job(job).await {
-                    tracing::error!("Daily stats generation failed: {:?}", e);
-                }
+        let state = self.persistence_state.clone();
+        self.register("daily_stats", Duration::from_secs(86400), move || {
+            let date = Utc::now().format("%Y-%m-%d").to_string();
+            run_job_type(state.clone(), JobType::GenerateDailyStats { date })
+        });
+
+        // Nightly, covers the day that just ended
+        let state = self.persistence_state.clone();
+        self.register("player_stats", Duration::from_secs(86400), move || {
+            let date = (Utc::now() - chrono::Duration::days(1)).format("%Y-%m-%d").to_string();
+            run_job_type(state.clone(), JobType::AggregatePlayerStats { date })
+        });
+
+        let store = self.persistence_state.store.clone();
+        self.register("analytics_rollup", Duration::from_secs(86400), move || {
+            let store = store.clone();
+            async move {
+                let summary = rollup_analytics_events(&store, rollup_day()).await?;
+                Ok(serde_json::to_value(summary)?)
             }
         });
+    }
+
+    /// Metrics of every registered job, by name
+    pub fn scheduled_job_metrics(&self) -> BTreeMap<String, ScheduledJobMetrics> {
+        self.scheduled
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|job| (job.name.clone(), job.metrics().clone()))
+            .collect()
+    }
+
+    /// Start the job scheduler: one ticker per registered job
+    pub async fn start_scheduler(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let jobs = self.scheduled.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        tracing::info!("Starting background job scheduler with {} jobs", jobs.len());
+
+        for job in jobs {
+            tokio::spawn(async move {
+                let mut ticker = interval(job.interval);
+                ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                loop {
+                    ticker.tick().await;
+                    job.trigger();
+                }
+            });
+        }
 
         Ok(())
     }
@@ -258,6 +394,11 @@ impl JobSystem {
                     "stats_records_created": 500
                 }))
             }
+            JobType::AggregatePlayerStats { date } => {
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+                let summary = aggregate_player_stats(&self.persistence_state.store, date).await?;
+                Ok(serde_json::to_value(summary)?)
+            }
             JobType::ProcessAchievements { user_id } => {
                 // Mock achievement processing
                 tracing::info!("Processing achievements for user {}", user_id);
@@ -311,6 +452,7 @@ impl JobSystem {
         };
 
         JobStats {
+            scheduled: self.scheduled_job_metrics(),
             active_jobs: active_jobs.len(),
             total_jobs_today: history.iter()
                 .filter(|j| j.started_at.date_naive() == Utc::now().date_naive())
@@ -326,6 +468,7 @@ impl JobSystem {
 /// Job statistics
 #[derive(Debug, Serialize)]
 pub struct JobStats {
+    pub scheduled: BTreeMap<String, ScheduledJobMetrics>,
     pub active_jobs: usize,
     pub total_jobs_today: usize,
     pub completed_jobs: usize,
@@ -334,6 +477,191 @@ pub struct JobStats {
     pub job_type_distribution: HashMap<String, usize>,
 }
 
+/// Run a `JobType` on a throwaway job system, as a scheduled job body
+fn run_job_type(state: PersistenceState, job_type: JobType) -> JobFuture {
+    Box::pin(async move {
+        let job_system = JobSystem::new(state);
+        let result = job_system.execute_job(job_type).await?;
+        Ok(result.metadata)
+    })
+}
+
+/// Rank every player in `player_stats` by total score and write the `leaderboard` rows of `season`.
+/// Returns the number of entries written.
+pub async fn recompute_leaderboard(store: &PocketBaseStore, season: &str) -> Result<usize, BoxError> {
+    let mut players: Vec<PlayerStats> = store.list_all("player_stats", "", "player_id").await?;
+    // Ties keep player_id order so ranks don't shuffle between runs
+    players.sort_by_key(|stats| std::cmp::Reverse(stats.total_score));
+
+    for (index, stats) in players.iter().enumerate() {
+        let entry = serde_json::json!({
+            "user_id": stats.player_id,
+            "username": stats.player_id,
+            "rank": index + 1,
+            "score": stats.total_score,
+            "games_played": stats.games,
+            "win_rate": if stats.games > 0 { stats.wins as f64 / stats.games as f64 } else { 0.0 },
+            "avg_score": stats.avg_score,
+            "best_score": stats.best_score,
+            "season": season,
+        });
+        let key = format!("user_id = '{}' && season = '{}'", stats.player_id, season);
+        store.upsert("leaderboard", &key, &entry).await?;
+    }
+
+    Ok(players.len())
+}
+
+/// Mark `active` game sessions not updated for `max_age` as `finished`; returns how many were closed
+pub async fn cleanup_stale_sessions(store: &PocketBaseStore, max_age: Duration) -> Result<usize, BoxError> {
+    let cutoff = Utc::now() - chrono::Duration::from_std(max_age)?;
+    let filter = format!("status = 'active' && updated < '{}'", pb_datetime::format(&cutoff));
+    let stale: Vec<GameSession> = store.list_all("game_sessions", &filter, "updated").await?;
+
+    for session in &stale {
+        store.update("game_sessions", &session.id, &serde_json::json!({ "status": "finished" })).await?;
+    }
+
+    Ok(stale.len())
+}
+
+/// Outcome of a player stats aggregation run
+#[derive(Debug, Default, Serialize)]
+pub struct AggregationSummary {
+    pub days_processed: Vec<String>,
+    pub matches_read: usize,
+    pub players_updated: usize,
+}
+
+/// Aggregate every day after the stored checkpoint up to `up_to`.
+/// Without a checkpoint the run starts at the day of the oldest stored match, so the first run backfills
+/// history. The checkpoint advances after each finished day, so a crashed run resumes at the first
+/// unfinished day; asking for a day at or before the checkpoint just recomputes that day.
+pub async fn aggregate_player_stats(
+    store: &PocketBaseStore,
+    up_to: NaiveDate,
+) -> Result<AggregationSummary, Box<dyn std::error::Error + Send + Sync>> {
+    let checkpoint_filter = format!("job = '{}'", PLAYER_STATS_JOB);
+    let checkpoint: Option<JobCheckpoint> = store.find_first("job_checkpoints", &checkpoint_filter).await?;
+    let last_completed = checkpoint
+        .and_then(|c| NaiveDate::parse_from_str(&c.last_completed_date, "%Y-%m-%d").ok());
+
+    let first_day = match last_completed {
+        Some(last) if last < up_to => last.succ_opt().unwrap_or(up_to),
+        Some(_) => up_to,
+        None => oldest_match_day(store).await?.map_or(up_to, |oldest| oldest.min(up_to)),
+    };
+
+    let mut summary = AggregationSummary::default();
+    let mut day = first_day;
+    while day <= up_to {
+        let (matches_read, players_updated) = aggregate_player_stats_day(store, day).await?;
+        summary.matches_read += matches_read;
+        summary.players_updated += players_updated;
+        summary.days_processed.push(day.format("%Y-%m-%d").to_string());
+
+        if last_completed.is_none_or(|last| day > last) {
+            let checkpoint = JobCheckpoint {
+                id: String::new(),
+                job: PLAYER_STATS_JOB.to_string(),
+                last_completed_date: day.format("%Y-%m-%d").to_string(),
+            };
+            store.upsert("job_checkpoints", &checkpoint_filter, &checkpoint).await?;
+        }
+
+        match day.succ_opt() {
+            Some(next) => day = next,
+            None => break,
+        }
+    }
+
+    tracing::info!(
+        "Aggregated player stats for {} day(s): {} matches, {} player updates",
+        summary.days_processed.len(),
+        summary.matches_read,
+        summary.players_updated
+    );
+    Ok(summary)
+}
+
+/// Day of the oldest stored match, None when no match finished yet
+async fn oldest_match_day(store: &PocketBaseStore) -> Result<Option<NaiveDate>, BoxError> {
+    let page = store.list::<MatchResult>("match_results", "", "finished_at", 1, 1).await?;
+    Ok(page.items.first().map(|result| result.finished_at.date_naive()))
+}
+
+/// Rebuild one day's per-player rows, then refresh the all-time totals of everyone who played.
+/// Returns (matches read, players updated).
+async fn aggregate_player_stats_day(
+    store: &PocketBaseStore,
+    day: NaiveDate,
+) -> Result<(usize, usize), Box<dyn std::error::Error + Send + Sync>> {
+    let start = day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let end = start + chrono::Duration::days(1);
+    let filter = format!(
+        "finished_at >= '{}' && finished_at < '{}'",
+        pb_datetime::format(&start),
+        pb_datetime::format(&end)
+    );
+    let matches: Vec<MatchResult> = store.list_all("match_results", &filter, "finished_at").await?;
+
+    let daily = daily_stats_from_matches(&day.format("%Y-%m-%d").to_string(), &matches);
+    for row in &daily {
+        let key = format!("player_id = '{}' && date = '{}'", row.player_id, row.date);
+        store.upsert("player_daily_stats", &key, row).await?;
+    }
+
+    for row in &daily {
+        let days: Vec<PlayerDailyStats> = store
+            .list_all("player_daily_stats", &format!("player_id = '{}'", row.player_id), "date")
+            .await?;
+        let totals = totals_from_daily(&row.player_id, &days);
+        store.upsert("player_stats", &format!("player_id = '{}'", row.player_id), &totals).await?;
+    }
+
+    Ok((matches.len(), daily.len()))
+}
+
+/// Per-player totals for one day of matches
+pub fn daily_stats_from_matches(date: &str, matches: &[MatchResult]) -> Vec<PlayerDailyStats> {
+    let mut by_player: BTreeMap<&str, PlayerDailyStats> = BTreeMap::new();
+    for result in matches {
+        for player in &result.players {
+            let row = by_player.entry(&player.player_id).or_insert_with(|| PlayerDailyStats {
+                player_id: player.player_id.clone(),
+                date: date.to_string(),
+                ..Default::default()
+            });
+            row.games += 1;
+            row.wins += u64::from(player.placement == 1);
+            row.total_score += player.score;
+            row.best_score = row.best_score.max(player.score);
+        }
+    }
+    by_player.into_values().collect()
+}
+
+/// Fold a player's daily rows into all-time totals
+pub fn totals_from_daily(player_id: &str, days: &[PlayerDailyStats]) -> PlayerStats {
+    let mut stats = PlayerStats {
+        player_id: player_id.to_string(),
+        ..Default::default()
+    };
+    for day in days {
+        stats.games += day.games;
+        stats.wins += day.wins;
+        stats.total_score += day.total_score;
+        stats.best_score = stats.best_score.max(day.best_score);
+        if day.date > stats.last_aggregated_date {
+            stats.last_aggregated_date = day.date.clone();
+        }
+    }
+    if stats.games > 0 {
+        stats.avg_score = stats.total_score as f64 / stats.games as f64;
+    }
+    stats
+}
+
 /// Manual job execution for API endpoints
 pub async fn execute_manual_job(
     job_system: &JobSystem,
@@ -367,6 +695,7 @@ pub async fn get_job_history(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::persistence::create_persistence_state;
 
     #[test]
     fn test_job_type_creation() {
@@ -400,6 +729,172 @@ mod tests {
         assert_eq!(stats.total_jobs_today, 0);
     }
 
+    fn synthetic_match(room_id: &str, finished_at: &str, players: &[(&str, u64, u32)]) -> MatchResult {
+        MatchResult {
+            id: String::new(),
+            room_id: room_id.to_string(),
+            game_mode: "deathmatch".to_string(),
+            players: players
+                .iter()
+                .map(|&(player_id, score, placement)| crate::collections::MatchPlayerResult {
+                    player_id: player_id.to_string(),
+                    score,
+                    placement,
+                })
+                .collect(),
+            player_ids: String::new(),
+            duration_seconds: 300,
+            finished_at: finished_at.parse().unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_player_stats_aggregation_is_idempotent_and_resumable() {
+        let (url, records) = crate::persistence::mock_pocketbase::spawn().await;
+        let store = PocketBaseStore::new(&url);
+
+        for result in [
+            synthetic_match("r1", "2024-05-01T09:00:00Z", &[("alice", 100, 1), ("bob", 40, 2)]),
+            synthetic_match("r2", "2024-05-01T23:59:59Z", &[("alice", 20, 2), ("bob", 60, 1)]),
+            // Ngày khác - không được tính vào 2024-05-01
+            synthetic_match("r3", "2024-05-02T00:00:00Z", &[("alice", 300, 1)]),
+        ] {
+            crate::persistence::mock_pocketbase::insert_match_result(&store, &result).await;
+        }
+
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let summary = aggregate_player_stats(&store, day).await.unwrap();
+        assert_eq!(summary.days_processed, vec!["2024-05-01"]);
+        assert_eq!(summary.matches_read, 2);
+
+        let alice = crate::persistence::get_player_stats(&store, "alice").await.unwrap().unwrap();
+        assert_eq!((alice.games, alice.wins, alice.total_score, alice.best_score), (2, 1, 120, 100));
+        assert_eq!(alice.avg_score, 60.0);
+        let bob = crate::persistence::get_player_stats(&store, "bob").await.unwrap().unwrap();
+        assert_eq!((bob.games, bob.wins, bob.best_score), (2, 1, 60));
+
+        // Chạy lại cùng ngày không được cộng dồn
+        aggregate_player_stats(&store, day).await.unwrap();
+        let again = crate::persistence::get_player_stats(&store, "alice").await.unwrap().unwrap();
+        assert_eq!(again, alice);
+        assert_eq!(records.lock().unwrap()["player_stats"].len(), 2);
+
+        // Checkpoint đang ở 2024-05-01: chạy tới 2024-05-03 sẽ tiếp tục từ 2024-05-02
+        let summary = aggregate_player_stats(&store, NaiveDate::from_ymd_opt(2024, 5, 3).unwrap()).await.unwrap();
+        assert_eq!(summary.days_processed, vec!["2024-05-02", "2024-05-03"]);
+
+        let alice = crate::persistence::get_player_stats(&store, "alice").await.unwrap().unwrap();
+        assert_eq!((alice.games, alice.wins, alice.total_score, alice.best_score), (3, 2, 420, 300));
+        assert_eq!(alice.last_aggregated_date, "2024-05-02");
+
+        let checkpoint = &records.lock().unwrap()["job_checkpoints"];
+        assert_eq!(checkpoint.len(), 1);
+        assert_eq!(checkpoint[0]["last_completed_date"], "2024-05-03");
+    }
+
+    #[tokio::test]
+    async fn first_aggregation_backfills_from_the_oldest_match() {
+        let (url, records) = crate::persistence::mock_pocketbase::spawn().await;
+        let store = PocketBaseStore::new(&url);
+        for result in [
+            synthetic_match("r1", "2024-05-01T09:00:00Z", &[("alice", 100, 1)]),
+            synthetic_match("r2", "2024-05-02T09:00:00Z", &[("alice", 50, 1)]),
+        ] {
+            crate::persistence::mock_pocketbase::insert_match_result(&store, &result).await;
+        }
+
+        // Chưa có checkpoint: bắt đầu từ ngày của match cũ nhất, không chỉ ngày `up_to`
+        let summary = aggregate_player_stats(&store, NaiveDate::from_ymd_opt(2024, 5, 3).unwrap()).await.unwrap();
+        assert_eq!(summary.days_processed, vec!["2024-05-01", "2024-05-02", "2024-05-03"]);
+        assert_eq!(summary.matches_read, 2);
+
+        let alice = crate::persistence::get_player_stats(&store, "alice").await.unwrap().unwrap();
+        assert_eq!((alice.games, alice.total_score), (2, 150));
+        assert_eq!(records.lock().unwrap()["job_checkpoints"][0]["last_completed_date"], "2024-05-03");
+    }
+
+    #[tokio::test]
+    async fn registered_job_fires_once_per_interval() {
+        let job_system = JobSystem::new(create_persistence_state("http://localhost:8090".to_string()));
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = runs.clone();
+        job_system.register("fast", Duration::from_millis(100), move || {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(serde_json::json!({}))
+            }
+        });
+
+        job_system.start_scheduler().await.unwrap();
+        // Tick ngay lúc start rồi mỗi 100ms: 0, 100, 200, 300, 400
+        tokio::time::sleep(Duration::from_millis(450)).await;
+
+        assert_eq!(runs.load(Ordering::SeqCst), 5);
+        let metrics = &job_system.scheduled_job_metrics()["fast"];
+        assert_eq!((metrics.success_count, metrics.failure_count, metrics.skipped_count), (5, 0, 0));
+        assert!(metrics.last_run.is_some());
+        assert_eq!(metrics.interval_ms, 100);
+    }
+
+    #[tokio::test]
+    async fn long_running_job_is_not_reentered() {
+        let job_system = JobSystem::new(create_persistence_state("http://localhost:8090".to_string()));
+        let in_flight = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let max_in_flight = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (current, max) = (in_flight.clone(), max_in_flight.clone());
+        job_system.register("slow", Duration::from_millis(50), move || {
+            let (current, max) = (current.clone(), max.clone());
+            async move {
+                let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                max.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(220)).await;
+                current.fetch_sub(1, Ordering::SeqCst);
+                Err("slow job gave up".into())
+            }
+        });
+
+        job_system.start_scheduler().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(420)).await;
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 1);
+        let metrics = &job_system.scheduled_job_metrics()["slow"];
+        assert_eq!(metrics.failure_count, 1);
+        assert!(metrics.running, "second run starts once the first one finished");
+        assert!(metrics.skipped_count >= 4, "{metrics:?}");
+        assert_eq!(metrics.last_error.as_deref(), Some("slow job gave up"));
+    }
+
+    #[tokio::test]
+    async fn leaderboard_recompute_ranks_players_by_total_score() {
+        let (url, records) = crate::persistence::mock_pocketbase::spawn().await;
+        let store = PocketBaseStore::new(&url);
+        for (player_id, total_score, games, wins) in [("alice", 120, 2, 1), ("bob", 300, 3, 3), ("carol", 80, 4, 0)] {
+            let stats = PlayerStats {
+                player_id: player_id.to_string(),
+                games,
+                wins,
+                total_score,
+                ..Default::default()
+            };
+            store.create("player_stats", &stats).await.unwrap();
+        }
+
+        assert_eq!(recompute_leaderboard(&store, "season_1").await.unwrap(), 3);
+        // Chạy lại chỉ cập nhật, không nhân đôi
+        assert_eq!(recompute_leaderboard(&store, "season_1").await.unwrap(), 3);
+
+        let records = records.lock().unwrap();
+        let leaderboard = &records["leaderboard"];
+        assert_eq!(leaderboard.len(), 3);
+        let ranks: Vec<(&str, u64)> = leaderboard
+            .iter()
+            .map(|entry| (entry["user_id"].as_str().unwrap(), entry["rank"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(ranks, vec![("bob", 1), ("alice", 2), ("carol", 3)]);
+        assert_eq!(leaderboard[0]["win_rate"], 1.0);
+    }
+
     #[test]
     fn test_job_result_creation() {
         let job_result = JobResult {
@@ -2,10 +2,12 @@ use common_net::telemetry;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+pub mod analytics;
 pub mod api;
 pub mod collections;
 pub mod jobs;
 pub mod persistence;
+pub mod seasons;
 
 fn main() {
     telemetry::init("services");
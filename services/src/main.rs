@@ -1,17 +1,12 @@
-use common_net::telemetry;
+use common_net::{shutdown, telemetry};
 use axum::{Router};
 use hyper::{server::conn::AddrIncoming, Server};
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 
-mod api;
-mod collections;
-mod jobs;
-mod persistence;
-
-use api::create_api_router;
-use jobs::JobSystem;
-use persistence::create_persistence_state;
+use services::api::create_api_router;
+use services::jobs::JobSystem;
+use services::persistence::create_persistence_state;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -21,6 +16,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Get configuration
     let pocketbase_url = std::env::var("POCKETBASE_URL")
         .unwrap_or_else(|_| "http://localhost:8090".to_string());
+    pocketbase::migrations::bootstrap(&pocketbase::PocketBaseClient::new(&pocketbase_url)).await?;
 
     let api_port = std::env::var("SERVICES_API_PORT")
         .unwrap_or_else(|_| "3001".to_string())
@@ -34,8 +30,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Initialize persistence state
     let persistence_state = create_persistence_state(pocketbase_url.clone());
 
+    // Leaderboard scores are written behind; the flusher drains the buffer once more on shutdown
+    let (shutdown_tx, shutdown_rx) = shutdown::channel();
+    let score_flusher = tokio::spawn(persistence_state.score_buffer.clone().run(shutdown_rx.clone()));
+    tokio::spawn(async move {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for ctrl_c: {:?}", e);
+        }
+        shutdown::trigger(&shutdown_tx);
+    });
+
     // Initialize job system
     let job_system = JobSystem::new(persistence_state.clone());
+    job_system.register_default_jobs();
 
     // Start background job scheduler
     tokio::spawn(async move {
@@ -55,7 +62,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     Server::builder(incoming)
         .serve(app.into_make_service())
+        .with_graceful_shutdown(shutdown::wait(shutdown_rx))
         .await?;
 
+    score_flusher.await?;
+
     Ok(())
 }
@@ -2,26 +2,394 @@
 /// Handles saving game results, updating leaderboards, and maintaining game history
 
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use common_net::shutdown::{self, ShutdownReceiver};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
-use tokio::sync::RwLock;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, RwLock};
+use tokio::time::{interval, Duration, MissedTickBehavior};
 use uuid::Uuid;
 
-use crate::collections::{Match, Participant, LeaderboardEntry, User, UserStats, InventoryItem};
+use crate::collections::{pb_datetime, Match, MatchResult, Participant, PlayerStats, InventoryItem};
+
+/// Page size used when a caller needs every matching record
+const LIST_ALL_PAGE_SIZE: u32 = 200;
+
+/// PocketBase rejects batches above its `batch.maxRequests` setting (50 by default)
+pub(crate) const BATCH_MAX_REQUESTS: usize = 50;
+
+/// Players looked up per `leaderboard` query when flushing, keeps the filter string short
+const FLUSH_LOOKUP_CHUNK: usize = 50;
+
+/// Season leaderboard writes and the scheduled recompute target
+pub const CURRENT_SEASON: &str = "season_1";
 
 /// Persistence service state
 pub struct PersistenceState {
     pub pocketbase_url: String,
+    pub store: PocketBaseStore,
     pub match_history: RwLock<HashMap<String, Match>>,
     pub participant_history: RwLock<HashMap<String, Vec<Participant>>>,
+    pub score_buffer: Arc<ScoreWriteBuffer>,
 }
 
 impl Clone for PersistenceState {
     fn clone(&self) -> Self {
         Self {
             pocketbase_url: self.pocketbase_url.clone(),
+            store: self.store.clone(),
             match_history: RwLock::new(HashMap::new()),
             participant_history: RwLock::new(HashMap::new()),
+            score_buffer: self.score_buffer.clone(),
+        }
+    }
+}
+
+/// Minimal PocketBase records client for the collections owned by services
+#[derive(Debug, Clone)]
+pub struct PocketBaseStore {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+/// One sub-request of `POST /api/batch`; PocketBase runs the whole batch in a single transaction
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchRequest {
+    pub method: &'static str,
+    pub url: String,
+    pub body: serde_json::Value,
+}
+
+impl BatchRequest {
+    pub fn create(collection: &str, body: serde_json::Value) -> Self {
+        Self {
+            method: "POST",
+            url: format!("/api/collections/{}/records", collection),
+            body,
+        }
+    }
+
+    pub fn update(collection: &str, id: &str, body: serde_json::Value) -> Self {
+        Self {
+            method: "PATCH",
+            url: format!("/api/collections/{}/records/{}", collection, id),
+            body,
+        }
+    }
+}
+
+/// One page of a PocketBase list response
+#[derive(Debug, Deserialize)]
+pub struct RecordPage<T> {
+    #[serde(rename = "totalPages")]
+    pub total_pages: u32,
+    pub items: Vec<T>,
+}
+
+impl PocketBaseStore {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn records_url(&self, collection: &str) -> String {
+        format!("{}/api/collections/{}/records", self.base_url, collection)
+    }
+
+    /// List one page; `filter`/`sort` use PocketBase syntax and may be empty
+    pub async fn list<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        filter: &str,
+        sort: &str,
+        page: u32,
+        per_page: u32,
+    ) -> Result<RecordPage<T>, Box<dyn std::error::Error + Send + Sync>> {
+        let page = page.to_string();
+        let per_page = per_page.to_string();
+        let mut query = vec![("page", page.as_str()), ("perPage", per_page.as_str())];
+        if !filter.is_empty() {
+            query.push(("filter", filter));
+        }
+        if !sort.is_empty() {
+            query.push(("sort", sort));
+        }
+
+        let response = self
+            .http
+            .get(self.records_url(collection))
+            .query(&query)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    /// Walk every page of a filtered list
+    pub async fn list_all<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        filter: &str,
+        sort: &str,
+    ) -> Result<Vec<T>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut items = Vec::new();
+        let mut page = 1;
+        loop {
+            let batch: RecordPage<T> = self.list(collection, filter, sort, page, LIST_ALL_PAGE_SIZE).await?;
+            items.extend(batch.items);
+            if page >= batch.total_pages {
+                return Ok(items);
+            }
+            page += 1;
+        }
+    }
+
+    pub async fn find_first<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        filter: &str,
+    ) -> Result<Option<T>, Box<dyn std::error::Error + Send + Sync>> {
+        let page: RecordPage<T> = self.list(collection, filter, "", 1, 1).await?;
+        Ok(page.items.into_iter().next())
+    }
+
+    pub async fn create<B: Serialize>(
+        &self,
+        collection: &str,
+        body: &B,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .http
+            .post(self.records_url(collection))
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn update<B: Serialize>(
+        &self,
+        collection: &str,
+        id: &str,
+        body: &B,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self
+            .http
+            .patch(format!("{}/{}", self.records_url(collection), id))
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    /// Send up to `BATCH_MAX_REQUESTS` writes in one round trip
+    pub async fn batch(&self, requests: &[BatchRequest]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if requests.is_empty() {
+            return Ok(());
+        }
+        self.http
+            .post(format!("{}/api/batch", self.base_url))
+            .json(&serde_json::json!({ "requests": requests }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Update the record matching `filter` or create it; used for keyed rows like (player_id, date)
+    pub async fn upsert<B: Serialize>(
+        &self,
+        collection: &str,
+        filter: &str,
+        body: &B,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let existing: Option<serde_json::Value> = self.find_first(collection, filter).await?;
+        match existing.as_ref().and_then(|record| record["id"].as_str()) {
+            Some(id) => self.update(collection, id, body).await?,
+            None => self.create(collection, body).await?,
+        };
+        Ok(())
+    }
+}
+
+/// Record keys (player ids, dates) are embedded in filter strings, so only allow a safe charset
+pub fn is_valid_record_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= 64
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Flush policy of `ScoreWriteBuffer`
+#[derive(Debug, Clone)]
+pub struct ScoreBufferConfig {
+    pub flush_interval: Duration,
+    /// Number of distinct players pending that triggers an early flush
+    pub max_pending: usize,
+}
+
+impl Default for ScoreBufferConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval: Duration::from_secs(5),
+            max_pending: 500,
+        }
+    }
+}
+
+/// Write-behind queue for leaderboard scores.
+///
+/// Submissions only touch memory; a flush writes each player's best pending score
+/// to `leaderboard` through the batch API, skipping players whose stored score is already higher.
+pub struct ScoreWriteBuffer {
+    store: PocketBaseStore,
+    season: String,
+    config: ScoreBufferConfig,
+    pending: Mutex<HashMap<String, u64>>,
+    buffer_full: Notify,
+    /// Serializes flushes so two of them never create the same player's row twice
+    flushing: tokio::sync::Mutex<()>,
+}
+
+/// The part of a `leaderboard` row a flush needs to decide between create, update and skip
+#[derive(Debug, Deserialize)]
+struct StoredBestScore {
+    id: String,
+    user_id: String,
+    #[serde(default)]
+    best_score: u64,
+}
+
+impl ScoreWriteBuffer {
+    pub fn new(store: PocketBaseStore, season: &str, config: ScoreBufferConfig) -> Self {
+        Self {
+            store,
+            season: season.to_string(),
+            config,
+            pending: Mutex::new(HashMap::new()),
+            buffer_full: Notify::new(),
+            flushing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn pending(&self) -> std::sync::MutexGuard<'_, HashMap<String, u64>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue a score; repeated submissions for a player keep the max
+    pub fn submit(&self, player_id: &str, score: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !is_valid_record_key(player_id) {
+            return Err(format!("invalid player id: {}", player_id).into());
+        }
+
+        let mut pending = self.pending();
+        let best = pending.entry(player_id.to_string()).or_insert(score);
+        *best = (*best).max(score);
+        if pending.len() >= self.config.max_pending {
+            self.buffer_full.notify_one();
+        }
+        Ok(())
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pending().len()
+    }
+
+    /// Write everything pending; returns how many records were created or updated.
+    /// On failure the drained scores go back into the buffer for the next flush.
+    pub async fn flush(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let _flushing = self.flushing.lock().await;
+        let drained = std::mem::take(&mut *self.pending());
+        if drained.is_empty() {
+            return Ok(0);
+        }
+
+        match self.write(&drained).await {
+            Ok(written) => Ok(written),
+            Err(err) => {
+                let mut pending = self.pending();
+                for (player_id, score) in drained {
+                    let best = pending.entry(player_id).or_insert(score);
+                    *best = (*best).max(score);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn write(&self, scores: &HashMap<String, u64>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let players: Vec<&String> = scores.keys().collect();
+        let mut stored: HashMap<String, StoredBestScore> = HashMap::new();
+        for chunk in players.chunks(FLUSH_LOOKUP_CHUNK) {
+            let any_player = chunk
+                .iter()
+                .map(|player_id| format!("user_id = '{}'", player_id))
+                .collect::<Vec<_>>()
+                .join(" || ");
+            let filter = format!("season = '{}' && ({})", self.season, any_player);
+            for record in self.store.list_all::<StoredBestScore>("leaderboard", &filter, "").await? {
+                stored.insert(record.user_id.clone(), record);
+            }
+        }
+
+        let now = pb_datetime::format(&Utc::now());
+        let requests: Vec<BatchRequest> = scores
+            .iter()
+            .filter_map(|(player_id, &score)| match stored.get(player_id) {
+                Some(record) if record.best_score >= score => None,
+                Some(record) => Some(BatchRequest::update(
+                    "leaderboard",
+                    &record.id,
+                    serde_json::json!({ "best_score": score, "last_played": now }),
+                )),
+                None => Some(BatchRequest::create(
+                    "leaderboard",
+                    serde_json::json!({
+                        "user_id": player_id,
+                        "username": player_id,
+                        "season": self.season,
+                        "best_score": score,
+                        "last_played": now,
+                    }),
+                )),
+            })
+            .collect();
+
+        for chunk in requests.chunks(BATCH_MAX_REQUESTS) {
+            self.store.batch(chunk).await?;
+        }
+        Ok(requests.len())
+    }
+
+    /// Flush every `flush_interval` or as soon as the buffer fills; flushes once more after shutdown
+    pub async fn run(self: Arc<Self>, shutdown_rx: ShutdownReceiver) {
+        let mut ticker = interval(self.config.flush_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+
+        let shutdown = shutdown::wait(shutdown_rx);
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = ticker.tick() => {}
+                _ = self.buffer_full.notified() => {}
+            }
+            if let Err(err) = self.flush().await {
+                tracing::warn!("Leaderboard score flush failed, will retry: {:?}", err);
+            }
+        }
+
+        match self.flush().await {
+            Ok(written) => tracing::info!("Flushed {} pending leaderboard scores on shutdown", written),
+            Err(err) => tracing::error!(
+                "Dropping {} pending leaderboard scores, final flush failed: {:?}",
+                self.pending_len(),
+                err
+            ),
         }
     }
 }
@@ -80,7 +448,10 @@ impl From<&Participant> for GameParticipant {
 
 /// Create persistence state
 pub fn create_persistence_state(pocketbase_url: String) -> PersistenceState {
+    let store = PocketBaseStore::new(&pocketbase_url);
     PersistenceState {
+        score_buffer: Arc::new(ScoreWriteBuffer::new(store.clone(), CURRENT_SEASON, ScoreBufferConfig::default())),
+        store,
         pocketbase_url,
         match_history: RwLock::new(HashMap::new()),
         participant_history: RwLock::new(HashMap::new()),
@@ -192,7 +563,7 @@ async fn update_leaderboards_and_stats(
     }
 
     // Update leaderboard rankings
-    update_leaderboard_rankings(state, &game_result.game_mode).await?;
+    update_leaderboard_rankings(state, game_result).await?;
 
     Ok(())
 }
@@ -219,18 +590,15 @@ async fn update_user_stats(
     Ok(())
 }
 
-/// Update leaderboard rankings for a game mode
+/// Queue participants' scores for the leaderboard; ranks are recomputed by the scheduled job
 async fn update_leaderboard_rankings(
-    _state: &PersistenceState,
-    _game_mode: &str,
+    state: &PersistenceState,
+    game_result: &GameResult,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Mock implementation - in real app would:
-    // 1. Query all users' recent performance
-    // 2. Calculate new rankings
-    // 3. Update leaderboard collection
-    // 4. Update user tiers
-
-    tracing::debug!("Updating leaderboard rankings for game mode: {}", _game_mode);
+    tracing::debug!("Queueing leaderboard scores for game mode: {}", game_result.game_mode);
+    for participant in &game_result.participants {
+        state.score_buffer.submit(&participant.user_id, participant.score)?;
+    }
     Ok(())
 }
 
@@ -267,6 +635,37 @@ pub async fn get_user_match_history(
     Ok(user_matches)
 }
 
+/// Newest-first match history for a player; `before` is an exclusive `finished_at` cursor
+pub async fn list_player_matches(
+    store: &PocketBaseStore,
+    player_id: &str,
+    limit: u32,
+    before: Option<DateTime<Utc>>,
+) -> Result<Vec<MatchResult>, Box<dyn std::error::Error + Send + Sync>> {
+    if !is_valid_record_key(player_id) {
+        return Err(format!("invalid player id: {}", player_id).into());
+    }
+
+    let mut filter = format!("player_ids ~ '|{}|'", player_id);
+    if let Some(before) = before {
+        filter.push_str(&format!(" && finished_at < '{}'", pb_datetime::format(&before)));
+    }
+
+    let page: RecordPage<MatchResult> = store.list("match_results", &filter, "-finished_at", 1, limit).await?;
+    Ok(page.items)
+}
+
+/// Aggregated totals for a player, None until the stats job has seen them
+pub async fn get_player_stats(
+    store: &PocketBaseStore,
+    player_id: &str,
+) -> Result<Option<PlayerStats>, Box<dyn std::error::Error + Send + Sync>> {
+    if !is_valid_record_key(player_id) {
+        return Err(format!("invalid player id: {}", player_id).into());
+    }
+    store.find_first("player_stats", &format!("player_id = '{}'", player_id)).await
+}
+
 /// Get user statistics summary
 pub async fn get_user_stats_summary(
     _state: &PersistenceState,
@@ -362,6 +761,166 @@ pub async fn cleanup_old_data(
     Ok(150) // Records cleaned up
 }
 
+/// In-memory stand-in for the PocketBase records API, enough for the filters services emits
+#[cfg(test)]
+pub(crate) mod mock_pocketbase {
+    use axum::{
+        extract::{Path, Query, State},
+        routing::{get, patch, post},
+        Json, Router,
+    };
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::PocketBaseStore;
+    use crate::collections::MatchResult;
+
+    pub type Records = Arc<Mutex<HashMap<String, Vec<Value>>>>;
+
+    /// Start the mock on a random port, returning its base URL and the backing records
+    pub async fn spawn() -> (String, Records) {
+        let records: Records = Arc::default();
+        let app = Router::new()
+            .route("/api/collections/:collection/records", get(list).post(create))
+            .route("/api/collections/:collection/records/:id", patch(update))
+            .route("/api/batch", post(batch))
+            .with_state(records.clone());
+
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        (url, records)
+    }
+
+    /// Store a finished match the way the worker does, including the `player_ids` lookup field
+    pub async fn insert_match_result(store: &PocketBaseStore, result: &MatchResult) {
+        let mut body = result.clone();
+        body.player_ids = result.players.iter().map(|p| format!("|{}", p.player_id)).collect::<String>() + "|";
+        store.create("match_results", &body).await.expect("insert match result");
+    }
+
+    async fn list(
+        State(records): State<Records>,
+        Path(collection): Path<String>,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> Json<Value> {
+        let mut items: Vec<Value> = records
+            .lock()
+            .unwrap()
+            .get(&collection)
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|record| params.get("filter").is_none_or(|filter| matches_filter(record, filter)))
+            .collect();
+
+        if let Some(sort) = params.get("sort") {
+            let (field, descending) = match sort.strip_prefix('-') {
+                Some(field) => (field, true),
+                None => (sort.as_str(), false),
+            };
+            items.sort_by(|a, b| {
+                let ordering = field_text(a, field).cmp(&field_text(b, field));
+                if descending { ordering.reverse() } else { ordering }
+            });
+        }
+
+        let page: usize = params.get("page").and_then(|p| p.parse().ok()).unwrap_or(1);
+        let per_page: usize = params.get("perPage").and_then(|p| p.parse().ok()).unwrap_or(30);
+        let total_pages = items.len().div_ceil(per_page).max(1);
+        let items: Vec<Value> = items.into_iter().skip((page - 1) * per_page).take(per_page).collect();
+
+        Json(json!({ "page": page, "perPage": per_page, "totalPages": total_pages, "items": items }))
+    }
+
+    async fn create(
+        State(records): State<Records>,
+        Path(collection): Path<String>,
+        Json(body): Json<Value>,
+    ) -> Json<Value> {
+        Json(insert_record(&mut records.lock().unwrap(), &collection, body))
+    }
+
+    async fn update(
+        State(records): State<Records>,
+        Path((collection, id)): Path<(String, String)>,
+        Json(body): Json<Value>,
+    ) -> Json<Value> {
+        Json(update_record(&mut records.lock().unwrap(), &collection, &id, body))
+    }
+
+    /// Applies POST/PATCH sub-requests in order, like PocketBase's `/api/batch`
+    async fn batch(State(records): State<Records>, Json(body): Json<Value>) -> Json<Value> {
+        let mut records = records.lock().unwrap();
+        let mut results = Vec::new();
+        for request in body["requests"].as_array().cloned().unwrap_or_default() {
+            let path = request["url"].as_str().unwrap_or_default().trim_start_matches("/api/collections/");
+            let segments: Vec<&str> = path.split('/').collect();
+            let record = match (request["method"].as_str(), segments.as_slice()) {
+                (Some("POST"), [collection, "records"]) => insert_record(&mut records, collection, request["body"].clone()),
+                (Some("PATCH"), [collection, "records", id]) => {
+                    update_record(&mut records, collection, id, request["body"].clone())
+                }
+                other => panic!("unsupported batch request {other:?}"),
+            };
+            results.push(json!({ "status": 200, "body": record }));
+        }
+        Json(Value::Array(results))
+    }
+
+    fn insert_record(records: &mut HashMap<String, Vec<Value>>, collection: &str, mut body: Value) -> Value {
+        body["id"] = json!(uuid::Uuid::new_v4().simple().to_string());
+        records.entry(collection.to_string()).or_default().push(body.clone());
+        body
+    }
+
+    fn update_record(records: &mut HashMap<String, Vec<Value>>, collection: &str, id: &str, body: Value) -> Value {
+        let record = records
+            .get_mut(collection)
+            .and_then(|items| items.iter_mut().find(|r| r["id"] == id))
+            .expect("record exists");
+        for (key, value) in body.as_object().cloned().unwrap_or_default() {
+            if key != "id" {
+                record[key] = value;
+            }
+        }
+        record.clone()
+    }
+
+    fn field_text(record: &Value, field: &str) -> String {
+        match &record[field] {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
+
+    /// Supports `field op 'value'` clauses joined by `&&` with op in `=`, `~`, `<`, `>=`;
+    /// a clause may be a parenthesized `||` group of such comparisons
+    fn matches_filter(record: &Value, filter: &str) -> bool {
+        filter.split(" && ").all(|clause| {
+            let group = clause.trim().trim_start_matches('(').trim_end_matches(')');
+            group.split(" || ").any(|comparison| matches_comparison(record, comparison))
+        })
+    }
+
+    fn matches_comparison(record: &Value, comparison: &str) -> bool {
+        let mut parts = comparison.trim().splitn(3, ' ');
+        let (Some(field), Some(op), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+            return false;
+        };
+        let value = value.trim_matches('\'');
+        let actual = field_text(record, field);
+        match op {
+            "=" => actual == value,
+            "~" => actual.contains(value),
+            "<" => actual.as_str() < value,
+            ">=" => actual.as_str() >= value,
+            _ => false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,4 +981,72 @@ mod tests {
         assert_eq!(summary.total_games, 100);
         assert_eq!(summary.win_rate, 0.75);
     }
+
+    fn leaderboard_rows(records: &mock_pocketbase::Records) -> Vec<serde_json::Value> {
+        records.lock().unwrap().get("leaderboard").cloned().unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn rapid_score_submissions_flush_as_one_record_with_the_max() {
+        let (url, records) = mock_pocketbase::spawn().await;
+        let config = ScoreBufferConfig {
+            flush_interval: Duration::from_secs(3600),
+            max_pending: 100,
+        };
+        let buffer = ScoreWriteBuffer::new(PocketBaseStore::new(&url), "season_1", config);
+
+        for score in [120, 900, 40, 899, 300, 900, 10] {
+            buffer.submit("player_1", score).unwrap();
+        }
+        assert_eq!(buffer.pending_len(), 1);
+        assert!(leaderboard_rows(&records).is_empty(), "submit must not write through");
+
+        assert_eq!(buffer.flush().await.unwrap(), 1);
+        let rows = leaderboard_rows(&records);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["user_id"], "player_1");
+        assert_eq!(rows[0]["season"], "season_1");
+        assert_eq!(rows[0]["best_score"], 900);
+
+        // A lower score never overwrites the stored best; a higher one updates the same row
+        buffer.submit("player_1", 500).unwrap();
+        assert_eq!(buffer.flush().await.unwrap(), 0);
+        buffer.submit("player_1", 1200).unwrap();
+        assert_eq!(buffer.flush().await.unwrap(), 1);
+        let rows = leaderboard_rows(&records);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["best_score"], 1200);
+
+        assert!(buffer.submit("bad id'", 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn score_buffer_flushes_when_full_and_on_shutdown() {
+        let (url, records) = mock_pocketbase::spawn().await;
+        let config = ScoreBufferConfig {
+            flush_interval: Duration::from_secs(3600),
+            max_pending: 2,
+        };
+        let buffer = Arc::new(ScoreWriteBuffer::new(PocketBaseStore::new(&url), "season_1", config));
+        let (shutdown_tx, shutdown_rx) = shutdown::channel();
+        let runner = tokio::spawn(buffer.clone().run(shutdown_rx));
+
+        buffer.submit("player_1", 10).unwrap();
+        buffer.submit("player_2", 20).unwrap();
+        for _ in 0..50 {
+            if leaderboard_rows(&records).len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(leaderboard_rows(&records).len(), 2, "full buffer flushes before the interval");
+
+        buffer.submit("player_3", 30).unwrap();
+        shutdown::trigger(&shutdown_tx);
+        runner.await.unwrap();
+        assert_eq!(buffer.pending_len(), 0);
+        let rows = leaderboard_rows(&records);
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().any(|row| row["user_id"] == "player_3" && row["best_score"] == 30));
+    }
 }
@@ -0,0 +1,509 @@
+//! Leaderboard seasons per game mode
+//! Score submissions land in the active season of their game mode and in the all-time table;
+//! the rollover job closes ended seasons, archives their top players and opens the next season
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::collections::{pb_datetime, Season, SeasonResult, SeasonReward};
+use crate::persistence::{is_valid_record_key, PocketBaseStore};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// `season` of the leaderboard rows spanning every season; every submission updates it
+pub const ALL_TIME_SEASON: &str = "all_time";
+
+/// Length of the next season when a closed one has no usable start/end
+const DEFAULT_SEASON_LENGTH_DAYS: i64 = 30;
+
+/// Which standings a leaderboard query asks for (`season=current|all|<id>`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeasonSelector {
+    /// Active season of the game mode, all-time when none is active
+    Current,
+    All,
+    Id(String),
+}
+
+impl SeasonSelector {
+    /// Missing or empty means `current`
+    pub fn parse(raw: Option<&str>) -> Self {
+        match raw.map(str::trim) {
+            None | Some("") | Some("current") => Self::Current,
+            Some("all") => Self::All,
+            Some(id) => Self::Id(id.to_string()),
+        }
+    }
+}
+
+/// Settings of the season rollover job
+#[derive(Debug, Clone)]
+pub struct RolloverPolicy {
+    /// Players archived into `season_results` per closed season
+    pub top_n: usize,
+    /// Grant a `season_rewards` record to every archived player
+    pub grant_rewards: bool,
+}
+
+impl Default for RolloverPolicy {
+    fn default() -> Self {
+        Self {
+            top_n: 100,
+            grant_rewards: false,
+        }
+    }
+}
+
+/// One ranked line of a leaderboard
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Standing {
+    pub rank: u32,
+    pub user_id: String,
+    pub username: String,
+    pub score: u64,
+}
+
+/// Standings of one season, or of all time when `season` is None
+#[derive(Debug, Clone, Serialize)]
+pub struct SeasonStandings {
+    pub season: Option<Season>,
+    pub standings: Vec<Standing>,
+}
+
+/// Where a submitted score landed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SubmittedScore {
+    /// None when the game mode has no active season and only all-time was updated
+    pub season_id: Option<String>,
+    /// Rank in the active season, or all-time without one
+    pub rank: u32,
+    /// Player's best score in that same table
+    pub best_score: u64,
+}
+
+/// Outcome of closing one season
+#[derive(Debug, Clone, Serialize)]
+pub struct SeasonRollover {
+    pub closed_season: String,
+    pub game_mode: String,
+    pub archived: usize,
+    pub rewards: usize,
+    pub opened_season: String,
+}
+
+/// The part of a `leaderboard` row season ranking needs
+#[derive(Debug, Deserialize)]
+struct ScoreRow {
+    id: String,
+    user_id: String,
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    best_score: u64,
+}
+
+fn check_key(key: &str) -> Result<(), BoxError> {
+    if is_valid_record_key(key) {
+        Ok(())
+    } else {
+        Err(format!("invalid record key: {}", key).into())
+    }
+}
+
+fn scope_filter(season: &str, game_mode: &str) -> String {
+    format!("season = '{}' && game_mode = '{}'", season, game_mode)
+}
+
+pub async fn active_season(store: &PocketBaseStore, game_mode: &str) -> Result<Option<Season>, BoxError> {
+    check_key(game_mode)?;
+    store
+        .find_first("seasons", &format!("game_mode = '{}' && active = true", game_mode))
+        .await
+}
+
+/// Every season, newest first; only those of `game_mode` when given
+pub async fn list_seasons(store: &PocketBaseStore, game_mode: Option<&str>) -> Result<Vec<Season>, BoxError> {
+    let filter = match game_mode {
+        Some(game_mode) => {
+            check_key(game_mode)?;
+            format!("game_mode = '{}'", game_mode)
+        }
+        None => String::new(),
+    };
+    store.list_all("seasons", &filter, "-starts_at").await
+}
+
+/// Rows of one (season, game_mode) table, best score first; ties keep user_id order
+async fn ranked_rows(store: &PocketBaseStore, season: &str, game_mode: &str) -> Result<Vec<ScoreRow>, BoxError> {
+    let mut rows: Vec<ScoreRow> = store.list_all("leaderboard", &scope_filter(season, game_mode), "user_id").await?;
+    rows.sort_by_key(|row| std::cmp::Reverse(row.best_score));
+    Ok(rows)
+}
+
+fn to_standings(rows: Vec<ScoreRow>, limit: usize) -> Vec<Standing> {
+    rows.into_iter()
+        .take(limit)
+        .enumerate()
+        .map(|(index, row)| Standing {
+            rank: index as u32 + 1,
+            user_id: row.user_id,
+            username: row.username,
+            score: row.best_score,
+        })
+        .collect()
+}
+
+/// Keep the player's best score in one (season, game_mode) table; returns the stored best
+async fn record_best(
+    store: &PocketBaseStore,
+    season: &str,
+    game_mode: &str,
+    user_id: &str,
+    username: &str,
+    score: u64,
+) -> Result<u64, BoxError> {
+    let filter = format!("{} && user_id = '{}'", scope_filter(season, game_mode), user_id);
+    let now = pb_datetime::format(&Utc::now());
+    match store.find_first::<ScoreRow>("leaderboard", &filter).await? {
+        Some(row) if row.best_score >= score => Ok(row.best_score),
+        Some(row) => {
+            let body = serde_json::json!({ "best_score": score, "score": score, "username": username, "last_played": now });
+            store.update("leaderboard", &row.id, &body).await?;
+            Ok(score)
+        }
+        None => {
+            let body = serde_json::json!({
+                "user_id": user_id,
+                "username": username,
+                "season": season,
+                "game_mode": game_mode,
+                "best_score": score,
+                "score": score,
+                "last_played": now,
+            });
+            store.create("leaderboard", &body).await?;
+            Ok(score)
+        }
+    }
+}
+
+/// Record a score in the all-time table and, if the game mode has one, its active season
+pub async fn submit_score(
+    store: &PocketBaseStore,
+    game_mode: &str,
+    user_id: &str,
+    username: &str,
+    score: u64,
+) -> Result<SubmittedScore, BoxError> {
+    check_key(game_mode)?;
+    check_key(user_id)?;
+
+    let all_time_best = record_best(store, ALL_TIME_SEASON, game_mode, user_id, username, score).await?;
+    // The score is already safe in all-time; a failed season lookup must not fail the submission
+    let season = match active_season(store, game_mode).await {
+        Ok(season) => season,
+        Err(e) => {
+            tracing::warn!("Active season lookup for {} failed, ranking on the all-time board: {}", game_mode, e);
+            None
+        }
+    };
+    let (scope, best_score) = match &season {
+        Some(season) => (
+            season.id.as_str(),
+            record_best(store, &season.id, game_mode, user_id, username, score).await?,
+        ),
+        None => (ALL_TIME_SEASON, all_time_best),
+    };
+    let rank = ranked_rows(store, scope, game_mode)
+        .await?
+        .iter()
+        .position(|row| row.user_id == user_id)
+        .map_or(0, |index| index as u32 + 1);
+
+    Ok(SubmittedScore {
+        season_id: season.map(|season| season.id),
+        rank,
+        best_score,
+    })
+}
+
+/// Top `limit` standings for a game mode. None when the season id is unknown or belongs to
+/// another game mode. Closed seasons are read from their frozen `season_results` archive.
+pub async fn standings(
+    store: &PocketBaseStore,
+    game_mode: &str,
+    selector: &SeasonSelector,
+    limit: usize,
+) -> Result<Option<SeasonStandings>, BoxError> {
+    check_key(game_mode)?;
+    let season = match selector {
+        SeasonSelector::Current => active_season(store, game_mode).await?,
+        SeasonSelector::All => None,
+        SeasonSelector::Id(id) => {
+            if !is_valid_record_key(id) {
+                return Ok(None);
+            }
+            match store.find_first::<Season>("seasons", &format!("id = '{}'", id)).await? {
+                Some(season) if season.game_mode == game_mode => Some(season),
+                _ => return Ok(None),
+            }
+        }
+    };
+
+    let standings = match &season {
+        Some(season) if !season.active => archived_standings(store, &season.id, limit).await?,
+        Some(season) => to_standings(ranked_rows(store, &season.id, game_mode).await?, limit),
+        None => to_standings(ranked_rows(store, ALL_TIME_SEASON, game_mode).await?, limit),
+    };
+    Ok(Some(SeasonStandings { season, standings }))
+}
+
+async fn archived_standings(store: &PocketBaseStore, season_id: &str, limit: usize) -> Result<Vec<Standing>, BoxError> {
+    let mut results: Vec<SeasonResult> = store
+        .list_all("season_results", &format!("season_id = '{}'", season_id), "rank")
+        .await?;
+    results.sort_by_key(|result| result.rank);
+    Ok(results
+        .into_iter()
+        .take(limit)
+        .map(|result| Standing {
+            rank: result.rank,
+            user_id: result.user_id,
+            username: result.username,
+            score: result.score,
+        })
+        .collect())
+}
+
+/// Reward granted for a final placement
+pub fn placement_reward(rank: u32) -> &'static str {
+    match rank {
+        1 => "season_champion",
+        2..=3 => "season_podium",
+        4..=10 => "season_top_10",
+        _ => "season_finisher",
+    }
+}
+
+/// Next season of the same length, starting when `closed` ended; seasons that would already be
+/// over by `now` (services was down) are skipped
+fn next_season(closed: &Season, now: DateTime<Utc>) -> Season {
+    let length = Some(closed.ends_at - closed.starts_at)
+        .filter(|length| *length > chrono::Duration::zero())
+        .unwrap_or_else(|| chrono::Duration::days(DEFAULT_SEASON_LENGTH_DAYS));
+    let mut starts_at = closed.ends_at;
+    while starts_at + length <= now {
+        starts_at += length;
+    }
+    Season {
+        id: String::new(),
+        game_mode: closed.game_mode.clone(),
+        starts_at,
+        ends_at: starts_at + length,
+        active: true,
+    }
+}
+
+/// Close every active season that ended by `now`: archive its top `policy.top_n` into
+/// `season_results`, grant rewards if enabled, open the next season, then deactivate it.
+/// Every step is an upsert, so a run interrupted midway is completed by the next one.
+pub async fn roll_over_seasons(
+    store: &PocketBaseStore,
+    now: DateTime<Utc>,
+    policy: &RolloverPolicy,
+) -> Result<Vec<SeasonRollover>, BoxError> {
+    let active: Vec<Season> = store.list_all("seasons", "active = true", "game_mode").await?;
+    let mut rollovers = Vec::new();
+
+    for season in active.into_iter().filter(|season| season.ends_at <= now) {
+        let rows = ranked_rows(store, &season.id, &season.game_mode).await?;
+        let top = to_standings(rows, policy.top_n);
+
+        for standing in &top {
+            let result = SeasonResult {
+                id: String::new(),
+                season_id: season.id.clone(),
+                game_mode: season.game_mode.clone(),
+                rank: standing.rank,
+                user_id: standing.user_id.clone(),
+                username: standing.username.clone(),
+                score: standing.score,
+            };
+            let key = format!("season_id = '{}' && user_id = '{}'", season.id, standing.user_id);
+            store.upsert("season_results", &key, &result).await?;
+        }
+
+        let mut rewards = 0;
+        if policy.grant_rewards {
+            for standing in &top {
+                let reward = SeasonReward {
+                    id: String::new(),
+                    season_id: season.id.clone(),
+                    user_id: standing.user_id.clone(),
+                    rank: standing.rank,
+                    reward: placement_reward(standing.rank).to_string(),
+                };
+                let key = format!("season_id = '{}' && user_id = '{}'", season.id, standing.user_id);
+                store.upsert("season_rewards", &key, &reward).await?;
+                rewards += 1;
+            }
+        }
+
+        let next = next_season(&season, now);
+        let next_key = format!(
+            "game_mode = '{}' && starts_at = '{}'",
+            next.game_mode,
+            pb_datetime::format(&next.starts_at)
+        );
+        store.upsert("seasons", &next_key, &next).await?;
+        let opened: Option<Season> = store.find_first("seasons", &next_key).await?;
+
+        store
+            .update("seasons", &season.id, &serde_json::json!({ "active": false }))
+            .await?;
+
+        tracing::info!(
+            "Season {} of {} closed: {} archived, {} rewards",
+            season.id,
+            season.game_mode,
+            top.len(),
+            rewards
+        );
+        rollovers.push(SeasonRollover {
+            closed_season: season.id,
+            game_mode: season.game_mode,
+            archived: top.len(),
+            rewards,
+            opened_season: opened.map(|season| season.id).unwrap_or_default(),
+        });
+    }
+
+    Ok(rollovers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::mock_pocketbase;
+
+    async fn create_season(store: &PocketBaseStore, game_mode: &str, starts_at: &str, ends_at: &str) -> String {
+        let season = Season {
+            id: String::new(),
+            game_mode: game_mode.to_string(),
+            starts_at: starts_at.parse().unwrap(),
+            ends_at: ends_at.parse().unwrap(),
+            active: true,
+        };
+        let record = store.create("seasons", &season).await.unwrap();
+        record["id"].as_str().unwrap().to_string()
+    }
+
+    fn ranking(standings: &SeasonStandings) -> Vec<(&str, u64)> {
+        standings.standings.iter().map(|s| (s.user_id.as_str(), s.score)).collect()
+    }
+
+    #[tokio::test]
+    async fn submissions_land_in_the_active_season_of_their_game_mode() {
+        let (url, records) = mock_pocketbase::spawn().await;
+        let store = PocketBaseStore::new(&url);
+        let season_id = create_season(&store, "endless_runner", "2024-05-01T00:00:00Z", "2024-06-01T00:00:00Z").await;
+
+        let submitted = submit_score(&store, "endless_runner", "alice", "Alice", 500).await.unwrap();
+        assert_eq!(submitted, SubmittedScore { season_id: Some(season_id.clone()), rank: 1, best_score: 500 });
+        let submitted = submit_score(&store, "endless_runner", "bob", "Bob", 900).await.unwrap();
+        assert_eq!(submitted.rank, 1);
+        // Điểm thấp hơn không ghi đè best
+        let submitted = submit_score(&store, "endless_runner", "alice", "Alice", 100).await.unwrap();
+        assert_eq!((submitted.rank, submitted.best_score), (2, 500));
+
+        // Game mode chưa có season thì chỉ vào all-time
+        let submitted = submit_score(&store, "deathmatch", "alice", "Alice", 30).await.unwrap();
+        assert_eq!(submitted, SubmittedScore { season_id: None, rank: 1, best_score: 30 });
+
+        let rows = records.lock().unwrap()["leaderboard"].clone();
+        let in_season = rows.iter().filter(|row| row["season"] == season_id.as_str()).count();
+        let all_time = rows.iter().filter(|row| row["season"] == ALL_TIME_SEASON).count();
+        assert_eq!((in_season, all_time), (2, 3));
+
+        let current = standings(&store, "endless_runner", &SeasonSelector::Current, 10).await.unwrap().unwrap();
+        assert_eq!(current.season.as_ref().unwrap().id, season_id);
+        assert_eq!(ranking(&current), vec![("bob", 900), ("alice", 500)]);
+        let fallback = standings(&store, "deathmatch", &SeasonSelector::Current, 10).await.unwrap().unwrap();
+        assert!(fallback.season.is_none());
+        assert_eq!(ranking(&fallback), vec![("alice", 30)]);
+        assert!(standings(&store, "deathmatch", &SeasonSelector::Id(season_id), 10).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_season_lookup_still_records_the_score_all_time() {
+        let (url, records) = mock_pocketbase::spawn().await;
+        let store = PocketBaseStore::new(&url);
+        // Record hỏng làm active_season lỗi khi parse
+        records.lock().unwrap().entry("seasons".to_string()).or_default().push(serde_json::json!({
+            "id": "broken", "game_mode": "endless_runner", "active": true, "starts_at": "not a date"
+        }));
+
+        let submitted = submit_score(&store, "endless_runner", "alice", "Alice", 500).await.unwrap();
+        assert_eq!(submitted, SubmittedScore { season_id: None, rank: 1, best_score: 500 });
+        assert_eq!(records.lock().unwrap()["leaderboard"][0]["season"], ALL_TIME_SEASON);
+    }
+
+    #[tokio::test]
+    async fn rollover_archives_top_players_and_freezes_the_closed_season() {
+        let (url, records) = mock_pocketbase::spawn().await;
+        let store = PocketBaseStore::new(&url);
+        let first = create_season(&store, "endless_runner", "2024-05-01T00:00:00Z", "2024-05-08T00:00:00Z").await;
+        for (player, score) in [("alice", 300), ("bob", 700), ("carol", 500)] {
+            submit_score(&store, "endless_runner", player, player, score).await.unwrap();
+        }
+
+        // Chưa hết hạn thì không làm gì
+        let before_end = "2024-05-07T23:00:00Z".parse().unwrap();
+        assert!(roll_over_seasons(&store, before_end, &RolloverPolicy::default()).await.unwrap().is_empty());
+
+        let policy = RolloverPolicy { top_n: 2, grant_rewards: true };
+        let now = "2024-05-08T00:05:00Z".parse().unwrap();
+        let rollovers = roll_over_seasons(&store, now, &policy).await.unwrap();
+        assert_eq!(rollovers.len(), 1);
+        assert_eq!((rollovers[0].archived, rollovers[0].rewards), (2, 2));
+        let second = rollovers[0].opened_season.clone();
+
+        let new_season = active_season(&store, "endless_runner").await.unwrap().unwrap();
+        assert_eq!(new_season.id, second);
+        assert_eq!(new_season.starts_at, "2024-05-08T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(new_season.ends_at, "2024-05-15T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+
+        // Điểm mới vào season mới; season cũ giữ nguyên bảng đã chốt
+        let submitted = submit_score(&store, "endless_runner", "alice", "alice", 1000).await.unwrap();
+        assert_eq!((submitted.season_id.as_deref(), submitted.rank), (Some(second.as_str()), 1));
+
+        let previous = standings(&store, "endless_runner", &SeasonSelector::Id(first.clone()), 10).await.unwrap().unwrap();
+        assert!(!previous.season.as_ref().unwrap().active);
+        assert_eq!(ranking(&previous), vec![("bob", 700), ("carol", 500)]);
+        let all = standings(&store, "endless_runner", &SeasonSelector::All, 10).await.unwrap().unwrap();
+        assert_eq!(ranking(&all), vec![("alice", 1000), ("bob", 700), ("carol", 500)]);
+
+        let listed = list_seasons(&store, Some("endless_runner")).await.unwrap();
+        assert_eq!(listed.iter().map(|season| season.id.as_str()).collect::<Vec<_>>(), vec![second.as_str(), first.as_str()]);
+        assert!(list_seasons(&store, Some("deathmatch")).await.unwrap().is_empty());
+
+        let records = records.lock().unwrap();
+        assert_eq!(records["season_results"].len(), 2);
+        assert_eq!(records["season_rewards"][0]["reward"], "season_champion");
+        assert_eq!(records["seasons"].len(), 2);
+    }
+
+    #[test]
+    fn next_season_skips_periods_missed_while_down() {
+        let closed = Season {
+            id: "s1".to_string(),
+            game_mode: "deathmatch".to_string(),
+            starts_at: "2024-05-01T00:00:00Z".parse().unwrap(),
+            ends_at: "2024-05-08T00:00:00Z".parse().unwrap(),
+            active: true,
+        };
+        let next = next_season(&closed, "2024-05-20T00:00:00Z".parse().unwrap());
+        assert_eq!(next.starts_at, "2024-05-15T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(next.ends_at, "2024-05-22T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+    }
+}
@@ -0,0 +1,150 @@
+//! PocketBase giả dùng chung cho integration test: luôn healthy, có đủ collection của schema và giữ record
+//! trong memory theo (collection, id) để service restart rồi sync lại được. Filter/sort của list bị bỏ qua,
+//! update record chưa có thì tạo mới.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde_json::{json, Value};
+
+use crate::BoxError;
+
+#[derive(Default)]
+struct Store {
+    records: BTreeMap<(String, String), Value>,
+    /// (collection, body) theo thứ tự create
+    created: Vec<(String, Value)>,
+    next_id: u64,
+}
+
+type Shared = Arc<Mutex<Store>>;
+
+/// PocketBase giả đang chạy; server sống tới hết runtime của test
+#[derive(Clone)]
+pub struct FakePocketBase {
+    pub url: String,
+    store: Shared,
+}
+
+impl FakePocketBase {
+    /// Bind port ngẫu nhiên và serve trên runtime hiện tại
+    pub fn spawn() -> Result<Self, BoxError> {
+        let store = Shared::default();
+        let collections: Vec<Value> = std::iter::once(pocketbase::migrations::META_COLLECTION)
+            .chain(pocketbase::migrations::collections().into_iter().map(|definition| definition.name))
+            .map(|name| json!({ "id": name, "name": name, "schema": [], "indexes": [], "rules": null, "created": "", "updated": "" }))
+            .collect();
+        let app = Router::new()
+            .route("/api/health", get(|| async { Json(json!({ "code": 200 })) }))
+            .route("/api/collections", get(move || async move { Json(collections) }))
+            .route("/api/collections/:collection/records", get(list_records).post(create_record))
+            .route(
+                "/api/collections/:collection/records/:id",
+                get(get_record).patch(update_record).delete(delete_record),
+            )
+            .with_state(store.clone());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(axum::Server::from_tcp(listener)?.serve(app.into_make_service()));
+        Ok(Self { url, store })
+    }
+
+    /// Record hiện tại của `collection` có `id`
+    pub fn record(&self, collection: &str, id: &str) -> Option<Value> {
+        self.lock().records.get(&(collection.to_string(), id.to_string())).cloned()
+    }
+
+    /// Mọi record hiện tại của `collection`, theo id
+    pub fn records(&self, collection: &str) -> Vec<Value> {
+        self.lock()
+            .records
+            .iter()
+            .filter(|((owner, _), _)| owner == collection)
+            .map(|(_, record)| record.clone())
+            .collect()
+    }
+
+    /// Body của mọi lần create, theo thứ tự: (collection, body)
+    pub fn created(&self) -> Vec<(String, Value)> {
+        self.lock().created.clone()
+    }
+
+    /// Ghi sẵn record (vd. dữ liệu có từ trước khi service start); `record` phải có `id`
+    pub fn insert(&self, collection: &str, mut record: Value) {
+        let id = record["id"].as_str().expect("seeded record has an id").to_string();
+        stamp(&mut record, &id);
+        self.lock().records.insert((collection.to_string(), id), record);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Store> {
+        self.store.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Thêm `id`/`created`/`updated` mà client PocketBase cần khi đọc record
+fn stamp(record: &mut Value, id: &str) {
+    record["id"] = json!(id);
+    for field in ["created", "updated"] {
+        if record.get(field).is_none() {
+            record[field] = json!("");
+        }
+    }
+}
+
+async fn create_record(State(store): State<Shared>, Path(collection): Path<String>, Json(mut record): Json<Value>) -> Json<Value> {
+    let mut store = store.lock().unwrap();
+    store.created.push((collection.clone(), record.clone()));
+    let id = match record["id"].as_str().filter(|id| !id.is_empty()) {
+        Some(id) => id.to_string(),
+        None => {
+            store.next_id += 1;
+            format!("record{}", store.next_id)
+        }
+    };
+    stamp(&mut record, &id);
+    store.records.insert((collection, id), record.clone());
+    Json(record)
+}
+
+async fn list_records(State(store): State<Shared>, Path(collection): Path<String>) -> Json<Value> {
+    let items: Vec<Value> = store
+        .lock()
+        .unwrap()
+        .records
+        .iter()
+        .filter(|((owner, _), _)| *owner == collection)
+        .map(|(_, record)| record.clone())
+        .collect();
+    Json(json!({ "page": 1, "perPage": items.len(), "totalItems": items.len(), "items": items }))
+}
+
+async fn get_record(State(store): State<Shared>, Path(key): Path<(String, String)>) -> Response {
+    match store.lock().unwrap().records.get(&key) {
+        Some(record) => Json(record.clone()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn update_record(State(store): State<Shared>, Path((collection, id)): Path<(String, String)>, Json(body): Json<Value>) -> Json<Value> {
+    let mut store = store.lock().unwrap();
+    let record = store.records.entry((collection, id.clone())).or_insert_with(|| json!({}));
+    for (key, value) in body.as_object().into_iter().flatten() {
+        record[key] = value.clone();
+    }
+    stamp(record, &id);
+    Json(record.clone())
+}
+
+async fn delete_record(State(store): State<Shared>, Path(key): Path<(String, String)>) -> StatusCode {
+    store.lock().unwrap().records.remove(&key);
+    StatusCode::NO_CONTENT
+}
@@ -0,0 +1,251 @@
+//! Cluster gateway + worker + room-manager chạy in-process trên port ngẫu nhiên cho integration test.
+//! Mỗi service khởi động qua `run(config, shutdown_rx)` như trong binary tổng; cluster chờ địa chỉ thật từ
+//! `ready_tx` thay vì sleep, và dừng mọi service khi bị drop.
+
+use std::net::SocketAddr;
+
+use common_net::shutdown::{self, ShutdownSender};
+use gateway::{
+    auth::{AuthConfig, AuthService, User},
+    cors::AllowedOrigins,
+    GatewayConfig,
+};
+use room_manager::RoomManagerConfig;
+use serde_json::{json, Value};
+use tokio::{sync::oneshot, task::JoinHandle};
+use worker::WorkerConfig;
+
+pub mod fake_pocketbase;
+
+pub use fake_pocketbase::FakePocketBase;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Shared secret nội bộ mà cả ba service trong cluster dùng, thay cho ROOM_MANAGER_INTERNAL_SECRET
+pub const INTERNAL_SECRET: &str = "test-cluster-internal-secret";
+
+/// Config của từng service trước khi start. Địa chỉ bind là port 0; endpoint giữa các service và
+/// các kênh `ready_tx` do cluster điền
+pub struct ClusterConfig {
+    pub gateway: GatewayConfig,
+    pub worker: WorkerConfig,
+    pub room_manager: RoomManagerConfig,
+    /// PocketBase giả của cluster; room-manager luôn dùng, worker chỉ dùng khi test gán vào `worker.pocketbase_url`
+    pub pocketbase_url: String,
+}
+
+impl ClusterConfig {
+    fn new(pocketbase_url: String) -> Self {
+        let ephemeral: SocketAddr = ([127, 0, 0, 1], 0).into();
+        Self {
+            gateway: GatewayConfig {
+                bind_addr: ephemeral,
+                worker_endpoint: String::new(),
+                allowed_origins: AllowedOrigins::any(),
+                quic_bind_addr: None,
+                rate_limit_per_second: 0,
+                stun_servers: Vec::new(),
+                admin_token: None,
+                ws_ping_interval: None,
+                worker_token: None,
+                worker_tls: None,
+                room_manager_url: None,
+                internal_secret: Some(INTERNAL_SECRET.to_string()),
+                ready_tx: None,
+                reload_rx: None,
+                drain_rx: None,
+            },
+            worker: WorkerConfig {
+                rpc_addr: ephemeral,
+                metrics_addr: ephemeral,
+                fail_fast: false,
+                pocketbase_url: None,
+                keyframe_interval_ticks: worker::simulation::DEFAULT_KEYFRAME_INTERVAL_TICKS,
+                room_manager_url: None,
+                leaderboard_url: None,
+                internal_secret: Some(INTERNAL_SECRET.to_string()),
+                rpc_token: None,
+                rpc_tls: None,
+                ready_tx: None,
+                rpc_ready_tx: None,
+                drain_rx: None,
+                drained_tx: None,
+            },
+            room_manager: RoomManagerConfig {
+                metrics_addr: ephemeral,
+                capacity_overrides: Default::default(),
+                ready_tx: None,
+                pocketbase_url: Some(pocketbase_url.clone()),
+                internal_secret: Some(INTERNAL_SECRET.to_string()),
+                reload_rx: None,
+            },
+            pocketbase_url,
+        }
+    }
+}
+
+/// Cluster đang chạy. Drop là trigger shutdown và abort mọi service; `shutdown` thì chờ service dừng hẳn
+pub struct TestCluster {
+    /// Base URL HTTP của gateway, ví dụ `http://127.0.0.1:41234`
+    pub gateway_url: String,
+    pub worker_endpoint: String,
+    /// REST API của room-manager (cũng là cổng metrics của nó)
+    pub room_manager_url: String,
+    /// Client HTTP gọi gateway, dùng với `url`
+    pub http: reqwest::Client,
+    auth: AuthService,
+    pocketbase: FakePocketBase,
+    shutdown_tx: ShutdownSender,
+    services: Vec<(&'static str, JoinHandle<Result<(), BoxError>>)>,
+}
+
+impl TestCluster {
+    pub async fn start() -> Result<Self, BoxError> {
+        Self::start_with(|_| {}).await
+    }
+
+    /// Như `start` nhưng test chỉnh config của từng service trước khi khởi động
+    pub async fn start_with(configure: impl FnOnce(&mut ClusterConfig)) -> Result<Self, BoxError> {
+        let pocketbase = FakePocketBase::spawn()?;
+        let mut config = ClusterConfig::new(pocketbase.url.clone());
+        configure(&mut config);
+        let ClusterConfig { mut gateway, mut worker, mut room_manager, .. } = config;
+
+        let (shutdown_tx, shutdown_rx) = shutdown::channel();
+        let mut cluster = Self {
+            gateway_url: String::new(),
+            worker_endpoint: String::new(),
+            room_manager_url: String::new(),
+            http: reqwest::Client::builder().timeout(std::time::Duration::from_secs(5)).build()?,
+            auth: AuthService::from_config(&AuthConfig::from_env()),
+            pocketbase,
+            shutdown_tx,
+            services: Vec::new(),
+        };
+
+        let (ready_tx, ready_rx) = oneshot::channel();
+        room_manager.ready_tx = Some(ready_tx);
+        cluster.spawn("room_manager", room_manager::run(room_manager, shutdown_rx.clone()));
+        cluster.room_manager_url = format!("http://{}", cluster.wait_ready("room_manager", ready_rx).await?);
+
+        let (ready_tx, ready_rx) = oneshot::channel();
+        worker.rpc_ready_tx = Some(ready_tx);
+        worker.room_manager_url.get_or_insert_with(|| cluster.room_manager_url.clone());
+        cluster.spawn("worker", worker::run(worker, shutdown_rx.clone()));
+        cluster.worker_endpoint = format!("http://{}", cluster.wait_ready("worker", ready_rx).await?);
+
+        let (ready_tx, ready_rx) = oneshot::channel();
+        gateway.ready_tx = Some(ready_tx);
+        gateway.worker_endpoint = cluster.worker_endpoint.clone();
+        gateway.room_manager_url = Some(cluster.room_manager_url.clone());
+        cluster.spawn("gateway", gateway::run(gateway, shutdown_rx));
+        cluster.gateway_url = format!("http://{}", cluster.wait_ready("gateway", ready_rx).await?);
+
+        Ok(cluster)
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.gateway_url)
+    }
+
+    /// Client gRPC tới worker của cluster
+    pub fn worker(&self) -> Result<worker::rpc::Client, BoxError> {
+        Ok(worker::rpc::client(&self.worker_endpoint)?)
+    }
+
+    /// JWT hợp lệ với gateway của cluster cho `user_id`
+    pub fn token(&self, user_id: &str) -> String {
+        let user = User {
+            id: user_id.to_string(),
+            username: user_id.to_string(),
+            email: format!("{user_id}@example.com"),
+            role: "user".to_string(),
+        };
+        self.auth.generate_token(&user).expect("token")
+    }
+
+    /// Tạo phòng public deathmatch qua `POST /rooms/create` của gateway, trả về room_id
+    pub async fn create_room(&self, name: &str, host_id: &str) -> Result<String, BoxError> {
+        let created: Value = self
+            .http
+            .post(self.url(gateway::ROOMS_CREATE_PATH))
+            .json(&json!({
+                "name": name,
+                "game_mode": "deathmatch",
+                "max_players": 4,
+                "host_player_id": host_id,
+                "settings": null
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        match created["room_id"].as_str() {
+            Some(room_id) if created["success"] == true => Ok(room_id.to_string()),
+            _ => Err(format!("create room failed: {created}").into()),
+        }
+    }
+
+    /// Mở WS tới gateway bằng client SDK (frame nhị phân của `common-net`) và join `room_id` dưới tên `player_id`
+    pub async fn ws_client(&self, room_id: &str, player_id: &str) -> Result<client_sdk::GameClient, BoxError> {
+        let client = client_sdk::GameClient::connect(&self.gateway_url.replacen("http", "ws", 1), &self.token(player_id)).await?;
+        client.join_room(room_id).await?;
+        Ok(client)
+    }
+
+    /// Record PocketBase giả đã nhận, theo thứ tự tạo: (collection, body)
+    pub fn pocketbase_records(&self) -> Vec<(String, Value)> {
+        self.pocketbase.created()
+    }
+
+    /// Trigger shutdown rồi chờ từng service dừng; lỗi đầu tiên của service được trả về
+    pub async fn shutdown(&mut self) -> Result<(), BoxError> {
+        shutdown::trigger(&self.shutdown_tx);
+        let mut first_err = None;
+        for (name, service) in self.services.drain(..) {
+            let result = match service.await {
+                Ok(result) => result,
+                Err(err) => Err(format!("{name} panicked: {err}").into()),
+            };
+            if let Err(err) = result {
+                first_err.get_or_insert(err);
+            }
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+
+    fn spawn(
+        &mut self,
+        name: &'static str,
+        service: impl std::future::Future<Output = Result<(), BoxError>> + Send + 'static,
+    ) {
+        self.services.push((name, tokio::spawn(service)));
+    }
+
+    /// Địa chỉ service báo qua `ready_tx`; service dừng trước khi báo thì trả lỗi của nó
+    async fn wait_ready(&mut self, name: &'static str, ready_rx: oneshot::Receiver<SocketAddr>) -> Result<SocketAddr, BoxError> {
+        match ready_rx.await {
+            Ok(addr) => Ok(addr),
+            Err(_) => {
+                let err = match self.services.pop() {
+                    Some((_, service)) => match service.await {
+                        Ok(Err(err)) => err.to_string(),
+                        Ok(Ok(())) => "exited".to_string(),
+                        Err(err) => err.to_string(),
+                    },
+                    None => "not started".to_string(),
+                };
+                Err(format!("{name} stopped before it was ready: {err}").into())
+            }
+        }
+    }
+}
+
+impl Drop for TestCluster {
+    fn drop(&mut self) {
+        shutdown::trigger(&self.shutdown_tx);
+        for (_, service) in &self.services {
+            service.abort();
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use client_sdk::{GameSnapshot, PlayerInput};
+use futures::StreamExt;
+use reqwest::StatusCode;
+use test_harness::{BoxError, TestCluster};
+use worker::leaderboard_client::{LeaderboardClient, ScoreSubmission};
+
+#[tokio::test]
+async fn readyz_is_ok_when_every_dependency_is_up() -> Result<(), BoxError> {
+    let cluster = TestCluster::start().await?;
+
+    let ready = cluster.http.get(cluster.url(gateway::READYZ_PATH)).send().await?;
+    assert_eq!(StatusCode::OK, ready.status());
+    let body: serde_json::Value = ready.json().await?;
+    assert_eq!(body, serde_json::json!({ "worker": "ok", "room_manager": "ok", "pocketbase": "ok" }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn rooms_are_managed_through_room_manager_api() -> Result<(), BoxError> {
+    let cluster = TestCluster::start().await?;
+
+    // Không có shared secret thì room-manager từ chối
+    let unauthorized = cluster
+        .http
+        .get(format!("{}{}", cluster.room_manager_url, room_manager::api::ROOMS_PATH))
+        .send()
+        .await?;
+    assert_eq!(StatusCode::UNAUTHORIZED, unauthorized.status());
+
+    let room_id = cluster.create_room("api room", "host").await?;
+
+    let joined: serde_json::Value = cluster
+        .http
+        .post(cluster.url(gateway::ROOMS_JOIN_PATH))
+        .json(&serde_json::json!({ "room_id": room_id, "player_id": "player-1" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(true, joined["success"], "{joined}");
+    assert_eq!(2, joined["room"]["current_players"]);
+
+    let listed: serde_json::Value = cluster
+        .http
+        .get(cluster.url("/rooms/list?game_mode=deathmatch"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let rooms = listed["rooms"].as_array().expect("rooms");
+    assert_eq!(1, rooms.len());
+    assert_eq!(room_id, rooms[0]["id"]);
+    assert_eq!(2, rooms[0]["current_players"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn ws_player_moves_after_sending_input() -> Result<(), BoxError> {
+    let cluster = TestCluster::start().await?;
+    let room_id = cluster.create_room("ws room", "alice").await?;
+    let alice = cluster.ws_client(&room_id, "alice").await?;
+
+    let velocity_x = |snapshot: &GameSnapshot| snapshot.player("alice")?.pointer("/velocity/velocity/0")?.as_f64();
+    let mut snapshots = alice.snapshots();
+    alice.send_input(PlayerInput::movement(1.0, 0.0, 0.0))?;
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(snapshot) = snapshots.next().await {
+            if velocity_x(&snapshot).is_some_and(|x| x > 0.0) {
+                return;
+            }
+            // Worker chỉ tick khi có input
+            alice.send_input(PlayerInput::movement(1.0, 0.0, 0.0)).expect("send input");
+        }
+    })
+    .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_score_above_the_mode_cap_is_rejected() -> Result<(), BoxError> {
+    let cluster = TestCluster::start().await?;
+    let room_id = cluster.create_room("cap room", "alice").await?;
+    let submit = |body: serde_json::Value| {
+        cluster.http.post(cluster.url("/api/leaderboard/submit")).bearer_auth(cluster.token("alice")).json(&body).send()
+    };
+
+    let rejected = submit(serde_json::json!({ "game_mode": "deathmatch", "room_id": room_id, "score": 10_001 })).await?;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, rejected.status());
+    let body: serde_json::Value = rejected.json().await?;
+    assert_eq!(body["error"]["reason"], "above_max_score");
+
+    // Không có room gần đây cùng mode thì cũng bị từ chối
+    let no_room = submit(serde_json::json!({ "game_mode": "endless_runner", "room_id": room_id, "score": 50 })).await?;
+    assert_eq!(StatusCode::FORBIDDEN, no_room.status());
+
+    let leaderboard: serde_json::Value =
+        cluster.http.get(cluster.url("/api/leaderboard?game_mode=deathmatch")).send().await?.json().await?;
+    assert_eq!(leaderboard["leaderboard"], serde_json::json!([]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn worker_score_for_a_finished_room_is_accepted_and_ranked() -> Result<(), BoxError> {
+    let cluster = TestCluster::start().await?;
+    let room_id = cluster.create_room("ranked room", "alice").await?;
+    let finished = cluster
+        .http
+        .post(format!("{}{}", cluster.room_manager_url, room_manager::api::ROOM_FINISH_PATH.replace(":id", &room_id)))
+        .header(room_manager::api::INTERNAL_SECRET_HEADER, test_harness::INTERNAL_SECRET)
+        .send()
+        .await?;
+    assert_eq!(StatusCode::OK, finished.status());
+
+    let leaderboard = LeaderboardClient::new(&cluster.gateway_url, test_harness::INTERNAL_SECRET);
+    let submission = |player_id: &str, score| ScoreSubmission {
+        room_id: room_id.clone(),
+        tick: 600,
+        game_mode: "deathmatch".to_string(),
+        player_id: player_id.to_string(),
+        player_name: player_id.to_uppercase(),
+        score,
+    };
+    leaderboard.submit(&submission("alice", 12)).await?;
+    // Player không thuộc room thì gateway từ chối dù có secret
+    assert!(leaderboard.submit(&submission("mallory", 9_999)).await.is_err());
+    let forged = LeaderboardClient::new(&cluster.gateway_url, "wrong-secret");
+    assert!(forged.submit(&submission("alice", 9_999)).await.is_err());
+
+    let ranked: serde_json::Value =
+        cluster.http.get(cluster.url("/api/leaderboard?game_mode=deathmatch")).send().await?.json().await?;
+    assert_eq!(
+        ranked["leaderboard"],
+        serde_json::json!([{ "rank": 1, "player_id": "alice", "player_name": "ALICE", "score": 12, "game_mode": "deathmatch" }])
+    );
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use proto::worker::v1::{
+    CreateRoomRequest, EndGameRequest, JoinRoomAsPlayerRequest, JoinRoomRequest, PushInputRequest, RoomSettings,
+    StartGameRequest,
+};
+use serde_json::json;
+use test_harness::{BoxError, TestCluster};
+use worker::rpc;
+
+fn input_json(sequence: u32) -> String {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    json!({ "player_id": "alice", "input_sequence": sequence, "movement": [0.0, 0.0, 1.0], "timestamp": timestamp })
+        .to_string()
+}
+
+fn arena() -> CreateRoomRequest {
+    CreateRoomRequest {
+        room_name: "arena".to_string(),
+        host_id: "alice".to_string(),
+        host_name: "Alice".to_string(),
+        settings: Some(RoomSettings { max_players: 4, min_players_to_start: 1, ..Default::default() }),
+    }
+}
+
+/// Tạo room của alice trên worker, bắt đầu trận và spawn entity của alice
+async fn start_arena(client: &mut rpc::Client) -> Result<String, BoxError> {
+    let room = client.create_room(arena()).await?.into_inner();
+    assert!(room.success, "{}", room.error);
+    let started = client
+        .start_game(StartGameRequest { room_id: room.room_id.clone(), player_id: "alice".to_string() })
+        .await?
+        .into_inner();
+    assert!(started.success, "{}", started.error);
+    let joined = client
+        .join_room(JoinRoomRequest { room_id: room.room_id.clone(), player_id: "alice".to_string(), player_name: String::new() })
+        .await?
+        .into_inner();
+    assert!(joined.ok, "{}", joined.error);
+    Ok(room.room_id)
+}
+
+#[tokio::test]
+async fn shutdown_persists_scores_of_active_rooms() -> Result<(), BoxError> {
+    let mut cluster = TestCluster::start_with(|config| config.worker.pocketbase_url = Some(config.pocketbase_url.clone())).await?;
+    let mut client = cluster.worker()?;
+    let room_id = start_arena(&mut client).await?;
+
+    // Mỗi input tick simulation theo thời gian thật; auto-run cộng điểm
+    for sequence in 1..=10 {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client.push_input(PushInputRequest { room_id: room_id.clone(), sequence, payload_json: input_json(sequence) }).await?;
+    }
+
+    tokio::time::timeout(Duration::from_secs(10), cluster.shutdown()).await??;
+
+    let records = cluster.pocketbase_records();
+    let (_, record) = records.iter().find(|(collection, _)| collection == "match_results").expect("match result written on shutdown");
+    assert_eq!(record["room_id"], room_id.as_str());
+    assert_eq!(record["players"][0]["player_id"], "alice");
+    assert!(record["players"][0]["score"].as_u64().unwrap_or_default() > 0, "{record}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn drain_refuses_new_joins_while_active_room_keeps_ticking() -> Result<(), BoxError> {
+    let (drain_tx, drain_rx) = common_net::shutdown::channel();
+    let (drained_tx, drained_rx) = common_net::shutdown::channel();
+    let mut cluster = TestCluster::start_with(|config| {
+        config.worker.drain_rx = Some(drain_rx);
+        config.worker.drained_tx = Some(drained_tx);
+    })
+    .await?;
+    let mut client = cluster.worker()?;
+    let room_id = start_arena(&mut client).await?;
+
+    common_net::shutdown::trigger(&drain_tx);
+    let mut refused = None;
+    for _ in 0..50 {
+        let created = client.create_room(arena()).await?.into_inner();
+        if !created.success {
+            refused = Some(created.error);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(refused.as_deref(), Some(rpc::DRAINING_ERROR));
+
+    let join = client
+        .join_room(JoinRoomRequest { room_id: room_id.clone(), player_id: "bob".to_string(), player_name: String::new() })
+        .await?
+        .into_inner();
+    assert!(!join.ok && join.error == rpc::DRAINING_ERROR, "{join:?}");
+    let join_as_player = client
+        .join_room_as_player(JoinRoomAsPlayerRequest {
+            room_id: room_id.clone(),
+            player_id: "bob".to_string(),
+            player_name: "Bob".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert!(!join_as_player.success && join_as_player.error == rpc::DRAINING_ERROR);
+
+    // Room đang chơi vẫn nhận input và tick tiếp
+    let mut ticks = Vec::new();
+    for sequence in 1..=5 {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let pushed = client
+            .push_input(PushInputRequest { room_id: room_id.clone(), sequence, payload_json: input_json(sequence) })
+            .await?
+            .into_inner();
+        assert!(pushed.ok, "{}", pushed.error);
+        ticks.push(pushed.snapshot.map(|snapshot| snapshot.tick).unwrap_or_default());
+    }
+    assert!(ticks.windows(2).all(|pair| pair[1] > pair[0]), "{ticks:?}");
+    assert!(!*drained_rx.borrow());
+
+    client.end_game(EndGameRequest { room_id }).await?;
+    tokio::time::timeout(Duration::from_secs(5), common_net::shutdown::wait(drained_rx)).await?;
+
+    tokio::time::timeout(Duration::from_secs(10), cluster.shutdown()).await??;
+    Ok(())
+}
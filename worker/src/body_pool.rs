@@ -0,0 +1,192 @@
+//! Pool body Rapier cho obstacle/enemy/pickup: body của entity bị despawn được tắt đi và giữ lại
+//! theo hình collider, lần spawn sau cùng hình thì bật lại ở vị trí mới thay vì cấp phát body +
+//! collider mới. Endless runner spawn/cull obstacle liên tục nên đây là đường nóng.
+
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::Component;
+use rapier3d::prelude::*;
+
+/// Tổng số body rảnh pool giữ lại (mọi hình cộng lại), vượt thì body despawn bị xoá như thường
+pub const DEFAULT_BODY_POOL_CAPACITY: usize = 512;
+
+/// Hình collider + loại body; body chỉ được dùng lại cho spawn cùng key.
+/// Kích thước lưu dạng bit của f32 để làm key HashMap được
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BodyShape {
+    dynamic: bool,
+    /// [radius, 0, 0] với ball, half extents với cuboid
+    size: [u32; 3],
+    ball: bool,
+}
+
+impl BodyShape {
+    pub fn fixed_ball(radius: f32) -> Self {
+        Self { dynamic: false, size: [radius.to_bits(), 0, 0], ball: true }
+    }
+
+    pub fn dynamic_ball(radius: f32) -> Self {
+        Self { dynamic: true, ..Self::fixed_ball(radius) }
+    }
+
+    pub fn fixed_cuboid(hx: f32, hy: f32, hz: f32) -> Self {
+        Self { dynamic: false, size: [hx.to_bits(), hy.to_bits(), hz.to_bits()], ball: false }
+    }
+
+    fn body(&self, position: [f32; 3]) -> RigidBody {
+        let builder = if self.dynamic { RigidBodyBuilder::dynamic() } else { RigidBodyBuilder::fixed() };
+        builder.translation(vector![position[0], position[1], position[2]]).build()
+    }
+
+    fn collider(&self) -> Collider {
+        let [x, y, z] = self.size.map(f32::from_bits);
+        if self.ball {
+            ColliderBuilder::ball(x).build()
+        } else {
+            ColliderBuilder::cuboid(x, y, z).build()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BodyPool {
+    free: HashMap<BodyShape, Vec<RigidBodyHandle>>,
+    capacity: usize,
+    len: usize,
+    reused: u64,
+    allocated: u64,
+}
+
+impl Default for BodyPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_BODY_POOL_CAPACITY)
+    }
+}
+
+impl BodyPool {
+    /// `capacity` = 0 thì không giữ body nào, mọi spawn đều cấp phát mới
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            free: HashMap::new(),
+            capacity,
+            len: 0,
+            reused: 0,
+            allocated: 0,
+        }
+    }
+
+    /// Số body đang nằm rảnh trong pool (vẫn có trong RigidBodySet nhưng bị tắt)
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// (số spawn dùng lại body, số spawn cấp phát mới)
+    pub fn stats(&self) -> (u64, u64) {
+        (self.reused, self.allocated)
+    }
+
+    /// Tỉ lệ spawn dùng lại body trong pool, 0 khi chưa spawn gì
+    pub fn reuse_rate(&self) -> f64 {
+        let total = self.reused + self.allocated;
+        if total == 0 {
+            0.0
+        } else {
+            self.reused as f64 / total as f64
+        }
+    }
+
+    /// Body cho spawn mới ở `position`: body rảnh cùng hình được đặt lại vị trí, xoá vận tốc/lực và bật lên,
+    /// không có thì cấp phát body + collider mới
+    pub fn acquire(
+        &mut self,
+        shape: BodyShape,
+        position: [f32; 3],
+        bodies: &mut RigidBodySet,
+        colliders: &mut ColliderSet,
+    ) -> RigidBodyHandle {
+        let reused = self.free.get_mut(&shape).and_then(Vec::pop);
+        let metrics = crate::simulation_metrics();
+        if let Some(handle) = reused.filter(|handle| bodies.contains(*handle)) {
+            self.len -= 1;
+            self.reused += 1;
+            metrics.inc_body_spawns(true);
+
+            let body = &mut bodies[handle];
+            body.set_position(Isometry::translation(position[0], position[1], position[2]), false);
+            body.set_linvel(Vector::zeros(), false);
+            body.set_angvel(Vector::zeros(), false);
+            body.reset_forces(false);
+            body.reset_torques(false);
+            body.set_enabled(true);
+            body.wake_up(true);
+            return handle;
+        }
+
+        self.allocated += 1;
+        metrics.inc_body_spawns(false);
+        let handle = bodies.insert(shape.body(position));
+        colliders.insert_with_parent(shape.collider(), handle, bodies);
+        handle
+    }
+
+    /// Giữ lại body của entity vừa despawn; false khi pool đầy, lúc đó caller tự xoá body
+    pub fn release(&mut self, shape: BodyShape, handle: RigidBodyHandle, bodies: &mut RigidBodySet) -> bool {
+        if self.len >= self.capacity {
+            return false;
+        }
+        let Some(body) = bodies.get_mut(handle) else {
+            return false;
+        };
+        body.set_enabled(false);
+        self.free.entry(shape).or_default().push(handle);
+        self.len += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn released_bodies_are_disabled_and_reused_for_the_same_shape_only() {
+        let (mut bodies, mut colliders) = (RigidBodySet::new(), ColliderSet::new());
+        let mut pool = BodyPool::new(2);
+        let wall = BodyShape::fixed_cuboid(2.0, 1.0, 0.5);
+        let spike = BodyShape::fixed_ball(0.5);
+
+        let first = pool.acquire(wall, [0.0, 0.5, 10.0], &mut bodies, &mut colliders);
+        assert!(pool.release(wall, first, &mut bodies));
+        assert!(!bodies[first].is_enabled());
+
+        // Khác hình thì cấp phát mới, cùng hình thì lấy lại đúng body cũ ở vị trí mới
+        let other = pool.acquire(spike, [0.0, 0.5, 20.0], &mut bodies, &mut colliders);
+        assert_ne!(other, first);
+        let again = pool.acquire(wall, [3.0, 0.5, 30.0], &mut bodies, &mut colliders);
+        assert_eq!(again, first);
+        assert!(bodies[again].is_enabled());
+        assert_eq!(bodies[again].translation(), &vector![3.0, 0.5, 30.0]);
+        assert_eq!(pool.stats(), (1, 2));
+        assert_eq!(colliders.len(), 2);
+    }
+
+    #[test]
+    fn pool_is_bounded() {
+        let (mut bodies, mut colliders) = (RigidBodySet::new(), ColliderSet::new());
+        let mut pool = BodyPool::new(3);
+        let shape = BodyShape::dynamic_ball(0.6);
+        let handles: Vec<_> = (0..5).map(|i| pool.acquire(shape, [i as f32, 0.0, 0.0], &mut bodies, &mut colliders)).collect();
+
+        let kept = handles.iter().filter(|&&handle| pool.release(shape, handle, &mut bodies)).count();
+        assert_eq!(kept, 3);
+        assert_eq!(pool.len(), 3);
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::{InputActions, PlayerInput, RUNNER_LANES};
+
+/// Prefix player_id của bot - chỉ gồm chữ/số/`-` để qua được InputValidator
+pub const BOT_ID_PREFIX: &str = common_net::matchmaking::BOT_PLAYER_PREFIX;
+/// Bot né obstacle nằm trong bán kính này (units)
+pub const OBSTACLE_AVOID_RADIUS: f32 = 3.0;
+/// Số bot tối đa cho một lần AddBots
+pub const MAX_BOTS_PER_REQUEST: u32 = 64;
+
+/// Độ khó quyết định thời gian phản ứng và độ "tinh mắt" của bot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotDifficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl BotDifficulty {
+    /// Parse tên độ khó từ RPC; chuỗi rỗng = Normal
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "normal" => Some(BotDifficulty::Normal),
+            "easy" => Some(BotDifficulty::Easy),
+            "hard" => Some(BotDifficulty::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BotDifficulty::Easy => "easy",
+            BotDifficulty::Normal => "normal",
+            BotDifficulty::Hard => "hard",
+        }
+    }
+
+    /// Số tick giữa hai lần bot ra quyết định. Luôn >= 2 để không chạm rate limit 60 input/s
+    fn think_interval_ticks(&self) -> u64 {
+        match self {
+            BotDifficulty::Easy => 12,
+            BotDifficulty::Normal => 6,
+            BotDifficulty::Hard => 3,
+        }
+    }
+
+    /// Xác suất thấy obstacle phía trước trong một lần ra quyết định
+    fn awareness(&self) -> f32 {
+        match self {
+            BotDifficulty::Easy => 0.5,
+            BotDifficulty::Normal => 0.85,
+            BotDifficulty::Hard => 1.0,
+        }
+    }
+
+    /// Xác suất nhảy ngẫu nhiên mỗi lần ra quyết định
+    fn jump_chance(&self) -> f32 {
+        match self {
+            BotDifficulty::Easy => 0.02,
+            BotDifficulty::Normal => 0.05,
+            BotDifficulty::Hard => 0.08,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BotState {
+    room_id: String,
+    difficulty: BotDifficulty,
+    sequence: u32,
+    next_think_tick: u64,
+}
+
+/// Những gì bot nhìn thấy trong tick hiện tại, GameWorld dựng từ ECS
+#[derive(Debug, Default)]
+pub struct BotSenses {
+    /// Vị trí của từng bot theo player_id
+    pub positions: HashMap<String, [f32; 3]>,
+    pub pickups: Vec<[f32; 3]>,
+    pub obstacles: Vec<[f32; 3]>,
+}
+
+/// Quản lý bot players: mỗi tick sinh `PlayerInput` cho bot tới lượt ra quyết định.
+/// Input đi qua InputBuffer/InputValidator giống hệt input của người chơi thật.
+#[derive(Debug, Clone)]
+pub struct BotController {
+    bots: BTreeMap<String, BotState>, // BTreeMap để thứ tự rút rng giữa các bot cố định theo seed
+    next_id: u64,
+    /// Some = endless runner, bot đổi lane thay vì lái tự do
+    lanes: Option<Vec<f32>>,
+}
+
+impl Default for BotController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BotController {
+    /// Mặc định theo endless runner (3 lane) giống GameWorld
+    pub fn new() -> Self {
+        Self {
+            bots: BTreeMap::new(),
+            next_id: 0,
+            lanes: Some(RUNNER_LANES.to_vec()),
+        }
+    }
+
+    /// Bot lái tự do trên mặt phẳng x/z (không có lane)
+    pub fn free_roam() -> Self {
+        Self {
+            lanes: None,
+            ..Self::new()
+        }
+    }
+
+    /// Đăng ký bot mới và trả về player_id của nó
+    pub fn register(&mut self, room_id: &str, difficulty: BotDifficulty, current_tick: u64) -> String {
+        self.next_id += 1;
+        let bot_id = format!("{}{}", BOT_ID_PREFIX, self.next_id);
+
+        // Lệch pha để các bot không cùng ra quyết định trong một tick
+        let interval = difficulty.think_interval_ticks();
+        self.bots.insert(
+            bot_id.clone(),
+            BotState {
+                room_id: room_id.to_string(),
+                difficulty,
+                sequence: 0,
+                next_think_tick: current_tick + self.next_id % interval,
+            },
+        );
+        bot_id
+    }
+
+    pub fn remove(&mut self, bot_id: &str) -> bool {
+        self.bots.remove(bot_id).is_some()
+    }
+
+    pub fn is_bot(&self, player_id: &str) -> bool {
+        self.bots.contains_key(player_id)
+    }
+
+    pub fn bots_in_room(&self, room_id: &str) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .bots
+            .iter()
+            .filter(|(_, bot)| bot.room_id == room_id)
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn bot_ids(&self) -> impl Iterator<Item = &String> {
+        self.bots.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bots.is_empty()
+    }
+
+    /// Sinh input cho các bot tới lượt trong tick này; mọi quyết định random rút từ `rng` của world
+    pub fn plan(
+        &mut self,
+        senses: &BotSenses,
+        current_tick: u64,
+        timestamp_ms: u64,
+        rng: &mut impl Rng,
+    ) -> Vec<PlayerInput> {
+        let mut inputs = Vec::new();
+
+        for (bot_id, bot) in self.bots.iter_mut() {
+            if current_tick < bot.next_think_tick {
+                continue;
+            }
+            let Some(&position) = senses.positions.get(bot_id) else {
+                continue;
+            };
+            bot.next_think_tick = current_tick + bot.difficulty.think_interval_ticks();
+
+            let sees_obstacles = rng.gen::<f32>() < bot.difficulty.awareness();
+            let (movement, mut actions) = match &self.lanes {
+                Some(lanes) => steer_in_lanes(lanes, position, senses, sees_obstacles),
+                None => steer_free(position, senses, sees_obstacles),
+            };
+            if rng.gen::<f32>() < bot.difficulty.jump_chance() {
+                actions.jump = true;
+            }
+
+            bot.sequence += 1;
+            inputs.push(PlayerInput {
+                player_id: bot_id.clone(),
+                input_sequence: bot.sequence,
+                movement,
+                timestamp: timestamp_ms,
+                actions,
+            });
+        }
+
+        inputs
+    }
+}
+
+/// Obstacle phía trước (hoặc ngay cạnh) trong lane có tâm `lane_x`
+fn lane_blocked(lane_x: f32, lane_width: f32, position: [f32; 3], obstacles: &[[f32; 3]]) -> bool {
+    obstacles.iter().any(|obstacle| {
+        let ahead = obstacle[2] - position[2];
+        (obstacle[0] - lane_x).abs() < lane_width / 2.0 && (-0.5..=OBSTACLE_AVOID_RADIUS).contains(&ahead)
+    })
+}
+
+fn nearest_index(values: &[f32], target: f32) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| (*a - target).abs().total_cmp(&(*b - target).abs()))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+fn nearest_pickup(position: [f32; 3], pickups: &[[f32; 3]]) -> Option<[f32; 3]> {
+    pickups.iter().copied().min_by(|a, b| {
+        planar_distance(position, *a).total_cmp(&planar_distance(position, *b))
+    })
+}
+
+fn planar_distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// Endless runner: né sang lane trống gần nhất, không bị chặn thì hướng về lane có pickup gần nhất.
+/// Hết lane trống thì nhảy qua obstacle.
+fn steer_in_lanes(lanes: &[f32], position: [f32; 3], senses: &BotSenses, sees_obstacles: bool) -> ([f32; 3], InputActions) {
+    let mut actions = InputActions::default();
+    if lanes.is_empty() {
+        return ([0.0, 0.0, 1.0], actions);
+    }
+
+    let lane_width = if lanes.len() > 1 { (lanes[1] - lanes[0]).abs() } else { OBSTACLE_AVOID_RADIUS };
+    let current = nearest_index(lanes, position[0]);
+    let obstacles: &[[f32; 3]] = if sees_obstacles { &senses.obstacles } else { &[] };
+    let blocked = |index: usize| lane_blocked(lanes[index], lane_width, position, obstacles);
+
+    let target = if blocked(current) {
+        // Lane kề bên trước, lane xa hơn sau
+        let mut free: Vec<usize> = (0..lanes.len()).filter(|&index| !blocked(index)).collect();
+        free.sort_by_key(|&index| index.abs_diff(current));
+        match free.first() {
+            Some(&index) => index,
+            None => {
+                actions.jump = true;
+                current
+            }
+        }
+    } else {
+        nearest_pickup(position, &senses.pickups)
+            .map(|pickup| nearest_index(lanes, pickup[0]))
+            .filter(|&index| !blocked(index))
+            .unwrap_or(current)
+    };
+
+    let steer_x = ((lanes[target] - position[0]) / lane_width).clamp(-1.0, 1.0);
+    ([steer_x, 0.0, 1.0], actions)
+}
+
+/// Lái tự do: kéo về pickup gần nhất, bị obstacle trong OBSTACLE_AVOID_RADIUS đẩy ra
+fn steer_free(position: [f32; 3], senses: &BotSenses, sees_obstacles: bool) -> ([f32; 3], InputActions) {
+    let mut steer = [0.0f32, 0.0f32];
+    if let Some(pickup) = nearest_pickup(position, &senses.pickups) {
+        let distance = planar_distance(position, pickup).max(f32::EPSILON);
+        steer[0] += (pickup[0] - position[0]) / distance;
+        steer[1] += (pickup[2] - position[2]) / distance;
+    }
+
+    if sees_obstacles {
+        for obstacle in &senses.obstacles {
+            let distance = planar_distance(position, *obstacle);
+            if distance < OBSTACLE_AVOID_RADIUS {
+                // Càng gần càng bị đẩy mạnh
+                let push = 2.0 * (OBSTACLE_AVOID_RADIUS - distance) / OBSTACLE_AVOID_RADIUS;
+                let distance = distance.max(f32::EPSILON);
+                steer[0] += (position[0] - obstacle[0]) / distance * push;
+                steer[1] += (position[2] - obstacle[2]) / distance * push;
+            }
+        }
+    }
+
+    let length = (steer[0].powi(2) + steer[1].powi(2)).sqrt();
+    if length > 1.0 {
+        steer[0] /= length;
+        steer[1] /= length;
+    }
+    ([steer[0], 0.0, steer[1]], InputActions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn senses(bot_id: &str, position: [f32; 3]) -> BotSenses {
+        BotSenses {
+            positions: HashMap::from([(bot_id.to_string(), position)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn difficulty_parses_rpc_names() {
+        assert_eq!(BotDifficulty::parse(""), Some(BotDifficulty::Normal));
+        assert_eq!(BotDifficulty::parse("HARD"), Some(BotDifficulty::Hard));
+        assert_eq!(BotDifficulty::parse("easy"), Some(BotDifficulty::Easy));
+        assert_eq!(BotDifficulty::parse("nightmare"), None);
+    }
+
+    #[test]
+    fn free_roaming_bot_steers_toward_nearest_pickup() {
+        let mut senses = senses("bot-1", [0.0, 1.0, 0.0]);
+        senses.pickups = vec![[5.0, 1.0, 0.0], [-20.0, 1.0, 0.0]];
+
+        let (movement, _) = steer_free([0.0, 1.0, 0.0], &senses, true);
+        assert!(movement[0] > 0.9, "{:?}", movement);
+        assert!(movement[2].abs() < 0.01);
+    }
+
+    #[test]
+    fn free_roaming_bot_is_pushed_away_from_close_obstacle() {
+        let mut senses = senses("bot-1", [0.0, 1.0, 0.0]);
+        senses.pickups = vec![[0.0, 1.0, 10.0]];
+        senses.obstacles = vec![[0.0, 0.5, 1.0]];
+
+        let (movement, _) = steer_free([0.0, 1.0, 0.0], &senses, true);
+        assert!(movement[2] < 0.0, "obstacle within 3 units should win over the pickup: {:?}", movement);
+
+        // Obstacle ngoài bán kính thì bỏ qua
+        senses.obstacles = vec![[0.0, 0.5, 5.0]];
+        let (movement, _) = steer_free([0.0, 1.0, 0.0], &senses, true);
+        assert!(movement[2] > 0.9);
+    }
+
+    #[test]
+    fn runner_bot_changes_lane_away_from_obstacle() {
+        let mut senses = senses("bot-1", [0.0, 1.0, 0.0]);
+        senses.obstacles = vec![[0.0, 0.5, 2.0]];
+
+        let (movement, actions) = steer_in_lanes(&RUNNER_LANES, [0.0, 1.0, 0.0], &senses, true);
+        assert!(movement[0].abs() >= 0.99, "{:?}", movement);
+        assert!(!actions.jump);
+
+        // Không nhìn thấy obstacle thì giữ lane
+        let (movement, _) = steer_in_lanes(&RUNNER_LANES, [0.0, 1.0, 0.0], &senses, false);
+        assert_eq!(movement[0], 0.0);
+    }
+
+    #[test]
+    fn runner_bot_jumps_when_every_lane_is_blocked() {
+        let mut senses = senses("bot-1", [0.0, 1.0, 0.0]);
+        senses.obstacles = RUNNER_LANES.iter().map(|&x| [x, 0.5, 1.5]).collect();
+
+        let (movement, actions) = steer_in_lanes(&RUNNER_LANES, [0.0, 1.0, 0.0], &senses, true);
+        assert_eq!(movement[0], 0.0);
+        assert!(actions.jump);
+    }
+
+    #[test]
+    fn runner_bot_moves_to_pickup_lane() {
+        let mut senses = senses("bot-1", [0.0, 1.0, 0.0]);
+        senses.pickups = vec![[-3.0, 1.0, 8.0]];
+
+        let (movement, _) = steer_in_lanes(&RUNNER_LANES, [0.0, 1.0, 0.0], &senses, true);
+        assert!(movement[0] < 0.0);
+    }
+
+    #[test]
+    fn bots_only_emit_input_on_their_think_ticks() {
+        let mut bots = BotController::new();
+        let bot_id = bots.register("room-1", BotDifficulty::Hard, 0);
+        let senses = senses(&bot_id, [0.0, 1.0, 0.0]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+
+        let emitted: usize = (0..30).map(|tick| bots.plan(&senses, tick, 0, &mut rng).len()).sum();
+        assert_eq!(emitted, 10, "hard bots think every 3 ticks");
+
+        let inputs = bots.plan(&senses, 1_000, 0, &mut rng);
+        assert_eq!(inputs[0].input_sequence, 11);
+        assert_eq!(bots.bots_in_room("room-1"), vec![bot_id.clone()]);
+        assert!(bots.bots_in_room("room-2").is_empty());
+        assert!(bots.remove(&bot_id));
+        assert!(bots.is_empty());
+    }
+
+    #[test]
+    fn world_bots_feed_inputs_through_the_validator() {
+        use crate::simulation::{GameWorld, Player, VelocityQ};
+
+        let mut world = GameWorld::new();
+        let bot_id = world.add_bot("room-1", BotDifficulty::Hard);
+        world.add_pickup([3.0, 1.0, 10.0], 5);
+        for _ in 0..30 {
+            world.accumulator = world.tick_rate;
+            world.tick();
+        }
+
+        // Input của bot đã qua ingest_inputs và được áp vào velocity
+        let buffer = &world.input_buffers[&bot_id];
+        assert!(buffer.inputs.is_empty());
+        assert_eq!(buffer.last_processed_sequence, 10);
+        let (player, velocity) = world
+            .world
+            .query::<(&Player, &VelocityQ)>()
+            .iter(&world.world)
+            .map(|(player, velocity)| (player.clone(), velocity.clone()))
+            .next()
+            .expect("bot entity");
+        assert!(player.is_bot);
+        assert!(velocity.velocity[0] > 0.0, "bot should steer toward the pickup lane");
+
+        assert!(world.remove_player(&bot_id));
+        assert!(world.bots.is_empty());
+        assert_eq!(world.world.query::<&Player>().iter(&world.world).count(), 0);
+    }
+}
@@ -0,0 +1,161 @@
+//! Checkpoint tiến độ player trong trận dài (mode tính điểm theo quãng đường): worker định kỳ ghi score/quãng đường
+//! của từng player qua hàng đợi write-behind có giới hạn, nên worker crash thì player vào lại room bật `resume_progress`
+//! được khôi phục điểm. Checkpoint của room bị xoá sau khi kết quả trận đã ghi để không tính điểm hai lần.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::database::PocketBaseClient;
+use crate::room::Room;
+
+pub const DEFAULT_CHECKPOINT_INTERVAL_SECONDS: u32 = 30;
+/// Session mới chỉ được khôi phục từ checkpoint ghi trong khoảng này
+pub const RESUME_GRACE_SECONDS: u64 = 300;
+/// Số thao tác ghi chờ tối đa; đầy thì checkpoint mới bị bỏ (lần sau ghi score mới hơn) thay vì chặn tick
+pub const CHECKPOINT_QUEUE_CAPACITY: usize = 1024;
+
+/// Tiến độ hiện tại của player trong simulation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerProgress {
+    pub score: u32,
+    /// Quãng đường đã chạy theo trục z
+    pub distance: f32,
+}
+
+/// Record trong collection `player_checkpoints`, mỗi cặp (player, room) một record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgressCheckpoint {
+    pub player_id: String,
+    pub room_id: String,
+    pub game_mode: String,
+    pub score: u32,
+    pub distance: f32,
+    pub tick: u64,
+    /// Unix timestamp in seconds
+    pub saved_at: u64,
+}
+
+/// Checkpoint điểm cao nhất cùng `game_mode` còn trong cửa sổ resume tính tới `now`
+pub fn best_checkpoint<'a>(checkpoints: &'a [ProgressCheckpoint], game_mode: &str, now: u64) -> Option<&'a ProgressCheckpoint> {
+    checkpoints
+        .iter()
+        .filter(|checkpoint| checkpoint.game_mode == game_mode && now.saturating_sub(checkpoint.saved_at) <= RESUME_GRACE_SECONDS)
+        .max_by_key(|checkpoint| (checkpoint.score, checkpoint.tick))
+}
+
+/// Nhớ lần checkpoint gần nhất của từng room và score đã ghi của từng player
+#[derive(Debug, Default)]
+pub struct CheckpointTracker {
+    /// room id -> tick của lần checkpoint gần nhất (hoặc lúc bắt đầu theo dõi room)
+    last_tick: HashMap<String, u64>,
+    /// (room id, player id) -> score đã checkpoint
+    last_score: HashMap<(String, String), u32>,
+}
+
+impl CheckpointTracker {
+    /// Checkpoint của các room đã qua `checkpoint_interval_seconds` kể từ lần trước; room thấy lần đầu chỉ bắt đầu đếm.
+    /// Bot và player có score không đổi từ checkpoint trước bị bỏ qua
+    pub fn collect<'a>(
+        &mut self,
+        rooms: impl IntoIterator<Item = &'a Room>,
+        progress: &HashMap<String, PlayerProgress>,
+        current_tick: u64,
+        ticks_per_second: u64,
+        now: u64,
+    ) -> Vec<ProgressCheckpoint> {
+        let mut checkpoints = Vec::new();
+        for room in rooms {
+            let last_tick = *self.last_tick.entry(room.id.clone()).or_insert(current_tick);
+            let interval_ticks = (room.settings.checkpoint_interval_seconds as u64 * ticks_per_second).max(1);
+            if current_tick < last_tick + interval_ticks {
+                continue;
+            }
+            self.last_tick.insert(room.id.clone(), current_tick);
+
+            for player in room.players.values().filter(|player| !player.is_bot) {
+                let Some(&PlayerProgress { score, distance }) = progress.get(&player.id) else {
+                    continue;
+                };
+                let key = (room.id.clone(), player.id.clone());
+                if self.last_score.get(&key) == Some(&score) {
+                    continue;
+                }
+                self.last_score.insert(key, score);
+                checkpoints.push(ProgressCheckpoint {
+                    player_id: player.id.clone(),
+                    room_id: room.id.clone(),
+                    game_mode: room.settings.game_mode.as_str().to_string(),
+                    score,
+                    distance,
+                    tick: current_tick,
+                    saved_at: now,
+                });
+            }
+        }
+        checkpoints
+    }
+
+    /// Room đã kết thúc thì thôi theo dõi
+    pub fn forget_room(&mut self, room_id: &str) {
+        self.last_tick.remove(room_id);
+        self.last_score.retain(|(room, _), _| room != room_id);
+    }
+}
+
+#[derive(Debug)]
+enum CheckpointOp {
+    Save(ProgressCheckpoint),
+    DeleteRoom(String),
+}
+
+/// Hàng đợi write-behind có giới hạn tới PocketBase; mọi thao tác ghi theo đúng thứ tự xếp vào queue
+#[derive(Debug, Clone)]
+pub struct CheckpointQueue {
+    tx: mpsc::Sender<CheckpointOp>,
+}
+
+impl CheckpointQueue {
+    /// Tạo queue và task ghi nền; task dừng khi mọi bản sao của queue bị drop
+    pub fn spawn(store: PocketBaseClient) -> (Self, tokio::task::JoinHandle<()>) {
+        let (tx, mut rx) = mpsc::channel(CHECKPOINT_QUEUE_CAPACITY);
+        let writer = tokio::spawn(async move {
+            while let Some(op) = rx.recv().await {
+                match op {
+                    CheckpointOp::Save(checkpoint) => {
+                        if let Err(err) = store.save_checkpoint(&checkpoint).await {
+                            warn!(player_id = %checkpoint.player_id, room_id = %checkpoint.room_id, %err, "worker: failed to save checkpoint");
+                        }
+                    }
+                    CheckpointOp::DeleteRoom(room_id) => match store.delete_room_checkpoints(&room_id).await {
+                        Ok(deleted) => debug!(%room_id, deleted, "worker: deleted checkpoints of finished room"),
+                        Err(err) => warn!(%room_id, %err, "worker: failed to delete checkpoints"),
+                    },
+                }
+            }
+        });
+        (Self { tx }, writer)
+    }
+
+    /// Không chờ: queue đầy thì bỏ checkpoint và trả về false
+    pub fn save(&self, checkpoint: ProgressCheckpoint) -> bool {
+        self.push(CheckpointOp::Save(checkpoint))
+    }
+
+    /// Xoá checkpoint của room, sau mọi checkpoint đã xếp trước đó
+    pub fn delete_room(&self, room_id: &str) -> bool {
+        self.push(CheckpointOp::DeleteRoom(room_id.to_string()))
+    }
+
+    fn push(&self, op: CheckpointOp) -> bool {
+        match self.tx.try_send(op) {
+            Ok(()) => true,
+            Err(err) => {
+                warn!(%err, "worker: checkpoint queue full, dropping write");
+                false
+            }
+        }
+    }
+}
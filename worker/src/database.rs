@@ -8,9 +8,12 @@ use dashmap::DashMap;
 use once_cell::sync::Lazy;
 use std::sync::atomic::{AtomicU64, Ordering};
 
+use crate::checkpoint::ProgressCheckpoint;
+
 const POCKETBASE_URL: &str = "http://127.0.0.1:8090";
 const DEFAULT_EMAIL: &str = "admin@pocketbase.local";
 const DEFAULT_PASSWORD: &str = "123456789";
+const CHECKPOINTS_COLLECTION: &str = "player_checkpoints";
 
 // Performance optimizations
 const CACHE_TTL_SECONDS: u64 = 30; // Cache game state for 30 seconds
@@ -156,10 +159,54 @@ pub struct GameSessionRecord {
     pub updated: Option<String>,
 }
 
+/// Kết quả một trận đã kết thúc, ghi vào collection `match_results`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchResultRecord {
+    pub room_id: String,
+    pub game_mode: String,
+    pub players: Vec<MatchPlayerResult>,
+    pub duration_seconds: u64,
+    /// Unix timestamp in seconds
+    pub finished_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchPlayerResult {
+    pub player_id: String,
+    pub score: u32,
+    /// 1 = hạng nhất; bằng điểm thì cùng hạng
+    pub placement: u32,
+}
+
+impl MatchResultRecord {
+    /// Body gửi lên PocketBase. `player_ids` dạng `|a|b|` để services lọc theo player bằng `~`,
+    /// `finished_at` theo format datetime của PocketBase để so sánh chuỗi đúng thứ tự.
+    pub fn to_pocketbase_json(&self) -> Value {
+        let player_ids: String = self.players.iter().map(|p| format!("|{}", p.player_id)).collect();
+        let finished_at = chrono::DateTime::from_timestamp(self.finished_at as i64, 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%d %H:%M:%S%.3fZ")
+            .to_string();
+
+        json!({
+            "room_id": self.room_id,
+            "game_mode": self.game_mode,
+            "players": self.players,
+            "player_ids": format!("{}|", player_ids),
+            "duration_seconds": self.duration_seconds,
+            "finished_at": finished_at,
+        })
+    }
+}
+
 impl PocketBaseClient {
     pub fn new() -> Self {
+        Self::with_url(POCKETBASE_URL)
+    }
+
+    pub fn with_url(base_url: &str) -> Self {
         Self {
-            base_client: BasePocketBaseClient::new(POCKETBASE_URL),
+            base_client: BasePocketBaseClient::new(base_url),
         }
     }
 
@@ -280,6 +327,74 @@ impl PocketBaseClient {
         }
     }
 
+    pub async fn save_match_result(&self, result: &MatchResultRecord) -> Result<String> {
+        match self.base_client.create_record("match_results", result.to_pocketbase_json()).await {
+            Ok(record) => {
+                info!("Saved match result for room {} (ID: {})", result.room_id, record.id);
+                Ok(record.id)
+            }
+            Err(e) => {
+                METRICS.record_db_error();
+                error!("Failed to save match result for room {}: {}", result.room_id, e);
+                Err(anyhow!("Failed to save match result: {}", e))
+            }
+        }
+    }
+
+    /// Ghi checkpoint: cập nhật record của cặp (player, room) nếu đã có, ngược lại tạo mới
+    pub async fn save_checkpoint(&self, checkpoint: &ProgressCheckpoint) -> Result<()> {
+        let existing = self
+            .checkpoint_records("player_id", &checkpoint.player_id)
+            .await?
+            .into_iter()
+            .find(|record| record.fields.get("room_id").and_then(Value::as_str) == Some(checkpoint.room_id.as_str()));
+        let body = serde_json::to_value(checkpoint)?;
+        let saved = match existing {
+            Some(record) => self.base_client.update_record(CHECKPOINTS_COLLECTION, &record.id, body).await,
+            None => self.base_client.create_record(CHECKPOINTS_COLLECTION, body).await,
+        };
+        saved.map(|_| ()).map_err(|e| {
+            METRICS.record_db_error();
+            anyhow!("Failed to save checkpoint: {}", e)
+        })
+    }
+
+    /// Checkpoint của player ở mọi room
+    pub async fn player_checkpoints(&self, player_id: &str) -> Result<Vec<ProgressCheckpoint>> {
+        let records = self.checkpoint_records("player_id", player_id).await?;
+        Ok(records
+            .into_iter()
+            .filter_map(|record| serde_json::to_value(record.fields).ok())
+            .filter_map(|fields| serde_json::from_value(fields).ok())
+            .collect())
+    }
+
+    /// Xoá mọi checkpoint của room; trả về số record đã xoá
+    pub async fn delete_room_checkpoints(&self, room_id: &str) -> Result<usize> {
+        let records = self.checkpoint_records("room_id", room_id).await?;
+        for record in &records {
+            self.base_client
+                .delete_record(CHECKPOINTS_COLLECTION, &record.id)
+                .await
+                .map_err(|e| anyhow!("Failed to delete checkpoint {}: {}", record.id, e))?;
+        }
+        Ok(records.len())
+    }
+
+    /// Filter chỉ một điều kiện vì client không encode query (`&&` sẽ tách param); giá trị phải là key an toàn
+    async fn checkpoint_records(&self, field: &str, value: &str) -> Result<Vec<pocketbase::Record>> {
+        if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(anyhow!("invalid checkpoint key: {}", value));
+        }
+        self.base_client
+            .list_records(CHECKPOINTS_COLLECTION, Some(&format!("{}='{}'", field, value)), None)
+            .await
+            .map_err(|e| {
+                METRICS.record_db_error();
+                anyhow!("Failed to list checkpoints: {}", e)
+            })
+    }
+
     /// Get performance metrics for monitoring
     pub fn get_performance_metrics(&self) -> (u64, u64, u64, u64, u64) {
         METRICS.get_stats()
@@ -0,0 +1,48 @@
+//! Gửi điểm do simulation tính lên leaderboard của gateway; client không còn là nguồn điểm tin cậy
+
+use std::time::Duration;
+
+pub use common_net::scores::{ScoreSubmission, SCORES_PATH};
+
+use crate::room_manager_client::INTERNAL_SECRET_HEADER;
+use crate::BoxError;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct LeaderboardClient {
+    http: reqwest::Client,
+    base_url: String,
+    secret: String,
+}
+
+impl LeaderboardClient {
+    pub fn new(base_url: &str, secret: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            secret: secret.into(),
+        }
+    }
+
+    /// Gateway từ chối (room lạ, player không thuộc room, ...) thì trả lỗi kèm lý do
+    pub async fn submit(&self, submission: &ScoreSubmission) -> Result<(), BoxError> {
+        let response = self
+            .http
+            .post(format!("{}{}", self.base_url, SCORES_PATH))
+            .header(INTERNAL_SECRET_HEADER, &self.secret)
+            .json(submission)
+            .send()
+            .await?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        if !status.is_success() || body.get("success").and_then(|v| v.as_bool()) != Some(true) {
+            let error = body["error"]["message"].as_str().unwrap_or("unknown error");
+            return Err(format!("gateway returned {} for score of {}: {}", status, submission.player_id, error).into());
+        }
+        Ok(())
+    }
+}
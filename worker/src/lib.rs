@@ -1,20 +1,39 @@
 #![recursion_limit = "256"]
 
+use common_net::health::{self, DependencyStatus};
 use common_net::metrics::{self, SimulationMetrics};
-use std::{net::SocketAddr, sync::Arc};
-use tracing::info;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tracing::{info, warn};
 
 pub type BoxError = metrics::BoxError;
 
 const DEFAULT_METRICS_ADDR: &str = "127.0.0.1:3100";
 const DEFAULT_RPC_ADDR: &str = "127.0.0.1:50051";
 pub const METRICS_PATH: &str = "/metrics";
+/// Thời gian tối đa ghi kết quả room khi shutdown; quá hạn thì bỏ để process vẫn thoát được
+pub const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+/// Nhịp kiểm tra room đang chơi khi drain
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct WorkerSettings {
     pub rpc_addr: String,
     pub metrics_addr: String,
     pub fail_fast: bool,
+    #[serde(default)]
+    pub pocketbase_url: Option<String>,
+    #[serde(default = "default_keyframe_interval_ticks")]
+    pub keyframe_interval_ticks: u64,
+    #[serde(default)]
+    pub room_manager_url: Option<String>,
+    #[serde(default)]
+    pub leaderboard_url: Option<String>,
+    /// Token client gRPC phải gửi trong `x-internal-token`; None thì theo WORKER_RPC_TOKEN
+    #[serde(default)]
+    pub rpc_token: Option<String>,
+    /// TLS (và mTLS nếu có `client_ca_path`) cho gRPC; None là plaintext, chỉ dựa vào token
+    #[serde(default)]
+    pub rpc_tls: Option<rpc::RpcTlsSettings>,
 }
 impl Default for WorkerSettings {
     fn default() -> Self {
@@ -22,15 +41,55 @@ impl Default for WorkerSettings {
             rpc_addr: DEFAULT_RPC_ADDR.into(),
             metrics_addr: DEFAULT_METRICS_ADDR.into(),
             fail_fast: false,
+            pocketbase_url: None,
+            keyframe_interval_ticks: default_keyframe_interval_ticks(),
+            room_manager_url: None,
+            leaderboard_url: None,
+            rpc_token: None,
+            rpc_tls: None,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+fn default_keyframe_interval_ticks() -> u64 {
+    simulation::DEFAULT_KEYFRAME_INTERVAL_TICKS
+}
+
+fn env_keyframe_interval_ticks() -> u64 {
+    std::env::var("WORKER_KEYFRAME_INTERVAL_TICKS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|ticks| *ticks > 0)
+        .unwrap_or_else(default_keyframe_interval_ticks)
+}
+
+#[derive(Debug)]
 pub struct WorkerConfig {
     pub rpc_addr: SocketAddr,
     pub metrics_addr: SocketAddr,
     pub fail_fast: bool,
+    /// PocketBase để ghi match_results; None thì không lưu kết quả trận
+    pub pocketbase_url: Option<String>,
+    /// Snapshot Full bắt buộc sau mỗi ngần này tick
+    pub keyframe_interval_ticks: u64,
+    /// Room-manager nhận thông báo room Finished khi trận kết thúc; None thì không báo
+    pub room_manager_url: Option<String>,
+    /// Gateway nhận điểm cuối trận (và lúc chết ở endless runner) cho leaderboard; None thì không gửi
+    pub leaderboard_url: Option<String>,
+    /// Shared secret gửi kèm khi gọi room-manager và gateway; None thì theo ROOM_MANAGER_INTERNAL_SECRET
+    pub internal_secret: Option<String>,
+    /// Token nội bộ của gRPC; None thì theo WORKER_RPC_TOKEN
+    pub rpc_token: Option<String>,
+    /// TLS/mTLS cho gRPC; None là plaintext
+    pub rpc_tls: Option<rpc::RpcTlsSettings>,
+    /// Nhận địa chỉ metrics/health thật sau khi bind
+    pub ready_tx: Option<tokio::sync::oneshot::Sender<SocketAddr>>,
+    /// Nhận địa chỉ gRPC thật sau khi bind (cấu hình port 0)
+    pub rpc_ready_tx: Option<tokio::sync::oneshot::Sender<SocketAddr>>,
+    /// Tín hiệu bắt đầu drain: từ chối join/create room, room đang chơi tick tiếp tới khi kết thúc
+    pub drain_rx: Option<common_net::shutdown::ShutdownReceiver>,
+    /// Được trigger khi drain xong (không còn room đang chơi)
+    pub drained_tx: Option<common_net::shutdown::ShutdownSender>,
 }
 impl WorkerConfig {
     pub fn from_env() -> Result<Self, BoxError> {
@@ -38,6 +97,17 @@ impl WorkerConfig {
             rpc_addr: env_socket("WORKER_RPC_ADDR", DEFAULT_RPC_ADDR)?,
             metrics_addr: env_socket("WORKER_METRICS_ADDR", DEFAULT_METRICS_ADDR)?,
             fail_fast: std::env::var("WORKER_FAIL_FAST").ok().as_deref() == Some("1"),
+            pocketbase_url: std::env::var("WORKER_POCKETBASE_URL").ok(),
+            keyframe_interval_ticks: env_keyframe_interval_ticks(),
+            room_manager_url: std::env::var("WORKER_ROOM_MANAGER_URL").ok(),
+            leaderboard_url: std::env::var("WORKER_LEADERBOARD_URL").ok(),
+            internal_secret: None,
+            rpc_token: None,
+            rpc_tls: rpc::RpcTlsSettings::from_env(),
+            ready_tx: None,
+            rpc_ready_tx: None,
+            drain_rx: None,
+            drained_tx: None,
         })
     }
     pub fn from_settings(s: WorkerSettings) -> Result<Self, BoxError> {
@@ -48,6 +118,17 @@ impl WorkerConfig {
                 .parse()
                 .map_err(|e| Box::new(e) as BoxError)?,
             fail_fast: s.fail_fast,
+            pocketbase_url: s.pocketbase_url,
+            keyframe_interval_ticks: s.keyframe_interval_ticks,
+            room_manager_url: s.room_manager_url,
+            leaderboard_url: s.leaderboard_url,
+            internal_secret: None,
+            rpc_token: s.rpc_token,
+            rpc_tls: s.rpc_tls,
+            ready_tx: None,
+            rpc_ready_tx: None,
+            drain_rx: None,
+            drained_tx: None,
         })
     }
 }
@@ -60,6 +141,12 @@ impl WorkerSettings {
             metrics_addr: std::env::var("WORKER_METRICS_ADDR")
                 .unwrap_or_else(|_| DEFAULT_METRICS_ADDR.to_string()),
             fail_fast: std::env::var("WORKER_FAIL_FAST").ok().as_deref() == Some("1"),
+            pocketbase_url: std::env::var("WORKER_POCKETBASE_URL").ok(),
+            keyframe_interval_ticks: env_keyframe_interval_ticks(),
+            room_manager_url: std::env::var("WORKER_ROOM_MANAGER_URL").ok(),
+            leaderboard_url: std::env::var("WORKER_LEADERBOARD_URL").ok(),
+            rpc_token: None,
+            rpc_tls: rpc::RpcTlsSettings::from_env(),
         })
     }
 }
@@ -86,15 +173,81 @@ pub async fn run(
 ) -> Result<(), BoxError> {
     simulation_metrics().on_startup();
 
-    let _metrics_task =
-        metrics::spawn_metrics_exporter(config.metrics_addr, METRICS_PATH, "worker");
-
-    let state = Arc::new(crate::rpc::WorkerState::default());
+    let mut state = crate::rpc::WorkerState::default();
+    let mut checkpoint_writer = None;
+    if let Some(url) = &config.pocketbase_url {
+        pocketbase::migrations::bootstrap(&pocketbase::PocketBaseClient::new(url)).await?;
+        let store = crate::database::PocketBaseClient::with_url(url);
+        let (queue, writer) = checkpoint::CheckpointQueue::spawn(store.clone());
+        checkpoint_writer = Some(writer);
+        state = state.with_match_store(store).with_checkpoint_queue(queue);
+    }
+    let internal_secret = config.internal_secret.clone().or_else(room_manager_client::internal_secret_from_env);
+    if let Some(url) = &config.room_manager_url {
+        state = state.with_room_manager_client(room_manager_client::RoomManagerClient::new(url, internal_secret.clone()));
+    }
+    if let Some(url) = &config.leaderboard_url {
+        // Gateway từ chối mọi điểm không kèm secret, gửi không có secret chỉ làm mất điểm trong im lặng
+        let secret = internal_secret.ok_or("WORKER_LEADERBOARD_URL needs ROOM_MANAGER_INTERNAL_SECRET")?;
+        state = state.with_leaderboard_client(leaderboard_client::LeaderboardClient::new(url, secret));
+    }
+    state.game_world.get_mut().set_keyframe_policy(simulation::KeyframePolicy {
+        interval_ticks: config.keyframe_interval_ticks,
+        ..Default::default()
+    });
+    let state = Arc::new(state);
     let svc = crate::rpc::WorkerService::new(state.clone());
+    // File cert/key lỗi thì dừng ngay thay vì chạy plaintext
+    let mut rpc_security = match config.rpc_token.clone() {
+        Some(token) => crate::rpc::RpcSecurity::new(token),
+        None => crate::rpc::RpcSecurity::from_env(),
+    };
+    // Không có token mà nghe ngoài loopback thì dừng thay vì mở gRPC cho cả mạng
+    rpc_security.check_bind(config.rpc_addr)?;
+    if rpc_security.token.is_none() {
+        warn!("worker: WORKER_RPC_TOKEN not set, gRPC on loopback accepts calls without a token");
+    }
+    if let Some(tls) = &config.rpc_tls {
+        rpc_security = rpc_security.with_tls(crate::rpc::ServerTls::load(tls)?);
+    } else {
+        info!("worker: gRPC without TLS, clients are authenticated by the internal token only");
+    }
+
+    // Bind gRPC trước khi báo ready để client gọi được ngay khi nhận địa chỉ
+    let rpc_listener = tokio::net::TcpListener::bind(config.rpc_addr)
+        .await
+        .map_err(|err| Box::new(err) as BoxError)?;
+    let rpc_addr = rpc_listener.local_addr().map_err(|err| Box::new(err) as BoxError)?;
+    if let Some(rpc_ready_tx) = config.rpc_ready_tx {
+        let _ = rpc_ready_tx.send(rpc_addr);
+    }
 
-    info!(addr = %config.rpc_addr, "worker: starting gRPC");
+    let _metrics_task = match config.ready_tx {
+        Some(ready_tx) => {
+            // Bind trước để báo đúng địa chỉ khi cấu hình port 0
+            let listener = tokio::net::TcpListener::bind(config.metrics_addr)
+                .await
+                .map_err(|err| Box::new(err) as BoxError)?;
+            let metrics_addr = listener.local_addr().map_err(|err| Box::new(err) as BoxError)?;
+            let _ = ready_tx.send(metrics_addr);
+            let readiness = readiness(state.clone());
+            tokio::spawn(async move {
+                if let Err(err) = metrics::serve_metrics_with_readiness(listener, METRICS_PATH, readiness).await {
+                    tracing::error!(%err, %metrics_addr, "worker: metrics exporter dừng bất thường");
+                }
+            })
+        }
+        None => metrics::spawn_metrics_exporter(
+            config.metrics_addr,
+            METRICS_PATH,
+            "worker",
+            readiness(state.clone()),
+        ),
+    };
+
+    info!(addr = %rpc_addr, "worker: starting gRPC");
     let grpc_task = tokio::spawn(async move {
-        crate::rpc::serve_rpc(config.rpc_addr, svc).await;
+        crate::rpc::serve_rpc_on(rpc_listener, svc, rpc_security).await;
     });
 
     // Room manager cleanup task
@@ -105,19 +258,156 @@ pub async fn run(
             interval.tick().await;
 
             let mut room_manager = cleanup_state.room_manager.write().await;
-            room_manager.cleanup();
+            let removed = room_manager.cleanup();
             drop(room_manager);
+            for room_id in &removed {
+                cleanup_state.room_events.close(room_id);
+            }
 
             tracing::debug!("Room manager cleanup completed");
         }
     });
 
+    // Countdown auto-start và ready timeout tính theo giây; đồng hồ trận cộng đủ số tick của một giây.
+    // Checkpoint điểm cũng xét mỗi giây, room nào đủ interval thì xếp vào queue ghi
+    let ready_state = state.clone();
+    let ready_task = tokio::spawn(async move {
+        let ticks_per_second = ready_state.game_world.read().await.ticks_per_second();
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            ready_state.run_ready_checks(now).await;
+            ready_state.run_match_checks(ticks_per_second).await;
+            ready_state.run_checkpoints(now).await;
+        }
+    });
+
+    // Tóm tắt hiệu quả delta encoding của từng room
+    let encoding_state = state.clone();
+    let encoding_task = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(10));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut last = std::collections::HashMap::new();
+        loop {
+            interval.tick().await;
+            encoding_state.log_encoding_summary(&mut last).await;
+        }
+    });
+
+    // Simulation chỉ tick khi có input; room chỉ có bot thì tự tick để bot vẫn chạy.
+    // Nhịp đọc lại mỗi vòng vì room adaptive có thể hạ/nâng tick rate
+    let bot_state = state.clone();
+    let bot_task = tokio::spawn(async move {
+        loop {
+            let tick_rate = bot_state.game_world.read().await.tick_rate;
+            tokio::time::sleep(tick_rate).await;
+
+            let mut game_world = bot_state.game_world.write().await;
+            if game_world.bots.is_empty() {
+                continue;
+            }
+            let started = std::time::Instant::now();
+            game_world.tick();
+            bot_state.room_events.snapshot_ready(game_world.get_current_tick());
+            drop(game_world);
+            bot_state.record_tick_cost(started.elapsed()).await;
+        }
+    });
+
+    // Drain chỉ đổi cách nhận request; RPC và tick vẫn chạy tới khi nhận shutdown
+    let drain_task = config.drain_rx.map(|drain_rx| {
+        let drain_state = state.clone();
+        let drained_tx = config.drained_tx;
+        tokio::spawn(async move {
+            common_net::shutdown::wait(drain_rx).await;
+            drain(&drain_state).await;
+            if let Some(drained_tx) = drained_tx {
+                common_net::shutdown::trigger(&drained_tx);
+            }
+        })
+    });
+
     common_net::shutdown::wait(shutdown_rx).await;
+    if let Some(drain_task) = drain_task {
+        drain_task.abort();
+    }
+    // Dừng RPC và tick trước để score không đổi trong lúc ghi
     grpc_task.abort();
     cleanup_task.abort();
+    ready_task.abort();
+    encoding_task.abort();
+    bot_task.abort();
+    // Checkpoint còn trong queue không cần nữa: kết quả trận được ghi ngay sau đây
+    if let Some(writer) = checkpoint_writer {
+        writer.abort();
+    }
+    flush_on_shutdown(&state, SHUTDOWN_FLUSH_TIMEOUT).await;
     Ok(())
 }
 
+/// `/readyz` của worker: lock game world và room manager phải lấy được; PocketBase (nếu có cấu hình) chỉ là degraded
+pub fn readiness(state: Arc<crate::rpc::WorkerState>) -> health::Readiness {
+    let world_state = state.clone();
+    let rooms_state = state.clone();
+    let readiness = health::Readiness::new("worker")
+        .required("game_world", move || {
+            let state = world_state.clone();
+            async move {
+                drop(state.game_world.read().await);
+                DependencyStatus::Ok
+            }
+        })
+        .required("room_manager", move || {
+            let state = rooms_state.clone();
+            async move {
+                drop(state.room_manager.read().await);
+                DependencyStatus::Ok
+            }
+        });
+    match state.match_store.clone() {
+        Some(store) => readiness.optional("pocketbase", move || {
+            let store = store.clone();
+            async move {
+                match store.test_connection().await {
+                    Ok(true) => DependencyStatus::Ok,
+                    _ => DependencyStatus::Down,
+                }
+            }
+        }),
+        None => readiness,
+    }
+}
+
+/// Bật drain rồi chờ tới khi không còn room đang chơi; thời gian tối đa do caller quyết định
+pub async fn drain(state: &crate::rpc::WorkerState) {
+    state.begin_drain();
+    info!("worker: bắt đầu drain, từ chối join mới");
+    while state.has_playing_rooms().await {
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+    info!("worker: drain xong, không còn room đang chơi");
+}
+
+/// Ghi kết quả các room đang chơi xuống PocketBase, tối đa `timeout`
+pub async fn flush_on_shutdown(state: &crate::rpc::WorkerState, timeout: Duration) -> usize {
+    match tokio::time::timeout(timeout, state.persist_active_rooms()).await {
+        Ok(saved) => {
+            info!(saved, "worker: persisted active rooms on shutdown");
+            saved
+        }
+        Err(_) => {
+            warn!(?timeout, "worker: shutdown flush timed out, unsaved room results dropped");
+            0
+        }
+    }
+}
+
 fn env_socket(key: &str, default: &str) -> Result<SocketAddr, BoxError> {
     let value = std::env::var(key).unwrap_or_else(|_| default.to_string());
     Ok(value.parse().map_err(|err| Box::new(err) as BoxError)?)
@@ -129,6 +419,18 @@ pub mod simulation;
 pub mod database;
 pub mod validation;
 pub mod room;
+pub mod room_manager_client;
+pub mod leaderboard_client;
+pub mod spawn;
+pub mod bots;
+pub mod steering;
+pub mod tick_rate;
+pub mod runner_track;
+pub mod body_pool;
+pub mod overload;
+pub mod room_events;
+pub mod system_message;
+pub mod checkpoint;
 
 #[cfg(test)]
 mod tests {
@@ -211,14 +513,10 @@ mod tests {
     #[tokio::test]
     async fn test_end_to_end_client_worker_integration() {
         use proto::worker::v1::{worker_client::WorkerClient, JoinRoomRequest, PushInputRequest};
-        use std::time::Duration;
 
         // Start test worker server
         let (endpoint, server_handle) = crate::rpc::spawn_test_server().await;
 
-        // Give server time to start
-        tokio::time::sleep(Duration::from_millis(100)).await;
-
         // Create client connection
         let mut client = crate::rpc::client(&endpoint).expect("Failed to create client");
 
@@ -228,6 +526,7 @@ mod tests {
             .join_room(JoinRoomRequest {
                 room_id: "test_room".to_string(),
                 player_id: "test_player".to_string(),
+                player_name: String::new(),
             })
             .await
             .expect("Failed to join room");
@@ -247,6 +546,7 @@ mod tests {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64,
+            actions: Default::default(),
         };
 
         let input_json = serde_json::to_string(&input).unwrap();
@@ -285,6 +585,7 @@ mod tests {
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
                     .as_millis() as u64,
+                actions: Default::default(),
             };
 
             let input_json = serde_json::to_string(&input).unwrap();
@@ -320,14 +621,10 @@ mod tests {
     #[tokio::test]
     async fn test_input_processing_end_to_end() {
         use proto::worker::v1::{worker_client::WorkerClient, JoinRoomRequest, PushInputRequest};
-        use std::time::Duration;
 
         // Start test worker server
         let (endpoint, server_handle) = crate::rpc::spawn_test_server().await;
 
-        // Give server time to start
-        tokio::time::sleep(Duration::from_millis(100)).await;
-
         // Create client connection
         let mut client = crate::rpc::client(&endpoint).expect("Failed to create client");
 
@@ -336,6 +633,7 @@ mod tests {
             .join_room(JoinRoomRequest {
                 room_id: "test_room".to_string(),
                 player_id: "test_player".to_string(),
+                player_name: String::new(),
             })
             .await
             .expect("Failed to join room");
@@ -354,6 +652,7 @@ mod tests {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64,
+            actions: Default::default(),
         };
 
         let initial_input_json = serde_json::to_string(&initial_input).unwrap();
@@ -401,6 +700,7 @@ mod tests {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64,
+            actions: Default::default(),
         };
 
         let move_right_json = serde_json::to_string(&move_right_input).unwrap();
@@ -415,6 +715,7 @@ mod tests {
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
                     .as_millis() as u64,
+                actions: Default::default(),
             };
 
             let input_json = serde_json::to_string(&input).unwrap();
@@ -474,6 +775,7 @@ mod tests {
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
                     .as_millis() as u64,
+                actions: Default::default(),
             };
 
             let input_json = serde_json::to_string(&input).unwrap();
@@ -508,6 +810,80 @@ mod tests {
         println!("✓ Input processing end-to-end test completed successfully");
     }
 
+    #[tokio::test]
+    async fn add_bots_and_backfill_over_rpc() {
+        use proto::worker::v1::{
+            AddBotsRequest, CreateRoomRequest, GetRoomInfoRequest, JoinRoomAsPlayerRequest, RoomSettings,
+        };
+
+        let (endpoint, server_handle) = crate::rpc::spawn_test_server().await;
+        let mut client = crate::rpc::client(&endpoint).expect("client");
+
+        let room_id = client
+            .create_room(CreateRoomRequest {
+                room_name: "bots".to_string(),
+                host_id: "host".to_string(),
+                host_name: "Host".to_string(),
+                settings: Some(RoomSettings {
+                    max_players: 4,
+                    min_players_to_start: 3,
+                    backfill_with_bots: true,
+                    ..Default::default()
+                }),
+            })
+            .await
+            .expect("create room")
+            .into_inner()
+            .room_id;
+
+        let player_count = |client: &mut crate::rpc::Client| {
+            let mut client = client.clone();
+            let room_id = room_id.clone();
+            async move {
+                client
+                    .get_room_info(GetRoomInfoRequest { room_id })
+                    .await
+                    .expect("room info")
+                    .into_inner()
+                    .room
+                    .expect("room")
+                    .player_count
+            }
+        };
+        // Host + 2 bot backfill
+        assert_eq!(player_count(&mut client).await, 3);
+
+        let add = |count: u32, difficulty: &str| AddBotsRequest {
+            room_id: room_id.clone(),
+            count,
+            difficulty: difficulty.to_string(),
+        };
+        let invalid = client.add_bots(add(1, "nightmare")).await.unwrap().into_inner();
+        assert!(!invalid.success);
+
+        // Chỉ còn 1 chỗ: thêm được 1 bot kèm lỗi room đầy
+        let added = client.add_bots(add(5, "hard")).await.unwrap().into_inner();
+        assert!(added.success);
+        assert_eq!(added.bot_ids.len(), 1);
+        assert!(!added.error.is_empty());
+        assert_eq!(player_count(&mut client).await, 4);
+
+        // Player thật vẫn vào được room đầy bot
+        let joined = client
+            .join_room_as_player(JoinRoomAsPlayerRequest {
+                room_id: room_id.clone(),
+                player_id: "p2".to_string(),
+                player_name: "P2".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(joined.success, "{}", joined.error);
+        assert_eq!(player_count(&mut client).await, 4);
+
+        server_handle.abort();
+    }
+
     #[test]
     fn test_comprehensive_game_simulation() {
         // Comprehensive test với tất cả các loại entities
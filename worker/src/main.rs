@@ -131,10 +131,11 @@ async fn main() {
 
             let total_frames = FRAME_COUNT.load(Ordering::Relaxed);
             let total_syncs = DB_SYNC_COUNT.load(Ordering::Relaxed);
+            let encoding = game_world.encoding_stats();
 
             // Use info level for better visibility during testing
             tracing::info!(
-                "PERF STATS - Frames: {}, Syncs: {}, Cache: {}/{}/{}, DB: {}/{}/{}ms, Hit Rate: {:.2}%",
+                "PERF STATS - Frames: {}, Syncs: {}, Cache: {}/{}/{}, DB: {}/{}/{}ms, Hit Rate: {:.2}%, Snapshots: {} delta/{} full, Delta: {:.0}/{:.0}B ({:.1}%)",
                 total_frames, total_syncs,
                 games_cached, players_cached, sessions_cached,
                 db_queries, db_errors, avg_query_time,
@@ -142,7 +143,10 @@ async fn main() {
                     (cache_hits as f64 / (cache_hits + cache_misses) as f64) * 100.0
                 } else {
                     0.0
-                }
+                },
+                encoding.deltas_sent, encoding.fulls_sent(),
+                encoding.average_delta_bytes(), encoding.average_full_bytes(),
+                encoding.delta_ratio() * 100.0
             );
         }
 
@@ -163,10 +167,11 @@ async fn main() {
 
             let total_frames = FRAME_COUNT.load(Ordering::Relaxed);
             let total_syncs = DB_SYNC_COUNT.load(Ordering::Relaxed);
+            let encoding = game_world.encoding_stats();
 
             // Use info level for better visibility during testing
             tracing::info!(
-                "PERF STATS - Frames: {}, Syncs: {}, Cache: {}/{}/{}, DB: {}/{}/{}ms, Hit Rate: {:.2}%",
+                "PERF STATS - Frames: {}, Syncs: {}, Cache: {}/{}/{}, DB: {}/{}/{}ms, Hit Rate: {:.2}%, Snapshots: {} delta/{} full, Delta: {:.0}/{:.0}B ({:.1}%)",
                 total_frames, total_syncs,
                 games_cached, players_cached, sessions_cached,
                 db_queries, db_errors, avg_query_time,
@@ -174,7 +179,10 @@ async fn main() {
                     (cache_hits as f64 / (cache_hits + cache_misses) as f64) * 100.0
                 } else {
                     0.0
-                }
+                },
+                encoding.deltas_sent, encoding.fulls_sent(),
+                encoding.average_delta_bytes(), encoding.average_full_bytes(),
+                encoding.delta_ratio() * 100.0
             );
         }
     }
@@ -0,0 +1,189 @@
+//! Bảo vệ ngân sách CPU của tick: đo EWMA thời gian `fixed_update`, vượt ngưỡng so với tick budget thì
+//! giảm tải từng bậc theo thứ tự cố định, tải hạ xuống thì khôi phục lại từng bậc.
+
+use std::time::Duration;
+
+/// Mỗi bậc giữ nguyên mọi cắt giảm của các bậc thấp hơn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum DegradationLevel {
+    #[default]
+    Normal = 0,
+    /// AI của enemy chỉ chạy mỗi 2 tick
+    HalfRateEnemyAi = 1,
+    /// AOI của player cập nhật thưa hơn
+    SlowAoi = 2,
+    /// Endless runner vẫn đẩy con trỏ track nhưng không spawn obstacle
+    SkipObstacleGeneration = 3,
+    /// Entity phải dịch nhiều hơn mới được đưa vào delta
+    CoarseDeltas = 4,
+}
+
+impl DegradationLevel {
+    pub fn as_i64(self) -> i64 {
+        self as i64
+    }
+
+    fn from_index(index: i64) -> Self {
+        match index {
+            i64::MIN..=0 => Self::Normal,
+            1 => Self::HalfRateEnemyAi,
+            2 => Self::SlowAoi,
+            3 => Self::SkipObstacleGeneration,
+            _ => Self::CoarseDeltas,
+        }
+    }
+
+    fn up(self) -> Self {
+        Self::from_index(self.as_i64() + 1)
+    }
+
+    fn down(self) -> Self {
+        Self::from_index(self.as_i64() - 1)
+    }
+
+    /// Số tick giữa hai lần chạy steering của enemy
+    pub fn enemy_ai_interval(self) -> u64 {
+        if self >= Self::HalfRateEnemyAi {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Số tick giữa hai lần tính lại visible cells của một player
+    pub fn aoi_update_interval(self) -> u64 {
+        if self >= Self::SlowAoi {
+            30
+        } else {
+            10
+        }
+    }
+
+    pub fn spawns_obstacles(self) -> bool {
+        self < Self::SkipObstacleGeneration
+    }
+
+    /// Độ lệch position đã quantize (đơn vị i16) tối thiểu để entity tính là đã đổi trong delta
+    pub fn position_change_threshold(self) -> i32 {
+        if self >= Self::CoarseDeltas {
+            4
+        } else {
+            1
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OverloadPolicy {
+    /// EWMA vượt tỉ lệ này của tick budget thì tăng một bậc
+    pub degrade_fraction: f64,
+    /// EWMA dưới tỉ lệ này của tick budget thì giảm một bậc
+    pub recover_fraction: f64,
+    /// Trọng số của mẫu mới trong EWMA
+    pub ewma_alpha: f64,
+    /// Số tick tối thiểu giữa hai lần đổi bậc, tránh nhảy bậc liên tục
+    pub cooldown_ticks: u64,
+}
+
+impl Default for OverloadPolicy {
+    fn default() -> Self {
+        Self {
+            degrade_fraction: 0.8,
+            recover_fraction: 0.5,
+            ewma_alpha: 0.1,
+            cooldown_ticks: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OverloadController {
+    pub policy: OverloadPolicy,
+    ewma_secs: f64,
+    level: DegradationLevel,
+    last_change_tick: u64,
+}
+
+impl OverloadController {
+    pub fn new(policy: OverloadPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    pub fn level(&self) -> DegradationLevel {
+        self.level
+    }
+
+    /// EWMA thời gian chạy `fixed_update`
+    pub fn ewma(&self) -> Duration {
+        Duration::from_secs_f64(self.ewma_secs)
+    }
+
+    /// Ghi thời gian một `fixed_update` so với `budget` (tick rate); trả về (bậc cũ, bậc mới) khi đổi bậc
+    pub fn observe(
+        &mut self,
+        elapsed: Duration,
+        budget: Duration,
+        tick: u64,
+    ) -> Option<(DegradationLevel, DegradationLevel)> {
+        let sample = elapsed.as_secs_f64();
+        self.ewma_secs = if self.ewma_secs == 0.0 {
+            sample
+        } else {
+            self.policy.ewma_alpha * sample + (1.0 - self.policy.ewma_alpha) * self.ewma_secs
+        };
+
+        if tick.saturating_sub(self.last_change_tick) < self.policy.cooldown_ticks {
+            return None;
+        }
+        let load = self.ewma_secs / budget.as_secs_f64().max(f64::EPSILON);
+        let next = if load > self.policy.degrade_fraction {
+            self.level.up()
+        } else if load < self.policy.recover_fraction {
+            self.level.down()
+        } else {
+            self.level
+        };
+        if next == self.level {
+            return None;
+        }
+
+        let previous = self.level;
+        self.level = next;
+        self.last_change_tick = tick;
+        Some((previous, next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_steps_one_at_a_time_with_cooldown_and_recovers() {
+        let mut controller = OverloadController::new(OverloadPolicy {
+            ewma_alpha: 1.0,
+            cooldown_ticks: 2,
+            ..OverloadPolicy::default()
+        });
+        let budget = Duration::from_millis(16);
+        let slow = Duration::from_millis(15);
+
+        let mut levels = Vec::new();
+        for tick in 2..=12 {
+            controller.observe(slow, budget, tick);
+            levels.push(controller.level().as_i64());
+        }
+        // Mỗi 2 tick lên một bậc, dừng ở bậc cao nhất
+        assert_eq!(levels, [1, 1, 2, 2, 3, 3, 4, 4, 4, 4, 4]);
+
+        // Tải giữa hai ngưỡng thì giữ nguyên bậc
+        assert_eq!(controller.observe(Duration::from_millis(10), budget, 20), None);
+        assert_eq!(
+            controller.observe(Duration::from_millis(1), budget, 22),
+            Some((DegradationLevel::CoarseDeltas, DegradationLevel::SkipObstacleGeneration))
+        );
+    }
+}
@@ -1,9 +1,15 @@
+use common_net::game_modes::{self, GameModeDescriptor, ScoringType};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::Duration;
 use tracing::info;
 use uuid::Uuid;
 
+use crate::checkpoint::DEFAULT_CHECKPOINT_INTERVAL_SECONDS;
+use crate::database::{MatchPlayerResult, MatchResultRecord};
+use crate::leaderboard_client::ScoreSubmission;
+use crate::tick_rate::{self, TickRateGovernor, TickRatePolicy};
+
 /// Room state enum
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RoomState {
@@ -21,6 +27,30 @@ pub enum GameMode {
     TeamDeathmatch, // Chia đội
     CaptureTheFlag, // Cướp cờ
     KingOfTheHill,  // Vua đồi
+    EndlessRunner,  // Chạy tới khi chết, mỗi player kết thúc riêng
+}
+
+impl GameMode {
+    /// Tên dạng snake_case dùng khi lưu ra PocketBase/services
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameMode::Deathmatch => "deathmatch",
+            GameMode::TeamDeathmatch => "team_deathmatch",
+            GameMode::CaptureTheFlag => "capture_the_flag",
+            GameMode::KingOfTheHill => "king_of_the_hill",
+            GameMode::EndlessRunner => "endless_runner",
+        }
+    }
+
+    /// Descriptor của mode trong registry dùng chung (sức chứa, kiểu tính điểm, settings mặc định)
+    pub fn descriptor(&self) -> Option<GameModeDescriptor> {
+        game_modes::from_id(self.as_str())
+    }
+
+    /// Cách mode tính điểm theo registry
+    pub fn scoring(&self) -> Option<ScoringType> {
+        self.descriptor().map(|mode| mode.scoring)
+    }
 }
 
 /// Room settings
@@ -35,6 +65,94 @@ pub struct RoomSettings {
     pub allow_spectators: bool,
     pub auto_start: bool,
     pub min_players_to_start: u32,
+    /// Room thiếu người thì lấp bằng bot tới `min_players_to_start`
+    #[serde(default)]
+    pub backfill_with_bots: bool,
+    /// Mất kết nối quá số giây này mà chưa rejoin thì entity của player bị despawn
+    #[serde(default = "default_rejoin_grace_seconds")]
+    pub rejoin_grace_seconds: u32,
+    /// Room auto-start: player chưa ready sau số giây này (tính từ lúc join hoặc room về Waiting) bị kick
+    #[serde(default = "default_ready_timeout_seconds")]
+    pub ready_timeout_seconds: u32,
+    /// Deathmatch/team deathmatch kết thúc khi có player đạt số điểm này; None = chỉ tính giờ
+    #[serde(default)]
+    pub score_target: Option<u32>,
+    /// Cho phép worker hạ tick rate của room khi quá tải
+    #[serde(default)]
+    pub adaptive_tick_rate: bool,
+    /// Tick rate gốc của room (Hz), trong khoảng `MIN_TICK_HZ..=MAX_TICK_HZ`; chỉ đổi được trước khi trận bắt đầu
+    #[serde(default = "default_tick_hz")]
+    pub tick_hz: u32,
+    /// Seed sinh obstacle/pickup/power-up; None thì room tự chọn ngẫu nhiên lúc tạo và ghi lại ở đây
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Message of the day, gửi riêng cho player khi vào room
+    #[serde(default)]
+    pub motd: Option<String>,
+    /// Số giây giữa hai lần checkpoint điểm của player (mode tính điểm theo quãng đường)
+    #[serde(default = "default_checkpoint_interval_seconds")]
+    pub checkpoint_interval_seconds: u32,
+    /// Player mới vào room được khôi phục điểm từ checkpoint tốt nhất cùng mode
+    #[serde(default)]
+    pub resume_progress: bool,
+    /// Settings JSON tự do gửi kèm lúc tạo room, vd. `{"spawn_points": [[x,y,z], ...]}`; Null nếu không có
+    #[serde(default)]
+    pub custom: serde_json::Value,
+}
+
+/// Hai đội của mode theo đội (team deathmatch, cướp cờ)
+pub const TEAMS: [&str; 2] = ["red", "blue"];
+
+pub const DEFAULT_REJOIN_GRACE_SECONDS: u32 = 60;
+pub const DEFAULT_READY_TIMEOUT_SECONDS: u32 = 60;
+/// Thời gian đếm ngược từ lúc mọi người ready tới lúc vào Playing
+pub const AUTO_START_COUNTDOWN_SECONDS: u64 = 5;
+
+fn default_checkpoint_interval_seconds() -> u32 {
+    DEFAULT_CHECKPOINT_INTERVAL_SECONDS
+}
+
+fn default_tick_hz() -> u32 {
+    tick_rate::BASE_TICK_HZ
+}
+
+fn default_rejoin_grace_seconds() -> u32 {
+    DEFAULT_REJOIN_GRACE_SECONDS
+}
+
+fn default_ready_timeout_seconds() -> u32 {
+    DEFAULT_READY_TIMEOUT_SECONDS
+}
+
+pub(crate) fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn rank_players(mut players: Vec<&RoomPlayer>) -> Vec<MatchPlayerResult> {
+    players.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+
+    let mut results: Vec<MatchPlayerResult> = Vec::with_capacity(players.len());
+    for (index, player) in players.iter().enumerate() {
+        let placement = match results.last() {
+            Some(prev) if prev.score == player.score => prev.placement,
+            _ => index as u32 + 1,
+        };
+        results.push(MatchPlayerResult {
+            player_id: player.id.clone(),
+            score: player.score,
+            placement,
+        });
+    }
+    results
+}
+
+impl RoomSettings {
+    pub fn rejoin_grace(&self) -> Duration {
+        Duration::from_secs(self.rejoin_grace_seconds as u64)
+    }
 }
 
 impl Default for RoomSettings {
@@ -49,6 +167,17 @@ impl Default for RoomSettings {
             allow_spectators: true,
             auto_start: true,
             min_players_to_start: 2,
+            backfill_with_bots: false,
+            rejoin_grace_seconds: DEFAULT_REJOIN_GRACE_SECONDS,
+            ready_timeout_seconds: DEFAULT_READY_TIMEOUT_SECONDS,
+            score_target: None,
+            adaptive_tick_rate: false,
+            tick_hz: tick_rate::BASE_TICK_HZ,
+            seed: None,
+            motd: None,
+            checkpoint_interval_seconds: DEFAULT_CHECKPOINT_INTERVAL_SECONDS,
+            resume_progress: false,
+            custom: serde_json::Value::Null,
         }
     }
 }
@@ -65,6 +194,8 @@ pub struct RoomPlayer {
     pub score: u32,
     pub ping: u32, // milliseconds
     pub last_seen: u64, // Unix timestamp in seconds
+    #[serde(default)]
+    pub is_bot: bool,
 }
 
 impl RoomPlayer {
@@ -84,6 +215,16 @@ impl RoomPlayer {
             score: 0,
             ping: 0,
             last_seen: now,
+            is_bot: false,
+        }
+    }
+
+    pub fn bot(id: String) -> Self {
+        let name = format!("Bot {}", id.trim_start_matches(crate::bots::BOT_ID_PREFIX));
+        Self {
+            is_bot: true,
+            is_ready: true,
+            ..Self::new(id, name, false)
         }
     }
 
@@ -141,18 +282,62 @@ pub struct Room {
     pub ended_at: Option<u64>, // Unix timestamp in seconds
     pub password_hash: Option<String>, // Hashed password for private rooms
     pub game_world_id: Option<String>, // Link to game world instance
+    /// Lúc room vào Waiting gần nhất; hạn ready của player tính từ đây hoặc từ lúc join
+    #[serde(default)]
+    pub waiting_since: u64,
+    /// Room đang Starting vì mọi người đã ready: thời điểm hết countdown
+    #[serde(default)]
+    pub countdown_ends_at: Option<u64>,
+    /// Số tick đã chơi từ lúc vào Playing, dùng cho `time_limit`
+    #[serde(default)]
+    pub match_ticks: u64,
+    /// Endless runner: player đã chết, không còn tính là đang chơi
+    #[serde(default)]
+    pub finished_players: Vec<String>,
+    /// Số player đầu của `finished_players` đã được gửi điểm lúc chết lên leaderboard
+    #[serde(default)]
+    pub submitted_deaths: usize,
+    /// Quyết định tick rate hiệu lực khi bật `adaptive_tick_rate`
+    #[serde(skip)]
+    pub tick_governor: TickRateGovernor,
+}
+
+/// Lý do trận kết thúc, đi kèm event `match_ended`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchEndReason {
+    TimeLimit,
+    ScoreTarget,
+    AllPlayersFinished,
+}
+
+impl MatchEndReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchEndReason::TimeLimit => "time_limit",
+            MatchEndReason::ScoreTarget => "score_target",
+            MatchEndReason::AllPlayersFinished => "all_players_finished",
+        }
+    }
 }
 
 impl Room {
-    pub fn new(name: String, host_id: String, host_name: String, settings: RoomSettings) -> Self {
+    pub fn new(name: String, host_id: String, host_name: String, mut settings: RoomSettings) -> Self {
         let id = Uuid::new_v4().to_string();
+        settings.seed.get_or_insert_with(rand::random);
+        settings.tick_hz = tick_rate::clamp_tick_hz(settings.tick_hz);
+        let tick_governor = TickRateGovernor::new(TickRatePolicy::with_base_hz(settings.tick_hz));
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
 
+        let mut host = RoomPlayer::new(host_id.clone(), host_name, true);
+        if settings.game_mode.descriptor().is_some_and(|mode| mode.team_based) {
+            host.team = Some(TEAMS[0].to_string());
+        }
         let mut players = HashMap::new();
-        players.insert(host_id.clone(), RoomPlayer::new(host_id.clone(), host_name, true));
+        players.insert(host_id.clone(), host);
 
         Self {
             id,
@@ -167,6 +352,12 @@ impl Room {
             ended_at: None,
             password_hash: None,
             game_world_id: None,
+            waiting_since: now,
+            countdown_ends_at: None,
+            match_ticks: 0,
+            finished_players: Vec::new(),
+            submitted_deaths: 0,
+            tick_governor,
         }
     }
 
@@ -195,24 +386,95 @@ impl Room {
         self.can_join(&player_id)?;
 
         let player_id_clone = player_id.clone();
-        let player = RoomPlayer::new(player_id, player_name, false);
+        let mut player = RoomPlayer::new(player_id, player_name, false);
+        player.team = self.next_team();
         self.players.insert(player_id_clone.clone(), player);
 
         info!("Player {} joined room {}", player_id_clone, self.id);
         Ok(())
     }
 
+    /// Thêm bot; khác player thật ở chỗ được vào cả room đang chơi
+    pub fn add_bot(&mut self, bot_id: String) -> Result<(), RoomError> {
+        if matches!(self.state, RoomState::Finished | RoomState::Closed) {
+            return Err(RoomError::RoomNotAcceptingPlayers);
+        }
+        if self.players.len() >= self.settings.max_players as usize {
+            return Err(RoomError::RoomFull);
+        }
+        if self.players.contains_key(&bot_id) {
+            return Err(RoomError::AlreadyInRoom);
+        }
+
+        let mut bot = RoomPlayer::bot(bot_id.clone());
+        bot.team = self.next_team();
+        self.players.insert(bot_id, bot);
+        self.try_start_countdown(unix_now());
+        Ok(())
+    }
+
+    /// Đội cho player vào tiếp theo: mode theo đội thì vào đội ít người hơn (hoà thì đội đứng trước),
+    /// mode không chia đội thì None
+    pub fn next_team(&self) -> Option<String> {
+        if !self.settings.game_mode.descriptor().is_some_and(|mode| mode.team_based) {
+            return None;
+        }
+        TEAMS
+            .iter()
+            .min_by_key(|&&team| self.players.values().filter(|player| player.team.as_deref() == Some(team)).count())
+            .map(|team| team.to_string())
+    }
+
+    pub fn bot_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.players.values().filter(|p| p.is_bot).map(|p| p.id.clone()).collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn human_count(&self) -> usize {
+        self.players.values().filter(|p| !p.is_bot).count()
+    }
+
+    /// Số bot cần thêm để đủ `min_players_to_start` (0 nếu room không bật backfill)
+    pub fn bot_backfill_needed(&self) -> usize {
+        if !self.settings.backfill_with_bots || self.human_count() == 0 {
+            return 0;
+        }
+        let target = (self.settings.min_players_to_start.min(self.settings.max_players)) as usize;
+        target.saturating_sub(self.players.len())
+    }
+
+    /// Room backfill đầy thì một bot nhường chỗ cho player thật sắp join; trả về id bot bị bỏ
+    pub fn evict_bot_for_human(&mut self, player_id: &str) -> Option<String> {
+        if !self.settings.backfill_with_bots
+            || self.state != RoomState::Waiting
+            || self.players.len() < self.settings.max_players as usize
+            || self.players.contains_key(player_id)
+            || self.spectators.contains_key(player_id)
+        {
+            return None;
+        }
+        let bot_id = self.bot_ids().pop()?;
+        self.players.remove(&bot_id);
+        Some(bot_id)
+    }
+
     /// Remove player from room
     pub fn remove_player(&mut self, player_id: &str) -> Result<(), RoomError> {
+        self.remove_player_at(player_id, unix_now())
+    }
+
+    fn remove_player_at(&mut self, player_id: &str, now: u64) -> Result<(), RoomError> {
         if !self.players.contains_key(player_id) {
             return Err(RoomError::PlayerNotInRoom);
         }
 
         self.players.remove(player_id);
 
-        // If host left, assign new host or close room
+        // If host left, assign new host or close room (bot không làm host)
         if player_id == self.host_id {
-            if let Some(new_host) = self.players.keys().next().cloned() {
+            let new_host = self.players.values().filter(|p| !p.is_bot).map(|p| p.id.clone()).min();
+            if let Some(new_host) = new_host {
                 self.host_id = new_host.clone();
                 if let Some(host_player) = self.players.get_mut(&new_host) {
                     host_player.is_host = true;
@@ -223,6 +485,13 @@ impl Room {
             }
         }
 
+        // Người rời có thể là người cuối chưa ready, hoặc làm room thiếu người khi đang đếm ngược
+        if self.state == RoomState::Starting && self.countdown_ends_at.is_some() && !self.all_ready() {
+            self.return_to_waiting(now);
+        } else {
+            self.try_start_countdown(now);
+        }
+
         info!("Player {} left room {}", player_id, self.id);
         Ok(())
     }
@@ -305,13 +574,190 @@ impl Room {
         Ok(())
     }
 
+    /// Bảng điểm cuối trận (kể cả bot), xếp theo score giảm dần; bằng điểm thì cùng hạng
+    pub fn final_scoreboard(&self) -> Vec<MatchPlayerResult> {
+        rank_players(self.players.values().collect())
+    }
+
+    /// Kết quả trận để lưu vào `match_results`; None nếu room chưa kết thúc
+    pub fn match_result(&self) -> Option<MatchResultRecord> {
+        if self.state != RoomState::Finished {
+            return None;
+        }
+        let finished_at = self.ended_at?;
+
+        // Bot không vào bảng xếp hạng
+        let results = rank_players(self.players.values().filter(|p| !p.is_bot).collect());
+
+        Some(MatchResultRecord {
+            room_id: self.id.clone(),
+            game_mode: self.settings.game_mode.as_str().to_string(),
+            players: results,
+            duration_seconds: finished_at.saturating_sub(self.started_at.unwrap_or(finished_at)),
+            finished_at,
+        })
+    }
+
+    /// Điểm của player thật để gửi leaderboard, gắn room và `tick` simulation; bot hoặc id lạ thì None
+    pub fn score_submission(&self, player_id: &str, tick: u64) -> Option<ScoreSubmission> {
+        let player = self.players.get(player_id).filter(|player| !player.is_bot)?;
+        Some(ScoreSubmission {
+            room_id: self.id.clone(),
+            tick,
+            game_mode: self.settings.game_mode.as_str().to_string(),
+            player_id: player.id.clone(),
+            player_name: player.name.clone(),
+            score: player.score as u64,
+        })
+    }
+
+    /// Điểm cuối trận của mọi player thật
+    pub fn final_score_submissions(&self, tick: u64) -> Vec<ScoreSubmission> {
+        self.players.keys().filter_map(|player_id| self.score_submission(player_id, tick)).collect()
+    }
+
+    /// Cộng `ticks` vào thời gian trận, đồng bộ score từ simulation và đánh dấu player đã chết (endless runner).
+    /// Trả về lý do nếu trận đã đủ điều kiện kết thúc; room chưa Playing thì luôn None
+    pub fn advance_match(
+        &mut self,
+        ticks: u64,
+        ticks_per_second: u64,
+        scores: &HashMap<String, u32>,
+        died: &[String],
+    ) -> Option<MatchEndReason> {
+        if self.state != RoomState::Playing {
+            return None;
+        }
+        self.match_ticks += ticks;
+        for player in self.players.values_mut() {
+            if let Some(&score) = scores.get(&player.id) {
+                player.score = score;
+            }
+        }
+
+        let scoring = self.settings.game_mode.scoring();
+        if scoring == Some(ScoringType::Distance) {
+            for player_id in died {
+                if self.players.contains_key(player_id) && !self.finished_players.contains(player_id) {
+                    self.finished_players.push(player_id.clone());
+                }
+            }
+            if !self.players.is_empty() && self.players.keys().all(|id| self.finished_players.contains(id)) {
+                return Some(MatchEndReason::AllPlayersFinished);
+            }
+        }
+
+        if scoring == Some(ScoringType::Kills) {
+            if let Some(target) = self.settings.score_target {
+                if self.players.values().any(|p| p.score >= target) {
+                    return Some(MatchEndReason::ScoreTarget);
+                }
+            }
+        }
+
+        match self.settings.time_limit {
+            Some(limit) if self.match_ticks >= limit.as_secs() * ticks_per_second => Some(MatchEndReason::TimeLimit),
+            _ => None,
+        }
+    }
+
+    /// Tick rate room đang chạy; room không bật adaptive luôn ở nhịp gốc
+    pub fn tick_rate_hz(&self) -> u32 {
+        self.tick_governor.current_hz()
+    }
+
+    /// Đổi tick rate gốc của room (clamp về khoảng cho phép); trận đã bắt đầu thì từ chối
+    pub fn set_tick_hz(&mut self, tick_hz: u32) -> Result<u32, RoomError> {
+        if self.state != RoomState::Waiting {
+            return Err(RoomError::InvalidState);
+        }
+        self.settings.tick_hz = tick_rate::clamp_tick_hz(tick_hz);
+        self.tick_governor = TickRateGovernor::new(TickRatePolicy::with_base_hz(self.settings.tick_hz));
+        Ok(self.settings.tick_hz)
+    }
+
     /// Set player as ready
     pub fn set_player_ready(&mut self, player_id: &str, ready: bool) -> Result<(), RoomError> {
-        if let Some(player) = self.players.get_mut(player_id) {
-            player.is_ready = ready;
-            Ok(())
-        } else {
-            Err(RoomError::PlayerNotInRoom)
+        self.set_player_ready_at(player_id, ready, unix_now())
+    }
+
+    /// Đổi trạng thái ready trong lobby. Room auto-start bắt đầu countdown khi người cuối cùng ready,
+    /// bỏ ready trong lúc đếm ngược thì huỷ countdown và cả room phải ready lại
+    pub fn set_player_ready_at(&mut self, player_id: &str, ready: bool, now: u64) -> Result<(), RoomError> {
+        if !matches!(self.state, RoomState::Waiting | RoomState::Starting) {
+            return Err(RoomError::InvalidState);
+        }
+        let player = self.players.get_mut(player_id).ok_or(RoomError::PlayerNotInRoom)?;
+        player.is_ready = ready;
+
+        if ready {
+            self.try_start_countdown(now);
+        } else if self.countdown_ends_at.is_some() {
+            self.return_to_waiting(now);
+        }
+        Ok(())
+    }
+
+    /// Mọi player hiện có đều ready và đủ `min_players_to_start`
+    pub fn all_ready(&self) -> bool {
+        self.players.len() >= self.settings.min_players_to_start.max(1) as usize
+            && self.players.values().all(|p| p.is_ready)
+    }
+
+    fn try_start_countdown(&mut self, now: u64) -> bool {
+        if !self.settings.auto_start || self.state != RoomState::Waiting || !self.all_ready() {
+            return false;
+        }
+        self.state = RoomState::Starting;
+        self.countdown_ends_at = Some(now + AUTO_START_COUNTDOWN_SECONDS);
+        info!("Room {} all players ready, starting in {}s", self.id, AUTO_START_COUNTDOWN_SECONDS);
+        true
+    }
+
+    /// Đưa room về Waiting: huỷ countdown, mọi player thật phải ready lại và hạn ready tính lại từ `now`
+    pub fn return_to_waiting(&mut self, now: u64) {
+        self.state = RoomState::Waiting;
+        self.countdown_ends_at = None;
+        self.waiting_since = now;
+        for player in self.players.values_mut() {
+            player.is_ready = player.is_bot;
+        }
+    }
+
+    /// Số giây countdown auto-start còn lại; None nếu room không đếm ngược
+    pub fn countdown_remaining(&self, now: u64) -> Option<u64> {
+        self.countdown_ends_at.map(|ends| ends.saturating_sub(now))
+    }
+
+    /// Chạy định kỳ: hết countdown thì vào Playing; room auto-start đang chờ thì kick player thật
+    /// quá `ready_timeout_seconds` vẫn chưa ready để lobby không kẹt. Trả về id các player bị kick
+    pub fn update_ready_check(&mut self, now: u64) -> Vec<String> {
+        match self.state {
+            RoomState::Starting => {
+                if self.countdown_ends_at.is_some_and(|ends| now >= ends) {
+                    self.countdown_ends_at = None;
+                    self.state = RoomState::Playing;
+                    self.started_at = Some(now);
+                    info!("Room {} countdown finished, game started", self.id);
+                }
+                Vec::new()
+            }
+            RoomState::Waiting if self.settings.auto_start => {
+                let timeout = self.settings.ready_timeout_seconds as u64;
+                let mut expired: Vec<String> = self
+                    .players
+                    .values()
+                    .filter(|p| !p.is_ready && !p.is_bot && now >= p.joined_at.max(self.waiting_since) + timeout)
+                    .map(|p| p.id.clone())
+                    .collect();
+                expired.sort();
+                for player_id in &expired {
+                    info!("Kicking player {} from room {}: not ready after {}s", player_id, self.id, timeout);
+                    let _ = self.remove_player_at(player_id, now);
+                }
+                expired
+            }
+            _ => Vec::new(),
         }
     }
 
@@ -351,6 +797,12 @@ impl Room {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs() - self.created_at,
+            players: {
+                let mut players: Vec<RoomPlayer> = self.players.values().cloned().collect();
+                players.sort_by(|a, b| a.joined_at.cmp(&b.joined_at).then_with(|| a.id.cmp(&b.id)));
+                players
+            },
+            countdown_seconds_left: self.countdown_remaining(unix_now()),
         }
     }
 
@@ -381,6 +833,9 @@ pub struct RoomInfo {
     pub has_password: bool,
     pub game_mode: GameMode,
     pub created_at: u64, // seconds ago
+    /// Player trong room kèm trạng thái ready để lobby hiện dấu tick
+    pub players: Vec<RoomPlayer>,
+    pub countdown_seconds_left: Option<u64>,
 }
 
 /// Room errors
@@ -524,6 +979,11 @@ impl RoomManager {
         self.rooms.get(room_id)
     }
 
+    /// Tất cả room đang có trên worker
+    pub fn rooms(&self) -> impl Iterator<Item = &Room> {
+        self.rooms.values()
+    }
+
     /// Get room by ID (mutable)
     pub fn get_room_mut(&mut self, room_id: &str) -> Option<&mut Room> {
         self.rooms.get_mut(room_id)
@@ -611,6 +1071,89 @@ impl RoomManager {
         Ok(())
     }
 
+    /// Kết thúc mọi room đang Playing với score lấy từ simulation, trả về kết quả để lưu (dùng khi worker shutdown)
+    pub fn finish_active_rooms(&mut self, scores: &HashMap<String, u32>) -> Vec<MatchResultRecord> {
+        self.rooms
+            .values_mut()
+            .filter(|room| room.state == RoomState::Playing)
+            .filter_map(|room| {
+                for player in room.players.values_mut() {
+                    if let Some(&score) = scores.get(&player.id) {
+                        player.score = score;
+                    }
+                }
+                room.end_game().ok()?;
+                room.match_result()
+            })
+            .collect()
+    }
+
+    /// Tiến thời gian mọi room đang Playing; room đủ điều kiện thì chuyển Finished và trả về bảng điểm cuối
+    pub fn advance_matches(
+        &mut self,
+        ticks: u64,
+        ticks_per_second: u64,
+        scores: &HashMap<String, u32>,
+        died: &[String],
+    ) -> Vec<FinishedMatch> {
+        self.rooms
+            .values_mut()
+            .filter_map(|room| {
+                let reason = room.advance_match(ticks, ticks_per_second, scores, died)?;
+                room.end_game().ok()?;
+                info!(room_id = %room.id, reason = reason.as_str(), "Match finished");
+                Some(FinishedMatch {
+                    room_id: room.id.clone(),
+                    reason,
+                    scoreboard: room.final_scoreboard(),
+                    result: room.match_result(),
+                })
+            })
+            .collect()
+    }
+
+    /// Endless runner: điểm của player vừa chết trong room vẫn đang chơi, mỗi player một lần.
+    /// Room kết thúc thì điểm đi theo `final_score_submissions` nên không lấy ở đây
+    pub fn take_runner_deaths(&mut self, tick: u64) -> Vec<ScoreSubmission> {
+        let mut submissions = Vec::new();
+        for room in self.rooms.values_mut().filter(|room| room.state == RoomState::Playing) {
+            for player_id in &room.finished_players[room.submitted_deaths..] {
+                submissions.extend(room.score_submission(player_id, tick));
+            }
+            room.submitted_deaths = room.finished_players.len();
+        }
+        submissions
+    }
+
+    /// Đưa thời gian xử lý tick vừa rồi cho các room đang chơi có bật adaptive; trả về room đổi tick rate
+    pub fn observe_tick_cost(&mut self, tick_cost: Duration) -> Vec<(String, u32)> {
+        self.rooms
+            .values_mut()
+            .filter(|room| room.state == RoomState::Playing && room.settings.adaptive_tick_rate)
+            .filter_map(|room| {
+                let hz = room.tick_governor.observe(tick_cost)?;
+                info!(room_id = %room.id, tick_rate_hz = hz, "Room tick rate changed");
+                Some((room.id.clone(), hz))
+            })
+            .collect()
+    }
+
+    /// Simulation dùng chung nên phải chạy theo room cần tick rate cao nhất; None nếu không có room nào đang chơi
+    pub fn required_tick_hz(&self) -> Option<u32> {
+        self.rooms
+            .values()
+            .filter(|room| room.state == RoomState::Playing)
+            .map(|room| room.tick_rate_hz())
+            .max()
+    }
+
+    /// Đổi tick rate gốc của room; chỉ được trước khi trận bắt đầu
+    pub fn set_room_tick_hz(&mut self, room_id: &str, tick_hz: u32) -> Result<u32, RoomError> {
+        self.get_room_mut(room_id)
+            .ok_or(RoomError::RoomNotFound)?
+            .set_tick_hz(tick_hz)
+    }
+
     /// Set player ready status
     pub fn set_player_ready(&mut self, room_id: &str, player_id: &str, ready: bool) -> Result<(), RoomError> {
         let room = self.get_room_mut(room_id)
@@ -635,6 +1178,17 @@ impl RoomManager {
         room.update_player_activity(player_id)
     }
 
+    /// Ready-check của mọi room; trả về (room_id, player_id) bị kick vì không ready kịp
+    pub fn update_ready_checks(&mut self, now: u64) -> Vec<(String, String)> {
+        let mut kicked = Vec::new();
+        for (room_id, room) in self.rooms.iter_mut() {
+            for player_id in room.update_ready_check(now) {
+                kicked.push((room_id.clone(), player_id));
+            }
+        }
+        kicked
+    }
+
     /// Get room info
     pub fn get_room_info(&self, room_id: &str) -> Result<RoomInfo, RoomError> {
         let room = self.get_room(room_id)
@@ -643,8 +1197,8 @@ impl RoomManager {
         Ok(room.get_room_info())
     }
 
-    /// Cleanup empty and old rooms
-    pub fn cleanup(&mut self) {
+    /// Cleanup empty and old rooms; trả về id các room đã bị dọn
+    pub fn cleanup(&mut self) -> Vec<String> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
@@ -652,7 +1206,7 @@ impl RoomManager {
 
         // Don't cleanup too frequently
         if now - self.last_cleanup < self.cleanup_interval.as_secs() {
-            return;
+            return Vec::new();
         }
 
         let mut rooms_to_remove = Vec::new();
@@ -693,13 +1247,16 @@ impl RoomManager {
             }
         }
 
+        let mut removed = Vec::new();
         for room_id in rooms_to_remove {
             if let Some(room) = self.rooms.remove(&room_id) {
                 info!("Cleaned up room: {} ({})", room_id, room.name);
+                removed.push(room_id);
             }
         }
 
         self.last_cleanup = now;
+        removed
     }
 
     /// Get total room count
@@ -724,6 +1281,17 @@ impl Default for RoomManager {
     }
 }
 
+/// Trận vừa kết thúc trong `advance_matches`
+#[derive(Debug, Clone)]
+pub struct FinishedMatch {
+    pub room_id: String,
+    pub reason: MatchEndReason,
+    /// Mọi player kể cả bot, để gửi cho client
+    pub scoreboard: Vec<MatchPlayerResult>,
+    /// Chỉ player thật, để ghi leaderboard
+    pub result: Option<MatchResultRecord>,
+}
+
 /// Room management operations result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoomOperationResult {
@@ -732,3 +1300,197 @@ pub struct RoomOperationResult {
     pub error: Option<String>,
     pub data: Option<serde_json::Value>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn room_without_seed_records_a_random_one() {
+        let explicit = RoomSettings { seed: Some(42), ..RoomSettings::default() };
+        let room = Room::new("a".to_string(), "host".to_string(), "Host".to_string(), explicit);
+        assert_eq!(room.settings.seed, Some(42));
+
+        let room = Room::new("b".to_string(), "host".to_string(), "Host".to_string(), RoomSettings::default());
+        assert!(room.settings.seed.is_some());
+    }
+
+    #[test]
+    fn tick_hz_is_clamped_and_fixed_once_the_match_starts() {
+        let settings = RoomSettings { tick_hz: 500, min_players_to_start: 1, ..RoomSettings::default() };
+        let mut room = Room::new("r".to_string(), "host".to_string(), "Host".to_string(), settings);
+        assert_eq!(room.settings.tick_hz, tick_rate::MAX_TICK_HZ);
+
+        assert_eq!(room.set_tick_hz(30).unwrap(), 30);
+        assert_eq!(room.tick_rate_hz(), 30);
+        assert_eq!(room.set_tick_hz(1).unwrap(), tick_rate::MIN_TICK_HZ);
+
+        room.set_tick_hz(30).unwrap();
+        room.start_game().unwrap();
+        assert!(matches!(room.set_tick_hz(60), Err(RoomError::InvalidState)));
+        assert_eq!(room.tick_rate_hz(), 30);
+    }
+
+    #[test]
+    fn match_result_ranks_players_by_score() {
+        let mut room = Room::new("r".to_string(), "host".to_string(), "Host".to_string(), RoomSettings::default());
+        room.add_player("p2".to_string(), "P2".to_string()).unwrap();
+        room.add_player("p3".to_string(), "P3".to_string()).unwrap();
+        assert!(room.match_result().is_none());
+
+        room.players.get_mut("host").unwrap().score = 10;
+        room.players.get_mut("p2").unwrap().score = 30;
+        room.players.get_mut("p3").unwrap().score = 10;
+        room.state = RoomState::Playing;
+        room.started_at = Some(1_000);
+        room.end_game().unwrap();
+        room.ended_at = Some(1_090);
+
+        let result = room.match_result().expect("finished room has a result");
+        assert_eq!(result.game_mode, "deathmatch");
+        assert_eq!(result.duration_seconds, 90);
+        let placements: Vec<(&str, u32)> = result.players.iter().map(|p| (p.player_id.as_str(), p.placement)).collect();
+        assert_eq!(placements, vec![("p2", 1), ("host", 2), ("p3", 2)]);
+
+        let body = result.to_pocketbase_json();
+        assert_eq!(body["player_ids"], "|p2|host|p3|");
+        assert_eq!(body["finished_at"], "1970-01-01 00:18:10.000Z");
+    }
+
+    #[test]
+    fn bots_backfill_room_and_stay_off_the_results() {
+        let settings = RoomSettings {
+            max_players: 3,
+            min_players_to_start: 3,
+            backfill_with_bots: true,
+            ..Default::default()
+        };
+        let mut room = Room::new("r".to_string(), "host".to_string(), "Host".to_string(), settings);
+        assert_eq!(room.bot_backfill_needed(), 2);
+        room.add_bot("bot-1".to_string()).unwrap();
+        room.add_bot("bot-2".to_string()).unwrap();
+        assert_eq!(room.bot_backfill_needed(), 0);
+        assert!(matches!(room.add_bot("bot-3".to_string()), Err(RoomError::RoomFull)));
+
+        // Player thật join room đầy: một bot nhường chỗ
+        assert_eq!(room.evict_bot_for_human("host"), None);
+        assert_eq!(room.evict_bot_for_human("p2").as_deref(), Some("bot-2"));
+        room.add_player("p2".to_string(), "P2".to_string()).unwrap();
+
+        // Host rời đi thì host mới là người thật, không phải bot
+        room.remove_player("host").unwrap();
+        assert_eq!(room.host_id, "p2");
+
+        room.players.get_mut("bot-1").unwrap().score = 500;
+        room.players.get_mut("p2").unwrap().score = 10;
+        room.state = RoomState::Playing;
+        room.end_game().unwrap();
+        let result = room.match_result().unwrap();
+        let ids: Vec<&str> = result.players.iter().map(|p| p.player_id.as_str()).collect();
+        assert_eq!(ids, vec!["p2"]);
+        assert_eq!(result.players[0].placement, 1);
+    }
+
+    fn lobby(min_players_to_start: u32) -> Room {
+        let settings = RoomSettings {
+            auto_start: true,
+            min_players_to_start,
+            ..Default::default()
+        };
+        let mut room = Room::new("r".to_string(), "host".to_string(), "Host".to_string(), settings);
+        room.add_player("p2".to_string(), "P2".to_string()).unwrap();
+        room.add_player("p3".to_string(), "P3".to_string()).unwrap();
+        room
+    }
+
+    #[test]
+    fn countdown_starts_only_after_the_last_player_is_ready() {
+        let mut room = lobby(3);
+        let now = room.waiting_since;
+
+        room.set_player_ready_at("host", true, now).unwrap();
+        room.set_player_ready_at("p2", true, now).unwrap();
+        assert_eq!(room.state, RoomState::Waiting);
+        assert_eq!(room.countdown_remaining(now), None);
+
+        room.set_player_ready_at("p3", true, now).unwrap();
+        assert_eq!(room.state, RoomState::Starting);
+        assert_eq!(room.countdown_remaining(now), Some(AUTO_START_COUNTDOWN_SECONDS));
+
+        // Bỏ ready giữa countdown: về Waiting và cả room phải ready lại
+        room.update_ready_check(now + 1);
+        room.set_player_ready_at("p2", false, now + 1).unwrap();
+        assert_eq!(room.state, RoomState::Waiting);
+        assert!(room.players.values().all(|p| !p.is_ready));
+
+        for player_id in ["host", "p2", "p3"] {
+            room.set_player_ready_at(player_id, true, now + 2).unwrap();
+        }
+        assert!(room.update_ready_check(now + 2 + AUTO_START_COUNTDOWN_SECONDS - 1).is_empty());
+        assert_eq!(room.state, RoomState::Starting);
+        room.update_ready_check(now + 2 + AUTO_START_COUNTDOWN_SECONDS);
+        assert_eq!(room.state, RoomState::Playing);
+        assert_eq!(room.started_at, Some(now + 2 + AUTO_START_COUNTDOWN_SECONDS));
+        assert!(matches!(room.set_player_ready_at("p2", false, now + 10), Err(RoomError::InvalidState)));
+
+        // Room không bật auto_start thì chờ host bấm start
+        let mut manual = lobby(3);
+        manual.settings.auto_start = false;
+        for player_id in ["host", "p2", "p3"] {
+            manual.set_player_ready_at(player_id, true, now).unwrap();
+        }
+        assert_eq!(manual.state, RoomState::Waiting);
+    }
+
+    #[test]
+    fn joining_player_resets_only_their_own_ready_flag() {
+        let mut room = lobby(4);
+        let now = room.waiting_since;
+        for player_id in ["host", "p2", "p3"] {
+            room.set_player_ready_at(player_id, true, now).unwrap();
+        }
+
+        room.add_player("p4".to_string(), "P4".to_string()).unwrap();
+        let ready: Vec<(String, bool)> = room
+            .get_room_info()
+            .players
+            .into_iter()
+            .map(|p| (p.id, p.is_ready))
+            .collect();
+        assert_eq!(ready.len(), 4);
+        for (player_id, is_ready) in ready {
+            assert_eq!(is_ready, player_id != "p4", "{player_id}");
+        }
+        assert_eq!(room.state, RoomState::Waiting);
+
+        // p4 không bao giờ ready: bị kick sau ready timeout, những người khác giữ nguyên
+        let deadline = room.players["p4"].joined_at.max(room.waiting_since) + DEFAULT_READY_TIMEOUT_SECONDS as u64;
+        assert!(room.update_ready_check(deadline - 1).is_empty());
+        assert_eq!(room.update_ready_check(deadline), vec!["p4".to_string()]);
+        assert!(!room.players.contains_key("p4"));
+        assert!(room.players.values().all(|p| p.is_ready));
+    }
+
+    #[test]
+    fn runner_deaths_are_submitted_once_while_the_room_keeps_playing() {
+        let mut manager = RoomManager::default();
+        let settings = RoomSettings { game_mode: GameMode::EndlessRunner, min_players_to_start: 1, ..RoomSettings::default() };
+        let room_id = manager.create_room("run".to_string(), "alice".to_string(), "Alice".to_string(), settings).unwrap();
+        manager.join_room(&room_id, "bob".to_string(), "Bob".to_string()).unwrap();
+        manager.start_game(&room_id, "alice").unwrap();
+        let scores = HashMap::from([("alice".to_string(), 120), ("bob".to_string(), 80)]);
+
+        assert!(manager.advance_matches(1, 60, &scores, &["bob".to_string()]).is_empty());
+        let deaths = manager.take_runner_deaths(7);
+        assert_eq!(deaths.len(), 1);
+        assert_eq!((deaths[0].player_id.as_str(), deaths[0].score, deaths[0].tick), ("bob", 80, 7));
+        assert!(manager.take_runner_deaths(8).is_empty());
+
+        // Người cuối chết thì trận kết thúc, điểm đi theo kết quả cuối trận
+        assert_eq!(manager.advance_matches(1, 60, &scores, &["alice".to_string()]).len(), 1);
+        assert!(manager.take_runner_deaths(9).is_empty());
+        let mut finals = manager.get_room(&room_id).unwrap().final_score_submissions(9);
+        finals.sort_by(|a, b| a.player_id.cmp(&b.player_id));
+        assert_eq!(finals.iter().map(|s| s.score).collect::<Vec<_>>(), vec![120, 80]);
+    }
+}
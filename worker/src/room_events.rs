@@ -0,0 +1,140 @@
+//! Fan-out `RoomEvent` cho các stream `StreamRoomEvents`: mỗi room một broadcast channel, tạo khi có subscriber
+//! đầu tiên và bỏ khi không còn ai nghe hoặc room bị dọn.
+
+use dashmap::DashMap;
+use proto::worker::v1::{
+    room_event::Event, ChatEvent, MatchEndedEvent, MatchPlacement, PlayerJoinedEvent, PlayerLeftEvent, RoomEvent,
+    SnapshotReadyEvent, SystemMessageEvent,
+};
+use tokio::sync::broadcast;
+
+use crate::database::MatchPlayerResult;
+use crate::system_message::SystemMessage;
+
+/// Số ký tự tối đa của một chat message
+pub const MAX_CHAT_CHARS: usize = 256;
+
+/// Số event mỗi subscriber được nợ trước khi bị tính là lagged và mất event cũ nhất
+pub const ROOM_EVENT_BUFFER: usize = 256;
+
+#[derive(Debug, Default)]
+pub struct RoomEventHub {
+    channels: DashMap<String, broadcast::Sender<RoomEvent>>,
+}
+
+impl RoomEventHub {
+    pub fn subscribe(&self, room_id: &str) -> broadcast::Receiver<RoomEvent> {
+        self.channels
+            .entry(room_id.to_string())
+            .or_insert_with(|| broadcast::channel(ROOM_EVENT_BUFFER).0)
+            .subscribe()
+    }
+
+    /// Số stream đang nghe room
+    pub fn subscriber_count(&self, room_id: &str) -> usize {
+        self.channels.get(room_id).map_or(0, |sender| sender.receiver_count())
+    }
+
+    /// Gửi event cho mọi subscriber của room; trả về số subscriber đã nhận
+    pub fn publish(&self, room_id: &str, event: Event) -> usize {
+        let Some(sender) = self.channels.get(room_id) else {
+            return 0;
+        };
+        let delivered = sender
+            .send(RoomEvent { room_id: room_id.to_string(), event: Some(event) })
+            .unwrap_or_default();
+        drop(sender);
+        if delivered == 0 {
+            // Mọi stream đã huỷ; subscriber mới sẽ tạo lại channel
+            self.channels.remove_if(room_id, |_, sender| sender.receiver_count() == 0);
+        }
+        delivered
+    }
+
+    /// Simulation dùng chung cho mọi room nên tick xong thì báo tất cả room đang có subscriber
+    pub fn snapshot_ready(&self, tick: u64) {
+        self.channels.retain(|room_id, sender| {
+            let event = RoomEvent {
+                room_id: room_id.clone(),
+                event: Some(Event::SnapshotReady(SnapshotReadyEvent { tick })),
+            };
+            sender.send(event).is_ok()
+        });
+    }
+
+    pub fn player_joined(&self, room_id: &str, player_id: &str, resumed: bool) -> usize {
+        self.publish(room_id, Event::PlayerJoined(PlayerJoinedEvent { player_id: player_id.to_string(), resumed }))
+    }
+
+    pub fn player_left(&self, room_id: &str, player_id: &str, reason: &str) -> usize {
+        self.publish(
+            room_id,
+            Event::PlayerLeft(PlayerLeftEvent { player_id: player_id.to_string(), reason: reason.to_string() }),
+        )
+    }
+
+    pub fn chat(&self, room_id: &str, player_id: &str, player_name: &str, message: &str, timestamp_ms: u64) -> usize {
+        self.publish(
+            room_id,
+            Event::Chat(ChatEvent {
+                player_id: player_id.to_string(),
+                message: message.to_string(),
+                timestamp_ms,
+                player_name: player_name.to_string(),
+            }),
+        )
+    }
+
+    pub fn match_ended(&self, room_id: &str, reason: &str, scoreboard: &[MatchPlayerResult]) -> usize {
+        let scoreboard = scoreboard
+            .iter()
+            .map(|result| MatchPlacement {
+                player_id: result.player_id.clone(),
+                score: result.score,
+                placement: result.placement,
+            })
+            .collect();
+        self.publish(room_id, Event::MatchEnded(MatchEndedEvent { reason: reason.to_string(), scoreboard }))
+    }
+
+    /// `recipient_id` khác None thì gateway chỉ chuyển cho player đó
+    pub fn system_message(&self, room_id: &str, recipient_id: Option<&str>, message: &SystemMessage) -> usize {
+        self.publish(
+            room_id,
+            Event::SystemMessage(SystemMessageEvent {
+                key: message.key.clone(),
+                params: message.params.clone().into_iter().collect(),
+                recipient_id: recipient_id.unwrap_or_default().to_string(),
+            }),
+        )
+    }
+
+    /// Room bị dọn: bỏ channel để stream của mọi subscriber kết thúc sau khi nhận hết event còn lại
+    pub fn close(&self, room_id: &str) -> bool {
+        self.channels.remove(room_id).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn closed_room_ends_streams_after_pending_events() {
+        let hub = RoomEventHub::default();
+        let mut first = hub.subscribe("arena");
+        let second = hub.subscribe("arena");
+        assert_eq!(hub.player_joined("arena", "alice", false), 2);
+        assert_eq!(hub.player_joined("lobby", "bob", false), 0);
+
+        // Subscriber huỷ thì không còn được tính
+        drop(second);
+        hub.snapshot_ready(7);
+        assert_eq!(hub.subscriber_count("arena"), 1);
+
+        assert!(hub.close("arena"));
+        assert!(matches!(first.recv().await.unwrap().event, Some(Event::PlayerJoined(_))));
+        assert!(matches!(first.recv().await.unwrap().event, Some(Event::SnapshotReady(SnapshotReadyEvent { tick: 7 }))));
+        assert!(matches!(first.recv().await, Err(broadcast::error::RecvError::Closed)));
+    }
+}
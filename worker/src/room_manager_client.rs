@@ -0,0 +1,54 @@
+//! Gọi REST API nội bộ của room-manager khi trận trong worker kết thúc
+
+use std::time::Duration;
+
+use crate::BoxError;
+
+/// Header shared secret giữa các service nội bộ, khớp với room-manager
+pub const INTERNAL_SECRET_HEADER: &str = "x-internal-secret";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Cùng biến môi trường ROOM_MANAGER_INTERNAL_SECRET với room-manager; không có mặc định
+pub fn internal_secret_from_env() -> Option<String> {
+    std::env::var("ROOM_MANAGER_INTERNAL_SECRET").ok().filter(|secret| !secret.is_empty())
+}
+
+#[derive(Debug, Clone)]
+pub struct RoomManagerClient {
+    http: reqwest::Client,
+    base_url: String,
+    /// None thì không gửi header; chỉ room-manager chưa đặt secret (nghe trên loopback) chấp nhận
+    secret: Option<String>,
+}
+
+impl RoomManagerClient {
+    pub fn new(base_url: &str, secret: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            secret,
+        }
+    }
+
+    /// Chuyển room sang Finished; room-manager không biết room này thì trả lỗi
+    pub async fn finish_room(&self, room_id: &str) -> Result<(), BoxError> {
+        let mut request = self.http.post(format!("{}/v1/rooms/{}/finish", self.base_url, room_id));
+        if let Some(secret) = &self.secret {
+            request = request.header(INTERNAL_SECRET_HEADER, secret);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(format!("room-manager returned {} for room {}", response.status(), room_id).into());
+        }
+        let body: serde_json::Value = response.json().await?;
+        if body.get("success").and_then(|v| v.as_bool()) != Some(true) {
+            let error = body.get("error").and_then(|v| v.as_str()).unwrap_or("unknown error");
+            return Err(format!("room-manager refused to finish room {}: {}", room_id, error).into());
+        }
+        Ok(())
+    }
+}
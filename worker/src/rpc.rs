@@ -1,4 +1,4 @@
-use std::{net::TcpListener, sync::Arc};
+use std::{collections::HashMap, pin::Pin, sync::{atomic::{AtomicBool, Ordering}, Arc}};
 
 use proto::worker::v1::{
     worker_client::WorkerClient,
@@ -13,26 +13,425 @@ use proto::worker::v1::{
     // Note: LeaveRoomAsSpectatorRequest/Response not implemented in proto yet
     StartGameRequest, StartGameResponse, EndGameRequest, EndGameResponse, SetPlayerReadyRequest,
     SetPlayerReadyResponse, UpdatePlayerPingRequest, UpdatePlayerPingResponse,
+    GetPlayerSnapshotRequest, GetPlayerSnapshotResponse, AddBotsRequest, AddBotsResponse,
+    ActiveRoom, ListActiveRoomsRequest, ListActiveRoomsResponse, NotifyDisconnectRequest,
+    NotifyDisconnectResponse, RemovePlayerRequest, RemovePlayerResponse, UpdatePlayerLatencyRequest,
+    UpdatePlayerLatencyResponse, RoomEvent, StreamRoomEventsRequest, SendChatRequest, SendChatResponse,
+    AnnounceRequest, AnnounceResponse, GetRoomPlayersRequest, GetRoomPlayersResponse, LiveRoomPlayer,
 };
 use tokio::sync::RwLock;
+use tokio_stream::{wrappers::{errors::BroadcastStreamRecvError, BroadcastStream}, Stream, StreamExt};
+use proto::auth::{AttachInternalToken, INTERNAL_TOKEN_HEADER};
 use tonic::{
+    service::{interceptor::InterceptedService, Interceptor},
     transport::{Channel, Endpoint, Server},
     Response, Status,
 };
+use common_net::telemetry::{new_request_id, REQUEST_ID_HEADER};
 use tracing::{error, info, warn};
 
-use crate::{simulation::{GameWorld, PlayerInput, SpectatorCameraMode}, simulation_metrics, room::{RoomManager, RoomSettings, GameMode, RoomListFilter, RoomState}};
+use common_net::game_modes::ScoringType;
+use crate::{checkpoint::{self, CheckpointQueue, CheckpointTracker, ProgressCheckpoint, DEFAULT_CHECKPOINT_INTERVAL_SECONDS}, room_manager_client::RoomManagerClient, leaderboard_client::{LeaderboardClient, ScoreSubmission}, bots::{BotDifficulty, MAX_BOTS_PER_REQUEST}, database::PocketBaseClient, room_events::{RoomEventHub, MAX_CHAT_CHARS}, simulation::{EncodedSnapshot, EncodingStats, FullSnapshotReason, GameWorld, SpectatorCameraMode}, simulation_metrics, system_message::{self, SystemMessage, MAX_ANNOUNCEMENT_CHARS}, tick_rate, validation, room::{unix_now, Room, RoomError, RoomManager, RoomPlayer, RoomSettings, GameMode, RoomListFilter, RoomState, DEFAULT_READY_TIMEOUT_SECONDS, DEFAULT_REJOIN_GRACE_SECONDS}};
 
 pub struct WorkerState {
     pub game_world: RwLock<GameWorld>,
     pub room_manager: RwLock<RoomManager>,
+    /// Nơi ghi `match_results` khi room kết thúc; None thì bỏ qua
+    pub match_store: Option<PocketBaseClient>,
+    /// Báo room-manager khi trận kết thúc; None thì chỉ đổi trạng thái trong worker
+    pub room_manager_client: Option<RoomManagerClient>,
+    /// Gửi điểm do simulation tính lên leaderboard của gateway; None thì không gửi
+    pub leaderboard_client: Option<LeaderboardClient>,
+    /// Sự kiện của từng room cho các stream `StreamRoomEvents`
+    pub room_events: RoomEventHub,
+    /// Hàng đợi ghi checkpoint điểm của player; None thì không checkpoint
+    pub checkpoint_queue: Option<CheckpointQueue>,
+    checkpoint_tracker: std::sync::Mutex<CheckpointTracker>,
+    draining: AtomicBool,
 }
 
+/// Lỗi trả cho join/create room khi worker đang drain
+pub const DRAINING_ERROR: &str = "worker is draining, not accepting new players";
+
 impl WorkerState {
     pub fn new() -> Self {
         Self {
             game_world: RwLock::new(GameWorld::new()),
             room_manager: RwLock::new(RoomManager::default()),
+            match_store: None,
+            room_manager_client: None,
+            leaderboard_client: None,
+            room_events: RoomEventHub::default(),
+            checkpoint_queue: None,
+            checkpoint_tracker: std::sync::Mutex::default(),
+            draining: AtomicBool::new(false),
+        }
+    }
+
+    /// Ngừng nhận room và player mới; room đang chơi vẫn tick tới khi kết thúc
+    pub fn begin_drain(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Còn room đang chơi thì drain chưa xong
+    pub async fn has_playing_rooms(&self) -> bool {
+        self.room_manager.read().await.rooms().any(|room| room.state == RoomState::Playing)
+    }
+
+    pub fn with_checkpoint_queue(mut self, queue: CheckpointQueue) -> Self {
+        self.checkpoint_queue = Some(queue);
+        self
+    }
+
+    pub fn with_room_manager_client(mut self, client: RoomManagerClient) -> Self {
+        self.room_manager_client = Some(client);
+        self
+    }
+
+    pub fn with_leaderboard_client(mut self, client: LeaderboardClient) -> Self {
+        self.leaderboard_client = Some(client);
+        self
+    }
+
+    pub fn with_match_store(mut self, store: PocketBaseClient) -> Self {
+        self.match_store = Some(store);
+        self
+    }
+
+    /// Kết thúc các room đang chơi và ghi `match_results` với score hiện tại; trả về số room đã ghi được
+    pub async fn persist_active_rooms(&self) -> usize {
+        let Some(store) = self.match_store.clone() else {
+            return 0;
+        };
+
+        let scores = self.game_world.read().await.player_scores();
+        let results = self.room_manager.write().await.finish_active_rooms(&scores);
+
+        let mut saved = 0;
+        for result in results {
+            match store.save_match_result(&result).await {
+                Ok(_) => saved += 1,
+                Err(err) => {
+                    warn!(room_id = %result.room_id, %err, "worker: failed to persist match result on shutdown");
+                    continue;
+                }
+            }
+            if let Err(err) = store.delete_room_checkpoints(&result.room_id).await {
+                warn!(room_id = %result.room_id, %err, "worker: failed to delete checkpoints on shutdown");
+            }
+        }
+        saved
+    }
+
+    /// Gửi điểm lên leaderboard ở background theo thứ tự; gateway từ chối thì chỉ log
+    pub fn submit_scores(&self, submissions: Vec<ScoreSubmission>) {
+        let Some(client) = self.leaderboard_client.clone() else {
+            return;
+        };
+        if submissions.is_empty() {
+            return;
+        }
+        tokio::spawn(async move {
+            for submission in submissions {
+                if let Err(err) = client.submit(&submission).await {
+                    warn!(room_id = %submission.room_id, player_id = %submission.player_id, %err, "worker: failed to submit leaderboard score");
+                }
+            }
+        });
+    }
+
+    /// Checkpoint điểm của player trong các room đang chơi mode tính điểm theo quãng đường, mỗi room theo
+    /// `checkpoint_interval_seconds`. Chỉ xếp vào queue nên không chặn tick. Trả về các checkpoint vừa xếp
+    pub async fn run_checkpoints(&self, now: u64) -> Vec<ProgressCheckpoint> {
+        let Some(queue) = self.checkpoint_queue.as_ref() else {
+            return Vec::new();
+        };
+        let rooms: Vec<Room> = self
+            .room_manager
+            .read()
+            .await
+            .rooms()
+            .filter(|room| room.state == RoomState::Playing && room.settings.game_mode.scoring() == Some(ScoringType::Distance))
+            .cloned()
+            .collect();
+        if rooms.is_empty() {
+            return Vec::new();
+        }
+
+        let (progress, current_tick, ticks_per_second) = {
+            let mut game_world = self.game_world.write().await;
+            (game_world.player_progress(), game_world.get_current_tick(), game_world.ticks_per_second())
+        };
+        let checkpoints = self
+            .checkpoint_tracker
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .collect(&rooms, &progress, current_tick, ticks_per_second, now);
+        for checkpoint in &checkpoints {
+            queue.save(checkpoint.clone());
+        }
+        checkpoints
+    }
+
+    /// Checkpoint tốt nhất cùng mode của player để khôi phục vào session mới; lỗi PocketBase thì bỏ qua
+    async fn resume_checkpoint(&self, player_id: &str, game_mode: &str) -> Option<ProgressCheckpoint> {
+        let store = self.match_store.as_ref()?;
+        match store.player_checkpoints(player_id).await {
+            Ok(checkpoints) => checkpoint::best_checkpoint(&checkpoints, game_mode, unix_now()).cloned(),
+            Err(err) => {
+                warn!(%player_id, %err, "worker: failed to load checkpoints, starting fresh");
+                None
+            }
+        }
+    }
+
+    /// Tiến đồng hồ trận thêm `ticks` tick. Room hết giờ, đạt score target hoặc (endless runner) mọi player đã chết
+    /// thì chuyển Finished: phát `match_ended` kèm bảng điểm, báo room-manager, ghi `match_results` và gửi điểm player thật
+    /// lên leaderboard. Endless runner gửi điểm của player ngay lúc chết. Trả về số room vừa kết thúc
+    pub async fn run_match_checks(&self, ticks: u64) -> usize {
+        let (finished, submissions, required_tick_hz) = {
+            let game_world = self.game_world.read().await;
+            let ticks_per_second = game_world.ticks_per_second();
+            let tick = game_world.get_current_tick();
+            let scores = game_world.player_scores();
+            let died = game_world.recently_died_players();
+            drop(game_world);
+            let mut room_manager = self.room_manager.write().await;
+            let finished = room_manager.advance_matches(ticks, ticks_per_second, &scores, &died);
+            let mut submissions = room_manager.take_runner_deaths(tick);
+            for finished_match in &finished {
+                if let Some(room) = room_manager.get_room(&finished_match.room_id) {
+                    submissions.extend(room.final_score_submissions(tick));
+                }
+            }
+            (finished, submissions, room_manager.required_tick_hz())
+        };
+        self.submit_scores(submissions);
+        if finished.is_empty() {
+            return 0;
+        }
+
+        {
+            let mut game_world = self.game_world.write().await;
+            // Room vừa kết thúc có thể là room cần tick rate cao nhất
+            if let Some(hz) = required_tick_hz {
+                game_world.set_tick_hz(hz);
+            }
+            for finished_match in &finished {
+                game_world.emit_match_ended(&finished_match.room_id, finished_match.reason.as_str(), finished_match.scoreboard.clone());
+                self.room_events.match_ended(&finished_match.room_id, finished_match.reason.as_str(), &finished_match.scoreboard);
+            }
+        }
+
+        {
+            let mut tracker = self.checkpoint_tracker.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            for finished_match in &finished {
+                tracker.forget_room(&finished_match.room_id);
+            }
+        }
+
+        for finished_match in &finished {
+            if let Some(client) = self.room_manager_client.clone() {
+                let room_id = finished_match.room_id.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = client.finish_room(&room_id).await {
+                        warn!(%room_id, %err, "worker: failed to mark room finished in room-manager");
+                    }
+                });
+            }
+            if let (Some(store), Some(result)) = (self.match_store.clone(), finished_match.result.clone()) {
+                let checkpoint_queue = self.checkpoint_queue.clone();
+                tokio::spawn(async move {
+                    match store.save_match_result(&result).await {
+                        // Kết quả đã ghi thì checkpoint của room không còn dùng để khôi phục
+                        Ok(_) => {
+                            if let Some(queue) = checkpoint_queue {
+                                queue.delete_room(&result.room_id);
+                            }
+                        }
+                        Err(err) => warn!(room_id = %result.room_id, %err, "worker: failed to persist match result"),
+                    }
+                });
+            }
+        }
+        finished.len()
+    }
+
+    /// Ghi thời gian xử lý một tick simulation. Room bật adaptive tick rate bị hạ nhịp khi worker quá tải liên tục
+    /// và nâng lại khi tải giảm; mỗi lần đổi đều phát `tick_rate_changed`. Trả về số room vừa đổi tick rate
+    pub async fn record_tick_cost(&self, tick_cost: std::time::Duration) -> usize {
+        let (changed, required_hz) = {
+            let mut room_manager = self.room_manager.write().await;
+            let changed = room_manager.observe_tick_cost(tick_cost);
+            (changed, room_manager.required_tick_hz())
+        };
+        if changed.is_empty() {
+            return 0;
+        }
+
+        let mut game_world = self.game_world.write().await;
+        for (room_id, hz) in &changed {
+            game_world.emit_tick_rate_changed(room_id, *hz);
+        }
+        if let Some(hz) = required_hz {
+            game_world.set_tick_hz(hz);
+        }
+        changed.len()
+    }
+
+    /// Ready-check định kỳ: room hết countdown thì vào Playing, player không ready kịp bị kick
+    /// khỏi room lẫn simulation. Trả về số player bị kick
+    pub async fn run_ready_checks(&self, now: u64) -> usize {
+        let mut room_manager = self.room_manager.write().await;
+        let starting: Vec<String> = room_manager
+            .rooms()
+            .filter(|room| room.state == RoomState::Starting)
+            .map(|room| room.id.clone())
+            .collect();
+        let names: HashMap<String, String> = room_manager
+            .rooms()
+            .flat_map(|room| room.players.values())
+            .map(|player| (player.id.clone(), player.name.clone()))
+            .collect();
+        let kicked = room_manager.update_ready_checks(now);
+        for room_id in &starting {
+            if let Some(room) = room_manager.get_room(room_id).filter(|room| room.state == RoomState::Playing) {
+                self.start_room_world(room, room_manager.required_tick_hz()).await;
+            }
+        }
+        for (room_id, player_id) in &kicked {
+            {
+                let mut game_world = self.game_world.write().await;
+                game_world.remove_player(player_id);
+                let name = names.get(player_id).map_or(player_id.as_str(), String::as_str);
+                let message = SystemMessage::for_player(system_message::PLAYER_KICKED, player_id, name)
+                    .with_param("reason", "ready_timeout");
+                self.push_system_message(&mut game_world, room_id, None, message);
+            }
+            self.room_events.player_left(room_id, player_id, "ready_timeout");
+            self.backfill_bots(&mut room_manager, room_id).await;
+            self.close_events_if_room_closed(&room_manager, room_id);
+            info!(%room_id, %player_id, "worker: kicked player who never readied up");
+        }
+        kicked.len()
+    }
+
+    /// Room vào Playing thì sinh lại world từ seed của room (world dùng chung nên room vào sau đè seed room trước)
+    /// và báo game.started cho room
+    async fn start_room_world(&self, room: &Room, required_tick_hz: Option<u32>) {
+        let mut game_world = self.game_world.write().await;
+        if let Some(seed) = room.settings.seed {
+            game_world.reseed(seed);
+            info!(room_id = %room.id, seed, "worker: seeded simulation for room");
+        }
+        if let Some(hz) = required_tick_hz {
+            game_world.set_tick_hz(hz);
+            info!(room_id = %room.id, room_tick_hz = room.settings.tick_hz, world_tick_hz = hz, "worker: simulation tick rate for room");
+        }
+        let mode = room.settings.game_mode.descriptor();
+        let message = SystemMessage::new(system_message::GAME_STARTED)
+            .with_param("game_mode", room.settings.game_mode.as_str())
+            .with_param("mode_name", mode.map_or_else(|| room.settings.game_mode.as_str().to_string(), |mode| mode.display_name));
+        self.push_system_message(&mut game_world, &room.id, None, message);
+    }
+
+    /// Ghi system message vào chat của room (player thấy trong snapshot kế tiếp) và phát cho stream của room;
+    /// `recipient_id` để chỉ player đó nhận
+    fn push_system_message(&self, game_world: &mut GameWorld, room_id: &str, recipient_id: Option<&str>, message: SystemMessage) {
+        self.room_events.system_message(room_id, recipient_id, &message);
+        game_world.add_system_message(Some(room_id), recipient_id, message);
+    }
+
+    /// Log số liệu delta encoding của từng room trong cửa sổ kể từ lần gọi trước (`last` giữ tổng lần trước).
+    /// Trả về số room đã log
+    pub async fn log_encoding_summary(&self, last: &mut HashMap<String, EncodingStats>) -> usize {
+        let room_players: Vec<(String, Vec<String>)> = self
+            .room_manager
+            .read()
+            .await
+            .rooms()
+            .map(|room| (room.id.clone(), room.players.keys().cloned().collect()))
+            .collect();
+        let game_world = self.game_world.read().await;
+
+        let mut totals = HashMap::new();
+        for (room_id, players) in room_players {
+            let mut total = EncodingStats::default();
+            for stats in players.iter().filter_map(|player_id| game_world.player_encoding_stats(player_id)) {
+                total.merge(&stats);
+            }
+            totals.insert(room_id, total);
+        }
+        drop(game_world);
+
+        let mut logged = 0;
+        for (room_id, total) in &totals {
+            let window = total.since(&last.get(room_id).copied().unwrap_or_default());
+            if window.encodes == 0 {
+                continue;
+            }
+            info!(
+                %room_id,
+                encodes = window.encodes,
+                deltas_sent = window.deltas_sent,
+                fulls_sent = window.fulls_sent(),
+                avg_delta_bytes = window.average_delta_bytes() as u64,
+                avg_full_bytes = window.average_full_bytes() as u64,
+                delta_ratio = format!("{:.3}", window.delta_ratio()),
+                sent_bytes = window.sent_bytes,
+                created = window.created,
+                updated = window.updated,
+                deleted = window.deleted,
+                below_threshold = window.forced(FullSnapshotReason::BelowThreshold),
+                too_many_changes = window.forced(FullSnapshotReason::TooManyChanges),
+                "worker: delta encoding summary"
+            );
+            logged += 1;
+        }
+        *last = totals;
+        logged
+    }
+
+    /// Room vừa đóng (người cuối rời) thì kết thúc stream sự kiện của room; room chỉ có trong simulation thì giữ
+    fn close_events_if_room_closed(&self, room_manager: &RoomManager, room_id: &str) {
+        if room_manager.get_room(room_id).is_some_and(|room| room.state == RoomState::Closed)
+            && self.room_events.close(room_id)
+        {
+            info!(%room_id, "worker: room closed, ended its event streams");
+        }
+    }
+
+    /// Room bật `backfill_with_bots`: lấp bot tới `min_players_to_start`, hết người thật thì dọn bot
+    async fn backfill_bots(&self, room_manager: &mut RoomManager, room_id: &str) {
+        let Some(room) = room_manager.get_room_mut(room_id) else {
+            return;
+        };
+
+        if room.human_count() == 0 {
+            let bot_ids = room.bot_ids();
+            if bot_ids.is_empty() {
+                return;
+            }
+            for bot_id in &bot_ids {
+                room.players.remove(bot_id);
+            }
+            let mut game_world = self.game_world.write().await;
+            for bot_id in &bot_ids {
+                game_world.remove_player(bot_id);
+            }
+            info!(%room_id, removed = bot_ids.len(), "worker: no humans left, removed bots");
+            return;
+        }
+
+        let needed = room.bot_backfill_needed();
+        if needed > 0 {
+            let mut game_world = self.game_world.write().await;
+            let (bot_ids, _) = spawn_bots(room, &mut game_world, needed as u32, BotDifficulty::Normal);
+            info!(%room_id, added = bot_ids.len(), "worker: backfilled room with bots");
         }
     }
 }
@@ -53,6 +452,48 @@ impl WorkerService {
     }
 }
 
+fn room_players_to_proto(players: &[RoomPlayer]) -> Vec<proto::worker::v1::RoomPlayerInfo> {
+    players
+        .iter()
+        .map(|player| proto::worker::v1::RoomPlayerInfo {
+            id: player.id.clone(),
+            name: player.name.clone(),
+            is_ready: player.is_ready,
+            is_host: player.is_host,
+            is_bot: player.is_bot,
+        })
+        .collect()
+}
+
+/// Tên hiển thị của player trong room, không còn trong room thì dùng player_id
+/// `settings_json` của proto RoomSettings: rỗng khi room không có settings JSON
+fn settings_json(custom: &serde_json::Value) -> String {
+    if custom.is_null() { String::new() } else { custom.to_string() }
+}
+
+fn player_name(room_manager: &RoomManager, room_id: &str, player_id: &str) -> String {
+    room_manager
+        .get_room(room_id)
+        .and_then(|room| room.players.get(player_id))
+        .map_or_else(|| player_id.to_string(), |player| player.name.clone())
+}
+
+/// Thêm tối đa `count` bot vào room lẫn game world; dừng ở lỗi đầu tiên (thường là RoomFull)
+fn spawn_bots(room: &mut Room, game_world: &mut GameWorld, count: u32, difficulty: BotDifficulty) -> (Vec<String>, Option<RoomError>) {
+    let mut bot_ids = Vec::new();
+    game_world.apply_spawn_settings(&room.settings.custom);
+    for _ in 0..count {
+        let team = room.next_team();
+        let bot_id = game_world.add_bot_with_team(&room.id, difficulty, team.as_deref());
+        if let Err(e) = room.add_bot(bot_id.clone()) {
+            game_world.remove_player(&bot_id);
+            return (bot_ids, Some(e));
+        }
+        bot_ids.push(bot_id);
+    }
+    (bot_ids, None)
+}
+
 #[tonic::async_trait]
 impl Worker for WorkerService {
     async fn join_room(
@@ -60,15 +501,62 @@ impl Worker for WorkerService {
         request: tonic::Request<JoinRoomRequest>,
     ) -> Result<Response<JoinRoomResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
         let room_id = req.room_id.clone();
         let player_id = req.player_id.clone();
 
         info!(%room_id, %player_id, "worker: player joining room");
 
+        if self.state.is_draining() {
+            warn!(%room_id, %player_id, "worker: draining, join refused");
+            return Ok(Response::new(JoinRoomResponse {
+                ok: false,
+                room_id,
+                snapshot: None,
+                error: DRAINING_ERROR.to_string(),
+                resumed: false,
+            }));
+        }
+
+        // JoinRoomAsPlayer đã báo player_joined cho thành viên room; ở đây chỉ báo player chỉ có entity hoặc nối lại
+        let (member_name, team, spawn_settings, resume_mode) = {
+            let room_manager = self.state.room_manager.read().await;
+            let room = room_manager.get_room(&room_id);
+            let member = room.and_then(|room| room.players.get(&player_id));
+            (
+                member.map(|player| player.name.clone()),
+                member.and_then(|player| player.team.clone()),
+                room.map_or(serde_json::Value::Null, |room| room.settings.custom.clone()),
+                room.filter(|room| room.settings.resume_progress).map(|room| room.settings.game_mode.as_str()),
+            )
+        };
+        // Đọc checkpoint trước khi khoá world vì phải gọi PocketBase
+        let checkpoint = match resume_mode {
+            Some(game_mode) => self.state.resume_checkpoint(&player_id, game_mode).await,
+            None => None,
+        };
         let mut game_world = self.state.game_world.write().await;
 
-        // Add player vào game world
-        let player_entity = game_world.add_player(player_id.clone());
+        // Rejoin trong grace thì nối lại entity cũ, ngược lại spawn player mới
+        game_world.apply_spawn_settings(&spawn_settings);
+        let join = game_world.join_player_with_team(player_id.clone(), team.as_deref());
+        game_world.set_player_room(&player_id, &room_id);
+        if join.resumed {
+            info!(%room_id, %player_id, "worker: player resumed previous entity");
+        } else if let Some(checkpoint) = checkpoint {
+            if game_world.restore_player_score(&player_id, checkpoint.score) {
+                info!(%room_id, %player_id, score = checkpoint.score, from_room = %checkpoint.room_id, "worker: restored player score from checkpoint");
+            }
+        }
+        // Tên gateway gửi (đã qua name policy của room-manager) được ưu tiên để Player component và chat cùng một chuỗi
+        let name = Some(req.player_name).filter(|name| !name.is_empty()).or_else(|| member_name.clone());
+        game_world.set_player_name(&player_id, name.as_deref().unwrap_or(&player_id));
+        if join.resumed || member_name.is_none() {
+            self.state.room_events.player_joined(&room_id, &player_id, join.resumed);
+            let name = name.as_deref().unwrap_or(&player_id);
+            let message = SystemMessage::for_player(system_message::PLAYER_JOINED, &player_id, name);
+            self.state.push_system_message(&mut game_world, &room_id, None, message);
+        }
 
         // Create initial AOI snapshot cho player mới
         let player_position = [0.0, 5.0, 0.0]; // Player spawn position
@@ -94,6 +582,7 @@ impl Worker for WorkerService {
                 payload_json: snapshot_json,
             }),
             error: String::new(),
+            resumed: join.resumed,
         }))
     }
 
@@ -102,6 +591,7 @@ impl Worker for WorkerService {
         request: tonic::Request<LeaveRoomRequest>,
     ) -> Result<Response<LeaveRoomResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, "");
         let room_id = req.room_id;
 
         // For now, just update metrics (in real implementation would remove player entity)
@@ -121,37 +611,29 @@ impl Worker for WorkerService {
         request: tonic::Request<PushInputRequest>,
     ) -> Result<Response<PushInputResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, "");
 
         info!(room_id = %req.room_id, sequence = %req.sequence, "worker: processing input");
 
         let mut game_world = self.state.game_world.write().await;
 
-        // Parse input từ JSON
-        let input: PlayerInput = match serde_json::from_str(&req.payload_json) {
+        // Parse + kiểm nội dung input trước khi xử lý (movement NaN/inf không được vào physics);
+        // sequence/rate limit được kiểm một lần lúc tick lấy input ra
+        let input = match validation::utils::parse_input_payload(&req.payload_json, &game_world.input_validator) {
             Ok(input) => input,
-            Err(e) => {
-                warn!("Failed to parse player input: {}", e);
+            Err(validation_error) => {
+                warn!(room_id = %req.room_id, "Rejected player input: {}", validation_error);
                 return Ok(Response::new(PushInputResponse {
                     ok: false,
                     room_id: req.room_id,
                     snapshot: None,
-                    error: format!("invalid_json: {}", e),
+                    error: format!("{}: {}", validation_error.code(), validation_error),
                 }));
             }
         };
 
         let player_id = input.player_id.clone();
-
-        // Validate input trước khi xử lý
-        if let Err(validation_error) = game_world.input_validator.validate_input(&input) {
-            warn!("Input validation failed for player {}: {}", player_id, validation_error);
-            return Ok(Response::new(PushInputResponse {
-                ok: false,
-                room_id: req.room_id,
-                snapshot: None,
-                error: format!("validation_error: {}", validation_error),
-            }));
-        }
+        record_span_ids("", &player_id);
 
         // Add input vào buffer sau khi validation thành công
         game_world.input_buffers
@@ -159,8 +641,11 @@ impl Worker for WorkerService {
             .or_insert_with(|| crate::simulation::InputBuffer::new())
             .add_input(input);
 
-        // Run game tick để process input
+        // Run game tick để process input - luôn chạy ít nhất một fixed step để input
+        // được xử lý ngay và snapshot trả về có tick mới
+        game_world.accumulator = game_world.accumulator.max(game_world.tick_rate);
         game_world.tick();
+        self.state.room_events.snapshot_ready(game_world.get_current_tick());
 
         // Get current snapshot with AOI optimization and delta encoding
         let snapshot = game_world.get_snapshot_for_player(&player_id);
@@ -192,6 +677,31 @@ impl Worker for WorkerService {
 
         info!(room_name = %req.room_name, host_id = %req.host_id, "worker: creating room");
 
+        if self.state.is_draining() {
+            return Ok(Response::new(CreateRoomResponse {
+                success: false,
+                room_id: String::new(),
+                error: DRAINING_ERROR.to_string(),
+            }));
+        }
+
+        let custom = match req.settings.as_ref().map(|s| s.settings_json.trim()).filter(|json| !json.is_empty()) {
+            Some(json) => match serde_json::from_str::<serde_json::Value>(json) {
+                Ok(custom) if custom.is_object() => custom,
+                Ok(_) => return Ok(Response::new(CreateRoomResponse {
+                    success: false,
+                    room_id: String::new(),
+                    error: "settings_json must be a JSON object".to_string(),
+                })),
+                Err(e) => return Ok(Response::new(CreateRoomResponse {
+                    success: false,
+                    room_id: String::new(),
+                    error: format!("invalid settings_json: {}", e),
+                })),
+            },
+            None => serde_json::Value::Null,
+        };
+
         let mut room_manager = self.state.room_manager.write().await;
 
         // Convert proto RoomSettings to internal RoomSettings
@@ -203,6 +713,7 @@ impl Worker for WorkerService {
                     1 => Some(GameMode::TeamDeathmatch),
                     2 => Some(GameMode::CaptureTheFlag),
                     3 => Some(GameMode::KingOfTheHill),
+                    4 => Some(GameMode::EndlessRunner),
                     _ => None,
                 })
                 .unwrap_or(GameMode::Deathmatch),
@@ -218,11 +729,39 @@ impl Worker for WorkerService {
             allow_spectators: req.settings.as_ref().map_or(true, |s| s.allow_spectators),
             auto_start: req.settings.as_ref().map_or(true, |s| s.auto_start),
             min_players_to_start: req.settings.as_ref().map_or(2, |s| s.min_players_to_start),
+            backfill_with_bots: req.settings.as_ref().is_some_and(|s| s.backfill_with_bots),
+            rejoin_grace_seconds: req.settings.as_ref()
+                .map(|s| s.rejoin_grace_seconds)
+                .filter(|&secs| secs > 0)
+                .unwrap_or(DEFAULT_REJOIN_GRACE_SECONDS),
+            ready_timeout_seconds: req.settings.as_ref()
+                .map(|s| s.ready_timeout_seconds)
+                .filter(|&secs| secs > 0)
+                .unwrap_or(DEFAULT_READY_TIMEOUT_SECONDS),
+            score_target: req.settings.as_ref()
+                .map(|s| s.score_target)
+                .filter(|&target| target > 0),
+            adaptive_tick_rate: req.settings.as_ref().is_some_and(|s| s.adaptive_tick_rate),
+            tick_hz: tick_rate::clamp_tick_hz(req.settings.as_ref().map_or(0, |s| s.tick_hz)),
+            seed: req.settings.as_ref().and_then(|s| s.seed),
+            motd: req.settings.as_ref()
+                .and_then(|s| s.motd.as_deref())
+                .map(|motd| motd.trim().chars().take(MAX_ANNOUNCEMENT_CHARS).collect::<String>())
+                .filter(|motd| !motd.is_empty()),
+            checkpoint_interval_seconds: req.settings.as_ref()
+                .map(|s| s.checkpoint_interval_seconds)
+                .filter(|&secs| secs > 0)
+                .unwrap_or(DEFAULT_CHECKPOINT_INTERVAL_SECONDS),
+            resume_progress: req.settings.as_ref().is_some_and(|s| s.resume_progress),
+            custom,
         };
+        let spawn_settings = settings.custom.clone();
 
         match room_manager.create_room(req.room_name, req.host_id, req.host_name, settings) {
             Ok(room_id) => {
                 info!("Room created successfully: {}", room_id);
+                self.state.game_world.write().await.apply_spawn_settings(&spawn_settings);
+                self.state.backfill_bots(&mut room_manager, &room_id).await;
                 Ok(Response::new(CreateRoomResponse {
                     success: true,
                     room_id,
@@ -259,6 +798,7 @@ impl Worker for WorkerService {
                     1 => GameMode::TeamDeathmatch,
                     2 => GameMode::CaptureTheFlag,
                     3 => GameMode::KingOfTheHill,
+                    4 => GameMode::EndlessRunner,
                     _ => GameMode::Deathmatch,
                 }),
                 has_password: if f.has_password { Some(true) } else { None },
@@ -291,6 +831,7 @@ impl Worker for WorkerService {
                         GameMode::TeamDeathmatch => 1,
                         GameMode::CaptureTheFlag => 2,
                         GameMode::KingOfTheHill => 3,
+                        GameMode::EndlessRunner => 4,
                     },
                     map_name: room.settings.map_name,
                     time_limit_seconds: room.settings.time_limit.map_or(0, |d| d.as_secs() as u32),
@@ -299,6 +840,17 @@ impl Worker for WorkerService {
                     allow_spectators: room.settings.allow_spectators,
                     auto_start: room.settings.auto_start,
                     min_players_to_start: room.settings.min_players_to_start,
+                    backfill_with_bots: room.settings.backfill_with_bots,
+                    rejoin_grace_seconds: room.settings.rejoin_grace_seconds,
+                    ready_timeout_seconds: room.settings.ready_timeout_seconds,
+                    score_target: room.settings.score_target.unwrap_or_default(),
+                    adaptive_tick_rate: room.settings.adaptive_tick_rate,
+                    tick_hz: room.settings.tick_hz,
+                    seed: room.settings.seed,
+                    motd: room.settings.motd.clone(),
+                    checkpoint_interval_seconds: room.settings.checkpoint_interval_seconds,
+                    resume_progress: room.settings.resume_progress,
+                    settings_json: settings_json(&room.settings.custom),
                 }),
                 state: match room.state {
                     RoomState::Waiting => 0,
@@ -316,8 +868,11 @@ impl Worker for WorkerService {
                     GameMode::TeamDeathmatch => 1,
                     GameMode::CaptureTheFlag => 2,
                     GameMode::KingOfTheHill => 3,
+                    GameMode::EndlessRunner => 4,
                 },
                 created_at_seconds_ago: room.created_at,
+                players: room_players_to_proto(&room.players),
+                countdown_seconds_left: room.countdown_seconds_left.unwrap_or_default() as u32,
             }
         }).collect();
 
@@ -333,6 +888,7 @@ impl Worker for WorkerService {
         request: tonic::Request<GetRoomInfoRequest>,
     ) -> Result<Response<GetRoomInfoResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, "");
 
         info!(room_id = %req.room_id, "worker: getting room info");
 
@@ -350,6 +906,7 @@ impl Worker for WorkerService {
                             GameMode::TeamDeathmatch => 1,
                             GameMode::CaptureTheFlag => 2,
                             GameMode::KingOfTheHill => 3,
+                            GameMode::EndlessRunner => 4,
                         },
                         map_name: room_info.settings.map_name,
                         time_limit_seconds: room_info.settings.time_limit.map_or(0, |d| d.as_secs() as u32),
@@ -358,6 +915,17 @@ impl Worker for WorkerService {
                         allow_spectators: room_info.settings.allow_spectators,
                         auto_start: room_info.settings.auto_start,
                         min_players_to_start: room_info.settings.min_players_to_start,
+                        backfill_with_bots: room_info.settings.backfill_with_bots,
+                        rejoin_grace_seconds: room_info.settings.rejoin_grace_seconds,
+                        ready_timeout_seconds: room_info.settings.ready_timeout_seconds,
+                        score_target: room_info.settings.score_target.unwrap_or_default(),
+                        adaptive_tick_rate: room_info.settings.adaptive_tick_rate,
+                        tick_hz: room_info.settings.tick_hz,
+                        seed: room_info.settings.seed,
+                        motd: room_info.settings.motd.clone(),
+                        checkpoint_interval_seconds: room_info.settings.checkpoint_interval_seconds,
+                        resume_progress: room_info.settings.resume_progress,
+                        settings_json: settings_json(&room_info.settings.custom),
                     }),
                     state: match room_info.state {
                         RoomState::Waiting => 0,
@@ -375,8 +943,11 @@ impl Worker for WorkerService {
                         GameMode::TeamDeathmatch => 1,
                         GameMode::CaptureTheFlag => 2,
                         GameMode::KingOfTheHill => 3,
+                        GameMode::EndlessRunner => 4,
                     },
                     created_at_seconds_ago: room_info.created_at,
+                    players: room_players_to_proto(&room_info.players),
+                    countdown_seconds_left: room_info.countdown_seconds_left.unwrap_or_default() as u32,
                 };
 
                 Ok(Response::new(GetRoomInfoResponse {
@@ -401,14 +972,40 @@ impl Worker for WorkerService {
         request: tonic::Request<JoinRoomAsPlayerRequest>,
     ) -> Result<Response<JoinRoomAsPlayerResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
 
         info!(room_id = %req.room_id, player_id = %req.player_id, "worker: player joining room");
 
+        if self.state.is_draining() {
+            return Ok(Response::new(JoinRoomAsPlayerResponse {
+                success: false,
+                error: DRAINING_ERROR.to_string(),
+            }));
+        }
+
         let mut room_manager = self.state.room_manager.write().await;
 
-        match room_manager.join_room(&req.room_id, req.player_id, req.player_name) {
+        // Room backfill đã đầy bot thì một bot nhường chỗ
+        let evicted = room_manager
+            .get_room_mut(&req.room_id)
+            .and_then(|room| room.evict_bot_for_human(&req.player_id));
+        if let Some(bot_id) = evicted {
+            self.state.game_world.write().await.remove_player(&bot_id);
+            info!(room_id = %req.room_id, %bot_id, "worker: bot left to make room for player");
+        }
+
+        match room_manager.join_room(&req.room_id, req.player_id.clone(), req.player_name.clone()) {
             Ok(_) => {
                 info!("Player joined room successfully");
+                self.state.room_events.player_joined(&req.room_id, &req.player_id, false);
+                let mut game_world = self.state.game_world.write().await;
+                game_world.set_player_room(&req.player_id, &req.room_id);
+                let joined = SystemMessage::for_player(system_message::PLAYER_JOINED, &req.player_id, &req.player_name);
+                self.state.push_system_message(&mut game_world, &req.room_id, None, joined);
+                if let Some(motd) = room_manager.get_room(&req.room_id).and_then(|room| room.settings.motd.clone()) {
+                    let motd = SystemMessage::new(system_message::ROOM_MOTD).with_param("motd", motd);
+                    self.state.push_system_message(&mut game_world, &req.room_id, Some(&req.player_id), motd);
+                }
                 Ok(Response::new(JoinRoomAsPlayerResponse {
                     success: true,
                     error: String::new(),
@@ -429,9 +1026,17 @@ impl Worker for WorkerService {
         request: tonic::Request<JoinRoomAsSpectatorRequest>,
     ) -> Result<Response<JoinRoomAsSpectatorResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, "");
 
         info!(room_id = %req.room_id, spectator_id = %req.spectator_id, "worker: spectator joining room");
 
+        if self.state.is_draining() {
+            return Ok(Response::new(JoinRoomAsSpectatorResponse {
+                success: false,
+                error: DRAINING_ERROR.to_string(),
+            }));
+        }
+
         let mut room_manager = self.state.room_manager.write().await;
 
         // First, join the room as spectator
@@ -465,14 +1070,21 @@ impl Worker for WorkerService {
         request: tonic::Request<LeaveRoomAsPlayerRequest>,
     ) -> Result<Response<LeaveRoomAsPlayerResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
 
         info!(room_id = %req.room_id, player_id = %req.player_id, "worker: player leaving room");
 
         let mut room_manager = self.state.room_manager.write().await;
+        let name = player_name(&room_manager, &req.room_id, &req.player_id);
 
         match room_manager.leave_room(&req.room_id, &req.player_id) {
             Ok(_) => {
                 info!("Player left room successfully");
+                self.state.room_events.player_left(&req.room_id, &req.player_id, "left");
+                let message = SystemMessage::for_player(system_message::PLAYER_LEFT, &req.player_id, &name);
+                self.state.push_system_message(&mut *self.state.game_world.write().await, &req.room_id, None, message);
+                self.state.backfill_bots(&mut room_manager, &req.room_id).await;
+                self.state.close_events_if_room_closed(&room_manager, &req.room_id);
                 Ok(Response::new(LeaveRoomAsPlayerResponse {
                     success: true,
                     error: String::new(),
@@ -498,6 +1110,7 @@ impl Worker for WorkerService {
         request: tonic::Request<StartGameRequest>,
     ) -> Result<Response<StartGameResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
 
         info!(room_id = %req.room_id, player_id = %req.player_id, "worker: starting game");
 
@@ -505,6 +1118,9 @@ impl Worker for WorkerService {
 
         match room_manager.start_game(&req.room_id, &req.player_id) {
             Ok(_) => {
+                if let Some(room) = room_manager.get_room(&req.room_id) {
+                    self.state.start_room_world(room, room_manager.required_tick_hz()).await;
+                }
                 info!("Game started successfully");
                 Ok(Response::new(StartGameResponse {
                     success: true,
@@ -526,14 +1142,29 @@ impl Worker for WorkerService {
         request: tonic::Request<EndGameRequest>,
     ) -> Result<Response<EndGameResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, "");
 
         info!(room_id = %req.room_id, "worker: ending game");
 
+        let tick = self.state.game_world.read().await.get_current_tick();
         let mut room_manager = self.state.room_manager.write().await;
 
         match room_manager.end_game(&req.room_id) {
             Ok(_) => {
                 info!("Game ended successfully");
+                let room = room_manager.get_room(&req.room_id);
+                let result = room.and_then(|room| room.match_result());
+                self.state.submit_scores(room.map(|room| room.final_score_submissions(tick)).unwrap_or_default());
+                let scoreboard = result.as_ref().map(|result| result.players.as_slice()).unwrap_or_default();
+                self.state.room_events.match_ended(&req.room_id, "ended", scoreboard);
+                if let (Some(store), Some(result)) = (self.state.match_store.clone(), result) {
+                    // Ghi kết quả ở background để không giữ lock room_manager khi gọi HTTP
+                    tokio::spawn(async move {
+                        if let Err(err) = store.save_match_result(&result).await {
+                            warn!(room_id = %result.room_id, %err, "worker: failed to persist match result");
+                        }
+                    });
+                }
                 Ok(Response::new(EndGameResponse {
                     success: true,
                     error: String::new(),
@@ -554,6 +1185,7 @@ impl Worker for WorkerService {
         request: tonic::Request<SetPlayerReadyRequest>,
     ) -> Result<Response<SetPlayerReadyResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
 
         info!(room_id = %req.room_id, player_id = %req.player_id, ready = %req.ready, "worker: setting player ready");
 
@@ -582,6 +1214,7 @@ impl Worker for WorkerService {
         request: tonic::Request<UpdatePlayerPingRequest>,
     ) -> Result<Response<UpdatePlayerPingResponse>, Status> {
         let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
 
         info!(room_id = %req.room_id, player_id = %req.player_id, ping = %req.ping, "worker: updating player ping");
 
@@ -603,13 +1236,535 @@ impl Worker for WorkerService {
             }
         }
     }
+
+    async fn get_player_snapshot(
+        &self,
+        request: tonic::Request<GetPlayerSnapshotRequest>,
+    ) -> Result<Response<GetPlayerSnapshotResponse>, Status> {
+        let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
+
+        // Gọi mỗi broadcast tick cho từng connection nên không log ở đây
+        let snapshot = self.state.game_world.write().await.get_snapshot_for_player(&req.player_id);
+        Ok(Response::new(player_snapshot_response(&req, snapshot)))
+    }
+
+    async fn request_keyframe(
+        &self,
+        request: tonic::Request<GetPlayerSnapshotRequest>,
+    ) -> Result<Response<GetPlayerSnapshotResponse>, Status> {
+        let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
+
+        info!(room_id = %req.room_id, player_id = %req.player_id, "worker: keyframe requested");
+        let snapshot = self.state.game_world.write().await.force_keyframe_for_player(&req.player_id);
+        Ok(Response::new(player_snapshot_response(&req, snapshot)))
+    }
+
+    async fn add_bots(
+        &self,
+        request: tonic::Request<AddBotsRequest>,
+    ) -> Result<Response<AddBotsResponse>, Status> {
+        let req = request.into_inner();
+        record_span_ids(&req.room_id, "");
+        let failure = |error: String| {
+            Response::new(AddBotsResponse {
+                success: false,
+                bot_ids: Vec::new(),
+                error,
+            })
+        };
+
+        let Some(difficulty) = BotDifficulty::parse(&req.difficulty) else {
+            return Ok(failure(format!("Invalid bot difficulty: {}", req.difficulty)));
+        };
+        if req.count == 0 || req.count > MAX_BOTS_PER_REQUEST {
+            return Ok(failure(format!("Bot count must be between 1 and {}", MAX_BOTS_PER_REQUEST)));
+        }
+
+        info!(room_id = %req.room_id, count = req.count, difficulty = difficulty.as_str(), "worker: adding bots");
+
+        let mut room_manager = self.state.room_manager.write().await;
+        let Some(room) = room_manager.get_room_mut(&req.room_id) else {
+            return Ok(failure(RoomError::RoomNotFound.to_string()));
+        };
+        let mut game_world = self.state.game_world.write().await;
+
+        // Room đầy giữa chừng thì vẫn trả về các bot đã thêm, kèm lỗi
+        let (bot_ids, error) = spawn_bots(room, &mut game_world, req.count, difficulty);
+        let error = error.map(|e| e.to_string()).unwrap_or_default();
+        if bot_ids.is_empty() {
+            warn!(room_id = %req.room_id, %error, "Failed to add bots");
+            return Ok(failure(error));
+        }
+
+        Ok(Response::new(AddBotsResponse {
+            success: true,
+            bot_ids,
+            error,
+        }))
+    }
+
+    async fn list_active_rooms(
+        &self,
+        _request: tonic::Request<ListActiveRoomsRequest>,
+    ) -> Result<Response<ListActiveRoomsResponse>, Status> {
+        let room_manager = self.state.room_manager.read().await;
+        let mut game_world = self.state.game_world.write().await;
+        let tick = game_world.current_tick;
+
+        let mut rooms: Vec<ActiveRoom> = room_manager
+            .rooms()
+            .filter(|room| room.state != RoomState::Closed)
+            .map(|room| {
+                let members = room.players.keys().chain(room.spectators.keys()).map(String::as_str);
+                ActiveRoom {
+                    room_id: room.id.clone(),
+                    player_count: room.players.len() as u32,
+                    spectator_count: room.spectators.len() as u32,
+                    tick,
+                    entity_count: game_world.entity_count_for(members) as u32,
+                }
+            })
+            .collect();
+        rooms.sort_by(|a, b| a.room_id.cmp(&b.room_id));
+
+        Ok(Response::new(ListActiveRoomsResponse { rooms }))
+    }
+
+    async fn notify_disconnect(
+        &self,
+        request: tonic::Request<NotifyDisconnectRequest>,
+    ) -> Result<Response<NotifyDisconnectResponse>, Status> {
+        let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
+        let room_manager = self.state.room_manager.read().await;
+        let grace_seconds = room_manager
+            .get_room(&req.room_id)
+            .map_or(DEFAULT_REJOIN_GRACE_SECONDS, |room| room.settings.rejoin_grace_seconds);
+        let mut game_world = self.state.game_world.write().await;
+
+        if !game_world.disconnect_player(&req.player_id, std::time::Duration::from_secs(grace_seconds as u64)) {
+            return Ok(Response::new(NotifyDisconnectResponse {
+                ok: false,
+                grace_seconds: 0,
+                error: format!("player {} has no entity", req.player_id),
+            }));
+        }
+
+        info!(room_id = %req.room_id, player_id = %req.player_id, grace_seconds, "worker: player disconnected, holding entity");
+        self.state.room_events.player_left(&req.room_id, &req.player_id, "disconnected");
+        let name = player_name(&room_manager, &req.room_id, &req.player_id);
+        let message = SystemMessage::for_player(system_message::PLAYER_DISCONNECTED, &req.player_id, &name);
+        self.state.push_system_message(&mut game_world, &req.room_id, None, message);
+        Ok(Response::new(NotifyDisconnectResponse {
+            ok: true,
+            grace_seconds,
+            error: String::new(),
+        }))
+    }
+
+    async fn remove_player(
+        &self,
+        request: tonic::Request<RemovePlayerRequest>,
+    ) -> Result<Response<RemovePlayerResponse>, Status> {
+        let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
+        let mut room_manager = self.state.room_manager.write().await;
+
+        // Player có thể chỉ có entity (join qua JoinRoom) mà không có trong room, nên không coi là lỗi
+        let name = player_name(&room_manager, &req.room_id, &req.player_id);
+        let left_room = room_manager.leave_room(&req.room_id, &req.player_id).is_ok();
+        let despawned = self.state.game_world.write().await.remove_player(&req.player_id);
+        if left_room {
+            self.state.backfill_bots(&mut room_manager, &req.room_id).await;
+        }
+        if left_room || despawned {
+            let reason = if req.reason.is_empty() { "removed" } else { req.reason.as_str() };
+            self.state.room_events.player_left(&req.room_id, &req.player_id, reason);
+            let message = SystemMessage::for_player(system_message::PLAYER_KICKED, &req.player_id, &name)
+                .with_param("reason", reason);
+            self.state.push_system_message(&mut *self.state.game_world.write().await, &req.room_id, None, message);
+            self.state.close_events_if_room_closed(&room_manager, &req.room_id);
+        }
+
+        if !left_room && !despawned {
+            return Ok(Response::new(RemovePlayerResponse {
+                ok: false,
+                error: format!("player {} is not in room {}", req.player_id, req.room_id),
+            }));
+        }
+
+        info!(room_id = %req.room_id, player_id = %req.player_id, reason = %req.reason, despawned, "worker: player removed");
+        Ok(Response::new(RemovePlayerResponse {
+            ok: true,
+            error: String::new(),
+        }))
+    }
+
+    async fn update_player_latency(
+        &self,
+        request: tonic::Request<UpdatePlayerLatencyRequest>,
+    ) -> Result<Response<UpdatePlayerLatencyResponse>, Status> {
+        let req = request.into_inner();
+
+        // Gọi mỗi giây từ gateway nên không log ở đây
+        let mut game_world = self.state.game_world.write().await;
+        let mut updated = 0;
+        let mut not_players = Vec::new();
+        for latency in req.latencies {
+            if game_world.set_player_rtt(&latency.player_id, latency.rtt_ms) {
+                updated += 1;
+            } else {
+                not_players.push(latency.player_id);
+            }
+        }
+
+        Ok(Response::new(UpdatePlayerLatencyResponse { updated, not_players }))
+    }
+
+    type StreamRoomEventsStream = Pin<Box<dyn Stream<Item = Result<RoomEvent, Status>> + Send>>;
+
+    async fn stream_room_events(
+        &self,
+        request: tonic::Request<StreamRoomEventsRequest>,
+    ) -> Result<Response<Self::StreamRoomEventsStream>, Status> {
+        let room_id = request.into_inner().room_id;
+        record_span_ids(&room_id, "");
+        if room_id.is_empty() {
+            return Err(Status::invalid_argument("room_id is required"));
+        }
+
+        // Client huỷ stream thì receiver bị drop cùng stream, hub không còn tính subscriber này
+        let events = BroadcastStream::new(self.state.room_events.subscribe(&room_id));
+        info!(%room_id, "worker: room event stream opened");
+        let stream = events.filter_map(move |event| match event {
+            Ok(event) => Some(Ok(event)),
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                warn!(%room_id, skipped, "worker: room event subscriber lagged, events dropped");
+                None
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn send_chat(
+        &self,
+        request: tonic::Request<SendChatRequest>,
+    ) -> Result<Response<SendChatResponse>, Status> {
+        let req = request.into_inner();
+        record_span_ids(&req.room_id, &req.player_id);
+        let message = req.message.trim();
+        if req.room_id.is_empty() || req.player_id.is_empty() || message.is_empty() || message.chars().count() > MAX_CHAT_CHARS {
+            return Ok(Response::new(SendChatResponse {
+                ok: false,
+                subscribers: 0,
+                error: format!("room_id, player_id and a 1-{MAX_CHAT_CHARS} character message are required"),
+            }));
+        }
+
+        let timestamp_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let name = match self.state.game_world.read().await.player_name(&req.player_id) {
+            Some(name) => name,
+            None => player_name(&*self.state.room_manager.read().await, &req.room_id, &req.player_id),
+        };
+        let subscribers = self.state.room_events.chat(&req.room_id, &req.player_id, &name, message, timestamp_ms);
+        Ok(Response::new(SendChatResponse { ok: true, subscribers: subscribers as u32, error: String::new() }))
+    }
+
+    async fn announce(
+        &self,
+        request: tonic::Request<AnnounceRequest>,
+    ) -> Result<Response<AnnounceResponse>, Status> {
+        let req = request.into_inner();
+        record_span_ids(&req.room_id, "");
+        let message = req.message.trim();
+        if message.is_empty() || message.chars().count() > MAX_ANNOUNCEMENT_CHARS {
+            return Ok(Response::new(AnnounceResponse {
+                ok: false,
+                rooms: 0,
+                error: format!("message must be 1-{MAX_ANNOUNCEMENT_CHARS} characters"),
+            }));
+        }
+
+        let room_manager = self.state.room_manager.read().await;
+        let room_ids: Vec<String> = if req.room_id.is_empty() {
+            room_manager.rooms().map(|room| room.id.clone()).collect()
+        } else if room_manager.get_room(&req.room_id).is_some() {
+            vec![req.room_id.clone()]
+        } else {
+            return Ok(Response::new(AnnounceResponse {
+                ok: false,
+                rooms: 0,
+                error: format!("room {} not found", req.room_id),
+            }));
+        };
+
+        let mut game_world = self.state.game_world.write().await;
+        let announcement = SystemMessage::new(system_message::SERVER_ANNOUNCEMENT).with_param("message", message);
+        for room_id in &room_ids {
+            self.state.push_system_message(&mut game_world, room_id, None, announcement.clone());
+        }
+        info!(room_id = %req.room_id, rooms = room_ids.len(), "worker: announcement sent");
+        Ok(Response::new(AnnounceResponse { ok: true, rooms: room_ids.len() as u32, error: String::new() }))
+    }
+
+    async fn get_room_players(
+        &self,
+        request: tonic::Request<GetRoomPlayersRequest>,
+    ) -> Result<Response<GetRoomPlayersResponse>, Status> {
+        let room_id = request.into_inner().room_id;
+        record_span_ids(&room_id, "");
+        if room_id.is_empty() {
+            return Ok(Response::new(GetRoomPlayersResponse {
+                ok: false,
+                players: Vec::new(),
+                error: "room_id is required".to_string(),
+            }));
+        }
+
+        // Thành viên lobby của room trên worker (có tên, team) cộng player chỉ có entity (vào qua JoinRoom)
+        let members: Vec<RoomPlayer> = {
+            let room_manager = self.state.room_manager.read().await;
+            room_manager
+                .get_room(&room_id)
+                .map(|room| room.players.values().cloned().collect())
+                .unwrap_or_default()
+        };
+        let mut live = self.state.game_world.write().await.live_players_in_room(&room_id);
+
+        let mut players: Vec<LiveRoomPlayer> = members
+            .into_iter()
+            .map(|member| {
+                let state = live.remove(&member.id);
+                LiveRoomPlayer {
+                    score: state.map_or(member.score, |state| state.score),
+                    team: member.team.unwrap_or_default(),
+                    connected: state.is_none_or(|state| state.connected),
+                    is_bot: member.is_bot || state.is_some_and(|state| state.is_bot),
+                    is_ready: member.is_ready,
+                    latency_ms: state.filter(|state| !state.is_bot && state.rtt_ms > 0).map(|state| state.rtt_ms),
+                    id: member.id,
+                    name: member.name,
+                }
+            })
+            .collect();
+        players.extend(live.into_iter().map(|(player_id, state)| LiveRoomPlayer {
+            name: player_id.clone(),
+            id: player_id,
+            score: state.score,
+            team: String::new(),
+            connected: state.connected,
+            is_bot: state.is_bot,
+            latency_ms: (!state.is_bot && state.rtt_ms > 0).then_some(state.rtt_ms),
+            is_ready: false,
+        }));
+        players.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(Response::new(GetRoomPlayersResponse { ok: true, players, error: String::new() }))
+    }
+}
+
+fn player_snapshot_response(req: &GetPlayerSnapshotRequest, snapshot: EncodedSnapshot) -> GetPlayerSnapshotResponse {
+    match snapshot.to_json_string() {
+        Ok(payload_json) => GetPlayerSnapshotResponse {
+            ok: true,
+            snapshot: Some(Snapshot {
+                tick: snapshot.tick(),
+                payload_json,
+            }),
+            error: String::new(),
+        },
+        Err(e) => {
+            warn!(room_id = %req.room_id, player_id = %req.player_id, "Failed to encode snapshot: {}", e);
+            GetPlayerSnapshotResponse {
+                ok: false,
+                snapshot: None,
+                error: e.to_string(),
+            }
+        }
+    }
+}
+
+/// Span cho mỗi lời gọi gRPC, mang request id gateway gửi qua metadata để log hai service nối được với nhau.
+/// room_id/player_id được handler ghi sau khi đọc request
+fn rpc_span(request: &tonic::codegen::http::Request<()>) -> tracing::Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(new_request_id);
+    tracing::info_span!(
+        "worker_rpc",
+        request_id = %request_id,
+        method = %request.uri().path(),
+        room_id = tracing::field::Empty,
+        player_id = tracing::field::Empty,
+    )
+}
+
+/// Ghi room_id/player_id lên span của lời gọi hiện tại; chuỗi rỗng là không có
+fn record_span_ids(room_id: &str, player_id: &str) {
+    let span = tracing::Span::current();
+    if !room_id.is_empty() {
+        span.record("room_id", room_id);
+    }
+    if !player_id.is_empty() {
+        span.record("player_id", player_id);
+    }
+}
+
+/// Đường dẫn PEM cho TLS của gRPC server; có `client_ca_path` thì client phải trình cert do CA đó ký (mTLS)
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RpcTlsSettings {
+    pub cert_path: std::path::PathBuf,
+    pub key_path: std::path::PathBuf,
+    #[serde(default)]
+    pub client_ca_path: Option<std::path::PathBuf>,
+}
+
+impl RpcTlsSettings {
+    /// WORKER_RPC_TLS_CERT và WORKER_RPC_TLS_KEY (thêm WORKER_RPC_TLS_CLIENT_CA cho mTLS); thiếu cert hoặc key thì None
+    pub fn from_env() -> Option<Self> {
+        let path = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty()).map(std::path::PathBuf::from);
+        Some(Self {
+            cert_path: path("WORKER_RPC_TLS_CERT")?,
+            key_path: path("WORKER_RPC_TLS_KEY")?,
+            client_ca_path: path("WORKER_RPC_TLS_CLIENT_CA"),
+        })
+    }
+}
+
+/// TLS server đã đọc từ file PEM
+#[derive(Clone)]
+pub struct ServerTls {
+    #[cfg(feature = "tls")]
+    config: tonic::transport::ServerTlsConfig,
+}
+
+impl ServerTls {
+    pub fn load(settings: &RpcTlsSettings) -> Result<Self, crate::BoxError> {
+        #[cfg(feature = "tls")]
+        {
+            use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+            let read = |path: &std::path::Path| {
+                std::fs::read(path).map_err(|err| format!("cannot read {}: {err}", path.display()))
+            };
+            let identity = Identity::from_pem(read(&settings.cert_path)?, read(&settings.key_path)?);
+            let mut config = ServerTlsConfig::new().identity(identity);
+            if let Some(ca_path) = &settings.client_ca_path {
+                config = config.client_ca_root(Certificate::from_pem(read(ca_path)?));
+            }
+            Ok(Self { config })
+        }
+        #[cfg(not(feature = "tls"))]
+        {
+            let _ = settings;
+            Err("worker was built without the `tls` feature, rpc_tls cannot be used".into())
+        }
+    }
 }
 
-pub async fn serve_rpc(addr: std::net::SocketAddr, svc: WorkerService) {
-    info!(%addr, "starting gRPC");
-    if let Err(e) = Server::builder()
-        .add_service(WorkerServer::new(svc))
-        .serve_with_shutdown(addr, async {
+/// Cách gRPC server xác thực client: token nội bộ khi có cấu hình, TLS/mTLS khi có cấu hình.
+/// Không có token thì chỉ được nghe trên loopback (dev local)
+#[derive(Clone)]
+pub struct RpcSecurity {
+    pub token: Option<String>,
+    pub tls: Option<ServerTls>,
+}
+
+impl RpcSecurity {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self { token: Some(token.into()), tls: None }
+    }
+
+    /// Token từ WORKER_RPC_TOKEN nếu có, không TLS
+    pub fn from_env() -> Self {
+        Self { token: proto::auth::internal_token_from_env(), tls: None }
+    }
+
+    pub fn with_tls(mut self, tls: ServerTls) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Không có token thì từ chối địa chỉ ngoài loopback: ai trong mạng cũng gọi được gRPC
+    pub fn check_bind(&self, addr: std::net::SocketAddr) -> Result<(), crate::BoxError> {
+        if self.token.is_none() && !addr.ip().is_loopback() {
+            return Err(format!("worker gRPC on {addr} needs WORKER_RPC_TOKEN (or rpc_token); only loopback may run without one").into());
+        }
+        Ok(())
+    }
+}
+
+/// Từ chối lời gọi thiếu hoặc sai `x-internal-token` trước khi tới handler; không có token thì cho qua (chỉ loopback)
+#[derive(Clone)]
+pub struct RequireInternalToken {
+    token: Option<Arc<str>>,
+}
+
+impl RequireInternalToken {
+    pub fn new(token: Option<&str>) -> Self {
+        Self { token: token.map(Into::into) }
+    }
+}
+
+impl Interceptor for RequireInternalToken {
+    fn call(&mut self, request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+        let Some(expected) = &self.token else {
+            return Ok(request);
+        };
+        let presented = request.metadata().get(INTERNAL_TOKEN_HEADER).and_then(|value| value.to_str().ok());
+        match presented {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(request),
+            _ => {
+                warn!(token_present = presented.is_some(), "worker: rejected gRPC call without a valid internal token");
+                Err(Status::unauthenticated("missing or invalid internal token"))
+            }
+        }
+    }
+}
+
+/// So sánh không dừng sớm ở byte khác đầu tiên, tránh lộ token qua thời gian phản hồi
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+pub async fn serve_rpc(addr: std::net::SocketAddr, svc: WorkerService, security: RpcSecurity) {
+    match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => serve_rpc_on(listener, svc, security).await,
+        Err(e) => error!(?e, %addr, "gRPC bind error"),
+    }
+}
+
+/// Như `serve_rpc` trên listener đã bind sẵn: client kết nối được ngay khi hàm này được gọi
+pub async fn serve_rpc_on(listener: tokio::net::TcpListener, svc: WorkerService, security: RpcSecurity) {
+    let addr = listener.local_addr().ok();
+    if let Some(Err(e)) = addr.map(|addr| security.check_bind(addr)) {
+        error!(%e, "gRPC refused to start");
+        return;
+    }
+    info!(?addr, tls = security.tls.is_some(), token = security.token.is_some(), "starting gRPC");
+    #[allow(unused_mut)]
+    let mut server = Server::builder().trace_fn(rpc_span);
+    #[cfg(feature = "tls")]
+    if let Some(tls) = security.tls {
+        server = match server.tls_config(tls.config) {
+            Ok(server) => server,
+            Err(e) => {
+                error!(?e, "gRPC TLS config error");
+                return;
+            }
+        };
+    }
+    if let Err(e) = server
+        .add_service(WorkerServer::with_interceptor(svc, RequireInternalToken::new(security.token.as_deref())))
+        .serve_with_incoming_shutdown(tokio_stream::wrappers::TcpListenerStream::new(listener), async {
             let _ = tokio::signal::ctrl_c().await;
         })
         .await
@@ -621,22 +1776,35 @@ pub async fn serve_rpc(addr: std::net::SocketAddr, svc: WorkerService) {
 pub fn channel(endpoint: &str) -> Result<Channel, tonic::transport::Error> {
     Ok(Endpoint::from_shared(endpoint.to_string())?.connect_lazy())
 }
-pub type Client = WorkerClient<Channel>;
+pub type Client = WorkerClient<InterceptedService<Channel, AttachInternalToken>>;
+/// Client gắn token từ WORKER_RPC_TOKEN nếu có, khớp với `spawn_test_server`
 pub fn client(endpoint: &str) -> Result<Client, tonic::transport::Error> {
-    Ok(WorkerClient::new(channel(endpoint)?))
+    Ok(WorkerClient::with_interceptor(channel(endpoint)?, AttachInternalToken::from_env()))
 }
 
 pub async fn spawn_test_server() -> (String, tokio::task::JoinHandle<()>) {
-    let listener = TcpListener::bind("127.0.0.1:0").expect("bind worker test");
+    spawn_test_server_with(Arc::new(WorkerState::default())).await
+}
+
+/// Như `spawn_test_server` nhưng test giữ `state` để điều khiển worker trực tiếp.
+/// Listener được bind trước khi trả về nên không cần chờ server khởi động
+pub async fn spawn_test_server_with(state: Arc<WorkerState>) -> (String, tokio::task::JoinHandle<()>) {
+    spawn_test_server_secured(state, RpcSecurity::from_env()).await
+}
+
+/// Như `spawn_test_server_with` với token/TLS do test chỉ định
+pub async fn spawn_test_server_secured(
+    state: Arc<WorkerState>,
+    security: RpcSecurity,
+) -> (String, tokio::task::JoinHandle<()>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind worker test");
     let addr = listener.local_addr().expect("addr");
-    drop(listener);
 
     let endpoint = format!("http://{}", addr);
-    let state = Arc::new(WorkerState::default());
     let svc = WorkerService::new(state);
 
     let handle = tokio::spawn(async move {
-        serve_rpc(addr, svc).await;
+        serve_rpc_on(listener, svc, security).await;
     });
     (endpoint, handle)
 }
@@ -0,0 +1,147 @@
+//! Sinh obstacle cho endless runner theo con trỏ z: hàng mới được đặt khi player dẫn đầu tới gần,
+//! mỗi hàng luôn chừa ít nhất một lane đi qua được và cách hàng trước tối thiểu `MIN_ROW_GAP`.
+//! Cùng seed thì cùng dãy hàng.
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::simulation::{PLAYER_RADIUS, RUNNER_LANES};
+use crate::steering::ObstacleFootprint;
+
+/// Hàng được đặt trước player dẫn đầu tối đa chừng này
+pub const SPAWN_AHEAD_DISTANCE: f32 = 100.0;
+/// Hàng đầu tiên cách player dẫn đầu chừng này lúc track bắt đầu
+pub const FIRST_ROW_DISTANCE: f32 = 60.0;
+pub const MIN_ROW_GAP: f32 = 25.0;
+/// Khoảng cách giữa hai hàng là MIN_ROW_GAP cộng thêm ngẫu nhiên tới mức này
+pub const ROW_GAP_JITTER: f32 = 15.0;
+/// Xác suất một hàng có power-up ở một lane trống
+pub const POWER_UP_CHANCE: f64 = 0.3;
+
+const OBSTACLE_TYPES: [&str; 3] = ["wall", "spike", "moving_platform"];
+const POWER_UP_TYPES: [&str; 3] = ["speed_boost", "jump_boost", "invincibility"];
+/// Loại lọt trong một lane, dùng thay khi obstacle rộng bịt mất lane trống cuối cùng
+const NARROW_OBSTACLE: &str = "wall";
+
+/// Một hàng obstacle ngang track tại `z`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackRow {
+    pub z: f32,
+    /// (lane, obstacle_type), lane là index trong RUNNER_LANES
+    pub obstacles: Vec<(usize, &'static str)>,
+    /// (lane, power_type), luôn nằm ở lane đi qua được
+    pub power_up: Option<(usize, &'static str)>,
+}
+
+impl TrackRow {
+    /// Lane mà player đứng giữa lane không chạm footprint của obstacle nào trong hàng
+    pub fn passable_lanes(&self) -> Vec<usize> {
+        (0..RUNNER_LANES.len())
+            .filter(|&lane| {
+                let x = RUNNER_LANES[lane];
+                self.obstacles.iter().all(|&(blocked, obstacle_type)| {
+                    let footprint = ObstacleFootprint::new([RUNNER_LANES[blocked], 0.5, self.z], obstacle_type);
+                    (x - footprint.center[0]).abs() >= footprint.half_extents[0] + PLAYER_RADIUS
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RunnerTrack {
+    rng: StdRng,
+    /// z của hàng kế tiếp; None khi chưa có player nào chạy
+    next_row_z: Option<f32>,
+}
+
+impl RunnerTrack {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            next_row_z: None,
+        }
+    }
+
+    pub fn next_row_z(&self) -> Option<f32> {
+        self.next_row_z
+    }
+
+    /// Các hàng cần sinh khi player dẫn đầu ở `lead_z`; gọi mỗi tick, đa số tick trả về rỗng
+    pub fn advance(&mut self, lead_z: f32) -> Vec<TrackRow> {
+        let mut next_z = self.next_row_z.unwrap_or(lead_z + FIRST_ROW_DISTANCE);
+        let mut rows = Vec::new();
+        while next_z <= lead_z + SPAWN_AHEAD_DISTANCE {
+            rows.push(self.row(next_z));
+            next_z += MIN_ROW_GAP + self.rng.gen::<f32>() * ROW_GAP_JITTER;
+        }
+        self.next_row_z = Some(next_z);
+        rows
+    }
+
+    fn row(&mut self, z: f32) -> TrackRow {
+        let mut lanes: Vec<usize> = (0..RUNNER_LANES.len()).collect();
+        lanes.shuffle(&mut self.rng);
+        // Chặn 1..=len-1 lane, không bao giờ cả hàng
+        let blocked = self.rng.gen_range(1..RUNNER_LANES.len());
+        let mut obstacles: Vec<(usize, &'static str)> = lanes[..blocked]
+            .iter()
+            .map(|&lane| (lane, OBSTACLE_TYPES[self.rng.gen_range(0..OBSTACLE_TYPES.len())]))
+            .collect();
+        obstacles.sort_by_key(|&(lane, _)| lane);
+
+        let mut row = TrackRow { z, obstacles, power_up: None };
+        if row.passable_lanes().is_empty() {
+            for obstacle in row.obstacles.iter_mut() {
+                obstacle.1 = NARROW_OBSTACLE;
+            }
+        }
+
+        if self.rng.gen_bool(POWER_UP_CHANCE) {
+            let passable = row.passable_lanes();
+            let lane = passable[self.rng.gen_range(0..passable.len())];
+            row.power_up = Some((lane, POWER_UP_TYPES[self.rng.gen_range(0..POWER_UP_TYPES.len())]));
+        }
+        row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chạy track như player chạy đều từ z = 0 tới `distance`
+    fn run(seed: u64, distance: f32) -> Vec<TrackRow> {
+        let mut track = RunnerTrack::new(seed);
+        let mut rows = Vec::new();
+        let mut lead_z = 0.0;
+        while lead_z < distance {
+            rows.extend(track.advance(lead_z));
+            lead_z += 0.2;
+        }
+        rows
+    }
+
+    #[test]
+    fn every_row_leaves_a_passable_lane_and_rows_keep_their_gap() {
+        for seed in 0..50 {
+            let rows = run(seed, 2_000.0);
+            assert!(rows.len() > 40, "seed {seed}: {} rows", rows.len());
+            for row in &rows {
+                assert!(!row.obstacles.is_empty());
+                assert!(!row.passable_lanes().is_empty(), "seed {seed}: blocked row {row:?}");
+                if let Some((lane, _)) = row.power_up {
+                    assert!(row.passable_lanes().contains(&lane));
+                }
+            }
+            for pair in rows.windows(2) {
+                assert!(pair[1].z - pair[0].z >= MIN_ROW_GAP, "seed {seed}: {} -> {}", pair[0].z, pair[1].z);
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_generates_the_same_track() {
+        assert_eq!(run(7, 500.0), run(7, 500.0));
+        assert_ne!(run(7, 500.0), run(8, 500.0));
+    }
+}
@@ -3,10 +3,22 @@ use bevy_ecs::prelude::*;
 use rapier3d::prelude::*;
 use rapier3d::geometry::DefaultBroadPhase;
 use rapier3d::dynamics::{MultibodyJointSet, ImpulseJointSet};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, time::{Duration, Instant}};
+use std::{collections::{HashMap, HashSet}, time::{Duration, Instant}};
 use tracing;
-
+use common_net::quantization::{quantize_i16, QuantizationConfig};
+
+use crate::body_pool::{BodyPool, BodyShape};
+use crate::checkpoint::PlayerProgress;
+use crate::overload::{DegradationLevel, OverloadController};
+use crate::bots::{BotController, BotDifficulty, BotSenses};
+use crate::database::MatchPlayerResult;
+use crate::runner_track::RunnerTrack;
+use crate::spawn::SpawnManager;
+use crate::steering::{self, EnemyState, ObstacleFootprint, SteeringBuffers, SteeringProfile, SteeringState};
+use crate::system_message::SystemMessage;
+use crate::tick_rate::{self, BASE_TICK_HZ};
 use crate::validation::InputValidator;
 
 pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
@@ -30,6 +42,14 @@ pub struct Player {
     pub score: u32,
     pub view_distance: f32, // Area of Interest radius
     pub last_position: [f32; 3], // For movement tracking
+    #[serde(default)]
+    pub is_bot: bool, // Bot không được tính vào leaderboard/rating
+    /// RTT (ms, đã làm mượt) gateway đo được, client hiện cạnh tên; 0 khi chưa có số đo
+    #[serde(default)]
+    pub rtt_ms: u32,
+    /// Tên hiển thị room-manager đã chốt khi join; trống thì dùng id
+    #[serde(default)]
+    pub name: String,
 }
 
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +62,38 @@ pub struct Lifetime {
     pub remaining: Duration,
 }
 
+/// Player đang slide - collider bị thu nhỏ cho tới khi hết `remaining_ticks`
+#[derive(Component, Debug, Clone)]
+pub struct Sliding {
+    pub remaining_ticks: u32,
+}
+
+/// Player mất kết nối: entity đứng yên, không nhận damage, rejoin trước `expires_at` thì nối lại
+#[derive(Component, Debug, Clone)]
+pub struct Disconnected {
+    pub since: Instant,
+    pub expires_at: Instant,
+}
+
+/// Trạng thái live của một player có entity trong world
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LivePlayerState {
+    pub score: u32,
+    /// 0 khi gateway chưa đo
+    pub rtt_ms: u32,
+    pub is_bot: bool,
+    /// false khi player đang trong rejoin grace
+    pub connected: bool,
+}
+
+/// Kết quả `GameWorld::join_player`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerJoin {
+    pub entity: Entity,
+    /// Nối lại entity cũ (giữ score, vị trí, effect) thay vì spawn mới
+    pub resumed: bool,
+}
+
 #[derive(Component, Debug, Clone)]
 pub struct RigidBodyHandle {
     pub handle: rapier3d::dynamics::RigidBodyHandle,
@@ -52,6 +104,31 @@ pub struct Obstacle {
     pub obstacle_type: String, // "wall", "spike", "moving_platform"
 }
 
+/// Obstacle dao động quanh `origin`: vị trí = origin + axis * range * sin(speed * t + phase)
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MovingPlatform {
+    pub origin: [f32; 3],
+    pub axis: [f32; 3],  // Hướng dao động (đã chuẩn hoá)
+    pub range: f32,      // Biên độ, platform không bao giờ đi xa origin quá khoảng này
+    pub speed: f32,      // Tần số góc (rad/s)
+    pub phase: f32,      // Pha ban đầu (rad)
+    pub elapsed: f32,    // Thời gian đã chạy (s)
+}
+
+impl MovingPlatform {
+    pub fn new(origin: [f32; 3], axis: [f32; 3], range: f32, speed: f32, phase: f32) -> Self {
+        let length = (axis[0].powi(2) + axis[1].powi(2) + axis[2].powi(2)).sqrt();
+        let axis = if length > f32::EPSILON { axis.map(|a| a / length) } else { [0.0, 1.0, 0.0] };
+        Self { origin, axis, range: range.abs(), speed, phase, elapsed: 0.0 }
+    }
+
+    /// Vị trí ở thời điểm hiện tại
+    pub fn position(&self) -> [f32; 3] {
+        let offset = self.range * (self.speed * self.elapsed + self.phase).sin();
+        [0, 1, 2].map(|i| self.origin[i] + self.axis[i] * offset)
+    }
+}
+
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct PowerUp {
     pub power_type: String, // "speed_boost", "jump_boost", "invincibility"
@@ -66,6 +143,7 @@ pub struct Enemy {
     pub speed: f32,
     pub last_attack: Instant,
     pub attack_cooldown: Duration,
+    pub steering: SteeringState,
 }
 
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
@@ -76,6 +154,15 @@ pub struct ChatMessage {
     pub message: String,
     pub timestamp: u64,
     pub message_type: ChatMessageType,
+    /// Chỉ có với `ChatMessageType::System`: key + tham số để client tự dịch, `message` khi đó là key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<SystemMessage>,
+    /// Room nhận tin; None là tin chung cho mọi room
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room_id: Option<String>,
+    /// Chỉ player này thấy tin (MOTD); None là cả room
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient_id: Option<String>,
 }
 
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
@@ -109,6 +196,32 @@ pub enum ChatMessageType {
 pub const POSITION_SCALE: f32 = 100.0; // Scale factor để chuyển f32 thành i16
 pub const ROTATION_SCALE: f32 = 10000.0; // Scale factor cho quaternion components
 pub const VELOCITY_SCALE: f32 = 50.0; // Scale factor cho velocity
+/// Position factor tối đa khi tự giảm độ chính xác cho snapshot trải rộng (0.01 * 2^16 đơn vị mỗi bước)
+const MAX_POSITION_FACTOR_DOUBLINGS: u32 = 16;
+
+/// Số tick một event còn được giữ để gửi cho player chưa lấy snapshot (1 giây ở 60Hz)
+pub const EVENT_RETENTION_TICKS: u64 = 60;
+
+/// RTT lớn hơn coi như mất kết nối, không báo số cao hơn cho client
+pub const MAX_PLAYER_RTT_MS: u32 = 10_000;
+
+/// Chu kỳ dựng lại spatial grid từ TransformQ, sửa lệch do spawn/despawn bỏ sót (5 giây ở 60Hz)
+pub const SPATIAL_GRID_REBUILD_TICKS: u64 = 300;
+
+// Player movement parameters
+pub const PLAYER_RADIUS: f32 = 0.5;
+pub const SLIDE_RADIUS: f32 = 0.25; // Collider khi slide
+pub const SLIDE_DURATION_TICKS: u32 = 30; // 0.5s ở 60Hz
+pub const SLIDE_MAX_SPEED: f32 = 8.0;
+pub const JUMP_SPEED: f32 = 6.0; // Vận tốc Y ngay sau khi nhảy
+pub const GROUND_CHECK_TOLERANCE: f32 = 0.1; // Khoảng hở tối đa dưới chân vẫn tính là grounded
+pub const RUNNER_LANES: [f32; 3] = [-3.0, 0.0, 3.0]; // Tâm x của các lane endless runner
+pub const RUNNER_SPEED: f32 = 12.0; // Tốc độ chạy (đơn vị/giây) của endless runner
+pub const RUNNER_POINTS_PER_UNIT: f32 = 10.0; // Điểm cho mỗi đơn vị quãng đường
+pub const RUNNER_CULL_DISTANCE: f32 = 30.0; // Obstacle lùi sau player cuối cùng quá khoảng này thì bị dọn
+pub const MOVING_PLATFORM_HALF_EXTENTS: [f32; 3] = [3.0, 0.3, 2.0];
+pub const MOVING_PLATFORM_RANGE: f32 = 0.5; // Biên độ mặc định của platform trên track (nhấp nhô theo y)
+pub const MOVING_PLATFORM_SPEED: f32 = std::f32::consts::PI; // Chu kỳ 2s
 
 /// Quantized transform để giảm kích thước dữ liệu
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -143,6 +256,10 @@ pub struct QuantizedPlayer {
     pub id: String,
     pub score: u32,
     pub view_distance: i16, // quantized view distance
+    #[serde(default)]
+    pub is_bot: bool,
+    #[serde(default)]
+    pub rtt_ms: u16,
 }
 
 /// Quantized pickup data
@@ -161,7 +278,7 @@ pub struct QuantizedObstacle {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuantizedPowerUp {
     pub power_type: String,
-    pub duration: u16, // quantized duration in ticks
+    pub duration: u16, // quantized duration in ticks (theo tick_duration_ms của snapshot)
     pub value: u32,
 }
 
@@ -178,50 +295,196 @@ pub struct QuantizedEnemy {
 pub struct DeltaSnapshot {
     pub tick: u64,
     pub base_tick: u64, // Reference tick cho delta
+    #[serde(default)]
+    pub quantization: SnapshotQuantization, // Luôn trùng với base, đổi header thì encoder gửi Full
     pub created_entities: Vec<QuantizedEntitySnapshot>, // Entities mới được tạo
     pub updated_entities: Vec<QuantizedEntitySnapshot>, // Entities có thay đổi
     pub deleted_entities: Vec<u32>, // Entity IDs bị xóa
     pub chat_messages: Vec<ChatMessage>, // Chat messages mới
     pub new_spectators: Vec<SpectatorSnapshot>, // Spectators mới
     pub removed_spectators: Vec<String>, // Spectator IDs bị xóa
+    #[serde(default)]
+    pub events: Vec<QuantizedGameEvent>, // Events của frame này
+    /// Unix millis lúc worker tạo snapshot, client dùng để nội suy theo giờ server
+    #[serde(default)]
+    pub server_time_ms: u64,
+    /// Độ dài một tick (ms) lúc tạo snapshot
+    #[serde(default)]
+    pub tick_duration_ms: f32,
+    /// Sequence input mới nhất của player đã được xử lý, client bỏ các input đã ack khi reconcile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_acked_sequence: Option<u32>,
+}
+
+/// Header quantize của snapshot: position là offset so với `origin` theo `position_factor` (đơn vị mỗi bước i16),
+/// velocity theo `velocity_factor`. Decoder dùng header này để dựng lại toạ độ tuyệt đối
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotQuantization {
+    pub origin: [f32; 3],
+    pub position_factor: f32,
+    pub velocity_factor: f32,
+}
+
+/// Khớp payload cũ chưa có header: toạ độ tuyệt đối, scale hằng số
+impl Default for SnapshotQuantization {
+    fn default() -> Self {
+        Self {
+            origin: [0.0; 3],
+            position_factor: 1.0 / POSITION_SCALE,
+            velocity_factor: 1.0 / VELOCITY_SCALE,
+        }
+    }
+}
+
+impl SnapshotQuantization {
+    /// Position f32 -> i16 tương đối với origin, dùng chung cho transform và event
+    pub fn quantize_position(&self, position: [f32; 3]) -> (i16, i16, i16) {
+        (
+            quantize_i16(position[0] - self.origin[0], self.position_factor),
+            quantize_i16(position[1] - self.origin[1], self.position_factor),
+            quantize_i16(position[2] - self.origin[2], self.position_factor),
+        )
+    }
+
+    pub fn dequantize_position(&self, position: (i16, i16, i16)) -> [f32; 3] {
+        [
+            self.origin[0] + position.0 as f32 * self.position_factor,
+            self.origin[1] + position.1 as f32 * self.position_factor,
+            self.origin[2] + position.2 as f32 * self.position_factor,
+        ]
+    }
+
+    fn quantize_velocity(&self, velocity: [f32; 3]) -> (i16, i16, i16) {
+        (
+            quantize_i16(velocity[0], self.velocity_factor),
+            quantize_i16(velocity[1], self.velocity_factor),
+            quantize_i16(velocity[2], self.velocity_factor),
+        )
+    }
+
+    fn dequantize_velocity(&self, velocity: (i16, i16, i16)) -> [f32; 3] {
+        [
+            velocity.0 as f32 * self.velocity_factor,
+            velocity.1 as f32 * self.velocity_factor,
+            velocity.2 as f32 * self.velocity_factor,
+        ]
+    }
+}
+
+/// Quantization mặc định của worker: position theo POSITION_SCALE, velocity theo VELOCITY_SCALE
+pub fn default_quantization() -> QuantizationConfig {
+    QuantizationConfig {
+        position_factor: 1.0 / POSITION_SCALE,
+        velocity_factor: 1.0 / VELOCITY_SCALE,
+        ..Default::default()
+    }
+}
+
+/// Origin cho snapshot quanh `center`, làm tròn theo `step` trên x/z để origin (và header) ít đổi khi player chạy
+pub fn snap_origin(center: [f32; 3], step: f32) -> [f32; 3] {
+    [(center[0] / step).round() * step, 0.0, (center[2] / step).round() * step]
+}
+
+/// Unix millis hiện tại, đóng dấu `server_time_ms` của snapshot
+pub fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
 }
 
 /// Full snapshot với quantization
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuantizedSnapshot {
     pub tick: u64,
+    #[serde(default)]
+    pub quantization: SnapshotQuantization,
     pub entities: Vec<QuantizedEntitySnapshot>,
     pub chat_messages: Vec<ChatMessage>,
     pub spectators: Vec<SpectatorSnapshot>,
+    #[serde(default)]
+    pub events: Vec<QuantizedGameEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Unix millis lúc worker tạo snapshot, client dùng để nội suy theo giờ server
+    #[serde(default)]
+    pub server_time_ms: u64,
+    /// Độ dài một tick (ms) lúc tạo snapshot
+    #[serde(default)]
+    pub tick_duration_ms: f32,
+    /// Sequence input mới nhất của player đã được xử lý, client bỏ các input đã ack khi reconcile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_acked_sequence: Option<u32>,
+}
+
+/// GameEvent sau quantize: position cùng scale với transform
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QuantizedGameEvent {
+    ItemUsed { player_id: String, item: String },
+    PickupCollected { player_id: String, value: u32, position: (i16, i16, i16) },
+    PlayerDamaged { player_id: String, amount: u32, source: String, position: (i16, i16, i16) },
+    PowerUpActivated { player_id: String, power_type: String, duration_ms: u32, position: (i16, i16, i16) },
+    PlayerDied { player_id: String, killer: Option<String>, position: (i16, i16, i16) },
+    FlagCaptured { player_id: String, team: String },
+    MatchEnded { room_id: String, reason: String, scoreboard: Vec<MatchPlayerResult> },
+    TickRateChanged { room_id: String, tick_rate_hz: u32 },
+}
+
+impl QuantizedGameEvent {
+    pub fn quantize(event: GameEvent, quantization: &SnapshotQuantization) -> Self {
+        let quantize_position = |position| quantization.quantize_position(position);
+        match event {
+            GameEvent::ItemUsed { player_id, item } => Self::ItemUsed { player_id, item },
+            GameEvent::PickupCollected { player_id, value, position } => Self::PickupCollected {
+                player_id,
+                value,
+                position: quantize_position(position),
+            },
+            GameEvent::PlayerDamaged { player_id, amount, source, position } => Self::PlayerDamaged {
+                player_id,
+                amount,
+                source,
+                position: quantize_position(position),
+            },
+            GameEvent::PowerUpActivated { player_id, power_type, duration_ms, position } => Self::PowerUpActivated {
+                player_id,
+                power_type,
+                duration_ms,
+                position: quantize_position(position),
+            },
+            GameEvent::PlayerDied { player_id, killer, position } => Self::PlayerDied {
+                player_id,
+                killer,
+                position: quantize_position(position),
+            },
+            GameEvent::FlagCaptured { player_id, team } => Self::FlagCaptured { player_id, team },
+            GameEvent::MatchEnded { room_id, reason, scoreboard } => Self::MatchEnded { room_id, reason, scoreboard },
+            GameEvent::TickRateChanged { room_id, tick_rate_hz } => Self::TickRateChanged { room_id, tick_rate_hz },
+        }
+    }
 }
 
 /// Quantization utilities
 impl QuantizedTransform {
-    /// Convert f32 position to i16 với scale factor
-    pub fn from_f32(position: [f32; 3], rotation: [f32; 4]) -> Self {
+    /// Convert f32 position to i16 theo header của snapshot
+    pub fn from_f32(position: [f32; 3], rotation: [f32; 4], quantization: &SnapshotQuantization) -> Self {
+        let rotation_factor = 1.0 / ROTATION_SCALE;
         Self {
-            position: (
-                (position[0] * POSITION_SCALE) as i16,
-                (position[1] * POSITION_SCALE) as i16,
-                (position[2] * POSITION_SCALE) as i16,
-            ),
+            position: quantization.quantize_position(position),
             rotation: (
-                (rotation[0] * ROTATION_SCALE) as i16,
-                (rotation[1] * ROTATION_SCALE) as i16,
-                (rotation[2] * ROTATION_SCALE) as i16,
-                (rotation[3] * ROTATION_SCALE) as i16,
+                quantize_i16(rotation[0], rotation_factor),
+                quantize_i16(rotation[1], rotation_factor),
+                quantize_i16(rotation[2], rotation_factor),
+                quantize_i16(rotation[3], rotation_factor),
             ),
         }
     }
 
-    /// Convert i16 back to f32
-    pub fn to_f32(&self) -> ([f32; 3], [f32; 4]) {
+    /// Convert i16 back to f32 (toạ độ tuyệt đối)
+    pub fn to_f32(&self, quantization: &SnapshotQuantization) -> ([f32; 3], [f32; 4]) {
         (
-            [
-                self.position.0 as f32 / POSITION_SCALE,
-                self.position.1 as f32 / POSITION_SCALE,
-                self.position.2 as f32 / POSITION_SCALE,
-            ],
+            quantization.dequantize_position(self.position),
             [
                 self.rotation.0 as f32 / ROTATION_SCALE,
                 self.rotation.1 as f32 / ROTATION_SCALE,
@@ -234,44 +497,189 @@ impl QuantizedTransform {
 
 impl QuantizedVelocity {
     /// Convert f32 velocity to i16
-    pub fn from_f32(velocity: [f32; 3], angular_velocity: [f32; 3]) -> Self {
+    pub fn from_f32(velocity: [f32; 3], angular_velocity: [f32; 3], quantization: &SnapshotQuantization) -> Self {
         Self {
-            velocity: (
-                (velocity[0] * VELOCITY_SCALE) as i16,
-                (velocity[1] * VELOCITY_SCALE) as i16,
-                (velocity[2] * VELOCITY_SCALE) as i16,
-            ),
-            angular_velocity: (
-                (angular_velocity[0] * VELOCITY_SCALE) as i16,
-                (angular_velocity[1] * VELOCITY_SCALE) as i16,
-                (angular_velocity[2] * VELOCITY_SCALE) as i16,
-            ),
+            velocity: quantization.quantize_velocity(velocity),
+            angular_velocity: quantization.quantize_velocity(angular_velocity),
         }
     }
 
     /// Convert i16 back to f32
-    pub fn to_f32(&self) -> ([f32; 3], [f32; 3]) {
+    pub fn to_f32(&self, quantization: &SnapshotQuantization) -> ([f32; 3], [f32; 3]) {
         (
-            [
-                self.velocity.0 as f32 / VELOCITY_SCALE,
-                self.velocity.1 as f32 / VELOCITY_SCALE,
-                self.velocity.2 as f32 / VELOCITY_SCALE,
-            ],
-            [
-                self.angular_velocity.0 as f32 / VELOCITY_SCALE,
-                self.angular_velocity.1 as f32 / VELOCITY_SCALE,
-                self.angular_velocity.2 as f32 / VELOCITY_SCALE,
-            ],
+            quantization.dequantize_velocity(self.velocity),
+            quantization.dequantize_velocity(self.angular_velocity),
         )
     }
 }
 
+/// Số tick tối đa giữa hai keyframe mặc định (2 giây ở nhịp gốc 60Hz)
+pub const DEFAULT_KEYFRAME_INTERVAL_TICKS: u64 = 120;
+
+/// Chính sách keyframe: ép gửi Full định kỳ để client lỡ mất delta vẫn tự hồi phục được
+#[derive(Debug, Clone, Copy)]
+pub struct KeyframePolicy {
+    /// Số tick tối đa giữa hai Full snapshot, tính ở `BASE_TICK_HZ`; world chạy nhịp khác thì quy đổi qua `at_tick_hz`
+    pub interval_ticks: u64,
+    /// Delta có nhiều thay đổi hơn ngưỡng này thì gửi Full luôn
+    pub max_delta_changes: usize,
+}
+
+impl Default for KeyframePolicy {
+    fn default() -> Self {
+        Self {
+            interval_ticks: DEFAULT_KEYFRAME_INTERVAL_TICKS,
+            max_delta_changes: 64,
+        }
+    }
+}
+
+impl KeyframePolicy {
+    /// Policy với `interval_ticks` quy đổi sang `tick_hz` để giữ nguyên khoảng thời gian giữa hai keyframe
+    pub fn at_tick_hz(self, tick_hz: u32) -> Self {
+        Self {
+            interval_ticks: (self.interval_ticks * tick_hz as u64 / BASE_TICK_HZ as u64).max(1),
+            ..self
+        }
+    }
+}
+
+/// Vì sao encoder gửi Full thay vì delta; thứ tự khớp `common_net::metrics::FULL_SNAPSHOT_REASONS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FullSnapshotReason {
+    /// Encoder chưa có base
+    First = 0,
+    KeyframeInterval = 1,
+    QuantizationChanged = 2,
+    /// Delta vượt `max_delta_changes`
+    TooManyChanges = 3,
+    /// Delta ít thay đổi hơn `delta_threshold`
+    BelowThreshold = 4,
+    /// Client yêu cầu resync (`encode_keyframe`)
+    Requested = 5,
+}
+
+impl FullSnapshotReason {
+    pub fn as_str(self) -> &'static str {
+        common_net::metrics::FULL_SNAPSHOT_REASONS[self as usize]
+    }
+}
+
+/// Số liệu cộng dồn của một encoder; byte tính theo JSON của snapshot/delta đã quantize
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EncodingStats {
+    pub encodes: u64,
+    /// Số lần encoder tính delta, kể cả delta sau đó bị thay bằng Full
+    pub deltas_computed: u64,
+    pub deltas_sent: u64,
+    /// Tổng byte của các delta đã tính
+    pub delta_bytes: u64,
+    /// Tổng byte Full snapshot ở mỗi lần encode, dù có gửi Full hay không
+    pub full_bytes: u64,
+    /// Tổng byte của encoding thực sự được chọn
+    pub sent_bytes: u64,
+    pub created: u64,
+    pub updated: u64,
+    pub deleted: u64,
+    /// Số lần gửi Full theo `FullSnapshotReason`
+    pub forced_full: [u64; 6],
+}
+
+impl EncodingStats {
+    pub fn fulls_sent(&self) -> u64 {
+        self.forced_full.iter().sum()
+    }
+
+    pub fn forced(&self, reason: FullSnapshotReason) -> u64 {
+        self.forced_full[reason as usize]
+    }
+
+    pub fn average_delta_bytes(&self) -> f64 {
+        self.delta_bytes as f64 / self.deltas_computed.max(1) as f64
+    }
+
+    pub fn average_full_bytes(&self) -> f64 {
+        self.full_bytes as f64 / self.encodes.max(1) as f64
+    }
+
+    /// Delta trung bình so với Full trung bình; 0 khi chưa tính delta lần nào
+    pub fn delta_ratio(&self) -> f64 {
+        if self.deltas_computed == 0 || self.full_bytes == 0 {
+            return 0.0;
+        }
+        self.average_delta_bytes() / self.average_full_bytes()
+    }
+
+    pub fn merge(&mut self, other: &EncodingStats) {
+        self.encodes += other.encodes;
+        self.deltas_computed += other.deltas_computed;
+        self.deltas_sent += other.deltas_sent;
+        self.delta_bytes += other.delta_bytes;
+        self.full_bytes += other.full_bytes;
+        self.sent_bytes += other.sent_bytes;
+        self.created += other.created;
+        self.updated += other.updated;
+        self.deleted += other.deleted;
+        for (total, count) in self.forced_full.iter_mut().zip(other.forced_full) {
+            *total += count;
+        }
+    }
+
+    /// Phần cộng thêm kể từ `earlier`; encoder bị bỏ (player rời room) làm tổng giảm thì tính 0
+    pub fn since(&self, earlier: &EncodingStats) -> EncodingStats {
+        let mut forced_full = self.forced_full;
+        for (count, before) in forced_full.iter_mut().zip(earlier.forced_full) {
+            *count = count.saturating_sub(before);
+        }
+        EncodingStats {
+            encodes: self.encodes.saturating_sub(earlier.encodes),
+            deltas_computed: self.deltas_computed.saturating_sub(earlier.deltas_computed),
+            deltas_sent: self.deltas_sent.saturating_sub(earlier.deltas_sent),
+            delta_bytes: self.delta_bytes.saturating_sub(earlier.delta_bytes),
+            full_bytes: self.full_bytes.saturating_sub(earlier.full_bytes),
+            sent_bytes: self.sent_bytes.saturating_sub(earlier.sent_bytes),
+            created: self.created.saturating_sub(earlier.created),
+            updated: self.updated.saturating_sub(earlier.updated),
+            deleted: self.deleted.saturating_sub(earlier.deleted),
+            forced_full,
+        }
+    }
+}
+
+/// Số byte JSON của `value` mà không cần giữ buffer
+fn json_len<T: Serialize>(value: &T) -> usize {
+    struct ByteCounter(usize);
+    impl std::io::Write for ByteCounter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = ByteCounter(0);
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
 /// Delta encoder để tính toán sự khác biệt giữa snapshots
 pub struct DeltaEncoder {
     /// Previous snapshot để so sánh
     pub previous_snapshot: Option<QuantizedSnapshot>,
     /// Threshold để quyết định có nên tạo delta hay không
     pub delta_threshold: usize, // Số entities thay đổi tối thiểu để tạo delta
+    pub keyframe_policy: KeyframePolicy,
+    /// Tick của Full snapshot gần nhất encoder này phát ra
+    pub last_keyframe_tick: u64,
+    /// GameWorld chỉ đưa vào snapshot các event phát sinh từ tick này trở đi, mỗi event tới encoder đúng một lần
+    pub events_since_tick: u64,
+    /// Position (đơn vị đã quantize) phải lệch quá mức này mới tính là entity đã đổi; tăng khi worker quá tải
+    pub position_change_threshold: i32,
+    /// Quantization của room; snapshot trải rộng quá range i16 thì position factor tự tăng từ mức này
+    pub quantization: QuantizationConfig,
+    pub stats: EncodingStats,
 }
 
 impl DeltaEncoder {
@@ -279,40 +687,140 @@ impl DeltaEncoder {
         Self {
             previous_snapshot: None,
             delta_threshold,
+            keyframe_policy: KeyframePolicy::default(),
+            last_keyframe_tick: 0,
+            events_since_tick: 0,
+            position_change_threshold: 1,
+            quantization: default_quantization(),
+            stats: EncodingStats::default(),
         }
     }
 
+    pub fn with_keyframe_policy(mut self, policy: KeyframePolicy) -> Self {
+        self.keyframe_policy = policy;
+        self
+    }
+
     /// Encode snapshot thành delta hoặc full snapshot
     pub fn encode_snapshot(&mut self, snapshot: GameSnapshot, current_tick: u64) -> EncodedSnapshot {
         let quantized = self.quantize_snapshot(snapshot);
+        let full_bytes = json_len(&quantized);
 
-        if let Some(ref prev) = self.previous_snapshot {
-            // Tính toán delta nếu có đủ sự thay đổi
-            let delta = self.create_delta(&quantized, prev, current_tick);
-            if self.should_use_delta(&delta) {
-                EncodedSnapshot::Delta(delta)
-            } else {
-                // Gửi full snapshot nếu delta quá lớn
-                self.previous_snapshot = Some(quantized.clone());
-                EncodedSnapshot::Full(quantized)
-            }
-        } else {
+        let Some(ref prev) = self.previous_snapshot else {
             // First snapshot luôn là full
-            self.previous_snapshot = Some(quantized.clone());
-            EncodedSnapshot::Full(quantized)
+            self.record(full_bytes, None, Some(FullSnapshotReason::First));
+            return self.keyframe(quantized, current_tick);
+        };
+
+        // Đến hạn keyframe hoặc header quantize đổi (origin/scale) thì gửi Full bất kể heuristic delta
+        let due = if current_tick.saturating_sub(self.last_keyframe_tick) >= self.keyframe_policy.interval_ticks {
+            Some(FullSnapshotReason::KeyframeInterval)
+        } else if prev.quantization != quantized.quantization {
+            Some(FullSnapshotReason::QuantizationChanged)
+        } else {
+            None
+        };
+        if let Some(reason) = due {
+            self.record(full_bytes, None, Some(reason));
+            return self.keyframe(quantized, current_tick);
+        }
+
+        let delta = self.create_delta(&quantized, prev, current_tick);
+        // Delta quá lớn (hoặc quá nhỏ để đáng gửi delta) thì gửi full snapshot
+        let forced = if Self::change_count(&delta) > self.keyframe_policy.max_delta_changes {
+            Some(FullSnapshotReason::TooManyChanges)
+        } else if !self.should_use_delta(&delta) {
+            Some(FullSnapshotReason::BelowThreshold)
+        } else {
+            None
+        };
+        self.record(full_bytes, Some(&delta), forced);
+        if forced.is_some() {
+            return self.keyframe(quantized, current_tick);
+        }
+        EncodedSnapshot::Delta(delta)
+    }
+
+    /// Luôn encode thành Full và lấy nó làm base cho các delta sau (client yêu cầu resync)
+    pub fn encode_keyframe(&mut self, snapshot: GameSnapshot, current_tick: u64) -> EncodedSnapshot {
+        let quantized = self.quantize_snapshot(snapshot);
+        self.record(json_len(&quantized), None, Some(FullSnapshotReason::Requested));
+        self.keyframe(quantized, current_tick)
+    }
+
+    fn keyframe(&mut self, quantized: QuantizedSnapshot, current_tick: u64) -> EncodedSnapshot {
+        self.previous_snapshot = Some(quantized.clone());
+        self.last_keyframe_tick = current_tick;
+        EncodedSnapshot::Full(quantized)
+    }
+
+    /// Cộng một lần encode vào `stats` và metrics; `forced` None nghĩa là gửi delta
+    fn record(&mut self, full_bytes: usize, delta: Option<&DeltaSnapshot>, forced: Option<FullSnapshotReason>) {
+        let delta_bytes = delta.map(json_len);
+        let stats = &mut self.stats;
+        stats.encodes += 1;
+        stats.full_bytes += full_bytes as u64;
+        if let (Some(delta), Some(bytes)) = (delta, delta_bytes) {
+            stats.deltas_computed += 1;
+            stats.delta_bytes += bytes as u64;
+            stats.created += delta.created_entities.len() as u64;
+            stats.updated += delta.updated_entities.len() as u64;
+            stats.deleted += delta.deleted_entities.len() as u64;
+        }
+        match forced {
+            Some(reason) => {
+                stats.forced_full[reason as usize] += 1;
+                stats.sent_bytes += full_bytes as u64;
+            }
+            None => {
+                stats.deltas_sent += 1;
+                stats.sent_bytes += delta_bytes.unwrap_or_default() as u64;
+            }
+        }
+        crate::simulation_metrics().observe_snapshot_encoding(full_bytes, delta_bytes, forced.map(FullSnapshotReason::as_str));
+    }
+
+    /// Header quantize cho snapshot: origin lấy từ snapshot, position factor tăng gấp đôi dần (từ mức của room)
+    /// tới khi offset xa nhất vừa range i16; bước luỹ thừa 2 để header không đổi mỗi tick
+    fn snapshot_quantization(&self, snapshot: &GameSnapshot) -> SnapshotQuantization {
+        let origin = snapshot.origin;
+        let max_offset = snapshot
+            .entities
+            .iter()
+            .map(|entity| entity.transform.position)
+            .chain(snapshot.events.iter().filter_map(GameEvent::position))
+            .flat_map(|position| (0..3).map(move |axis| (position[axis] - origin[axis]).abs()))
+            .fold(0.0_f32, f32::max);
+
+        let mut position_factor = self.quantization.position_factor;
+        for _ in 0..MAX_POSITION_FACTOR_DOUBLINGS {
+            if max_offset / position_factor <= i16::MAX as f32 {
+                break;
+            }
+            position_factor *= 2.0;
+        }
+
+        SnapshotQuantization {
+            origin,
+            position_factor,
+            velocity_factor: self.quantization.velocity_factor,
         }
     }
 
     /// Quantize GameSnapshot thành QuantizedSnapshot
     fn quantize_snapshot(&self, snapshot: GameSnapshot) -> QuantizedSnapshot {
+        let quantization = self.snapshot_quantization(&snapshot);
+        // Snapshot không ghi tick duration (test, replay cũ) thì coi như chạy ở nhịp gốc
+        let tick_ms = if snapshot.tick_duration_ms > 0.0 { snapshot.tick_duration_ms } else { 1000.0 / BASE_TICK_HZ as f32 };
         let entities = snapshot.entities.into_iter().map(|entity| {
             let quantized_transform = QuantizedTransform::from_f32(
                 entity.transform.position,
                 entity.transform.rotation,
+                &quantization,
             );
 
             let quantized_velocity = entity.velocity.map(|vel| {
-                QuantizedVelocity::from_f32(vel.velocity, vel.angular_velocity)
+                QuantizedVelocity::from_f32(vel.velocity, vel.angular_velocity, &quantization)
             });
 
             QuantizedEntitySnapshot {
@@ -322,28 +830,40 @@ impl DeltaEncoder {
                 player: entity.player.map(|p| QuantizedPlayer {
                     id: p.id,
                     score: p.score,
-                    view_distance: (p.view_distance * POSITION_SCALE) as i16,
+                    view_distance: quantize_i16(p.view_distance, quantization.position_factor),
+                    is_bot: p.is_bot,
+                    rtt_ms: p.rtt_ms.min(u16::MAX as u32) as u16,
                 }),
                 pickup: entity.pickup.map(|p| QuantizedPickup { value: p.value }),
                 obstacle: entity.obstacle.map(|o| QuantizedObstacle { obstacle_type: o.obstacle_type }),
                 power_up: entity.power_up.map(|pu| QuantizedPowerUp {
                     power_type: pu.power_type,
-                    duration: (pu.duration.as_secs() * 60) as u16, // Convert to ticks
+                    duration: (pu.duration.as_secs_f32() * 1000.0 / tick_ms).round().min(u16::MAX as f32) as u16,
                     value: pu.value,
                 }),
                 enemy: entity.enemy.map(|e| QuantizedEnemy {
                     enemy_type: e.enemy_type,
                     damage: e.damage,
-                    speed: (e.speed * VELOCITY_SCALE) as i16,
+                    speed: quantize_i16(e.speed, quantization.velocity_factor),
                 }),
             }
         }).collect();
 
         QuantizedSnapshot {
             tick: snapshot.tick,
+            quantization,
             entities,
             chat_messages: snapshot.chat_messages,
             spectators: snapshot.spectators,
+            events: snapshot
+                .events
+                .into_iter()
+                .map(|event| QuantizedGameEvent::quantize(event, &quantization))
+                .collect(),
+            seed: snapshot.seed,
+            server_time_ms: snapshot.server_time_ms,
+            tick_duration_ms: snapshot.tick_duration_ms,
+            last_acked_sequence: snapshot.last_acked_sequence,
         }
     }
 
@@ -403,40 +923,56 @@ impl DeltaEncoder {
         DeltaSnapshot {
             tick: current.tick,
             base_tick: previous.tick,
+            quantization: current.quantization,
             created_entities,
             updated_entities,
             deleted_entities,
             chat_messages: new_chat_messages,
             new_spectators,
             removed_spectators,
+            events: current.events.clone(), // Events chỉ sống trong một frame nên luôn gửi nguyên
+            server_time_ms: current.server_time_ms,
+            tick_duration_ms: current.tick_duration_ms,
+            last_acked_sequence: current.last_acked_sequence,
         }
     }
 
     /// Check if entity có sự thay đổi đáng kể để gửi delta
     fn has_significant_change(&self, current: &QuantizedEntitySnapshot, previous: &QuantizedEntitySnapshot) -> bool {
         // Check position change
-        let pos_diff_x = (current.transform.position.0 - previous.transform.position.0).abs() > 1;
-        let pos_diff_y = (current.transform.position.1 - previous.transform.position.1).abs() > 1;
-        let pos_diff_z = (current.transform.position.2 - previous.transform.position.2).abs() > 1;
+        // So sánh trên i32: hai giá trị bị clamp ở hai biên i16 trừ nhau sẽ tràn i16
+        let moved = |a: i16, b: i16| (a as i32 - b as i32).abs() > self.position_change_threshold;
+        let pos_diff_x = moved(current.transform.position.0, previous.transform.position.0);
+        let pos_diff_y = moved(current.transform.position.1, previous.transform.position.1);
+        let pos_diff_z = moved(current.transform.position.2, previous.transform.position.2);
 
         // Check velocity change (nếu có)
         let vel_changed = match (&current.velocity, &previous.velocity) {
             (Some(curr_vel), Some(prev_vel)) => {
-                (curr_vel.velocity.0 - prev_vel.velocity.0).abs() > 2 ||
-                (curr_vel.velocity.1 - prev_vel.velocity.1).abs() > 2 ||
-                (curr_vel.velocity.2 - prev_vel.velocity.2).abs() > 2
+                (curr_vel.velocity.0 as i32 - prev_vel.velocity.0 as i32).abs() > 2 ||
+                (curr_vel.velocity.1 as i32 - prev_vel.velocity.1 as i32).abs() > 2 ||
+                (curr_vel.velocity.2 as i32 - prev_vel.velocity.2 as i32).abs() > 2
             }
             (Some(_), None) | (None, Some(_)) => true,
             (None, None) => false,
         };
 
-        pos_diff_x || pos_diff_y || pos_diff_z || vel_changed
+        // Ping đổi thì client cần số mới để hiện trên scoreboard
+        let rtt_changed = match (&current.player, &previous.player) {
+            (Some(curr_player), Some(prev_player)) => curr_player.rtt_ms != prev_player.rtt_ms,
+            _ => false,
+        };
+
+        pos_diff_x || pos_diff_y || pos_diff_z || vel_changed || rtt_changed
     }
 
     /// Decide có nên sử dụng delta hay không dựa trên kích thước
     fn should_use_delta(&self, delta: &DeltaSnapshot) -> bool {
-        let total_changes = delta.created_entities.len() + delta.updated_entities.len() + delta.deleted_entities.len();
-        total_changes >= self.delta_threshold
+        Self::change_count(delta) >= self.delta_threshold
+    }
+
+    fn change_count(delta: &DeltaSnapshot) -> usize {
+        delta.created_entities.len() + delta.updated_entities.len() + delta.deleted_entities.len()
     }
 }
 
@@ -456,6 +992,14 @@ impl EncodedSnapshot {
         }
     }
 
+    /// Header quantize để decode position/velocity của snapshot này
+    pub fn quantization(&self) -> SnapshotQuantization {
+        match self {
+            EncodedSnapshot::Full(snapshot) => snapshot.quantization,
+            EncodedSnapshot::Delta(delta) => delta.quantization,
+        }
+    }
+
     /// Get payload as JSON string
     pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
@@ -464,10 +1008,11 @@ impl EncodedSnapshot {
 
 // ===== AOI (Area of Interest) System =====
 
-/// Grid cell coordinates (x, z) - chỉ dùng 2D cho simplicity
+/// Grid cell coordinates (x, y, z). `y` là tầng theo chiều cao, luôn 0 khi grid chỉ chia 2D
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct GridCell {
     pub x: i32,
+    pub y: i32,
     pub z: i32,
 }
 
@@ -476,6 +1021,8 @@ pub struct GridCell {
 pub struct SpatialGrid {
     /// Cell size in world units (ví dụ: 50.0)
     pub cell_size: f32,
+    /// Chiều cao mỗi tầng; None (mặc định) thì chỉ chia theo x/z như cũ
+    pub layer_height: Option<f32>,
     /// Map từ cell coordinates tới list of entities
     pub cells: HashMap<GridCell, Vec<Entity>>,
     /// Cache để track entity positions để detect movement
@@ -494,19 +1041,86 @@ impl SpatialGrid {
     pub fn new(cell_size: f32) -> Self {
         Self {
             cell_size,
+            layer_height: None,
             cells: HashMap::new(),
             entity_positions: HashMap::new(),
         }
     }
 
+    /// Grid chia thêm theo chiều cao: platform chồng nhau hay enemy bay cao không còn chung cell
+    pub fn with_layer_height(cell_size: f32, layer_height: f32) -> Self {
+        let mut grid = Self::new(cell_size);
+        grid.layer_height = Some(layer_height);
+        grid
+    }
+
+    /// Bật/tắt chia tầng, xếp lại các entity đang có vào cell mới
+    pub fn set_layer_height(&mut self, layer_height: Option<f32>) {
+        self.layer_height = layer_height;
+        let positions: Vec<_> = self.entity_positions.drain().collect();
+        self.rebuild_from(positions);
+    }
+
+    /// Xoá sạch grid rồi xếp lại đúng các entity trong `entities`
+    pub fn rebuild_from<I: IntoIterator<Item = (Entity, [f32; 3])>>(&mut self, entities: I) {
+        self.cells.clear();
+        self.entity_positions.clear();
+        for (entity, position) in entities {
+            self.cells.entry(self.world_to_cell(position)).or_default().push(entity);
+            self.entity_positions.insert(entity, position);
+        }
+    }
+
+    /// Debug: các entity không nằm đúng một lần trong cell ứng với vị trí đã lưu, hoặc nằm trong cell
+    /// mà không có vị trí. Rỗng nghĩa là grid nhất quán.
+    pub fn validate(&self) -> Vec<Entity> {
+        let mut placed = HashSet::new();
+        let mut invalid = Vec::new();
+        for (cell, entities) in &self.cells {
+            for &entity in entities {
+                let in_right_cell = self
+                    .entity_positions
+                    .get(&entity)
+                    .is_some_and(|position| self.world_to_cell(*position) == *cell);
+                if !in_right_cell || !placed.insert(entity) {
+                    invalid.push(entity);
+                }
+            }
+        }
+        invalid.extend(self.entity_positions.keys().filter(|entity| !placed.contains(*entity)));
+        invalid.sort();
+        invalid.dedup();
+        invalid
+    }
+
     /// Convert world position to grid cell coordinates
     pub fn world_to_cell(&self, position: [f32; 3]) -> GridCell {
         GridCell {
             x: (position[0] / self.cell_size).floor() as i32,
+            y: self.layer_height.map_or(0, |height| (position[1] / height).floor() as i32),
             z: (position[2] / self.cell_size).floor() as i32,
         }
     }
 
+    /// Số tầng trên/dưới nằm trong AOI: đủ phủ một cell_size theo chiều dọc, 0 khi grid 2D
+    fn aoi_layer_reach(&self) -> i32 {
+        self.layer_height.map_or(0, |height| (self.cell_size / height).ceil() as i32)
+    }
+
+    /// Các cell trong AOI quanh `center`: 3x3 theo x/z, cộng các tầng kề trong tầm `aoi_layer_reach`
+    fn aoi_cells(&self, center: GridCell) -> impl Iterator<Item = GridCell> {
+        let reach = self.aoi_layer_reach();
+        (-1..=1).flat_map(move |dx| {
+            (-reach..=reach).flat_map(move |dy| {
+                (-1..=1).map(move |dz| GridCell {
+                    x: center.x + dx,
+                    y: center.y + dy,
+                    z: center.z + dz,
+                })
+            })
+        })
+    }
+
     /// Add entity to grid at specific position
     pub fn add_entity(&mut self, entity: Entity, position: [f32; 3]) {
         let cell = self.world_to_cell(position);
@@ -571,43 +1185,38 @@ impl SpatialGrid {
         self.cells.get(&cell)
     }
 
-    /// Get all entities in a cell and its 8 neighbors (3x3 grid)
+    /// Get all entities in a cell and its 8 neighbors (3x3 grid), cùng các tầng kề khi chia tầng
     pub fn get_entities_in_aoi(&self, center_cell: GridCell) -> Vec<Entity> {
         let mut entities = Vec::new();
 
-        // Check center cell and 8 neighbors
-        for dx in -1..=1 {
-            for dz in -1..=1 {
-                let cell = GridCell {
-                    x: center_cell.x + dx,
-                    z: center_cell.z + dz,
-                };
-
-                if let Some(cell_entities) = self.cells.get(&cell) {
-                    entities.extend(cell_entities.iter().copied());
-                }
+        for cell in self.aoi_cells(center_cell) {
+            if let Some(cell_entities) = self.cells.get(&cell) {
+                entities.extend(cell_entities.iter().copied());
             }
         }
 
         entities
     }
 
-    /// Get player's AOI cells (center cell + neighbors)
-    pub fn get_player_aoi_cells(&self, player_position: [f32; 3]) -> Vec<GridCell> {
-        let center_cell = self.world_to_cell(player_position);
-        let mut cells = Vec::new();
-
-        // Get 3x3 grid of cells around player
-        for dx in -1..=1 {
-            for dz in -1..=1 {
-                cells.push(GridCell {
-                    x: center_cell.x + dx,
-                    z: center_cell.z + dz,
-                });
+    /// Ghi vào `out` các entity nằm trong những cell giao với hình vuông bán kính `radius` quanh `position`.
+    /// Không clear `out` và không cấp phát khi `out` đã đủ capacity.
+    pub fn entities_within(&self, position: [f32; 3], radius: f32, out: &mut Vec<Entity>) {
+        let min = self.world_to_cell([position[0] - radius, position[1] - radius, position[2] - radius]);
+        let max = self.world_to_cell([position[0] + radius, position[1] + radius, position[2] + radius]);
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    if let Some(cell_entities) = self.cells.get(&GridCell { x, y, z }) {
+                        out.extend(cell_entities.iter().copied());
+                    }
+                }
             }
         }
+    }
 
-        cells
+    /// Get player's AOI cells (center cell + neighbors)
+    pub fn get_player_aoi_cells(&self, player_position: [f32; 3]) -> Vec<GridCell> {
+        self.aoi_cells(self.world_to_cell(player_position)).collect()
     }
 
     /// Cleanup empty cells to save memory
@@ -631,15 +1240,85 @@ pub struct PlayerInput {
     pub input_sequence: u32,
     pub movement: [f32; 3], // x, y, z movement
     pub timestamp: u64,
+    #[serde(default)] // Client cũ chỉ gửi movement
+    pub actions: InputActions,
+}
+
+/// Các nút hành động trong một input frame
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputActions {
+    pub jump: bool,
+    pub slide: bool,
+    pub use_item: Option<String>,
+}
+
+/// Event gameplay phát sinh trong frame hiện tại, gửi kèm snapshot.
+/// Event có `position` chỉ tới player thấy cell đó (AOI); event không có position là global.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameEvent {
+    ItemUsed { player_id: String, item: String },
+    PickupCollected { player_id: String, value: u32, position: [f32; 3] },
+    /// `source` là loại enemy gây damage
+    PlayerDamaged { player_id: String, amount: u32, source: String, position: [f32; 3] },
+    PowerUpActivated { player_id: String, power_type: String, duration_ms: u32, position: [f32; 3] },
+    /// Chưa có health system nên simulation chưa phát event này
+    PlayerDied { player_id: String, killer: Option<String>, position: [f32; 3] },
+    /// Dành cho mode capture-the-flag, luôn gửi cho mọi player
+    FlagCaptured { player_id: String, team: String },
+    /// Room kết thúc trận, kèm bảng điểm cuối
+    MatchEnded { room_id: String, reason: String, scoreboard: Vec<MatchPlayerResult> },
+    /// Worker đổi tick rate của room vì tải; client chỉnh nội suy theo nhịp mới
+    TickRateChanged { room_id: String, tick_rate_hz: u32 },
+}
+
+impl GameEvent {
+    pub fn position(&self) -> Option<[f32; 3]> {
+        match self {
+            GameEvent::PickupCollected { position, .. }
+            | GameEvent::PlayerDamaged { position, .. }
+            | GameEvent::PowerUpActivated { position, .. }
+            | GameEvent::PlayerDied { position, .. } => Some(*position),
+            GameEvent::ItemUsed { .. }
+            | GameEvent::FlagCaptured { .. }
+            | GameEvent::MatchEnded { .. }
+            | GameEvent::TickRateChanged { .. } => None,
+        }
+    }
+}
+
+/// Event kèm tick phát sinh, GameWorld giữ trong `EVENT_RETENTION_TICKS`
+#[derive(Debug, Clone)]
+pub struct RecordedEvent {
+    pub tick: u64,
+    pub event: GameEvent,
 }
 
 /// Snapshot gửi về client
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameSnapshot {
     pub tick: u64,
+    /// Gốc để quantize position tương đối: AOI của player, hoặc tâm các entity với snapshot toàn world
+    #[serde(default)]
+    pub origin: [f32; 3],
     pub entities: Vec<EntitySnapshot>,
     pub chat_messages: Vec<ChatMessage>,
     pub spectators: Vec<SpectatorSnapshot>,
+    #[serde(default)]
+    pub events: Vec<GameEvent>,
+    /// Seed sinh map của world; có trong mọi snapshot của GameWorld để client vào muộn vẫn kiểm lại được
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Unix millis lúc worker tạo snapshot, client dùng để nội suy theo giờ server
+    #[serde(default)]
+    pub server_time_ms: u64,
+    /// Độ dài một tick (ms) lúc tạo snapshot
+    #[serde(default)]
+    pub tick_duration_ms: f32,
+    /// Sequence input mới nhất của player đã được xử lý, client bỏ các input đã ack khi reconcile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_acked_sequence: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -701,42 +1380,122 @@ impl SimulationWorld {
     pub fn create_snapshot(&self) -> GameSnapshot {
         GameSnapshot {
             tick: self.tick_count,
+            origin: [0.0; 3],
             entities: self.entities.clone(),
             chat_messages: Vec::new(), // SimulationWorld doesn't have chat
             spectators: Vec::new(), // SimulationWorld doesn't have spectators
+            events: Vec::new(),
+            seed: None,
+            server_time_ms: unix_millis(),
+            tick_duration_ms: 0.0,
+            last_acked_sequence: None,
         }
     }
 }
 
+/// Số input chưa xử lý tối đa mỗi player; client gửi nhanh hơn tốc độ xử lý thì input cũ nhất bị bỏ
+pub const MAX_BUFFERED_INPUTS: usize = 256;
+/// Input tới trước một sequence còn thiếu được giữ tối đa ngần này chờ sequence đó, quá hạn thì bỏ qua chỗ hổng
+pub const INPUT_REORDER_WINDOW: Duration = Duration::from_millis(100);
+
+/// Kết quả `InputBuffer::add_input`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputAdded {
+    Queued,
+    /// Sequence đã có trong buffer, input mới bị bỏ qua
+    Duplicate,
+    /// Sequence đã xử lý (hoặc đã bị bỏ qua vì mất), không nhận lại
+    AlreadyProcessed,
+    /// Buffer đầy: đã bỏ input cũ nhất để nhận input mới
+    DroppedOldest,
+}
+
 /// Input buffer để xử lý network latency
 #[derive(Debug, Clone)]
 pub struct InputBuffer {
     pub inputs: Vec<PlayerInput>,
     pub last_processed_sequence: u32,
+    pub max_inputs: usize,
+    pub reorder_window: Duration,
+    /// Lúc phát hiện sequence kế tiếp còn thiếu trong khi đã có input sau nó
+    gap_since: Option<Instant>,
 }
 
 impl InputBuffer {
     pub fn new() -> Self {
+        Self::with_max_inputs(MAX_BUFFERED_INPUTS)
+    }
+
+    pub fn with_max_inputs(max_inputs: usize) -> Self {
         Self {
             inputs: Vec::new(),
             last_processed_sequence: 0,
+            max_inputs: max_inputs.max(1),
+            reorder_window: INPUT_REORDER_WINDOW,
+            gap_since: None,
         }
     }
 
-    pub fn add_input(&mut self, input: PlayerInput) {
+    pub fn add_input(&mut self, input: PlayerInput) -> InputAdded {
+        if input.input_sequence <= self.last_processed_sequence {
+            return InputAdded::AlreadyProcessed;
+        }
         // Insert theo sequence number
         let insert_pos = self.inputs.partition_point(|i| i.input_sequence < input.input_sequence);
+        if self.inputs.get(insert_pos).is_some_and(|i| i.input_sequence == input.input_sequence) {
+            return InputAdded::Duplicate;
+        }
         self.inputs.insert(insert_pos, input);
+
+        if self.inputs.len() <= self.max_inputs {
+            return InputAdded::Queued;
+        }
+        let dropped = self.inputs.remove(0);
+        crate::simulation_metrics().inc_inputs_dropped(&dropped.player_id);
+        InputAdded::DroppedOldest
     }
 
+    /// Chuỗi input liên tiếp ngay sau `last_processed_sequence`; input nằm sau một chỗ hổng phải chờ.
+    /// Chưa xử lý input nào thì sequence nhỏ nhất đang có là điểm bắt đầu
     pub fn get_pending_inputs(&self) -> Vec<&PlayerInput> {
-        self.inputs.iter()
-            .filter(|input| input.input_sequence > self.last_processed_sequence)
+        let Some(first) = self.inputs.first() else {
+            return Vec::new();
+        };
+        let start = if self.last_processed_sequence == 0 {
+            first.input_sequence
+        } else {
+            self.last_processed_sequence + 1
+        };
+        self.inputs
+            .iter()
+            .zip(start..)
+            .take_while(|(input, expected)| input.input_sequence == *expected)
+            .map(|(input, _)| input)
             .collect()
     }
 
+    /// Sequence kế tiếp còn thiếu quá `reorder_window` (tính từ lần đầu thấy chỗ hổng) thì coi như mất hẳn:
+    /// bỏ qua tới ngay trước input sớm nhất đang có. Trả về khoảng sequence bị bỏ qua
+    pub fn skip_expired_gap(&mut self, now: Instant) -> Option<std::ops::RangeInclusive<u32>> {
+        let first = self.inputs.first()?.input_sequence;
+        if self.last_processed_sequence == 0 || first == self.last_processed_sequence + 1 {
+            self.gap_since = None;
+            return None;
+        }
+
+        let since = *self.gap_since.get_or_insert(now);
+        if now.duration_since(since) < self.reorder_window {
+            return None;
+        }
+        let missing = self.last_processed_sequence + 1..=first - 1;
+        self.last_processed_sequence = first - 1;
+        self.gap_since = None;
+        Some(missing)
+    }
+
     pub fn mark_processed(&mut self, sequence: u32) {
         self.last_processed_sequence = sequence;
+        self.gap_since = None;
         // Remove các input đã xử lý
         self.inputs.retain(|input| input.input_sequence > self.last_processed_sequence);
     }
@@ -760,12 +1519,27 @@ pub struct GameWorld {
     pub input_validator: InputValidator,
     pub last_tick: Instant,
     pub accumulator: Duration,
-    pub tick_rate: Duration, // 60Hz = 16.67ms per tick
+    pub tick_rate: Duration, // 60Hz = 16.67ms per tick; đổi qua set_tick_hz
     pub spatial_grid: SpatialGrid, // AOI system
     pub player_aois: HashMap<String, PlayerAOI>, // Track each player's AOI
     pub delta_encoder: DeltaEncoder, // Delta encoding system
+    pub player_encoders: HashMap<String, DeltaEncoder>, // Encoder riêng cho snapshot AOI của từng player
+    pub keyframe_policy: KeyframePolicy,
+    pub quantization: QuantizationConfig, // Quantization của room, encoder tự giảm độ chính xác khi cần
     pub last_keyframe_tick: u64, // Last time we sent a full snapshot
     pub current_tick: u64, // Current tick count (separate from world resource)
+    pub spawn_manager: SpawnManager, // Chọn spawn point cho player mới / respawn
+    pub events: Vec<RecordedEvent>, // Events của các tick gần đây, mỗi encoder lấy phần mới qua events_since_tick
+    pub bots: BotController, // Bot players, sinh input mỗi fixed tick
+    pub enemy_steering: SteeringBuffers, // Buffer dùng lại cho AI của enemy
+    pub runner_track: RunnerTrack, // Con trỏ sinh obstacle endless runner, seed theo `seed` trừ khi set_runner_seed
+    pub seed: u64, // Seed của world, client nhận qua snapshot để dự đoán/kiểm lại map
+    pub rng: StdRng, // Mọi lần random của simulation rút từ đây để cùng seed + cùng input thì ra cùng world
+    pub body_pool: BodyPool, // Body của obstacle/enemy/pickup đã despawn, bật lại khi spawn cùng hình
+    pub overload: OverloadController, // Giảm tải từng bậc khi fixed_update vượt tick budget
+    pub player_rooms: HashMap<String, String>, // player_id -> room_id, để lọc chat/system message theo room
+    #[cfg(test)]
+    injected_gameplay_delay: Duration, // Giả lập gameplay_logic chạy chậm
 }
 
 impl Default for GameWorld {
@@ -774,8 +1548,34 @@ impl Default for GameWorld {
     }
 }
 
+/// Cấu hình lúc tạo GameWorld
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameWorldConfig {
+    /// Seed sinh map; None thì chọn ngẫu nhiên
+    pub seed: Option<u64>,
+    /// Tick rate của world (Hz), clamp về `MIN_TICK_HZ..=MAX_TICK_HZ`
+    pub tick_hz: u32,
+}
+
+impl Default for GameWorldConfig {
+    fn default() -> Self {
+        Self { seed: None, tick_hz: BASE_TICK_HZ }
+    }
+}
+
 impl GameWorld {
+    /// World với seed ngẫu nhiên (đọc lại qua `seed`)
     pub fn new() -> Self {
+        Self::new_with_config(GameWorldConfig::default())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::new_with_config(GameWorldConfig { seed: Some(seed), ..GameWorldConfig::default() })
+    }
+
+    pub fn new_with_config(config: GameWorldConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(rand::random);
+        let tick_hz = tick_rate::clamp_tick_hz(config.tick_hz);
         let mut world = World::new();
 
         // Register components và resources
@@ -788,13 +1588,17 @@ impl GameWorld {
         let island_manager = IslandManager::new();
         let broad_phase = DefaultBroadPhase::new();
         let narrow_phase = NarrowPhase::new();
-        let bodies = RigidBodySet::new();
-        let colliders = ColliderSet::new();
+        let mut bodies = RigidBodySet::new();
+        let mut colliders = ColliderSet::new();
         let impulse_joints = ImpulseJointSet::new();
         let multibody_joints = MultibodyJointSet::new();
         let ccd_solver = CCDSolver::new();
         let query_pipeline = QueryPipeline::new();
 
+        // Mặt đất: half-space y = 0 để player có chỗ đứng (jump/grounded check)
+        let ground_body = bodies.insert(RigidBodyBuilder::fixed().build());
+        colliders.insert_with_parent(ColliderBuilder::halfspace(Vector::y_axis()).build(), ground_body, &mut bodies);
+
         Self {
             world,
             physics_pipeline,
@@ -812,12 +1616,27 @@ impl GameWorld {
             input_validator: InputValidator::with_default_config(),
             last_tick: Instant::now(),
             accumulator: Duration::from_secs(0),
-            tick_rate: Duration::from_millis(16), // 60Hz
+            tick_rate: Duration::from_secs(1) / tick_hz,
             spatial_grid: SpatialGrid::new(50.0), // 50 unit cells
             player_aois: HashMap::new(),
             delta_encoder: DeltaEncoder::new(5), // Delta threshold: 5 entities
+            player_encoders: HashMap::new(),
+            keyframe_policy: KeyframePolicy::default(),
+            quantization: default_quantization(),
             last_keyframe_tick: 0,
             current_tick: 0,
+            spawn_manager: SpawnManager::default(),
+            events: Vec::new(),
+            bots: BotController::new(),
+            enemy_steering: SteeringBuffers::default(),
+            runner_track: RunnerTrack::new(seed),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            player_rooms: HashMap::new(),
+            body_pool: BodyPool::default(),
+            overload: OverloadController::default(),
+            #[cfg(test)]
+            injected_gameplay_delay: Duration::ZERO,
         }
     }
 
@@ -830,11 +1649,12 @@ impl GameWorld {
         // Fixed timestep - chỉ tick khi đủ thời gian
         let mut ticks = 0;
         while self.accumulator >= self.tick_rate && ticks < 3 { // Max 3 ticks per frame
-            self.fixed_update();
-            self.current_tick += 1; // Increment tick count
+            self.timed_fixed_update();
             self.accumulator -= self.tick_rate;
             ticks += 1;
         }
+        // Tick bị cắt bởi giới hạn 3 tick/frame dồn lại trong accumulator
+        crate::simulation_metrics().set_accumulated_lag(self.accumulator);
 
         // Get current tick count
         let current_tick = self.current_tick;
@@ -843,68 +1663,255 @@ impl GameWorld {
         let base_snapshot = self.create_snapshot();
 
         // Use delta encoding
-        self.delta_encoder.encode_snapshot(base_snapshot, current_tick)
+        self.delta_encoder.position_change_threshold = self.overload.level().position_change_threshold();
+        let encoded = self.delta_encoder.encode_snapshot(base_snapshot, current_tick);
+        self.delta_encoder.events_since_tick = current_tick;
+        self.note_keyframe(&encoded);
+        encoded
     }
 
-    /// Chạy simulation trong thời gian ngắn để test
-    pub fn run_simulation_for_test(&mut self, duration_secs: f32) -> Vec<EncodedSnapshot> {
-        let mut snapshots = Vec::new();
-        let target_ticks = (duration_secs * 60.0) as u32; // 60 FPS
-
-        // Đảm bảo có ít nhất một số entities để test
-        if self.world.query::<&Player>().iter(&self.world).count() == 0 {
-            self.add_player("test_player".to_string());
-        }
+    /// Thời gian mô phỏng còn nợ: tăng dần qua các frame nghĩa là worker không theo kịp tick rate
+    pub fn accumulated_lag(&self) -> Duration {
+        self.accumulator
+    }
 
-        // Lưu tick count hiện tại để kiểm tra sau
-        let initial_tick = self.get_current_tick();
+    pub fn degradation_level(&self) -> DegradationLevel {
+        self.overload.level()
+    }
 
-        // Force tick bằng cách set accumulator đủ lớn
-        self.accumulator = self.tick_rate * target_ticks as u32;
+    /// Chạy một fixed tick và đưa thời gian chạy cho overload controller
+    fn timed_fixed_update(&mut self) {
+        let started = Instant::now();
+        self.fixed_update();
+        self.current_tick += 1;
 
-        for _i in 0..target_ticks {
-            // Get encoded snapshot (includes delta encoding)
-            let snapshot = self.tick();
-            snapshots.push(snapshot);
+        let elapsed = started.elapsed();
+        let Some((from, to)) = self.overload.observe(elapsed, self.tick_rate, self.current_tick) else {
+            return;
+        };
+        let ewma_ms = self.overload.ewma().as_secs_f64() * 1000.0;
+        let budget_ms = self.tick_rate.as_secs_f64() * 1000.0;
+        if to > from {
+            tracing::warn!(?from, ?to, ewma_ms, budget_ms, "simulation over tick budget, shedding load");
+        } else {
+            tracing::info!(?from, ?to, ewma_ms, budget_ms, "simulation load dropped, restoring");
         }
-
-        // Debug: kiểm tra tick count đã tăng chưa
-        let final_tick = self.get_current_tick();
-        println!("Initial tick: {}, Final tick: {}, Target ticks: {}", initial_tick, final_tick, target_ticks);
-
-        snapshots
+        crate::simulation_metrics().set_degradation_level(to.as_i64());
     }
 
-    /// Get current snapshot without mutating state (for external use) - deprecated, use get_snapshot_for_player
-    pub fn get_snapshot(&mut self) -> GameSnapshot {
-        // Fallback to full snapshot for backward compatibility
-        self.create_snapshot()
+    /// Chia AOI theo tầng cao `layer_height` (None = chỉ x/z)
+    pub fn set_aoi_layer_height(&mut self, layer_height: Option<f32>) {
+        self.spatial_grid.set_layer_height(layer_height);
     }
 
-    /// Get current tick count
-    pub fn get_current_tick(&self) -> u64 {
-        self.current_tick
+    /// Seed cố định cho track endless runner để sinh lại đúng dãy obstacle (replay, test)
+    pub fn set_runner_seed(&mut self, seed: u64) {
+        self.runner_track = RunnerTrack::new(seed);
     }
 
-    /// Force send keyframe (full snapshot) for specific player
-    pub fn force_keyframe_for_player(&mut self, player_id: &str) -> EncodedSnapshot {
-        // Create fresh delta encoder for this player
-        let mut player_encoder = DeltaEncoder::new(1); // Always send full for keyframe
-
-        let base_snapshot = self.create_snapshot();
-        let current_tick = self.world.resource::<TickCount>().0;
+    /// Sinh lại từ `seed` mọi thứ chưa sinh: track endless runner và các lần random về sau.
+    /// Entity đã có giữ nguyên, nên gọi trước khi room bắt đầu spawn
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+        self.runner_track = RunnerTrack::new(seed);
+    }
 
-        player_encoder.encode_snapshot(base_snapshot, current_tick)
+    /// Đổi keyframe policy cho encoder chung lẫn encoder của từng player; `interval_ticks` quy đổi theo tick rate hiện tại
+    pub fn set_keyframe_policy(&mut self, policy: KeyframePolicy) {
+        self.keyframe_policy = policy;
+        let policy = policy.at_tick_hz(self.tick_hz());
+        self.delta_encoder.keyframe_policy = policy;
+        for encoder in self.player_encoders.values_mut() {
+            encoder.keyframe_policy = policy;
+        }
+    }
+
+    /// Đổi quantization của room (vd. map rộng cần position factor lớn hơn); áp dụng từ snapshot kế tiếp
+    pub fn set_quantization(&mut self, config: QuantizationConfig) {
+        self.quantization = config.clone();
+        for encoder in self.player_encoders.values_mut() {
+            encoder.quantization = config.clone();
+        }
+        self.delta_encoder.quantization = config;
+    }
+
+    /// Số liệu encode cộng dồn của encoder chung và mọi encoder theo player
+    pub fn encoding_stats(&self) -> EncodingStats {
+        let mut stats = self.delta_encoder.stats;
+        for encoder in self.player_encoders.values() {
+            stats.merge(&encoder.stats);
+        }
+        stats
+    }
+
+    /// Số liệu encode của snapshot AOI gửi cho `player_id`; None khi player chưa nhận snapshot nào
+    pub fn player_encoding_stats(&self, player_id: &str) -> Option<EncodingStats> {
+        self.player_encoders.get(player_id).map(|encoder| encoder.stats)
+    }
+
+    fn note_keyframe(&mut self, encoded: &EncodedSnapshot) {
+        if let EncodedSnapshot::Full(full) = encoded {
+            self.last_keyframe_tick = full.tick;
+        }
+    }
+
+    fn player_encoder(&mut self, player_id: &str) -> &mut DeltaEncoder {
+        let policy = self.keyframe_policy.at_tick_hz(self.tick_hz());
+        let current_tick = self.current_tick;
+        let quantization = &self.quantization;
+        // Player mới không nhận lại event cũ hơn lúc encoder được tạo
+        let encoder = self.player_encoders.entry(player_id.to_string()).or_insert_with(|| {
+            let mut encoder = DeltaEncoder::new(5).with_keyframe_policy(policy);
+            encoder.events_since_tick = current_tick;
+            encoder.quantization = quantization.clone();
+            encoder
+        });
+        encoder.position_change_threshold = self.overload.level().position_change_threshold();
+        encoder
+    }
+
+    /// Ghi event cho tick đang chạy
+    fn emit(&mut self, event: GameEvent) {
+        self.events.push(RecordedEvent { tick: self.current_tick, event });
+    }
+
+    /// Độ dài một tick theo `tick_rate`, tính bằng ms
+    pub fn tick_duration_ms(&self) -> f32 {
+        self.tick_rate.as_secs_f32() * 1000.0
+    }
+
+    /// Tick rate hiện tại (Hz), làm tròn từ `tick_rate`
+    pub fn tick_hz(&self) -> u32 {
+        (1.0 / self.tick_rate.as_secs_f64().max(f64::EPSILON)).round() as u32
+    }
+
+    /// Số tick mỗi giây theo `tick_rate`
+    pub fn ticks_per_second(&self) -> u64 {
+        self.tick_hz().max(1) as u64
+    }
+
+    /// Báo trận của room đã kết thúc cho mọi client qua snapshot kế tiếp
+    pub fn emit_match_ended(&mut self, room_id: &str, reason: &str, scoreboard: Vec<MatchPlayerResult>) {
+        self.emit(GameEvent::MatchEnded {
+            room_id: room_id.to_string(),
+            reason: reason.to_string(),
+            scoreboard,
+        });
+    }
+
+    /// Báo client của room tick rate mới qua snapshot kế tiếp
+    pub fn emit_tick_rate_changed(&mut self, room_id: &str, tick_rate_hz: u32) {
+        self.emit(GameEvent::TickRateChanged { room_id: room_id.to_string(), tick_rate_hz });
+    }
+
+    /// Nhịp fixed update của cả world; keyframe cadence quy đổi theo để giữ nguyên khoảng thời gian
+    pub fn set_tick_hz(&mut self, tick_rate_hz: u32) {
+        self.tick_rate = Duration::from_secs(1) / tick_rate_hz.max(1);
+        self.set_keyframe_policy(self.keyframe_policy);
+    }
+
+    /// Player có event `player_died` còn trong cửa sổ `EVENT_RETENTION_TICKS`
+    pub fn recently_died_players(&self) -> Vec<String> {
+        self.events
+            .iter()
+            .filter_map(|recorded| match &recorded.event {
+                GameEvent::PlayerDied { player_id, .. } => Some(player_id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Event từ tick `since` trở đi; có `visible_cells` thì bỏ event có position nằm ngoài các cell đó
+    fn events_since(&self, since: u64, visible_cells: Option<&[GridCell]>) -> Vec<GameEvent> {
+        self.events
+            .iter()
+            .filter(|recorded| recorded.tick >= since)
+            .filter(|recorded| match (recorded.event.position(), visible_cells) {
+                (Some(position), Some(cells)) => cells.contains(&self.spatial_grid.world_to_cell(position)),
+                _ => true,
+            })
+            .map(|recorded| recorded.event.clone())
+            .collect()
+    }
+
+    /// Chạy simulation trong thời gian ngắn để test
+    pub fn run_simulation_for_test(&mut self, duration_secs: f32) -> Vec<EncodedSnapshot> {
+        let mut snapshots = Vec::new();
+        let target_ticks = (duration_secs * self.ticks_per_second() as f32).round() as u32;
+
+        // Đảm bảo có ít nhất một số entities để test
+        if self.world.query::<&Player>().iter(&self.world).count() == 0 {
+            self.add_player("test_player".to_string());
+        }
+
+        // Lưu tick count hiện tại để kiểm tra sau
+        let initial_tick = self.get_current_tick();
+
+        for _i in 0..target_ticks {
+            // Mỗi snapshot đúng một tick mô phỏng, không phụ thuộc thời gian thật test chạy
+            self.last_tick = Instant::now();
+            self.accumulator = self.tick_rate;
+            let snapshot = self.tick();
+            snapshots.push(snapshot);
+        }
+
+        // Debug: kiểm tra tick count đã tăng chưa
+        let final_tick = self.get_current_tick();
+        println!("Initial tick: {}, Final tick: {}, Target ticks: {}", initial_tick, final_tick, target_ticks);
+
+        snapshots
+    }
+
+    /// Get current snapshot without mutating state (for external use) - deprecated, use get_snapshot_for_player
+    pub fn get_snapshot(&mut self) -> GameSnapshot {
+        // Fallback to full snapshot for backward compatibility
+        self.create_snapshot()
+    }
+
+    /// Get current tick count
+    pub fn get_current_tick(&self) -> u64 {
+        self.current_tick
+    }
+
+    /// Force send keyframe (full snapshot) for specific player; các delta sau lấy keyframe này làm base
+    pub fn force_keyframe_for_player(&mut self, player_id: &str) -> EncodedSnapshot {
+        let base_snapshot = self.player_base_snapshot(player_id);
+        let current_tick = self.current_tick;
+
+        let encoder = self.player_encoder(player_id);
+        let encoded = encoder.encode_keyframe(base_snapshot, current_tick);
+        encoder.events_since_tick = current_tick;
+        self.note_keyframe(&encoded);
+        encoded
     }
 
     /// Get current snapshot for a specific player using AOI optimization và delta encoding
     pub fn get_snapshot_for_player(&mut self, player_id: &str) -> EncodedSnapshot {
-        let player_position = self.get_player_position(player_id)
-            .unwrap_or([0.0, 5.0, 0.0]);
+        let base_snapshot = self.player_base_snapshot(player_id);
+        let current_tick = self.current_tick;
+
+        // Mỗi player một encoder để delta/keyframe không lẫn giữa các AOI
+        let encoder = self.player_encoder(player_id);
+        let encoded = encoder.encode_snapshot(base_snapshot, current_tick);
+        encoder.events_since_tick = current_tick;
+        self.note_keyframe(&encoded);
+        encoded
+    }
+
+    /// Snapshot chỉ gồm entities trong AOI của player
+    fn player_base_snapshot(&mut self, player_id: &str) -> GameSnapshot {
+        let known_position = self.get_player_position(player_id);
+        let player_position = known_position.unwrap_or([0.0, 5.0, 0.0]);
 
         // Update player's AOI tracking
         self.update_player_aoi_grid(player_id);
 
+        // Event có position chỉ gửi khi nằm trong 3x3 cell quanh player
+        let events_since_tick = self.player_encoder(player_id).events_since_tick;
+        let visible_cells = known_position.map(|position| self.spatial_grid.get_player_aoi_cells(position));
+        let events = self.events_since(events_since_tick, visible_cells.as_deref());
+
         // Get entities in player's AOI using spatial grid
         let aoi_entities = if let Some(player_aoi) = self.player_aois.get(player_id) {
             let center_cell = self.spatial_grid.world_to_cell(player_position);
@@ -948,16 +1955,22 @@ impl GameWorld {
             }
         }
 
-        let base_snapshot = GameSnapshot {
-            tick: self.world.resource::<TickCount>().0,
+        GameSnapshot {
+            tick: self.current_tick,
+            origin: snap_origin(player_position, self.spatial_grid.cell_size),
             entities,
-            chat_messages: self.get_recent_chat_messages(20),
+            chat_messages: self.chat_messages_for(player_id, 20),
             spectators: self.get_spectator_snapshots(),
-        };
-
-        // Use delta encoding for this player's snapshot
-        let current_tick = self.world.resource::<TickCount>().0;
-        self.delta_encoder.encode_snapshot(base_snapshot, current_tick)
+            events,
+            seed: Some(self.seed),
+            server_time_ms: unix_millis(),
+            tick_duration_ms: self.tick_duration_ms(),
+            last_acked_sequence: self
+                .input_buffers
+                .get(player_id)
+                .map(|buffer| buffer.last_processed_sequence)
+                .filter(|&sequence| sequence > 0),
+        }
     }
 
     /// Update player's AOI tracking (called during snapshot generation) - DEPRECATED
@@ -983,6 +1996,52 @@ impl GameWorld {
         self.chat_messages[start..].to_vec()
     }
 
+    /// N tin gần nhất `player_id` được thấy: tin chung, tin của room player đang ở và tin gửi riêng cho player
+    pub fn chat_messages_for(&self, player_id: &str, count: usize) -> Vec<ChatMessage> {
+        let room_id = self.player_rooms.get(player_id);
+        let mut visible: Vec<ChatMessage> = self
+            .chat_messages
+            .iter()
+            .rev()
+            .filter(|message| message.room_id.is_none() || message.room_id.as_ref() == room_id)
+            .filter(|message| message.recipient_id.as_deref().is_none_or(|r| r == player_id))
+            .take(count)
+            .cloned()
+            .collect();
+        visible.reverse();
+        visible
+    }
+
+    /// Thêm system message vào chat của `room_id` (None: mọi room), `recipient_id` để gửi riêng một player
+    pub fn add_system_message(
+        &mut self,
+        room_id: Option<&str>,
+        recipient_id: Option<&str>,
+        system: SystemMessage,
+    ) -> ChatMessage {
+        let message = ChatMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            player_id: "system".to_string(),
+            player_name: "system".to_string(),
+            message: system.key.clone(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            message_type: ChatMessageType::System,
+            system: Some(system),
+            room_id: room_id.map(str::to_string),
+            recipient_id: recipient_id.map(str::to_string),
+        };
+        self.add_chat_message(message.clone());
+        message
+    }
+
+    /// Ghi room của player để lọc chat theo room
+    pub fn set_player_room(&mut self, player_id: &str, room_id: &str) {
+        self.player_rooms.insert(player_id.to_string(), room_id.to_string());
+    }
+
     /// Get spectator snapshots for all active spectators
     pub fn get_spectator_snapshots(&mut self) -> Vec<SpectatorSnapshot> {
         let mut query = self.world.query::<(Entity, &Spectator, &TransformQ)>();
@@ -1008,8 +2067,16 @@ impl GameWorld {
         // Tăng tick count (already done in tick() method)
         // current_tick is incremented in tick() method
 
+        // Bỏ event đã quá hạn giữ
+        let current_tick = self.current_tick;
+        self.events.retain(|recorded| current_tick.saturating_sub(recorded.tick) < EVENT_RETENTION_TICKS);
+
+        // 0. Bot players sinh input vào InputBuffer như client thật
+        self.drive_bots();
+
         // 1. Ingest và validate inputs
         self.ingest_inputs();
+        self.update_slides();
 
         // 2. Validate inputs (anti-cheat cơ bản)
         self.validate_inputs();
@@ -1018,8 +2085,11 @@ impl GameWorld {
         let delta_time = self.tick_rate;
         self.update_endless_runner(delta_time);
 
-        // 4. Physics step
+        // 4. Physics step (ECS quyết định x/z, Rapier quyết định y)
+        self.update_moving_platforms();
+        self.sync_player_bodies();
         self.physics_step();
+        self.sync_player_transforms();
 
         // 4.5. Update spatial grid với vị trí mới sau physics
         self.update_spatial_grid();
@@ -1030,15 +2100,50 @@ impl GameWorld {
         // 6. Cleanup (lifetime, etc.)
         self.cleanup();
 
-        // 7. Spatial grid maintenance (every 60 ticks)
-        if self.current_tick % 60 == 0 {
+        // 7. Spatial grid maintenance + dọn player mất kết nối quá grace (mỗi giây)
+        if self.current_tick.is_multiple_of(self.ticks_per_second()) {
             self.spatial_grid.cleanup_empty_cells();
+            self.reap_disconnected();
+        }
+        if self.current_tick.is_multiple_of(SPATIAL_GRID_REBUILD_TICKS) {
+            self.rebuild_spatial_grid();
         }
 
         // 8. Room cleanup
         // Note: RoomManager cleanup is handled separately in RPC service
     }
 
+    /// Bot nhìn vị trí của mình, pickups và obstacles rồi đẩy input vào InputBuffer.
+    /// Validate để ingest_inputs làm, giống input từ client.
+    fn drive_bots(&mut self) {
+        if self.bots.is_empty() {
+            return;
+        }
+
+        let mut senses = BotSenses::default();
+        let mut player_query = self.world.query::<(&Player, &TransformQ)>();
+        for (player, transform) in player_query.iter(&self.world) {
+            if player.is_bot {
+                senses.positions.insert(player.id.clone(), transform.position);
+            }
+        }
+        let mut pickup_query = self.world.query_filtered::<&TransformQ, With<Pickup>>();
+        senses.pickups = pickup_query.iter(&self.world).map(|t| t.position).collect();
+        let mut obstacle_query = self.world.query_filtered::<&TransformQ, With<Obstacle>>();
+        senses.obstacles = obstacle_query.iter(&self.world).map(|t| t.position).collect();
+
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        for input in self.bots.plan(&senses, self.current_tick, now_ms, &mut self.rng) {
+            self.input_buffers
+                .entry(input.player_id.clone())
+                .or_insert_with(InputBuffer::new)
+                .add_input(input);
+        }
+    }
+
     fn ingest_inputs(&mut self) {
         // Clean up validator periodically
         self.input_validator.cleanup();
@@ -1046,16 +2151,29 @@ impl GameWorld {
         // Collect input applications first to avoid borrowing conflicts
         let mut input_applications = Vec::new();
 
+        let now = Instant::now();
         for (player_id, buffer) in &mut self.input_buffers {
+            if let Some(missing) = buffer.skip_expired_gap(now) {
+                tracing::warn!(%player_id, from = missing.start(), to = missing.end(), "input sequence gap skipped");
+            }
             let pending_inputs = buffer.get_pending_inputs();
+            let last_sequence = pending_inputs.iter().map(|input| input.input_sequence).max();
 
             // Validate and process inputs for this player
             for input in pending_inputs {
                 match self.input_validator.validate_input(input) {
                     Ok(_) => {
-                        // Input is valid, use it
-                        if let Some(player_entity) = self.world.resource::<PlayerEntityMap>().map.get(player_id) {
-                            input_applications.push((*player_entity, input.movement[0] * 10.0, input.movement[2] * 10.0));
+                        // Input is valid, use it (player mất kết nối thì bị đóng băng)
+                        if let Some(player_entity) = self.world.resource::<PlayerEntityMap>().map.get(player_id)
+                            .filter(|&&entity| self.world.get::<Disconnected>(entity).is_none())
+                        {
+                            input_applications.push((
+                                *player_entity,
+                                player_id.clone(),
+                                input.movement[0] * 10.0,
+                                input.movement[2] * 10.0,
+                                input.actions.clone(),
+                            ));
                         }
                     }
                     Err(e) => {
@@ -1064,22 +2182,199 @@ impl GameWorld {
                     }
                 }
             }
+
+            // Actions (jump/slide/use_item) chỉ được áp dụng một lần
+            if let Some(sequence) = last_sequence {
+                buffer.mark_processed(sequence);
+            }
         }
 
         // Apply inputs after collecting and validating
-        for (player_entity, vel_x, vel_z) in input_applications {
+        for (player_entity, player_id, vel_x, vel_z, actions) in input_applications {
             if let Some(mut velocity) = self.world.get_mut::<VelocityQ>(player_entity) {
                 velocity.velocity[0] = vel_x;
                 velocity.velocity[2] = vel_z;
             }
+
+            if actions.jump {
+                self.try_jump(player_entity, &player_id);
+            }
+            if actions.slide {
+                self.start_slide(player_entity);
+            }
+            if let Some(item) = actions.use_item {
+                // Chưa có inventory - chỉ báo cho client qua snapshot
+                self.emit(GameEvent::ItemUsed { player_id, item });
+            }
+        }
+    }
+
+    /// Raycast xuống từ tâm body; chạm collider khác trong khoảng radius + tolerance thì coi là grounded
+    pub fn is_grounded(&self, body_handle: rapier3d::dynamics::RigidBodyHandle) -> bool {
+        let Some(body) = self.bodies.get(body_handle) else {
+            return false;
+        };
+
+        let radius = body
+            .colliders()
+            .first()
+            .and_then(|handle| self.colliders.get(*handle))
+            .and_then(|collider| collider.shape().as_ball())
+            .map(|ball| ball.radius)
+            .unwrap_or(PLAYER_RADIUS);
+
+        let origin = body.translation();
+        let ray = Ray::new(point![origin.x, origin.y, origin.z], vector![0.0, -1.0, 0.0]);
+        self.query_pipeline
+            .cast_ray(
+                &self.bodies,
+                &self.colliders,
+                &ray,
+                radius + GROUND_CHECK_TOLERANCE,
+                true,
+                QueryFilter::default().exclude_rigid_body(body_handle),
+            )
+            .is_some()
+    }
+
+    /// Nhảy khi đang đứng trên mặt đất; nhảy liên tục bị validator chặn
+    fn try_jump(&mut self, player_entity: Entity, player_id: &str) {
+        let Some(body_handle) = self.world.get::<RigidBodyHandle>(player_entity).map(|h| h.handle) else {
+            return;
+        };
+
+        if !self.is_grounded(body_handle) {
+            return;
+        }
+
+        if let Err(e) = self.input_validator.validate_jump(player_id, self.current_tick) {
+            tracing::debug!("Ignoring jump from player {}: {}", player_id, e);
+            return;
+        }
+
+        if let Some(body) = self.bodies.get_mut(body_handle) {
+            let impulse = JUMP_SPEED * body.mass();
+            body.apply_impulse(vector![0.0, impulse, 0.0], true);
+        }
+    }
+
+    /// Bắt đầu slide: thu nhỏ collider trong SLIDE_DURATION_TICKS
+    fn start_slide(&mut self, player_entity: Entity) {
+        if self.world.get::<Sliding>(player_entity).is_some() {
+            return;
+        }
+
+        let Some(body_handle) = self.world.get::<RigidBodyHandle>(player_entity).map(|h| h.handle) else {
+            return;
+        };
+
+        self.set_player_collider_radius(body_handle, SLIDE_RADIUS);
+        self.world.entity_mut(player_entity).insert(Sliding {
+            remaining_ticks: SLIDE_DURATION_TICKS,
+        });
+    }
+
+    /// Đếm ngược slide, hết thời gian thì trả collider về kích thước cũ
+    fn update_slides(&mut self) {
+        let mut finished = Vec::new();
+        let mut query = self.world.query::<(Entity, &mut Sliding, &RigidBodyHandle)>();
+        for (entity, mut sliding, body_handle) in query.iter_mut(&mut self.world) {
+            sliding.remaining_ticks = sliding.remaining_ticks.saturating_sub(1);
+            if sliding.remaining_ticks == 0 {
+                finished.push((entity, body_handle.handle));
+            }
+        }
+
+        for (entity, body_handle) in finished {
+            self.set_player_collider_radius(body_handle, PLAYER_RADIUS);
+            self.world.entity_mut(entity).remove::<Sliding>();
+        }
+    }
+
+    fn set_player_collider_radius(&mut self, body_handle: rapier3d::dynamics::RigidBodyHandle, radius: f32) {
+        let collider_handles = self
+            .bodies
+            .get(body_handle)
+            .map(|body| body.colliders().to_vec())
+            .unwrap_or_default();
+
+        for handle in collider_handles {
+            if let Some(collider) = self.colliders.get_mut(handle) {
+                collider.set_shape(SharedShape::ball(radius));
+            }
+        }
+    }
+
+    /// Dời moving platform tới vị trí tick này; player đang đứng trên mặt platform bị kéo theo cùng độ dời
+    fn update_moving_platforms(&mut self) {
+        let dt = self.tick_rate.as_secs_f32();
+        let mut moves = Vec::new();
+        let mut platform_query = self.world.query::<(&mut MovingPlatform, &mut TransformQ, &RigidBodyHandle)>();
+        for (mut platform, mut transform, body_handle) in platform_query.iter_mut(&mut self.world) {
+            let old = transform.position;
+            platform.elapsed += dt;
+            let new = platform.position();
+            transform.position = new;
+            if let Some(body) = self.bodies.get_mut(body_handle.handle) {
+                body.set_translation(vector![new[0], new[1], new[2]], true);
+            }
+            moves.push((old, [new[0] - old[0], new[1] - old[1], new[2] - old[2]]));
+        }
+        if moves.is_empty() {
+            return;
+        }
+
+        let [hx, hy, hz] = MOVING_PLATFORM_HALF_EXTENTS;
+        let mut player_query = self.world.query_filtered::<(&mut TransformQ, &RigidBodyHandle), With<Player>>();
+        for (mut transform, body_handle) in player_query.iter_mut(&mut self.world) {
+            let Some(body) = self.bodies.get_mut(body_handle.handle) else {
+                continue;
+            };
+            let feet = body.translation().y - PLAYER_RADIUS;
+            let standing_on = moves.iter().find(|(old, _)| {
+                (transform.position[0] - old[0]).abs() <= hx
+                    && (transform.position[2] - old[2]).abs() <= hz
+                    && (feet - (old[1] + hy)).abs() <= GROUND_CHECK_TOLERANCE
+            });
+            if let Some((_, delta)) = standing_on {
+                transform.position[0] += delta[0];
+                transform.position[2] += delta[2];
+                let translation = body.translation() + vector![delta[0], delta[1], delta[2]];
+                body.set_translation(translation, true);
+                transform.position[1] = translation.y;
+            }
+        }
+    }
+
+    /// Đẩy x/z từ ECS (auto-run, lane snapping, enemy steering) sang rigid body trước physics step
+    fn sync_player_bodies(&mut self) {
+        let mut query = self.world.query_filtered::<(&TransformQ, &RigidBodyHandle), Or<(With<Player>, With<Enemy>)>>();
+        for (transform, body_handle) in query.iter(&self.world) {
+            if let Some(body) = self.bodies.get_mut(body_handle.handle) {
+                let y = body.translation().y;
+                body.set_translation(vector![transform.position[0], y, transform.position[2]], false);
+            }
+        }
+    }
+
+    /// Lấy y (gravity, jump) từ Rapier về ECS sau physics step
+    fn sync_player_transforms(&mut self) {
+        let mut query = self
+            .world
+            .query_filtered::<(&mut TransformQ, &mut VelocityQ, &RigidBodyHandle), Or<(With<Player>, With<Enemy>)>>();
+        for (mut transform, mut velocity, body_handle) in query.iter_mut(&mut self.world) {
+            if let Some(body) = self.bodies.get(body_handle.handle) {
+                transform.position[1] = body.translation().y;
+                velocity.velocity[1] = body.linvel().y;
+            }
         }
     }
 
     fn validate_inputs(&mut self) {
         // Anti-cheat cơ bản: clamp velocity
-        for mut velocity in self.world.query::<&mut VelocityQ>().iter_mut(&mut self.world) {
-            // Clamp velocity để tránh cheating
-            let max_speed = 15.0;
+        for (mut velocity, sliding) in self.world.query::<(&mut VelocityQ, Option<&Sliding>)>().iter_mut(&mut self.world) {
+            // Clamp velocity để tránh cheating, slide thì chậm hơn
+            let max_speed = if sliding.is_some() { SLIDE_MAX_SPEED } else { 15.0 };
             let speed = (velocity.velocity[0].powi(2) + velocity.velocity[2].powi(2)).sqrt();
             if speed > max_speed {
                 velocity.velocity[0] *= max_speed / speed;
@@ -1111,12 +2406,16 @@ impl GameWorld {
     }
 
     fn gameplay_logic(&mut self) {
+        #[cfg(test)]
+        std::thread::sleep(self.injected_gameplay_delay);
+
         // Enhanced gameplay logic với collision detection thực tế hơn
         let mut entities_to_despawn = Vec::new();
         let mut scores_to_add = Vec::new();
         let mut new_pickups = Vec::new();
         let mut power_ups_collected = Vec::new();
         let mut damage_to_players = Vec::new();
+        let mut events = Vec::new();
 
         // Query để lấy tất cả player và pickup entities với physics bodies từ components
 
@@ -1136,11 +2435,16 @@ impl GameWorld {
                     if distance < 0.8 {
                         entities_to_despawn.push(pickup_entity);
                         scores_to_add.push((player.id.clone(), pickup.value));
+                        events.push(GameEvent::PickupCollected {
+                            player_id: player.id.clone(),
+                            value: pickup.value,
+                            position: pickup_transform.position,
+                        });
 
                         let new_pos = [
-                            (rand::random::<f32>() - 0.5) * 20.0,
+                            (self.rng.gen::<f32>() - 0.5) * 20.0,
                             1.0,
-                            (rand::random::<f32>() - 0.5) * 20.0,
+                            (self.rng.gen::<f32>() - 0.5) * 20.0,
                         ];
                         new_pickups.push((new_pos, pickup.value + 5));
 
@@ -1167,6 +2471,12 @@ impl GameWorld {
                     if distance < 0.7 {
                         entities_to_despawn.push(power_up_entity);
                         power_ups_collected.push((player.id.clone(), power_up.clone()));
+                        events.push(GameEvent::PowerUpActivated {
+                            player_id: player.id.clone(),
+                            power_type: power_up.power_type.clone(),
+                            duration_ms: power_up.duration.as_millis() as u32,
+                            position: power_up_transform.position,
+                        });
 
                         tracing::debug!(
                             "Power-up collected: player {} collected {} power-up",
@@ -1179,24 +2489,34 @@ impl GameWorld {
 
         // 3. Player vs Enemies (combat damage)
         {
-            let mut player_query = self.world.query::<(Entity, &TransformQ, &mut Player, &RigidBodyHandle)>();
+            let mut player_query = self
+                .world
+                .query_filtered::<(Entity, &TransformQ, &mut Player, &RigidBodyHandle), Without<Disconnected>>();
             let mut enemy_query = self.world.query::<(Entity, &TransformQ, &Enemy, &RigidBodyHandle)>();
 
             for (player_entity, player_transform, player, _player_rigid_body) in player_query.iter(&self.world) {
                 for (_enemy_entity, enemy_transform, enemy, _enemy_rigid_body) in enemy_query.iter(&self.world) {
-                    let player_pos = vector![player_transform.position[0], player_transform.position[1], player_transform.position[2]];
-                    let enemy_pos = vector![enemy_transform.position[0], enemy_transform.position[1], enemy_transform.position[2]];
-                    let distance = (player_pos - enemy_pos).magnitude();
-
-                    if distance < 1.0 {
-                        if enemy.last_attack.elapsed() >= enemy.attack_cooldown {
-                            damage_to_players.push((player.id.clone(), enemy.damage));
+                    // Chỉ enemy đang Attack mới gây damage, cho player trong tầm đánh trên mặt phẳng x/z
+                    if enemy.steering.enemy_state != EnemyState::Attack {
+                        continue;
+                    }
+                    let distance = ((player_transform.position[0] - enemy_transform.position[0]).powi(2)
+                        + (player_transform.position[2] - enemy_transform.position[2]).powi(2))
+                    .sqrt();
+
+                    if distance <= steering::ATTACK_STOP_DISTANCE && enemy.last_attack.elapsed() >= enemy.attack_cooldown {
+                        damage_to_players.push((player.id.clone(), enemy.damage));
+                        events.push(GameEvent::PlayerDamaged {
+                            player_id: player.id.clone(),
+                            amount: enemy.damage,
+                            source: enemy.enemy_type.clone(),
+                            position: player_transform.position,
+                        });
 
-                            tracing::debug!(
-                                "Enemy attack: {} enemy dealt {} damage to player {}",
-                                enemy.enemy_type, enemy.damage, player.id
-                            );
-                        }
+                        tracing::debug!(
+                            "Enemy attack: {} enemy dealt {} damage to player {}",
+                            enemy.enemy_type, enemy.damage, player.id
+                        );
                     }
                 }
             }
@@ -1206,7 +2526,7 @@ impl GameWorld {
         {
             let mut velocity_changes = Vec::new();
 
-            let mut player_query = self.world.query::<(Entity, &TransformQ, &RigidBodyHandle)>();
+            let mut player_query = self.world.query_filtered::<(Entity, &TransformQ, &RigidBodyHandle), Without<Disconnected>>();
             let mut obstacle_query = self.world.query::<(Entity, &TransformQ, &Obstacle, &RigidBodyHandle)>();
 
             for (player_entity, player_transform, _player_rigid_body) in player_query.iter(&self.world) {
@@ -1233,53 +2553,13 @@ impl GameWorld {
             }
         }
 
-        // 5. Enemy AI - đơn giản di chuyển về phía player gần nhất - collect changes first
-        {
-            let mut velocity_changes = Vec::new();
-
-            let mut enemy_query = self.world.query::<(Entity, &TransformQ, &Enemy, &RigidBodyHandle)>();
-            let mut player_query = self.world.query::<(Entity, &TransformQ, &Player, &RigidBodyHandle)>();
-
-            // Tạo list các players để tìm target
-            let players: Vec<_> = player_query.iter(&self.world)
-                .map(|(_, transform, player, _)| (player.id.clone(), transform.position))
-                .collect();
-
-            for (enemy_entity, enemy_transform, enemy, _enemy_rigid_body) in enemy_query.iter(&self.world) {
-                // Tìm player gần nhất
-                let mut nearest_player: Option<(String, [f32; 3])> = None;
-                let mut nearest_distance = f32::INFINITY;
-
-                for (player_id, player_pos) in &players {
-                    let enemy_pos = vector![enemy_transform.position[0], enemy_transform.position[1], enemy_transform.position[2]];
-                    let player_pos_vec = vector![player_pos[0], player_pos[1], player_pos[2]];
-                    let distance = (enemy_pos - player_pos_vec).magnitude();
-
-                    if distance < nearest_distance {
-                        nearest_distance = distance;
-                        nearest_player = Some((player_id.clone(), *player_pos));
-                    }
-                }
-
-                // Tính toán velocity mới nếu tìm thấy player gần
-                if let Some((_player_id, player_pos)) = nearest_player {
-                    if nearest_distance > 2.0 {
-                        let enemy_pos = vector![enemy_transform.position[0], enemy_transform.position[1], enemy_transform.position[2]];
-                        let player_pos_vec = vector![player_pos[0], player_pos[1], player_pos[2]];
-                        let direction = (player_pos_vec - enemy_pos).normalize();
-
-                        velocity_changes.push((enemy_entity, direction.x * enemy.speed, direction.z * enemy.speed));
-                    }
-                }
-            }
+        // 5. Enemy AI: patrol/chase/attack, leash và né obstacle (thưa hơn khi quá tải)
+        if self.current_tick.is_multiple_of(self.overload.level().enemy_ai_interval()) {
+            self.steer_enemies();
+        }
 
-            // Apply velocity changes after queries
-            for (enemy_entity, vel_x, vel_z) in velocity_changes {
-                if let Some(mut velocity) = self.world.get_mut::<VelocityQ>(enemy_entity) {
-                    velocity.velocity[0] = vel_x;
-                    velocity.velocity[2] = vel_z;
-                }
-            }
+        for event in events {
+            self.emit(event);
         }
 
         // Second pass: apply changes
@@ -1322,8 +2602,7 @@ impl GameWorld {
 
         // Despawn collected entities
         for entity in entities_to_despawn {
-            self.spatial_grid.remove_entity(entity);
-            self.world.despawn(entity);
+            self.despawn_entity(entity);
         }
 
         // Spawn new pickups
@@ -1332,6 +2611,62 @@ impl GameWorld {
         }
     }
 
+    /// Tính vận tốc cho mọi enemy bằng `steering::steer` rồi tích phân x/z (y do Rapier lo).
+    /// Chạy cho mọi enemy mỗi tick nên chỉ dùng buffer trong `enemy_steering`. Khi quá tải chỉ chạy mỗi
+    /// `enemy_ai_interval` tick nên tích phân bù cả khoảng đó.
+    fn steer_enemies(&mut self) {
+        let buffers = &mut self.enemy_steering;
+        buffers.players.clear();
+        buffers.results.clear();
+
+        let mut player_query = self.world.query_filtered::<&TransformQ, With<Player>>();
+        buffers.players.extend(player_query.iter(&self.world).map(|transform| transform.position));
+
+        let mut enemy_query = self.world.query::<(Entity, &TransformQ, &Enemy)>();
+        for (entity, transform, enemy) in enemy_query.iter(&self.world) {
+            buffers.nearby.clear();
+            self.spatial_grid
+                .entities_within(transform.position, steering::OBSTACLE_SEARCH_RADIUS, &mut buffers.nearby);
+            buffers.obstacles.clear();
+            for &nearby in &buffers.nearby {
+                if let (Some(obstacle), Some(obstacle_transform)) =
+                    (self.world.get::<Obstacle>(nearby), self.world.get::<TransformQ>(nearby))
+                {
+                    buffers.obstacles.push(ObstacleFootprint::new(obstacle_transform.position, &obstacle.obstacle_type));
+                }
+            }
+
+            let mut state = enemy.steering;
+            let velocity = steering::steer(
+                transform.position,
+                enemy.speed,
+                &SteeringProfile::for_enemy_type(&enemy.enemy_type),
+                &mut state,
+                &buffers.players,
+                &buffers.obstacles,
+            );
+            buffers.results.push((entity, velocity, state));
+        }
+
+        let dt = self.tick_rate.as_secs_f32() * self.overload.level().enemy_ai_interval() as f32;
+        for &(entity, [vel_x, vel_z], state) in &self.enemy_steering.results {
+            let Some(mut entity) = self.world.get_entity_mut(entity) else {
+                continue;
+            };
+            if let Some(mut enemy) = entity.get_mut::<Enemy>() {
+                enemy.steering = state;
+            }
+            if let Some(mut velocity) = entity.get_mut::<VelocityQ>() {
+                velocity.velocity[0] = vel_x;
+                velocity.velocity[2] = vel_z;
+            }
+            if let Some(mut transform) = entity.get_mut::<TransformQ>() {
+                transform.position[0] += vel_x * dt;
+                transform.position[2] += vel_z * dt;
+            }
+        }
+    }
+
     fn cleanup(&mut self) {
         // Cleanup entities với lifetime hết
         let mut to_despawn = Vec::new();
@@ -1343,8 +2678,7 @@ impl GameWorld {
         }
 
         for entity in to_despawn {
-            self.spatial_grid.remove_entity(entity);
-            self.world.despawn(entity);
+            self.despawn_entity(entity);
         }
 
         // Update lifetime cho các entities còn sống
@@ -1365,12 +2699,33 @@ impl GameWorld {
         }
     }
 
+    /// Self-heal: dựng lại grid từ TransformQ của mọi entity trong world
+    fn rebuild_spatial_grid(&mut self) {
+        let mut query = self.world.query::<(Entity, &TransformQ)>();
+        let positions: Vec<_> = query
+            .iter(&self.world)
+            .map(|(entity, transform)| (entity, transform.position))
+            .collect();
+        if cfg!(debug_assertions) {
+            let invalid = self.spatial_grid.validate();
+            if !invalid.is_empty() || positions.len() != self.spatial_grid.entity_positions.len() {
+                tracing::warn!(
+                    misplaced = invalid.len(),
+                    tracked = self.spatial_grid.entity_positions.len(),
+                    entities = positions.len(),
+                    "spatial grid out of sync, rebuilding"
+                );
+            }
+        }
+        self.spatial_grid.rebuild_from(positions);
+    }
+
     /// Update AOI for specific player
     fn update_player_aoi_grid(&mut self, player_id: &str) {
         // Update AOI for specific player
         if let Some(player_aoi) = self.player_aois.get_mut(player_id) {
             let current_tick = self.world.resource::<TickCount>().0;
-            if current_tick - player_aoi.last_update_tick >= 10 { // Update every 10 ticks
+            if current_tick - player_aoi.last_update_tick >= self.overload.level().aoi_update_interval() {
                 if let Some(player_entity) = self.world.resource::<PlayerEntityMap>().map.get(player_id) {
                     if let Some(transform) = self.world.get::<TransformQ>(*player_entity) {
                         player_aoi.visible_cells = self.spatial_grid.get_player_aoi_cells(transform.position);
@@ -1402,12 +2757,36 @@ impl GameWorld {
             });
         }
 
+        // Tâm bounding box x/z của mọi entity, để world trải dài (endless runner) vẫn nằm trong range i16
+        let (min, max) = entities.iter().fold(
+            ([f32::MAX; 3], [f32::MIN; 3]),
+            |(mut min, mut max), entity| {
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(entity.transform.position[axis]);
+                    max[axis] = max[axis].max(entity.transform.position[axis]);
+                }
+                (min, max)
+            },
+        );
+        let origin = if entities.is_empty() {
+            [0.0; 3]
+        } else {
+            snap_origin(std::array::from_fn(|axis| (min[axis] + max[axis]) / 2.0), self.spatial_grid.cell_size)
+        };
+
         let spectators = self.get_spectator_snapshots();
         GameSnapshot {
             tick: self.current_tick,
+            origin,
             entities,
-            chat_messages: self.get_recent_chat_messages(20),
+            // Snapshot toàn world không thuộc player nào: chỉ lấy tin chung, không lộ tin của từng room
+            chat_messages: self.chat_messages_for("", 20),
             spectators,
+            events: self.events_since(self.delta_encoder.events_since_tick, None),
+            seed: Some(self.seed),
+            server_time_ms: unix_millis(),
+            tick_duration_ms: self.tick_duration_ms(),
+            last_acked_sequence: None,
         }
     }
 
@@ -1422,6 +2801,97 @@ impl GameWorld {
         None
     }
 
+    /// Score hiện tại của từng player (kể cả bot) theo player id
+    pub fn player_scores(&self) -> HashMap<String, u32> {
+        self.world
+            .resource::<PlayerEntityMap>()
+            .map
+            .iter()
+            .filter_map(|(player_id, &entity)| {
+                self.world.get::<Player>(entity).map(|player| (player_id.clone(), player.score))
+            })
+            .collect()
+    }
+
+    /// Player (kể cả bot) có entity trong world và đang thuộc `room_id`, theo player id
+    pub fn live_players_in_room(&mut self, room_id: &str) -> HashMap<String, LivePlayerState> {
+        let player_rooms = &self.player_rooms;
+        self.world
+            .query::<(&Player, Option<&Disconnected>)>()
+            .iter(&self.world)
+            .filter(|(player, _)| player_rooms.get(&player.id).is_some_and(|room| room == room_id))
+            .map(|(player, disconnected)| {
+                let state = LivePlayerState {
+                    score: player.score,
+                    rtt_ms: player.rtt_ms,
+                    is_bot: player.is_bot,
+                    connected: disconnected.is_none(),
+                };
+                (player.id.clone(), state)
+            })
+            .collect()
+    }
+
+    /// Score và quãng đường đã chạy (trục z) của từng player thật, bot không được checkpoint
+    pub fn player_progress(&mut self) -> HashMap<String, PlayerProgress> {
+        self.world
+            .query::<(&Player, &TransformQ)>()
+            .iter(&self.world)
+            .filter(|(player, _)| !player.is_bot)
+            .map(|(player, transform)| {
+                (player.id.clone(), PlayerProgress { score: player.score, distance: transform.position[2].max(0.0) })
+            })
+            .collect()
+    }
+
+    /// Khôi phục score từ checkpoint cho player vừa spawn; false nếu player chưa có entity
+    pub fn restore_player_score(&mut self, player_id: &str, score: u32) -> bool {
+        let Some(&entity) = self.world.resource::<PlayerEntityMap>().map.get(player_id) else {
+            return false;
+        };
+        match self.world.get_mut::<Player>(entity) {
+            Some(mut player) => {
+                player.score = score;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Đặt tên hiển thị cho player; false nếu player chưa có entity
+    pub fn set_player_name(&mut self, player_id: &str, name: &str) -> bool {
+        let Some(&entity) = self.world.resource::<PlayerEntityMap>().map.get(player_id) else {
+            return false;
+        };
+        match self.world.get_mut::<Player>(entity) {
+            Some(mut player) => {
+                player.name = name.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Tên hiển thị trong Player component, None nếu không có entity hoặc chưa đặt tên
+    pub fn player_name(&self, player_id: &str) -> Option<String> {
+        let entity = *self.world.resource::<PlayerEntityMap>().map.get(player_id)?;
+        self.world.get::<Player>(entity).map(|player| player.name.clone()).filter(|name| !name.is_empty())
+    }
+
+    /// Ghi RTT gateway đo được vào Player component; false nếu id không phải player (spectator, chưa spawn, bot)
+    pub fn set_player_rtt(&mut self, player_id: &str, rtt_ms: u32) -> bool {
+        let Some(&entity) = self.world.resource::<PlayerEntityMap>().map.get(player_id) else {
+            return false;
+        };
+        match self.world.get_mut::<Player>(entity) {
+            Some(mut player) if !player.is_bot => {
+                player.rtt_ms = rtt_ms.min(MAX_PLAYER_RTT_MS);
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Lấy view distance của player từ player_id
     pub fn get_player_view_distance(&mut self, player_id: &str) -> Option<f32> {
         let mut query = self.world.query::<&Player>();
@@ -1434,11 +2904,35 @@ impl GameWorld {
     }
 
     pub fn add_player(&mut self, player_id: String) -> Entity {
+        self.add_player_with_team(player_id, None)
+    }
+
+    /// Dùng spawn points của room settings JSON (`{"spawn_points": [[x,y,z], ...]}`), room không có thì về
+    /// spawn points mặc định. World dùng chung giữa các room nên gọi trước mỗi lần spawn cho room;
+    /// spawn points không đổi thì giữ nguyên manager để nửa map của từng team không bị xếp lại
+    pub fn apply_spawn_settings(&mut self, settings: &serde_json::Value) {
+        let spawn_manager = SpawnManager::from_settings_json(settings).unwrap_or_default();
+        if spawn_manager.spawn_points() != self.spawn_manager.spawn_points() {
+            self.spawn_manager = spawn_manager;
+        }
+    }
+
+    /// Chọn vị trí spawn cho player (dùng cho cả join lẫn respawn)
+    pub fn next_spawn_position(&mut self, team: Option<&str>) -> [f32; 3] {
+        let players: std::collections::HashSet<Entity> =
+            self.world.resource::<PlayerEntityMap>().map.values().copied().collect();
+        self.spawn_manager.next_spawn_point(&self.spatial_grid, &players, team)
+    }
+
+    /// Add player, giới hạn spawn trong nửa map của team nếu có
+    pub fn add_player_with_team(&mut self, player_id: String, team: Option<&str>) -> Entity {
+        let spawn = self.next_spawn_position(team);
+
         // Add to physics world first
         let rigid_body = RigidBodyBuilder::dynamic()
-            .translation(vector![0.0, 5.0, 0.0])
+            .translation(vector![spawn[0], spawn[1], spawn[2]])
             .build();
-        let collider = ColliderBuilder::ball(0.5).build();
+        let collider = ColliderBuilder::ball(PLAYER_RADIUS).build();
 
         let body_handle = self.bodies.insert(rigid_body);
         self.colliders.insert_with_parent(collider, body_handle, &mut self.bodies);
@@ -1446,7 +2940,7 @@ impl GameWorld {
         // Create entity with components
         let entity = self.world.spawn((
             TransformQ {
-                position: [0.0, 5.0, 0.0],
+                position: spawn,
                 rotation: [0.0, 0.0, 0.0, 1.0],
             },
             VelocityQ {
@@ -1457,7 +2951,10 @@ impl GameWorld {
                 id: player_id.clone(),
                 score: 0,
                 view_distance: 50.0, // Default AOI radius
-                last_position: [0.0, 5.0, 0.0], // Initial position
+                last_position: spawn, // Initial position
+                is_bot: false,
+                rtt_ms: 0,
+                name: String::new(),
             },
             RigidBodyHandle {
                 handle: body_handle,
@@ -1472,12 +2969,148 @@ impl GameWorld {
         }
 
         // Add to spatial grid
-        self.spatial_grid.add_entity(entity_id, [0.0, 5.0, 0.0]);
+        self.spatial_grid.add_entity(entity_id, spawn);
 
         entity_id
     }
 
+    /// Thêm bot player vào world, trả về player_id của bot
+    pub fn add_bot(&mut self, room_id: &str, difficulty: BotDifficulty) -> String {
+        self.add_bot_with_team(room_id, difficulty, None)
+    }
+
+    /// Thêm bot, spawn trong nửa map của team nếu có
+    pub fn add_bot_with_team(&mut self, room_id: &str, difficulty: BotDifficulty, team: Option<&str>) -> String {
+        let bot_id = self.bots.register(room_id, difficulty, self.current_tick);
+        self.set_player_room(&bot_id, room_id);
+        let entity = self.add_player_with_team(bot_id.clone(), team);
+        if let Some(mut player) = self.world.get_mut::<Player>(entity) {
+            player.is_bot = true;
+        }
+        bot_id
+    }
+
+    /// Xoá player (hoặc bot) khỏi ECS, physics, spatial grid và các buffer theo player
+    pub fn remove_player(&mut self, player_id: &str) -> bool {
+        self.bots.remove(player_id);
+        self.input_buffers.remove(player_id);
+        self.player_encoders.remove(player_id);
+        self.player_aois.remove(player_id);
+        self.player_rooms.remove(player_id);
+
+        let Some(entity) = self.world.resource_mut::<PlayerEntityMap>().map.remove(player_id) else {
+            return false;
+        };
+        self.despawn_entity(entity)
+    }
+
+    /// Despawn entity cùng rigid body + collider của nó và vị trí trong spatial grid.
+    /// Mọi chỗ despawn phải đi qua đây, `world.despawn` trực tiếp để lại body Rapier vẫn được mô phỏng.
+    /// Gọi lại với entity đã despawn thì không làm gì và trả về false.
+    pub fn despawn_entity(&mut self, entity: Entity) -> bool {
+        if let Some(body_handle) = self.world.get::<RigidBodyHandle>(entity).map(|h| h.handle) {
+            // Body lấy từ pool được tắt và trả về pool, pool đầy thì xoá như body thường
+            let pooled = self
+                .world
+                .get::<BodyShape>(entity)
+                .is_some_and(|&shape| self.body_pool.release(shape, body_handle, &mut self.bodies));
+            if !pooled {
+                self.bodies.remove(
+                    body_handle,
+                    &mut self.island_manager,
+                    &mut self.colliders,
+                    &mut self.impulse_joints,
+                    &mut self.multibody_joints,
+                    true,
+                );
+            }
+        }
+        self.spatial_grid.remove_entity(entity);
+        self.world.despawn(entity)
+    }
+
+    /// Join qua JoinRoom: player còn entity (đang kết nối hoặc chưa hết grace) thì nối lại entity đó,
+    /// entity đã quá grace thì despawn và spawn player mới
+    pub fn join_player(&mut self, player_id: String) -> PlayerJoin {
+        self.join_player_with_team(player_id, None)
+    }
+
+    /// Như `join_player`, player mới được spawn trong nửa map của team
+    pub fn join_player_with_team(&mut self, player_id: String, team: Option<&str>) -> PlayerJoin {
+        if let Some(&entity) = self.world.resource::<PlayerEntityMap>().map.get(&player_id) {
+            let expired = self
+                .world
+                .get::<Disconnected>(entity)
+                .is_some_and(|disconnected| disconnected.expires_at <= Instant::now());
+            if !expired {
+                self.world.entity_mut(entity).remove::<Disconnected>();
+                return PlayerJoin { entity, resumed: true };
+            }
+            self.remove_player(&player_id);
+        }
+        PlayerJoin {
+            entity: self.add_player_with_team(player_id, team),
+            resumed: false,
+        }
+    }
+
+    /// Đóng băng entity của player vừa mất kết nối và giữ nó trong `grace` chờ rejoin
+    pub fn disconnect_player(&mut self, player_id: &str, grace: Duration) -> bool {
+        let Some(&entity) = self.world.resource::<PlayerEntityMap>().map.get(player_id) else {
+            return false;
+        };
+        self.input_buffers.remove(player_id);
+
+        let now = Instant::now();
+        let mut entity = self.world.entity_mut(entity);
+        if let Some(mut velocity) = entity.get_mut::<VelocityQ>() {
+            velocity.velocity[0] = 0.0;
+            velocity.velocity[2] = 0.0;
+            velocity.angular_velocity = [0.0, 0.0, 0.0];
+        }
+        entity.insert(Disconnected {
+            since: now,
+            expires_at: now + grace,
+        });
+        true
+    }
+
+    /// Despawn player mất kết nối mà không rejoin kịp
+    fn reap_disconnected(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<String> = self
+            .world
+            .query::<(&Player, &Disconnected)>()
+            .iter(&self.world)
+            .filter(|(_, disconnected)| disconnected.expires_at <= now)
+            .map(|(player, _)| player.id.clone())
+            .collect();
+        for player_id in expired {
+            tracing::info!(%player_id, "rejoin grace expired, removing player");
+            self.remove_player(&player_id);
+        }
+    }
+
     /// Add a spectator to the game world
+    /// Số entity player/spectator trong world ứng với các id này (id không có entity thì bỏ qua)
+    pub fn entity_count_for<'a>(&mut self, ids: impl IntoIterator<Item = &'a str>) -> usize {
+        let ids: HashSet<&str> = ids.into_iter().collect();
+        let players = self
+            .world
+            .resource::<PlayerEntityMap>()
+            .map
+            .keys()
+            .filter(|id| ids.contains(id.as_str()))
+            .count();
+        let spectators = self
+            .world
+            .query::<&Spectator>()
+            .iter(&self.world)
+            .filter(|spectator| ids.contains(spectator.id.as_str()))
+            .count();
+        players + spectators
+    }
+
     pub fn add_spectator(&mut self, spectator_id: String, camera_mode: SpectatorCameraMode) -> Entity {
         // Create spectator entity without physics body (spectators don't interact with physics)
         let entity = self.world.spawn((
@@ -1504,13 +3137,8 @@ impl GameWorld {
 
     pub fn add_pickup(&mut self, position: [f32; 3], value: u32) -> Entity {
         // Add to physics first
-        let rigid_body = RigidBodyBuilder::fixed()
-            .translation(vector![position[0], position[1], position[2]])
-            .build();
-        let collider = ColliderBuilder::ball(0.3).build();
-
-        let body_handle = self.bodies.insert(rigid_body);
-        self.colliders.insert_with_parent(collider, body_handle, &mut self.bodies);
+        let shape = BodyShape::fixed_ball(0.3);
+        let body_handle = self.body_pool.acquire(shape, position, &mut self.bodies, &mut self.colliders);
 
         // Create entity with components
         let entity = self.world.spawn((
@@ -1525,6 +3153,7 @@ impl GameWorld {
             RigidBodyHandle {
                 handle: body_handle,
             },
+            shape,
         ));
 
         let entity_id = entity.id();
@@ -1536,21 +3165,17 @@ impl GameWorld {
     }
 
     pub fn add_obstacle(&mut self, position: [f32; 3], obstacle_type: String) -> Entity {
-        // Add to physics first
-        let rigid_body = RigidBodyBuilder::fixed()
-            .translation(vector![position[0], position[1], position[2]])
-            .build();
-
         // Collider size phụ thuộc loại obstacle
-        let collider = match obstacle_type.as_str() {
-            "wall" => ColliderBuilder::cuboid(2.0, 1.0, 0.5).build(),
-            "spike" => ColliderBuilder::ball(0.5).build(),
-            "moving_platform" => ColliderBuilder::cuboid(3.0, 0.3, 2.0).build(),
-            _ => ColliderBuilder::cuboid(1.0, 1.0, 1.0).build(),
+        let shape = match obstacle_type.as_str() {
+            "wall" => BodyShape::fixed_cuboid(2.0, 1.0, 0.5),
+            "spike" => BodyShape::fixed_ball(0.5),
+            "moving_platform" => {
+                let [hx, hy, hz] = MOVING_PLATFORM_HALF_EXTENTS;
+                BodyShape::fixed_cuboid(hx, hy, hz)
+            }
+            _ => BodyShape::fixed_cuboid(1.0, 1.0, 1.0),
         };
-
-        let body_handle = self.bodies.insert(rigid_body);
-        self.colliders.insert_with_parent(collider, body_handle, &mut self.bodies);
+        let body_handle = self.body_pool.acquire(shape, position, &mut self.bodies, &mut self.colliders);
 
         // Create entity with components
         let entity = self.world.spawn((
@@ -1564,9 +3189,15 @@ impl GameWorld {
             RigidBodyHandle {
                 handle: body_handle,
             },
+            shape,
         ));
 
         let entity_id = entity.id();
+        if obstacle_type == "moving_platform" {
+            // Pha lấy theo z để các hàng platform không nhấp nhô đồng loạt
+            let platform = MovingPlatform::new(position, [0.0, 1.0, 0.0], MOVING_PLATFORM_RANGE, MOVING_PLATFORM_SPEED, position[2]);
+            self.set_moving_platform(entity_id, platform);
+        }
 
         // Add obstacle to spatial grid
         self.spatial_grid.add_entity(entity_id, position);
@@ -1574,15 +3205,30 @@ impl GameWorld {
         entity_id
     }
 
+    /// Gắn (hoặc thay) chuyển động cho obstacle; platform nhảy ngay tới vị trí ở t = 0
+    pub fn set_moving_platform(&mut self, entity: Entity, platform: MovingPlatform) {
+        let position = platform.position();
+        let Some(mut entity_mut) = self.world.get_entity_mut(entity) else {
+            return;
+        };
+        entity_mut.insert(platform);
+        if let Some(mut transform) = entity_mut.get_mut::<TransformQ>() {
+            transform.position = position;
+        }
+        if let Some(body_handle) = entity_mut.get::<RigidBodyHandle>().map(|h| h.handle) {
+            if let Some(body) = self.bodies.get_mut(body_handle) {
+                body.set_translation(vector![position[0], position[1], position[2]], true);
+            }
+        }
+        if self.spatial_grid.entity_positions.contains_key(&entity) {
+            self.spatial_grid.update_entity_position(entity, position);
+        }
+    }
+
     pub fn add_power_up(&mut self, position: [f32; 3], power_type: String, duration_secs: u64, value: u32) -> Entity {
         // Add to physics first
-        let rigid_body = RigidBodyBuilder::fixed()
-            .translation(vector![position[0], position[1], position[2]])
-            .build();
-        let collider = ColliderBuilder::ball(0.4).build();
-
-        let body_handle = self.bodies.insert(rigid_body);
-        self.colliders.insert_with_parent(collider, body_handle, &mut self.bodies);
+        let shape = BodyShape::fixed_ball(0.4);
+        let body_handle = self.body_pool.acquire(shape, position, &mut self.bodies, &mut self.colliders);
 
         // Create entity with components
         let entity = self.world.spawn((
@@ -1601,6 +3247,7 @@ impl GameWorld {
             RigidBodyHandle {
                 handle: body_handle,
             },
+            shape,
         ));
 
         let entity_id = entity.id();
@@ -1613,19 +3260,13 @@ impl GameWorld {
 
     pub fn add_enemy(&mut self, position: [f32; 3], enemy_type: String) -> Entity {
         // Add to physics first
-        let rigid_body = RigidBodyBuilder::dynamic()
-            .translation(vector![position[0], position[1], position[2]])
-            .build();
-
-        let collider = match enemy_type.as_str() {
-            "basic" => ColliderBuilder::ball(0.6).build(),
-            "fast" => ColliderBuilder::ball(0.4).build(),
-            "tank" => ColliderBuilder::ball(0.8).build(),
-            _ => ColliderBuilder::ball(0.6).build(),
+        let shape = match enemy_type.as_str() {
+            "basic" => BodyShape::dynamic_ball(0.6),
+            "fast" => BodyShape::dynamic_ball(0.4),
+            "tank" => BodyShape::dynamic_ball(0.8),
+            _ => BodyShape::dynamic_ball(0.6),
         };
-
-        let body_handle = self.bodies.insert(rigid_body);
-        self.colliders.insert_with_parent(collider, body_handle, &mut self.bodies);
+        let body_handle = self.body_pool.acquire(shape, position, &mut self.bodies, &mut self.colliders);
 
         // Enemy stats phụ thuộc loại
         let (damage, speed, attack_cooldown) = match enemy_type.as_str() {
@@ -1651,10 +3292,12 @@ impl GameWorld {
                 speed,
                 last_attack: Instant::now(),
                 attack_cooldown,
+                steering: SteeringState::new(position, self.rng.gen()),
             },
             RigidBodyHandle {
                 handle: body_handle,
             },
+            shape,
         ));
 
         let entity_id = entity.id();
@@ -1668,21 +3311,23 @@ impl GameWorld {
     /// Endless Runner specific gameplay logic
     pub fn update_endless_runner(&mut self, delta_time: Duration) {
         // Auto-run forward movement for all players
-        let mut player_query = self.world.query::<(&mut TransformQ, &mut Player)>();
+        let mut player_query = self.world.query_filtered::<(&mut TransformQ, &mut Player), Without<Disconnected>>();
         for (mut transform, mut player) in player_query.iter_mut(&mut self.world) {
-            let run_speed = 12.0; // Base running speed for endless runner
-            transform.position[2] += run_speed * delta_time.as_secs_f32();
+            transform.position[2] += RUNNER_SPEED * delta_time.as_secs_f32();
 
-            // Update player score based on distance traveled
+            // Điểm tính theo mốc quãng đường đã vượt qua, không cắt phần lẻ mỗi tick nên không phụ thuộc tick rate
             let distance_traveled = transform.position[2] - player.last_position[2];
             if distance_traveled > 0.0 {
-                player.score += (distance_traveled * 10.0) as u32; // Score per unit distance
+                let earned = (transform.position[2] * RUNNER_POINTS_PER_UNIT).floor()
+                    - (player.last_position[2] * RUNNER_POINTS_PER_UNIT).floor();
+                player.score += earned.max(0.0) as u32;
                 player.last_position = transform.position;
             }
         }
 
         // Procedural obstacle generation for endless runner
         self.generate_endless_runner_obstacles();
+        self.cull_passed_obstacles();
 
         // Lane-based movement constraints (keep players in their lanes)
         self.update_lane_positions();
@@ -1690,40 +3335,27 @@ impl GameWorld {
 
     /// Generate obstacles ahead of players for endless runner
     fn generate_endless_runner_obstacles(&mut self) {
-        let mut player_positions = Vec::new();
-        let mut player_query = self.world.query::<(&TransformQ, &Player)>();
-        for (transform, _) in player_query.iter(&self.world) {
-            player_positions.push(transform.position[2]);
-        }
-
-        for player_z in player_positions {
-            // Generate obstacles 60-100 units ahead (farther for endless runner)
-            if player_z % 25.0 < 0.1 { // Every 25 units for more spaced obstacles
-                let obstacle_z = player_z + 60.0 + (rand::random::<f32>() * 40.0);
-                let lane = rand::random::<usize>() % 3;
-                let lanes = [-3.0, 0.0, 3.0]; // Wider lanes for 3D
-
-                // Random obstacle type for variety
-                let obstacle_types = ["wall", "spike", "moving_platform"];
-                let obstacle_type = obstacle_types[rand::random::<usize>() % obstacle_types.len()];
-
-                self.add_obstacle(
-                    [lanes[lane], 0.5, obstacle_z],
-                    obstacle_type.to_string()
-                );
-            }
-
-            // Occasionally spawn power-ups
-            if player_z % 50.0 < 0.1 && rand::random::<f32>() < 0.3 { // 30% chance every 50 units
-                let powerup_z = player_z + 70.0 + (rand::random::<f32>() * 30.0);
-                let lane = rand::random::<usize>() % 3;
-                let lanes = [-3.0, 0.0, 3.0];
-
-                let power_types = ["speed_boost", "jump_boost", "invincibility"];
-                let power_type = power_types[rand::random::<usize>() % power_types.len()];
+        let mut player_query = self.world.query_filtered::<&TransformQ, (With<Player>, Without<Disconnected>)>();
+        let Some(lead_z) = player_query
+            .iter(&self.world)
+            .map(|transform| transform.position[2])
+            .max_by(|a, b| a.total_cmp(b))
+        else {
+            return;
+        };
 
+        // Quá tải thì vẫn đẩy con trỏ track (hết tải không sinh dồn các hàng đã bị bỏ qua) nhưng không spawn
+        let spawns = self.overload.level().spawns_obstacles();
+        for row in self.runner_track.advance(lead_z) {
+            if !spawns {
+                continue;
+            }
+            for &(lane, obstacle_type) in &row.obstacles {
+                self.add_obstacle([RUNNER_LANES[lane], 0.5, row.z], obstacle_type.to_string());
+            }
+            if let Some((lane, power_type)) = row.power_up {
                 self.add_power_up(
-                    [lanes[lane], 2.0, powerup_z],
+                    [RUNNER_LANES[lane], 2.0, row.z],
                     power_type.to_string(),
                     10, // 10 seconds duration
                     100 // 100 points value
@@ -1732,12 +3364,36 @@ impl GameWorld {
         }
     }
 
+    /// Dọn obstacle đã bị mọi player đang chơi bỏ xa phía sau. Obstacle không có Lifetime (wall...)
+    /// không thì tồn tại mãi cùng body Rapier của nó
+    fn cull_passed_obstacles(&mut self) {
+        let mut player_query = self.world.query_filtered::<&TransformQ, (With<Player>, Without<Disconnected>)>();
+        let Some(rearmost_z) = player_query
+            .iter(&self.world)
+            .map(|transform| transform.position[2])
+            .min_by(|a, b| a.total_cmp(b))
+        else {
+            return;
+        };
+
+        let cull_before = rearmost_z - RUNNER_CULL_DISTANCE;
+        let mut obstacle_query = self.world.query_filtered::<(Entity, &TransformQ), With<Obstacle>>();
+        let passed: Vec<Entity> = obstacle_query
+            .iter(&self.world)
+            .filter(|(_, transform)| transform.position[2] < cull_before)
+            .map(|(entity, _)| entity)
+            .collect();
+        for entity in passed {
+            self.despawn_entity(entity);
+        }
+    }
+
     /// Keep players in their lanes (snap to lane positions)
     fn update_lane_positions(&mut self) {
         let mut query = self.world.query::<(&mut TransformQ, &mut Player)>();
         for (mut transform, _) in query.iter_mut(&mut self.world) {
             // Snap to lane positions (x-axis) for endless runner
-            let lanes = [-3.0, 0.0, 3.0];
+            let lanes = RUNNER_LANES;
             let closest_lane = lanes.iter()
                 .min_by(|a, b| (transform.position[0] - **a).abs().partial_cmp(&(transform.position[0] - **b).abs()).unwrap())
                 .unwrap();
@@ -1748,13 +3404,8 @@ impl GameWorld {
     /// Add endless runner specific pickup (coins/gems)
     pub fn add_endless_runner_pickup(&mut self, position: [f32; 3], value: u32) -> Entity {
         // Add to physics first
-        let rigid_body = RigidBodyBuilder::fixed()
-            .translation(vector![position[0], position[1], position[2]])
-            .build();
-        let collider = ColliderBuilder::ball(0.4).build();
-
-        let body_handle = self.bodies.insert(rigid_body);
-        self.colliders.insert_with_parent(collider, body_handle, &mut self.bodies);
+        let shape = BodyShape::fixed_ball(0.4);
+        let body_handle = self.body_pool.acquire(shape, position, &mut self.bodies, &mut self.colliders);
 
         // Create entity with components
         let entity = self.world.spawn((
@@ -1769,6 +3420,7 @@ impl GameWorld {
             RigidBodyHandle {
                 handle: body_handle,
             },
+            shape,
         ));
 
         let entity_id = entity.id();
@@ -1794,23 +3446,23 @@ pub struct PlayerEntityMap {
     pub map: std::collections::HashMap<String, Entity>,
 }
 
-/// Spawn một số entities để test với gameplay thực tế hơn
+/// Spawn một số entities để test với gameplay thực tế hơn; vị trí random rút từ `world.rng`
 pub fn spawn_test_entities(world: &mut GameWorld) {
     // Spawn player ở vị trí trung tâm
     world.add_player("player_1".to_string());
 
     // Spawn nhiều pickups ở vị trí random với giá trị khác nhau
     for _ in 0..10 {
-        let x = (rand::random::<f32>() - 0.5) * 25.0;
-        let z = (rand::random::<f32>() - 0.5) * 25.0;
-        let value = (rand::random::<f32>() * 15.0 + 5.0) as u32; // Giá trị từ 5-20
+        let x = (world.rng.gen::<f32>() - 0.5) * 25.0;
+        let z = (world.rng.gen::<f32>() - 0.5) * 25.0;
+        let value = (world.rng.gen::<f32>() * 15.0 + 5.0) as u32; // Giá trị từ 5-20
         world.add_pickup([x, 1.0, z], value);
     }
 
     // Spawn obstacles để làm gameplay thú vị hơn
     for i in 0..6 {
         let x = (i as f32 - 3.0) * 4.0;
-        let z = (rand::random::<f32>() - 0.5) * 20.0;
+        let z = (world.rng.gen::<f32>() - 0.5) * 20.0;
         world.add_obstacle([x, 0.5, z], "wall".to_string());
     }
 
@@ -1824,7 +3476,7 @@ pub fn spawn_test_entities(world: &mut GameWorld) {
     // Spawn enemies để test AI và combat
     for i in 0..4 {
         let x = (i as f32 - 2.0) * 6.0;
-        let z = (rand::random::<f32>() - 0.5) * 15.0 + 10.0; // Spawn xa hơn để tránh player ban đầu
+        let z = (world.rng.gen::<f32>() - 0.5) * 15.0 + 10.0; // Spawn xa hơn để tránh player ban đầu
         let enemy_type = match i % 3 {
             0 => "basic",
             1 => "fast",
@@ -1833,3 +3485,821 @@ pub fn spawn_test_entities(world: &mut GameWorld) {
         world.add_enemy([x, 1.0, z], enemy_type.to_string());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner_track::{MIN_ROW_GAP, SPAWN_AHEAD_DISTANCE};
+
+    fn now_ms() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+
+    fn push_actions(world: &mut GameWorld, player_id: &str, sequence: u32, actions: InputActions) {
+        world
+            .input_buffers
+            .entry(player_id.to_string())
+            .or_insert_with(InputBuffer::new)
+            .add_input(PlayerInput {
+                player_id: player_id.to_string(),
+                input_sequence: sequence,
+                movement: [0.0, 0.0, 0.0],
+                timestamp: now_ms(),
+                actions,
+            });
+    }
+
+    fn sequenced_input(sequence: u32) -> PlayerInput {
+        PlayerInput {
+            player_id: "p1".to_string(),
+            input_sequence: sequence,
+            movement: [0.0, 0.0, 0.0],
+            timestamp: 0,
+            actions: InputActions::default(),
+        }
+    }
+
+    fn pending_sequences(buffer: &InputBuffer) -> Vec<u32> {
+        buffer.get_pending_inputs().iter().map(|input| input.input_sequence).collect()
+    }
+
+    #[test]
+    fn out_of_order_input_waits_for_missing_sequence_then_processes_in_order() {
+        let mut buffer = InputBuffer::new();
+        buffer.add_input(sequenced_input(1));
+        buffer.mark_processed(1);
+        assert_eq!(buffer.add_input(sequenced_input(1)), InputAdded::AlreadyProcessed);
+
+        let now = Instant::now();
+        buffer.add_input(sequenced_input(3));
+        assert_eq!(buffer.skip_expired_gap(now), None);
+        assert!(pending_sequences(&buffer).is_empty());
+
+        buffer.add_input(sequenced_input(2));
+        assert_eq!(buffer.skip_expired_gap(now + INPUT_REORDER_WINDOW * 2), None);
+        assert_eq!(pending_sequences(&buffer), vec![2, 3]);
+    }
+
+    #[test]
+    fn lost_sequence_is_skipped_after_reorder_window() {
+        let mut buffer = InputBuffer::new();
+        buffer.add_input(sequenced_input(1));
+        buffer.mark_processed(1);
+
+        let now = Instant::now();
+        buffer.add_input(sequenced_input(3));
+        buffer.add_input(sequenced_input(4));
+        assert_eq!(buffer.skip_expired_gap(now), None);
+        assert_eq!(buffer.skip_expired_gap(now + INPUT_REORDER_WINDOW / 2), None);
+        assert_eq!(buffer.skip_expired_gap(now + INPUT_REORDER_WINDOW), Some(2..=2));
+        assert_eq!(pending_sequences(&buffer), vec![3, 4]);
+
+        // Sequence đã bỏ qua tới muộn thì không được xử lý nữa
+        assert_eq!(buffer.add_input(sequenced_input(2)), InputAdded::AlreadyProcessed);
+    }
+
+    #[test]
+    fn flooded_input_buffer_stays_capped_and_ignores_duplicates() {
+        let input = |sequence: u32| PlayerInput {
+            player_id: "flooder".to_string(),
+            input_sequence: sequence,
+            movement: [1.0, 0.0, 0.0],
+            timestamp: 0,
+            actions: InputActions::default(),
+        };
+        let dropped_before = crate::simulation_metrics().inputs_dropped_total.with_label_values(&["flooder"]).get();
+
+        let mut buffer = InputBuffer::new();
+        for sequence in 1..=10_000 {
+            buffer.add_input(input(sequence));
+            assert!(buffer.inputs.len() <= MAX_BUFFERED_INPUTS);
+        }
+        assert_eq!(buffer.inputs.len(), MAX_BUFFERED_INPUTS);
+        // Giữ lại các input mới nhất, vẫn theo thứ tự sequence
+        assert_eq!(buffer.inputs.first().unwrap().input_sequence, 10_000 - MAX_BUFFERED_INPUTS as u32 + 1);
+        assert_eq!(buffer.inputs.last().unwrap().input_sequence, 10_000);
+        assert_eq!(
+            crate::simulation_metrics().inputs_dropped_total.with_label_values(&["flooder"]).get() - dropped_before,
+            10_000 - MAX_BUFFERED_INPUTS as u64
+        );
+
+        assert_eq!(buffer.add_input(input(10_000)), InputAdded::Duplicate);
+        assert_eq!(buffer.inputs.len(), MAX_BUFFERED_INPUTS);
+    }
+
+    fn step(world: &mut GameWorld, ticks: u32) {
+        for _ in 0..ticks {
+            world.fixed_update();
+            world.current_tick += 1;
+        }
+    }
+
+    fn body_of(world: &GameWorld, player: Entity) -> rapier3d::dynamics::RigidBodyHandle {
+        world.world.get::<RigidBodyHandle>(player).unwrap().handle
+    }
+
+    fn player_y(world: &GameWorld, player: Entity) -> f32 {
+        world.world.get::<TransformQ>(player).unwrap().position[1]
+    }
+
+    fn jump() -> InputActions {
+        InputActions { jump: true, ..Default::default() }
+    }
+
+    #[test]
+    fn old_input_without_actions_still_parses() {
+        let input: PlayerInput = serde_json::from_str(
+            r#"{"player_id":"p1","input_sequence":1,"movement":[1.0,0.0,0.0],"timestamp":0}"#,
+        )
+        .unwrap();
+        assert_eq!(input.actions, InputActions::default());
+    }
+
+    #[test]
+    fn jump_while_airborne_is_ignored() {
+        let mut world = GameWorld::new();
+        let player = world.add_player("p1".to_string());
+        step(&mut world, 2); // Spawn ở y=5, đang rơi
+        assert!(!world.is_grounded(body_of(&world, player)));
+
+        push_actions(&mut world, "p1", 1, jump());
+        step(&mut world, 1);
+
+        let body = &world.bodies[body_of(&world, player)];
+        assert!(body.linvel().y < 0.0, "airborne jump should not add upward velocity");
+    }
+
+    #[test]
+    fn grounded_jump_raises_y() {
+        let mut world = GameWorld::new();
+        let player = world.add_player("p1".to_string());
+        step(&mut world, 120); // Rơi xuống đất
+        assert!(world.is_grounded(body_of(&world, player)));
+        let grounded_y = player_y(&world, player);
+
+        push_actions(&mut world, "p1", 1, jump());
+        step(&mut world, 10);
+        assert!(player_y(&world, player) > grounded_y + 0.5);
+    }
+
+    #[test]
+    fn slide_ends_after_its_duration() {
+        let mut world = GameWorld::new();
+        let player = world.add_player("p1".to_string());
+        let radius = |world: &GameWorld| {
+            let body = &world.bodies[body_of(world, player)];
+            world.colliders[body.colliders()[0]].shape().as_ball().unwrap().radius
+        };
+
+        push_actions(&mut world, "p1", 1, InputActions { slide: true, ..Default::default() });
+        step(&mut world, SLIDE_DURATION_TICKS - 1);
+        assert!(world.world.get::<Sliding>(player).is_some());
+        assert_eq!(radius(&world), SLIDE_RADIUS);
+
+        step(&mut world, 1);
+        assert!(world.world.get::<Sliding>(player).is_none());
+        assert_eq!(radius(&world), PLAYER_RADIUS);
+    }
+
+    #[test]
+    fn use_item_emits_snapshot_event() {
+        let mut world = GameWorld::new();
+        world.add_player("p1".to_string());
+
+        push_actions(&mut world, "p1", 1, InputActions { use_item: Some("shield".to_string()), ..Default::default() });
+        step(&mut world, 1);
+
+        let snapshot = world.create_snapshot();
+        assert_eq!(
+            snapshot.events,
+            vec![GameEvent::ItemUsed { player_id: "p1".to_string(), item: "shield".to_string() }]
+        );
+    }
+
+    #[test]
+    fn enemy_sidesteps_wall_between_it_and_player() {
+        let mut world = GameWorld::new();
+        let player = world.add_player("p1".to_string());
+        world.world.get_mut::<TransformQ>(player).unwrap().position = [0.0, 1.0, 8.0];
+        let enemy = world.add_enemy([0.0, 1.0, 0.0], "basic".to_string());
+        world.add_obstacle([0.0, 0.5, 3.0], "wall".to_string()); // x: -2..2, z: 2.5..3.5
+
+        for _ in 0..60 {
+            world.steer_enemies();
+            let position = world.world.get::<TransformQ>(enemy).unwrap().position;
+            let inside_wall = position[0].abs() < 2.0 && (2.5..3.5).contains(&position[2]);
+            assert!(!inside_wall, "enemy went through the wall at {position:?}");
+        }
+
+        let end = world.world.get::<TransformQ>(enemy).unwrap().position;
+        assert!(end[0].abs() > 0.5, "enemy should be displaced laterally, ended at {end:?}");
+        assert!((end[0].powi(2) + end[2].powi(2)).sqrt() > 1.0, "enemy stalled at {end:?}");
+    }
+
+    #[test]
+    fn enemy_ignores_player_outside_aggro_and_chases_one_inside() {
+        let mut world = GameWorld::new();
+        let player = world.add_player("p1".to_string());
+        let enemy = world.add_enemy([0.0, 1.0, 0.0], "basic".to_string());
+        let aggro_range = SteeringProfile::for_enemy_type("basic").aggro_range;
+        let enemy_state = |world: &GameWorld| world.world.get::<Enemy>(enemy).unwrap().steering.enemy_state;
+
+        world.world.get_mut::<TransformQ>(player).unwrap().position = [0.0, 1.0, aggro_range + 5.0];
+        for _ in 0..10 {
+            world.steer_enemies();
+            assert_eq!(enemy_state(&world), EnemyState::Patrol);
+        }
+
+        // Đưa enemy về spawn rồi cho player bước vào aggro range
+        world.world.get_mut::<TransformQ>(enemy).unwrap().position = [0.0, 1.0, 0.0];
+        world.world.get_mut::<TransformQ>(player).unwrap().position = [0.0, 1.0, aggro_range - 1.0];
+        world.steer_enemies();
+        assert_eq!(enemy_state(&world), EnemyState::Chase);
+        let velocity = world.world.get::<VelocityQ>(enemy).unwrap().velocity;
+        assert!(velocity[2] > 0.0 && velocity[0].abs() < 1e-4, "enemy should head for the player, got {velocity:?}");
+    }
+
+    fn score_of(world: &GameWorld, player: Entity) -> u32 {
+        world.world.get::<Player>(player).unwrap().score
+    }
+
+    #[test]
+    fn rejoin_within_grace_resumes_frozen_entity() {
+        let mut world = GameWorld::new();
+        let first = world.join_player("p1".to_string());
+        assert!(!first.resumed);
+        step(&mut world, 10);
+        let score = score_of(&world, first.entity);
+        assert!(score > 0, "auto-run should have scored");
+
+        assert!(world.disconnect_player("p1", Duration::from_secs(60)));
+        let frozen_z = world.world.get::<TransformQ>(first.entity).unwrap().position[2];
+        step(&mut world, 10);
+        assert_eq!(score_of(&world, first.entity), score, "disconnected player must not keep scoring");
+        assert_eq!(world.world.get::<TransformQ>(first.entity).unwrap().position[2], frozen_z);
+
+        let rejoin = world.join_player("p1".to_string());
+        assert_eq!(rejoin, PlayerJoin { entity: first.entity, resumed: true });
+        assert_eq!(score_of(&world, rejoin.entity), score);
+        assert!(world.world.get::<Disconnected>(rejoin.entity).is_none());
+
+        step(&mut world, 10);
+        assert!(score_of(&world, rejoin.entity) > score);
+    }
+
+    #[test]
+    fn rejoin_after_grace_starts_fresh() {
+        let mut world = GameWorld::new();
+        let first = world.join_player("p1".to_string());
+        step(&mut world, 10);
+        assert!(score_of(&world, first.entity) > 0);
+
+        world.disconnect_player("p1", Duration::ZERO);
+        let rejoin = world.join_player("p1".to_string());
+        assert!(!rejoin.resumed);
+        assert_ne!(rejoin.entity, first.entity);
+        assert_eq!(score_of(&world, rejoin.entity), 0);
+        assert_eq!(world.world.resource::<PlayerEntityMap>().map.len(), 1);
+
+        // Không rejoin thì entity bị dọn ở lần maintenance kế tiếp
+        world.disconnect_player("p1", Duration::ZERO);
+        step(&mut world, 60);
+        assert!(world.world.resource::<PlayerEntityMap>().map.is_empty());
+    }
+
+    fn encoded_events(encoded: EncodedSnapshot) -> Vec<QuantizedGameEvent> {
+        match encoded {
+            EncodedSnapshot::Full(full) => full.events,
+            EncodedSnapshot::Delta(delta) => delta.events,
+        }
+    }
+
+    fn pickups_collected(events: &[QuantizedGameEvent]) -> Vec<&QuantizedGameEvent> {
+        events
+            .iter()
+            .filter(|event| matches!(event, QuantizedGameEvent::PickupCollected { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn collected_pickup_is_reported_once_in_the_next_snapshot() {
+        let mut world = GameWorld::new();
+        let player = world.add_player("p1".to_string());
+        world.get_snapshot_for_player("p1");
+        let position = world.world.get::<TransformQ>(player).unwrap().position;
+        world.add_pickup(position, 10);
+
+        step(&mut world, 1);
+        let encoded = world.get_snapshot_for_player("p1");
+        let quantization = encoded.quantization();
+        let events = encoded_events(encoded);
+        assert_eq!(
+            pickups_collected(&events),
+            [&QuantizedGameEvent::PickupCollected {
+                player_id: "p1".to_string(),
+                value: 10,
+                position: quantization.quantize_position(position),
+            }]
+        );
+        assert!(score_of(&world, player) >= 10);
+
+        step(&mut world, 2);
+        assert!(pickups_collected(&encoded_events(world.get_snapshot_for_player("p1"))).is_empty());
+    }
+
+    #[test]
+    fn positioned_events_respect_aoi_but_global_ones_reach_everyone() {
+        let mut world = GameWorld::new();
+        let near = world.add_player("near".to_string());
+        let far = world.add_player("far".to_string());
+        world.world.get_mut::<TransformQ>(near).unwrap().position = [0.0, 1.0, 0.0];
+        world.world.get_mut::<TransformQ>(far).unwrap().position = [0.0, 1.0, 500.0];
+        world.get_snapshot_for_player("near");
+        world.get_snapshot_for_player("far");
+
+        world.emit(GameEvent::PickupCollected { player_id: "far".to_string(), value: 5, position: [0.0, 1.0, 500.0] });
+        world.emit(GameEvent::FlagCaptured { player_id: "far".to_string(), team: "red".to_string() });
+
+        let near_events = encoded_events(world.get_snapshot_for_player("near"));
+        assert_eq!(
+            near_events,
+            [QuantizedGameEvent::FlagCaptured { player_id: "far".to_string(), team: "red".to_string() }]
+        );
+        let far_events = encoded_events(world.get_snapshot_for_player("far"));
+        assert_eq!(far_events.len(), 2);
+        assert_eq!(pickups_collected(&far_events).len(), 1);
+    }
+
+    fn decoded_position(encoded: &EncodedSnapshot, entity: Entity) -> [f32; 3] {
+        let EncodedSnapshot::Full(full) = encoded else {
+            panic!("expected a full snapshot");
+        };
+        let quantized = full.entities.iter().find(|e| e.id == entity.index()).expect("entity in snapshot");
+        quantized.transform.to_f32(&full.quantization).0
+    }
+
+    #[test]
+    fn far_runner_position_round_trips_relative_to_aoi_origin() {
+        let mut world = GameWorld::new();
+        let player = world.add_player("runner".to_string());
+        let position = [3.0, 1.0, 5000.0];
+        world.world.get_mut::<TransformQ>(player).unwrap().position = position;
+        world.spatial_grid.update_entity_position(player, position);
+        let coin = world.add_endless_runner_pickup([-3.0, 1.0, 5010.0], 1);
+
+        // Snapshot AOI: offset nhỏ quanh origin nên giữ nguyên độ chính xác 0.01
+        let encoded = world.get_snapshot_for_player("runner");
+        let quantization = encoded.quantization();
+        assert_eq!(quantization.origin, [0.0, 0.0, 5000.0]);
+        assert_eq!(quantization.position_factor, 1.0 / POSITION_SCALE);
+        for (entity, expected) in [(player, position), (coin, [-3.0, 1.0, 5010.0])] {
+            let decoded = decoded_position(&encoded, entity);
+            for axis in 0..3 {
+                assert!((decoded[axis] - expected[axis]).abs() <= quantization.position_factor, "{decoded:?} vs {expected:?}");
+            }
+        }
+
+        // Snapshot toàn world trải từ z=0 tới z=5000: encoder giảm độ chính xác thay vì để i16 tràn
+        world.add_pickup([0.0, 1.0, 0.0], 1);
+        let snapshot = world.create_snapshot();
+        let encoded = world.delta_encoder.encode_snapshot(snapshot, 1);
+        let quantization = encoded.quantization();
+        assert!(quantization.position_factor > 1.0 / POSITION_SCALE);
+        let decoded = decoded_position(&encoded, player);
+        for axis in 0..3 {
+            assert!((decoded[axis] - position[axis]).abs() <= quantization.position_factor, "{decoded:?} vs {position:?}");
+        }
+    }
+
+    #[test]
+    fn layered_grid_culls_entities_far_above_or_below() {
+        let mut world = World::new();
+        let ground = world.spawn_empty().id();
+        let ledge = world.spawn_empty().id();
+        let flyer = world.spawn_empty().id();
+        let positions = [(ground, [0.0, 1.0, 0.0]), (ledge, [10.0, 40.0, 0.0]), (flyer, [0.0, 400.0, 10.0])];
+
+        // Mặc định 2D: cùng cột x/z thì thấy nhau bất kể độ cao
+        let mut flat = SpatialGrid::new(50.0);
+        for (entity, position) in positions {
+            flat.add_entity(entity, position);
+        }
+        let mut seen = flat.get_entities_in_aoi(flat.world_to_cell([0.0, 1.0, 0.0]));
+        seen.sort();
+        assert_eq!(seen, [ground, ledge, flyer]);
+
+        let mut layered = SpatialGrid::with_layer_height(50.0, 25.0);
+        for (entity, position) in positions {
+            layered.add_entity(entity, position);
+        }
+        let mut seen = layered.get_entities_in_aoi(layered.world_to_cell([0.0, 1.0, 0.0]));
+        seen.sort();
+        assert_eq!(seen, [ground, ledge]);
+        assert_eq!(layered.get_entities_in_aoi(layered.world_to_cell([0.0, 400.0, 0.0])), [flyer]);
+
+        let mut nearby = Vec::new();
+        layered.entities_within([0.0, 1.0, 0.0], 20.0, &mut nearby);
+        assert_eq!(nearby, [ground]);
+
+        // Tắt chia tầng lúc đang chạy thì quay về hành vi 2D
+        layered.set_layer_height(None);
+        assert_eq!(layered.get_entities_in_aoi(layered.world_to_cell([0.0, 1.0, 0.0])).len(), 3);
+    }
+
+    #[test]
+    fn rebuild_from_repairs_a_corrupted_grid() {
+        let mut world = World::new();
+        let near = world.spawn_empty().id();
+        let far = world.spawn_empty().id();
+        let unseen = world.spawn_empty().id();
+        let truth = [(near, [5.0, 0.0, 5.0]), (far, [500.0, 0.0, 500.0]), (unseen, [10.0, 0.0, 0.0])];
+
+        let mut grid = SpatialGrid::new(50.0);
+        grid.add_entity(near, truth[0].1);
+        grid.add_entity(far, truth[1].1);
+        assert!(grid.validate().is_empty());
+
+        // Lệch kiểu despawn/update bỏ sót: `near` còn sót trong cell của `far`, `unseen` spawn không vào grid
+        let far_cell = grid.world_to_cell(truth[1].1);
+        grid.cells.entry(far_cell).or_default().push(near);
+        assert_eq!(grid.validate(), [near]);
+        assert_eq!(grid.get_entities_in_aoi(far_cell), [far, near]);
+        let origin = grid.world_to_cell([0.0, 0.0, 0.0]);
+        assert_eq!(grid.get_entities_in_aoi(origin), [near]);
+
+        grid.rebuild_from(truth);
+        assert!(grid.validate().is_empty());
+        let mut seen = grid.get_entities_in_aoi(origin);
+        seen.sort();
+        assert_eq!(seen, [near, unseen]);
+        assert_eq!(grid.get_entities_in_aoi(far_cell), [far]);
+    }
+
+    fn moving_entities(tick: u64, count: u32) -> GameSnapshot {
+        GameSnapshot {
+            tick,
+            origin: [0.0; 3],
+            entities: (0..count)
+                .map(|id| EntitySnapshot {
+                    id,
+                    transform: TransformQ {
+                        position: [tick as f32, 1.0, id as f32],
+                        rotation: [0.0, 0.0, 0.0, 1.0],
+                    },
+                    velocity: None,
+                    player: None,
+                    pickup: None,
+                    obstacle: None,
+                    power_up: None,
+                    enemy: None,
+                })
+                .collect(),
+            chat_messages: Vec::new(),
+            spectators: Vec::new(),
+            events: Vec::new(),
+            seed: None,
+            server_time_ms: tick * 50,
+            tick_duration_ms: 50.0,
+            last_acked_sequence: None,
+        }
+    }
+
+    #[test]
+    fn keyframes_follow_cadence_over_300_ticks() {
+        let mut encoder = DeltaEncoder::new(5);
+        let mut full_ticks = Vec::new();
+        for tick in 0..300 {
+            if let EncodedSnapshot::Full(full) = encoder.encode_snapshot(moving_entities(tick, 10), tick) {
+                full_ticks.push(full.tick);
+            }
+        }
+
+        assert_eq!(full_ticks, vec![0, DEFAULT_KEYFRAME_INTERVAL_TICKS, 2 * DEFAULT_KEYFRAME_INTERVAL_TICKS]);
+        assert_eq!(encoder.last_keyframe_tick, 2 * DEFAULT_KEYFRAME_INTERVAL_TICKS);
+    }
+
+    #[test]
+    fn large_delta_forces_keyframe() {
+        let mut encoder = DeltaEncoder::new(5).with_keyframe_policy(KeyframePolicy {
+            interval_ticks: 1_000,
+            max_delta_changes: 8,
+        });
+        assert!(matches!(encoder.encode_snapshot(moving_entities(0, 6), 0), EncodedSnapshot::Full(_)));
+        assert!(matches!(encoder.encode_snapshot(moving_entities(1, 6), 1), EncodedSnapshot::Delta(_)));
+        // 6 updated + 6 created > 8
+        assert!(matches!(encoder.encode_snapshot(moving_entities(2, 12), 2), EncodedSnapshot::Full(_)));
+        assert_eq!(encoder.last_keyframe_tick, 2);
+        assert_eq!(encoder.stats.forced(FullSnapshotReason::First), 1);
+        assert_eq!(encoder.stats.forced(FullSnapshotReason::TooManyChanges), 1);
+        assert_eq!((encoder.stats.deltas_sent, encoder.stats.created), (1, 6));
+    }
+
+    #[test]
+    fn slow_world_sends_fewer_snapshots_but_scores_the_same_per_second() {
+        let run = |tick_hz: u32| {
+            let mut world = GameWorld::new_with_config(GameWorldConfig { seed: Some(7), tick_hz });
+            world.add_player("runner".to_string());
+            let snapshots = world.run_simulation_for_test(1.0);
+            let durations: Vec<f32> = snapshots
+                .iter()
+                .map(|snapshot| match snapshot {
+                    EncodedSnapshot::Full(full) => full.tick_duration_ms,
+                    EncodedSnapshot::Delta(delta) => delta.tick_duration_ms,
+                })
+                .collect();
+            (durations, world.player_scores()["runner"])
+        };
+
+        let (slow, slow_score) = run(30);
+        let (fast, fast_score) = run(60);
+        assert_eq!((slow.len(), fast.len()), (30, 60));
+        assert!(slow.iter().all(|ms| (ms - 1000.0 / 30.0).abs() < 0.01), "{slow:?}");
+        assert!(fast.iter().all(|ms| (ms - 1000.0 / 60.0).abs() < 0.01), "{fast:?}");
+        assert_eq!(slow_score, fast_score);
+        assert!(slow_score > 0);
+    }
+
+    #[test]
+    fn keyframe_cadence_and_power_up_ticks_follow_the_tick_rate() {
+        let mut world = GameWorld::new_with_config(GameWorldConfig { seed: Some(7), tick_hz: 30 });
+        assert_eq!(world.ticks_per_second(), 30);
+        assert_eq!(world.player_encoder("p1").keyframe_policy.interval_ticks, DEFAULT_KEYFRAME_INTERVAL_TICKS / 2);
+        world.set_tick_hz(120);
+        assert_eq!(world.player_encoder("p1").keyframe_policy.interval_ticks, DEFAULT_KEYFRAME_INTERVAL_TICKS * 2);
+
+        // 5 giây power-up ở 30Hz là 150 tick
+        let mut snapshot = moving_entities(0, 1);
+        snapshot.tick_duration_ms = 1000.0 / 30.0;
+        snapshot.entities[0].power_up = Some(PowerUp { power_type: "speed".to_string(), duration: Duration::from_secs(5), value: 1 });
+        let quantized = DeltaEncoder::new(5).quantize_snapshot(snapshot);
+        assert_eq!(quantized.entities[0].power_up.as_ref().unwrap().duration, 150);
+        assert_eq!(GameWorld::new_with_config(GameWorldConfig { seed: None, tick_hz: 1_000 }).tick_hz(), tick_rate::MAX_TICK_HZ);
+    }
+
+    #[test]
+    fn deltas_of_a_mostly_static_world_are_much_smaller_than_full_snapshots() {
+        let mut world = GameWorld::new();
+        world.add_player("runner".to_string());
+        for i in 0..30 {
+            world.add_pickup([(i % 5) as f32 * 2.0 - 4.0, 1.0, 10.0 + i as f32 * 3.0], 1);
+        }
+
+        world.run_simulation_for_test(2.0);
+        let stats = world.encoding_stats();
+        assert!(stats.deltas_computed > 0, "{stats:?}");
+        assert_eq!(stats.forced(FullSnapshotReason::First), 1);
+        assert!(
+            stats.average_delta_bytes() < 0.25 * stats.average_full_bytes(),
+            "delta {:.0}B vs full {:.0}B",
+            stats.average_delta_bytes(),
+            stats.average_full_bytes()
+        );
+    }
+
+    #[test]
+    fn same_seed_and_inputs_produce_identical_quantized_snapshots() {
+        fn run(seed: u64) -> Vec<String> {
+            let mut world = GameWorld::with_seed(seed);
+            spawn_test_entities(&mut world);
+            world.add_bot("room-1", BotDifficulty::Hard);
+            (0..300u32)
+                .map(|tick| {
+                    if tick % 10 == 0 {
+                        world.input_buffers.entry("player_1".to_string()).or_insert_with(InputBuffer::new).add_input(
+                            PlayerInput {
+                                player_id: "player_1".to_string(),
+                                input_sequence: tick / 10 + 1,
+                                movement: [((tick / 50) as f32 - 3.0) * 0.5, 0.0, 1.0],
+                                timestamp: now_ms(),
+                                actions: InputActions { jump: tick % 30 == 0, ..Default::default() },
+                            },
+                        );
+                    }
+                    step(&mut world, 1);
+                    let mut snapshot = world.create_snapshot();
+                    // Giờ server khác nhau giữa hai lần chạy, không thuộc phần tất định
+                    snapshot.server_time_ms = 0;
+                    serde_json::to_string(&world.delta_encoder.quantize_snapshot(snapshot)).unwrap()
+                })
+                .collect()
+        }
+
+        let first = run(42);
+        assert!(first[0].contains("\"seed\":42"), "tick 0 snapshot carries the seed");
+        assert_eq!(first, run(42));
+        assert_ne!(first, run(43));
+    }
+
+    #[test]
+    fn requested_keyframe_covers_missed_delta() {
+        let mut world = GameWorld::new();
+        world.add_player("p1".to_string());
+        world.get_snapshot_for_player("p1");
+
+        step(&mut world, 5);
+        let missed_tick = world.get_snapshot_for_player("p1").tick();
+
+        step(&mut world, 1);
+        match world.force_keyframe_for_player("p1") {
+            EncodedSnapshot::Full(full) => {
+                assert!(full.tick >= missed_tick);
+                assert_eq!(world.last_keyframe_tick, full.tick);
+            }
+            EncodedSnapshot::Delta(_) => panic!("keyframe request must produce a full snapshot"),
+        }
+
+        // Delta tiếp theo lấy keyframe vừa gửi làm base
+        step(&mut world, 1);
+        if let EncodedSnapshot::Delta(delta) = world.get_snapshot_for_player("p1") {
+            assert_eq!(delta.base_tick, missed_tick + 1);
+        }
+    }
+
+    #[test]
+    fn snapshots_carry_server_time_tick_duration_and_input_ack() {
+        let timing = |snapshot: &EncodedSnapshot| match snapshot {
+            EncodedSnapshot::Full(full) => (full.server_time_ms, full.tick_duration_ms, full.last_acked_sequence),
+            EncodedSnapshot::Delta(delta) => (delta.server_time_ms, delta.tick_duration_ms, delta.last_acked_sequence),
+        };
+        let mut world = GameWorld::new();
+        world.add_player("p1".to_string());
+        let before = unix_millis();
+        let first = world.get_snapshot_for_player("p1");
+        let (first_time, tick_duration_ms, acked) = timing(&first);
+        assert!(first_time >= before);
+        assert_eq!(tick_duration_ms, world.tick_rate.as_secs_f32() * 1000.0);
+        assert_eq!(acked, None, "no input processed yet");
+
+        world.input_buffers.entry("p1".to_string()).or_insert_with(InputBuffer::new).add_input(PlayerInput {
+            player_id: "p1".to_string(),
+            input_sequence: 1,
+            movement: [0.0, 0.0, 1.0],
+            timestamp: now_ms(),
+            actions: InputActions::default(),
+        });
+        step(&mut world, 1);
+        let second = world.get_snapshot_for_player("p1");
+        let (second_time, _, acked) = timing(&second);
+        assert!(second_time >= first_time);
+        assert_eq!(acked, Some(1));
+
+        // Client nhận qua JSON: các field mới phải còn nguyên sau round-trip
+        for encoded in [first, second] {
+            let decoded: EncodedSnapshot = serde_json::from_str(&encoded.to_json_string().unwrap()).unwrap();
+            assert_eq!(timing(&decoded), timing(&encoded));
+        }
+    }
+
+    #[test]
+    fn runner_obstacles_behind_players_are_culled() {
+        let mut world = GameWorld::new();
+        let player = world.add_player("runner".to_string());
+        // Wall không có Lifetime, nằm sau player thì chỉ pass cull dọn được
+        let behind = world.add_obstacle([0.0, 0.5, -RUNNER_CULL_DISTANCE - 10.0], "wall".to_string());
+
+        let obstacle_count = |world: &mut GameWorld| world.world.query::<&Obstacle>().iter(&world.world).count();
+        let mut counts = Vec::new();
+        for _ in 0..10 {
+            step(&mut world, 600);
+            counts.push(obstacle_count(&mut world));
+        }
+
+        assert!(world.world.get_entity(behind).is_none());
+        let player_z = world.world.get::<TransformQ>(player).unwrap().position[2];
+        assert!(player_z > 1000.0, "player ran {player_z}");
+        // Chỉ còn obstacle trong khoảng [z - cull, z + spawn ahead], không tăng theo quãng đường đã chạy
+        let max_rows = ((RUNNER_CULL_DISTANCE + SPAWN_AHEAD_DISTANCE) / MIN_ROW_GAP) as usize + 2;
+        let max_live = max_rows * (RUNNER_LANES.len() - 1);
+        assert!(counts.iter().all(|&count| count <= max_live), "obstacle counts {counts:?}");
+        let with_body = world.world.query::<&RigidBodyHandle>().iter(&world.world).count();
+        assert_eq!(world.bodies.len(), with_body + 1 + world.body_pool.len());
+        // Obstacle bị cull trả body về pool, hàng mới phía trước dùng lại
+        let (reused, _) = world.body_pool.stats();
+        assert!(reused > 0, "culled obstacle bodies are recycled");
+        assert!(world.spatial_grid.validate().is_empty());
+    }
+
+    #[test]
+    fn despawned_entities_release_their_physics_bodies() {
+        let mut world = GameWorld::new();
+        // Tick dài để lifetime 30s của pickup hết sau ~300 tick, đủ nhiều vòng spawn/despawn
+        world.tick_rate = Duration::from_millis(100);
+        let player = world.add_player("p1".to_string());
+
+        // Mỗi tick rơi một pickup ngay chỗ player: bị nhặt ở tick sau, sinh pickup thay thế ở chỗ khác,
+        // pickup không ai nhặt hết lifetime 30s thì bị dọn
+        let mut spawned = 0;
+        for tick in 0..1_500u32 {
+            let position = world.world.get::<TransformQ>(player).unwrap().position;
+            world.add_pickup(position, 1);
+            spawned += 1;
+            step(&mut world, 1);
+
+            if tick.is_multiple_of(100) {
+                // Ground body và body rảnh trong pool là những body không có entity
+                let with_body = world.world.query::<&RigidBodyHandle>().iter(&world.world).count();
+                assert_eq!(world.bodies.len(), with_body + 1 + world.body_pool.len());
+                assert_eq!(world.colliders.len(), world.bodies.len());
+            }
+        }
+
+        assert!(world.world.get::<Player>(player).unwrap().score > 0, "pickups were collected");
+        // Không rò body: số body chỉ bằng số pickup còn sống (tối đa ~30s), không tăng theo số đã spawn
+        let lifetime_ticks = Duration::from_secs(30).as_millis() / world.tick_rate.as_millis();
+        assert!(
+            world.bodies.len() < (2 * lifetime_ticks) as usize + 100,
+            "{} bodies after {} pickups",
+            world.bodies.len(),
+            spawned
+        );
+        assert!(world.spatial_grid.validate().is_empty());
+    }
+
+    /// Platform dao động theo z quanh [0, 2, 10], đủ cao để không chạm mặt đất
+    fn z_platform(world: &mut GameWorld) -> Entity {
+        let platform = world.add_obstacle([0.0, 2.0, 10.0], "moving_platform".to_string());
+        world.set_moving_platform(platform, MovingPlatform::new([0.0, 2.0, 10.0], [0.0, 0.0, 1.0], 1.5, std::f32::consts::PI, 0.0));
+        platform
+    }
+
+    #[test]
+    fn moving_platform_oscillates_within_its_range() {
+        let mut world = GameWorld::new();
+        let platform = z_platform(&mut world);
+
+        let (mut min, mut max) = (f32::MAX, f32::MIN);
+        for _ in 0..240 {
+            step(&mut world, 1);
+            let position = world.world.get::<TransformQ>(platform).unwrap().position;
+            let body = world.bodies[world.world.get::<RigidBodyHandle>(platform).unwrap().handle].translation();
+            assert_eq!([body.x, body.y, body.z], position);
+            assert_eq!(world.spatial_grid.entity_positions[&platform], position);
+            assert_eq!([position[0], position[1]], [0.0, 2.0]);
+            min = min.min(position[2]);
+            max = max.max(position[2]);
+        }
+
+        // 4s = 2 chu kỳ: chạm gần hai biên nhưng không vượt
+        assert!((10.0 - 1.5 - 1e-4..10.0 - 1.4).contains(&min), "min z {min}");
+        assert!((10.0 + 1.4..=10.0 + 1.5 + 1e-4).contains(&max), "max z {max}");
+    }
+
+    #[test]
+    fn resting_player_is_carried_by_moving_platform() {
+        let mut world = GameWorld::new();
+        let platform = z_platform(&mut world);
+        let player = world.add_player("p1".to_string());
+
+        // Đặt player đứng ở mép sau mặt platform (top = 2.3)
+        let start = [0.0, 2.3 + PLAYER_RADIUS, 8.1];
+        world.world.get_mut::<TransformQ>(player).unwrap().position = start;
+        let body = body_of(&world, player);
+        world.bodies[body].set_translation(vector![start[0], start[1], start[2]], true);
+        world.bodies[body].set_linvel(vector![0.0, 0.0, 0.0], true);
+
+        let ticks = 10;
+        step(&mut world, ticks);
+
+        // Auto-run cộng thêm đúng độ dời của platform
+        let platform_dz = world.world.get::<TransformQ>(platform).unwrap().position[2] - 10.0;
+        let run = 12.0 * world.tick_rate.as_secs_f32() * ticks as f32;
+        assert!(platform_dz > 0.5, "platform moved {platform_dz}");
+        let position = world.world.get::<TransformQ>(player).unwrap().position;
+        assert!((position[2] - (start[2] + run + platform_dz)).abs() < 1e-3, "player z {}", position[2]);
+        assert!((position[1] - start[1]).abs() < 0.05, "player stays on top, y {}", position[1]);
+    }
+
+    #[test]
+    fn slow_gameplay_raises_degradation_level_then_recovers() {
+        use crate::overload::OverloadPolicy;
+
+        let mut world = GameWorld::new();
+        world.overload = OverloadController::new(OverloadPolicy {
+            ewma_alpha: 0.5,
+            cooldown_ticks: 3,
+            ..OverloadPolicy::default()
+        });
+        world.add_player("p1".to_string());
+
+        // gameplay_logic tốn trọn tick budget
+        world.injected_gameplay_delay = world.tick_rate;
+        let mut levels = Vec::new();
+        for _ in 0..20 {
+            world.timed_fixed_update();
+            levels.push(world.degradation_level());
+        }
+        assert!(levels.windows(2).all(|pair| pair[0] <= pair[1]), "{levels:?}");
+        assert_eq!(world.degradation_level(), DegradationLevel::CoarseDeltas);
+        assert!(!world.degradation_level().spawns_obstacles());
+
+        world.injected_gameplay_delay = Duration::ZERO;
+        for _ in 0..40 {
+            world.timed_fixed_update();
+        }
+        assert_eq!(world.degradation_level(), DegradationLevel::Normal);
+        assert_eq!(world.player_encoder("p1").position_change_threshold, 1);
+    }
+}
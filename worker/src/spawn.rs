@@ -0,0 +1,284 @@
+use std::collections::HashSet;
+
+use bevy_ecs::entity::Entity;
+
+use crate::simulation::SpatialGrid;
+
+/// Góc vàng (radian) - dùng để rải các điểm jitter đều quanh spawn point
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+/// Số lần thử jitter trước khi chấp nhận điểm tốt nhất tìm được
+const MAX_JITTER_ATTEMPTS: u32 = 16;
+
+/// Spawn configuration
+#[derive(Debug, Clone)]
+pub struct SpawnConfig {
+    /// Khoảng cách tối thiểu giữa spawn point và player gần nhất
+    pub min_separation: f32,
+    /// Bán kính jitter cơ bản khi tất cả spawn points đều bị chiếm
+    pub jitter_radius: f32,
+}
+
+impl Default for SpawnConfig {
+    fn default() -> Self {
+        Self {
+            min_separation: 2.0, // Player collider là ball r=0.5
+            jitter_radius: 2.0,
+        }
+    }
+}
+
+/// Quản lý spawn points cho players, chọn điểm xa player khác nhất
+#[derive(Debug, Clone)]
+pub struct SpawnManager {
+    config: SpawnConfig,
+    spawn_points: Vec<[f32; 3]>,
+    /// Thứ tự team xuất hiện - team đầu tiên lấy nửa map phía -z, team sau lấy phía +z
+    team_sides: Vec<String>,
+    /// Counter cho jitter để kết quả deterministic giữa các lần chạy
+    jitter_counter: u32,
+}
+
+impl Default for SpawnManager {
+    fn default() -> Self {
+        // Endless runner: 3 lanes x 4 hàng, lane giữa đứng đầu để player đầu tiên spawn ở [0, 5, 0]
+        Self::lanes(&[0.0, -3.0, 3.0], &[0.0, -4.0, -8.0, -12.0], 5.0)
+    }
+}
+
+impl SpawnManager {
+    pub fn new(spawn_points: Vec<[f32; 3]>, config: SpawnConfig) -> Self {
+        Self {
+            config,
+            spawn_points,
+            team_sides: Vec::new(),
+            jitter_counter: 0,
+        }
+    }
+
+    /// Spawn points xếp thành vòng tròn quanh `center`
+    pub fn ring(center: [f32; 3], radius: f32, count: usize) -> Self {
+        let points = (0..count)
+            .map(|i| {
+                let angle = i as f32 / count.max(1) as f32 * std::f32::consts::TAU;
+                [
+                    center[0] + radius * angle.cos(),
+                    center[1],
+                    center[2] + radius * angle.sin(),
+                ]
+            })
+            .collect();
+        Self::new(points, SpawnConfig::default())
+    }
+
+    /// Spawn points theo từng lane (x) và hàng (z)
+    pub fn lanes(lanes: &[f32], rows: &[f32], height: f32) -> Self {
+        let points = rows
+            .iter()
+            .flat_map(|&z| lanes.iter().map(move |&x| [x, height, z]))
+            .collect();
+        Self::new(points, SpawnConfig::default())
+    }
+
+    /// Đọc `spawn_points` ([[x,y,z], ...]) từ room settings JSON
+    pub fn from_settings_json(settings: &serde_json::Value) -> Option<Self> {
+        let points: Vec<[f32; 3]> = serde_json::from_value(settings.get("spawn_points")?.clone()).ok()?;
+        if points.is_empty() {
+            return None;
+        }
+        Some(Self::new(points, SpawnConfig::default()))
+    }
+
+    pub fn spawn_points(&self) -> &[[f32; 3]] {
+        &self.spawn_points
+    }
+
+    pub fn config(&self) -> &SpawnConfig {
+        &self.config
+    }
+
+    /// Chọn spawn point xa player gần nhất nhất; nếu tất cả đều quá gần thì jitter quanh điểm tốt nhất.
+    /// `team` giới hạn candidates trong nửa map của team (nếu có).
+    pub fn next_spawn_point(
+        &mut self,
+        grid: &SpatialGrid,
+        players: &HashSet<Entity>,
+        team: Option<&str>,
+    ) -> [f32; 3] {
+        let candidates = self.candidates_for_team(team);
+        if candidates.is_empty() {
+            return [0.0, 5.0, 0.0];
+        }
+
+        // Max theo khoảng cách tới player gần nhất, hòa thì lấy điểm đứng trước
+        let mut best = candidates[0];
+        let mut best_distance = nearest_player_distance(grid, players, best);
+        for &candidate in &candidates[1..] {
+            let distance = nearest_player_distance(grid, players, candidate);
+            if distance > best_distance {
+                best = candidate;
+                best_distance = distance;
+            }
+        }
+
+        if best_distance >= self.config.min_separation {
+            return best;
+        }
+
+        self.jittered_point(grid, players, best)
+    }
+
+    fn candidates_for_team(&mut self, team: Option<&str>) -> Vec<[f32; 3]> {
+        let Some(team) = team else {
+            return self.spawn_points.clone();
+        };
+
+        let side = match self.team_sides.iter().position(|t| t == team) {
+            Some(index) => index,
+            None => {
+                self.team_sides.push(team.to_string());
+                self.team_sides.len() - 1
+            }
+        };
+
+        let center_z = self.spawn_points.iter().map(|p| p[2]).sum::<f32>() / self.spawn_points.len().max(1) as f32;
+        let half: Vec<[f32; 3]> = self
+            .spawn_points
+            .iter()
+            .copied()
+            .filter(|p| if side % 2 == 0 { p[2] < center_z } else { p[2] >= center_z })
+            .collect();
+
+        // Map không chia được (ví dụ chỉ có 1 hàng) thì dùng toàn bộ
+        if half.is_empty() {
+            self.spawn_points.clone()
+        } else {
+            half
+        }
+    }
+
+    fn jittered_point(&mut self, grid: &SpatialGrid, players: &HashSet<Entity>, origin: [f32; 3]) -> [f32; 3] {
+        let mut best = origin;
+        let mut best_distance = f32::MIN;
+
+        for attempt in 0..MAX_JITTER_ATTEMPTS {
+            let step = self.jitter_counter.wrapping_add(attempt);
+            let angle = step as f32 * GOLDEN_ANGLE;
+            let radius = self.config.jitter_radius * (1.0 + attempt as f32 / 4.0);
+            let point = [
+                origin[0] + radius * angle.cos(),
+                origin[1],
+                origin[2] + radius * angle.sin(),
+            ];
+
+            let distance = nearest_player_distance(grid, players, point);
+            if distance >= self.config.min_separation {
+                best = point;
+                break;
+            }
+            if distance > best_distance {
+                best = point;
+                best_distance = distance;
+            }
+        }
+
+        self.jitter_counter = self.jitter_counter.wrapping_add(1);
+        best
+    }
+}
+
+/// Khoảng cách từ `point` tới player gần nhất trong 3x3 cells xung quanh (dùng spatial grid)
+fn nearest_player_distance(grid: &SpatialGrid, players: &HashSet<Entity>, point: [f32; 3]) -> f32 {
+    grid.get_entities_in_aoi(grid.world_to_cell(point))
+        .into_iter()
+        .filter(|entity| players.contains(entity))
+        .filter_map(|entity| grid.entity_positions.get(&entity))
+        .map(|pos| {
+            let dx = pos[0] - point[0];
+            let dy = pos[1] - point[1];
+            let dz = pos[2] - point[2];
+            (dx * dx + dy * dy + dz * dz).sqrt()
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::world::World;
+
+    fn occupy(world: &mut World, grid: &mut SpatialGrid, players: &mut HashSet<Entity>, pos: [f32; 3]) {
+        let entity = world.spawn_empty().id();
+        grid.add_entity(entity, pos);
+        players.insert(entity);
+    }
+
+    #[test]
+    fn picks_farthest_free_point() {
+        let mut world = World::new();
+        let mut grid = SpatialGrid::new(50.0);
+        let mut players = HashSet::new();
+        let mut spawns = SpawnManager::new(vec![[0.0, 5.0, 0.0], [10.0, 5.0, 0.0]], SpawnConfig::default());
+
+        assert_eq!(spawns.next_spawn_point(&grid, &players, None), [0.0, 5.0, 0.0]);
+
+        occupy(&mut world, &mut grid, &mut players, [0.0, 5.0, 0.0]);
+        assert_eq!(spawns.next_spawn_point(&grid, &players, None), [10.0, 5.0, 0.0]);
+    }
+
+    #[test]
+    fn crowded_points_fall_back_to_jitter() {
+        let mut world = World::new();
+        let mut grid = SpatialGrid::new(50.0);
+        let mut players = HashSet::new();
+        let mut spawns = SpawnManager::new(vec![[0.0, 5.0, 0.0]], SpawnConfig::default());
+
+        occupy(&mut world, &mut grid, &mut players, [0.0, 5.0, 0.0]);
+        let point = spawns.next_spawn_point(&grid, &players, None);
+        assert!(nearest_player_distance(&grid, &players, point) >= spawns.config().min_separation);
+    }
+
+    #[test]
+    fn teams_get_opposite_halves() {
+        let grid = SpatialGrid::new(50.0);
+        let players = HashSet::new();
+        let mut spawns = SpawnManager::new(vec![[0.0, 5.0, -10.0], [0.0, 5.0, 10.0]], SpawnConfig::default());
+
+        assert_eq!(spawns.next_spawn_point(&grid, &players, Some("red")), [0.0, 5.0, -10.0]);
+        assert_eq!(spawns.next_spawn_point(&grid, &players, Some("blue")), [0.0, 5.0, 10.0]);
+        assert_eq!(spawns.next_spawn_point(&grid, &players, Some("red")), [0.0, 5.0, -10.0]);
+    }
+
+    #[test]
+    fn spawn_points_from_settings_json() {
+        let settings = serde_json::json!({ "spawn_points": [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]] });
+        let spawns = SpawnManager::from_settings_json(&settings).expect("spawn points");
+        assert_eq!(spawns.spawn_points(), &[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+
+        assert!(SpawnManager::from_settings_json(&serde_json::json!({})).is_none());
+    }
+
+    #[test]
+    fn eight_players_spawn_apart() {
+        let mut game_world = crate::simulation::GameWorld::new();
+        for i in 0..8 {
+            game_world.add_player(format!("player_{}", i));
+        }
+
+        let positions: Vec<[f32; 3]> = game_world
+            .world
+            .query::<(&crate::simulation::Player, &crate::simulation::TransformQ)>()
+            .iter(&game_world.world)
+            .map(|(_, transform)| transform.position)
+            .collect();
+        assert_eq!(positions.len(), 8);
+
+        let min_separation = game_world.spawn_manager.config().min_separation;
+        for (i, a) in positions.iter().enumerate() {
+            for b in &positions[i + 1..] {
+                let distance = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt();
+                assert!(distance >= min_separation, "{:?} and {:?} spawned {} apart", a, b, distance);
+            }
+        }
+    }
+}
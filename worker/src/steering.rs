@@ -0,0 +1,286 @@
+use bevy_ecs::entity::Entity;
+use rapier3d::na::Vector2;
+
+/// Bắt đầu đẩy enemy ra khi khoảng cách tới mép obstacle nhỏ hơn giá trị này (units)
+pub const AVOID_DISTANCE: f32 = 3.0;
+/// Bán kính tìm obstacle trên spatial grid: AVOID_DISTANCE + half-extent lớn nhất của obstacle
+pub const OBSTACLE_SEARCH_RADIUS: f32 = AVOID_DISTANCE + 4.0;
+/// Enemy đứng lại khi đã đủ gần player để tấn công
+pub const ATTACK_STOP_DISTANCE: f32 = 2.0;
+const AVOID_WEIGHT: f32 = 2.0;
+/// Góc quay tối đa của hướng wander mỗi tick (radian)
+const WANDER_TURN_RATE: f32 = 0.3;
+/// Sau khi bị leash, enemy chỉ đuổi tiếp khi đã về trong bán kính leash * tỉ lệ này quanh spawn
+const LEASH_RESUME_RATIO: f32 = 0.25;
+
+/// Trạng thái AI của enemy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnemyState {
+    /// Đi lang thang quanh spawn (hoặc đang bị leash kéo về), không để ý player ngoài aggro range
+    #[default]
+    Patrol,
+    /// Đuổi player gần nhất
+    Chase,
+    /// Đứng lại đánh player trong tầm ATTACK_STOP_DISTANCE
+    Attack,
+}
+
+/// Tham số AI theo enemy_type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SteeringProfile {
+    /// Player trong bán kính này mới bị đuổi, ngoài ra enemy đi lang thang
+    pub aggro_range: f32,
+    /// Đã đuổi thì chỉ bỏ cuộc khi player ra xa hơn khoảng này (lớn hơn aggro_range để không giật qua lại)
+    pub deaggro_range: f32,
+    /// Đuổi xa spawn hơn khoảng này thì quay về
+    pub leash_distance: f32,
+    /// Tốc độ wander so với tốc độ đuổi
+    pub wander_pace: f32,
+}
+
+impl SteeringProfile {
+    pub fn for_enemy_type(enemy_type: &str) -> Self {
+        match enemy_type {
+            "fast" => Self { aggro_range: 20.0, deaggro_range: 30.0, leash_distance: 40.0, wander_pace: 0.6 },
+            "tank" => Self { aggro_range: 10.0, deaggro_range: 15.0, leash_distance: 20.0, wander_pace: 0.3 },
+            _ => Self { aggro_range: 15.0, deaggro_range: 22.0, leash_distance: 30.0, wander_pace: 0.5 },
+        }
+    }
+}
+
+/// State steering của từng enemy, nằm trong component `Enemy`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SteeringState {
+    pub spawn_position: [f32; 3],
+    pub wander_heading: f32,
+    /// Đang bị leash kéo về spawn
+    pub returning: bool,
+    pub enemy_state: EnemyState,
+    rng: u32,
+}
+
+impl SteeringState {
+    /// `seed` quyết định random walk khi wander; cùng seed thì cùng quỹ đạo
+    pub fn new(spawn_position: [f32; 3], seed: u32) -> Self {
+        let mut state = Self {
+            spawn_position,
+            wander_heading: 0.0,
+            returning: false,
+            enemy_state: EnemyState::Patrol,
+            rng: seed | 1, // xorshift không được bằng 0
+        };
+        state.wander_heading = state.next_random() * std::f32::consts::PI;
+        state
+    }
+
+    /// xorshift32, trả về số trong [-1, 1]
+    fn next_random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+/// Phần chiếm chỗ của obstacle trên mặt phẳng x/z, khớp với collider trong `add_obstacle`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObstacleFootprint {
+    pub center: [f32; 2],
+    pub half_extents: [f32; 2],
+}
+
+impl ObstacleFootprint {
+    pub fn new(position: [f32; 3], obstacle_type: &str) -> Self {
+        let half_extents = match obstacle_type {
+            "wall" => [2.0, 0.5],
+            "spike" => [0.5, 0.5],
+            "moving_platform" => [3.0, 2.0],
+            _ => [1.0, 1.0],
+        };
+        Self { center: [position[0], position[2]], half_extents }
+    }
+
+    fn center(&self) -> Vector2<f32> {
+        Vector2::new(self.center[0], self.center[1])
+    }
+
+    fn closest_point(&self, point: Vector2<f32>) -> Vector2<f32> {
+        Vector2::new(
+            point.x.clamp(self.center[0] - self.half_extents[0], self.center[0] + self.half_extents[0]),
+            point.y.clamp(self.center[1] - self.half_extents[1], self.center[1] + self.half_extents[1]),
+        )
+    }
+}
+
+/// Buffer dùng lại giữa các tick để AI của enemy không cấp phát mỗi tick
+#[derive(Debug, Default)]
+pub struct SteeringBuffers {
+    pub players: Vec<[f32; 3]>,
+    pub nearby: Vec<Entity>,
+    pub obstacles: Vec<ObstacleFootprint>,
+    /// (enemy, vận tốc x/z, state mới) chờ ghi lại vào ECS
+    pub results: Vec<(Entity, [f32; 2], SteeringState)>,
+}
+
+/// Trạng thái tiếp theo theo khoảng cách x/z tới player gần nhất. Patrol chỉ chuyển sang Chase khi player
+/// vào aggro range, còn đã Chase/Attack thì giữ tới khi player ra khỏi deaggro range; bị leash luôn là Patrol.
+pub fn next_state(current: EnemyState, profile: &SteeringProfile, returning: bool, nearest: Option<f32>) -> EnemyState {
+    if returning {
+        return EnemyState::Patrol;
+    }
+    let engaged = current != EnemyState::Patrol;
+    match nearest {
+        Some(distance) if distance <= ATTACK_STOP_DISTANCE => EnemyState::Attack,
+        Some(distance) if distance <= profile.aggro_range || (engaged && distance <= profile.deaggro_range) => {
+            EnemyState::Chase
+        }
+        _ => EnemyState::Patrol,
+    }
+}
+
+/// Vận tốc x/z của enemy trong tick này theo `EnemyState`: Patrol thì wander (hoặc quay về spawn khi
+/// vượt leash), Chase thì đuổi player gần nhất, Attack thì đứng yên; sau đó cộng lực né obstacle.
+pub fn steer(
+    position: [f32; 3],
+    speed: f32,
+    profile: &SteeringProfile,
+    state: &mut SteeringState,
+    players: &[[f32; 3]],
+    obstacles: &[ObstacleFootprint],
+) -> [f32; 2] {
+    let here = Vector2::new(position[0], position[2]);
+    let spawn = Vector2::new(state.spawn_position[0], state.spawn_position[2]);
+    let from_spawn = (here - spawn).norm();
+    if from_spawn > profile.leash_distance {
+        state.returning = true;
+    } else if state.returning && from_spawn <= profile.leash_distance * LEASH_RESUME_RATIO {
+        state.returning = false;
+    }
+
+    let nearest_player = players
+        .iter()
+        .map(|player| Vector2::new(player[0], player[2]))
+        .map(|player| (player, (player - here).norm()))
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+
+    state.enemy_state = next_state(state.enemy_state, profile, state.returning, nearest_player.map(|(_, distance)| distance));
+
+    let (desired, pace) = match (state.enemy_state, nearest_player) {
+        (EnemyState::Attack, _) => return [0.0, 0.0],
+        (EnemyState::Chase, Some((player, distance))) => ((player - here) / distance, 1.0),
+        _ if state.returning => ((spawn - here).try_normalize(f32::EPSILON).unwrap_or_default(), 1.0),
+        _ => {
+            state.wander_heading += state.next_random() * WANDER_TURN_RATE;
+            (Vector2::new(state.wander_heading.cos(), state.wander_heading.sin()), profile.wander_pace)
+        }
+    };
+
+    let steering = desired + avoidance(here, desired, obstacles) * AVOID_WEIGHT;
+    let Some(direction) = steering.try_normalize(f32::EPSILON) else {
+        return [0.0, 0.0];
+    };
+    if pace < 1.0 {
+        // Wander tiếp theo hướng đã bị obstacle bẻ, không lao lại vào tường
+        state.wander_heading = direction.y.atan2(direction.x);
+    }
+
+    let velocity = direction * speed * pace;
+    [velocity.x, velocity.y]
+}
+
+/// Lực đẩy ra khỏi các obstacle gần, cộng thêm lực sang ngang với obstacle nằm phía trước
+/// để enemy vòng qua thay vì đẩy thẳng vào tường rồi rung tại chỗ.
+fn avoidance(here: Vector2<f32>, desired: Vector2<f32>, obstacles: &[ObstacleFootprint]) -> Vector2<f32> {
+    let mut force = Vector2::zeros();
+    for obstacle in obstacles {
+        let mut offset = here - obstacle.closest_point(here);
+        if offset.norm() <= f32::EPSILON {
+            // Đã lọt vào trong footprint: đẩy ra theo hướng từ tâm
+            offset = here - obstacle.center();
+        }
+        let distance = offset.norm();
+        if distance >= AVOID_DISTANCE || distance <= f32::EPSILON {
+            continue;
+        }
+
+        let away = offset / distance;
+        let weight = 1.0 - distance / AVOID_DISTANCE;
+        force += away * weight;
+
+        let heading_into = -desired.dot(&away);
+        if heading_into > 0.0 {
+            // Vòng qua phía gần hơn; đâm thẳng vào giữa thì mặc định rẽ trái
+            let mut side = Vector2::new(-desired.y, desired.x);
+            if side.dot(&(here - obstacle.center())) < 0.0 {
+                side = -side;
+            }
+            force += side * weight * heading_into;
+        }
+    }
+    force
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn basic() -> SteeringProfile {
+        SteeringProfile::for_enemy_type("basic")
+    }
+
+    #[test]
+    fn chases_player_inside_aggro_range() {
+        let mut state = SteeringState::new([0.0, 1.0, 0.0], 7);
+        let velocity = steer([0.0, 1.0, 0.0], 2.0, &basic(), &mut state, &[[0.0, 1.0, 10.0]], &[]);
+        assert!((velocity[0]).abs() < 1e-4 && (velocity[1] - 2.0).abs() < 1e-4);
+        assert_eq!(state.enemy_state, EnemyState::Chase);
+    }
+
+    #[test]
+    fn chase_holds_until_deaggro_range_then_attack_in_melee_range() {
+        let profile = basic();
+        let between = (profile.aggro_range + profile.deaggro_range) / 2.0;
+        let mut state = SteeringState::new([0.0, 1.0, 0.0], 7);
+
+        // Giữa aggro và deaggro: đang patrol thì không để ý, đang đuổi thì đuổi tiếp
+        steer([0.0, 1.0, 0.0], 2.0, &profile, &mut state, &[[0.0, 1.0, between]], &[]);
+        assert_eq!(state.enemy_state, EnemyState::Patrol);
+        steer([0.0, 1.0, 0.0], 2.0, &profile, &mut state, &[[0.0, 1.0, profile.aggro_range]], &[]);
+        assert_eq!(state.enemy_state, EnemyState::Chase);
+        steer([0.0, 1.0, 0.0], 2.0, &profile, &mut state, &[[0.0, 1.0, between]], &[]);
+        assert_eq!(state.enemy_state, EnemyState::Chase);
+
+        let velocity = steer([0.0, 1.0, 0.0], 2.0, &profile, &mut state, &[[0.0, 1.0, ATTACK_STOP_DISTANCE]], &[]);
+        assert_eq!((state.enemy_state, velocity), (EnemyState::Attack, [0.0, 0.0]));
+
+        steer([0.0, 1.0, 0.0], 2.0, &profile, &mut state, &[[0.0, 1.0, profile.deaggro_range + 1.0]], &[]);
+        assert_eq!(state.enemy_state, EnemyState::Patrol);
+    }
+
+    #[test]
+    fn wanders_at_reduced_pace_without_players() {
+        let mut state = SteeringState::new([0.0, 1.0, 0.0], 7);
+        let velocity = steer([0.0, 1.0, 0.0], 2.0, &basic(), &mut state, &[[0.0, 1.0, 100.0]], &[]);
+        let speed = (velocity[0].powi(2) + velocity[1].powi(2)).sqrt();
+        assert!((speed - 2.0 * basic().wander_pace).abs() < 1e-4);
+    }
+
+    #[test]
+    fn leash_pulls_enemy_home_until_close_to_spawn() {
+        let profile = basic();
+        let mut state = SteeringState::new([0.0, 1.0, 0.0], 7);
+        let player = [[0.0, 1.0, profile.leash_distance + 5.0]];
+
+        let velocity = steer([0.0, 1.0, profile.leash_distance + 1.0], 2.0, &profile, &mut state, &player, &[]);
+        assert!(state.returning);
+        assert!(velocity[1] < 0.0, "enemy quay về spawn");
+
+        // Còn xa spawn thì vẫn về dù player ở ngay cạnh
+        let near_player = [[0.0, 1.0, profile.leash_distance * 0.5 + 3.0]];
+        steer([0.0, 1.0, profile.leash_distance * 0.5], 2.0, &profile, &mut state, &near_player, &[]);
+        assert!(state.returning);
+
+        steer([0.0, 1.0, 1.0], 2.0, &profile, &mut state, &player, &[]);
+        assert!(!state.returning);
+    }
+}
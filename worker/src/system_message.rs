@@ -0,0 +1,50 @@
+//! System message có key ổn định + tham số; client tự dịch/định dạng theo key thay vì server gửi câu tiếng Anh.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// `{name, player_id}`
+pub const PLAYER_JOINED: &str = "player.joined";
+/// `{name, player_id}`
+pub const PLAYER_LEFT: &str = "player.left";
+/// `{name, player_id}`: mất kết nối, entity còn giữ trong rejoin grace
+pub const PLAYER_DISCONNECTED: &str = "player.disconnected";
+/// `{name, player_id, reason}`
+pub const PLAYER_KICKED: &str = "player.kicked";
+/// `{game_mode, mode_name}`: id và tên hiển thị của mode trong registry
+pub const GAME_STARTED: &str = "game.started";
+/// `{message}`: admin gửi qua gateway
+pub const SERVER_ANNOUNCEMENT: &str = "server.announcement";
+/// `{motd}`: chỉ gửi cho player vừa vào room
+pub const ROOM_MOTD: &str = "room.motd";
+
+/// Số ký tự tối đa của announcement/MOTD
+pub const MAX_ANNOUNCEMENT_CHARS: usize = 512;
+
+/// BTreeMap để JSON của snapshot ổn định giữa các lần chạy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemMessage {
+    pub key: String,
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+}
+
+impl SystemMessage {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            params: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_param(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.params.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// player.joined / player.left / player.disconnected / player.kicked cho `player_id` tên `name`
+    pub fn for_player(key: &str, player_id: &str, name: &str) -> Self {
+        Self::new(key).with_param("name", name).with_param("player_id", player_id)
+    }
+}
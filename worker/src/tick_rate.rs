@@ -0,0 +1,162 @@
+//! Hạ tick rate của room khi worker không giữ nổi nhịp gốc, nâng lại khi tải giảm.
+//! Ngưỡng hạ và ngưỡng nâng khác nhau, lại phải giữ liên tục nhiều tick, để room không nhảy qua lại.
+
+use std::time::Duration;
+
+pub const BASE_TICK_HZ: u32 = 60;
+pub const REDUCED_TICK_HZ: u32 = 30;
+/// Khoảng tick rate room được chọn qua `tick_hz`
+pub const MIN_TICK_HZ: u32 = 10;
+pub const MAX_TICK_HZ: u32 = 120;
+
+/// Đưa `tick_hz` của room settings về khoảng cho phép; 0 = nhịp gốc
+pub fn clamp_tick_hz(tick_hz: u32) -> u32 {
+    if tick_hz == 0 {
+        return BASE_TICK_HZ;
+    }
+    tick_hz.clamp(MIN_TICK_HZ, MAX_TICK_HZ)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TickRatePolicy {
+    pub base_hz: u32,
+    pub reduced_hz: u32,
+    /// Số tick liên tiếp vượt budget của `base_hz` trước khi hạ
+    pub downshift_after_ticks: u32,
+    /// Tick tốn dưới tỉ lệ này của budget `base_hz` mới tính là đã hết tải
+    pub restore_below_ratio: f64,
+    /// Số tick liên tiếp dưới ngưỡng trên trước khi nâng lại
+    pub restore_after_ticks: u32,
+}
+
+impl Default for TickRatePolicy {
+    fn default() -> Self {
+        Self {
+            base_hz: BASE_TICK_HZ,
+            reduced_hz: REDUCED_TICK_HZ,
+            downshift_after_ticks: 30,
+            restore_below_ratio: 0.5,
+            restore_after_ticks: 120,
+        }
+    }
+}
+
+impl TickRatePolicy {
+    /// Policy cho room chạy ở `base_hz`; quá tải thì hạ còn một nửa nhưng không dưới `MIN_TICK_HZ`
+    pub fn with_base_hz(base_hz: u32) -> Self {
+        let base_hz = clamp_tick_hz(base_hz);
+        Self {
+            base_hz,
+            reduced_hz: (base_hz / 2).max(MIN_TICK_HZ).min(base_hz),
+            ..Self::default()
+        }
+    }
+
+    /// Thời gian một tick được phép tốn để giữ `base_hz`
+    pub fn budget(&self) -> Duration {
+        Duration::from_secs(1) / self.base_hz.max(1)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TickRateGovernor {
+    policy: TickRatePolicy,
+    current_hz: u32,
+    over_budget_streak: u32,
+    under_threshold_streak: u32,
+}
+
+impl Default for TickRateGovernor {
+    fn default() -> Self {
+        Self::new(TickRatePolicy::default())
+    }
+}
+
+impl TickRateGovernor {
+    pub fn new(policy: TickRatePolicy) -> Self {
+        Self {
+            policy,
+            current_hz: policy.base_hz,
+            over_budget_streak: 0,
+            under_threshold_streak: 0,
+        }
+    }
+
+    pub fn current_hz(&self) -> u32 {
+        self.current_hz
+    }
+
+    pub fn is_downshifted(&self) -> bool {
+        self.current_hz != self.policy.base_hz
+    }
+
+    /// Ghi thời gian xử lý một tick; trả về tick rate mới nếu vừa đổi
+    pub fn observe(&mut self, tick_cost: Duration) -> Option<u32> {
+        let budget = self.policy.budget();
+        if tick_cost > budget {
+            self.over_budget_streak += 1;
+        } else {
+            self.over_budget_streak = 0;
+        }
+        if tick_cost.as_secs_f64() < budget.as_secs_f64() * self.policy.restore_below_ratio {
+            self.under_threshold_streak += 1;
+        } else {
+            self.under_threshold_streak = 0;
+        }
+
+        if !self.is_downshifted() && self.over_budget_streak >= self.policy.downshift_after_ticks {
+            self.current_hz = self.policy.reduced_hz;
+        } else if self.is_downshifted() && self.under_threshold_streak >= self.policy.restore_after_ticks {
+            self.current_hz = self.policy.base_hz;
+        } else {
+            return None;
+        }
+        self.over_budget_streak = 0;
+        self.under_threshold_streak = 0;
+        Some(self.current_hz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alternating_load_does_not_flap() {
+        let mut governor = TickRateGovernor::default();
+        let budget = governor.policy.budget();
+        for _ in 0..1000 {
+            assert_eq!(governor.observe(budget * 2), None);
+            assert_eq!(governor.observe(budget / 4), None);
+        }
+        assert_eq!(governor.current_hz(), BASE_TICK_HZ);
+    }
+
+    #[test]
+    fn cost_between_thresholds_keeps_reduced_rate() {
+        let mut governor = TickRateGovernor::default();
+        let budget = governor.policy.budget();
+        for _ in 0..30 {
+            governor.observe(budget * 2);
+        }
+        assert_eq!(governor.current_hz(), REDUCED_TICK_HZ);
+
+        // Đã trong budget nhưng chưa đủ thấp để nâng lại
+        for _ in 0..1000 {
+            assert_eq!(governor.observe(budget * 3 / 4), None);
+        }
+        assert_eq!(governor.current_hz(), REDUCED_TICK_HZ);
+    }
+
+    #[test]
+    fn room_tick_hz_is_clamped_and_halved_under_load() {
+        assert_eq!(clamp_tick_hz(0), BASE_TICK_HZ);
+        assert_eq!(clamp_tick_hz(1), MIN_TICK_HZ);
+        assert_eq!(clamp_tick_hz(500), MAX_TICK_HZ);
+
+        let policy = TickRatePolicy::with_base_hz(30);
+        assert_eq!((policy.base_hz, policy.reduced_hz), (30, 15));
+        assert_eq!(TickRatePolicy::with_base_hz(10).reduced_hz, MIN_TICK_HZ);
+        assert_eq!(TickRatePolicy::with_base_hz(60), TickRatePolicy::default());
+    }
+}
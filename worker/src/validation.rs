@@ -12,6 +12,20 @@ pub enum ValidationError {
     TimestampTooOld(u64, u64),
     TimestampTooNew(u64, u64),
     RateLimitExceeded,
+    JumpTooSoon(u64, u64),
+    MalformedPayload(String),
+    MissingField(&'static str),
+}
+
+impl ValidationError {
+    /// Mã lỗi ngắn trả cho client trong `PushInputResponse.error` (`<code>: <chi tiết>`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            ValidationError::MalformedPayload(_) => "invalid_json",
+            ValidationError::MissingField(_) => "missing_field",
+            _ => "validation_error",
+        }
+    }
 }
 
 impl std::fmt::Display for ValidationError {
@@ -26,6 +40,9 @@ impl std::fmt::Display for ValidationError {
             ValidationError::TimestampTooOld(expected, actual) => write!(f, "Timestamp too old: expected > {}, got {}", expected, actual),
             ValidationError::TimestampTooNew(expected, actual) => write!(f, "Timestamp too new: expected < {}, got {}", expected, actual),
             ValidationError::RateLimitExceeded => write!(f, "Rate limit exceeded"),
+            ValidationError::JumpTooSoon(min_ticks, actual) => write!(f, "Jump too soon: need {} ticks between jumps, got {}", min_ticks, actual),
+            ValidationError::MalformedPayload(msg) => write!(f, "Malformed payload: {}", msg),
+            ValidationError::MissingField(field) => write!(f, "Missing field: {}", field),
         }
     }
 }
@@ -41,6 +58,8 @@ pub struct ValidationConfig {
     pub max_sequence_gap: u32,
     /// Rate limiting: max inputs per second per player
     pub max_inputs_per_second: u32,
+    /// Minimum simulation ticks between two jumps
+    pub min_jump_interval_ticks: u64,
 }
 
 impl Default for ValidationConfig {
@@ -50,6 +69,7 @@ impl Default for ValidationConfig {
             max_timestamp_diff_ms: 10000, // 10 seconds
             max_sequence_gap: 100,
             max_inputs_per_second: 60, // 60 FPS max
+            min_jump_interval_ticks: 20, // ~0.33s ở 60Hz
         }
     }
 }
@@ -61,6 +81,8 @@ pub struct InputValidator {
     last_sequences: HashMap<String, u32>,
     /// Track input timestamps for rate limiting
     input_timestamps: HashMap<String, Vec<u64>>,
+    /// Track tick of last accepted jump per player
+    last_jump_ticks: HashMap<String, u64>,
 }
 
 impl InputValidator {
@@ -69,6 +91,7 @@ impl InputValidator {
             config,
             last_sequences: HashMap::new(),
             input_timestamps: HashMap::new(),
+            last_jump_ticks: HashMap::new(),
         }
     }
 
@@ -78,14 +101,7 @@ impl InputValidator {
 
     /// Validate player input comprehensively
     pub fn validate_input(&mut self, input: &crate::simulation::PlayerInput) -> Result<(), ValidationError> {
-        // Validate player_id
-        self.validate_player_id(&input.player_id)?;
-
-        // Validate movement vector
-        self.validate_movement(&input.movement)?;
-
-        // Validate timestamp
-        self.validate_timestamp(input.timestamp)?;
+        self.check_input(input)?;
 
         // Validate sequence number
         self.validate_sequence(&input.player_id, input.input_sequence)?;
@@ -99,6 +115,27 @@ impl InputValidator {
         Ok(())
     }
 
+    /// Chỉ kiểm nội dung input (player_id, movement, timestamp), không ghi nhận sequence/rate limit.
+    /// Dùng khi nhận input để báo lỗi sớm; `validate_input` đầy đủ chạy lúc simulation lấy input ra khỏi buffer
+    pub fn check_input(&self, input: &crate::simulation::PlayerInput) -> Result<(), ValidationError> {
+        self.validate_player_id(&input.player_id)?;
+        self.validate_movement(&input.movement)?;
+        self.validate_timestamp(input.timestamp)
+    }
+
+    /// Gate jump spam: accept a jump only if enough ticks passed since the last accepted one
+    pub fn validate_jump(&mut self, player_id: &str, current_tick: u64) -> Result<(), ValidationError> {
+        if let Some(&last_tick) = self.last_jump_ticks.get(player_id) {
+            let elapsed = current_tick.saturating_sub(last_tick);
+            if elapsed < self.config.min_jump_interval_ticks {
+                return Err(ValidationError::JumpTooSoon(self.config.min_jump_interval_ticks, elapsed));
+            }
+        }
+
+        self.last_jump_ticks.insert(player_id.to_string(), current_tick);
+        Ok(())
+    }
+
     fn validate_player_id(&self, player_id: &str) -> Result<(), ValidationError> {
         if player_id.is_empty() {
             return Err(ValidationError::InvalidPlayerId("Empty player_id".to_string()));
@@ -249,6 +286,36 @@ pub mod utils {
         Ok(())
     }
 
+    /// Trường bắt buộc của `PlayerInput` (`actions` có default)
+    pub const REQUIRED_INPUT_FIELDS: [&str; 4] = ["player_id", "input_sequence", "movement", "timestamp"];
+
+    /// Parse payload JSON của `PushInput` thành `PlayerInput` rồi kiểm nội dung (`check_input`).
+    /// Thiếu trường, sai kiểu, sequence 0 (gateway điền 0 khi client không gửi) hay movement không hữu hạn
+    /// (vd. `1e39` tràn f32 thành inf) đều thành `ValidationError` thay vì lọt vào physics
+    pub fn parse_input_payload(
+        json_str: &str,
+        validator: &InputValidator,
+    ) -> Result<crate::simulation::PlayerInput, ValidationError> {
+        validate_input_json(json_str).map_err(|e| ValidationError::MalformedPayload(e.to_string()))?;
+
+        let value: serde_json::Value =
+            serde_json::from_str(json_str).map_err(|e| ValidationError::MalformedPayload(e.to_string()))?;
+        let fields = value
+            .as_object()
+            .ok_or_else(|| ValidationError::MalformedPayload("expected a JSON object".to_string()))?;
+        if let Some(missing) = REQUIRED_INPUT_FIELDS.iter().find(|field| fields.get(**field).filter(|v| !v.is_null()).is_none()) {
+            return Err(ValidationError::MissingField(missing));
+        }
+
+        let input: crate::simulation::PlayerInput =
+            serde_json::from_value(value).map_err(|e| ValidationError::MalformedPayload(e.to_string()))?;
+        if input.input_sequence == 0 {
+            return Err(ValidationError::InvalidSequence(0));
+        }
+        validator.check_input(&input)?;
+        Ok(input)
+    }
+
     /// Parse and validate input in one step
     pub fn parse_and_validate_input(
         json_str: &str,
@@ -344,4 +411,53 @@ mod tests {
         // Third input should be rate limited
         assert!(validator.check_rate_limit("player1").is_err());
     }
+
+    #[test]
+    fn push_input_payloads_are_rejected_with_a_reason() {
+        let validator = InputValidator::with_default_config();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let payload = |movement: &str| {
+            format!(r#"{{"player_id":"p1","input_sequence":1,"movement":{movement},"timestamp":{now}}}"#)
+        };
+        let parse = |json: &str| utils::parse_input_payload(json, &validator);
+
+        assert!(parse(&payload("[1.0,0.0,0.5]")).is_ok());
+
+        // JSON không có NaN; số vượt f32 thành inf
+        assert!(matches!(parse(&payload("[NaN,0.0,0.0]")), Err(ValidationError::MalformedPayload(_))));
+        let overflow = parse(&payload("[1e39,0.0,0.0]")).unwrap_err();
+        assert!(matches!(overflow, ValidationError::InvalidMovement(_)), "{overflow}");
+        assert_eq!(overflow.code(), "validation_error");
+
+        let missing = parse(&format!(r#"{{"player_id":"p1","movement":[0,0,0],"timestamp":{now}}}"#)).unwrap_err();
+        assert!(matches!(missing, ValidationError::MissingField("input_sequence")));
+        assert_eq!(missing.code(), "missing_field");
+        assert!(matches!(
+            parse(&format!(r#"{{"player_id":"p1","input_sequence":1,"movement":null,"timestamp":{now}}}"#)),
+            Err(ValidationError::MissingField("movement"))
+        ));
+        assert!(matches!(
+            parse(&format!(r#"{{"player_id":"p1","input_sequence":0,"movement":[0,0,0],"timestamp":{now}}}"#)),
+            Err(ValidationError::InvalidSequence(0))
+        ));
+        assert!(matches!(parse(&payload("[1.0,0.0]")), Err(ValidationError::MalformedPayload(_))));
+        assert!(matches!(parse("[]"), Err(ValidationError::MalformedPayload(_))));
+        assert_eq!(parse("").unwrap_err().code(), "invalid_json");
+    }
+
+    #[test]
+    fn test_jump_spam_gating() {
+        let mut validator = InputValidator::new(ValidationConfig {
+            min_jump_interval_ticks: 10,
+            ..Default::default()
+        });
+
+        assert!(validator.validate_jump("player1", 100).is_ok());
+        assert!(validator.validate_jump("player1", 105).is_err());
+        assert!(validator.validate_jump("player2", 105).is_ok());
+        assert!(validator.validate_jump("player1", 110).is_ok());
+    }
 }
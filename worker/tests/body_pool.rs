@@ -0,0 +1,49 @@
+use std::time::{Duration, Instant};
+
+use worker::{body_pool::BodyPool, simulation::GameWorld};
+
+const SPAWNS: usize = 10_000;
+/// Số obstacle còn sống cùng lúc, như một đoạn track endless runner
+const LIVE_WINDOW: usize = 64;
+const OBSTACLE_TYPES: [&str; 3] = ["wall", "spike", "moving_platform"];
+
+/// Spawn `SPAWNS` obstacle, giữ `LIVE_WINDOW` cái mới nhất, cái cũ nhất bị despawn như khi cull
+fn churn(world: &mut GameWorld) -> Duration {
+    let mut live = std::collections::VecDeque::with_capacity(LIVE_WINDOW + 1);
+    let started = Instant::now();
+    for i in 0..SPAWNS {
+        let obstacle_type = OBSTACLE_TYPES[i % OBSTACLE_TYPES.len()];
+        live.push_back(world.add_obstacle([0.0, 0.5, i as f32 * 25.0], obstacle_type.to_string()));
+        if live.len() > LIVE_WINDOW {
+            let oldest = live.pop_front().expect("oldest obstacle");
+            world.despawn_entity(oldest);
+        }
+    }
+    started.elapsed()
+}
+
+/// So sánh chi phí spawn có và không có pool qua 10k obstacle. Chỉ kiểm tra số body được cấp phát,
+/// thời gian in ra để xem (`-- --nocapture`), chạy `--release` để số liệu có nghĩa
+#[test]
+fn pooled_spawns_reuse_bodies_over_10k_spawns() {
+    let mut unpooled = GameWorld::new();
+    unpooled.body_pool = BodyPool::new(0);
+    let unpooled_time = churn(&mut unpooled);
+
+    let mut pooled = GameWorld::new();
+    let pooled_time = churn(&mut pooled);
+
+    let (reused, allocated) = unpooled.body_pool.stats();
+    assert_eq!((reused, allocated), (0, SPAWNS as u64));
+    let (reused, allocated) = pooled.body_pool.stats();
+    assert_eq!(reused + allocated, SPAWNS as u64);
+    // Chỉ cấp phát lúc pool còn trống: tối đa một cửa sổ live + một body rảnh mỗi hình
+    assert!(allocated <= (LIVE_WINDOW + OBSTACLE_TYPES.len()) as u64, "allocated {allocated}");
+    assert!(pooled.body_pool.reuse_rate() > 0.99);
+    // Cùng số body sống, body rảnh trong pool nằm trong giới hạn
+    assert!(pooled.body_pool.len() <= pooled.body_pool.capacity());
+    assert_eq!(unpooled.bodies.len(), LIVE_WINDOW + 1);
+
+    println!("without pool: {unpooled_time:?} ({:?}/spawn)", unpooled_time / SPAWNS as u32);
+    println!("with pool:    {pooled_time:?} ({:?}/spawn)", pooled_time / SPAWNS as u32);
+}
@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+use worker::{bots::BotDifficulty, simulation::GameWorld};
+
+const ROOMS: usize = 10;
+const BOTS_PER_ROOM: usize = 20;
+const TICK_BUDGET: Duration = Duration::from_millis(16);
+
+/// 200 bot trên 10 room trong 10 giây, mỗi room một GameWorld. Chạy bằng
+/// `cargo test -p worker --release --test bots -- --ignored`
+#[test]
+#[ignore]
+fn two_hundred_bots_keep_ticks_under_budget() {
+    let mut worlds: Vec<GameWorld> = (0..ROOMS)
+        .map(|room| {
+            let mut world = GameWorld::new();
+            let room_id = format!("load-room-{}", room);
+            for _ in 0..BOTS_PER_ROOM {
+                world.add_bot(&room_id, BotDifficulty::Normal);
+            }
+            world
+        })
+        .collect();
+
+    let mut tick_time = Duration::ZERO;
+    let mut ticks = 0u32;
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(10) {
+        let frame = Instant::now();
+        for world in &mut worlds {
+            world.accumulator = world.tick_rate;
+            let tick_started = Instant::now();
+            world.tick();
+            tick_time += tick_started.elapsed();
+            ticks += 1;
+        }
+        if let Some(rest) = TICK_BUDGET.checked_sub(frame.elapsed()) {
+            std::thread::sleep(rest);
+        }
+    }
+
+    let average = tick_time / ticks;
+    println!("{} room ticks, average {:?}", ticks, average);
+    assert!(average < TICK_BUDGET, "average tick {:?} exceeds {:?}", average, TICK_BUDGET);
+    for world in &worlds {
+        assert_eq!(world.bots.len(), BOTS_PER_ROOM);
+    }
+}
@@ -0,0 +1,91 @@
+use worker::checkpoint::{self, CheckpointTracker, ProgressCheckpoint};
+use worker::room::{GameMode, RoomManager, RoomSettings};
+use worker::simulation::GameWorld;
+
+const STARTED_AT: u64 = 1_700_000_000;
+
+#[test]
+fn restored_score_matches_the_last_checkpoint_after_a_crash() {
+    let mut rooms = RoomManager::default();
+    let settings = RoomSettings {
+        max_players: 4,
+        game_mode: GameMode::EndlessRunner,
+        resume_progress: true,
+        ..RoomSettings::default()
+    };
+    let room_id = rooms
+        .create_room("marathon".to_string(), "runner".to_string(), "Runner".to_string(), settings)
+        .expect("create room");
+    let room = rooms.get_room(&room_id).expect("room").clone();
+    let interval_seconds = room.settings.checkpoint_interval_seconds as u64;
+
+    let mut world = GameWorld::new();
+    world.set_runner_seed(7);
+    world.add_player("runner".to_string());
+    let ticks_per_second = world.ticks_per_second();
+
+    // Vec đứng thay cho queue ghi PocketBase; worker kiểm tra checkpoint mỗi giây
+    let mut tracker = CheckpointTracker::default();
+    let mut saved: Vec<ProgressCheckpoint> = Vec::new();
+    for second in 0..=90u64 {
+        if second > 0 {
+            for _ in 0..ticks_per_second {
+                world.accumulator = world.tick_rate;
+                world.tick();
+            }
+        }
+        let progress = world.player_progress();
+        let checkpoints = tracker.collect([&room], &progress, world.get_current_tick(), ticks_per_second, STARTED_AT + second);
+        saved.extend(checkpoints);
+    }
+    let crashed_tick = world.get_current_tick();
+    let crashed_score = world.player_scores()["runner"];
+    drop(world);
+
+    assert_eq!(saved.len(), 3, "{saved:?}");
+    let last = saved.last().expect("checkpoint written");
+    assert!(last.score > 0 && last.distance > 0.0, "{last:?}");
+    assert!(crashed_tick - last.tick <= interval_seconds * ticks_per_second);
+    assert!(crashed_score >= last.score);
+
+    // Worker mới: player vào room mới cùng mode trong cửa sổ resume được khôi phục checkpoint tốt nhất
+    let mut world = GameWorld::new();
+    world.add_player("runner".to_string());
+    let now = STARTED_AT + 90 + 10;
+    let best = checkpoint::best_checkpoint(&saved, GameMode::EndlessRunner.as_str(), now).expect("best checkpoint");
+    assert_eq!(best, last);
+    assert!(world.restore_player_score("runner", best.score));
+    assert_eq!(world.player_scores()["runner"], last.score);
+
+    assert!(checkpoint::best_checkpoint(&saved, GameMode::Deathmatch.as_str(), now).is_none());
+    let too_late = STARTED_AT + 90 + checkpoint::RESUME_GRACE_SECONDS + 1;
+    assert!(checkpoint::best_checkpoint(&saved, GameMode::EndlessRunner.as_str(), too_late).is_none());
+}
+
+#[test]
+fn unchanged_scores_are_not_checkpointed_again() {
+    let mut rooms = RoomManager::default();
+    let settings = RoomSettings { game_mode: GameMode::EndlessRunner, checkpoint_interval_seconds: 5, ..RoomSettings::default() };
+    let room_id = rooms
+        .create_room("idle".to_string(), "idler".to_string(), "Idler".to_string(), settings)
+        .expect("create room");
+    let room = rooms.get_room(&room_id).expect("room").clone();
+
+    let mut world = GameWorld::new();
+    world.add_player("idler".to_string());
+    assert!(world.restore_player_score("idler", 120));
+    let progress = world.player_progress();
+    let tps = world.ticks_per_second();
+
+    let mut tracker = CheckpointTracker::default();
+    assert!(tracker.collect([&room], &progress, 0, tps, STARTED_AT).is_empty());
+    assert!(tracker.collect([&room], &progress, 4 * tps, tps, STARTED_AT + 4).is_empty());
+    let first = tracker.collect([&room], &progress, 5 * tps, tps, STARTED_AT + 5);
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].score, 120);
+    assert!(tracker.collect([&room], &progress, 10 * tps, tps, STARTED_AT + 10).is_empty());
+
+    tracker.forget_room(&room_id);
+    assert!(tracker.collect([&room], &progress, 10 * tps, tps, STARTED_AT + 10).is_empty());
+    assert_eq!(tracker.collect([&room], &progress, 15 * tps, tps, STARTED_AT + 15).len(), 1);
+}
@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use common_net::{metrics, telemetry};
+use common_net::{health, metrics, telemetry};
 use reqwest::StatusCode;
 
 #[tokio::test]
@@ -35,3 +35,36 @@ async fn metrics_endpoint_returns_expected_keys(
     server.abort();
     Ok(())
 }
+
+#[tokio::test]
+async fn readyz_reports_worker_locks(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    telemetry::init("worker-test");
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let readiness = worker::readiness(std::sync::Arc::new(worker::rpc::WorkerState::default()));
+
+    let server = tokio::spawn(async move {
+        if let Err(err) = metrics::serve_metrics_with_readiness(listener, worker::METRICS_PATH, readiness).await {
+            panic!("metrics server failed: {err}");
+        }
+    });
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()?;
+
+    let health = client.get(format!("http://{}{}", addr, health::HEALTHZ_PATH)).send().await?;
+    assert_eq!(StatusCode::OK, health.status());
+
+    let resp = client.get(format!("http://{}{}", addr, health::READYZ_PATH)).send().await?;
+    assert_eq!(StatusCode::OK, resp.status());
+    let body: serde_json::Value = resp.json().await?;
+    assert_eq!(body["game_world"], "ok");
+    assert_eq!(body["room_manager"], "ok");
+    assert!(body.get("pocketbase").is_none());
+
+    server.abort();
+    Ok(())
+}
@@ -0,0 +1,810 @@
+use std::time::Duration;
+
+use proto::worker::v1::{
+    AddBotsRequest, AnnounceRequest, CreateRoomRequest, GetPlayerSnapshotRequest, GetRoomPlayersRequest, JoinRoomAsPlayerRequest,
+    JoinRoomAsSpectatorRequest, JoinRoomRequest, ListActiveRoomsRequest, NotifyDisconnectRequest, PlayerLatency, PushInputRequest,
+    RemovePlayerRequest, RoomSettings, UpdatePlayerLatencyRequest,
+};
+use serde_json::Value;
+use worker::rpc;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+async fn create_room(client: &mut rpc::Client, name: &str, host_id: &str) -> Result<String, BoxError> {
+    let response = client
+        .create_room(CreateRoomRequest {
+            room_name: name.to_string(),
+            host_id: host_id.to_string(),
+            host_name: host_id.to_string(),
+            settings: Some(RoomSettings {
+                max_players: 4,
+                allow_spectators: true,
+                ..Default::default()
+            }),
+        })
+        .await?
+        .into_inner();
+    assert!(response.success, "{}", response.error);
+    Ok(response.room_id)
+}
+
+#[tokio::test]
+async fn active_rooms_report_player_and_spectator_counts() -> Result<(), BoxError> {
+    let (endpoint, server) = rpc::spawn_test_server().await;
+    let mut client = rpc::client(&endpoint)?;
+
+    let arena = create_room(&mut client, "arena", "host-a").await?;
+    let lobby = create_room(&mut client, "lobby", "host-b").await?;
+
+    let joined = client
+        .join_room_as_player(JoinRoomAsPlayerRequest {
+            room_id: arena.clone(),
+            player_id: "alice".to_string(),
+            player_name: "Alice".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert!(joined.success, "{}", joined.error);
+    // Spawn entity cho alice trong simulation
+    assert!(
+        client
+            .join_room(JoinRoomRequest { room_id: arena.clone(), player_id: "alice".to_string(), player_name: String::new() })
+            .await?
+            .into_inner()
+            .ok
+    );
+
+    let watched = client
+        .join_room_as_spectator(JoinRoomAsSpectatorRequest {
+            room_id: lobby.clone(),
+            spectator_id: "watcher".to_string(),
+            spectator_name: "Watcher".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert!(watched.success, "{}", watched.error);
+
+    let rooms = client.list_active_rooms(ListActiveRoomsRequest {}).await?.into_inner().rooms;
+    assert_eq!(rooms.len(), 2);
+
+    let arena_room = rooms.iter().find(|room| room.room_id == arena).expect("arena listed");
+    assert_eq!(
+        (arena_room.player_count, arena_room.spectator_count, arena_room.entity_count),
+        (2, 0, 1)
+    );
+    let lobby_room = rooms.iter().find(|room| room.room_id == lobby).expect("lobby listed");
+    assert_eq!(
+        (lobby_room.player_count, lobby_room.spectator_count, lobby_room.entity_count),
+        (1, 1, 1)
+    );
+    assert_eq!(arena_room.tick, lobby_room.tick);
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn room_players_report_live_connection_latency_and_bots() -> Result<(), BoxError> {
+    let (endpoint, server) = rpc::spawn_test_server().await;
+    let mut client = rpc::client(&endpoint)?;
+
+    let arena = create_room(&mut client, "arena", "host-a").await?;
+    let joined = client
+        .join_room_as_player(JoinRoomAsPlayerRequest {
+            room_id: arena.clone(),
+            player_id: "alice".to_string(),
+            player_name: "Alice".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert!(joined.success, "{}", joined.error);
+    for player_id in ["alice", "bob"] {
+        let join = JoinRoomRequest { room_id: arena.clone(), player_id: player_id.to_string(), player_name: String::new() };
+        assert!(client.join_room(join).await?.into_inner().ok);
+    }
+    let bots = client
+        .add_bots(AddBotsRequest { room_id: arena.clone(), count: 1, difficulty: String::new() })
+        .await?
+        .into_inner();
+    assert!(bots.success, "{}", bots.error);
+    let latency = PlayerLatency { room_id: arena.clone(), player_id: "alice".to_string(), rtt_ms: 42 };
+    client.update_player_latency(UpdatePlayerLatencyRequest { latencies: vec![latency] }).await?;
+    let disconnected = client
+        .notify_disconnect(NotifyDisconnectRequest { room_id: arena.clone(), player_id: "bob".to_string() })
+        .await?
+        .into_inner();
+    assert!(disconnected.ok, "{}", disconnected.error);
+
+    let players = client
+        .get_room_players(GetRoomPlayersRequest { room_id: arena.clone() })
+        .await?
+        .into_inner();
+    assert!(players.ok, "{}", players.error);
+    let find = |id: &str| players.players.iter().find(|player| player.id == id).expect("player listed");
+
+    let alice = find("alice");
+    assert!(!alice.is_ready);
+    assert_eq!((alice.name.as_str(), alice.connected, alice.is_bot, alice.latency_ms), ("Alice", true, false, Some(42)));
+    // Host trong lobby chưa có entity vẫn được liệt kê
+    let host = find("host-a");
+    assert_eq!((host.connected, host.latency_ms), (true, None));
+    let bob = find("bob");
+    assert_eq!((bob.name.as_str(), bob.connected), ("bob", false));
+    let bot = find(&bots.bot_ids[0]);
+    assert_eq!((bot.is_bot, bot.latency_ms), (true, None));
+    assert_eq!(players.players.len(), 4);
+
+    let other = client.get_room_players(GetRoomPlayersRequest { room_id: "empty".to_string() }).await?.into_inner();
+    assert!(other.ok && other.players.is_empty());
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn reconnect_within_grace_resumes_player_entity() -> Result<(), BoxError> {
+    let (endpoint, server) = rpc::spawn_test_server().await;
+    let mut client = rpc::client(&endpoint)?;
+
+    let arena = create_room(&mut client, "arena", "host-a").await?;
+    let join = JoinRoomRequest { room_id: arena.clone(), player_id: "alice".to_string(), player_name: String::new() };
+    let first = client.join_room(join.clone()).await?.into_inner();
+    assert!(first.ok && !first.resumed);
+
+    let disconnected = client
+        .notify_disconnect(NotifyDisconnectRequest { room_id: arena.clone(), player_id: "alice".to_string() })
+        .await?
+        .into_inner();
+    assert!(disconnected.ok, "{}", disconnected.error);
+    assert_eq!(disconnected.grace_seconds, 60);
+
+    let rejoined = client.join_room(join).await?.into_inner();
+    assert!(rejoined.ok && rejoined.resumed);
+
+    let unknown = client
+        .notify_disconnect(NotifyDisconnectRequest { room_id: arena, player_id: "nobody".to_string() })
+        .await?
+        .into_inner();
+    assert!(!unknown.ok);
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn room_event_stream_delivers_joins_and_ends_when_the_room_closes() -> Result<(), BoxError> {
+    use proto::worker::v1::{room_event::Event, LeaveRoomAsPlayerRequest, SendChatRequest, StreamRoomEventsRequest};
+
+    let state = std::sync::Arc::new(rpc::WorkerState::new());
+    let (endpoint, server) = rpc::spawn_test_server_with(state.clone()).await;
+    let mut client = rpc::client(&endpoint)?;
+    let arena = create_room(&mut client, "arena", "host-a").await?;
+    let subscribe = StreamRoomEventsRequest { room_id: arena.clone() };
+
+    let mut events = client.stream_room_events(subscribe.clone()).await?.into_inner();
+    let joined = client
+        .join_room_as_player(JoinRoomAsPlayerRequest {
+            room_id: arena.clone(),
+            player_id: "alice".to_string(),
+            player_name: "Alice".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert!(joined.success, "{}", joined.error);
+
+    let event = tokio::time::timeout(Duration::from_secs(2), events.message()).await??.expect("player_joined");
+    assert_eq!(event.room_id, arena);
+    match event.event {
+        Some(Event::PlayerJoined(joined)) => assert_eq!((joined.player_id.as_str(), joined.resumed), ("alice", false)),
+        other => panic!("expected player_joined, got {other:?}"),
+    }
+    let event = tokio::time::timeout(Duration::from_secs(2), events.message()).await??.expect("system_message");
+    match event.event {
+        Some(Event::SystemMessage(system)) => {
+            assert_eq!(system.key, "player.joined");
+            assert_eq!(system.params.get("name").map(String::as_str), Some("Alice"));
+            assert!(system.recipient_id.is_empty());
+        }
+        other => panic!("expected system_message, got {other:?}"),
+    }
+
+    let chat = client
+        .send_chat(SendChatRequest { room_id: arena.clone(), player_id: "alice".to_string(), message: "gg".to_string() })
+        .await?
+        .into_inner();
+    assert!(chat.ok && chat.subscribers == 1, "{chat:?}");
+    let event = tokio::time::timeout(Duration::from_secs(2), events.message()).await??.expect("chat");
+    assert!(
+        matches!(event.event, Some(Event::Chat(ref chat)) if chat.message == "gg" && chat.player_name == "Alice"),
+        "{event:?}"
+    );
+
+    // Client huỷ stream thì worker bỏ subscriber đó
+    let cancelled = client.stream_room_events(subscribe).await?.into_inner();
+    assert_eq!(state.room_events.subscriber_count(&arena), 2);
+    drop(cancelled);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    while state.room_events.subscriber_count(&arena) != 1 {
+        assert!(tokio::time::Instant::now() < deadline, "cancelled stream is still subscribed");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    // Người cuối rời thì room đóng và stream kết thúc sau các event player_left
+    for player_id in ["alice", "host-a"] {
+        let left = client
+            .leave_room_as_player(LeaveRoomAsPlayerRequest { room_id: arena.clone(), player_id: player_id.to_string() })
+            .await?
+            .into_inner();
+        assert!(left.success, "{}", left.error);
+    }
+    let mut left = Vec::new();
+    while let Some(event) = tokio::time::timeout(Duration::from_secs(2), events.message()).await?? {
+        if let Some(Event::PlayerLeft(player_left)) = event.event {
+            left.push(player_left.player_id);
+        }
+    }
+    assert_eq!(left, ["alice", "host-a"]);
+    assert_eq!(state.room_events.subscriber_count(&arena), 0);
+
+    server.abort();
+    Ok(())
+}
+
+/// Entity id của player trong snapshot JSON (`{"Full": {...}}` hoặc `{"Delta": {...}}`),
+/// kèm `deleted_entities` nếu là delta
+fn player_entity_ids(payload_json: &str, player_id: &str) -> (Vec<u64>, Option<Vec<u64>>) {
+    let snapshot: Value = serde_json::from_str(payload_json).expect("snapshot json");
+    let (entities, deleted) = match (&snapshot["Full"], &snapshot["Delta"]) {
+        (Value::Object(full), _) => (&full["entities"], None),
+        (_, Value::Object(delta)) => (&delta["created_entities"], Some(&delta["deleted_entities"])),
+        _ => panic!("unexpected snapshot {payload_json}"),
+    };
+    let present = entities
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|entity| entity["player"]["id"] == player_id)
+        .filter_map(|entity| entity["id"].as_u64())
+        .collect();
+    let deleted = deleted.map(|ids| ids.as_array().into_iter().flatten().filter_map(Value::as_u64).collect());
+    (present, deleted)
+}
+
+#[tokio::test]
+async fn removed_player_entity_is_gone_from_next_snapshot() -> Result<(), BoxError> {
+    let (endpoint, server) = rpc::spawn_test_server().await;
+    let mut client = rpc::client(&endpoint)?;
+
+    let arena = create_room(&mut client, "arena", "host-a").await?;
+    for player_id in ["alice", "bob"] {
+        let joined = client
+            .join_room(JoinRoomRequest { room_id: arena.clone(), player_id: player_id.to_string(), player_name: String::new() })
+            .await?
+            .into_inner();
+        assert!(joined.ok, "{}", joined.error);
+    }
+
+    let snapshot_request = GetPlayerSnapshotRequest { room_id: arena.clone(), player_id: "bob".to_string() };
+    let before = client.request_keyframe(snapshot_request.clone()).await?.into_inner();
+    let (alice_entities, _) = player_entity_ids(&before.snapshot.expect("snapshot").payload_json, "alice");
+    assert_eq!(alice_entities.len(), 1);
+
+    let removed = client
+        .remove_player(RemovePlayerRequest {
+            room_id: arena.clone(),
+            player_id: "alice".to_string(),
+            reason: "kicked by host".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert!(removed.ok, "{}", removed.error);
+
+    let after = client.get_player_snapshot(snapshot_request).await?.into_inner();
+    let (present, deleted) = player_entity_ids(&after.snapshot.expect("snapshot").payload_json, "alice");
+    assert!(present.is_empty());
+    // Delta thì entity cũ phải nằm trong deleted_entities, Full thì chỉ cần không còn
+    if let Some(deleted) = deleted {
+        assert_eq!(deleted, alice_entities);
+    }
+
+    let again = client
+        .remove_player(RemovePlayerRequest { room_id: arena, player_id: "alice".to_string(), reason: String::new() })
+        .await?
+        .into_inner();
+    assert!(!again.ok);
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn malformed_input_payloads_get_a_structured_error() -> Result<(), BoxError> {
+    let (endpoint, server) = rpc::spawn_test_server().await;
+    let mut client = rpc::client(&endpoint)?;
+
+    let arena = create_room(&mut client, "arena", "host-a").await?;
+    let joined = client
+        .join_room(JoinRoomRequest { room_id: arena.clone(), player_id: "alice".to_string(), player_name: String::new() })
+        .await?
+        .into_inner();
+    assert!(joined.ok, "{}", joined.error);
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_millis() as u64;
+    let cases = [
+        (format!(r#"{{"player_id":"alice","input_sequence":1,"movement":[1e39,0,0],"timestamp":{now}}}"#), "validation_error:"),
+        (format!(r#"{{"player_id":"alice","input_sequence":1,"movement":[NaN,0,0],"timestamp":{now}}}"#), "invalid_json:"),
+        (format!(r#"{{"player_id":"alice","movement":[0,0,1],"timestamp":{now}}}"#), "missing_field:"),
+    ];
+    for (sequence, (payload_json, code)) in cases.into_iter().enumerate() {
+        let response = client
+            .push_input(PushInputRequest { room_id: arena.clone(), sequence: sequence as u32 + 1, payload_json })
+            .await?
+            .into_inner();
+        assert!(!response.ok);
+        assert!(response.error.starts_with(code), "{}", response.error);
+        assert!(response.snapshot.is_none());
+    }
+
+    // Simulation không bị đầu độc: input hợp lệ sau đó vẫn chạy
+    let payload_json = format!(r#"{{"player_id":"alice","input_sequence":4,"movement":[0,0,1],"timestamp":{now}}}"#);
+    let response =
+        client.push_input(PushInputRequest { room_id: arena, sequence: 4, payload_json }).await?.into_inner();
+    assert!(response.ok, "{}", response.error);
+    assert!(!response.snapshot.expect("snapshot").payload_json.contains("NaN"));
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn deathmatch_finishes_when_a_player_reaches_the_score_target() {
+    use worker::room::{RoomError, RoomSettings as WorkerRoomSettings, RoomState};
+    use worker::simulation::{GameEvent, Player};
+
+    let state = rpc::WorkerState::new();
+    let room_id = {
+        let mut rooms = state.room_manager.write().await;
+        let room_id = rooms
+            .create_room(
+                "arena".to_string(),
+                "alice".to_string(),
+                "Alice".to_string(),
+                WorkerRoomSettings { score_target: Some(30), ..Default::default() },
+            )
+            .unwrap();
+        rooms.join_room(&room_id, "bob".to_string(), "Bob".to_string()).unwrap();
+        rooms.start_game(&room_id, "alice").unwrap();
+        room_id
+    };
+    let alice = {
+        let mut game_world = state.game_world.write().await;
+        game_world.add_player("bob".to_string());
+        game_world.add_player("alice".to_string())
+    };
+
+    state.game_world.write().await.world.get_mut::<Player>(alice).unwrap().score = 20;
+    assert_eq!(state.run_match_checks(1).await, 0);
+    assert_eq!(state.room_manager.read().await.get_room(&room_id).unwrap().state, RoomState::Playing);
+
+    state.game_world.write().await.world.get_mut::<Player>(alice).unwrap().score = 30;
+    assert_eq!(state.run_match_checks(1).await, 1);
+    assert_eq!(state.room_manager.read().await.get_room(&room_id).unwrap().state, RoomState::Finished);
+
+    let game_world = state.game_world.read().await;
+    let scoreboard = game_world
+        .events
+        .iter()
+        .find_map(|recorded| match &recorded.event {
+            GameEvent::MatchEnded { room_id: ended, reason, scoreboard } if *ended == room_id => {
+                assert_eq!(reason, "score_target");
+                Some(scoreboard.clone())
+            }
+            _ => None,
+        })
+        .expect("match_ended event is emitted");
+    assert_eq!(scoreboard[0].player_id, "alice");
+    assert_eq!((scoreboard[0].score, scoreboard[0].placement), (30, 1));
+    assert_eq!(scoreboard[1].player_id, "bob");
+    drop(game_world);
+
+    let late = state.room_manager.write().await.join_room(&room_id, "carol".to_string(), "Carol".to_string());
+    assert!(matches!(late, Err(RoomError::RoomNotAcceptingPlayers)));
+}
+
+#[tokio::test]
+async fn overloaded_adaptive_room_downshifts_then_restores() {
+    use worker::room::RoomSettings as WorkerRoomSettings;
+    use worker::simulation::GameEvent;
+    use worker::tick_rate::{TickRatePolicy, BASE_TICK_HZ, REDUCED_TICK_HZ};
+
+    let state = rpc::WorkerState::new();
+    let room_id = {
+        let mut rooms = state.room_manager.write().await;
+        let room_id = rooms
+            .create_room(
+                "arena".to_string(),
+                "alice".to_string(),
+                "Alice".to_string(),
+                WorkerRoomSettings { adaptive_tick_rate: true, min_players_to_start: 1, ..Default::default() },
+            )
+            .unwrap();
+        rooms.start_game(&room_id, "alice").unwrap();
+        room_id
+    };
+    let policy = TickRatePolicy::default();
+    let tick_rate_events = |events: &[worker::simulation::RecordedEvent]| -> Vec<u32> {
+        events
+            .iter()
+            .filter_map(|recorded| match &recorded.event {
+                GameEvent::TickRateChanged { room_id: changed, tick_rate_hz } if *changed == room_id => Some(*tick_rate_hz),
+                _ => None,
+            })
+            .collect()
+    };
+
+    // Mỗi tick tốn gấp đôi budget 60Hz
+    let slow_tick = policy.budget() * 2;
+    let mut changed = 0;
+    for _ in 0..policy.downshift_after_ticks {
+        changed += state.record_tick_cost(slow_tick).await;
+    }
+    assert_eq!(changed, 1);
+    assert_eq!(state.room_manager.read().await.get_room(&room_id).unwrap().tick_rate_hz(), REDUCED_TICK_HZ);
+    assert_eq!(state.game_world.read().await.tick_rate, Duration::from_secs(1) / REDUCED_TICK_HZ);
+
+    // Vẫn quá tải nhẹ: giữ 30Hz
+    for _ in 0..policy.restore_after_ticks * 2 {
+        state.record_tick_cost(policy.budget()).await;
+    }
+    assert_eq!(state.room_manager.read().await.get_room(&room_id).unwrap().tick_rate_hz(), REDUCED_TICK_HZ);
+
+    let fast_tick = policy.budget() / 10;
+    for _ in 0..policy.restore_after_ticks {
+        state.record_tick_cost(fast_tick).await;
+    }
+    assert_eq!(state.room_manager.read().await.get_room(&room_id).unwrap().tick_rate_hz(), BASE_TICK_HZ);
+    assert_eq!(state.game_world.read().await.tick_rate, Duration::from_secs(1) / BASE_TICK_HZ);
+    assert_eq!(tick_rate_events(&state.game_world.read().await.events), vec![REDUCED_TICK_HZ, BASE_TICK_HZ]);
+}
+
+#[tokio::test]
+async fn room_tick_hz_drives_the_simulation_and_is_fixed_after_start() -> Result<(), BoxError> {
+    use proto::worker::v1::{GetRoomInfoRequest, StartGameRequest};
+    use worker::room::RoomError;
+
+    let state = std::sync::Arc::new(rpc::WorkerState::new());
+    let (endpoint, server) = rpc::spawn_test_server_with(state.clone()).await;
+    let mut client = rpc::client(&endpoint)?;
+
+    let created = client
+        .create_room(CreateRoomRequest {
+            room_name: "slow".to_string(),
+            host_id: "host".to_string(),
+            host_name: "Host".to_string(),
+            settings: Some(RoomSettings { max_players: 4, min_players_to_start: 1, tick_hz: 30, ..Default::default() }),
+        })
+        .await?
+        .into_inner();
+    assert!(created.success, "{}", created.error);
+    let room_id = created.room_id;
+
+    let info = client.get_room_info(GetRoomInfoRequest { room_id: room_id.clone() }).await?.into_inner();
+    assert_eq!(info.room.and_then(|room| room.settings).map(|settings| settings.tick_hz), Some(30));
+
+    let started = client
+        .start_game(StartGameRequest { room_id: room_id.clone(), player_id: "host".to_string() })
+        .await?
+        .into_inner();
+    assert!(started.success, "{}", started.error);
+    assert_eq!(state.game_world.read().await.ticks_per_second(), 30);
+
+    let changed = state.room_manager.write().await.set_room_tick_hz(&room_id, 60);
+    assert!(matches!(changed, Err(RoomError::InvalidState)));
+    assert_eq!(state.room_manager.read().await.get_room(&room_id).unwrap().tick_rate_hz(), 30);
+
+    server.abort();
+    Ok(())
+}
+
+/// (key, params) của các system message trong chat của snapshot
+fn system_messages(payload_json: &str) -> Vec<(String, Value)> {
+    let snapshot: Value = serde_json::from_str(payload_json).expect("snapshot json");
+    let body = if snapshot["Full"].is_object() { &snapshot["Full"] } else { &snapshot["Delta"] };
+    body["chat_messages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|message| message.get("system"))
+        .map(|system| (system["key"].as_str().unwrap_or_default().to_string(), system["params"].clone()))
+        .collect()
+}
+
+#[tokio::test]
+async fn join_shows_a_player_joined_system_message_in_the_next_snapshot() -> Result<(), BoxError> {
+    let (endpoint, server) = rpc::spawn_test_server().await;
+    let mut client = rpc::client(&endpoint)?;
+
+    let arena = create_room(&mut client, "arena", "host-a").await?;
+    let joined = client
+        .join_room_as_player(JoinRoomAsPlayerRequest {
+            room_id: arena.clone(),
+            player_id: "alice".to_string(),
+            player_name: "Alice".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert!(joined.success, "{}", joined.error);
+    let spawned = client
+        .join_room(JoinRoomRequest { room_id: arena.clone(), player_id: "alice".to_string(), player_name: String::new() })
+        .await?
+        .into_inner();
+    assert!(spawned.ok, "{}", spawned.error);
+
+    let snapshot = client
+        .get_player_snapshot(GetPlayerSnapshotRequest { room_id: arena, player_id: "alice".to_string() })
+        .await?
+        .into_inner();
+    let messages = system_messages(&snapshot.snapshot.expect("snapshot").payload_json);
+    let joined = messages.iter().find(|(key, _)| key == "player.joined").expect("player.joined in snapshot");
+    assert_eq!(joined.1, serde_json::json!({ "name": "Alice", "player_id": "alice" }));
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn join_uses_the_resolved_display_name_for_the_entity_and_chat() -> Result<(), BoxError> {
+    use proto::worker::v1::{room_event::Event, SendChatRequest, StreamRoomEventsRequest};
+
+    let state = std::sync::Arc::new(rpc::WorkerState::new());
+    let (endpoint, server) = rpc::spawn_test_server_with(state.clone()).await;
+    let mut client = rpc::client(&endpoint)?;
+    let arena = create_room(&mut client, "arena", "host-a").await?;
+    let joined = client
+        .join_room_as_player(JoinRoomAsPlayerRequest {
+            room_id: arena.clone(),
+            player_id: "alice-2".to_string(),
+            player_name: "Alice".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert!(joined.success, "{}", joined.error);
+
+    // Gateway chuyển tên room-manager đã chốt (có hậu tố khi trùng) vào JoinRoom
+    let spawned = client
+        .join_room(JoinRoomRequest {
+            room_id: arena.clone(),
+            player_id: "alice-2".to_string(),
+            player_name: "Alice (2)".to_string(),
+        })
+        .await?
+        .into_inner();
+    assert!(spawned.ok, "{}", spawned.error);
+    assert_eq!(state.game_world.read().await.player_name("alice-2").as_deref(), Some("Alice (2)"));
+
+    let mut events = client.stream_room_events(StreamRoomEventsRequest { room_id: arena.clone() }).await?.into_inner();
+    let chat = client
+        .send_chat(SendChatRequest { room_id: arena, player_id: "alice-2".to_string(), message: "hi".to_string() })
+        .await?
+        .into_inner();
+    assert!(chat.ok, "{chat:?}");
+    let event = tokio::time::timeout(Duration::from_secs(2), events.message()).await??.expect("chat");
+    match event.event {
+        Some(Event::Chat(chat)) => assert_eq!((chat.player_id.as_str(), chat.player_name.as_str()), ("alice-2", "Alice (2)")),
+        other => panic!("expected chat, got {other:?}"),
+    }
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn announcement_and_motd_stay_in_their_room() -> Result<(), BoxError> {
+    let (endpoint, server) = rpc::spawn_test_server().await;
+    let mut client = rpc::client(&endpoint)?;
+
+    let arena = create_room(&mut client, "arena", "host-a").await?;
+    let lobby = client
+        .create_room(CreateRoomRequest {
+            room_name: "lobby".to_string(),
+            host_id: "host-b".to_string(),
+            host_name: "host-b".to_string(),
+            settings: Some(RoomSettings { max_players: 4, motd: Some("welcome to the lobby".to_string()), ..Default::default() }),
+        })
+        .await?
+        .into_inner()
+        .room_id;
+    for (room_id, player_id) in [(&arena, "alice"), (&lobby, "bob"), (&lobby, "carol")] {
+        let joined = client
+            .join_room_as_player(JoinRoomAsPlayerRequest {
+                room_id: room_id.clone(),
+                player_id: player_id.to_string(),
+                player_name: player_id.to_string(),
+            })
+            .await?
+            .into_inner();
+        assert!(joined.success, "{}", joined.error);
+        let spawned = client
+            .join_room(JoinRoomRequest { room_id: room_id.clone(), player_id: player_id.to_string(), player_name: String::new() })
+            .await?
+            .into_inner();
+        assert!(spawned.ok, "{}", spawned.error);
+    }
+
+    let announced = client
+        .announce(AnnounceRequest { room_id: arena.clone(), message: "server restarts in 5 minutes".to_string() })
+        .await?
+        .into_inner();
+    assert!(announced.ok && announced.rooms == 1, "{announced:?}");
+    let unknown = client
+        .announce(AnnounceRequest { room_id: "missing".to_string(), message: "hello".to_string() })
+        .await?
+        .into_inner();
+    assert!(!unknown.ok);
+
+    let mut keys = std::collections::HashMap::new();
+    for (room_id, player_id) in [(&arena, "alice"), (&lobby, "bob")] {
+        let snapshot = client
+            .request_keyframe(GetPlayerSnapshotRequest { room_id: room_id.clone(), player_id: player_id.to_string() })
+            .await?
+            .into_inner();
+        keys.insert(player_id, system_messages(&snapshot.snapshot.expect("snapshot").payload_json));
+    }
+
+    let alice = &keys["alice"];
+    let announcement = alice.iter().find(|(key, _)| key == "server.announcement").expect("announcement in arena");
+    assert_eq!(announcement.1["message"], "server restarts in 5 minutes");
+    assert!(alice.iter().all(|(key, _)| key != "room.motd"));
+
+    // Bob không thấy announcement của arena, thấy MOTD của mình nhưng không thấy MOTD gửi riêng cho carol
+    let bob = &keys["bob"];
+    assert!(bob.iter().all(|(key, _)| key != "server.announcement"));
+    assert!(bob.iter().all(|(_, params)| params["player_id"] != "alice"));
+    let motd: Vec<_> = bob.iter().filter(|(key, _)| key == "room.motd").collect();
+    assert_eq!(motd.len(), 1);
+    assert_eq!(motd[0].1["motd"], "welcome to the lobby");
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn finished_match_submits_real_player_scores_to_the_gateway() -> Result<(), BoxError> {
+    use axum::{routing::post, Json, Router};
+    use worker::leaderboard_client::{LeaderboardClient, ScoreSubmission, SCORES_PATH};
+    use worker::room::RoomSettings as WorkerRoomSettings;
+    use worker::simulation::Player;
+
+    // Gateway giả ghi lại điểm nhận được
+    let (submitted_tx, mut submitted_rx) = tokio::sync::mpsc::unbounded_channel::<ScoreSubmission>();
+    let app = Router::new().route(
+        SCORES_PATH,
+        post(move |Json(submission): Json<ScoreSubmission>| {
+            let submitted_tx = submitted_tx.clone();
+            async move {
+                let _ = submitted_tx.send(submission);
+                Json(serde_json::json!({ "success": true, "rank": 1 }))
+            }
+        }),
+    );
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let gateway_url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(app.into_make_service()));
+
+    let state = rpc::WorkerState::new().with_leaderboard_client(LeaderboardClient::new(&gateway_url, "secret"));
+    let room_id = {
+        let mut rooms = state.room_manager.write().await;
+        let room_id = rooms
+            .create_room(
+                "arena".to_string(),
+                "alice".to_string(),
+                "Alice".to_string(),
+                WorkerRoomSettings { score_target: Some(30), ..Default::default() },
+            )
+            .unwrap();
+        rooms.join_room(&room_id, "bob".to_string(), "Bob".to_string()).unwrap();
+        rooms.start_game(&room_id, "alice").unwrap();
+        room_id
+    };
+    let alice = {
+        let mut game_world = state.game_world.write().await;
+        game_world.add_player("bob".to_string());
+        game_world.add_player("alice".to_string())
+    };
+    state.game_world.write().await.world.get_mut::<Player>(alice).unwrap().score = 30;
+    let tick = state.game_world.read().await.get_current_tick();
+    assert_eq!(state.run_match_checks(1).await, 1);
+
+    let mut submissions = Vec::new();
+    for _ in 0..2 {
+        submissions.push(tokio::time::timeout(Duration::from_secs(5), submitted_rx.recv()).await?.expect("submission"));
+    }
+    submissions.sort_by(|a, b| a.player_id.cmp(&b.player_id));
+    assert_eq!(
+        submissions[0],
+        ScoreSubmission {
+            room_id: room_id.clone(),
+            tick,
+            game_mode: "deathmatch".to_string(),
+            player_id: "alice".to_string(),
+            player_name: "Alice".to_string(),
+            score: 30,
+        }
+    );
+    assert_eq!((submissions[1].player_id.as_str(), submissions[1].score), ("bob", 0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn team_room_spawns_players_on_their_team_half_of_the_room_spawn_points() -> Result<(), BoxError> {
+    let state = std::sync::Arc::new(rpc::WorkerState::new());
+    let (endpoint, server) = rpc::spawn_test_server_with(state.clone()).await;
+    let mut client = rpc::client(&endpoint)?;
+
+    let spawn_points = [[-10.0, 1.0, -10.0], [10.0, 1.0, -10.0], [-10.0, 1.0, 10.0], [10.0, 1.0, 10.0]];
+    let settings = |settings_json: String| RoomSettings {
+        max_players: 4,
+        game_mode: 1, // team deathmatch
+        settings_json,
+        ..Default::default()
+    };
+    let created = client
+        .create_room(CreateRoomRequest {
+            room_name: "teams".to_string(),
+            host_id: "host-a".to_string(),
+            host_name: "Host".to_string(),
+            settings: Some(settings(serde_json::json!({ "spawn_points": spawn_points }).to_string())),
+        })
+        .await?
+        .into_inner();
+    assert!(created.success, "{}", created.error);
+    let room_id = created.room_id;
+
+    for player_id in ["alice", "bob"] {
+        let joined = client
+            .join_room_as_player(JoinRoomAsPlayerRequest {
+                room_id: room_id.clone(),
+                player_id: player_id.to_string(),
+                player_name: player_id.to_string(),
+            })
+            .await?
+            .into_inner();
+        assert!(joined.success, "{}", joined.error);
+        let join = JoinRoomRequest { room_id: room_id.clone(), player_id: player_id.to_string(), player_name: String::new() };
+        assert!(client.join_room(join).await?.into_inner().ok);
+    }
+
+    // Host vào đội đầu, player sau vào đội ít người hơn
+    let players = client.get_room_players(GetRoomPlayersRequest { room_id: room_id.clone() }).await?.into_inner();
+    let team = |id: &str| players.players.iter().find(|player| player.id == id).map(|player| player.team.clone());
+    assert_eq!(team("host-a").as_deref(), Some("red"));
+    assert_eq!(team("alice").as_deref(), Some("blue"));
+    assert_eq!(team("bob").as_deref(), Some("red"));
+
+    // Spawn lấy từ settings của room, mỗi team một nửa map theo trục z
+    let mut game_world = state.game_world.write().await;
+    let alice = game_world.get_player_position("alice").expect("alice spawned");
+    let bob = game_world.get_player_position("bob").expect("bob spawned");
+    drop(game_world);
+    for position in [alice, bob] {
+        assert!(
+            spawn_points.iter().any(|point| (point[0] - position[0]).abs() < 0.5 && (point[2] - position[2]).abs() < 0.5),
+            "{position:?} is not a room spawn point"
+        );
+    }
+    assert!(alice[2] < 0.0 && bob[2] > 0.0, "alice {alice:?}, bob {bob:?}");
+
+    let rejected = client
+        .create_room(CreateRoomRequest {
+            room_name: "broken".to_string(),
+            host_id: "host-b".to_string(),
+            host_name: "Host".to_string(),
+            settings: Some(settings("[1, 2".to_string())),
+        })
+        .await?
+        .into_inner();
+    assert!(!rejected.success);
+
+    server.abort();
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use proto::{
+    auth::AttachInternalToken,
+    worker::v1::{worker_client::WorkerClient, ListActiveRoomsRequest},
+};
+use tonic::Code;
+use worker::rpc::{self, RpcSecurity, WorkerState};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[tokio::test]
+async fn calls_without_the_internal_token_are_rejected() -> Result<(), BoxError> {
+    let (endpoint, _server) =
+        rpc::spawn_test_server_secured(Arc::new(WorkerState::default()), RpcSecurity::new("rpc-auth-test-token")).await;
+
+    let mut anonymous = WorkerClient::new(rpc::channel(&endpoint)?);
+    let status = anonymous.list_active_rooms(ListActiveRoomsRequest {}).await.expect_err("no token");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let mut wrong = WorkerClient::with_interceptor(rpc::channel(&endpoint)?, AttachInternalToken::new("not-the-token"));
+    let status = wrong.list_active_rooms(ListActiveRoomsRequest {}).await.expect_err("wrong token");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let mut client = WorkerClient::with_interceptor(rpc::channel(&endpoint)?, AttachInternalToken::new("rpc-auth-test-token"));
+    assert!(client.list_active_rooms(ListActiveRoomsRequest {}).await?.into_inner().rooms.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn serving_without_a_token_is_refused_off_loopback() {
+    let security = RpcSecurity { token: None, tls: None };
+    assert!(security.check_bind("127.0.0.1:50051".parse().expect("addr")).is_ok());
+    assert!(security.check_bind("0.0.0.0:50051".parse().expect("addr")).is_err());
+    assert!(RpcSecurity::new("t").check_bind("0.0.0.0:50051".parse().expect("addr")).is_ok());
+}
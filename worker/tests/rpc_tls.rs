@@ -0,0 +1,78 @@
+#![cfg(feature = "tls")]
+
+use std::{path::Path, sync::Arc};
+
+use proto::worker::v1::{worker_client::WorkerClient, ListActiveRoomsRequest};
+use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint, Identity};
+use worker::rpc::{self, RpcSecurity, RpcTlsSettings, ServerTls, WorkerService, WorkerState};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+struct Pki {
+    ca: String,
+    server: (String, String),
+    client: (String, String),
+}
+
+/// CA tự ký, cert server cho `localhost` và cert client, cả hai do CA ký
+fn pki() -> Result<Pki, BoxError> {
+    let ca_key = KeyPair::generate()?;
+    let mut ca_params = CertificateParams::new(Vec::<String>::new())?;
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = ca_params.self_signed(&ca_key)?;
+
+    let server_key = KeyPair::generate()?;
+    let server = CertificateParams::new(vec!["localhost".to_string()])?.signed_by(&server_key, &ca, &ca_key)?;
+
+    let client_key = KeyPair::generate()?;
+    let mut client_params = CertificateParams::new(vec!["gateway".to_string()])?;
+    client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+    let client = client_params.signed_by(&client_key, &ca, &ca_key)?;
+
+    Ok(Pki {
+        ca: ca.pem(),
+        server: (server.pem(), server_key.serialize_pem()),
+        client: (client.pem(), client_key.serialize_pem()),
+    })
+}
+
+fn write(dir: &Path, name: &str, pem: &str) -> Result<std::path::PathBuf, BoxError> {
+    let path = dir.join(name);
+    std::fs::write(&path, pem)?;
+    Ok(path)
+}
+
+#[tokio::test]
+async fn mtls_server_accepts_only_clients_with_a_certificate_from_the_client_ca() -> Result<(), BoxError> {
+    let pki = pki()?;
+    let dir = std::env::temp_dir().join(format!("worker-rpc-tls-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let settings = RpcTlsSettings {
+        cert_path: write(&dir, "server.pem", &pki.server.0)?,
+        key_path: write(&dir, "server.key", &pki.server.1)?,
+        client_ca_path: Some(write(&dir, "ca.pem", &pki.ca)?),
+    };
+    let security = RpcSecurity::new("rpc-tls-test-token").with_tls(ServerTls::load(&settings)?);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let endpoint = format!("https://localhost:{}", listener.local_addr()?.port());
+    let svc = WorkerService::new(Arc::new(WorkerState::default()));
+    let _server = tokio::spawn(rpc::serve_rpc_on(listener, svc, security));
+
+    let tls = ClientTlsConfig::new().ca_certificate(Certificate::from_pem(&pki.ca)).domain_name("localhost");
+    let connect = |tls: ClientTlsConfig| -> Result<_, BoxError> {
+        let channel = Endpoint::from_shared(endpoint.clone())?.tls_config(tls)?.connect_lazy();
+        Ok(WorkerClient::with_interceptor(channel, proto::auth::AttachInternalToken::new("rpc-tls-test-token")))
+    };
+
+    let mut with_cert = connect(tls.clone().identity(Identity::from_pem(&pki.client.0, &pki.client.1)))?;
+    assert!(with_cert.list_active_rooms(ListActiveRoomsRequest {}).await?.into_inner().rooms.is_empty());
+
+    // Không có cert client thì handshake bị worker từ chối
+    let mut without_cert = connect(tls)?;
+    assert!(without_cert.list_active_rooms(ListActiveRoomsRequest {}).await.is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}